@@ -0,0 +1,507 @@
+use axum::{Router, middleware, routing::{get, post, put, patch, delete}};
+use utoipa::{Modify, OpenApi};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa_swagger_ui::SwaggerUi;
+use tower_http::cors::{CorsLayer, Any};
+use axum::http::{Method, HeaderName, HeaderValue, header};
+
+use crate::auth::{require_role, Role, RoleGuard};
+use crate::error::{self, REQUEST_ID_HEADER};
+use crate::handlers;
+use crate::idempotency::{self, IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use crate::models;
+use crate::state::AppState;
+
+/// API Documentation
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        handlers::health_check,
+        handlers::db_health_check,
+        // Enhanced Asset Management Endpoints
+        handlers::list_assets,
+        handlers::create_asset,
+        handlers::get_asset,
+        handlers::update_asset,
+        handlers::update_asset_price,
+        handlers::get_complete_asset,
+        handlers::get_asset_price_history,
+        handlers::import_csv_prices,
+        handlers::list_companies,
+        handlers::list_indices,
+        // User Management Endpoints
+        handlers::list_users,
+        handlers::create_user,
+        handlers::get_user,
+        handlers::get_user_extended,
+        handlers::update_user,
+        handlers::delete_user,
+        handlers::login,
+        handlers::logout,
+        handlers::refresh_token,
+        handlers::logout_all,
+        // Portfolio Management Endpoints
+        handlers::list_portfolios,
+        handlers::create_portfolio,
+        handlers::get_portfolio,
+        handlers::update_portfolio,
+        handlers::delete_portfolio,
+        handlers::get_portfolio_summary,
+        handlers::get_portfolio_holdings,
+        // Risk Analysis Endpoints
+        handlers::get_user_risk_metrics,
+        handlers::get_portfolio_risk_analysis,
+        handlers::get_risk_summary,
+        handlers::get_portfolio_risk_summary,
+        handlers::get_user_risk_summary,
+        handlers::get_user_latest_risk_metrics,
+        handlers::calculate_user_risk_metrics,
+        handlers::get_user_risk_trend,
+        handlers::get_portfolio_var,
+        handlers::get_asset_correlation,
+        handlers::get_portfolio_correlation,
+        // Fund Management Endpoints
+        handlers::deposit_funds,
+        handlers::withdraw_funds,
+        handlers::allocate_funds,
+        handlers::deallocate_funds,
+        handlers::upgrade_to_premium,
+        handlers::get_account_summary,
+        // Trading Endpoints
+        handlers::buy_asset,
+        handlers::sell_asset,
+        handlers::get_portfolio_balance,
+        handlers::get_portfolio_holdings_summary,
+        // Order Endpoints
+        handlers::place_order,
+        handlers::preview_order,
+        handlers::execute_batch,
+        handlers::list_orders,
+        handlers::get_order,
+        handlers::amend_order,
+        handlers::cancel_order,
+        // Tax Lot Endpoints
+        handlers::get_cost_basis_method,
+        handlers::set_cost_basis_method,
+        handlers::list_holding_lots,
+        handlers::list_realized_gains,
+        handlers::get_realized_pnl,
+        // Fee Endpoints
+        handlers::list_fee_schedules,
+        handlers::set_fee_schedule,
+        handlers::delete_fee_schedule,
+        // Trading Rule Endpoints
+        handlers::get_trading_limits,
+        handlers::set_trading_limits,
+        handlers::list_quantity_increments,
+        handlers::set_quantity_increment,
+        // Rebalancing Endpoints
+        handlers::get_allocation_targets,
+        handlers::set_allocation_targets,
+        handlers::get_drift,
+        handlers::rebalance_portfolio,
+        // Recurring Investment Endpoints
+        handlers::list_recurring_investments,
+        handlers::create_recurring_investment,
+        handlers::get_recurring_investment,
+        handlers::update_recurring_investment,
+        handlers::delete_recurring_investment,
+        handlers::list_recurring_runs,
+        // Performance Endpoints
+        handlers::get_portfolio_performance,
+        handlers::get_portfolio_returns,
+        handlers::get_user_returns,
+        handlers::backfill_portfolio_snapshots,
+        // Payment & Subscription Endpoints
+        handlers::set_payment_method,
+        handlers::manage_subscription,
+        handlers::get_enhanced_asset_details,
+        handlers::get_enhanced_account_summary,
+        handlers::get_fund_transaction_history,
+        handlers::get_portfolio_balance_sp,
+        handlers::get_portfolio_holdings_summary_sp,
+        handlers::list_portfolio_transactions
+    ),
+    components(
+        schemas(
+            // Errors
+            error::ApiErrorBody,
+            // Enhanced Asset Management Models
+            models::Asset,
+            models::CreateAssetRequest,
+            models::UpdateAssetRequest,
+            models::AssetPriceHistory,
+            models::CompleteAsset,
+            models::UpdatePriceRequest,
+            models::UpdatePriceResponse,
+            // Stock-Specific Models
+            models::StockDetails,
+            models::CreateStockRequest,
+            models::UpdateStockDetailsRequest,
+            // Cryptocurrency Models
+            models::CryptoDetails,
+            models::CreateCryptoRequest,
+            models::UpdateCryptoDetailsRequest,
+            // Index Models
+            models::IndexDetails,
+            models::CreateIndexRequest,
+            models::UpdateIndexDetailsRequest,
+            // Commodity Models
+            models::CommodityDetails,
+            models::CreateCommodityRequest,
+            models::UpdateCommodityDetailsRequest,
+            // Asset Utils
+            models::AssetType,
+            // CSV Import Models
+            models::CsvImportRequest,
+            models::CsvImportResult,
+            // User Management Models
+            models::User,
+            models::ExtendedUser,
+            models::CreateUserRequest,
+            models::UpdateUserRequest,
+            models::LoginRequest,
+            models::LoginResponse,
+            models::RefreshTokenRequest,
+            models::TokenResponse,
+            models::LogoutAllResponse,
+            models::SetPaymentMethodRequest,
+            models::PaymentMethodResponse,
+            models::ManageSubscriptionRequest,
+            models::SubscriptionResponse,
+            // Portfolio Management Models
+            models::Portfolio,
+            models::CreatePortfolioRequest,
+            models::UpdatePortfolioRequest,
+            models::PortfolioSummary,
+            models::AssetHolding,
+            // Risk Analysis Models
+            models::RiskMetrics,
+            models::RiskAnalysis,
+            models::PortfolioRiskAnalysis,
+            models::RiskSummary,
+            models::VarMethod,
+            models::VarContribution,
+            models::PortfolioVar,
+            models::CorrelationAsset,
+            models::AssetCorrelation,
+            // Fund Management Models
+            models::DepositRequest,
+            models::WithdrawRequest,
+            models::AllocateRequest,
+            models::DeallocateRequest,
+            models::UpgradePremiumRequest,
+            models::FundOperationResponse,
+            models::PremiumUpgradeResponse,
+            models::AccountSummary,
+            models::PortfolioBalance,
+            models::FundTransaction,
+            // Trading Models
+            models::BuyAssetRequest,
+            models::SellAssetRequest,
+            models::BuyAssetResponse,
+            models::SellAssetResponse,
+            models::PortfolioHolding,
+            models::TradingTransaction,
+            models::TransactionPage,
+            models::PortfolioHoldingsSummary,
+            // Order Models
+            models::OrderSide,
+            models::OrderType,
+            models::TimeInForce,
+            models::OrderStatus,
+            models::PlaceOrderRequest,
+            models::AmendOrderRequest,
+            models::Order,
+            models::OrderPreviewRequest,
+            models::TradeProjection,
+            models::OrderPreview,
+            models::BatchLeg,
+            models::BatchOrderRequest,
+            models::BatchStatus,
+            models::BatchLegStatus,
+            models::BatchLegError,
+            models::BatchLegResult,
+            models::BatchOrderResponse,
+            // Tax Lot Models
+            models::CostBasisMethod,
+            models::CostBasisSettings,
+            models::SetCostBasisRequest,
+            models::LotSelection,
+            models::TaxLot,
+            models::RealizedLot,
+            models::SaleRealizedGain,
+            models::AssetRealizedPnl,
+            models::RealizedPnl,
+            // Fee Models
+            models::FeeType,
+            models::FeeTier,
+            models::FeeSchedule,
+            models::SetFeeScheduleRequest,
+            // Trading Rule Models
+            models::TradingLimits,
+            models::SetTradingLimitsRequest,
+            models::QuantityIncrement,
+            models::SetQuantityIncrementRequest,
+            models::RuleViolation,
+            // Rebalancing Models
+            models::AllocationTarget,
+            models::AllocationTargets,
+            models::AllocationTargetInput,
+            models::SetAllocationTargetsRequest,
+            models::AllocationDrift,
+            models::DriftReport,
+            models::RebalanceRequest,
+            models::RebalanceTrade,
+            models::RebalancePlan,
+            // Recurring Investment Models
+            models::RecurrenceFrequency,
+            models::RecurringInvestment,
+            models::CreateRecurringInvestmentRequest,
+            models::UpdateRecurringInvestmentRequest,
+            models::RecurringRunStatus,
+            models::RecurringInvestmentRun,
+            // Performance Models
+            models::PerformanceInterval,
+            models::PerformancePoint,
+            models::PortfolioPerformance,
+            models::ReturnPeriod,
+            models::PeriodReturn,
+            models::PortfolioReturns,
+            models::UserReturns,
+            models::BackfillSnapshotsRequest,
+            models::BackfillSnapshotsResponse
+        )
+    ),
+    tags(
+        (name = "health", description = "Health check endpoints"),
+        (name = "assets", description = "Comprehensive asset management, price data import, and market data endpoints"),
+        (name = "users", description = "User management, authentication, and fund management endpoints"),
+        (name = "portfolios", description = "Portfolio management and trading endpoints"),
+        (name = "orders", description = "Limit, stop and stop-limit orders filled as prices move"),
+        (name = "fees", description = "Trading fee schedules by user type and asset type"),
+        (name = "trading-rules", description = "Pre-trade limits per portfolio and quantity increments per asset type"),
+        (name = "rebalancing", description = "Target allocations, drift reports and rebalancing trades"),
+        (name = "recurring-investments", description = "Schedules buying a fixed amount of an asset every day, week or month"),
+        (name = "performance", description = "Daily valuation snapshots, portfolio performance over time and time- and money-weighted returns"),
+        (name = "risk", description = "Risk analysis and portfolio analytics endpoints")
+    ),
+    modifiers(&SecurityAddon),
+    info(
+        title = "Portfolio Management API v2.0",
+        version = "2.0.0",
+        description = "Comprehensive API for managing portfolios with enhanced asset management, CSV data import, real-time price tracking, and comprehensive trading functionality"
+    )
+)]
+struct ApiDoc;
+
+/// Registers the bearer token scheme that protected endpoints refer to
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build()),
+            );
+        }
+    }
+}
+
+/// CORS for the configured origins; credentials (the session cookies) are
+/// only allowed when the origins are listed explicitly
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    if origins.iter().any(|origin| origin == "*") {
+        return CorsLayer::new().allow_origin(Any);
+    }
+
+    // Origins were validated when the configuration was loaded
+    let origins: Vec<HeaderValue> = origins.iter()
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_credentials(true)
+}
+
+/// Every route of the API with its middleware, ready to serve
+pub fn router(state: AppState) -> Router {
+    // Configure CORS with explicit settings
+    let cors = cors_layer(&state.config.cors_allowed_origins)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::ACCEPT,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("x-requested-with"),
+            REQUEST_ID_HEADER,
+            IDEMPOTENCY_KEY_HEADER,
+        ])
+        .expose_headers([REQUEST_ID_HEADER, IDEMPOTENT_REPLAYED_HEADER])
+        .max_age(std::time::Duration::from_secs(3600));
+    
+    // Route groups that declare required roles get this guard as a route layer
+    let admin_only = || middleware::from_fn_with_state(RoleGuard::new(state.clone(), &[Role::Admin]), require_role);
+    // Endpoints that move money accept an Idempotency-Key
+    let idempotent = || middleware::from_fn_with_state(state.clone(), idempotency::idempotent);
+
+    // Enhanced Asset Management Routes (public market data reads)
+    let asset_routes = Router::new()
+        // Core Asset Reads
+        .route("/assets", get(handlers::list_assets))
+        .route("/assets/{asset_id}", get(handlers::get_asset))
+        .route("/assets/{asset_id}/complete", get(handlers::get_complete_asset))
+        .route("/assets/{asset_id}/enhanced", get(handlers::get_enhanced_asset_details))
+        .route("/assets/{asset_id}/price-history", get(handlers::get_asset_price_history))
+        // Asset Type Filters
+        .route("/assets/companies", get(handlers::list_companies))
+        .route("/assets/indices", get(handlers::list_indices));
+
+    // Market Data Management Routes (Admin)
+    let market_data_routes = Router::new()
+        // Core Asset Writes
+        .route("/assets", post(handlers::create_asset))
+        .route("/assets/{asset_id}", put(handlers::update_asset))
+        .route("/assets/{asset_id}", delete(handlers::delete_asset))
+        // Price Management
+        .route("/assets/{asset_id}/price", post(handlers::update_asset_price))
+        // CSV Data Import
+        .route("/assets/import/csv", post(handlers::import_csv_prices))
+        .route_layer(admin_only());
+
+    // User Management Routes
+    let user_routes = Router::new()
+        // Core User Operations
+        .route("/users", post(handlers::create_user))
+        .route("/users/login", post(handlers::login))
+        .route("/users/logout", post(handlers::logout))
+        .route("/users/token/refresh", post(handlers::refresh_token))
+        .route("/users/{userId}/logout-all", post(handlers::logout_all))
+        .route("/users/{userId}", get(handlers::get_user))
+        .route("/users/{userId}/complete", get(handlers::get_user_extended))
+        .route("/users/{userId}", put(handlers::update_user))
+        .route("/users/{userId}", delete(handlers::delete_user))
+        // Payment & Subscription Management
+        .route("/users/{userId}/payment-method", put(handlers::set_payment_method))
+        .route("/users/{userId}/subscription", post(handlers::manage_subscription))
+        // Fund Management Operations
+        .route("/users/{userId}/deposit", post(handlers::deposit_funds).route_layer(idempotent()))
+        .route("/users/{userId}/withdraw", post(handlers::withdraw_funds).route_layer(idempotent()))
+        .route("/users/{userId}/allocate", post(handlers::allocate_funds).route_layer(idempotent()))
+        .route("/users/{userId}/deallocate", post(handlers::deallocate_funds).route_layer(idempotent()))
+        .route("/users/{userId}/upgrade-premium", post(handlers::upgrade_to_premium))
+        .route("/users/{userId}/account-summary", get(handlers::get_account_summary))
+        .route("/users/{userId}/account-summary-enhanced", get(handlers::get_enhanced_account_summary))
+        .route("/users/{userId}/fund-transactions", get(handlers::get_fund_transaction_history))
+        .route("/users/{userId}/returns", get(handlers::get_user_returns));
+
+    // Portfolio Management Routes
+    let portfolio_routes = Router::new()
+        // Core Portfolio Operations
+        .route("/portfolios", get(handlers::list_portfolios))
+        .route("/portfolios", post(handlers::create_portfolio))
+        .route("/portfolios/{portfolio_id}", get(handlers::get_portfolio))
+        .route("/portfolios/{portfolio_id}", put(handlers::update_portfolio))
+        .route("/portfolios/{portfolio_id}", delete(handlers::delete_portfolio))
+        .route("/portfolios/{portfolio_id}/summary", get(handlers::get_portfolio_summary))
+        .route("/portfolios/{portfolio_id}/holdings", get(handlers::get_portfolio_holdings))
+        // Trading Operations
+        .route("/portfolios/buy", post(handlers::buy_asset).route_layer(idempotent()))
+        .route("/portfolios/sell", post(handlers::sell_asset).route_layer(idempotent()))
+        .route("/portfolios/{portfolio_id}/balance", get(handlers::get_portfolio_balance))
+        .route("/portfolios/{portfolio_id}/balance-sp", get(handlers::get_portfolio_balance_sp))
+        .route("/portfolios/{portfolio_id}/holdings-summary", get(handlers::get_portfolio_holdings_summary))
+        .route("/portfolios/{portfolio_id}/holdings-summary-sp", get(handlers::get_portfolio_holdings_summary_sp))
+        .route("/portfolios/{portfolio_id}/transactions", get(handlers::list_portfolio_transactions))
+        // Tax lots and realized gains
+        .route("/portfolios/{portfolio_id}/cost-basis", get(handlers::get_cost_basis_method))
+        .route("/portfolios/{portfolio_id}/cost-basis", put(handlers::set_cost_basis_method))
+        .route("/portfolios/{portfolio_id}/holdings/{asset_id}/lots", get(handlers::list_holding_lots))
+        .route("/portfolios/{portfolio_id}/realized-gains", get(handlers::list_realized_gains))
+        .route("/portfolios/{portfolio_id}/realized-pnl", get(handlers::get_realized_pnl))
+        // Pre-trade limits
+        .route("/portfolios/{portfolio_id}/trading-limits", get(handlers::get_trading_limits))
+        .route("/portfolios/{portfolio_id}/trading-limits", put(handlers::set_trading_limits))
+        // Rebalancing
+        .route("/portfolios/{portfolio_id}/allocation-targets", get(handlers::get_allocation_targets))
+        .route("/portfolios/{portfolio_id}/allocation-targets", put(handlers::set_allocation_targets))
+        .route("/portfolios/{portfolio_id}/drift", get(handlers::get_drift))
+        .route("/portfolios/{portfolio_id}/rebalance", post(handlers::rebalance_portfolio).route_layer(idempotent()))
+        // Recurring investments
+        .route("/portfolios/{portfolio_id}/recurring-investments", get(handlers::list_recurring_investments))
+        .route("/portfolios/{portfolio_id}/recurring-investments", post(handlers::create_recurring_investment).route_layer(idempotent()))
+        .route("/recurring-investments/{schedule_id}", get(handlers::get_recurring_investment))
+        .route("/recurring-investments/{schedule_id}", put(handlers::update_recurring_investment))
+        .route("/recurring-investments/{schedule_id}", delete(handlers::delete_recurring_investment))
+        .route("/recurring-investments/{schedule_id}/runs", get(handlers::list_recurring_runs))
+        // Performance
+        .route("/portfolios/{portfolio_id}/performance", get(handlers::get_portfolio_performance))
+        .route("/portfolios/{portfolio_id}/returns", get(handlers::get_portfolio_returns))
+        // Orders
+        .route("/portfolios/{portfolio_id}/orders", post(handlers::place_order).route_layer(idempotent()))
+        .route("/portfolios/{portfolio_id}/orders", get(handlers::list_orders))
+        .route("/portfolios/{portfolio_id}/orders/preview", post(handlers::preview_order))
+        .route("/portfolios/{portfolio_id}/orders/batch", post(handlers::execute_batch).route_layer(idempotent()))
+        .route("/orders/{order_id}", get(handlers::get_order))
+        .route("/orders/{order_id}", patch(handlers::amend_order))
+        .route("/orders/{order_id}", delete(handlers::cancel_order));
+
+    // Risk Analysis Routes
+    let risk_routes = Router::new()
+        .route("/risk/metrics/user/{userId}", get(handlers::get_user_risk_metrics))
+        .route("/risk/metrics/portfolio/{portfolio_id}", get(handlers::get_portfolio_risk_analysis))
+        .route("/risk/summary/portfolio/{portfolio_id}", get(handlers::get_portfolio_risk_summary))
+        .route("/risk/summary/user/{userId}", get(handlers::get_user_risk_summary))
+        .route("/risk/latest/{userId}", get(handlers::get_user_latest_risk_metrics))
+        .route("/risk/calculate/{userId}", post(handlers::calculate_user_risk_metrics))
+        .route("/risk/trend/{userId}", get(handlers::get_user_risk_trend))
+        .route("/risk/var/portfolio/{portfolio_id}", get(handlers::get_portfolio_var))
+        .route("/risk/correlation", get(handlers::get_asset_correlation))
+        .route("/risk/correlation/portfolio/{portfolio_id}", get(handlers::get_portfolio_correlation));
+
+    // Fee Schedule Routes
+    let fee_routes = Router::new()
+        .route("/fee-schedules", get(handlers::list_fee_schedules))
+        .route("/quantity-increments", get(handlers::list_quantity_increments));
+
+    // System-wide Routes (Admin)
+    let admin_routes = Router::new()
+        .route("/users", get(handlers::list_users))
+        .route("/risk/summary", get(handlers::get_risk_summary))
+        .route("/fee-schedules", put(handlers::set_fee_schedule))
+        .route("/fee-schedules/{fee_schedule_id}", delete(handlers::delete_fee_schedule))
+        .route("/quantity-increments/{asset_type}", put(handlers::set_quantity_increment))
+        .route("/portfolio-snapshots/backfill", post(handlers::backfill_portfolio_snapshots))
+        .route_layer(admin_only());
+
+    // Combine all API routes under v1
+    let api_v1 = Router::new()
+        .merge(asset_routes)
+        .merge(market_data_routes)
+        .merge(admin_routes)
+        .merge(user_routes)
+        .merge(portfolio_routes)
+        .merge(fee_routes)
+        .merge(risk_routes);
+
+    Router::new()
+        .merge(SwaggerUi::new("/swagger-ui")
+            .url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .route("/health", get(handlers::health_check))
+        .route("/db-health", get(handlers::db_health_check))
+        .nest("/api/v1", api_v1)
+        .layer(middleware::from_fn(error::request_id))
+        .layer(cors)
+        .with_state(state)
+}
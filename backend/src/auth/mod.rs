@@ -0,0 +1,197 @@
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::{decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+mod password;
+mod role;
+mod session;
+
+pub use password::*;
+pub use role::*;
+pub use session::*;
+
+/// Name of the cookie `login` sets next to the Authorization header
+const TOKEN_COOKIE: &str = "token";
+
+// JWT claims structure
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String, // user_id
+    pub sid: String, // session_id, checked against the session store
+    pub role: Role,  // taken from UserType when the token is issued
+    pub exp: usize,  // expiration time
+}
+
+/// Keys used to sign and verify session tokens
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_token_ttl_secs: u64,
+}
+
+impl JwtKeys {
+    pub fn new(config: &AuthConfig) -> Self {
+        let secret = config.jwt_secret.expose().as_bytes();
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            access_token_ttl_secs: config.access_token_ttl_secs,
+        }
+    }
+
+    /// Signs an access token for one session that expires after the configured access TTL
+    pub fn issue(&self, user_id: Uuid, session_id: Uuid, role: Role) -> Result<String, jsonwebtoken::errors::Error> {
+        let expiration = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() + self.access_token_ttl_secs;
+
+        let claims = Claims {
+            sub: user_id.to_string(),
+            sid: session_id.to_string(),
+            role,
+            exp: expiration as usize,
+        };
+
+        encode(&Header::default(), &claims, &self.encoding)
+    }
+
+    /// Checks signature and expiry, then returns the user and session the token was issued to
+    fn verify(&self, token: &str) -> Result<AuthUser, AuthError> {
+        let data = decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                _ => AuthError::InvalidToken,
+            })?;
+
+        Ok(AuthUser {
+            user_id: Uuid::parse_str(&data.claims.sub).map_err(|_| AuthError::InvalidToken)?,
+            session_id: Uuid::parse_str(&data.claims.sid).map_err(|_| AuthError::InvalidToken)?,
+            role: data.claims.role,
+        })
+    }
+}
+
+// =============================================================
+// ERRORS
+// =============================================================
+
+/// Why a request was refused; every variant renders through `ApiError` as
+/// `UNAUTHORIZED` or `FORBIDDEN`
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    SessionRevoked,
+    InvalidRefreshToken,
+    RefreshTokenReused,
+    Forbidden,
+    InsufficientRole,
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::Forbidden | AuthError::InsufficientRole => StatusCode::FORBIDDEN,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            AuthError::MissingToken => "Authentication required",
+            AuthError::InvalidToken => "Invalid authentication token",
+            AuthError::ExpiredToken => "Authentication token has expired",
+            AuthError::SessionRevoked => "Session has been revoked",
+            AuthError::InvalidRefreshToken => "Invalid or expired refresh token",
+            AuthError::RefreshTokenReused => "Refresh token was already used; the session has been revoked",
+            AuthError::Forbidden => "You do not have access to this resource",
+            AuthError::InsufficientRole => "This operation requires the Admin role",
+        }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+// =============================================================
+// EXTRACTOR
+// =============================================================
+
+/// The caller, authenticated from the `Authorization: Bearer` header or the
+/// `token` cookie. Adding it to a handler's arguments makes the route require
+/// a valid, unexpired token whose session has not been revoked.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub role: Role,
+}
+
+impl AuthUser {
+    /// Refuses access to another user's resources
+    pub fn ensure_user(&self, user_id: Uuid) -> Result<(), AuthError> {
+        if self.user_id == user_id {
+            Ok(())
+        } else {
+            Err(AuthError::Forbidden)
+        }
+    }
+
+    /// Refuses access to a portfolio the caller does not own
+    pub async fn ensure_portfolio(&self, state: &AppState, portfolio_id: i32) -> Result<(), ApiError> {
+        let portfolio = state.store.get_portfolio(portfolio_id).await?;
+        self.ensure_user(portfolio.user_id)?;
+        Ok(())
+    }
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(parts)
+            .or_else(|| cookie(&parts.headers, TOKEN_COOKIE))
+            .ok_or(AuthError::MissingToken)?;
+
+        let user = state.jwt.verify(token)?;
+
+        // Logging out revokes the session, which must outlive the token's own expiry check
+        if !state.store.session_active(user.session_id).await? {
+            return Err(AuthError::SessionRevoked.into());
+        }
+
+        Ok(user)
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts.headers.get(header::AUTHORIZATION)?
+        .to_str().ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Value of the named cookie, if the request carries a non-empty one
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, value)| *cookie_name == name && !value.is_empty())
+        .map(|(_, value)| value)
+}
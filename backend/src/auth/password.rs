@@ -0,0 +1,34 @@
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use subtle::ConstantTimeEq;
+
+/// Outcome of checking a login attempt against the stored password
+#[derive(Debug, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Valid,
+    /// Correct, but the row still holds a pre-hashing plaintext password
+    /// and should be rehashed
+    ValidLegacy,
+    Invalid,
+}
+
+/// Hashes a password into an Argon2id PHC string
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// Checks `password` against a stored PHC hash, or against a legacy
+/// plaintext value for rows written before passwords were hashed
+pub fn verify_password(password: &str, stored: &str) -> PasswordCheck {
+    match PasswordHash::new(stored) {
+        Ok(hash) => match Argon2::default().verify_password(password.as_bytes(), &hash) {
+            Ok(()) => PasswordCheck::Valid,
+            Err(_) => PasswordCheck::Invalid,
+        },
+        Err(_) if bool::from(password.as_bytes().ct_eq(stored.as_bytes())) => PasswordCheck::ValidLegacy,
+        Err(_) => PasswordCheck::Invalid,
+    }
+}
@@ -0,0 +1,54 @@
+use axum::extract::{FromRequestParts, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+use super::{AuthError, AuthUser};
+
+/// What a user may do, derived from `Users.UserType` and carried in the token claims
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    Basic,
+    Premium,
+    Admin,
+}
+
+impl Role {
+    /// Unknown user types get the least privileged role
+    pub fn from_user_type(user_type: &str) -> Self {
+        match user_type {
+            "Admin" => Role::Admin,
+            "Premium" => Role::Premium,
+            _ => Role::Basic,
+        }
+    }
+}
+
+/// Roles a route group accepts, handed to `require_role` as its middleware state
+#[derive(Clone)]
+pub struct RoleGuard {
+    state: AppState,
+    roles: &'static [Role],
+}
+
+impl RoleGuard {
+    pub fn new(state: AppState, roles: &'static [Role]) -> Self {
+        Self { state, roles }
+    }
+}
+
+/// Route-group middleware: authenticates the caller like `AuthUser` does and
+/// refuses anyone whose role is not in the group's list
+pub async fn require_role(State(guard): State<RoleGuard>, request: Request, next: Next) -> Result<Response, ApiError> {
+    let (mut parts, body) = request.into_parts();
+    let user = AuthUser::from_request_parts(&mut parts, &guard.state).await?;
+
+    if !guard.roles.contains(&user.role) {
+        return Err(AuthError::InsufficientRole.into());
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
@@ -0,0 +1,55 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::config::AuthConfig;
+
+use super::TOKEN_COOKIE;
+
+/// Cookie holding the refresh token
+pub const REFRESH_COOKIE: &str = "refresh_token";
+
+/// Only the user endpoints that rotate or revoke sessions need the refresh cookie
+const REFRESH_COOKIE_PATH: &str = "/api/v1/users";
+
+/// A new opaque refresh token together with the digest the store keeps
+pub struct RefreshToken {
+    pub token: String,
+    pub hash: String,
+}
+
+impl RefreshToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+
+        let token = to_hex(&bytes);
+        let hash = hash_refresh_token(&token);
+        Self { token, hash }
+    }
+}
+
+/// Digest used to look a presented refresh token up in the store
+pub fn hash_refresh_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `Set-Cookie` values handing both tokens of a session to the browser;
+/// each cookie lives as long as its token
+pub fn session_cookies(config: &AuthConfig, access_token: &str, refresh_token: &str) -> [String; 2] {
+    [
+        format!("{}={}; HttpOnly; Path=/; Max-Age={}", TOKEN_COOKIE, access_token, config.access_token_ttl_secs),
+        format!("{}={}; HttpOnly; Path={}; Max-Age={}", REFRESH_COOKIE, refresh_token, REFRESH_COOKIE_PATH, config.refresh_token_ttl_secs),
+    ]
+}
+
+/// `Set-Cookie` values that remove both session cookies
+pub fn cleared_cookies() -> [String; 2] {
+    [
+        format!("{}=; HttpOnly; Path=/; Max-Age=0", TOKEN_COOKIE),
+        format!("{}=; HttpOnly; Path={}; Max-Age=0", REFRESH_COOKIE, REFRESH_COOKIE_PATH),
+    ]
+}
@@ -0,0 +1,357 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::db::PoolSettings;
+
+/// File read when `CONFIG_FILE` is not set; it is optional
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// =============================================================
+// CONFIG
+// =============================================================
+
+/// Application settings, loaded once at startup.
+///
+/// Each value comes from the first of: environment variable (including `.env`),
+/// the TOML file, the built-in default. Settings without a default are required.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_address: SocketAddr,
+    /// Origins allowed by CORS; `*` allows any origin without credentials
+    pub cors_allowed_origins: Vec<String>,
+    pub store: StoreConfig,
+    pub auth: AuthConfig,
+}
+
+/// Persistence backend, with the settings only that backend needs
+#[derive(Debug, Clone)]
+pub enum StoreConfig {
+    SqlServer(DatabaseConfig),
+    Memory(MemoryConfig),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryConfig {
+    /// Administrator created at startup; without a database there is no
+    /// other way to grant the Admin role
+    pub admin: Option<AdminAccount>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminAccount {
+    pub email: String,
+    pub password: Secret,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: Secret,
+    pub name: String,
+    pub trust_cert: bool,
+    pub pool: PoolSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub jwt_secret: Secret,
+    pub access_token_ttl_secs: u64,
+    pub refresh_token_ttl_secs: u64,
+}
+
+/// A value that must never reach logs; `Debug` and `Display` print a placeholder
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"[redacted]\"")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(value.to_string()))
+    }
+}
+
+// =============================================================
+// TOML FILE
+// =============================================================
+
+// Every field is optional so the file only needs the settings it overrides
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    store_backend: Option<String>,
+    server: ServerFile,
+    database: DatabaseFile,
+    memory: MemoryFile,
+    auth: AuthFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerFile {
+    bind_address: Option<SocketAddr>,
+    cors_allowed_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DatabaseFile {
+    host: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    password: Option<Secret>,
+    name: Option<String>,
+    trust_cert: Option<bool>,
+    pool: PoolFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PoolFile {
+    max_size: Option<u32>,
+    min_idle: Option<u32>,
+    idle_timeout_secs: Option<u64>,
+    connection_timeout_secs: Option<u64>,
+    test_on_checkout: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MemoryFile {
+    admin_email: Option<String>,
+    admin_password: Option<Secret>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthFile {
+    jwt_secret: Option<Secret>,
+    access_token_ttl_secs: Option<u64>,
+    refresh_token_ttl_secs: Option<u64>,
+}
+
+// =============================================================
+// LOADING
+// =============================================================
+
+impl Config {
+    /// Reads `.env`, the TOML file and the environment, then validates the result.
+    /// Every problem found is reported at once so startup fails fast.
+    pub fn load() -> Result<Self> {
+        dotenvy::dotenv().ok();
+
+        let file = read_file()?;
+        let mut loader = Loader::default();
+        let config = loader.build(file);
+
+        if !loader.errors.is_empty() {
+            bail!("Invalid configuration:\n  - {}", loader.errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    /// Human-readable summary for the startup log; secrets stay redacted
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("Bind address: {}", self.bind_address),
+            format!("CORS allowed origins: {}", self.cors_allowed_origins.join(", ")),
+            format!(
+                "Token lifetimes: access {}s / refresh {}s",
+                self.auth.access_token_ttl_secs, self.auth.refresh_token_ttl_secs
+            ),
+        ];
+        if let StoreConfig::Memory(MemoryConfig { admin: Some(admin) }) = &self.store {
+            lines.push(format!("Administrator: {} (password {})", admin.email, admin.password));
+        }
+        if let StoreConfig::SqlServer(db) = &self.store {
+            lines.push(format!("Database: {}@{}:{}/{} (password {})", db.user, db.host, db.port, db.name, db.password));
+            lines.push(format!(
+                "Pool: max {} / min idle {} connections, idle timeout {}s, test on checkout: {}",
+                db.pool.max_size,
+                db.pool.min_idle,
+                db.pool.idle_timeout.as_secs(),
+                db.pool.test_on_checkout
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn read_file() -> Result<FileConfig> {
+    let (path, required) = match env::var("CONFIG_FILE") {
+        Ok(path) if !path.trim().is_empty() => (PathBuf::from(path), true),
+        _ => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+    };
+
+    if !path.exists() {
+        if required {
+            bail!("CONFIG_FILE points to {}, which does not exist", path.display());
+        }
+        return Ok(FileConfig::default());
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Resolves settings one by one, collecting errors instead of stopping at the first
+#[derive(Default)]
+struct Loader {
+    errors: Vec<String>,
+}
+
+impl Loader {
+    /// Environment variable, else the file's value, else `None`
+    fn lookup<T: FromStr>(&mut self, key: &str, file: Option<T>) -> Option<T> {
+        match env::var(key) {
+            Ok(value) if !value.trim().is_empty() => match value.trim().parse() {
+                Ok(parsed) => Some(parsed),
+                Err(_) => {
+                    self.errors.push(format!("{} has an invalid value '{}'", key, value));
+                    None
+                },
+            },
+            _ => file,
+        }
+    }
+
+    fn optional<T: FromStr>(&mut self, key: &str, file: Option<T>, default: T) -> T {
+        self.lookup(key, file).unwrap_or(default)
+    }
+
+    fn required<T: FromStr + Default>(&mut self, key: &str, file: Option<T>) -> T {
+        self.lookup(key, file).unwrap_or_else(|| {
+            self.errors.push(format!("{} is required", key));
+            T::default()
+        })
+    }
+
+    fn check(&mut self, ok: bool, message: &str) {
+        if !ok {
+            self.errors.push(message.to_string());
+        }
+    }
+
+    fn build(&mut self, file: FileConfig) -> Config {
+        let bind_address = self.optional(
+            "BIND_ADDRESS",
+            file.server.bind_address,
+            SocketAddr::from(([0, 0, 0, 0], 8080)),
+        );
+
+        let cors_allowed_origins = match env::var("CORS_ALLOWED_ORIGINS") {
+            Ok(value) if !value.trim().is_empty() => value.split(',').map(|origin| origin.trim().to_string()).collect(),
+            _ => file.server.cors_allowed_origins.unwrap_or_else(|| vec!["*".to_string()]),
+        };
+        for origin in &cors_allowed_origins {
+            self.check(
+                origin == "*" || origin.parse::<axum::http::HeaderValue>().is_ok(),
+                &format!("CORS_ALLOWED_ORIGINS contains an invalid origin '{}'", origin),
+            );
+        }
+
+        let backend = self.optional("STORE_BACKEND", file.store_backend, "sqlserver".to_string());
+        let store = match backend.as_str() {
+            "sqlserver" => StoreConfig::SqlServer(self.database(file.database)),
+            "memory" => StoreConfig::Memory(self.memory(file.memory)),
+            other => {
+                self.errors.push(format!("STORE_BACKEND '{}' is unknown, expected 'sqlserver' or 'memory'", other));
+                StoreConfig::Memory(MemoryConfig::default())
+            },
+        };
+
+        Config {
+            bind_address,
+            cors_allowed_origins,
+            store,
+            auth: self.auth(file.auth),
+        }
+    }
+
+    fn database(&mut self, file: DatabaseFile) -> DatabaseConfig {
+        let pool = PoolSettings {
+            max_size: self.optional("DATABASE_POOL_MAX_SIZE", file.pool.max_size, 10),
+            min_idle: self.optional("DATABASE_POOL_MIN_IDLE", file.pool.min_idle, 1),
+            idle_timeout: Duration::from_secs(self.optional("DATABASE_POOL_IDLE_TIMEOUT_SECS", file.pool.idle_timeout_secs, 300)),
+            connection_timeout: Duration::from_secs(self.optional("DATABASE_POOL_CONNECTION_TIMEOUT_SECS", file.pool.connection_timeout_secs, 15)),
+            test_on_checkout: self.optional("DATABASE_POOL_TEST_ON_CHECKOUT", file.pool.test_on_checkout, true),
+        };
+        self.check(pool.max_size > 0, "DATABASE_POOL_MAX_SIZE must be at least 1");
+        self.check(pool.min_idle <= pool.max_size, "DATABASE_POOL_MIN_IDLE cannot exceed DATABASE_POOL_MAX_SIZE");
+
+        // No fallbacks for the connection itself: a missing value must not
+        // silently point the API at somebody else's server
+        DatabaseConfig {
+            host: self.required("DATABASE_HOST", file.host),
+            port: self.optional("DATABASE_PORT", file.port, 1433),
+            user: self.required("DATABASE_USER", file.user),
+            password: self.required("DATABASE_PASSWORD", file.password),
+            name: self.required("DATABASE_NAME", file.name),
+            trust_cert: self.optional("DATABASE_TRUST_CERT", file.trust_cert, true),
+            pool,
+        }
+    }
+
+    fn memory(&mut self, file: MemoryFile) -> MemoryConfig {
+        let email: Option<String> = self.lookup("ADMIN_EMAIL", file.admin_email);
+        let password: Option<Secret> = self.lookup("ADMIN_PASSWORD", file.admin_password);
+        let admin = match (email, password) {
+            (Some(email), Some(password)) => Some(AdminAccount { email, password }),
+            (None, None) => None,
+            _ => {
+                self.errors.push("ADMIN_EMAIL and ADMIN_PASSWORD must be set together".to_string());
+                None
+            },
+        };
+        MemoryConfig { admin }
+    }
+
+    fn auth(&mut self, file: AuthFile) -> AuthConfig {
+        let auth = AuthConfig {
+            jwt_secret: self.required("JWT_SECRET", file.jwt_secret),
+            access_token_ttl_secs: self.optional("ACCESS_TOKEN_TTL_SECS", file.access_token_ttl_secs, 15 * 60),
+            refresh_token_ttl_secs: self.optional("REFRESH_TOKEN_TTL_SECS", file.refresh_token_ttl_secs, 30 * 24 * 3600),
+        };
+
+        self.check(auth.access_token_ttl_secs > 0, "ACCESS_TOKEN_TTL_SECS must be positive");
+        self.check(
+            auth.refresh_token_ttl_secs > auth.access_token_ttl_secs,
+            "REFRESH_TOKEN_TTL_SECS must be longer than ACCESS_TOKEN_TTL_SECS",
+        );
+        // Session expiry is computed with DATEADD, which takes an int
+        self.check(
+            auth.refresh_token_ttl_secs <= i32::MAX as u64,
+            "REFRESH_TOKEN_TTL_SECS is too large",
+        );
+        auth
+    }
+}
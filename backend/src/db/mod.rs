@@ -1,3 +1,3 @@
-pub mod db;
+mod pool;
 
-pub use db::*; 
\ No newline at end of file
+pub use pool::*;
@@ -0,0 +1,182 @@
+use tiberius::{Client, Config, AuthMethod, ExecuteResult, QueryStream, ToSql};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+use anyhow::Result;
+use std::borrow::Cow;
+use std::time::Duration;
+
+use crate::config::DatabaseConfig;
+
+/// A single SQL Server connection as handed out by the pool. Calls go
+/// straight to the driver, but the connection remembers when it can no
+/// longer be trusted so the pool closes it instead of handing it out again.
+pub struct DbClient {
+    client: Client<Compat<TcpStream>>,
+    /// A call failed outside SQL Server or never finished, for instance
+    /// because the request was dropped, so the TDS stream may be cut off
+    /// part-way through a message
+    broken: bool,
+    /// A transaction was begun and has not been committed or rolled back
+    in_transaction: bool,
+}
+
+impl DbClient {
+    pub async fn query<'a, 'b>(
+        &'a mut self,
+        query: impl Into<Cow<'b, str>>,
+        params: &'b [&'b dyn ToSql],
+    ) -> tiberius::Result<QueryStream<'a>>
+    where
+        'a: 'b,
+    {
+        // Broken until the call comes back, in case it never does
+        let was_broken = std::mem::replace(&mut self.broken, true);
+        let result = self.client.query(query, params).await;
+        self.broken = was_broken || failed_outside_server(&result);
+        result
+    }
+
+    pub async fn execute<'a>(
+        &mut self,
+        query: impl Into<Cow<'a, str>>,
+        params: &[&dyn ToSql],
+    ) -> tiberius::Result<ExecuteResult> {
+        let was_broken = std::mem::replace(&mut self.broken, true);
+        let result = self.client.execute(query, params).await;
+        self.broken = was_broken || failed_outside_server(&result);
+        result
+    }
+
+    pub async fn simple_query<'a, 'b>(
+        &'a mut self,
+        query: impl Into<Cow<'b, str>>,
+    ) -> tiberius::Result<QueryStream<'a>>
+    where
+        'a: 'b,
+    {
+        let was_broken = std::mem::replace(&mut self.broken, true);
+        let result = self.client.simple_query(query).await;
+        self.broken = was_broken || failed_outside_server(&result);
+        result
+    }
+
+    /// Opens a transaction. Until it is committed or rolled back the pool
+    /// will not reuse the connection: a caller dropped part-way through gets
+    /// it closed, which makes SQL Server roll the transaction back, rather
+    /// than handed to the next caller with the transaction still open.
+    pub async fn begin_transaction(&mut self) -> tiberius::Result<()> {
+        self.in_transaction = true;
+        self.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+        Ok(())
+    }
+
+    pub async fn commit(&mut self) -> tiberius::Result<()> {
+        self.simple_query("COMMIT").await?.into_results().await?;
+        self.in_transaction = false;
+        Ok(())
+    }
+
+    /// Rolls back whatever is still open; a procedure that failed may have
+    /// rolled the transaction back already
+    pub async fn rollback(&mut self) -> tiberius::Result<()> {
+        self.simple_query("IF @@TRANCOUNT > 0 ROLLBACK").await?.into_results().await?;
+        self.in_transaction = false;
+        Ok(())
+    }
+}
+
+/// Errors SQL Server raised itself leave the connection usable; I/O,
+/// protocol and decoding errors may not
+fn failed_outside_server<T>(result: &tiberius::Result<T>) -> bool {
+    matches!(result, Err(error) if !matches!(error, tiberius::error::Error::Server(_)))
+}
+
+/// Shared SQL Server connection pool
+pub type DbPool = bb8::Pool<TiberiusConnectionManager>;
+
+/// Pool tuning knobs, part of `DatabaseConfig`
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_size: u32,
+    pub min_idle: u32,
+    pub idle_timeout: Duration,
+    pub connection_timeout: Duration,
+    pub test_on_checkout: bool,
+}
+
+/// bb8 connection manager for tiberius clients
+pub struct TiberiusConnectionManager {
+    config: Config,
+}
+
+impl TiberiusConnectionManager {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl bb8::ManageConnection for TiberiusConnectionManager {
+    type Connection = DbClient;
+    type Error = tiberius::error::Error;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let tcp = TcpStream::connect(self.config.get_addr()).await?;
+        tcp.set_nodelay(true)?;
+        let client = Client::connect(self.config.clone(), tcp.compat_write()).await?;
+        Ok(DbClient { client, broken: false, in_transaction: false })
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.simple_query("SELECT 1").await?.into_row().await?;
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.broken || conn.in_transaction
+    }
+}
+
+fn db_config(settings: &DatabaseConfig) -> Config {
+    let mut config = Config::new();
+
+    config.host(&settings.host);
+    config.port(settings.port);
+    config.authentication(AuthMethod::sql_server(&settings.user, settings.password.expose()));
+    if settings.trust_cert {
+        config.trust_cert();
+    }
+    config.database(&settings.name);
+
+    config
+}
+
+/// Builds the connection pool once at startup.
+///
+/// Connections are opened lazily in the background so the API still comes up
+/// when the database is unreachable; `/db-health` reports the actual state.
+pub fn create_pool(settings: &DatabaseConfig) -> DbPool {
+    bb8::Pool::builder()
+        .max_size(settings.pool.max_size)
+        .min_idle(settings.pool.min_idle)
+        .idle_timeout(settings.pool.idle_timeout)
+        .connection_timeout(settings.pool.connection_timeout)
+        .test_on_check_out(settings.pool.test_on_checkout)
+        .build_unchecked(TiberiusConnectionManager::new(db_config(settings)))
+}
+
+pub async fn test_database_connectivity(settings: &DatabaseConfig) -> Result<()> {
+    println!("Testing database connectivity...");
+
+    let pool = create_pool(settings);
+    let result = match pool.get().await {
+        Ok(_client) => {
+            println!("✅ Database connection successful!");
+            Ok(())
+        },
+        Err(e) => {
+            println!("❌ Database connection failed: {}", e);
+            Err(e.into())
+        }
+    };
+    result
+}
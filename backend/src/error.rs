@@ -0,0 +1,227 @@
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::auth::AuthError;
+use crate::store::StoreError;
+
+/// Header carrying the request id, echoed back on every response
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// =============================================================
+// ERRORS
+// =============================================================
+
+/// Error returned by every handler, rendered as an `ApiErrorBody`
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    Validation(String),
+    InsufficientFunds(String),
+    Conflict(String),
+    /// A pre-trade rule refused the trade; `details` names the rule
+    TradeRejected(String),
+    /// An `Idempotency-Key` replayed with a different request
+    IdempotencyKeyReused(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// Raw driver error; logged with the request id but never sent to the client
+    Database(String),
+    Internal(String),
+    /// Any of the above with structured `details` for the client
+    Detailed(Box<ApiError>, serde_json::Value),
+}
+
+impl ApiError {
+    pub fn with_details(self, details: serde_json::Value) -> Self {
+        ApiError::Detailed(Box::new(self), details)
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::InsufficientFunds(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::TradeRejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::IdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Detailed(inner, _) => inner.status(),
+        }
+    }
+
+    /// Stable, machine-readable code clients can branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::Validation(_) => "VALIDATION_ERROR",
+            ApiError::InsufficientFunds(_) => "INSUFFICIENT_FUNDS",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::TradeRejected(_) => "TRADE_REJECTED",
+            ApiError::IdempotencyKeyReused(_) => "IDEMPOTENCY_KEY_REUSED",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::Database(_) => "DATABASE_ERROR",
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+            ApiError::Detailed(inner, _) => inner.code(),
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            ApiError::NotFound(msg)
+            | ApiError::Validation(msg)
+            | ApiError::InsufficientFunds(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::TradeRejected(msg)
+            | ApiError::IdempotencyKeyReused(msg)
+            | ApiError::Unauthorized(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::Internal(msg) => msg,
+            ApiError::Database(_) => "A database error occurred",
+            ApiError::Detailed(inner, _) => inner.message(),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Database(msg) => write!(f, "{}", msg),
+            other => write!(f, "{}", other.message()),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// JSON body of every error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiErrorBody {
+    /// Machine-readable error code
+    #[schema(example = "INSUFFICIENT_FUNDS")]
+    pub code: &'static str,
+    /// Human-readable description, safe to show to users
+    #[schema(example = "Insufficient funds in portfolio for this purchase")]
+    pub message: String,
+    /// Optional structured context, e.g. per-row validation errors
+    pub details: Option<serde_json::Value>,
+    /// Id of the request, also sent in the `X-Request-Id` header
+    #[schema(example = "5f0c6c52-1f5e-4a5b-9d8e-0f7b3c2a1d90")]
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// The body a response for this error carries; raw database and internal
+    /// errors are logged here with the request id instead
+    pub fn into_body(self) -> ApiErrorBody {
+        let request_id = REQUEST_ID.try_with(|id| id.clone()).ok();
+
+        if let ApiError::Database(raw) | ApiError::Internal(raw) = &self {
+            eprintln!("[{}] {}", request_id.as_deref().unwrap_or("-"), raw);
+        }
+
+        let (error, details) = match self {
+            ApiError::Detailed(inner, details) => (*inner, Some(details)),
+            other => (other, None),
+        };
+
+        ApiErrorBody {
+            code: error.code(),
+            message: error.message().to_string(),
+            details,
+            request_id,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        (status, Json(self.into_body())).into_response()
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        match err {
+            StoreError::NotFound(msg) => ApiError::NotFound(msg),
+            StoreError::BadRequest(msg) => ApiError::Validation(msg),
+            StoreError::InsufficientFunds(msg) => ApiError::InsufficientFunds(msg),
+            StoreError::Conflict(msg) => ApiError::Conflict(msg),
+            StoreError::Forbidden(msg) => ApiError::Forbidden(msg),
+            StoreError::Database(msg) => ApiError::Database(msg),
+            StoreError::Internal(msg) => ApiError::Internal(msg),
+        }
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(err: AuthError) -> Self {
+        match err.status() {
+            StatusCode::FORBIDDEN => ApiError::Forbidden(err.message().to_string()),
+            _ => ApiError::Unauthorized(err.message().to_string()),
+        }
+    }
+}
+
+/// Malformed bodies, path segments and query strings are the client's fault;
+/// anything else an extractor rejects with is a server bug
+fn rejection(status: StatusCode, message: String) -> ApiError {
+    if status.is_server_error() {
+        ApiError::Internal(message)
+    } else {
+        ApiError::Validation(message)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        self::rejection(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        self::rejection(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        self::rejection(rejection.status(), rejection.body_text())
+    }
+}
+
+// =============================================================
+// REQUEST ID
+// =============================================================
+
+/// Tags each request with an id (the caller's `X-Request-Id` if it sent one)
+/// so error bodies and server logs can be matched up
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
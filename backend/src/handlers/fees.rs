@@ -0,0 +1,148 @@
+use axum::extract::State;
+use crate::extract::{Json, Path};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{check_scale, AssetType, FeeSchedule, FeeType, SetFeeScheduleRequest, MONEY_SCALE, PRICE_SCALE},
+    state::AppState,
+};
+
+fn check_rate(rate: Decimal, field: &str) -> Result<(), ApiError> {
+    if rate < Decimal::ZERO || rate > Decimal::ONE_HUNDRED {
+        return Err(ApiError::Validation(format!("{} must be between 0 and 100", field)));
+    }
+    // Rates are stored as DECIMAL(9,4), the same scale as prices
+    check_scale(rate, PRICE_SCALE, field).map_err(ApiError::Validation)
+}
+
+/// Checks that a schedule carries exactly the values its fee type uses
+fn validate_fee_schedule(schedule: &SetFeeScheduleRequest) -> Result<(), ApiError> {
+    if !["Basic", "Premium"].contains(&schedule.user_type.as_str()) {
+        return Err(ApiError::Validation("User type must be Basic or Premium".to_string()));
+    }
+
+    let fee_type = schedule.fee_type.as_str();
+    let uses = [
+        ("Flat fee", schedule.flat_fee.is_some(), schedule.fee_type == FeeType::Flat),
+        ("Rate", schedule.rate.is_some(), schedule.fee_type == FeeType::Percentage),
+        ("Tiers", schedule.tiers.is_some(), schedule.fee_type == FeeType::Tiered),
+    ];
+    for (field, given, needed) in uses {
+        match (given, needed) {
+            (true, false) => return Err(ApiError::Validation(format!("{} is not allowed on {} schedules", field, fee_type))),
+            (false, true) => return Err(ApiError::Validation(format!("{} is required on {} schedules", field, fee_type))),
+            _ => {},
+        }
+    }
+
+    if let Some(flat_fee) = schedule.flat_fee {
+        if flat_fee < Decimal::ZERO {
+            return Err(ApiError::Validation("Flat fee cannot be negative".to_string()));
+        }
+        check_scale(flat_fee, MONEY_SCALE, "Flat fee").map_err(ApiError::Validation)?;
+    }
+    if let Some(rate) = schedule.rate {
+        check_rate(rate, "Rate")?;
+    }
+    if let Some(minimum_fee) = schedule.minimum_fee {
+        if minimum_fee < Decimal::ZERO {
+            return Err(ApiError::Validation("Minimum fee cannot be negative".to_string()));
+        }
+        check_scale(minimum_fee, MONEY_SCALE, "Minimum fee").map_err(ApiError::Validation)?;
+    }
+
+    if let Some(tiers) = &schedule.tiers {
+        if !tiers.iter().any(|tier| tier.min_trade_value.is_zero()) {
+            return Err(ApiError::Validation("Tiers need one starting at a trade value of 0".to_string()));
+        }
+        for (index, tier) in tiers.iter().enumerate() {
+            if tier.min_trade_value < Decimal::ZERO {
+                return Err(ApiError::Validation("Tier trade values cannot be negative".to_string()));
+            }
+            check_scale(tier.min_trade_value, MONEY_SCALE, "Tier trade value").map_err(ApiError::Validation)?;
+            check_rate(tier.rate, "Tier rate")?;
+            if tiers[..index].iter().any(|other| other.min_trade_value == tier.min_trade_value) {
+                return Err(ApiError::Validation("Tier trade values must be unique".to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// List the trading fee schedules
+#[utoipa::path(
+    get,
+    path = "/api/v1/fee-schedules",
+    tag = "fees",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Fee schedules by user type, each one's default first", body = Vec<FeeSchedule>),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_fee_schedules(
+    State(state): State<AppState>,
+    _auth: AuthUser
+) -> Result<Json<Vec<FeeSchedule>>, ApiError> {
+    let schedules = state.store.list_fee_schedules().await?;
+
+    Ok(Json(schedules))
+}
+
+/// Create or replace the fee schedule for a user type and asset type (admin only)
+#[utoipa::path(
+    put,
+    path = "/api/v1/fee-schedules",
+    tag = "fees",
+    security(("bearer_auth" = [])),
+    request_body = SetFeeScheduleRequest,
+    responses(
+        (status = 200, description = "Fee schedule saved; applies to trades from now on", body = FeeSchedule),
+        (status = 400, description = "Invalid schedule", body = ApiErrorBody),
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn set_fee_schedule(
+    State(state): State<AppState>,
+    Json(mut request): Json<SetFeeScheduleRequest>
+) -> Result<Json<FeeSchedule>, ApiError> {
+    // Stored under the canonical asset type name
+    if let Some(asset_type) = &request.asset_type {
+        request.asset_type = Some(asset_type.parse::<AssetType>().map_err(ApiError::Validation)?.to_string());
+    }
+    validate_fee_schedule(&request)?;
+
+    let schedule = state.store.set_fee_schedule(&request).await?;
+
+    Ok(Json(schedule))
+}
+
+/// Delete a fee schedule (admin only)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fee-schedules/{fee_schedule_id}",
+    tag = "fees",
+    security(("bearer_auth" = [])),
+    params(
+        ("fee_schedule_id" = i32, Path, description = "Fee schedule ID")
+    ),
+    responses(
+        (status = 204, description = "Fee schedule deleted; trades it covered fall back to the default schedule or no fee"),
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 404, description = "Fee schedule not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_fee_schedule(
+    State(state): State<AppState>,
+    Path(fee_schedule_id): Path<i32>
+) -> Result<StatusCode, ApiError> {
+    state.store.delete_fee_schedule(fee_schedule_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
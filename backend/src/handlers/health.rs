@@ -1,7 +1,7 @@
-use axum::{response::Json, http::StatusCode};
+use axum::{response::Json, http::StatusCode, extract::State};
 use serde_json::{json, Value};
 use utoipa::ToSchema;
-use crate::db::get_db_client;
+use crate::state::AppState;
 
 /// Basic health check response
 #[derive(serde::Serialize, ToSchema)]
@@ -42,7 +42,7 @@ pub async fn health_check() -> Json<Value> {
 
 /// Database connectivity health check
 /// 
-/// Tests the connection to the SQL Server database
+/// Tests the connection to the configured store (SQL Server unless running in memory)
 #[utoipa::path(
     get,
     path = "/db-health",
@@ -52,13 +52,14 @@ pub async fn health_check() -> Json<Value> {
     ),
     tag = "health"
 )]
-pub async fn db_health_check() -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    match get_db_client().await {
-        Ok(_client) => {
+pub async fn db_health_check(State(state): State<AppState>) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let backend = state.store.backend_name();
+    match state.store.ping().await {
+        Ok(()) => {
             Ok(Json(json!({
                 "status": "healthy",
                 "database": "connected",
-                "connection": "SQL Server",
+                "connection": backend,
                 "timestamp": chrono::Utc::now().to_rfc3339(),
                 "message": "Database connection successful"
             })))
@@ -70,11 +71,11 @@ pub async fn db_health_check() -> Result<Json<Value>, (StatusCode, Json<Value>)>
                 Json(json!({
                     "status": "unhealthy",
                     "database": "disconnected",
-                    "connection": "SQL Server",
+                    "connection": backend,
                     "timestamp": chrono::Utc::now().to_rfc3339(),
                     "error": format!("Database connection failed: {}", e)
                 }))
             ))
         }
     }
-} 
\ No newline at end of file
+}
@@ -2,6 +2,13 @@ mod health;
 mod users;
 mod assets;
 mod portfolio;
+mod orders;
+mod tax_lots;
+mod fees;
+mod trading_rules;
+mod rebalancing;
+mod recurring;
+mod performance;
 mod risk;
 
 pub use health::*;
@@ -11,4 +18,11 @@ pub use users::*;
 pub use assets::*;
 
 pub use portfolio::*;
+pub use orders::*;
+pub use tax_lots::*;
+pub use fees::*;
+pub use trading_rules::*;
+pub use rebalancing::*;
+pub use recurring::*;
+pub use performance::*;
 pub use risk::*; 
\ No newline at end of file
@@ -0,0 +1,384 @@
+use axum::extract::State;
+use crate::extract::{Json, Path, Query};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{
+        check_scale, round_to_scale, AmendOrderRequest, BatchOrderRequest, BatchOrderResponse, BuyAssetRequest,
+        Order, OrderPreview, OrderPreviewRequest, OrderSide, OrderStatus, OrderType, PlaceOrderRequest,
+        SellAssetRequest, TimeInForce, MONEY_SCALE, PRICE_SCALE, QUANTITY_SCALE,
+    },
+    orders,
+    pretrade::{self, ProposedTrade},
+    state::AppState,
+};
+use super::portfolio::{validate_lots, validate_trade};
+
+/// Most legs a single batch may carry
+const MAX_BATCH_LEGS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct ListOrdersQuery {
+    pub status: Option<OrderStatus>,
+}
+
+/// Checks that the prices an order carries match its type
+fn validate_order(order: &PlaceOrderRequest) -> Result<(), ApiError> {
+    if order.quantity <= Decimal::ZERO {
+        return Err(ApiError::Validation("Quantity must be positive".to_string()));
+    }
+    check_scale(order.quantity, QUANTITY_SCALE, "Quantity").map_err(ApiError::Validation)?;
+
+    let (needs_limit, needs_stop) = match order.order_type {
+        OrderType::Market => (false, false),
+        OrderType::Limit => (true, false),
+        OrderType::Stop => (false, true),
+        OrderType::StopLimit => (true, true),
+    };
+    for (field, price, needed) in [("Limit price", order.limit_price, needs_limit), ("Stop price", order.stop_price, needs_stop)] {
+        match (price, needed) {
+            (Some(price), true) => {
+                if price <= Decimal::ZERO {
+                    return Err(ApiError::Validation(format!("{} must be positive", field)));
+                }
+                check_scale(price, PRICE_SCALE, field).map_err(ApiError::Validation)?;
+            },
+            (Some(_), false) => {
+                return Err(ApiError::Validation(format!(
+                    "{} is not allowed on {} orders", field, order.order_type.as_str()
+                )));
+            },
+            (None, true) => {
+                return Err(ApiError::Validation(format!(
+                    "{} is required on {} orders", field, order.order_type.as_str()
+                )));
+            },
+            (None, false) => {},
+        }
+    }
+
+    // Stops wait for a price move, which an immediate-or-cancel order cannot
+    if order.time_in_force == Some(TimeInForce::Ioc) && matches!(order.order_type, OrderType::Stop | OrderType::StopLimit) {
+        return Err(ApiError::Validation("IOC is only allowed on Market and Limit orders".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Checks an amendment changes something and that its values are well formed;
+/// whether a limit price fits the order's type is left to the store
+fn validate_amendment(amendment: &AmendOrderRequest) -> Result<(), ApiError> {
+    if amendment.quantity.is_none() && amendment.limit_price.is_none() {
+        return Err(ApiError::Validation("Provide a quantity or a limit price to amend".to_string()));
+    }
+    if let Some(quantity) = amendment.quantity {
+        if quantity <= Decimal::ZERO {
+            return Err(ApiError::Validation("Quantity must be positive".to_string()));
+        }
+        check_scale(quantity, QUANTITY_SCALE, "Quantity").map_err(ApiError::Validation)?;
+    }
+    if let Some(limit_price) = amendment.limit_price {
+        if limit_price <= Decimal::ZERO {
+            return Err(ApiError::Validation("Limit price must be positive".to_string()));
+        }
+        check_scale(limit_price, PRICE_SCALE, "Limit price").map_err(ApiError::Validation)?;
+    }
+    Ok(())
+}
+
+/// Loads an order the caller owns
+async fn owned_order(state: &AppState, auth: &AuthUser, order_id: i64) -> Result<Order, ApiError> {
+    let order = state.store.get_order(order_id).await?;
+    auth.ensure_portfolio(state, order.portfolio_id).await?;
+    Ok(order)
+}
+
+/// Place an order in a portfolio
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolios/{portfolio_id}/orders",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    request_body = PlaceOrderRequest,
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID to place the order in"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
+    ),
+    responses(
+        (status = 201, description = "Order placed; market and marketable orders come back executed", body = Order),
+        (status = 400, description = "Invalid order or insufficient holdings", body = ApiErrorBody),
+        (status = 404, description = "Portfolio or asset not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Insufficient funds, refused by a pre-trade rule, or Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn place_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<PlaceOrderRequest>
+) -> Result<(StatusCode, Json<Order>), ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+    validate_order(&request)?;
+
+    pretrade::check(state.store.as_ref(), &ProposedTrade {
+        portfolio_id,
+        asset_id: request.asset_id,
+        side: request.side,
+        quantity: request.quantity,
+        price: request.limit_price,
+    }).await?;
+
+    let order = state.store.place_order(portfolio_id, &request).await?;
+    let order = orders::submit(state.store.as_ref(), order).await?;
+
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+/// Preview a buy or sell without executing it
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolios/{portfolio_id}/orders/preview",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    request_body = OrderPreviewRequest,
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID to preview the trade in")
+    ),
+    responses(
+        (status = 200, description = "What the trade would do, and the pre-trade rules it breaks; nothing is recorded", body = OrderPreview),
+        (status = 400, description = "Invalid request data, lot selection or insufficient holdings", body = ApiErrorBody),
+        (status = 404, description = "Portfolio or asset not found", body = ApiErrorBody),
+        (status = 422, description = "Insufficient funds", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn preview_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<OrderPreviewRequest>
+) -> Result<Json<OrderPreview>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+    validate_trade(request.quantity, request.unit_price)?;
+    if let Some(lots) = &request.lots {
+        if request.side == OrderSide::Buy {
+            return Err(ApiError::Validation("Lots can only be selected when selling".to_string()));
+        }
+        validate_lots(lots, request.quantity)?;
+    }
+
+    // Breaches are reported rather than refusing the preview
+    let snapshot = state.store.pre_trade_snapshot(portfolio_id, request.asset_id).await?;
+    let violations = pretrade::violations(&ProposedTrade {
+        portfolio_id,
+        asset_id: request.asset_id,
+        side: request.side,
+        quantity: request.quantity,
+        price: request.unit_price,
+    }, &snapshot);
+
+    let preview = match request.side {
+        OrderSide::Buy => state.store.preview_buy(&BuyAssetRequest {
+            portfolio_id,
+            asset_id: request.asset_id,
+            quantity: request.quantity,
+            unit_price: request.unit_price,
+        }).await?,
+        OrderSide::Sell => state.store.preview_sell(&SellAssetRequest {
+            portfolio_id,
+            asset_id: request.asset_id,
+            quantity: request.quantity,
+            unit_price: request.unit_price,
+            lots: request.lots,
+        }).await?,
+    };
+
+    let position_pct_after = if preview.portfolio_value_after > Decimal::ZERO {
+        round_to_scale(
+            preview.quantity_held_after * snapshot.price / preview.portfolio_value_after * Decimal::ONE_HUNDRED,
+            MONEY_SCALE,
+        )
+    } else {
+        Decimal::ZERO
+    };
+
+    Ok(Json(OrderPreview {
+        portfolio_id,
+        asset_id: request.asset_id,
+        side: request.side,
+        projection: preview.projection,
+        quantity_held_after: preview.quantity_held_after,
+        average_price_after: preview.average_price_after,
+        position_pct_after,
+        violations,
+    }))
+}
+
+/// Execute several buys and sells in one transaction
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolios/{portfolio_id}/orders/batch",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    request_body = BatchOrderRequest,
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID to trade in"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
+    ),
+    responses(
+        (status = 200, description = "Batch processed; sells run before buys and each leg reports its own outcome", body = BatchOrderResponse),
+        (status = 400, description = "Invalid legs", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn execute_batch(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<BatchOrderRequest>
+) -> Result<Json<BatchOrderResponse>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    if request.legs.is_empty() || request.legs.len() > MAX_BATCH_LEGS {
+        return Err(ApiError::Validation(format!("A batch must have between 1 and {} legs", MAX_BATCH_LEGS)));
+    }
+    for (index, leg) in request.legs.iter().enumerate() {
+        let leg_check = validate_trade(leg.quantity, leg.unit_price).and_then(|()| match &leg.lots {
+            Some(_) if leg.side == OrderSide::Buy => {
+                Err(ApiError::Validation("Lots can only be selected when selling".to_string()))
+            },
+            Some(lots) => validate_lots(lots, leg.quantity),
+            None => Ok(()),
+        });
+        leg_check.map_err(|error| error.with_details(json!({ "leg": index })))?;
+    }
+
+    let response = orders::execute_batch(state.store.as_ref(), portfolio_id, &request).await?;
+
+    Ok(Json(response))
+}
+
+/// List a portfolio's orders
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/orders",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("status" = Option<OrderStatus>, Query, description = "Only orders with this status")
+    ),
+    responses(
+        (status = 200, description = "Orders, newest first", body = Vec<Order>),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_orders(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<ListOrdersQuery>
+) -> Result<Json<Vec<Order>>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let orders = state.store.list_orders(portfolio_id, query.status).await?;
+
+    Ok(Json(orders))
+}
+
+/// Get an order
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{order_id}",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    params(
+        ("order_id" = i64, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Order retrieved successfully", body = Order),
+        (status = 404, description = "Order not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_order(State(state): State<AppState>, auth: AuthUser, Path(order_id): Path<i64>) -> Result<Json<Order>, ApiError> {
+    let order = owned_order(&state, &auth, order_id).await?;
+    Ok(Json(order))
+}
+
+/// Amend the quantity or limit price of a pending order
+#[utoipa::path(
+    patch,
+    path = "/api/v1/orders/{order_id}",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    request_body = AmendOrderRequest,
+    params(
+        ("order_id" = i64, Path, description = "Order ID to amend")
+    ),
+    responses(
+        (status = 200, description = "Order amended; it comes back executed if the new terms are marketable", body = Order),
+        (status = 400, description = "Invalid amendment or insufficient holdings", body = ApiErrorBody),
+        (status = 404, description = "Order not found", body = ApiErrorBody),
+        (status = 409, description = "Order already executed, failed or cancelled", body = ApiErrorBody),
+        (status = 422, description = "Insufficient funds for the larger reservation, or refused by a pre-trade rule", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn amend_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i64>,
+    Json(request): Json<AmendOrderRequest>
+) -> Result<Json<Order>, ApiError> {
+    let order = owned_order(&state, &auth, order_id).await?;
+    validate_amendment(&request)?;
+
+    pretrade::check(state.store.as_ref(), &ProposedTrade {
+        portfolio_id: order.portfolio_id,
+        asset_id: order.asset_id,
+        side: order.side,
+        quantity: request.quantity.unwrap_or(order.quantity),
+        price: request.limit_price.or(order.limit_price),
+    }).await?;
+
+    let order = state.store.amend_order(order_id, &request).await?;
+    let order = orders::submit(state.store.as_ref(), order).await?;
+
+    Ok(Json(order))
+}
+
+/// Cancel a pending order and release its reserved funds
+#[utoipa::path(
+    delete,
+    path = "/api/v1/orders/{order_id}",
+    tag = "orders",
+    security(("bearer_auth" = [])),
+    params(
+        ("order_id" = i64, Path, description = "Order ID to cancel")
+    ),
+    responses(
+        (status = 200, description = "Order cancelled", body = Order),
+        (status = 404, description = "Order not found", body = ApiErrorBody),
+        (status = 409, description = "Order already executed, failed or cancelled", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn cancel_order(State(state): State<AppState>, auth: AuthUser, Path(order_id): Path<i64>) -> Result<Json<Order>, ApiError> {
+    owned_order(&state, &auth, order_id).await?;
+
+    let order = state.store.cancel_order(order_id, "Cancelled by user").await?;
+
+    Ok(Json(order))
+}
@@ -0,0 +1,172 @@
+use axum::extract::State;
+use crate::extract::{Json, Path, Query};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{
+        BackfillSnapshotsRequest, BackfillSnapshotsResponse, PerformanceInterval, PortfolioPerformance,
+        PortfolioReturns, UserReturns,
+    },
+    performance,
+    scheduler,
+    state::AppState,
+};
+
+#[derive(Deserialize)]
+pub struct PerformanceQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub interval: Option<PerformanceInterval>,
+}
+
+/// Get a portfolio's equity curve from its daily valuation snapshots
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/performance",
+    tag = "performance",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("from" = Option<NaiveDate>, Query, description = "First day, the earliest snapshot by default"),
+        ("to" = Option<NaiveDate>, Query, description = "Last day, today by default"),
+        ("interval" = Option<PerformanceInterval>, Query, description = "Daily (default), Weekly or Monthly points")
+    ),
+    responses(
+        (status = 200, description = "Portfolio value over time; no points before the first snapshot is taken", body = PortfolioPerformance),
+        (status = 400, description = "Invalid date range", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_portfolio_performance(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<PerformanceQuery>
+) -> Result<Json<PortfolioPerformance>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let to = query.to.unwrap_or_else(scheduler::today);
+    if query.from.is_some_and(|from| from > to) {
+        return Err(ApiError::Validation("from must not be after to".to_string()));
+    }
+
+    let snapshots = state.store.portfolio_snapshots(portfolio_id, query.from, Some(to)).await?;
+    let interval = query.interval.unwrap_or(PerformanceInterval::Daily);
+
+    Ok(Json(performance::equity_curve(portfolio_id, &snapshots, interval)))
+}
+
+/// Get a portfolio's time- and money-weighted returns over the standard periods
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/returns",
+    tag = "performance",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Returns for 1M, 3M, YTD, 1Y and since inception; no periods before the first snapshot is taken", body = PortfolioReturns),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_portfolio_returns(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<PortfolioReturns>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let snapshots = state.store.portfolio_snapshots(portfolio_id, None, None).await?;
+
+    Ok(Json(PortfolioReturns {
+        portfolio_id,
+        as_of: snapshots.last().map(|s| s.snapshot_date),
+        periods: performance::period_returns(&snapshots),
+    }))
+}
+
+/// Get the returns of all of a user's portfolios taken together
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/returns",
+    tag = "performance",
+    security(("bearer_auth" = [])),
+    params(
+        ("user_id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Returns for 1M, 3M, YTD, 1Y and since inception", body = UserReturns),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_user_returns(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<UserReturns>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    state.store.get_user(user_id).await?;
+    let portfolio_ids: Vec<i32> = state.store.list_portfolios(Some(user_id)).await?
+        .iter()
+        .map(|p| p.portfolio_id)
+        .collect();
+
+    let mut portfolio_snapshots = Vec::with_capacity(portfolio_ids.len());
+    for portfolio_id in &portfolio_ids {
+        portfolio_snapshots.push(state.store.portfolio_snapshots(*portfolio_id, None, None).await?);
+    }
+    // Money moved between the user's portfolios nets out here
+    let snapshots = performance::combine_snapshots(&portfolio_snapshots);
+
+    Ok(Json(UserReturns {
+        user_id,
+        portfolio_ids,
+        as_of: snapshots.last().map(|s| s.snapshot_date),
+        periods: performance::period_returns(&snapshots),
+    }))
+}
+
+/// Rebuild daily valuation snapshots from price and transaction history (admin only)
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolio-snapshots/backfill",
+    tag = "performance",
+    security(("bearer_auth" = [])),
+    request_body = BackfillSnapshotsRequest,
+    responses(
+        (status = 200, description = "Snapshots rebuilt through today", body = BackfillSnapshotsResponse),
+        (status = 400, description = "Start date in the future", body = ApiErrorBody),
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn backfill_portfolio_snapshots(
+    State(state): State<AppState>,
+    Json(request): Json<BackfillSnapshotsRequest>
+) -> Result<Json<BackfillSnapshotsResponse>, ApiError> {
+    if request.from.is_some_and(|from| from > scheduler::today()) {
+        return Err(ApiError::Validation("from cannot be in the future".to_string()));
+    }
+
+    let portfolio_ids = match request.portfolio_id {
+        Some(portfolio_id) => vec![portfolio_id],
+        None => state.store.list_portfolios(None).await?.iter().map(|p| p.portfolio_id).collect(),
+    };
+
+    let mut snapshots = 0;
+    for portfolio_id in &portfolio_ids {
+        snapshots += performance::backfill_snapshots(state.store.as_ref(), *portfolio_id, request.from).await?;
+    }
+
+    Ok(Json(BackfillSnapshotsResponse { portfolios: portfolio_ids.len(), snapshots }))
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use axum::extract::State;
+use crate::extract::{Json, Path};
+use rust_decimal::Decimal;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{
+        check_scale, AllocationTargets, AssetType, DriftReport, RebalancePlan, RebalanceRequest,
+        SetAllocationTargetsRequest, MONEY_SCALE, PRICE_SCALE,
+    },
+    rebalancer,
+    state::AppState,
+};
+
+/// Also spells each asset type the way holdings report it, so targets match them
+fn validate_targets(request: &mut SetAllocationTargetsRequest) -> Result<(), ApiError> {
+    let per_asset = request.targets.first().is_some_and(|t| t.asset_id.is_some());
+    let mut seen = HashSet::new();
+    let mut total = Decimal::ZERO;
+
+    for target in &mut request.targets {
+        let subject = match (target.asset_id, &target.asset_type) {
+            (Some(asset_id), None) => asset_id.to_string(),
+            (None, Some(asset_type)) => {
+                let asset_type = asset_type.parse::<AssetType>().map_err(ApiError::Validation)?.to_string();
+                target.asset_type = Some(asset_type.clone());
+                asset_type
+            },
+            _ => return Err(ApiError::Validation("Each target needs either an asset_id or an asset_type".to_string())),
+        };
+        if target.asset_id.is_some() != per_asset {
+            return Err(ApiError::Validation("Targets must be all per asset or all per asset type".to_string()));
+        }
+        if !seen.insert(subject.clone()) {
+            return Err(ApiError::Validation(format!("Duplicate target for {}", subject)));
+        }
+        if target.weight <= Decimal::ZERO {
+            return Err(ApiError::Validation("Weights must be positive".to_string()));
+        }
+        check_scale(target.weight, PRICE_SCALE, "Weight").map_err(ApiError::Validation)?;
+        total += target.weight;
+    }
+    if total > Decimal::ONE_HUNDRED {
+        return Err(ApiError::Validation(format!("Weights add up to {}%, more than 100%", total.normalize())));
+    }
+
+    if let Some(tolerance) = request.drift_tolerance_pct {
+        if tolerance < Decimal::ZERO || tolerance > Decimal::ONE_HUNDRED {
+            return Err(ApiError::Validation("Drift tolerance must be between 0 and 100".to_string()));
+        }
+        check_scale(tolerance, PRICE_SCALE, "Drift tolerance").map_err(ApiError::Validation)?;
+    }
+    if let Some(min_trade_value) = request.min_trade_value {
+        if min_trade_value < Decimal::ZERO {
+            return Err(ApiError::Validation("Minimum trade value cannot be negative".to_string()));
+        }
+        check_scale(min_trade_value, MONEY_SCALE, "Minimum trade value").map_err(ApiError::Validation)?;
+    }
+    Ok(())
+}
+
+/// Get a portfolio's target allocation
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/allocation-targets",
+    tag = "rebalancing",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Target allocation; no targets when none were set", body = AllocationTargets),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_allocation_targets(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<AllocationTargets>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let targets = state.store.allocation_targets(portfolio_id).await?;
+
+    Ok(Json(targets))
+}
+
+/// Replace a portfolio's target allocation
+#[utoipa::path(
+    put,
+    path = "/api/v1/portfolios/{portfolio_id}/allocation-targets",
+    tag = "rebalancing",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    request_body = SetAllocationTargetsRequest,
+    responses(
+        (status = 200, description = "Target allocation saved", body = AllocationTargets),
+        (status = 400, description = "Invalid targets", body = ApiErrorBody),
+        (status = 404, description = "Portfolio or asset not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn set_allocation_targets(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(mut request): Json<SetAllocationTargetsRequest>
+) -> Result<Json<AllocationTargets>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+    validate_targets(&mut request)?;
+
+    let targets = state.store.set_allocation_targets(portfolio_id, &request).await?;
+
+    Ok(Json(targets))
+}
+
+/// Report how far a portfolio's allocation has drifted from its targets
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/drift",
+    tag = "rebalancing",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Current weights against the targets", body = DriftReport),
+        (status = 404, description = "Portfolio not found or no targets set", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_drift(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<DriftReport>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let report = rebalancer::drift_report(state.store.as_ref(), portfolio_id).await?;
+
+    Ok(Json(report))
+}
+
+/// Plan the trades that bring a portfolio back to its targets, and optionally execute them
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolios/{portfolio_id}/rebalance",
+    tag = "rebalancing",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
+    ),
+    request_body = RebalanceRequest,
+    responses(
+        (status = 200, description = "Rebalance plan, with the batch order's outcome when executed", body = RebalancePlan),
+        (status = 404, description = "Portfolio not found or no targets set", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn rebalance_portfolio(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<RebalanceRequest>
+) -> Result<Json<RebalancePlan>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let plan = rebalancer::rebalance(state.store.as_ref(), portfolio_id, &request).await?;
+
+    Ok(Json(plan))
+}
@@ -0,0 +1,262 @@
+use axum::{extract::State, http::StatusCode};
+use crate::extract::{Json, Path};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{
+        check_scale, CreateRecurringInvestmentRequest, RecurrenceFrequency, RecurringInvestment,
+        RecurringInvestmentRun, UpdateRecurringInvestmentRequest, MONEY_SCALE,
+    },
+    scheduler,
+    state::AppState,
+    store::RecurringSchedule,
+};
+
+fn validate_schedule(
+    amount: Decimal,
+    frequency: RecurrenceFrequency,
+    day_of_week: Option<i32>,
+    day_of_month: Option<i32>,
+) -> Result<(), ApiError> {
+    if amount <= Decimal::ZERO {
+        return Err(ApiError::Validation("Amount must be positive".to_string()));
+    }
+    check_scale(amount, MONEY_SCALE, "Amount").map_err(ApiError::Validation)?;
+
+    match (frequency, day_of_week, day_of_month) {
+        (RecurrenceFrequency::Daily, None, None) => Ok(()),
+        (RecurrenceFrequency::Weekly, Some(1..=7), None) => Ok(()),
+        (RecurrenceFrequency::Monthly, None, Some(1..=28)) => Ok(()),
+        (RecurrenceFrequency::Daily, _, _) => Err(ApiError::Validation(
+            "Daily schedules take no day_of_week or day_of_month".to_string(),
+        )),
+        (RecurrenceFrequency::Weekly, _, _) => Err(ApiError::Validation(
+            "Weekly schedules need a day_of_week from 1 (Monday) to 7 (Sunday) and no day_of_month".to_string(),
+        )),
+        (RecurrenceFrequency::Monthly, _, _) => Err(ApiError::Validation(
+            "Monthly schedules need a day_of_month from 1 to 28 and no day_of_week".to_string(),
+        )),
+    }
+}
+
+/// Loads a recurring investment the caller owns
+async fn owned_schedule(state: &AppState, auth: &AuthUser, schedule_id: i32) -> Result<RecurringInvestment, ApiError> {
+    let schedule = state.store.get_recurring_investment(schedule_id).await?;
+    auth.ensure_portfolio(state, schedule.portfolio_id).await?;
+    Ok(schedule)
+}
+
+/// List a portfolio's recurring investments
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/recurring-investments",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Recurring investments of the portfolio", body = [RecurringInvestment]),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_recurring_investments(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<Vec<RecurringInvestment>>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let schedules = state.store.list_recurring_investments(portfolio_id).await?;
+
+    Ok(Json(schedules))
+}
+
+/// Schedule a recurring investment in a portfolio
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolios/{portfolio_id}/recurring-investments",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    request_body = CreateRecurringInvestmentRequest,
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID to invest in"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
+    ),
+    responses(
+        (status = 201, description = "Recurring investment scheduled", body = RecurringInvestment),
+        (status = 400, description = "Invalid schedule", body = ApiErrorBody),
+        (status = 404, description = "Portfolio or asset not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn create_recurring_investment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<CreateRecurringInvestmentRequest>
+) -> Result<(StatusCode, Json<RecurringInvestment>), ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+    validate_schedule(request.amount, request.frequency, request.day_of_week, request.day_of_month)?;
+
+    let today = scheduler::today();
+    let start_date = request.start_date.unwrap_or(today);
+    if start_date < today {
+        return Err(ApiError::Validation("Start date cannot be in the past".to_string()));
+    }
+
+    let schedule = RecurringSchedule {
+        amount: request.amount,
+        frequency: request.frequency,
+        day_of_week: request.day_of_week,
+        day_of_month: request.day_of_month,
+        next_run_date: Some(scheduler::next_run_date(request.frequency, request.day_of_week, request.day_of_month, start_date)),
+    };
+    let schedule = state.store.create_recurring_investment(portfolio_id, request.asset_id, &schedule).await?;
+
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// Get a recurring investment
+#[utoipa::path(
+    get,
+    path = "/api/v1/recurring-investments/{schedule_id}",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    params(
+        ("schedule_id" = i32, Path, description = "Recurring investment ID")
+    ),
+    responses(
+        (status = 200, description = "Recurring investment", body = RecurringInvestment),
+        (status = 404, description = "Recurring investment not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_recurring_investment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(schedule_id): Path<i32>
+) -> Result<Json<RecurringInvestment>, ApiError> {
+    let schedule = owned_schedule(&state, &auth, schedule_id).await?;
+
+    Ok(Json(schedule))
+}
+
+/// Change, pause or resume a recurring investment
+#[utoipa::path(
+    put,
+    path = "/api/v1/recurring-investments/{schedule_id}",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    request_body = UpdateRecurringInvestmentRequest,
+    params(
+        ("schedule_id" = i32, Path, description = "Recurring investment ID")
+    ),
+    responses(
+        (status = 200, description = "Recurring investment updated", body = RecurringInvestment),
+        (status = 400, description = "Invalid schedule", body = ApiErrorBody),
+        (status = 404, description = "Recurring investment not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn update_recurring_investment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(schedule_id): Path<i32>,
+    Json(update): Json<UpdateRecurringInvestmentRequest>
+) -> Result<Json<RecurringInvestment>, ApiError> {
+    let current = owned_schedule(&state, &auth, schedule_id).await?;
+
+    // A new frequency brings its own days rather than mixing with the old ones
+    let (frequency, day_of_week, day_of_month) = match update.frequency {
+        Some(frequency) => (frequency, update.day_of_week, update.day_of_month),
+        None => (
+            current.frequency,
+            update.day_of_week.or(current.day_of_week),
+            update.day_of_month.or(current.day_of_month),
+        ),
+    };
+    let amount = update.amount.unwrap_or(current.amount);
+    validate_schedule(amount, frequency, day_of_week, day_of_month)?;
+
+    let rescheduled = frequency != current.frequency
+        || day_of_week != current.day_of_week
+        || day_of_month != current.day_of_month;
+    let next_run_date: Option<NaiveDate> = match update.active.unwrap_or(current.active) {
+        false => None,
+        true if rescheduled || !current.active => {
+            Some(scheduler::next_run_date(frequency, day_of_week, day_of_month, scheduler::today()))
+        },
+        true => current.next_run_date,
+    };
+
+    let schedule = state.store.update_recurring_investment(schedule_id, &RecurringSchedule {
+        amount,
+        frequency,
+        day_of_week,
+        day_of_month,
+        next_run_date,
+    }).await?;
+
+    Ok(Json(schedule))
+}
+
+/// Delete a recurring investment and its run history
+#[utoipa::path(
+    delete,
+    path = "/api/v1/recurring-investments/{schedule_id}",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    params(
+        ("schedule_id" = i32, Path, description = "Recurring investment ID")
+    ),
+    responses(
+        (status = 204, description = "Recurring investment deleted"),
+        (status = 404, description = "Recurring investment not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_recurring_investment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(schedule_id): Path<i32>
+) -> Result<StatusCode, ApiError> {
+    owned_schedule(&state, &auth, schedule_id).await?;
+
+    state.store.delete_recurring_investment(schedule_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the runs of a recurring investment, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/recurring-investments/{schedule_id}/runs",
+    tag = "recurring-investments",
+    security(("bearer_auth" = [])),
+    params(
+        ("schedule_id" = i32, Path, description = "Recurring investment ID")
+    ),
+    responses(
+        (status = 200, description = "Executed and skipped runs", body = [RecurringInvestmentRun]),
+        (status = 404, description = "Recurring investment not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_recurring_runs(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(schedule_id): Path<i32>
+) -> Result<Json<Vec<RecurringInvestmentRun>>, ApiError> {
+    owned_schedule(&state, &auth, schedule_id).await?;
+
+    let runs = state.store.list_recurring_runs(schedule_id).await?;
+
+    Ok(Json(runs))
+}
@@ -1,11 +1,40 @@
-use axum::{Json, extract::Path};
+use axum::extract::State;
+use crate::extract::{Json, Path, Query};
+use serde::Deserialize;
 use uuid::Uuid;
-use crate::{models::{RiskAnalysis, PortfolioRiskAnalysis, RiskSummary, RiskMetrics}, db};
-use axum::http::StatusCode;
+use crate::{models::{RiskAnalysis, PortfolioRiskAnalysis, RiskSummary, RiskMetrics, PortfolioVar, VarMethod, AssetCorrelation}, error::{ApiError, ApiErrorBody}, state::AppState, auth::AuthUser, risk};
 
-// Helper function to safely convert SQL Server Numeric to f64
-fn numeric_to_f64(numeric: tiberius::numeric::Numeric) -> f64 {
-    numeric.to_string().parse::<f64>().unwrap_or(0.0)
+/// Longest VaR horizon accepted, about a year of trading days
+const MAX_VAR_HORIZON: u32 = 250;
+
+/// Days of price history correlations are measured over unless asked otherwise
+const DEFAULT_CORRELATION_DAYS: u32 = 90;
+
+/// Longest correlation lookback accepted, five years
+const MAX_CORRELATION_DAYS: u32 = 1825;
+
+/// Most assets a correlation matrix is worked out for
+const MAX_CORRELATION_ASSETS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct VarQuery {
+    pub confidence: Option<f64>,
+    pub horizon: Option<u32>,
+    pub method: Option<VarMethod>,
+}
+
+#[derive(Deserialize)]
+pub struct CorrelationQuery {
+    pub asset_ids: Option<String>,
+    pub days: Option<u32>,
+}
+
+fn correlation_days(query: &CorrelationQuery) -> Result<u32, ApiError> {
+    let days = query.days.unwrap_or(DEFAULT_CORRELATION_DAYS);
+    if !(2..=MAX_CORRELATION_DAYS).contains(&days) {
+        return Err(ApiError::Validation(format!("days must be between 2 and {}", MAX_CORRELATION_DAYS)));
+    }
+    Ok(days)
 }
 
 /// Get user risk metrics
@@ -13,65 +42,24 @@ fn numeric_to_f64(numeric: tiberius::numeric::Numeric) -> f64 {
     get,
     path = "/api/v1/risk/metrics/user/{userId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch risk metrics for")
     ),
     responses(
         (status = 200, description = "Risk metrics retrieved successfully", body = RiskAnalysis),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_user_risk_metrics(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<RiskAnalysis>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    // Check if user exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1";
-    let tiberius_uuid = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    let stream = client.query(check_query, &[&tiberius_uuid]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check user existence: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
-    }
+) -> Result<Json<RiskAnalysis>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    let query = "SELECT * FROM portfolio.vw_RiskAnalysis WHERE UserID = @P1";
-    let stream = client.query(query, &[&tiberius_uuid]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::NOT_FOUND, "Risk analysis not found".to_string()))?;
-
-    let risk_analysis = RiskAnalysis {
-        user_id,
-        user_name: row.get::<&str, _>("UserName").unwrap_or_default().to_string(),
-        user_type: row.get::<&str, _>("UserType").unwrap_or_default().to_string(),
-        total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
-        total_investment: row.get::<tiberius::numeric::Numeric, _>("TotalInvestment")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        maximum_drawdown: row.get::<tiberius::numeric::Numeric, _>("MaximumDrawdown")
-            .map(numeric_to_f64),
-        sharpe_ratio: row.get::<tiberius::numeric::Numeric, _>("SharpeRatio")
-            .map(numeric_to_f64),
-        risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
-        last_updated: row.get::<chrono::NaiveDateTime, _>("RiskMetricsLastUpdated")
-            .map(|dt| dt.to_string())
-            .unwrap_or_default(),
-    };
+    let risk_analysis = state.store.user_risk_analysis(user_id).await?;
 
     Ok(Json(risk_analysis))
 }
@@ -81,100 +69,44 @@ pub async fn get_user_risk_metrics(
     get,
     path = "/api/v1/risk/metrics/portfolio/{portfolioId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("portfolioId" = i32, Path, description = "Portfolio ID to fetch risk analysis for")
     ),
     responses(
         (status = 200, description = "Portfolio risk analysis retrieved successfully", body = PortfolioRiskAnalysis),
-        (status = 404, description = "Portfolio not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_portfolio_risk_analysis(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(portfolio_id): Path<i32>
-) -> Result<Json<PortfolioRiskAnalysis>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    // First check if portfolio exists and get basic info
-    let portfolio_query = "SELECT p.*, rm.* FROM portfolio.Portfolios p 
-        LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = p.UserID 
-        WHERE p.PortfolioID = @P1";
-    let stream = client.query(portfolio_query, &[&portfolio_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::NOT_FOUND, "Portfolio not found".to_string()))?;
-
-    let risk_analysis = PortfolioRiskAnalysis {
-        portfolio_id,
-        portfolio_name: row.get::<&str, _>("Name").unwrap_or_default().to_string(),
-        current_funds: row.get::<tiberius::numeric::Numeric, _>("CurrentFunds")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        current_profit_pct: row.get::<tiberius::numeric::Numeric, _>("CurrentProfitPct")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        maximum_drawdown: row.get::<tiberius::numeric::Numeric, _>("MaximumDrawdown")
-            .map(numeric_to_f64),
-        beta: row.get::<tiberius::numeric::Numeric, _>("Beta")
-            .map(numeric_to_f64),
-        sharpe_ratio: row.get::<tiberius::numeric::Numeric, _>("SharpeRatio")
-            .map(numeric_to_f64),
-        risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
-    };
+) -> Result<Json<PortfolioRiskAnalysis>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let risk_analysis = state.store.portfolio_risk_analysis(portfolio_id).await?;
 
     Ok(Json(risk_analysis))
 }
 
-/// Get overall risk summary
+/// Get overall risk summary (admin only)
 #[utoipa::path(
     get,
     path = "/api/v1/risk/summary",
     tag = "risk",
+    security(("bearer_auth" = [])),
     responses(
         (status = 200, description = "Risk summary retrieved successfully", body = RiskSummary),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn get_risk_summary() -> Result<Json<RiskSummary>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let query = "
-        SELECT 
-            COUNT(DISTINCT u.UserID) as TotalUsers,
-            COUNT(DISTINCT p.PortfolioID) as TotalPortfolios,
-            SUM(p.CurrentFunds) as TotalAssetsUnderManagement,
-            AVG(CAST(rm.VolatilityScore as float)) as AverageSystemRisk
-        FROM portfolio.Users u
-        LEFT JOIN portfolio.Portfolios p ON p.UserID = u.UserID
-        LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = u.UserID";
-
-    let stream = client.query(query, &[]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get risk summary".to_string()))?;
-
-    let summary = RiskSummary {
-        total_users: row.get("TotalUsers").unwrap_or_default(),
-        total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
-        total_assets_under_management: row.get::<tiberius::numeric::Numeric, _>("TotalAssetsUnderManagement")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        average_system_risk: row.get::<f64, _>("AverageSystemRisk")
-            .unwrap_or_default(),
-        calculated_at: chrono::Utc::now().naive_utc().to_string(),
-    };
-
-    Ok(Json(summary))
+pub async fn get_risk_summary(State(state): State<AppState>) -> Result<Json<RiskSummary>, ApiError> {
+    let risk_summary = state.store.risk_summary().await?;
+
+    Ok(Json(risk_summary))
 }
 
 /// Get portfolio-specific risk summary
@@ -182,68 +114,26 @@ pub async fn get_risk_summary() -> Result<Json<RiskSummary>, (StatusCode, String
     get,
     path = "/api/v1/risk/summary/portfolio/{portfolioId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("portfolioId" = i32, Path, description = "Portfolio ID to fetch risk summary for")
     ),
     responses(
         (status = 200, description = "Portfolio risk summary retrieved successfully", body = RiskSummary),
-        (status = 404, description = "Portfolio not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_portfolio_risk_summary(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(portfolio_id): Path<i32>
-) -> Result<Json<RiskSummary>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    // First check if portfolio exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Portfolios WHERE PortfolioID = @P1";
-    let stream = client.query(check_query, &[&portfolio_id]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check portfolio existence: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch portfolio check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "Portfolio not found".to_string()));
-    }
+) -> Result<Json<RiskSummary>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let risk_summary = state.store.portfolio_risk_summary(portfolio_id).await?;
 
-    let query = "
-        SELECT 
-            1 as TotalUsers,
-            1 as TotalPortfolios,
-            p.CurrentFunds as TotalAssetsUnderManagement,
-            COALESCE(CAST(rm.VolatilityScore as float), 0) as AverageSystemRisk
-        FROM portfolio.Portfolios p
-        LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = p.UserID
-        WHERE p.PortfolioID = @P1";
-
-    let stream = client.query(query, &[&portfolio_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get risk summary".to_string()))?;
-
-    let summary = RiskSummary {
-        total_users: row.get("TotalUsers").unwrap_or_default(),
-        total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
-        total_assets_under_management: row.get::<tiberius::numeric::Numeric, _>("TotalAssetsUnderManagement")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        average_system_risk: row.get::<f64, _>("AverageSystemRisk")
-            .unwrap_or_default(),
-        calculated_at: chrono::Utc::now().naive_utc().to_string(),
-    };
-
-    Ok(Json(summary))
+    Ok(Json(risk_summary))
 }
 
 /// Get user-specific risk summary
@@ -251,71 +141,26 @@ pub async fn get_portfolio_risk_summary(
     get,
     path = "/api/v1/risk/summary/user/{userId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch risk summary for")
     ),
     responses(
         (status = 200, description = "User risk summary retrieved successfully", body = RiskSummary),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_user_risk_summary(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<RiskSummary>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    // Check if user exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1";
-    let tiberius_uuid = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    let stream = client.query(check_query, &[&tiberius_uuid]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check user existence: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
-    }
+) -> Result<Json<RiskSummary>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    let query = "
-        SELECT 
-            1 as TotalUsers,
-            COUNT(DISTINCT p.PortfolioID) as TotalPortfolios,
-            SUM(p.CurrentFunds) as TotalAssetsUnderManagement,
-            COALESCE(CAST(rm.VolatilityScore as float), 0) as AverageSystemRisk
-        FROM portfolio.Users u
-        LEFT JOIN portfolio.Portfolios p ON p.UserID = u.UserID
-        LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = u.UserID
-        WHERE u.UserID = @P1
-        GROUP BY rm.VolatilityScore";
-
-    let stream = client.query(query, &[&tiberius_uuid]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get risk summary".to_string()))?;
-
-    let summary = RiskSummary {
-        total_users: row.get("TotalUsers").unwrap_or_default(),
-        total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
-        total_assets_under_management: row.get::<tiberius::numeric::Numeric, _>("TotalAssetsUnderManagement")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        average_system_risk: row.get::<f64, _>("AverageSystemRisk")
-            .unwrap_or_default(),
-        calculated_at: chrono::Utc::now().naive_utc().to_string(),
-    };
-
-    Ok(Json(summary))
+    let risk_summary = state.store.user_risk_summary(user_id).await?;
+
+    Ok(Json(risk_summary))
 }
 
 /// Get latest risk metrics for a user using stored procedure
@@ -323,123 +168,54 @@ pub async fn get_user_risk_summary(
     get,
     path = "/api/v1/risk/latest/{userId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch latest risk metrics for")
     ),
     responses(
         (status = 200, description = "Latest risk metrics retrieved successfully", body = RiskMetrics),
-        (status = 404, description = "User not found or no risk metrics available"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found or no risk metrics available", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_user_latest_risk_metrics(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<RiskMetrics>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_uuid = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    let query = "EXEC portfolio.sp_GetUserLatestRiskMetrics @P1";
-    
-    let stream = client.query(query, &[&tiberius_uuid]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::NOT_FOUND, "No risk metrics found for user".to_string()))?;
-
-    let risk_metrics = RiskMetrics {
-        metric_id: row.get("MetricID").unwrap_or_default(),
-        user_id,
-        maximum_drawdown: row.get::<tiberius::numeric::Numeric, _>("MaximumDrawdown")
-            .map(numeric_to_f64),
-        beta: row.get::<tiberius::numeric::Numeric, _>("Beta")
-            .map(numeric_to_f64),
-        sharpe_ratio: row.get::<tiberius::numeric::Numeric, _>("SharpeRatio")
-            .map(numeric_to_f64),
-        absolute_return: row.get::<tiberius::numeric::Numeric, _>("AbsoluteReturn")
-            .map(numeric_to_f64),
-        volatility_score: row.get::<tiberius::numeric::Numeric, _>("VolatilityScore")
-            .map(numeric_to_f64),
-        risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
-        captured_at: row.get::<chrono::NaiveDateTime, _>("CapturedAt")
-            .map(|dt| dt.to_string())
-            .unwrap_or_default(),
-    };
+) -> Result<Json<RiskMetrics>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let risk_metrics = state.store.latest_risk_metrics(user_id).await?;
 
     Ok(Json(risk_metrics))
 }
 
-/// Calculate fresh risk metrics for a user using stored procedure
+/// Calculate fresh risk metrics for a user from their portfolios' daily value history
 #[utoipa::path(
     post,
     path = "/api/v1/risk/calculate/{userId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to calculate risk metrics for")
     ),
     responses(
         (status = 200, description = "Risk metrics calculated successfully", body = RiskMetrics),
-        (status = 403, description = "User is not premium or access denied"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 403, description = "User is not premium or access denied", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn calculate_user_risk_metrics(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<RiskMetrics>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_uuid = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    let query = "EXEC portfolio.sp_CalculateUserRiskMetrics @P1, @P2";
-    let days_back = 90i32; // Default to 90 days
-    
-    let stream = client.query(query, &[&tiberius_uuid, &days_back]).await.map_err(|e| {
-        let error_msg = e.to_string();
-        if error_msg.contains("Risk metrics only available for premium users") {
-            return (StatusCode::FORBIDDEN, "Risk metrics only available for premium users".to_string());
-        }
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute calculation: {}", e))
-    })?;
-    
-    let _row = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Calculation completed but no results returned".to_string()))?;
-
-    // After calculation, get the latest metrics
-    let latest_query = "EXEC portfolio.sp_GetUserLatestRiskMetrics @P1";
-    let latest_stream = client.query(latest_query, &[&tiberius_uuid]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch latest metrics: {}", e)))?;
-    
-    let latest_row = latest_stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch latest results: {}", e)))?
-        .into_iter()
-        .next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Calculation completed but no metrics available".to_string()))?;
-
-    let risk_metrics = RiskMetrics {
-        metric_id: latest_row.get("MetricID").unwrap_or_default(),
-        user_id,
-        maximum_drawdown: latest_row.get::<tiberius::numeric::Numeric, _>("MaximumDrawdown")
-            .map(numeric_to_f64),
-        beta: latest_row.get::<tiberius::numeric::Numeric, _>("Beta")
-            .map(numeric_to_f64),
-        sharpe_ratio: latest_row.get::<tiberius::numeric::Numeric, _>("SharpeRatio")
-            .map(numeric_to_f64),
-        absolute_return: latest_row.get::<tiberius::numeric::Numeric, _>("AbsoluteReturn")
-            .map(numeric_to_f64),
-        volatility_score: latest_row.get::<tiberius::numeric::Numeric, _>("VolatilityScore")
-            .map(numeric_to_f64),
-        risk_level: latest_row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
-        captured_at: latest_row.get::<chrono::NaiveDateTime, _>("CapturedAt")
-            .map(|dt| dt.to_string())
-            .unwrap_or_default(),
-    };
+) -> Result<Json<RiskMetrics>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let days_back = 90; // Default to 90 days
+    let calculated = risk::calculate_user_risk(state.store.as_ref(), user_id, days_back).await?;
+    let risk_metrics = state.store.record_risk_metrics(user_id, &calculated).await?;
 
     Ok(Json(risk_metrics))
 }
@@ -449,22 +225,24 @@ pub async fn calculate_user_risk_metrics(
     get,
     path = "/api/v1/risk/trend/{userId}",
     tag = "risk",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch risk trend for"),
         ("days" = Option<i32>, Query, description = "Number of days back to analyze (default: 90)")
     ),
     responses(
         (status = 200, description = "Risk trend retrieved successfully", body = Vec<RiskMetrics>),
-        (status = 404, description = "User not found or no risk metrics available"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found or no risk metrics available", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_user_risk_trend(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>
-) -> Result<Json<Vec<RiskMetrics>>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
+    Query(params): Query<std::collections::HashMap<String, String>>
+) -> Result<Json<Vec<RiskMetrics>>, ApiError> {
+    auth.ensure_user(user_id)?;
 
     // Extract days parameter from query or default to 90
     let days_back = params
@@ -472,41 +250,121 @@ pub async fn get_user_risk_trend(
         .and_then(|d| d.parse::<i32>().ok())
         .unwrap_or(90i32);
 
-    let tiberius_uuid = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    let sp_query = "EXEC portfolio.sp_GetUserRiskTrend @P1, @P2";
-    
-    let stream = client.query(sp_query, &[&tiberius_uuid, &days_back]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let results = stream.into_first_result().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?;
-
-    if results.is_empty() {
-        return Err((StatusCode::NOT_FOUND, "No risk trend data found for user".to_string()));
+    let risk_trend = state.store.risk_trend(user_id, days_back).await?;
+
+    Ok(Json(risk_trend))
+}
+
+/// Get Value-at-Risk and expected shortfall (CVaR) of a portfolio's holdings
+#[utoipa::path(
+    get,
+    path = "/api/v1/risk/var/portfolio/{portfolioId}",
+    tag = "risk",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolioId" = i32, Path, description = "Portfolio ID to estimate tail risk for"),
+        ("confidence" = Option<f64>, Query, description = "Confidence level between 0.5 and 1 (default: 0.95)"),
+        ("horizon" = Option<u32>, Query, description = "Horizon in days, 1 to 250 (default: 1)"),
+        ("method" = Option<VarMethod>, Query, description = "historical (default), parametric or montecarlo")
+    ),
+    responses(
+        (status = 200, description = "VaR and CVaR with each holding's contribution", body = PortfolioVar),
+        (status = 400, description = "Invalid parameters or not enough price history", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_portfolio_var(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<VarQuery>
+) -> Result<Json<PortfolioVar>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let confidence = query.confidence.unwrap_or(0.95);
+    if !(confidence > 0.5 && confidence < 1.0) {
+        return Err(ApiError::Validation("confidence must be between 0.5 and 1".to_string()));
     }
+    let horizon = query.horizon.unwrap_or(1);
+    if !(1..=MAX_VAR_HORIZON).contains(&horizon) {
+        return Err(ApiError::Validation(format!("horizon must be between 1 and {} days", MAX_VAR_HORIZON)));
+    }
+    let method = query.method.unwrap_or_default();
+
+    let var = risk::portfolio_var(state.store.as_ref(), portfolio_id, method, confidence, horizon).await?;
 
-    let mut risk_trend = Vec::new();
-    for row in results {
-        let risk_metrics = RiskMetrics {
-            metric_id: row.get("MetricID").unwrap_or_default(),
-            user_id,
-            maximum_drawdown: row.get::<tiberius::numeric::Numeric, _>("MaximumDrawdown")
-                .map(numeric_to_f64),
-            beta: row.get::<tiberius::numeric::Numeric, _>("Beta")
-                .map(numeric_to_f64),
-            sharpe_ratio: row.get::<tiberius::numeric::Numeric, _>("SharpeRatio")
-                .map(numeric_to_f64),
-            absolute_return: row.get::<tiberius::numeric::Numeric, _>("AbsoluteReturn")
-                .map(numeric_to_f64),
-            volatility_score: row.get::<tiberius::numeric::Numeric, _>("VolatilityScore")
-                .map(numeric_to_f64),
-            risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
-            captured_at: row.get::<chrono::NaiveDateTime, _>("CapturedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-        };
-        risk_trend.push(risk_metrics);
+    Ok(Json(var))
+}
+
+/// Get the correlation and covariance matrices of assets' daily returns
+#[utoipa::path(
+    get,
+    path = "/api/v1/risk/correlation",
+    tag = "risk",
+    security(("bearer_auth" = [])),
+    params(
+        ("asset_ids" = String, Query, description = "Comma-separated asset IDs, 2 to 50"),
+        ("days" = Option<u32>, Query, description = "Days of price history, 2 to 1825 (default: 90)")
+    ),
+    responses(
+        (status = 200, description = "Matrices in the order the assets were given", body = AssetCorrelation),
+        (status = 400, description = "Invalid parameters or not enough price history", body = ApiErrorBody),
+        (status = 404, description = "Asset not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_asset_correlation(
+    State(state): State<AppState>,
+    _auth: AuthUser,
+    Query(query): Query<CorrelationQuery>
+) -> Result<Json<AssetCorrelation>, ApiError> {
+    let days = correlation_days(&query)?;
+
+    let mut asset_ids: Vec<i32> = Vec::new();
+    for id in query.asset_ids.as_deref().unwrap_or_default().split(',').filter(|id| !id.trim().is_empty()) {
+        let asset_id = id.trim().parse()
+            .map_err(|_| ApiError::Validation(format!("Invalid asset ID: {}", id.trim())))?;
+        if !asset_ids.contains(&asset_id) {
+            asset_ids.push(asset_id);
+        }
+    }
+    if !(2..=MAX_CORRELATION_ASSETS).contains(&asset_ids.len()) {
+        return Err(ApiError::Validation(format!("asset_ids must list between 2 and {} assets", MAX_CORRELATION_ASSETS)));
     }
 
-    Ok(Json(risk_trend))
-} 
\ No newline at end of file
+    let correlation = risk::asset_correlation(state.store.as_ref(), &asset_ids, days).await?;
+
+    Ok(Json(correlation))
+}
+
+/// Get the correlation and covariance matrices of the assets a portfolio holds
+#[utoipa::path(
+    get,
+    path = "/api/v1/risk/correlation/portfolio/{portfolioId}",
+    tag = "risk",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolioId" = i32, Path, description = "Portfolio whose current holdings are compared"),
+        ("days" = Option<u32>, Query, description = "Days of price history, 2 to 1825 (default: 90)")
+    ),
+    responses(
+        (status = 200, description = "Matrices over the current holdings; empty without any", body = AssetCorrelation),
+        (status = 400, description = "Invalid parameters or not enough price history", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_portfolio_correlation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<CorrelationQuery>
+) -> Result<Json<AssetCorrelation>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let days = correlation_days(&query)?;
+    let correlation = risk::portfolio_correlation(state.store.as_ref(), portfolio_id, days).await?;
+
+    Ok(Json(correlation))
+}
@@ -0,0 +1,254 @@
+use axum::extract::State;
+use crate::extract::{Json, Path, Query};
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{AssetRealizedPnl, CostBasisSettings, RealizedLot, RealizedPnl, SaleRealizedGain, SetCostBasisRequest, TaxLot},
+    state::AppState,
+};
+
+#[derive(Deserialize)]
+pub struct RealizedGainsQuery {
+    pub asset_id: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct RealizedPnlQuery {
+    pub year: Option<i32>,
+}
+
+/// Groups per-lot rows, newest sale first, into one entry per sale
+fn group_by_sale(lots: Vec<RealizedLot>) -> Vec<SaleRealizedGain> {
+    let mut sales: Vec<SaleRealizedGain> = Vec::new();
+    for lot in lots {
+        match sales.last_mut() {
+            Some(sale) if sale.sell_transaction_id == lot.sell_transaction_id => {
+                sale.quantity += lot.quantity;
+                sale.proceeds += lot.proceeds;
+                sale.cost_basis += lot.cost_basis;
+                sale.realized_gain += lot.realized_gain;
+                sale.lots.push(lot);
+            },
+            _ => sales.push(SaleRealizedGain {
+                sell_transaction_id: lot.sell_transaction_id,
+                asset_id: lot.asset_id,
+                symbol: lot.symbol.clone(),
+                sold_at: lot.realized_at.clone(),
+                sale_price: lot.sale_price,
+                quantity: lot.quantity,
+                proceeds: lot.proceeds,
+                cost_basis: lot.cost_basis,
+                realized_gain: lot.realized_gain,
+                cost_basis_method: lot.cost_basis_method,
+                lots: vec![lot],
+            }),
+        }
+    }
+    sales
+}
+
+/// Get a portfolio's cost basis method
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/cost-basis",
+    tag = "portfolios",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Cost basis method", body = CostBasisSettings),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_cost_basis_method(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<CostBasisSettings>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let cost_basis_method = state.store.cost_basis_method(portfolio_id).await?;
+
+    Ok(Json(CostBasisSettings { portfolio_id, cost_basis_method }))
+}
+
+/// Change a portfolio's cost basis method
+#[utoipa::path(
+    put,
+    path = "/api/v1/portfolios/{portfolio_id}/cost-basis",
+    tag = "portfolios",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    request_body = SetCostBasisRequest,
+    responses(
+        (status = 200, description = "Cost basis method updated; earlier sales keep their basis", body = CostBasisSettings),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn set_cost_basis_method(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<SetCostBasisRequest>
+) -> Result<Json<CostBasisSettings>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    state.store.set_cost_basis_method(portfolio_id, request.cost_basis_method).await?;
+
+    Ok(Json(CostBasisSettings { portfolio_id, cost_basis_method: request.cost_basis_method }))
+}
+
+/// List the open tax lots of a holding
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/holdings/{asset_id}/lots",
+    tag = "portfolios",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("asset_id" = i32, Path, description = "Asset ID")
+    ),
+    responses(
+        (status = 200, description = "Lots with shares left, oldest first", body = Vec<TaxLot>),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_holding_lots(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((portfolio_id, asset_id)): Path<(i32, i32)>
+) -> Result<Json<Vec<TaxLot>>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let lots = state.store.open_lots(portfolio_id, Some(asset_id)).await?;
+
+    Ok(Json(lots))
+}
+
+/// List the realized gain of each sale in a portfolio
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/realized-gains",
+    tag = "portfolios",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("asset_id" = Option<i32>, Query, description = "Only sales of this asset"),
+        ("from" = Option<NaiveDate>, Query, description = "First day included (YYYY-MM-DD)"),
+        ("to" = Option<NaiveDate>, Query, description = "Last day included (YYYY-MM-DD)")
+    ),
+    responses(
+        (status = 200, description = "Sales with the lots they consumed, newest first", body = Vec<SaleRealizedGain>),
+        (status = 400, description = "Invalid date range", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_realized_gains(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<RealizedGainsQuery>
+) -> Result<Json<Vec<SaleRealizedGain>>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ApiError::Validation("from must not be after to".to_string()));
+        }
+    }
+
+    let lots = state.store.realized_lots(portfolio_id, query.asset_id, query.from, query.to).await?;
+
+    Ok(Json(group_by_sale(lots)))
+}
+
+/// Get a portfolio's realized P&L for a year, to date for the current one
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/realized-pnl",
+    tag = "portfolios",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID"),
+        ("year" = Option<i32>, Query, description = "Calendar year, the current one by default")
+    ),
+    responses(
+        (status = 200, description = "Realized P&L in total and per asset", body = RealizedPnl),
+        (status = 400, description = "Year in the future", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_realized_pnl(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Query(query): Query<RealizedPnlQuery>
+) -> Result<Json<RealizedPnl>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let today = Utc::now().date_naive();
+    let year = query.year.unwrap_or(today.year());
+    if year > today.year() {
+        return Err(ApiError::Validation("year must not be in the future".to_string()));
+    }
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| ApiError::Validation("Invalid year".to_string()))?;
+    let to = if year == today.year() {
+        today
+    } else {
+        NaiveDate::from_ymd_opt(year, 12, 31)
+            .ok_or_else(|| ApiError::Validation("Invalid year".to_string()))?
+    };
+
+    let sales = group_by_sale(state.store.realized_lots(portfolio_id, None, Some(from), Some(to)).await?);
+
+    let mut by_asset: Vec<AssetRealizedPnl> = Vec::new();
+    for sale in &sales {
+        let index = match by_asset.iter().position(|a| a.asset_id == sale.asset_id) {
+            Some(index) => index,
+            None => {
+                by_asset.push(AssetRealizedPnl {
+                    asset_id: sale.asset_id,
+                    symbol: sale.symbol.clone(),
+                    sales: 0,
+                    proceeds: Decimal::ZERO,
+                    cost_basis: Decimal::ZERO,
+                    realized_gain: Decimal::ZERO,
+                });
+                by_asset.len() - 1
+            },
+        };
+        let asset = &mut by_asset[index];
+        asset.sales += 1;
+        asset.proceeds += sale.proceeds;
+        asset.cost_basis += sale.cost_basis;
+        asset.realized_gain += sale.realized_gain;
+    }
+    by_asset.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    Ok(Json(RealizedPnl {
+        portfolio_id,
+        year,
+        from: from.to_string(),
+        to: to.to_string(),
+        sales: sales.len() as i32,
+        proceeds: by_asset.iter().map(|a| a.proceeds).sum(),
+        cost_basis: by_asset.iter().map(|a| a.cost_basis).sum(),
+        realized_gain: by_asset.iter().map(|a| a.realized_gain).sum(),
+        by_asset,
+    }))
+}
@@ -0,0 +1,145 @@
+use axum::extract::State;
+use crate::extract::{Json, Path};
+use rust_decimal::Decimal;
+
+use crate::{
+    auth::AuthUser,
+    error::{ApiError, ApiErrorBody},
+    models::{
+        check_scale, AssetType, QuantityIncrement, SetQuantityIncrementRequest, SetTradingLimitsRequest,
+        TradingLimits, MONEY_SCALE, PRICE_SCALE, QUANTITY_SCALE,
+    },
+    state::AppState,
+};
+
+fn validate_limits(limits: &SetTradingLimitsRequest) -> Result<(), ApiError> {
+    if let Some(pct) = limits.max_position_pct {
+        if pct <= Decimal::ZERO || pct > Decimal::ONE_HUNDRED {
+            return Err(ApiError::Validation("Max position percentage must be above 0 and at most 100".to_string()));
+        }
+        check_scale(pct, PRICE_SCALE, "Max position percentage").map_err(ApiError::Validation)?;
+    }
+    for (field, value) in [("Max order notional", limits.max_order_notional), ("Max daily volume", limits.max_daily_volume)] {
+        if let Some(value) = value {
+            if value <= Decimal::ZERO {
+                return Err(ApiError::Validation(format!("{} must be positive", field)));
+            }
+            check_scale(value, MONEY_SCALE, field).map_err(ApiError::Validation)?;
+        }
+    }
+    if limits.max_price_age_minutes.is_some_and(|minutes| minutes <= 0) {
+        return Err(ApiError::Validation("Max price age must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Get a portfolio's pre-trade limits
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolios/{portfolio_id}/trading-limits",
+    tag = "trading-rules",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    responses(
+        (status = 200, description = "Trading limits; absent values are not enforced", body = TradingLimits),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_trading_limits(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>
+) -> Result<Json<TradingLimits>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+
+    let limits = state.store.trading_limits(portfolio_id).await?;
+
+    Ok(Json(limits))
+}
+
+/// Replace a portfolio's pre-trade limits
+#[utoipa::path(
+    put,
+    path = "/api/v1/portfolios/{portfolio_id}/trading-limits",
+    tag = "trading-rules",
+    security(("bearer_auth" = [])),
+    params(
+        ("portfolio_id" = i32, Path, description = "Portfolio ID")
+    ),
+    request_body = SetTradingLimitsRequest,
+    responses(
+        (status = 200, description = "Trading limits saved; omitted limits are removed", body = TradingLimits),
+        (status = 400, description = "Invalid limits", body = ApiErrorBody),
+        (status = 404, description = "Portfolio not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn set_trading_limits(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(portfolio_id): Path<i32>,
+    Json(request): Json<SetTradingLimitsRequest>
+) -> Result<Json<TradingLimits>, ApiError> {
+    auth.ensure_portfolio(&state, portfolio_id).await?;
+    validate_limits(&request)?;
+
+    let limits = state.store.set_trading_limits(portfolio_id, &request).await?;
+
+    Ok(Json(limits))
+}
+
+/// List the quantity increment of each asset type
+#[utoipa::path(
+    get,
+    path = "/api/v1/quantity-increments",
+    tag = "trading-rules",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Quantity increments by asset type", body = Vec<QuantityIncrement>),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn list_quantity_increments(
+    State(state): State<AppState>,
+    _auth: AuthUser
+) -> Result<Json<Vec<QuantityIncrement>>, ApiError> {
+    let increments = state.store.quantity_increments().await?;
+
+    Ok(Json(increments))
+}
+
+/// Change the quantity increment of an asset type (admin only)
+#[utoipa::path(
+    put,
+    path = "/api/v1/quantity-increments/{asset_type}",
+    tag = "trading-rules",
+    security(("bearer_auth" = [])),
+    params(
+        ("asset_type" = String, Path, description = "Stock, Index, Cryptocurrency or Commodity")
+    ),
+    request_body = SetQuantityIncrementRequest,
+    responses(
+        (status = 200, description = "Quantity increment saved; applies to trades from now on", body = QuantityIncrement),
+        (status = 400, description = "Invalid asset type or increment", body = ApiErrorBody),
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn set_quantity_increment(
+    State(state): State<AppState>,
+    Path(asset_type): Path<String>,
+    Json(request): Json<SetQuantityIncrementRequest>
+) -> Result<Json<QuantityIncrement>, ApiError> {
+    let asset_type = asset_type.parse::<AssetType>().map_err(ApiError::Validation)?;
+    if request.increment <= Decimal::ZERO {
+        return Err(ApiError::Validation("Increment must be positive".to_string()));
+    }
+    check_scale(request.increment, QUANTITY_SCALE, "Increment").map_err(ApiError::Validation)?;
+
+    let increment = state.store.set_quantity_increment(&asset_type.to_string(), request.increment).await?;
+
+    Ok(Json(increment))
+}
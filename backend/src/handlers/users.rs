@@ -1,59 +1,27 @@
-use axum::{Json, extract::Path};
-use crate::{models::{User, ExtendedUser, CreateUserRequest, UpdateUserRequest, LoginRequest, LoginResponse, DepositRequest, WithdrawRequest, AllocateRequest, DeallocateRequest, UpgradePremiumRequest, FundOperationResponse, PremiumUpgradeResponse, AccountSummary, SetPaymentMethodRequest, PaymentMethodResponse, ManageSubscriptionRequest, SubscriptionResponse, FundTransaction}, db};
-use tiberius::time::chrono;
+use axum::extract::State;
+use crate::extract::{Json, Path};
+use crate::{models::{User, ExtendedUser, CreateUserRequest, UpdateUserRequest, LoginRequest, LoginResponse, RefreshTokenRequest, TokenResponse, LogoutAllResponse, DepositRequest, WithdrawRequest, AllocateRequest, DeallocateRequest, UpgradePremiumRequest, FundOperationResponse, PremiumUpgradeResponse, AccountSummary, SetPaymentMethodRequest, PaymentMethodResponse, ManageSubscriptionRequest, SubscriptionResponse, FundTransaction, check_scale, MONEY_SCALE}, error::{ApiError, ApiErrorBody}, state::AppState, store::{NewSession, RefreshOutcome, UserCredentials}, auth::{cleared_cookies, cookie, hash_password, hash_refresh_token, session_cookies, verify_password, AuthError, AuthUser, PasswordCheck, RefreshToken, Role, REFRESH_COOKIE}};
+use rust_decimal::Decimal;
 use uuid::Uuid;
-use jsonwebtoken::{encode, Header, EncodingKey};
-use serde::{Serialize, Deserialize};
-use axum::http::header::{AUTHORIZATION, SET_COOKIE};
+use axum::http::header::{AUTHORIZATION, SET_COOKIE, USER_AGENT};
 use axum::response::Response;
-use axum::http::{StatusCode, response::Builder};
-use std::time::{SystemTime, UNIX_EPOCH};
-
-// Helper function to safely convert SQL Server Numeric to f64
-fn numeric_to_f64(numeric: tiberius::numeric::Numeric) -> f64 {
-    numeric.to_string().parse::<f64>().unwrap_or(0.0)
-}
+use axum::http::{HeaderMap, StatusCode, response::Builder};
 
 
-/// List all users
+/// List all users (admin only)
 #[utoipa::path(
     get,
     path = "/api/v1/users",
     tag = "users",
+    security(("bearer_auth" = [])),
     responses(
         (status = 200, description = "List of users retrieved successfully", body = Vec<User>),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 403, description = "Caller is not an administrator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn list_users() -> Result<Json<Vec<User>>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let query = "SELECT UserID, Name, Email, CountryOfResidence, IBAN, UserType, CreatedAt, UpdatedAt FROM portfolio.Users ORDER BY CreatedAt DESC";
-    let stream = client.query(query, &[]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?;
-    
-    let users = rows.into_iter().map(|row| {
-        User {
-            user_id: row.get::<tiberius::Uuid, _>("UserID")
-                .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
-                .unwrap_or_default(),
-            name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-            email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
-            country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
-            iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
-            user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-            created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-            updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-        }
-    }).collect();
+pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<User>>, ApiError> {
+    let users = state.store.list_users().await?;
 
     Ok(Json(users))
 }
@@ -63,46 +31,20 @@ pub async fn list_users() -> Result<Json<Vec<User>>, (StatusCode, String)> {
     get,
     path = "/api/v1/users/{user_id}",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to fetch")
     ),
     responses(
         (status = 200, description = "User found successfully", body = User),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn get_user(Path(user_id): Path<Uuid>) -> Result<Json<User>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Use simple query for basic user info
-    let query = "SELECT UserID, Name, Email, CountryOfResidence, IBAN, UserType, CreatedAt, UpdatedAt FROM portfolio.Users WHERE UserID = @P1";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
-
-    let user = User {
-        user_id: Uuid::from_bytes(*tiberius_user_id.as_bytes()),
-        name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-        email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
-        country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
-        iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
-        user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-        created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-            .map(|dt| dt.to_string())
-            .unwrap_or_default(),
-        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
-            .map(|dt| dt.to_string())
-            .unwrap_or_default(),
-    };
+pub async fn get_user(State(state): State<AppState>, auth: AuthUser, Path(user_id): Path<Uuid>) -> Result<Json<User>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let user = state.store.get_user(user_id).await?;
 
     Ok(Json(user))
 }
@@ -112,84 +54,20 @@ pub async fn get_user(Path(user_id): Path<Uuid>) -> Result<Json<User>, (StatusCo
     get,
     path = "/api/v1/users/{user_id}/complete",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to fetch complete info for")
     ),
     responses(
         (status = 200, description = "Complete user info retrieved successfully", body = ExtendedUser),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn get_user_extended(Path(user_id): Path<Uuid>) -> Result<Json<ExtendedUser>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Use the stored procedure for complete user info
-    let query = "EXEC portfolio.sp_GetUserCompleteInfo @P1";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
-
-    let user = ExtendedUser {
-        user_id: Uuid::from_bytes(*tiberius_user_id.as_bytes()),
-        name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-        email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
-        country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
-        iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
-        user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-        account_balance: row.get::<tiberius::numeric::Numeric, _>("AccountBalance")
-            .map(numeric_to_f64).unwrap_or_default(),
-        
-        // Payment Method Fields
-        payment_method_type: row.get::<&str, _>("PaymentMethodType").map(|s| s.to_string()),
-        payment_method_details: row.get::<&str, _>("PaymentMethodDetails").map(|s| s.to_string()),
-        payment_method_expiry: match row.try_get::<chrono::NaiveDate, _>("PaymentMethodExpiry") {
-            Ok(Some(d)) => Some(d.format("%Y-%m-%d").to_string()),
-            _ => None,
-        },
-        payment_method_active: row.get::<bool, _>("PaymentMethodActive").unwrap_or(false),
-        
-        // Subscription Fields
-        is_premium: row.get::<bool, _>("IsPremium").unwrap_or(false),
-        premium_start_date: match row.try_get::<chrono::NaiveDateTime, _>("PremiumStartDate") {
-            Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
-            _ => None,
-        },
-        premium_end_date: match row.try_get::<chrono::NaiveDateTime, _>("PremiumEndDate") {
-            Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
-            _ => None,
-        },
-        monthly_subscription_rate: row.get::<tiberius::numeric::Numeric, _>("MonthlySubscriptionRate")
-            .map(numeric_to_f64),
-        auto_renew_subscription: row.get::<bool, _>("AutoRenewSubscription").unwrap_or(false),
-        last_subscription_payment: match row.try_get::<chrono::NaiveDateTime, _>("LastSubscriptionPayment") {
-            Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
-            _ => None,
-        },
-        next_subscription_payment: match row.try_get::<chrono::NaiveDateTime, _>("NextSubscriptionPayment") {
-            Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
-            _ => None,
-        },
-        
-        // Calculated fields
-        days_remaining_in_subscription: row.get::<i32, _>("DaysRemainingInSubscription").unwrap_or(0),
-        subscription_expired: row.get::<bool, _>("SubscriptionExpired").unwrap_or(false),
-        
-        created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
-            .unwrap_or_default(),
-        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
-            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
-            .unwrap_or_default(),
-    };
+pub async fn get_user_extended(State(state): State<AppState>, auth: AuthUser, Path(user_id): Path<Uuid>) -> Result<Json<ExtendedUser>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let user = state.store.get_user_extended(user_id).await?;
 
     Ok(Json(user))
 }
@@ -202,54 +80,26 @@ pub async fn get_user_extended(Path(user_id): Path<Uuid>) -> Result<Json<Extende
     request_body = CreateUserRequest,
     responses(
         (status = 201, description = "User created successfully", body = User),
-        (status = 400, description = "Invalid request data"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data", body = ApiErrorBody),
+        (status = 403, description = "Only Basic accounts can be self-registered", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn create_user(Json(user): Json<CreateUserRequest>) -> Result<Json<User>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    // Check if email already exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Users WHERE Email = @P1";
-    let stream = client.query(check_query, &[&user.email]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check email: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch email check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count > 0 {
-        return Err((StatusCode::BAD_REQUEST, "Email already exists".to_string()));
+pub async fn create_user(State(state): State<AppState>, Json(mut user): Json<CreateUserRequest>) -> Result<Json<User>, ApiError> {
+    // Registration is public and the user type sets the trading fees, so new
+    // accounts are Basic: Premium comes with a paid subscription, and the
+    // Admin role is only granted in the database or, with the in-memory
+    // store, through ADMIN_EMAIL at startup
+    if user.user_type != "Basic" {
+        return Err(ApiError::Forbidden("New accounts are Basic; Premium comes with a subscription".to_string()));
     }
 
-    let query = "EXEC portfolio.sp_CreateUser @P1, @P2, @P3, @P4, @P5, @P6";
-    let stream = client.query(
-        query,
-        &[
-            &user.name,
-            &user.email,
-            &user.password,
-            &user.country_of_residence,
-            &user.iban,
-            &user.user_type,
-        ],
-    ).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create user: {}", e)))?;
-
-    let result = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get created user: {}", e)))?;
-    
-    let row = result.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from create user".to_string()))?;
-    
-    let user_id = row.get::<tiberius::Uuid, _>("UserID")
-        .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "UserID was null".to_string()))?;
-
-    get_user(Path(user_id)).await.map(|user| Json(user.0))
+    user.password = hash_password(&user.password)
+        .map_err(|e| ApiError::Internal(format!("Password hashing error: {}", e)))?;
+
+    let created_user = state.store.create_user(&user).await?;
+
+    Ok(Json(created_user))
 }
 
 /// Update a user
@@ -257,80 +107,34 @@ pub async fn create_user(Json(user): Json<CreateUserRequest>) -> Result<Json<Use
     put,
     path = "/api/v1/users/{user_id}",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = UpdateUserRequest,
     params(
         ("user_id" = String, Path, description = "User ID to update")
     ),
     responses(
         (status = 200, description = "User updated successfully", body = User),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 403, description = "Only administrators may change the user type", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn update_user(Path(user_id): Path<Uuid>, Json(update): Json<UpdateUserRequest>) -> Result<Json<User>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Use stored procedure for update
-    let query = "EXEC portfolio.sp_UpdateUser @P1, @P2, @P3, @P4, @P5, @P6, @P7";
-    
-    // Prepare parameters - pass NULL for fields that shouldn't be updated
-    let name_param: Option<&str> = update.name.as_deref();
-    let email_param: Option<&str> = update.email.as_deref();
-    let password_param: Option<&str> = update.password.as_deref();
-    let country_param: Option<&str> = update.country_of_residence.as_deref();
-    let iban_param: Option<&str> = update.iban.as_deref();
-    let user_type_param: Option<&str> = update.user_type.as_deref();
-
-    let stream = client.query(
-        query,
-        &[
-            &tiberius_user_id,
-            &name_param,
-            &email_param,
-            &password_param,
-            &country_param,
-            &iban_param,
-            &user_type_param,
-        ],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Email already exists") {
-            (StatusCode::BAD_REQUEST, "Email already exists".to_string())
-        } else if error_msg.contains("No fields to update") {
-            (StatusCode::BAD_REQUEST, "No fields to update".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update user: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch updated user: {}", e)))?;
-
-    let user = rows.into_iter().next().map(|row| {
-        User {
-            user_id: row.get::<tiberius::Uuid, _>("UserID")
-                .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
-                .unwrap_or_default(),
-            name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-            email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
-            country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
-            iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
-            user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-            created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-            updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-        }
-    }).ok_or((StatusCode::NOT_FOUND, "User not found after update".to_string()))?;
+pub async fn update_user(State(state): State<AppState>, auth: AuthUser, Path(user_id): Path<Uuid>, Json(mut update): Json<UpdateUserRequest>) -> Result<Json<User>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    Ok(Json(user))
+    // Owners reach Premium through the subscription endpoints, which charge for it
+    if update.user_type.is_some() && auth.role != Role::Admin {
+        return Err(AuthError::InsufficientRole.into());
+    }
+
+    if let Some(password) = update.password.as_mut() {
+        *password = hash_password(password)
+            .map_err(|e| ApiError::Internal(format!("Password hashing error: {}", e)))?;
+    }
+
+    let updated_user = state.store.update_user(user_id, &update).await?;
+
+    Ok(Json(updated_user))
 }
 
 /// Delete a user
@@ -338,43 +142,20 @@ pub async fn update_user(Path(user_id): Path<Uuid>, Json(update): Json<UpdateUse
     delete,
     path = "/api/v1/users/{user_id}",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to delete")
     ),
     responses(
         (status = 204, description = "User deleted successfully"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn delete_user(Path(user_id): Path<Uuid>) -> Result<StatusCode, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Check if user exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1";
-    let stream = client.query(check_query, &[&tiberius_user_id]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check user existence: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
-    }
+pub async fn delete_user(State(state): State<AppState>, auth: AuthUser, Path(user_id): Path<Uuid>) -> Result<StatusCode, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    let query = "DELETE FROM portfolio.Users WHERE UserID = @P1";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete user: {}", e)))?;
-    
-    stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to confirm deletion: {}", e)))?;
+    state.store.delete_user(user_id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -387,78 +168,99 @@ pub async fn delete_user(Path(user_id): Path<Uuid>) -> Result<StatusCode, (Statu
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
-        (status = 401, description = "Invalid credentials"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Invalid credentials", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn login(Json(login): Json<LoginRequest>) -> Result<Response<String>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("DB connect error: {}", e)))?;
-
-    let query = "SELECT * FROM portfolio.Users WHERE Email = @P1";
-    let stream = client.query(query, &[&login.email]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Query error: {}", e)))?;
-    
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Row error: {}", e)))?;
-
-    let user = rows.into_iter().next().map(|row| {
-        let user_id = row.get::<tiberius::Uuid, _>("UserID")
-            .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
-            .unwrap_or_default();
-            
-        (
-            row.get::<&str, _>("Password").unwrap_or("").to_string(),
-            User {
-                user_id,
-                name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-                email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
-                country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
-                iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
-                user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-                created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-                    .map(|dt| dt.to_string())
-                    .unwrap_or_default(),
-                updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
-                    .map(|dt| dt.to_string())
-                    .unwrap_or_default(),
+pub async fn login(State(state): State<AppState>, headers: HeaderMap, Json(login): Json<LoginRequest>) -> Result<Response<String>, ApiError> {
+    let credentials = state.store.find_credentials(&login.email).await?
+        .ok_or(ApiError::Unauthorized("Invalid credentials".to_string()))?;
+
+    let UserCredentials { password, user } = credentials;
+
+    match verify_password(&login.password, &password) {
+        PasswordCheck::Valid => {},
+        PasswordCheck::ValidLegacy => {
+            // Upgrade rows that still hold a plaintext password; the login itself already succeeded
+            let rehashed = hash_password(&login.password)
+                .map_err(|e| ApiError::Internal(format!("Password hashing error: {}", e)))?;
+            if let Err(e) = state.store.set_password(user.user_id, &rehashed).await {
+                eprintln!("Failed to rehash password for user {}: {}", user.user_id, e);
             }
-        )
-    }).ok_or((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))?;
-
-    let (password, user) = user;
-
-    // Verify password (now using plain text comparison)
-    if password != login.password {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        },
+        PasswordCheck::Invalid => {
+            return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+        },
     }
 
+    // Open a server-side session backing the refresh token
+    let session_id = Uuid::new_v4();
+    let refresh = RefreshToken::generate();
+    state.store.create_session(&NewSession {
+        session_id,
+        user_id: user.user_id,
+        refresh_token_hash: refresh.hash,
+        ttl_secs: state.config.auth.refresh_token_ttl_secs as i32,
+        ip_address: client_ip(&headers),
+        user_agent: headers.get(USER_AGENT).and_then(|v| v.to_str().ok()).map(str::to_string),
+    }).await?;
+
     // Create JWT token
-    let expiration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as usize + 24 * 3600; // 24 hours from now
-
-    let claims = Claims {
-        sub: user.user_id.to_string(),
-        exp: expiration,
-    };
+    let token = state.jwt.issue(user.user_id, session_id, Role::from_user_type(&user.user_type))
+        .map_err(|e| ApiError::Internal(format!("Token creation error: {}", e)))?;
+
+    session_response(&state, &token, &refresh.token, &LoginResponse {
+        token: token.clone(),
+        refresh_token: refresh.token.clone(),
+        expires_in: state.config.auth.access_token_ttl_secs,
+        user,
+    })
+}
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret("your-secret-key".as_bytes()) // Use environment variable in production
-    ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Token creation error: {}", e)))?;
+/// Rotate a refresh token
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/token/refresh",
+    tag = "users",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "New token pair issued; the presented refresh token is no longer valid", body = TokenResponse),
+        (status = 401, description = "Refresh token invalid, expired, revoked or already used", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Option<Json<RefreshTokenRequest>>
+) -> Result<Response<String>, ApiError> {
+    let presented = request.and_then(|Json(r)| r.refresh_token)
+        .or_else(|| cookie(&headers, REFRESH_COOKIE).map(str::to_string))
+        .ok_or(AuthError::InvalidRefreshToken)?;
+
+    let refresh = RefreshToken::generate();
+    let outcome = state.store.rotate_session(
+        &hash_refresh_token(&presented),
+        &refresh.hash,
+        state.config.auth.refresh_token_ttl_secs as i32,
+    ).await?;
+
+    let (session_id, user_id) = match outcome {
+        RefreshOutcome::Rotated { session_id, user_id } => (session_id, user_id),
+        RefreshOutcome::Reused => return Err(AuthError::RefreshTokenReused.into()),
+        RefreshOutcome::Invalid => return Err(AuthError::InvalidRefreshToken.into()),
+    };
 
-    // Create response with token in header and cookie
-    let response = Builder::new()
-        .status(StatusCode::OK)
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header(SET_COOKIE, format!("token={}; HttpOnly; Path=/; Max-Age=86400", token))
-        .body(serde_json::to_string(&LoginResponse { token: token.clone(), user }).unwrap())
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Response creation error: {}", e)))?;
+    // Read the role again so promotions and demotions apply from the next refresh
+    let user = state.store.get_user(user_id).await?;
+    let token = state.jwt.issue(user_id, session_id, Role::from_user_type(&user.user_type))
+        .map_err(|e| ApiError::Internal(format!("Token creation error: {}", e)))?;
 
-    Ok(response)
+    session_response(&state, &token, &refresh.token, &TokenResponse {
+        token: token.clone(),
+        refresh_token: refresh.token.clone(),
+        expires_in: state.config.auth.access_token_ttl_secs,
+    })
 }
 
 /// User logout
@@ -466,27 +268,75 @@ pub async fn login(Json(login): Json<LoginRequest>) -> Result<Response<String>,
     post,
     path = "/api/v1/users/logout",
     tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Logout successful; the session's tokens are revoked"),
+        (status = 401, description = "Not authenticated", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn logout(State(state): State<AppState>, auth: AuthUser) -> Result<Response<String>, ApiError> {
+    state.store.revoke_session(auth.session_id).await?;
+
+    cleared_session_response("Logged out successfully".to_string())
+}
+
+/// Log out of every device
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/logout-all",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    params(
+        ("user_id" = String, Path, description = "User whose sessions are revoked")
+    ),
     responses(
-        (status = 200, description = "Logout successful"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "All sessions revoked", body = LogoutAllResponse),
+        (status = 401, description = "Not authenticated", body = ApiErrorBody),
+        (status = 403, description = "Not the caller's account", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
-pub async fn logout() -> Result<Response<String>, (StatusCode, String)> {
-    // Clear the token cookie
-    let response = Builder::new()
+pub async fn logout_all(State(state): State<AppState>, auth: AuthUser, Path(user_id): Path<Uuid>) -> Result<Response<String>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let sessions_revoked = state.store.revoke_user_sessions(user_id).await?;
+
+    cleared_session_response(serde_json::to_string(&LogoutAllResponse { sessions_revoked }).unwrap())
+}
+
+// Builds a 200 response that hands a session's tokens to the client
+fn session_response<T: serde::Serialize>(state: &AppState, token: &str, refresh_token: &str, body: &T) -> Result<Response<String>, ApiError> {
+    let [token_cookie, refresh_cookie] = session_cookies(&state.config.auth, token, refresh_token);
+
+    Builder::new()
         .status(StatusCode::OK)
-        .header(SET_COOKIE, "token=; HttpOnly; Path=/; Max-Age=0")
-        .body("Logged out successfully".to_string())
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Response creation error: {}", e)))?;
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header(SET_COOKIE, token_cookie)
+        .header(SET_COOKIE, refresh_cookie)
+        .body(serde_json::to_string(body).unwrap())
+        .map_err(|e| ApiError::Internal(format!("Response creation error: {}", e)))
+}
+
+// Builds a 200 response that removes the session cookies
+fn cleared_session_response(body: String) -> Result<Response<String>, ApiError> {
+    let [token_cookie, refresh_cookie] = cleared_cookies();
 
-    Ok(response)
+    Builder::new()
+        .status(StatusCode::OK)
+        .header(SET_COOKIE, token_cookie)
+        .header(SET_COOKIE, refresh_cookie)
+        .body(body)
+        .map_err(|e| ApiError::Internal(format!("Response creation error: {}", e)))
 }
 
-// JWT claims structure
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String, // user_id
-    exp: usize,  // expiration time
+// First address in X-Forwarded-For, as set by the reverse proxy
+fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers.get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
 }
 
 // =============================================================
@@ -498,53 +348,36 @@ struct Claims {
     post,
     path = "/api/v1/users/{user_id}/deposit",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = DepositRequest,
     params(
-        ("user_id" = String, Path, description = "User ID to deposit funds to")
+        ("user_id" = String, Path, description = "User ID to deposit funds to"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
     ),
     responses(
         (status = 200, description = "Funds deposited successfully", body = FundOperationResponse),
-        (status = 400, description = "Invalid request data"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn deposit_funds(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>, 
     Json(request): Json<DepositRequest>
-) -> Result<Json<FundOperationResponse>, (StatusCode, String)> {
-    if request.amount <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Amount must be positive".to_string()));
-    }
+) -> Result<Json<FundOperationResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
+    if request.amount <= Decimal::ZERO {
+        return Err(ApiError::Validation("Amount must be positive".to_string()));
+    }
+    check_scale(request.amount, MONEY_SCALE, "Amount").map_err(ApiError::Validation)?;
 
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
     let description = request.description.unwrap_or_else(|| "Deposit via API".to_string());
-
-    let query = "EXEC portfolio.sp_DepositFunds @P1, @P2, @P3";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.amount, &description],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to deposit funds: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch deposit result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from deposit".to_string()))?;
-
-    let new_balance: f64 = row.get::<tiberius::numeric::Numeric, _>("NewBalance")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
+    let new_balance = state.store.deposit_funds(user_id, request.amount, &description).await?;
 
     Ok(Json(FundOperationResponse {
         status: "Success".to_string(),
@@ -559,55 +392,36 @@ pub async fn deposit_funds(
     post,
     path = "/api/v1/users/{user_id}/withdraw",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = WithdrawRequest,
     params(
-        ("user_id" = String, Path, description = "User ID to withdraw funds from")
+        ("user_id" = String, Path, description = "User ID to withdraw funds from"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
     ),
     responses(
         (status = 200, description = "Funds withdrawn successfully", body = FundOperationResponse),
-        (status = 400, description = "Invalid request data or insufficient balance"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data or insufficient balance", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn withdraw_funds(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>, 
     Json(request): Json<WithdrawRequest>
-) -> Result<Json<FundOperationResponse>, (StatusCode, String)> {
-    if request.amount <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Amount must be positive".to_string()));
-    }
+) -> Result<Json<FundOperationResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
+    if request.amount <= Decimal::ZERO {
+        return Err(ApiError::Validation("Amount must be positive".to_string()));
+    }
+    check_scale(request.amount, MONEY_SCALE, "Amount").map_err(ApiError::Validation)?;
 
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
     let description = request.description.unwrap_or_else(|| "Withdrawal via API".to_string());
-
-    let query = "EXEC portfolio.sp_WithdrawFunds @P1, @P2, @P3";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.amount, &description],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Insufficient balance") {
-            (StatusCode::BAD_REQUEST, "Insufficient account balance".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to withdraw funds: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch withdrawal result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from withdrawal".to_string()))?;
-
-    let new_balance: f64 = row.get::<tiberius::numeric::Numeric, _>("NewBalance")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
+    let new_balance = state.store.withdraw_funds(user_id, request.amount, &description).await?;
 
     Ok(Json(FundOperationResponse {
         status: "Success".to_string(),
@@ -622,65 +436,41 @@ pub async fn withdraw_funds(
     post,
     path = "/api/v1/users/{user_id}/allocate",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = AllocateRequest,
     params(
-        ("user_id" = String, Path, description = "User ID to allocate funds from")
+        ("user_id" = String, Path, description = "User ID to allocate funds from"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
     ),
     responses(
         (status = 200, description = "Funds allocated successfully", body = FundOperationResponse),
-        (status = 400, description = "Invalid request data or insufficient balance"),
-        (status = 404, description = "User or portfolio not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data or insufficient balance", body = ApiErrorBody),
+        (status = 404, description = "User or portfolio not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn allocate_funds(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>, 
     Json(request): Json<AllocateRequest>
-) -> Result<Json<FundOperationResponse>, (StatusCode, String)> {
-    if request.amount <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Amount must be positive".to_string()));
+) -> Result<Json<FundOperationResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    if request.amount <= Decimal::ZERO {
+        return Err(ApiError::Validation("Amount must be positive".to_string()));
     }
+    check_scale(request.amount, MONEY_SCALE, "Amount").map_err(ApiError::Validation)?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    let query = "EXEC portfolio.sp_AllocateFunds @P1, @P2, @P3";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.portfolio_id, &request.amount],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Portfolio not found") {
-            (StatusCode::NOT_FOUND, "Portfolio not found".to_string())
-        } else if error_msg.contains("Insufficient balance") {
-            (StatusCode::BAD_REQUEST, "Insufficient account balance".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to allocate funds: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch allocation result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from allocation".to_string()))?;
-
-    let new_balance: f64 = row.get::<tiberius::numeric::Numeric, _>("NewUserBalance")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
-    let new_portfolio_funds: f64 = row.get::<tiberius::numeric::Numeric, _>("NewPortfolioFunds")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
+    let balances = state.store.allocate_funds(user_id, request.portfolio_id, request.amount).await?;
 
     Ok(Json(FundOperationResponse {
         status: "Success".to_string(),
         amount: request.amount,
-        new_balance,
-        new_portfolio_funds: Some(new_portfolio_funds),
+        new_balance: balances.new_user_balance,
+        new_portfolio_funds: Some(balances.new_portfolio_funds),
     }))
 }
 
@@ -689,65 +479,41 @@ pub async fn allocate_funds(
     post,
     path = "/api/v1/users/{user_id}/deallocate",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = DeallocateRequest,
     params(
-        ("user_id" = String, Path, description = "User ID to deallocate funds to")
+        ("user_id" = String, Path, description = "User ID to deallocate funds to"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Optional key making retries safe; a replay returns the first response")
     ),
     responses(
         (status = 200, description = "Funds deallocated successfully", body = FundOperationResponse),
-        (status = 400, description = "Invalid request data or insufficient portfolio funds"),
-        (status = 404, description = "User or portfolio not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data or insufficient portfolio funds", body = ApiErrorBody),
+        (status = 404, description = "User or portfolio not found", body = ApiErrorBody),
+        (status = 409, description = "A request with the same Idempotency-Key is still running", body = ApiErrorBody),
+        (status = 422, description = "Idempotency-Key already used with a different request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn deallocate_funds(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>, 
     Json(request): Json<DeallocateRequest>
-) -> Result<Json<FundOperationResponse>, (StatusCode, String)> {
-    if request.amount <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Amount must be positive".to_string()));
+) -> Result<Json<FundOperationResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    if request.amount <= Decimal::ZERO {
+        return Err(ApiError::Validation("Amount must be positive".to_string()));
     }
+    check_scale(request.amount, MONEY_SCALE, "Amount").map_err(ApiError::Validation)?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    let query = "EXEC portfolio.sp_DeallocateFunds @P1, @P2, @P3";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.portfolio_id, &request.amount],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Portfolio not found") {
-            (StatusCode::NOT_FOUND, "Portfolio not found".to_string())
-        } else if error_msg.contains("Insufficient funds") {
-            (StatusCode::BAD_REQUEST, "Insufficient portfolio funds".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to deallocate funds: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch deallocation result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from deallocation".to_string()))?;
-
-    let new_balance: f64 = row.get::<tiberius::numeric::Numeric, _>("NewUserBalance")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
-    let new_portfolio_funds: f64 = row.get::<tiberius::numeric::Numeric, _>("NewPortfolioFunds")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
+    let balances = state.store.deallocate_funds(user_id, request.portfolio_id, request.amount).await?;
 
     Ok(Json(FundOperationResponse {
         status: "Success".to_string(),
         amount: request.amount,
-        new_balance,
-        new_portfolio_funds: Some(new_portfolio_funds),
+        new_balance: balances.new_user_balance,
+        new_portfolio_funds: Some(balances.new_portfolio_funds),
     }))
 }
 
@@ -756,64 +522,42 @@ pub async fn deallocate_funds(
     post,
     path = "/api/v1/users/{user_id}/upgrade-premium",
     tag = "users",
+    security(("bearer_auth" = [])),
     request_body = UpgradePremiumRequest,
     params(
         ("user_id" = String, Path, description = "User ID to upgrade")
     ),
     responses(
         (status = 200, description = "User upgraded to premium successfully", body = PremiumUpgradeResponse),
-        (status = 400, description = "Invalid request data or insufficient balance"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request data or insufficient balance", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn upgrade_to_premium(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>, 
     Json(request): Json<UpgradePremiumRequest>
-) -> Result<Json<PremiumUpgradeResponse>, (StatusCode, String)> {
+) -> Result<Json<PremiumUpgradeResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
+    ensure_not_admin(auth)?;
+
     let subscription_months = request.subscription_months.unwrap_or(1);
-    let monthly_rate = request.monthly_rate.unwrap_or(50.0);
+    let monthly_rate = request.monthly_rate.unwrap_or(Decimal::from(50));
 
     if subscription_months <= 0 {
-        return Err((StatusCode::BAD_REQUEST, "Subscription months must be positive".to_string()));
+        return Err(ApiError::Validation("Subscription months must be positive".to_string()));
     }
 
-    if monthly_rate <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Monthly rate must be positive".to_string()));
+    if monthly_rate <= Decimal::ZERO {
+        return Err(ApiError::Validation("Monthly rate must be positive".to_string()));
     }
+    check_scale(monthly_rate, MONEY_SCALE, "Monthly rate").map_err(ApiError::Validation)?;
+
+    let new_balance = state.store.upgrade_to_premium(user_id, subscription_months, monthly_rate).await?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    let query = "EXEC portfolio.sp_UpgradeToPremium @P1, @P2, @P3";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &subscription_months, &monthly_rate],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Insufficient balance") {
-            (StatusCode::BAD_REQUEST, "Insufficient account balance for premium upgrade".to_string())
-        } else if error_msg.contains("already Premium") {
-            (StatusCode::BAD_REQUEST, "User is already Premium".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to upgrade to premium: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch upgrade result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned from upgrade".to_string()))?;
-
-    let new_balance: f64 = row.get::<tiberius::numeric::Numeric, _>("NewBalance")
-        .map(numeric_to_f64)
-        .unwrap_or_default();
-    let amount_paid = subscription_months as f64 * monthly_rate;
+    let amount_paid = Decimal::from(subscription_months) * monthly_rate;
 
     Ok(Json(PremiumUpgradeResponse {
         status: "Success".to_string(),
@@ -828,54 +572,24 @@ pub async fn upgrade_to_premium(
     get,
     path = "/api/v1/users/{user_id}/account-summary",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to get account summary for")
     ),
     responses(
         (status = 200, description = "Account summary retrieved successfully", body = AccountSummary),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_account_summary(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<AccountSummary>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    let query = "EXEC portfolio.sp_GetUserAccountSummary @P1";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get account summary: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch account summary: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
-
-    let summary = AccountSummary {
-        user_id,
-        name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
-        user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
-        account_balance: row.get::<tiberius::numeric::Numeric, _>("AccountBalance")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        total_portfolio_value: row.get::<tiberius::numeric::Numeric, _>("TotalPortfolioValue")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        total_net_worth: row.get::<tiberius::numeric::Numeric, _>("TotalNetWorth")
-            .map(numeric_to_f64)
-            .unwrap_or_default(),
-        portfolio_count: row.get("PortfolioCount").unwrap_or(0),
-    };
+) -> Result<Json<AccountSummary>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let summary = state.store.account_summary(user_id).await?;
 
     Ok(Json(summary))
 }
@@ -885,51 +599,28 @@ pub async fn get_account_summary(
     put,
     path = "/api/v1/users/{user_id}/payment-method",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to set payment method for")
     ),
     request_body = SetPaymentMethodRequest,
     responses(
         (status = 200, description = "Payment method updated successfully", body = PaymentMethodResponse),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn set_payment_method(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
     Json(request): Json<SetPaymentMethodRequest>
-) -> Result<Json<PaymentMethodResponse>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-    
-    // Parse expiry date if provided
-    let expiry_date: Option<&str> = request.payment_method_expiry.as_deref();
-
-    let query = "EXEC portfolio.sp_SetUserPaymentMethod @P1, @P2, @P3, @P4";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.payment_method_type, &request.payment_method_details, &expiry_date],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to set payment method: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned".to_string()))?;
-
-    let status: String = row.get::<&str, _>("Status").unwrap_or("Unknown").to_string();
-    let message: String = row.get::<&str, _>("Message").unwrap_or("Payment method updated").to_string();
-
-    Ok(Json(PaymentMethodResponse { status, message }))
+) -> Result<Json<PaymentMethodResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
+
+    let response = state.store.set_payment_method(user_id, &request).await?;
+
+    Ok(Json(response))
 }
 
 /// Manage user subscription (activate, renew, cancel)
@@ -937,91 +628,47 @@ pub async fn set_payment_method(
     post,
     path = "/api/v1/users/{user_id}/subscription",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("user_id" = String, Path, description = "User ID to manage subscription for")
     ),
     request_body = ManageSubscriptionRequest,
     responses(
         (status = 200, description = "Subscription managed successfully", body = SubscriptionResponse),
-        (status = 400, description = "Invalid request or insufficient balance"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Invalid request or insufficient balance", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn manage_subscription(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
     Json(request): Json<ManageSubscriptionRequest>
-) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+) -> Result<Json<SubscriptionResponse>, ApiError> {
+    auth.ensure_user(user_id)?;
+    ensure_not_admin(auth)?;
+
     // Validate action
     if !["ACTIVATE", "RENEW", "CANCEL"].contains(&request.action.as_str()) {
-        return Err((StatusCode::BAD_REQUEST, "Invalid action. Use ACTIVATE, RENEW, or CANCEL".to_string()));
+        return Err(ApiError::Validation("Invalid action. Use ACTIVATE, RENEW, or CANCEL".to_string()));
     }
 
     let months_to_add = request.months_to_add.unwrap_or(1);
-    let monthly_rate = request.monthly_rate.unwrap_or(50.0);
+    let monthly_rate = request.monthly_rate.unwrap_or(Decimal::from(50));
 
     if months_to_add <= 0 {
-        return Err((StatusCode::BAD_REQUEST, "Months to add must be positive".to_string()));
+        return Err(ApiError::Validation("Months to add must be positive".to_string()));
     }
 
-    if monthly_rate <= 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "Monthly rate must be positive".to_string()));
+    if monthly_rate <= Decimal::ZERO {
+        return Err(ApiError::Validation("Monthly rate must be positive".to_string()));
     }
+    check_scale(monthly_rate, MONEY_SCALE, "Monthly rate").map_err(ApiError::Validation)?;
 
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    let query = "EXEC portfolio.sp_ManageSubscription @P1, @P2, @P3, @P4";
-    let stream = client.query(
-        query,
-        &[&tiberius_user_id, &request.action, &months_to_add, &monthly_rate],
-    ).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else if error_msg.contains("Insufficient") {
-            (StatusCode::BAD_REQUEST, "Insufficient account balance for subscription".to_string())
-        } else if error_msg.contains("Invalid action") {
-            (StatusCode::BAD_REQUEST, "Invalid action specified".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to manage subscription: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch result: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No result returned".to_string()))?;
-
-    let status: String = row.get::<&str, _>("Status").unwrap_or("Unknown").to_string();
-    
-    if request.action == "CANCEL" {
-        let message: String = row.get::<&str, _>("Message").unwrap_or("Subscription cancelled").to_string();
-        Ok(Json(SubscriptionResponse {
-            status,
-            amount_paid: None,
-            months_added: None,
-            new_balance: None,
-            message: Some(message),
-        }))
-    } else {
-        let amount_paid: Option<f64> = row.get::<tiberius::numeric::Numeric, _>("AmountPaid")
-            .map(numeric_to_f64);
-        let months_added: Option<i32> = row.get("MonthsAdded");
-        let new_balance: Option<f64> = row.get::<tiberius::numeric::Numeric, _>("NewBalance")
-            .map(numeric_to_f64);
-
-        Ok(Json(SubscriptionResponse {
-            status,
-            amount_paid,
-            months_added,
-            new_balance,
-            message: Some("Subscription updated successfully".to_string()),
-        }))
-    }
+    let response = state.store.manage_subscription(user_id, &request.action, months_to_add, monthly_rate).await?;
+
+    Ok(Json(response))
 }
 
 /// Get user fund transaction history using enhanced view
@@ -1029,66 +676,24 @@ pub async fn manage_subscription(
     get,
     path = "/api/v1/users/{userId}/fund-transactions",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch fund transaction history for")
     ),
     responses(
         (status = 200, description = "Fund transaction history retrieved successfully", body = Vec<FundTransaction>),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_fund_transaction_history(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<Vec<FundTransaction>>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Check if user exists
-    let check_query = "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1";
-    let stream = client.query(check_query, &[&tiberius_user_id]).await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check user existence: {}", e)))?;
-    
-    let result = stream.into_first_result().await.map_err(|e|
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user check results: {}", e)))?;
-    
-    let count: i32 = result.first()
-        .and_then(|row| row.get("count"))
-        .unwrap_or(0);
-
-    if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
-    }
+) -> Result<Json<Vec<FundTransaction>>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    // Use the enhanced fund transaction history view
-    let query = "SELECT * FROM portfolio.vw_FundTransactionHistory WHERE UserID = @P1 ORDER BY CreatedAt DESC";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute query: {}", e)))?;
-    
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch results: {}", e)))?;
-
-    let transactions = rows.into_iter().map(|row| {
-        FundTransaction {
-            fund_transaction_id: row.get("FundTransactionID").unwrap_or_default(),
-            user_id,
-            portfolio_id: row.get("PortfolioID"),
-            transaction_type: row.get::<&str, _>("TransactionType").unwrap_or("").to_string(),
-            amount: row.get::<tiberius::numeric::Numeric, _>("Amount")
-                .map(numeric_to_f64)
-                .unwrap_or_default(),
-            balance_after: row.get::<tiberius::numeric::Numeric, _>("BalanceAfter")
-                .map(numeric_to_f64)
-                .unwrap_or_default(),
-            description: row.get::<&str, _>("Description").map(|s| s.to_string()),
-            related_asset_transaction_id: row.get("RelatedAssetTransactionID"),
-            created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
-                .map(|dt| dt.to_string())
-                .unwrap_or_default(),
-        }
-    }).collect();
+    let transactions = state.store.fund_transactions(user_id).await?;
 
     Ok(Json(transactions))
 }
@@ -1098,143 +703,32 @@ pub async fn get_fund_transaction_history(
     get,
     path = "/api/v1/users/{userId}/account-summary-enhanced",
     tag = "users",
+    security(("bearer_auth" = [])),
     params(
         ("userId" = String, Path, description = "User ID to fetch enhanced account summary for")
     ),
     responses(
         (status = 200, description = "Enhanced account summary retrieved successfully", body = serde_json::Value),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 404, description = "User not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
     )
 )]
 pub async fn get_enhanced_account_summary(
+    State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let mut client = db::get_db_client().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to database: {}", e)))?;
-
-    let tiberius_user_id = tiberius::Uuid::from_bytes(*user_id.as_bytes());
-
-    // Use the enhanced user account summary view
-    let query = "SELECT * FROM portfolio.vw_UserAccountSummary WHERE UserID = @P1";
-    let stream = client.query(query, &[&tiberius_user_id]).await.map_err(|e| {
-        let error_msg = format!("{}", e);
-        if error_msg.contains("User not found") {
-            (StatusCode::NOT_FOUND, "User not found".to_string())
-        } else {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get enhanced account summary: {}", e))
-        }
-    })?;
-
-    let rows = stream.into_first_result().await.map_err(|e| 
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch enhanced account summary: {}", e)))?;
-
-    let row = rows.into_iter().next()
-        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
-
-    // Build comprehensive account summary
-    let mut summary = serde_json::Map::new();
-
-    // Basic user info
-    summary.insert("user_id".to_string(), serde_json::Value::String(user_id.to_string()));
-    summary.insert("name".to_string(), serde_json::Value::String(row.get::<&str, _>("Name").unwrap_or("").to_string()));
-    summary.insert("email".to_string(), serde_json::Value::String(row.get::<&str, _>("Email").unwrap_or("").to_string()));
-    summary.insert("country_of_residence".to_string(), serde_json::Value::String(row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string()));
-    summary.insert("user_type".to_string(), serde_json::Value::String(row.get::<&str, _>("UserType").unwrap_or("").to_string()));
-
-    // Financial information
-    summary.insert("account_balance".to_string(), serde_json::Value::Number(
-        serde_json::Number::from_f64(row.get::<tiberius::numeric::Numeric, _>("AccountBalance").map(numeric_to_f64).unwrap_or_default()).unwrap()
-    ));
-    summary.insert("total_funds_in_portfolios".to_string(), serde_json::Value::Number(
-        serde_json::Number::from_f64(row.get::<tiberius::numeric::Numeric, _>("TotalFundsInPortfolios").map(numeric_to_f64).unwrap_or_default()).unwrap()
-    ));
-    summary.insert("total_market_value".to_string(), serde_json::Value::Number(
-        serde_json::Number::from_f64(row.get::<tiberius::numeric::Numeric, _>("TotalMarketValue").map(numeric_to_f64).unwrap_or_default()).unwrap()
-    ));
-    summary.insert("total_net_worth".to_string(), serde_json::Value::Number(
-        serde_json::Number::from_f64(row.get::<tiberius::numeric::Numeric, _>("TotalNetWorth").map(numeric_to_f64).unwrap_or_default()).unwrap()
-    ));
-
-    // Payment method information
-    if let Some(payment_type) = row.get::<&str, _>("PaymentMethodType") {
-        summary.insert("payment_method_type".to_string(), serde_json::Value::String(payment_type.to_string()));
-    }
-    if let Some(payment_details) = row.get::<&str, _>("PaymentMethodDetails") {
-        summary.insert("payment_method_details".to_string(), serde_json::Value::String(payment_details.to_string()));
-    }
-    // Safely handle payment method expiry date which might be NULL
-    match row.try_get::<chrono::NaiveDate, _>("PaymentMethodExpiry") {
-        Ok(Some(payment_expiry)) => {
-            summary.insert("payment_method_expiry".to_string(), serde_json::Value::String(payment_expiry.to_string()));
-        },
-        _ => {} // Handle NULL or conversion error gracefully
-    }
-    summary.insert("payment_method_active".to_string(), serde_json::Value::Bool(
-        row.get::<bool, _>("PaymentMethodActive").unwrap_or(false)
-    ));
-
-    // Subscription information
-    summary.insert("is_premium".to_string(), serde_json::Value::Bool(
-        row.get::<bool, _>("IsPremium").unwrap_or(false)
-    ));
-    // Safely handle premium start date which might be NULL
-    match row.try_get::<chrono::NaiveDate, _>("PremiumStartDate") {
-        Ok(Some(premium_start)) => {
-            summary.insert("premium_start_date".to_string(), serde_json::Value::String(premium_start.to_string()));
-        },
-        _ => {} // Handle NULL or conversion error gracefully
-    }
-    // Safely handle premium end date which might be NULL
-    match row.try_get::<chrono::NaiveDate, _>("PremiumEndDate") {
-        Ok(Some(premium_end)) => {
-            summary.insert("premium_end_date".to_string(), serde_json::Value::String(premium_end.to_string()));
-        },
-        _ => {} // Handle NULL or conversion error gracefully
-    }
-    summary.insert("monthly_subscription_rate".to_string(), serde_json::Value::Number(
-        serde_json::Number::from_f64(row.get::<tiberius::numeric::Numeric, _>("MonthlySubscriptionRate").map(numeric_to_f64).unwrap_or_default()).unwrap()
-    ));
-    summary.insert("auto_renew_subscription".to_string(), serde_json::Value::Bool(
-        row.get::<bool, _>("AutoRenewSubscription").unwrap_or(false)
-    ));
-    
-    // Subscription status calculations
-    summary.insert("days_remaining_in_subscription".to_string(), serde_json::Value::Number(
-        serde_json::Number::from(row.get::<i32, _>("DaysRemainingInSubscription").unwrap_or(0))
-    ));
-    summary.insert("subscription_expired".to_string(), serde_json::Value::Bool(
-        row.get::<bool, _>("SubscriptionExpired").unwrap_or(false)
-    ));
-
-    // Portfolio statistics
-    summary.insert("total_portfolios".to_string(), serde_json::Value::Number(
-        serde_json::Number::from(row.get::<i32, _>("TotalPortfolios").unwrap_or(0))
-    ));
-
-    // Recent activity
-    // Safely handle last fund transaction date which might be NULL
-    match row.try_get::<chrono::NaiveDateTime, _>("LastFundTransactionDate") {
-        Ok(Some(last_fund_transaction)) => {
-            summary.insert("last_fund_transaction_date".to_string(), serde_json::Value::String(last_fund_transaction.to_string()));
-        },
-        _ => {} // Handle NULL or conversion error gracefully
-    }
-    // Safely handle last trade date which might be NULL
-    match row.try_get::<chrono::NaiveDateTime, _>("LastTradeDate") {
-        Ok(Some(last_trade)) => {
-            summary.insert("last_trade_date".to_string(), serde_json::Value::String(last_trade.to_string()));
-        },
-        _ => {} // Handle NULL or conversion error gracefully
-    }
+) -> Result<Json<serde_json::Value>, ApiError> {
+    auth.ensure_user(user_id)?;
 
-    // Account dates
-    summary.insert("created_at".to_string(), serde_json::Value::String(
-        row.get::<chrono::NaiveDateTime, _>("CreatedAt").map(|dt| dt.to_string()).unwrap_or_default()
-    ));
-    summary.insert("updated_at".to_string(), serde_json::Value::String(
-        row.get::<chrono::NaiveDateTime, _>("UpdatedAt").map(|dt| dt.to_string()).unwrap_or_default()
-    ));
+    let summary = state.store.enhanced_account_summary(user_id).await?;
 
-    Ok(Json(serde_json::Value::Object(summary)))
-} 
\ No newline at end of file
+    Ok(Json(summary))
+}
+
+// Subscriptions rewrite UserType to Premium or Basic, which would drop the Admin role
+fn ensure_not_admin(auth: AuthUser) -> Result<(), ApiError> {
+    if auth.role == Role::Admin {
+        return Err(ApiError::Validation("Administrator accounts cannot hold a subscription".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use axum::body::{to_bytes, Body};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{header, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::state::AppState;
+use crate::store::IdempotencyClaim;
+
+/// Header a client sets to make a retried request safe
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Set on responses that were replayed instead of executed
+pub const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Fits the `IdempotencyKeys.IdempotencyKey` column
+const MAX_KEY_LENGTH: usize = 255;
+
+/// How long a completed key is remembered
+const KEY_TTL_SECS: i32 = 24 * 60 * 60;
+
+/// How long a claim holds without a response. The request renews it while
+/// its handler runs; one dropped between claiming and completing (client
+/// gone, server restarted) stops renewing, and after this long a retry may
+/// take the key over.
+const CLAIM_LEASE_SECS: i32 = 60;
+
+/// How often a running request renews its claim, well inside the lease
+const CLAIM_RENEW_INTERVAL: Duration = Duration::from_secs(20);
+
+/// The guarded endpoints take small JSON bodies
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Route middleware for trades and fund movements.
+///
+/// Requests without an `Idempotency-Key` pass straight through. With one, the
+/// first request runs and its response is stored; later requests with the
+/// same key and body get that response back, and a different body is refused.
+/// Server errors are not stored, so the client can retry them with the same key.
+pub async fn idempotent(State(state): State<AppState>, request: Request, next: Next) -> Result<Response, ApiError> {
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(request).await);
+    };
+    let key = key.to_str()
+        .ok()
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| ApiError::Validation(format!(
+            "Idempotency-Key must be 1 to {} visible ASCII characters", MAX_KEY_LENGTH
+        )))?
+        .to_string();
+
+    // Keys belong to the caller, so two users can pick the same one
+    let (mut parts, body) = request.into_parts();
+    let auth = AuthUser::from_request_parts(&mut parts, &state).await?;
+    let body = to_bytes(body, MAX_BODY_BYTES).await
+        .map_err(|_| ApiError::Validation("Request body is too large".to_string()))?;
+    let request_hash = request_hash(&parts.method, parts.uri.path(), &body);
+
+    match state.store.claim_idempotency_key(auth.user_id, &key, &request_hash, CLAIM_LEASE_SECS).await? {
+        IdempotencyClaim::New => {},
+        IdempotencyClaim::Replay { status, body } => return Ok(replay(status, body)),
+        IdempotencyClaim::Mismatch => {
+            return Err(ApiError::IdempotencyKeyReused(
+                "Idempotency-Key was already used with a different request".to_string(),
+            ));
+        },
+        IdempotencyClaim::InProgress => {
+            return Err(ApiError::Conflict(
+                "A request with this Idempotency-Key is still being processed".to_string(),
+            ));
+        },
+    }
+
+    // A slow handler must not let its claim lapse, or a retry would run the trade again
+    let handler = next.run(Request::from_parts(parts, Body::from(body)));
+    tokio::pin!(handler);
+    let mut renewal = tokio::time::interval_at(tokio::time::Instant::now() + CLAIM_RENEW_INTERVAL, CLAIM_RENEW_INTERVAL);
+    let response = loop {
+        tokio::select! {
+            response = &mut handler => break response,
+            _ = renewal.tick() => {
+                if let Err(e) = state.store.renew_idempotency_key(auth.user_id, &key, CLAIM_LEASE_SECS).await {
+                    eprintln!("Failed to renew Idempotency-Key {}: {}", key, e);
+                }
+            },
+        }
+    };
+
+    if response.status().is_server_error() {
+        if let Err(e) = state.store.release_idempotency_key(auth.user_id, &key).await {
+            eprintln!("Failed to release Idempotency-Key {}: {}", key, e);
+        }
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await
+        .map_err(|e| ApiError::Internal(format!("Failed to buffer response: {}", e)))?;
+
+    // The operation already happened; failing to remember it must not hide that
+    let stored = String::from_utf8_lossy(&body);
+    if let Err(e) = state.store.complete_idempotency_key(auth.user_id, &key, parts.status.as_u16(), &stored, KEY_TTL_SECS).await {
+        eprintln!("Failed to store response for Idempotency-Key {}: {}", key, e);
+    }
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+fn request_hash(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b" ");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn replay(status: u16, body: String) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+        (IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true")),
+    ];
+    (status, headers, body).into_response()
+}
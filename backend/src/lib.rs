@@ -0,0 +1,21 @@
+//! meuPortfolio backend. `main.rs` loads the configuration and serves
+//! [`app::router`]; the API tests in `tests/` drive the same router over
+//! the in-memory store.
+
+pub mod app;
+pub mod auth;
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod extract;
+pub mod handlers;
+pub mod idempotency;
+pub mod models;
+pub mod orders;
+pub mod performance;
+pub mod pretrade;
+pub mod rebalancer;
+pub mod risk;
+pub mod scheduler;
+pub mod state;
+pub mod store;
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 
 // =============================================================
@@ -33,8 +34,10 @@ impl std::fmt::Display for AssetType {
     }
 }
 
-impl AssetType {
-    pub fn from_str(s: &str) -> Result<AssetType, String> {
+impl std::str::FromStr for AssetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AssetType, String> {
         match s.to_lowercase().as_str() {
             "stock" => Ok(AssetType::Stock),
             "cryptocurrency" | "crypto" => Ok(AssetType::Cryptocurrency),
@@ -43,7 +46,9 @@ impl AssetType {
             _ => Err(format!("Invalid asset type: {}", s)),
         }
     }
+}
 
+impl AssetType {
     #[allow(dead_code)]
     pub fn valid_types() -> Vec<&'static str> {
         vec!["Stock", "Cryptocurrency", "Index", "Commodity"]
@@ -60,11 +65,11 @@ pub struct AssetFactory;
 
 impl AssetFactory {
     pub fn validate_asset_type(asset_type: &str) -> Result<AssetType, String> {
-        AssetType::from_str(asset_type)
+        asset_type.parse()
     }
 
     #[allow(dead_code)]
-    pub fn validate_stock_data(sector: &str, country: &str, market_cap: Option<f64>) -> Result<(), String> {
+    pub fn validate_stock_data(sector: &str, country: &str, market_cap: Option<Decimal>) -> Result<(), String> {
         if sector.trim().is_empty() {
             return Err("Sector cannot be empty for stocks".to_string());
         }
@@ -74,7 +79,7 @@ impl AssetFactory {
         }
 
         if let Some(cap) = market_cap {
-            if cap < 0.0 {
+            if cap < Decimal::ZERO {
                 return Err("Market cap cannot be negative".to_string());
             }
         }
@@ -83,19 +88,19 @@ impl AssetFactory {
     }
 
     #[allow(dead_code)]
-    pub fn validate_crypto_data(blockchain: &str, max_supply: Option<f64>, circulating_supply: Option<f64>) -> Result<(), String> {
+    pub fn validate_crypto_data(blockchain: &str, max_supply: Option<Decimal>, circulating_supply: Option<Decimal>) -> Result<(), String> {
         if blockchain.trim().is_empty() {
             return Err("Blockchain cannot be empty for cryptocurrencies".to_string());
         }
 
         if let Some(max) = max_supply {
-            if max <= 0.0 {
+            if max <= Decimal::ZERO {
                 return Err("Max supply must be positive".to_string());
             }
         }
 
         if let Some(circulating) = circulating_supply {
-            if circulating < 0.0 {
+            if circulating < Decimal::ZERO {
                 return Err("Circulating supply cannot be negative".to_string());
             }
 
@@ -161,7 +166,7 @@ impl AssetUtils {
     }
 
     /// Validate basic asset data
-    pub fn validate_basic_asset_data(symbol: &str, name: &str, price: Option<f64>, volume: Option<i64>, shares: Option<f64>) -> Result<(), String> {
+    pub fn validate_basic_asset_data(symbol: &str, name: &str, price: Option<Decimal>, volume: Option<i64>, shares: Option<Decimal>) -> Result<(), String> {
         if symbol.trim().is_empty() {
             return Err("Symbol cannot be empty".to_string());
         }
@@ -171,7 +176,7 @@ impl AssetUtils {
         }
 
         if let Some(p) = price {
-            if p < 0.0 {
+            if p < Decimal::ZERO {
                 return Err("Price cannot be negative".to_string());
             }
         }
@@ -183,7 +188,7 @@ impl AssetUtils {
         }
 
         if let Some(s) = shares {
-            if s < 0.0 {
+            if s < Decimal::ZERO {
                 return Err("Available shares cannot be negative".to_string());
             }
         }
@@ -193,17 +198,17 @@ impl AssetUtils {
 
     /// Calculate market value
     #[allow(dead_code)]
-    pub fn calculate_market_value(price: f64, shares: f64) -> f64 {
+    pub fn calculate_market_value(price: Decimal, shares: Decimal) -> Decimal {
         price * shares
     }
 
     /// Format price for display
     #[allow(dead_code)]
-    pub fn format_price(price: f64, asset_type: &AssetType) -> String {
+    pub fn format_price(price: Decimal, asset_type: &AssetType) -> String {
         match asset_type {
             AssetType::Stock | AssetType::Index => format!("${:.2}", price),
             AssetType::Cryptocurrency => {
-                if price >= 1.0 {
+                if price >= Decimal::ONE {
                     format!("${:.2}", price)
                 } else {
                     format!("${:.6}", price)
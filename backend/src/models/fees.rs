@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::{round_to_scale, MONEY_SCALE, serialize_money, serialize_optional_money};
+
+// =============================================================
+// FEE SCHEDULES
+// =============================================================
+
+/// How a fee schedule prices a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum FeeType {
+    /// `flat_fee` per trade
+    Flat,
+    /// `rate` percent of the trade value
+    Percentage,
+    /// The `rate` of the highest tier the trade value reaches
+    Tiered,
+}
+
+impl FeeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeType::Flat => "Flat",
+            FeeType::Percentage => "Percentage",
+            FeeType::Tiered => "Tiered",
+        }
+    }
+}
+
+impl std::str::FromStr for FeeType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Flat" => Ok(FeeType::Flat),
+            "Percentage" => Ok(FeeType::Percentage),
+            "Tiered" => Ok(FeeType::Tiered),
+            _ => Err(format!("Invalid fee type: {}", s)),
+        }
+    }
+}
+
+/// Rate of a tiered schedule from a trade value upwards
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeTier {
+    #[schema(example = "1000")]
+    #[serde(serialize_with = "serialize_money")]
+    pub min_trade_value: Decimal,
+    /// Percent of the trade value
+    #[schema(example = "0.75")]
+    pub rate: Decimal,
+}
+
+/// Fees for one user type trading one asset type
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeSchedule {
+    pub fee_schedule_id: i32,
+    #[schema(example = "Basic")]
+    pub user_type: String,
+    /// Absent for the user type's default schedule
+    #[schema(example = "Cryptocurrency")]
+    pub asset_type: Option<String>,
+    pub fee_type: FeeType,
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub flat_fee: Option<Decimal>,
+    /// Percent of the trade value
+    pub rate: Option<Decimal>,
+    #[serde(serialize_with = "serialize_money")]
+    pub minimum_fee: Decimal,
+    /// Lowest tier first; empty unless `fee_type` is `Tiered`
+    pub tiers: Vec<FeeTier>,
+    pub updated_at: String,
+}
+
+impl FeeSchedule {
+    /// Fee for a trade, as `fn_CalculateTradingFee` computes it
+    pub fn fee_for(&self, trade_value: Decimal) -> Decimal {
+        let rate = match self.fee_type {
+            FeeType::Tiered => self.tiers.iter()
+                .filter(|tier| tier.min_trade_value <= trade_value)
+                .max_by_key(|tier| tier.min_trade_value)
+                .map(|tier| tier.rate),
+            _ => self.rate,
+        };
+        let fee = match self.fee_type {
+            FeeType::Flat => self.flat_fee.unwrap_or_default(),
+            _ => round_to_scale(trade_value * rate.unwrap_or_default() / Decimal::ONE_HUNDRED, MONEY_SCALE),
+        };
+        fee.max(self.minimum_fee)
+    }
+
+    /// Highest fee a trade worth at most `trade_value` can cost, which is what
+    /// a buy order reserves. Tiers lower the rate as trades grow, so a trade
+    /// just below a tier can cost more than a larger one.
+    pub fn max_fee_for(&self, trade_value: Decimal) -> Decimal {
+        let below_tiers = self.tiers.iter()
+            .map(|tier| tier.min_trade_value - Decimal::new(1, MONEY_SCALE))
+            .filter(|value| *value >= Decimal::ZERO && *value < trade_value);
+        std::iter::once(trade_value)
+            .chain(below_tiers)
+            .map(|value| self.fee_for(value))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Request to create or replace the schedule for a user type and asset type
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetFeeScheduleRequest {
+    /// Basic or Premium
+    #[schema(example = "Basic")]
+    pub user_type: String,
+    /// Stock, Index, Cryptocurrency or Commodity; omit for the user type's default
+    #[schema(example = "Stock")]
+    pub asset_type: Option<String>,
+    pub fee_type: FeeType,
+    /// Required for Flat schedules
+    pub flat_fee: Option<Decimal>,
+    /// Percent of the trade value, required for Percentage schedules
+    #[schema(example = "0.25")]
+    pub rate: Option<Decimal>,
+    #[schema(example = "1.00")]
+    pub minimum_fee: Option<Decimal>,
+    /// Required for Tiered schedules, with one tier starting at 0
+    pub tiers: Option<Vec<FeeTier>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(fee_type: FeeType, flat_fee: Option<Decimal>, rate: Option<Decimal>, minimum_fee: Decimal) -> FeeSchedule {
+        FeeSchedule {
+            fee_schedule_id: 1,
+            user_type: "Basic".to_string(),
+            asset_type: None,
+            fee_type,
+            flat_fee,
+            rate,
+            minimum_fee,
+            tiers: Vec::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn tier(min_trade_value: i64, rate: Decimal) -> FeeTier {
+        FeeTier { min_trade_value: Decimal::from(min_trade_value), rate }
+    }
+
+    #[test]
+    fn tiers_apply_from_their_minimum_value() {
+        // 1% up to 1000, 0.5% from 1000, 0.25% from 10000
+        let tiered = FeeSchedule {
+            tiers: vec![
+                tier(0, Decimal::ONE),
+                tier(1000, Decimal::new(5, 1)),
+                tier(10000, Decimal::new(25, 2)),
+            ],
+            ..schedule(FeeType::Tiered, None, None, Decimal::ZERO)
+        };
+
+        assert_eq!(tiered.fee_for(Decimal::new(99999, 2)), Decimal::new(1000, 2));
+        assert_eq!(tiered.fee_for(Decimal::from(1000)), Decimal::from(5));
+        assert_eq!(tiered.fee_for(Decimal::new(999999, 2)), Decimal::new(5000, 2));
+        assert_eq!(tiered.fee_for(Decimal::from(10000)), Decimal::from(25));
+    }
+
+    #[test]
+    fn the_minimum_fee_is_a_floor() {
+        // 0.25% is below the 1.00 minimum up to a trade of 400
+        let percentage = schedule(FeeType::Percentage, None, Some(Decimal::new(25, 2)), Decimal::ONE);
+        assert_eq!(percentage.fee_for(Decimal::from(100)), Decimal::ONE);
+        assert_eq!(percentage.fee_for(Decimal::from(400)), Decimal::ONE);
+        assert_eq!(percentage.fee_for(Decimal::from(404)), Decimal::new(101, 2));
+
+        let flat = schedule(FeeType::Flat, Some(Decimal::new(50, 2)), None, Decimal::ONE);
+        assert_eq!(flat.fee_for(Decimal::from(5000)), Decimal::ONE);
+    }
+
+    #[test]
+    fn the_worst_case_fee_looks_below_each_tier() {
+        // 1% below 1000, 0.5% from there
+        let tiered = FeeSchedule {
+            tiers: vec![tier(0, Decimal::ONE), tier(1000, Decimal::new(5, 1))],
+            ..schedule(FeeType::Tiered, None, None, Decimal::ZERO)
+        };
+
+        // A trade of 999.99 costs 10.00, more than the 5.50 on 1100
+        assert_eq!(tiered.fee_for(Decimal::from(1100)), Decimal::new(550, 2));
+        assert_eq!(tiered.max_fee_for(Decimal::from(1100)), Decimal::from(10));
+        assert_eq!(tiered.max_fee_for(Decimal::from(5000)), Decimal::from(25));
+        assert_eq!(tiered.max_fee_for(Decimal::from(500)), Decimal::from(5));
+
+        let percentage = schedule(FeeType::Percentage, None, Some(Decimal::new(25, 2)), Decimal::ONE);
+        assert_eq!(percentage.max_fee_for(Decimal::from(1000)), percentage.fee_for(Decimal::from(1000)));
+    }
+
+    #[test]
+    fn percentage_fees_round_to_cents() {
+        let percentage = schedule(FeeType::Percentage, None, Some(Decimal::new(25, 2)), Decimal::ZERO);
+        // 0.25% of 12.34 is 0.03085
+        assert_eq!(percentage.fee_for(Decimal::new(1234, 2)), Decimal::new(3, 2));
+        assert_eq!(percentage.fee_for(Decimal::ZERO), Decimal::ZERO);
+    }
+}
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::{serialize_money, serialize_optional_money};
+
 /// Request to deposit funds to user account
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DepositRequest {
     /// Amount to deposit (must be positive)
-    pub amount: f64,
+    pub amount: Decimal,
     /// Optional description for the deposit
     pub description: Option<String>,
 }
@@ -15,7 +18,7 @@ pub struct DepositRequest {
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct WithdrawRequest {
     /// Amount to withdraw (must be positive)
-    pub amount: f64,
+    pub amount: Decimal,
     /// Optional description for the withdrawal
     pub description: Option<String>,
 }
@@ -26,7 +29,7 @@ pub struct AllocateRequest {
     /// Portfolio ID to allocate funds to
     pub portfolio_id: i32,
     /// Amount to allocate (must be positive)
-    pub amount: f64,
+    pub amount: Decimal,
 }
 
 /// Request to deallocate funds from portfolio back to user account
@@ -35,7 +38,7 @@ pub struct DeallocateRequest {
     /// Portfolio ID to deallocate funds from
     pub portfolio_id: i32,
     /// Amount to deallocate (must be positive)
-    pub amount: f64,
+    pub amount: Decimal,
 }
 
 /// Request to upgrade user to premium
@@ -44,7 +47,7 @@ pub struct UpgradePremiumRequest {
     /// Number of months for subscription (default: 1)
     pub subscription_months: Option<i32>,
     /// Monthly rate (default: 50.00)
-    pub monthly_rate: Option<f64>,
+    pub monthly_rate: Option<Decimal>,
 }
 
 /// Response for fund operations (deposit, withdraw, allocate, deallocate)
@@ -53,11 +56,14 @@ pub struct FundOperationResponse {
     /// Operation status
     pub status: String,
     /// Amount processed
-    pub amount: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub amount: Decimal,
     /// New user account balance
-    pub new_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub new_balance: Decimal,
     /// New portfolio funds (for allocate/deallocate operations)
-    pub new_portfolio_funds: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub new_portfolio_funds: Option<Decimal>,
 }
 
 /// Response for premium upgrade
@@ -66,11 +72,13 @@ pub struct PremiumUpgradeResponse {
     /// Operation status
     pub status: String,
     /// Amount paid for upgrade
-    pub amount_paid: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub amount_paid: Decimal,
     /// Number of subscription months
     pub subscription_months: i32,
     /// New account balance after payment
-    pub new_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub new_balance: Decimal,
 }
 
 /// User account summary with portfolio information
@@ -83,11 +91,14 @@ pub struct AccountSummary {
     /// User type (Basic or Premium)
     pub user_type: String,
     /// Current account balance (cash)
-    pub account_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub account_balance: Decimal,
     /// Total value of all portfolios
-    pub total_portfolio_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_portfolio_value: Decimal,
     /// Total net worth (account balance + portfolio value)
-    pub total_net_worth: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_net_worth: Decimal,
     /// Number of portfolios owned
     pub portfolio_count: i32,
 }
@@ -100,13 +111,22 @@ pub struct PortfolioBalance {
     /// Portfolio name
     pub portfolio_name: String,
     /// Cash balance in portfolio
-    pub cash_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub cash_balance: Decimal,
+    /// Cash held back for pending buy orders
+    #[serde(serialize_with = "serialize_money")]
+    pub reserved_funds: Decimal,
     /// Total market value of holdings
-    pub holdings_value: f64,
-    /// Total portfolio value (cash + holdings)
-    pub total_portfolio_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub holdings_value: Decimal,
+    /// Total portfolio value (cash + reserved funds + holdings)
+    #[serde(serialize_with = "serialize_money")]
+    pub total_portfolio_value: Decimal,
     /// Number of different assets held
     pub holdings_count: Option<i32>,
+    /// Trading fees paid over the portfolio's lifetime
+    #[serde(serialize_with = "serialize_money")]
+    pub fees_paid: Decimal,
 }
 
 /// Fund transaction record
@@ -121,9 +141,11 @@ pub struct FundTransaction {
     /// Transaction type
     pub transaction_type: String,
     /// Transaction amount
-    pub amount: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub amount: Decimal,
     /// Account balance after transaction
-    pub balance_after: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub balance_after: Decimal,
     /// Transaction description
     pub description: Option<String>,
     /// Related asset transaction ID (if applicable)
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 
 // Re-export all module types
@@ -7,14 +8,30 @@ mod portfolio;
 mod risk;
 mod funds;
 mod trading;
+mod orders;
+mod tax_lots;
+mod fees;
+mod trading_rules;
+mod rebalancing;
+mod recurring;
+mod performance;
 mod assets;
+mod money;
 
 pub use user::*;
 pub use portfolio::*;
 pub use risk::*;
 pub use funds::*;
 pub use trading::*;
+pub use orders::*;
+pub use tax_lots::*;
+pub use fees::*;
+pub use trading_rules::*;
+pub use rebalancing::*;
+pub use recurring::*;
+pub use performance::*;
 pub use assets::*;
+pub use money::*;
 
 // =============================================================
 // CORE ASSET MODELS
@@ -26,9 +43,10 @@ pub struct Asset {
     pub name: String,
     pub symbol: String,
     pub asset_type: String,
-    pub price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub price: Decimal,
     pub volume: i64,
-    pub available_shares: f64,
+    pub available_shares: Decimal,
     pub last_updated: String,
 }
 
@@ -36,7 +54,8 @@ pub struct Asset {
 pub struct AssetPriceHistory {
     pub asset_id: i32,
     pub symbol: String,
-    pub price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub price: Decimal,
     pub volume: i64,
     pub timestamp: String,
 }
@@ -50,11 +69,11 @@ pub struct CreateAssetRequest {
     #[schema(example = "Stock")]
     pub asset_type: String,  // Stock, Index, Cryptocurrency, Commodity
     #[schema(example = "150.00")]
-    pub initial_price: Option<f64>,
+    pub initial_price: Option<Decimal>,
     #[schema(example = "1000000")]
     pub initial_volume: Option<i64>,
     #[schema(example = "1000000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -62,17 +81,17 @@ pub struct UpdateAssetRequest {
     #[schema(example = "Apple Inc. (Updated)")]
     pub name: Option<String>,
     #[schema(example = "155.00")]
-    pub price: Option<f64>,
+    pub price: Option<Decimal>,
     #[schema(example = "2000000")]
     pub volume: Option<i64>,
     #[schema(example = "1100000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct UpdatePriceRequest {
     #[schema(example = "150.75")]
-    pub price: f64,
+    pub price: Decimal,
     #[schema(example = "1500000")]
     pub volume: Option<i64>,
 }
@@ -98,7 +117,8 @@ pub struct StockDetails {
     #[schema(example = "United States")]
     pub country: String,
     #[schema(example = "2500000000000.00")]
-    pub market_cap: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub market_cap: Decimal,
     pub last_updated: String,
 }
 
@@ -110,18 +130,18 @@ pub struct CreateStockRequest {
     #[schema(example = "Apple Inc.")]
     pub name: String,
     #[schema(example = "150.00")]
-    pub initial_price: Option<f64>,
+    pub initial_price: Option<Decimal>,
     #[schema(example = "1000000")]
     pub initial_volume: Option<i64>,
     #[schema(example = "1000000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
     // Stock-specific fields
     #[schema(example = "Technology")]
     pub sector: String,
     #[schema(example = "United States")]
     pub country: String,
     #[schema(example = "2500000000000.00")]
-    pub market_cap: Option<f64>,
+    pub market_cap: Option<Decimal>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -132,7 +152,7 @@ pub struct UpdateStockDetailsRequest {
     #[schema(example = "United States")]
     pub country: String,
     #[schema(example = "2500000000000.00")]
-    pub market_cap: f64,
+    pub market_cap: Decimal,
 }
 
 // =============================================================
@@ -146,9 +166,9 @@ pub struct CryptoDetails {
     #[schema(example = "Bitcoin")]
     pub blockchain: String,
     #[schema(example = "21000000")]
-    pub max_supply: Option<f64>,
+    pub max_supply: Option<Decimal>,
     #[schema(example = "19500000")]
-    pub circulating_supply: f64,
+    pub circulating_supply: Decimal,
     pub last_updated: String,
 }
 
@@ -160,18 +180,18 @@ pub struct CreateCryptoRequest {
     #[schema(example = "Bitcoin")]
     pub name: String,
     #[schema(example = "45000.00")]
-    pub initial_price: Option<f64>,
+    pub initial_price: Option<Decimal>,
     #[schema(example = "100000")]
     pub initial_volume: Option<i64>,
     #[schema(example = "21000000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
     // Crypto-specific fields
     #[schema(example = "Bitcoin")]
     pub blockchain: String,
     #[schema(example = "21000000")]
-    pub max_supply: Option<f64>,
+    pub max_supply: Option<Decimal>,
     #[schema(example = "19500000")]
-    pub circulating_supply: Option<f64>,
+    pub circulating_supply: Option<Decimal>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -180,9 +200,9 @@ pub struct UpdateCryptoDetailsRequest {
     #[schema(example = "Bitcoin")]
     pub blockchain: String,
     #[schema(example = "21000000")]
-    pub max_supply: Option<f64>,
+    pub max_supply: Option<Decimal>,
     #[schema(example = "19500000")]
-    pub circulating_supply: f64,
+    pub circulating_supply: Decimal,
 }
 
 // =============================================================
@@ -212,11 +232,11 @@ pub struct CreateIndexRequest {
     #[schema(example = "SPDR S&P 500 ETF Trust")]
     pub name: String,
     #[schema(example = "420.00")]
-    pub initial_price: Option<f64>,
+    pub initial_price: Option<Decimal>,
     #[schema(example = "50000000")]
     pub initial_volume: Option<i64>,
     #[schema(example = "500000000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
     // Index-specific fields
     #[schema(example = "United States")]
     pub country: String,
@@ -264,11 +284,11 @@ pub struct CreateCommodityRequest {
     #[schema(example = "Gold")]
     pub name: String,
     #[schema(example = "1800.00")]
-    pub initial_price: Option<f64>,
+    pub initial_price: Option<Decimal>,
     #[schema(example = "10000")]
     pub initial_volume: Option<i64>,
     #[schema(example = "1000000.0")]
-    pub available_shares: Option<f64>,
+    pub available_shares: Option<Decimal>,
     // Commodity-specific fields
     #[schema(example = "Precious Metals")]
     pub category: String,
@@ -321,14 +341,14 @@ pub struct CsvImportRequest {
     #[schema(example = "United States")]
     pub country: Option<String>,
     #[schema(example = "2500000000000.00")]
-    pub market_cap: Option<f64>,
+    pub market_cap: Option<Decimal>,
     // Crypto-specific fields (optional)
     #[schema(example = "Bitcoin")]
     pub blockchain: Option<String>,
     #[schema(example = "21000000")]
-    pub max_supply: Option<f64>,
+    pub max_supply: Option<Decimal>,
     #[schema(example = "19500000")]
-    pub circulating_supply: Option<f64>,
+    pub circulating_supply: Option<Decimal>,
     // Index-specific fields (optional)
     #[schema(example = "North America")]
     pub region: Option<String>,
@@ -0,0 +1,65 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Serialize, Serializer};
+
+// =============================================================
+// DECIMAL SCALES
+// =============================================================
+
+// Scales of the DECIMAL columns in database/migrations. Amounts are exact
+// only if they fit the column they end up in, so requests are checked
+// against these instead of letting SQL Server round them silently.
+
+/// Balances, funds, totals: `DECIMAL(18,2)`
+pub const MONEY_SCALE: u32 = 2;
+/// Unit and average prices: `DECIMAL(18,4)`
+pub const PRICE_SCALE: u32 = 4;
+/// Quantities and shares: `DECIMAL(18,6)`
+pub const QUANTITY_SCALE: u32 = 6;
+
+/// Rejects values with more decimal places than `scale`
+pub fn check_scale(value: Decimal, scale: u32, field: &str) -> Result<(), String> {
+    if value.normalize().scale() > scale {
+        return Err(format!("{} cannot have more than {} decimal places", field, scale));
+    }
+    Ok(())
+}
+
+/// Rounds like SQL Server does when assigning to a `DECIMAL(p, scale)`
+pub fn round_to_scale(value: Decimal, scale: u32) -> Decimal {
+    value.round_dp_with_strategy(scale, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Rounds to `scale` and pads to it, so the value always prints with that
+/// many places, as a `DECIMAL(p, scale)` column does
+fn at_scale(value: Decimal, scale: u32) -> Decimal {
+    let mut value = round_to_scale(value, scale);
+    value.rescale(scale);
+    value
+}
+
+// =============================================================
+// RESPONSE SERIALIZATION
+// =============================================================
+
+// Amounts are worked out at whatever scale their inputs had, in either
+// store, so responses write them at their column's scale instead.
+
+/// `serialize_with` for amounts: always `MONEY_SCALE` places
+pub fn serialize_money<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    Serialize::serialize(&at_scale(*value, MONEY_SCALE), serializer)
+}
+
+/// `serialize_with` for prices: always `PRICE_SCALE` places
+pub fn serialize_price<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    Serialize::serialize(&at_scale(*value, PRICE_SCALE), serializer)
+}
+
+/// `serialize_money` for optional amounts
+pub fn serialize_optional_money<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.map(|value| at_scale(value, MONEY_SCALE)).serialize(serializer)
+}
+
+/// `serialize_price` for optional prices
+pub fn serialize_optional_price<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.map(|value| at_scale(value, PRICE_SCALE)).serialize(serializer)
+}
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::{
+    BuyAssetResponse, LotSelection, RuleViolation, SellAssetResponse, serialize_money, serialize_optional_price,
+    serialize_price,
+};
+
+// =============================================================
+// ORDER ENUMS
+// =============================================================
+
+// Each enum is stored by the name it serializes to, so `as_str` and
+// `FromStr` are the only mapping between the API and the database
+
+/// Direction of an order; stored as `Transactions.TransactionType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum OrderSide {
+    #[default]
+    Buy,
+    Sell,
+}
+
+/// When an order may execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum OrderType {
+    /// At the current price, as soon as it is placed
+    #[default]
+    Market,
+    /// At the limit price or better
+    Limit,
+    /// At the market once the price reaches the stop price
+    Stop,
+    /// As a limit order once the price reaches the stop price
+    StopLimit,
+}
+
+/// How long an order stays on the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeInForce {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Expires at the end of the day it was placed
+    Day,
+    /// Immediate or cancel: whatever cannot fill on placement is cancelled
+    Ioc,
+}
+
+/// Order lifecycle; stored as `Transactions.Status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum OrderStatus {
+    #[default]
+    Pending,
+    Executed,
+    Failed,
+    Cancelled,
+}
+
+impl OrderSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderSide::Buy => "Buy",
+            OrderSide::Sell => "Sell",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Buy" => Ok(OrderSide::Buy),
+            "Sell" => Ok(OrderSide::Sell),
+            _ => Err(format!("Invalid order side: {}", s)),
+        }
+    }
+}
+
+impl OrderType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderType::Market => "Market",
+            OrderType::Limit => "Limit",
+            OrderType::Stop => "Stop",
+            OrderType::StopLimit => "StopLimit",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Market" => Ok(OrderType::Market),
+            "Limit" => Ok(OrderType::Limit),
+            "Stop" => Ok(OrderType::Stop),
+            "StopLimit" => Ok(OrderType::StopLimit),
+            _ => Err(format!("Invalid order type: {}", s)),
+        }
+    }
+}
+
+impl TimeInForce {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Day => "DAY",
+            TimeInForce::Ioc => "IOC",
+        }
+    }
+}
+
+impl std::str::FromStr for TimeInForce {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GTC" => Ok(TimeInForce::Gtc),
+            "DAY" => Ok(TimeInForce::Day),
+            "IOC" => Ok(TimeInForce::Ioc),
+            _ => Err(format!("Invalid time in force: {}", s)),
+        }
+    }
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "Pending",
+            OrderStatus::Executed => "Executed",
+            OrderStatus::Failed => "Failed",
+            OrderStatus::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(OrderStatus::Pending),
+            "Executed" => Ok(OrderStatus::Executed),
+            "Failed" => Ok(OrderStatus::Failed),
+            "Cancelled" => Ok(OrderStatus::Cancelled),
+            _ => Err(format!("Invalid order status: {}", s)),
+        }
+    }
+}
+
+// =============================================================
+// ORDER MODELS
+// =============================================================
+
+/// Request to place an order in a portfolio
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlaceOrderRequest {
+    /// Asset ID to trade
+    pub asset_id: i32,
+    /// Buy or Sell
+    pub side: OrderSide,
+    /// Market, Limit, Stop or StopLimit
+    pub order_type: OrderType,
+    /// GTC (default), DAY or IOC
+    pub time_in_force: Option<TimeInForce>,
+    /// Quantity to trade (must be positive)
+    #[schema(example = "10")]
+    pub quantity: Decimal,
+    /// Required for Limit and StopLimit orders
+    #[schema(example = "150.00")]
+    pub limit_price: Option<Decimal>,
+    /// Required for Stop and StopLimit orders
+    #[schema(example = "145.00")]
+    pub stop_price: Option<Decimal>,
+}
+
+/// Request to amend a pending order; omitted fields are left unchanged
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AmendOrderRequest {
+    /// New quantity (must be positive)
+    #[schema(example = "5")]
+    pub quantity: Option<Decimal>,
+    /// New limit price; only for Limit and StopLimit orders
+    #[schema(example = "148.50")]
+    pub limit_price: Option<Decimal>,
+}
+
+/// An order and where it is in its lifecycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Order {
+    /// Order ID, which is also the ID of its transaction
+    pub order_id: i64,
+    /// Portfolio ID
+    pub portfolio_id: i32,
+    /// Asset ID
+    pub asset_id: i32,
+    /// Asset symbol
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    /// Quantity to trade
+    pub quantity: Decimal,
+    #[serde(serialize_with = "serialize_optional_price")]
+    pub limit_price: Option<Decimal>,
+    #[serde(serialize_with = "serialize_optional_price")]
+    pub stop_price: Option<Decimal>,
+    /// Whether the stop price of a StopLimit order has been reached
+    pub triggered: bool,
+    /// Portfolio cash held for a pending buy
+    #[serde(serialize_with = "serialize_money")]
+    pub reserved_funds: Decimal,
+    pub status: OrderStatus,
+    /// Why the order was cancelled or failed
+    pub status_reason: Option<String>,
+    /// Execution price, once executed
+    #[serde(serialize_with = "serialize_optional_price")]
+    pub fill_price: Option<Decimal>,
+    pub executed_at: Option<String>,
+    /// End of the trading day for DAY orders
+    pub expires_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// =============================================================
+// ORDER PREVIEW
+// =============================================================
+
+/// A trade to preview; the same fields `/portfolios/buy` and `/portfolios/sell` take
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrderPreviewRequest {
+    /// Asset ID to trade
+    pub asset_id: i32,
+    /// Buy or Sell
+    pub side: OrderSide,
+    /// Quantity to trade (must be positive)
+    #[schema(example = "10")]
+    pub quantity: Decimal,
+    /// Optional unit price (if not provided, uses current market price)
+    #[schema(example = "150.00")]
+    pub unit_price: Option<Decimal>,
+    /// Lots to sell from, as on `/portfolios/sell`; only for sells
+    pub lots: Option<Vec<LotSelection>>,
+}
+
+/// What the buy or sell endpoint would return for the trade
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum TradeProjection {
+    Buy(BuyAssetResponse),
+    Sell(SellAssetResponse),
+}
+
+/// The outcome of a trade worked out without executing it
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrderPreview {
+    pub portfolio_id: i32,
+    pub asset_id: i32,
+    pub side: OrderSide,
+    /// Response the trade would get, with status `Preview` and no transaction ID
+    pub projection: TradeProjection,
+    /// Quantity of the asset held after the trade
+    pub quantity_held_after: Decimal,
+    /// Average cost of the quantity held after the trade; 0 when nothing is left
+    #[serde(serialize_with = "serialize_price")]
+    pub average_price_after: Decimal,
+    /// Market value of the position as a share of the portfolio's value after the trade, in percent
+    #[schema(example = "12.50")]
+    pub position_pct_after: Decimal,
+    /// Every pre-trade rule the trade breaks; empty when it would be accepted
+    pub violations: Vec<RuleViolation>,
+}
+
+// =============================================================
+// BATCH ORDERS
+// =============================================================
+
+/// One buy or sell in a batch
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchLeg {
+    /// Asset ID to trade
+    pub asset_id: i32,
+    /// Buy or Sell
+    pub side: OrderSide,
+    /// Quantity to trade (must be positive)
+    #[schema(example = "10")]
+    pub quantity: Decimal,
+    /// Optional unit price (if not provided, uses current market price)
+    pub unit_price: Option<Decimal>,
+    /// Lots to sell from, as on `/portfolios/sell`; only for sells
+    pub lots: Option<Vec<LotSelection>>,
+}
+
+/// Request to trade several assets in one go; sells run before buys
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchOrderRequest {
+    pub legs: Vec<BatchLeg>,
+    /// When set, one failing leg cancels the whole batch; otherwise failing
+    /// legs are left out and the rest still execute
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+/// How a batch ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum BatchStatus {
+    /// Every leg executed
+    Executed,
+    /// Some legs failed and the others executed
+    PartiallyExecuted,
+    /// Nothing executed
+    Failed,
+}
+
+/// How one leg of a batch ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum BatchLegStatus {
+    Executed,
+    Failed,
+    /// Not executed because another leg of an all-or-nothing batch failed
+    Skipped,
+}
+
+/// Why a leg failed, as the error body its own request would have got
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchLegError {
+    #[schema(example = "INSUFFICIENT_FUNDS")]
+    pub code: String,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+/// Outcome of one leg, in the order the legs were sent
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchLegResult {
+    /// Position of the leg in the request, from 0
+    pub leg: usize,
+    pub asset_id: i32,
+    pub side: OrderSide,
+    pub status: BatchLegStatus,
+    /// What the buy or sell endpoint returned for the leg, once executed
+    pub result: Option<TradeProjection>,
+    pub error: Option<BatchLegError>,
+}
+
+/// Result of a batch, with one entry per leg
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchOrderResponse {
+    pub portfolio_id: i32,
+    pub status: BatchStatus,
+    pub legs: Vec<BatchLegResult>,
+}
@@ -0,0 +1,172 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::serialize_money;
+
+// =============================================================
+// VALUATION SNAPSHOTS
+// =============================================================
+
+/// A portfolio's value at the end of one day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub snapshot_date: NaiveDate,
+    /// Cash, including funds held back for pending orders
+    pub cash_value: Decimal,
+    /// Holdings at that day's closing prices
+    pub holdings_value: Decimal,
+    /// Money allocated to the portfolio that day, less money deallocated
+    pub net_flows: Decimal,
+}
+
+impl PortfolioSnapshot {
+    pub fn total_value(&self) -> Decimal {
+        self.cash_value + self.holdings_value
+    }
+}
+
+/// Request to rebuild stored snapshots from price and transaction history
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackfillSnapshotsRequest {
+    /// Every portfolio when absent
+    pub portfolio_id: Option<i32>,
+    /// First day to rebuild; each portfolio's creation date by default
+    pub from: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackfillSnapshotsResponse {
+    pub portfolios: usize,
+    /// Daily snapshots written
+    pub snapshots: usize,
+}
+
+// =============================================================
+// PERFORMANCE
+// =============================================================
+
+/// Spacing of the points of an equity curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum PerformanceInterval {
+    Daily,
+    /// Weeks end on Sunday
+    Weekly,
+    Monthly,
+}
+
+/// One point of an equity curve, at the last snapshot of its interval
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PerformancePoint {
+    pub date: NaiveDate,
+    #[schema(example = "1250.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub cash_value: Decimal,
+    #[schema(example = "8900.40")]
+    #[serde(serialize_with = "serialize_money")]
+    pub holdings_value: Decimal,
+    #[schema(example = "10150.40")]
+    #[serde(serialize_with = "serialize_money")]
+    pub total_value: Decimal,
+    /// Allocations less deallocations within the interval
+    #[schema(example = "500.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub net_flows: Decimal,
+    /// Change in value since the first point that flows do not account for
+    #[schema(example = "150.40")]
+    #[serde(serialize_with = "serialize_money")]
+    pub gain: Decimal,
+}
+
+/// A portfolio's value over time, for charting
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PortfolioPerformance {
+    pub portfolio_id: i32,
+    pub interval: PerformanceInterval,
+    /// First and last day with a snapshot in the requested range
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[serde(serialize_with = "serialize_money")]
+    pub start_value: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub end_value: Decimal,
+    /// Allocations less deallocations after the first point
+    #[serde(serialize_with = "serialize_money")]
+    pub net_flows: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub gain: Decimal,
+    pub points: Vec<PerformancePoint>,
+}
+
+// =============================================================
+// RETURNS
+// =============================================================
+
+/// Standard reporting periods, each ending at the latest snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum ReturnPeriod {
+    #[serde(rename = "1M")]
+    OneMonth,
+    #[serde(rename = "3M")]
+    ThreeMonths,
+    /// Since the last day of the previous year
+    #[serde(rename = "YTD")]
+    YearToDate,
+    #[serde(rename = "1Y")]
+    OneYear,
+    /// Since the first snapshot
+    Inception,
+}
+
+/// Returns over one period, in percent
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PeriodReturn {
+    pub period: ReturnPeriod,
+    /// Day returns are measured from; the first snapshot when the history
+    /// is shorter than the period
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[schema(example = "9500.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub start_value: Decimal,
+    #[schema(example = "10150.40")]
+    #[serde(serialize_with = "serialize_money")]
+    pub end_value: Decimal,
+    /// Allocations less deallocations after `from`
+    #[schema(example = "500.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub net_flows: Decimal,
+    /// Growth with the effect of allocations and deallocations removed;
+    /// absent when no money was invested
+    #[schema(example = "1.52")]
+    pub time_weighted_return: Option<Decimal>,
+    /// Return earned on the money actually invested, allowing for when it
+    /// came in and out; absent when it has no solution
+    #[schema(example = "1.48")]
+    pub money_weighted_return: Option<Decimal>,
+    /// Only for periods of a year or more
+    pub annualized_time_weighted_return: Option<Decimal>,
+    /// The XIRR; only for periods of a year or more
+    pub annualized_money_weighted_return: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PortfolioReturns {
+    pub portfolio_id: i32,
+    /// Date of the latest snapshot
+    pub as_of: Option<NaiveDate>,
+    pub periods: Vec<PeriodReturn>,
+}
+
+/// Returns of all of a user's portfolios taken together
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserReturns {
+    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    pub portfolio_ids: Vec<i32>,
+    /// Date of the latest snapshot
+    pub as_of: Option<NaiveDate>,
+    pub periods: Vec<PeriodReturn>,
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::{serialize_money, serialize_price};
+
 /// Represents a portfolio in the system
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct Portfolio {
@@ -14,9 +17,10 @@ pub struct Portfolio {
     #[schema(example = "2024-03-20T10:00:00")]
     pub creation_date: String,
     #[schema(example = "10000.50", minimum = 0)]
-    pub current_funds: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub current_funds: Decimal,
     #[schema(example = "15.5")]
-    pub current_profit_pct: f64,
+    pub current_profit_pct: Decimal,
     #[schema(example = "2024-03-20T10:00:00")]
     pub last_updated: String,
 }
@@ -28,18 +32,17 @@ pub struct CreatePortfolioRequest {
     pub name: String,
     #[serde(default)]
     #[schema(example = "10000.50", minimum = 0)]
-    pub initial_funds: Option<f64>,
+    pub initial_funds: Option<Decimal>,
     #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
     pub user_id: Uuid,
 }
 
-/// Request payload for updating a portfolio
+/// Request payload for updating a portfolio. Its cash only moves through
+/// allocations and deallocations, which keep the fund transaction ledger.
 #[derive(Deserialize, ToSchema)]
 pub struct UpdatePortfolioRequest {
     #[schema(example = "Tech Growth Portfolio Updated")]
     pub name: Option<String>,
-    #[schema(example = "15000.75", minimum = 0)]
-    pub current_funds: Option<f64>,
 }
 
 /// Portfolio summary from the database view
@@ -52,9 +55,10 @@ pub struct PortfolioSummary {
     #[schema(example = "John Doe")]
     pub owner: String,
     #[schema(example = "10000.50", minimum = 0)]
-    pub current_funds: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub current_funds: Decimal,
     #[schema(example = "15.5")]
-    pub current_profit_pct: f64,
+    pub current_profit_pct: Decimal,
     #[schema(example = "2024-03-20T10:00:00")]
     pub creation_date: String,
     #[schema(example = "42")]
@@ -77,9 +81,11 @@ pub struct AssetHolding {
     #[schema(example = "Company")]
     pub asset_type: String,
     #[schema(example = "100", minimum = 0)]
-    pub quantity_held: f64,
+    pub quantity_held: Decimal,
     #[schema(example = "175.50", minimum = 0)]
-    pub current_price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub current_price: Decimal,
     #[schema(example = "17550.00", minimum = 0)]
-    pub market_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub market_value: Decimal,
 } 
\ No newline at end of file
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::{BatchOrderResponse, OrderSide, serialize_money, serialize_price};
+
+// =============================================================
+// ALLOCATION TARGETS
+// =============================================================
+
+/// Drift tolerance of a portfolio that has not set one, in percentage points
+pub const DEFAULT_DRIFT_TOLERANCE_PCT: Decimal = Decimal::from_parts(5, 0, 0, false, 0);
+
+/// Target weight of one asset or of every asset of one type
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AllocationTarget {
+    /// Set for per-asset targets
+    pub asset_id: Option<i32>,
+    pub symbol: Option<String>,
+    /// Set for per-asset-type targets
+    #[schema(example = "Stock")]
+    pub asset_type: Option<String>,
+    /// Share of the portfolio's value, in percent
+    #[schema(example = "60")]
+    pub weight: Decimal,
+}
+
+/// A portfolio's target allocation; whatever the weights leave out of 100% is kept in cash
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AllocationTargets {
+    pub portfolio_id: i32,
+    /// Percentage points a weight may drift from its target before it is rebalanced
+    #[schema(example = "5")]
+    pub drift_tolerance_pct: Decimal,
+    /// Trades worth less than this are left out of a rebalance
+    #[schema(example = "50.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub min_trade_value: Decimal,
+    /// Empty until targets are set
+    pub targets: Vec<AllocationTarget>,
+    pub updated_at: Option<String>,
+}
+
+/// One target in a `SetAllocationTargetsRequest`; give either `asset_id` or `asset_type`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AllocationTargetInput {
+    pub asset_id: Option<i32>,
+    #[schema(example = "Stock")]
+    pub asset_type: Option<String>,
+    #[schema(example = "60")]
+    pub weight: Decimal,
+}
+
+/// Request replacing a portfolio's targets; all of them per asset or all per asset type
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetAllocationTargetsRequest {
+    /// Weights adding up to at most 100; an empty list removes the targets
+    pub targets: Vec<AllocationTargetInput>,
+    /// Defaults to 5 percentage points
+    #[schema(example = "5")]
+    pub drift_tolerance_pct: Option<Decimal>,
+    /// Defaults to 0
+    #[schema(example = "50.00")]
+    pub min_trade_value: Option<Decimal>,
+}
+
+// =============================================================
+// DRIFT
+// =============================================================
+
+/// How far one asset or asset type is from its target
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AllocationDrift {
+    pub asset_id: Option<i32>,
+    pub symbol: Option<String>,
+    pub asset_type: Option<String>,
+    /// 0 for holdings the targets leave out
+    pub target_weight: Decimal,
+    pub current_weight: Decimal,
+    /// Current weight minus target weight, in percentage points
+    #[schema(example = "-4.25")]
+    pub drift: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub current_value: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub target_value: Decimal,
+    pub within_tolerance: bool,
+}
+
+/// A portfolio's allocation measured against its targets at current prices
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DriftReport {
+    pub portfolio_id: i32,
+    /// Cash plus holdings at current prices
+    #[serde(serialize_with = "serialize_money")]
+    pub total_value: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub cash: Decimal,
+    pub cash_weight: Decimal,
+    /// What the targets leave out of 100%
+    pub cash_target_weight: Decimal,
+    pub drift_tolerance_pct: Decimal,
+    /// Whether any weight is outside the tolerance
+    pub needs_rebalance: bool,
+    pub allocations: Vec<AllocationDrift>,
+}
+
+// =============================================================
+// REBALANCING
+// =============================================================
+
+/// Request to work out a rebalance and optionally carry it out
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RebalanceRequest {
+    /// Execute the trades as a batch order instead of only returning them
+    #[serde(default)]
+    pub execute: bool,
+    /// When executing, cancel every trade if one fails
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+/// One trade of a rebalance, at the asset's current price
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RebalanceTrade {
+    pub asset_id: i32,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    #[serde(serialize_with = "serialize_price")]
+    pub price: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub value: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub estimated_fee: Decimal,
+}
+
+/// The trades that bring a portfolio back within its drift tolerance
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RebalancePlan {
+    pub portfolio_id: i32,
+    /// Allocation before the rebalance
+    pub drift: DriftReport,
+    /// Sells first, then buys
+    pub trades: Vec<RebalanceTrade>,
+    /// Cash left once every trade and its fee has gone through
+    #[serde(serialize_with = "serialize_money")]
+    pub projected_cash: Decimal,
+    /// Targets the plan could not fully reach, and why
+    pub notes: Vec<String>,
+    /// Outcome of the batch order, when the plan was executed
+    pub execution: Option<BatchOrderResponse>,
+}
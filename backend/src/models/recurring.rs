@@ -0,0 +1,152 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::{serialize_money, serialize_optional_money, serialize_optional_price};
+
+// =============================================================
+// RECURRING INVESTMENTS
+// =============================================================
+
+/// How often a recurring investment buys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum RecurrenceFrequency {
+    Daily,
+    /// On `day_of_week`
+    Weekly,
+    /// On `day_of_month`
+    Monthly,
+}
+
+impl RecurrenceFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurrenceFrequency::Daily => "Daily",
+            RecurrenceFrequency::Weekly => "Weekly",
+            RecurrenceFrequency::Monthly => "Monthly",
+        }
+    }
+}
+
+impl std::str::FromStr for RecurrenceFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Daily" => Ok(RecurrenceFrequency::Daily),
+            "Weekly" => Ok(RecurrenceFrequency::Weekly),
+            "Monthly" => Ok(RecurrenceFrequency::Monthly),
+            _ => Err(format!("Invalid recurrence frequency: {}", s)),
+        }
+    }
+}
+
+/// A fixed amount of one asset bought on a schedule, in fractional quantities
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecurringInvestment {
+    pub schedule_id: i32,
+    pub portfolio_id: i32,
+    pub asset_id: i32,
+    pub symbol: String,
+    /// Invested on each run; the trading fee is charged on top
+    #[schema(example = "200.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub amount: Decimal,
+    pub frequency: RecurrenceFrequency,
+    /// 1 (Monday) to 7 (Sunday), for weekly schedules
+    #[schema(example = 1)]
+    pub day_of_week: Option<i32>,
+    /// 1 to 28, for monthly schedules
+    pub day_of_month: Option<i32>,
+    /// Paused schedules keep their history but do not run
+    pub active: bool,
+    /// Day of the next run; absent while paused
+    pub next_run_date: Option<NaiveDate>,
+    pub last_run_date: Option<NaiveDate>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Request to schedule a recurring investment
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateRecurringInvestmentRequest {
+    pub asset_id: i32,
+    #[schema(example = "200.00")]
+    pub amount: Decimal,
+    pub frequency: RecurrenceFrequency,
+    /// Required for weekly schedules: 1 (Monday) to 7 (Sunday)
+    #[schema(example = 1)]
+    pub day_of_week: Option<i32>,
+    /// Required for monthly schedules: 1 to 28
+    pub day_of_month: Option<i32>,
+    /// First day the schedule may run; defaults to today
+    pub start_date: Option<NaiveDate>,
+}
+
+/// Request to change a recurring investment; omitted fields are kept
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRecurringInvestmentRequest {
+    #[schema(example = "250.00")]
+    pub amount: Option<Decimal>,
+    pub frequency: Option<RecurrenceFrequency>,
+    pub day_of_week: Option<i32>,
+    pub day_of_month: Option<i32>,
+    /// `false` pauses the schedule, `true` resumes it from today
+    pub active: Option<bool>,
+}
+
+/// What happened on one run of a recurring investment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum RecurringRunStatus {
+    /// Claimed by the runner and not finished yet
+    Running,
+    Executed,
+    /// Not bought; `reason` says why
+    Skipped,
+}
+
+impl RecurringRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringRunStatus::Running => "Running",
+            RecurringRunStatus::Executed => "Executed",
+            RecurringRunStatus::Skipped => "Skipped",
+        }
+    }
+}
+
+impl std::str::FromStr for RecurringRunStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Running" => Ok(RecurringRunStatus::Running),
+            "Executed" => Ok(RecurringRunStatus::Executed),
+            "Skipped" => Ok(RecurringRunStatus::Skipped),
+            _ => Err(format!("Invalid run status: {}", s)),
+        }
+    }
+}
+
+/// One run of a recurring investment and the fund transaction that records it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecurringInvestmentRun {
+    pub run_id: i64,
+    pub schedule_id: i32,
+    /// Day the run was due
+    pub run_date: NaiveDate,
+    pub status: RecurringRunStatus,
+    /// Buy transaction of an executed run
+    pub transaction_id: Option<i64>,
+    /// `AssetPurchase` of an executed run, `RecurringSkipped` of a skipped one
+    pub fund_transaction_id: Option<i64>,
+    pub quantity: Option<Decimal>,
+    #[serde(serialize_with = "serialize_optional_price")]
+    pub unit_price: Option<Decimal>,
+    /// Cost of the buy including its fee
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub total_cost: Option<Decimal>,
+    pub reason: Option<String>,
+    pub executed_at: String,
+}
@@ -1,6 +1,10 @@
-use serde::Serialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 use uuid::Uuid;
+
+use super::serialize_money;
 //
 /// Risk metrics for a user from the database
 #[derive(Serialize, ToSchema)]
@@ -9,15 +13,23 @@ pub struct RiskMetrics {
     pub metric_id: i32,
     #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
     pub user_id: Uuid,
+    /// Largest fall from a peak over the window, in percent
     #[schema(example = "-15.5")]
     pub maximum_drawdown: Option<f64>,
+    /// Against the S&P 500 (`SPX`)
     #[schema(example = "1.2")]
     pub beta: Option<f64>,
     #[schema(example = "0.8")]
     pub sharpe_ratio: Option<f64>,
+    #[schema(example = "1.1")]
+    pub sortino_ratio: Option<f64>,
+    #[schema(example = "0.6")]
+    pub calmar_ratio: Option<f64>,
+    /// Time-weighted return over the window, in percent
     #[schema(example = "25.5")]
     pub absolute_return: Option<f64>,
-    #[schema(example = "0.75")]
+    /// Annualized volatility of daily returns, in percent
+    #[schema(example = "18.4")]
     pub volatility_score: Option<f64>,
     #[schema(example = "Moderate")]
     pub risk_level: String,
@@ -37,7 +49,8 @@ pub struct RiskAnalysis {
     #[schema(example = "3")]
     pub total_portfolios: i32,
     #[schema(example = "150000.50")]
-    pub total_investment: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_investment: Decimal,
     #[schema(example = "-15.5")]
     pub maximum_drawdown: Option<f64>,
     #[schema(example = "0.8")]
@@ -56,9 +69,10 @@ pub struct PortfolioRiskAnalysis {
     #[schema(example = "Tech Growth Portfolio")]
     pub portfolio_name: String,
     #[schema(example = "150000.50")]
-    pub current_funds: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub current_funds: Decimal,
     #[schema(example = "25.5")]
-    pub current_profit_pct: f64,
+    pub current_profit_pct: Decimal,
     #[schema(example = "-15.5")]
     pub maximum_drawdown: Option<f64>,
     #[schema(example = "1.2")]
@@ -77,9 +91,111 @@ pub struct RiskSummary {
     #[schema(example = "10")]
     pub total_portfolios: i32,
     #[schema(example = "1500000.50")]
-    pub total_assets_under_management: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_assets_under_management: Decimal,
     #[schema(example = "0.75")]
     pub average_system_risk: f64,
     #[schema(example = "2024-03-20T10:00:00")]
     pub calculated_at: String,
-} 
\ No newline at end of file
+}
+
+/// How Value-at-Risk is estimated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VarMethod {
+    /// Replays the portfolio through each past period of the lookback
+    #[default]
+    Historical,
+    /// Assumes normally distributed returns with the historical mean and covariance
+    Parametric,
+    /// Simulates returns drawn from the historical mean and covariance
+    MonteCarlo,
+}
+
+/// One holding's share of a portfolio's tail risk
+#[derive(Serialize, ToSchema)]
+pub struct VarContribution {
+    #[schema(example = "1")]
+    pub asset_id: i32,
+    #[schema(example = "AAPL")]
+    pub symbol: String,
+    #[schema(example = "17550.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub market_value: Decimal,
+    /// Share of the holdings' value, in percent
+    #[schema(example = "62.50")]
+    pub weight: Decimal,
+    /// Contributions add up to the portfolio's VaR and CVaR; a holding
+    /// that offsets the others contributes a negative amount
+    #[schema(example = "412.80")]
+    #[serde(serialize_with = "serialize_money")]
+    pub var: Decimal,
+    #[schema(example = "530.15")]
+    #[serde(serialize_with = "serialize_money")]
+    pub cvar: Decimal,
+}
+
+/// Value-at-Risk and expected shortfall of a portfolio's current holdings
+#[derive(Serialize, ToSchema)]
+pub struct PortfolioVar {
+    #[schema(example = "1")]
+    pub portfolio_id: i32,
+    pub method: VarMethod,
+    #[schema(example = "0.95")]
+    pub confidence: f64,
+    #[schema(example = "1")]
+    pub horizon_days: u32,
+    /// Market value of the holdings; cash carries no risk
+    #[schema(example = "28080.00")]
+    #[serde(serialize_with = "serialize_money")]
+    pub exposure: Decimal,
+    /// Loss over the horizon that is only exceeded `1 - confidence` of the time
+    #[schema(example = "652.10")]
+    #[serde(serialize_with = "serialize_money")]
+    pub var: Decimal,
+    /// VaR as a percentage of the exposure
+    #[schema(example = "2.32")]
+    pub var_percent: Decimal,
+    /// Average loss when the VaR is exceeded (expected shortfall)
+    #[schema(example = "840.35")]
+    #[serde(serialize_with = "serialize_money")]
+    pub cvar: Decimal,
+    #[schema(example = "2.99")]
+    pub cvar_percent: Decimal,
+    /// Days of returns the estimate is based on, common to every holding
+    #[schema(example = "364")]
+    pub observations: usize,
+    pub history_from: Option<NaiveDate>,
+    pub history_to: Option<NaiveDate>,
+    pub contributions: Vec<VarContribution>,
+}
+
+/// An asset in a correlation matrix, in the order of its rows and columns
+#[derive(Serialize, ToSchema)]
+pub struct CorrelationAsset {
+    #[schema(example = "1")]
+    pub asset_id: i32,
+    #[schema(example = "AAPL")]
+    pub symbol: String,
+}
+
+/// Pairwise correlation and covariance of assets' daily returns
+#[derive(Serialize, ToSchema)]
+pub struct AssetCorrelation {
+    /// Set when the assets are a portfolio's current holdings
+    #[schema(example = "1")]
+    pub portfolio_id: Option<i32>,
+    /// Calendar days of price history looked at
+    #[schema(example = "90")]
+    pub days: u32,
+    /// Returns the matrices are based on, between days every asset traded
+    #[schema(example = "61")]
+    pub observations: usize,
+    pub history_from: Option<NaiveDate>,
+    pub history_to: Option<NaiveDate>,
+    pub assets: Vec<CorrelationAsset>,
+    /// Correlation between -1 and 1; `null` for an asset whose price never moved
+    pub correlation: Vec<Vec<Option<f64>>>,
+    /// Sample covariance of the returns, as fractions
+    pub covariance: Vec<Vec<f64>>,
+}
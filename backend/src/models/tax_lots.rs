@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::{serialize_money, serialize_price};
+
+// =============================================================
+// COST BASIS METHOD
+// =============================================================
+
+/// Which lots a sale consumes; stored as `Portfolios.CostBasisMethod`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum CostBasisMethod {
+    /// Oldest lots first
+    #[default]
+    #[serde(rename = "FIFO")]
+    Fifo,
+    /// Newest lots first
+    #[serde(rename = "LIFO")]
+    Lifo,
+    /// The same fraction of every open lot, keeping the average cost
+    Average,
+    /// The lots named on each sale; order fills fall back to FIFO
+    SpecificLot,
+}
+
+impl CostBasisMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostBasisMethod::Fifo => "FIFO",
+            CostBasisMethod::Lifo => "LIFO",
+            CostBasisMethod::Average => "Average",
+            CostBasisMethod::SpecificLot => "SpecificLot",
+        }
+    }
+}
+
+impl std::str::FromStr for CostBasisMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "FIFO" => Ok(CostBasisMethod::Fifo),
+            "LIFO" => Ok(CostBasisMethod::Lifo),
+            "Average" => Ok(CostBasisMethod::Average),
+            "SpecificLot" => Ok(CostBasisMethod::SpecificLot),
+            _ => Err(format!("Invalid cost basis method: {}", s)),
+        }
+    }
+}
+
+/// A portfolio's cost basis method
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CostBasisSettings {
+    pub portfolio_id: i32,
+    pub cost_basis_method: CostBasisMethod,
+}
+
+/// Request to change a portfolio's cost basis method; applies to later sales
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetCostBasisRequest {
+    pub cost_basis_method: CostBasisMethod,
+}
+
+// =============================================================
+// LOT MODELS
+// =============================================================
+
+/// Shares to sell from one lot
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LotSelection {
+    pub lot_id: i64,
+    #[schema(example = "2.5")]
+    pub quantity: Decimal,
+}
+
+/// A purchase and how much of it is still held
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TaxLot {
+    pub lot_id: i64,
+    pub portfolio_id: i32,
+    pub asset_id: i32,
+    pub symbol: String,
+    /// The buy that opened the lot; absent for lots carried over from
+    /// holdings that predate lot tracking
+    pub buy_transaction_id: Option<i64>,
+    pub acquired_at: String,
+    /// Quantity bought
+    pub quantity: Decimal,
+    /// Quantity not yet sold
+    pub remaining_quantity: Decimal,
+    #[serde(serialize_with = "serialize_price")]
+    pub unit_cost: Decimal,
+    /// Cost of the remaining quantity
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+}
+
+/// The part of a sale taken from one lot
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RealizedLot {
+    pub sell_transaction_id: i64,
+    pub lot_id: i64,
+    pub portfolio_id: i32,
+    pub asset_id: i32,
+    pub symbol: String,
+    pub acquired_at: String,
+    #[serde(serialize_with = "serialize_price")]
+    pub unit_cost: Decimal,
+    #[serde(serialize_with = "serialize_price")]
+    pub sale_price: Decimal,
+    pub quantity: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub proceeds: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub realized_gain: Decimal,
+    pub cost_basis_method: CostBasisMethod,
+    pub realized_at: String,
+}
+
+/// Realized gain of one sale, with the lots it consumed
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SaleRealizedGain {
+    pub sell_transaction_id: i64,
+    pub asset_id: i32,
+    pub symbol: String,
+    pub sold_at: String,
+    #[serde(serialize_with = "serialize_price")]
+    pub sale_price: Decimal,
+    pub quantity: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub proceeds: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub realized_gain: Decimal,
+    pub cost_basis_method: CostBasisMethod,
+    pub lots: Vec<RealizedLot>,
+}
+
+/// Realized P&L of one asset over a period
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AssetRealizedPnl {
+    pub asset_id: i32,
+    pub symbol: String,
+    pub sales: i32,
+    #[serde(serialize_with = "serialize_money")]
+    pub proceeds: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub realized_gain: Decimal,
+}
+
+/// Realized P&L of a portfolio from the start of a year to a date
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RealizedPnl {
+    pub portfolio_id: i32,
+    #[schema(example = "2025")]
+    pub year: i32,
+    #[schema(example = "2025-01-01")]
+    pub from: String,
+    /// Today for the current year, otherwise the last day of the year
+    #[schema(example = "2025-06-30")]
+    pub to: String,
+    pub sales: i32,
+    #[serde(serialize_with = "serialize_money")]
+    pub proceeds: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+    #[serde(serialize_with = "serialize_money")]
+    pub realized_gain: Decimal,
+    pub by_asset: Vec<AssetRealizedPnl>,
+}
@@ -1,7 +1,11 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 use uuid::Uuid;
-//
+
+use super::{LotSelection, OrderSide, OrderStatus, serialize_money, serialize_price};
+
 /// Request to buy an asset
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BuyAssetRequest {
@@ -10,9 +14,9 @@ pub struct BuyAssetRequest {
     /// Asset ID to buy
     pub asset_id: i32,
     /// Quantity to buy (must be positive)
-    pub quantity: f64,
+    pub quantity: Decimal,
     /// Optional unit price (if not provided, uses current market price)
-    pub unit_price: Option<f64>,
+    pub unit_price: Option<Decimal>,
 }
 
 /// Request to sell an asset
@@ -23,9 +27,12 @@ pub struct SellAssetRequest {
     /// Asset ID to sell
     pub asset_id: i32,
     /// Quantity to sell (must be positive)
-    pub quantity: f64,
+    pub quantity: Decimal,
     /// Optional unit price (if not provided, uses current market price)
-    pub unit_price: Option<f64>,
+    pub unit_price: Option<Decimal>,
+    /// Lots to sell from, adding up to `quantity`; required when the portfolio
+    /// uses the SpecificLot cost basis method, otherwise overrides it
+    pub lots: Option<Vec<LotSelection>>,
 }
 
 /// Response for buy operation
@@ -36,13 +43,19 @@ pub struct BuyAssetResponse {
     /// Transaction ID
     pub transaction_id: i64,
     /// Quantity purchased
-    pub quantity_purchased: f64,
+    pub quantity_purchased: Decimal,
     /// Price per share
-    pub price_per_share: f64,
-    /// Total cost of purchase
-    pub total_cost: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub price_per_share: Decimal,
+    /// Total cost of purchase, fee included
+    #[serde(serialize_with = "serialize_money")]
+    pub total_cost: Decimal,
+    /// Trading fee charged
+    #[serde(serialize_with = "serialize_money")]
+    pub fee: Decimal,
     /// Remaining cash funds in portfolio
-    pub remaining_funds: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub remaining_funds: Decimal,
 }
 
 /// Response for sell operation
@@ -53,13 +66,25 @@ pub struct SellAssetResponse {
     /// Transaction ID
     pub transaction_id: i64,
     /// Quantity sold
-    pub quantity_sold: f64,
+    pub quantity_sold: Decimal,
     /// Price per share
-    pub price_per_share: f64,
-    /// Total proceeds from sale
-    pub total_proceeds: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub price_per_share: Decimal,
+    /// Total proceeds from sale, net of the fee
+    #[serde(serialize_with = "serialize_money")]
+    pub total_proceeds: Decimal,
+    /// Trading fee charged
+    #[serde(serialize_with = "serialize_money")]
+    pub fee: Decimal,
     /// New cash funds balance in portfolio
-    pub new_funds_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub new_funds_balance: Decimal,
+    /// Cost of the lots sold
+    #[serde(serialize_with = "serialize_money")]
+    pub cost_basis: Decimal,
+    /// Proceeds net of the fee minus cost basis, which includes the buy fees
+    #[serde(serialize_with = "serialize_money")]
+    pub realized_gain: Decimal,
 }
 
 /// Portfolio holding information
@@ -78,17 +103,22 @@ pub struct PortfolioHolding {
     /// Asset type
     pub asset_type: String,
     /// Quantity held
-    pub quantity_held: f64,
+    pub quantity_held: Decimal,
     /// Average purchase price
-    pub average_price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub average_price: Decimal,
     /// Total cost basis
-    pub total_cost: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_cost: Decimal,
     /// Current market price
-    pub current_price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub current_price: Decimal,
     /// Current market value
-    pub current_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub current_value: Decimal,
     /// Unrealized gain/loss
-    pub unrealized_gain_loss: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub unrealized_gain_loss: Decimal,
     /// Last updated timestamp
     pub last_updated: String,
 }
@@ -111,17 +141,39 @@ pub struct TradingTransaction {
     /// Transaction type (Buy or Sell)
     pub transaction_type: String,
     /// Quantity traded
-    pub quantity: f64,
+    pub quantity: Decimal,
     /// Unit price
-    pub unit_price: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub unit_price: Decimal,
     /// Total value (quantity * unit_price)
-    pub total_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_value: Decimal,
     /// Transaction status
     pub status: String,
     /// Transaction timestamp
     pub transaction_date: String,
 }
 
+/// Filters for a portfolio's transaction history; all are optional
+#[derive(Debug, Default)]
+pub struct TransactionFilter {
+    pub asset_id: Option<i32>,
+    pub transaction_type: Option<OrderSide>,
+    pub status: Option<OrderStatus>,
+    /// First day included
+    pub from: Option<NaiveDate>,
+    /// Last day included
+    pub to: Option<NaiveDate>,
+}
+
+/// One page of a portfolio's transaction history, newest first
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionPage {
+    pub transactions: Vec<TradingTransaction>,
+    /// Pass as `cursor` to get the next page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
 /// Portfolio holdings summary
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PortfolioHoldingsSummary {
@@ -132,11 +184,14 @@ pub struct PortfolioHoldingsSummary {
     /// List of holdings
     pub holdings: Vec<PortfolioHolding>,
     /// Total holdings value
-    pub total_holdings_value: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_holdings_value: Decimal,
     /// Total cost basis
-    pub total_cost_basis: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_cost_basis: Decimal,
     /// Total unrealized gain/loss
-    pub total_unrealized_gain_loss: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub total_unrealized_gain_loss: Decimal,
     /// Number of different assets
     pub assets_count: i32,
 } 
\ No newline at end of file
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+
+use super::serialize_optional_money;
+
+// =============================================================
+// TRADING LIMITS
+// =============================================================
+
+/// A portfolio's pre-trade limits; an absent value means no limit
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TradingLimits {
+    pub portfolio_id: i32,
+    /// Largest share of the portfolio's value one asset may reach through a buy, in percent
+    #[schema(example = "25")]
+    pub max_position_pct: Option<Decimal>,
+    /// Largest value of a single trade or order
+    #[schema(example = "5000.00")]
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub max_order_notional: Option<Decimal>,
+    /// Largest value bought and sold in one day, this trade included
+    #[schema(example = "20000.00")]
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub max_daily_volume: Option<Decimal>,
+    /// Refuse trades in assets whose price was last updated longer ago than this
+    #[schema(example = "60")]
+    pub max_price_age_minutes: Option<i32>,
+    /// Absent until limits are first set
+    pub updated_at: Option<String>,
+}
+
+/// Request replacing all of a portfolio's limits; omitted ones are removed
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct SetTradingLimitsRequest {
+    #[schema(example = "25")]
+    pub max_position_pct: Option<Decimal>,
+    #[schema(example = "5000.00")]
+    pub max_order_notional: Option<Decimal>,
+    #[schema(example = "20000.00")]
+    pub max_daily_volume: Option<Decimal>,
+    #[schema(example = "60")]
+    pub max_price_age_minutes: Option<i32>,
+}
+
+// =============================================================
+// QUANTITY INCREMENTS
+// =============================================================
+
+/// Smallest tradable step for every asset of a type; quantities must be a multiple of it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuantityIncrement {
+    #[schema(example = "Stock")]
+    pub asset_type: String,
+    #[schema(example = "1")]
+    pub increment: Decimal,
+    pub updated_at: String,
+}
+
+/// Request to change the quantity increment of an asset type
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetQuantityIncrementRequest {
+    #[schema(example = "0.000001")]
+    pub increment: Decimal,
+}
+
+// =============================================================
+// RULE VIOLATIONS
+// =============================================================
+
+/// A pre-trade rule a trade breaks, with the limit it enforces and the value that broke it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleViolation {
+    #[schema(example = "max_order_notional")]
+    pub rule: String,
+    pub message: String,
+    #[schema(example = "5000.00")]
+    pub limit: Decimal,
+    #[schema(example = "7350.00")]
+    pub actual: Decimal,
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::{serialize_money, serialize_optional_money};
+
 /// Basic user model (backward compatible)
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct User {
@@ -39,7 +42,8 @@ pub struct ExtendedUser {
     #[schema(example = "Premium")]
     pub user_type: String,
     #[schema(example = 1000.50)]
-    pub account_balance: f64,
+    #[serde(serialize_with = "serialize_money")]
+    pub account_balance: Decimal,
     
     // Payment Method Fields
     #[schema(example = "CreditCard")]
@@ -57,7 +61,8 @@ pub struct ExtendedUser {
     #[schema(example = "2025-01-01T00:00:00Z")]
     pub premium_end_date: Option<String>,
     #[schema(example = 50.00)]
-    pub monthly_subscription_rate: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub monthly_subscription_rate: Option<Decimal>,
     pub auto_renew_subscription: bool,
     #[schema(example = "2024-01-01T00:00:00Z")]
     pub last_subscription_payment: Option<String>,
@@ -127,7 +132,7 @@ pub struct ManageSubscriptionRequest {
     #[schema(example = 3)]
     pub months_to_add: Option<i32>,
     #[schema(example = 50.00)]
-    pub monthly_rate: Option<f64>,
+    pub monthly_rate: Option<Decimal>,
 }
 
 /// Response for payment method operations
@@ -141,9 +146,11 @@ pub struct PaymentMethodResponse {
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SubscriptionResponse {
     pub status: String,
-    pub amount_paid: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub amount_paid: Option<Decimal>,
     pub months_added: Option<i32>,
-    pub new_balance: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_money")]
+    pub new_balance: Option<Decimal>,
     pub message: Option<String>,
 }
 
@@ -161,5 +168,36 @@ pub struct LoginRequest {
 pub struct LoginResponse {
     #[schema(example = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...")]
     pub token: String,
+    #[schema(example = "9f2c4e0b7d5a...")]
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    #[schema(example = 900)]
+    pub expires_in: u64,
     pub user: User,
-} 
\ No newline at end of file
+}
+
+/// Request payload for rotating a refresh token; falls back to the `refresh_token` cookie
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshTokenRequest {
+    #[schema(example = "9f2c4e0b7d5a...")]
+    pub refresh_token: Option<String>,
+}
+
+/// New token pair issued by a refresh
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    #[schema(example = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...")]
+    pub token: String,
+    #[schema(example = "9f2c4e0b7d5a...")]
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    #[schema(example = 900)]
+    pub expires_in: u64,
+}
+
+/// Response payload for logging out of every device
+#[derive(Serialize, ToSchema)]
+pub struct LogoutAllResponse {
+    #[schema(example = 3)]
+    pub sessions_revoked: u64,
+}
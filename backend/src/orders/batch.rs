@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::error::ApiError;
+use crate::models::{
+    round_to_scale, BatchLeg, BatchLegError, BatchLegResult, BatchLegStatus, BatchOrderRequest, BatchOrderResponse,
+    BatchStatus, OrderSide, TradeProjection, MONEY_SCALE,
+};
+use crate::pretrade::{self, ProposedTrade};
+use crate::store::{BatchOutcome, Store};
+
+fn leg_error(error: ApiError) -> BatchLegError {
+    let body = error.into_body();
+    BatchLegError {
+        code: body.code.to_string(),
+        message: body.message,
+        details: body.details,
+    }
+}
+
+/// Runs a batch of buys and sells in a portfolio.
+///
+/// Sells go first so their proceeds can pay for the buys. Each leg goes through
+/// the pre-trade rules as if the legs accepted before it had run: they count
+/// towards the day's volume, the quantity held of their asset and the
+/// portfolio's value. The legs that pass execute in one store transaction. In an
+/// all-or-nothing batch the first leg to fail stops everything; otherwise it is
+/// left out and the transaction is run again with the remaining legs.
+pub async fn execute_batch(store: &dyn Store, portfolio_id: i32, request: &BatchOrderRequest) -> Result<BatchOrderResponse, ApiError> {
+    let legs = &request.legs;
+    let order: Vec<usize> = (0..legs.len())
+        .filter(|&index| legs[index].side == OrderSide::Sell)
+        .chain((0..legs.len()).filter(|&index| legs[index].side == OrderSide::Buy))
+        .collect();
+
+    let mut errors: Vec<Option<ApiError>> = legs.iter().map(|_| None).collect();
+    let mut results: Vec<Option<TradeProjection>> = legs.iter().map(|_| None).collect();
+
+    let mut batch_volume = Decimal::ZERO;
+    let mut batch_value = Decimal::ZERO;
+    let mut batch_quantities: HashMap<i32, Decimal> = HashMap::new();
+    for &index in &order {
+        let leg = &legs[index];
+        let mut snapshot = match store.pre_trade_snapshot(portfolio_id, leg.asset_id).await {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                errors[index] = Some(error.into());
+                continue;
+            },
+        };
+        snapshot.traded_today += batch_volume;
+        snapshot.portfolio_value += batch_value;
+        snapshot.quantity_held += batch_quantities.get(&leg.asset_id).copied().unwrap_or_default();
+
+        let trade = ProposedTrade {
+            portfolio_id,
+            asset_id: leg.asset_id,
+            side: leg.side,
+            quantity: leg.quantity,
+            price: leg.unit_price,
+        };
+        match pretrade::evaluate(&trade, &snapshot) {
+            Ok(()) => {
+                // Cash and shares change hands; the value only moves when the
+                // trade's price differs from the market's
+                let notional = trade.notional(&snapshot);
+                let market_value = round_to_scale(trade.quantity * snapshot.price, MONEY_SCALE);
+                let (quantity, value) = match trade.side {
+                    OrderSide::Buy => (trade.quantity, market_value - notional),
+                    OrderSide::Sell => (-trade.quantity, notional - market_value),
+                };
+                *batch_quantities.entry(leg.asset_id).or_default() += quantity;
+                batch_value += value;
+                batch_volume += notional;
+            },
+            Err(violation) => errors[index] = Some(violation.into()),
+        }
+    }
+
+    let mut pending: Vec<usize> = order.into_iter().filter(|&index| errors[index].is_none()).collect();
+    if request.all_or_nothing && errors.iter().any(Option::is_some) {
+        // A leg the rules refused already fails the whole batch
+        pending.clear();
+    }
+    while !pending.is_empty() {
+        let batch: Vec<&BatchLeg> = pending.iter().map(|&index| &legs[index]).collect();
+        match store.execute_batch(portfolio_id, &batch).await? {
+            BatchOutcome::Committed(projections) => {
+                for (&index, projection) in pending.iter().zip(projections) {
+                    results[index] = Some(projection);
+                }
+                break;
+            },
+            BatchOutcome::RolledBack(position, error) => {
+                errors[pending.remove(position)] = Some(error.into());
+                if request.all_or_nothing {
+                    break;
+                }
+            },
+        }
+    }
+
+    let executed = results.iter().filter(|result| result.is_some()).count();
+    let status = match executed {
+        0 => BatchStatus::Failed,
+        n if n == legs.len() => BatchStatus::Executed,
+        _ => BatchStatus::PartiallyExecuted,
+    };
+
+    let legs = legs.iter()
+        .zip(results.into_iter().zip(errors))
+        .enumerate()
+        .map(|(index, (leg, (result, error)))| BatchLegResult {
+            leg: index,
+            asset_id: leg.asset_id,
+            side: leg.side,
+            status: match (&result, &error) {
+                (Some(_), _) => BatchLegStatus::Executed,
+                (None, Some(_)) => BatchLegStatus::Failed,
+                (None, None) => BatchLegStatus::Skipped,
+            },
+            result,
+            error: error.map(leg_error),
+        })
+        .collect();
+
+    Ok(BatchOrderResponse { portfolio_id, status, legs })
+}
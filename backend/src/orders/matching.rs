@@ -0,0 +1,153 @@
+use rust_decimal::Decimal;
+
+use crate::models::{Order, OrderSide, OrderType};
+
+/// What the matching worker should do with a pending order at the current price
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// Leave the order on the book
+    Wait,
+    /// The stop price of a stop-limit order was reached but its limit was not
+    Trigger,
+    /// Execute at the given price
+    Fill(Decimal),
+}
+
+/// Decides whether `order` executes at the market price `price`.
+///
+/// Orders always fill at the market price, which is the limit price or better
+/// whenever a limit is crossed. Stops trigger when the price moves through them
+/// against the position: up for buys, down for sells.
+pub fn evaluate(order: &Order, price: Decimal) -> Decision {
+    // Assets created by a CSV import have no price until one is set
+    if price <= Decimal::ZERO {
+        return Decision::Wait;
+    }
+
+    let stop_reached = || order.stop_price.is_some_and(|stop| match order.side {
+        OrderSide::Buy => price >= stop,
+        OrderSide::Sell => price <= stop,
+    });
+    let limit_crossed = || order.limit_price.is_some_and(|limit| match order.side {
+        OrderSide::Buy => price <= limit,
+        OrderSide::Sell => price >= limit,
+    });
+
+    match order.order_type {
+        OrderType::Market => Decision::Fill(price),
+        OrderType::Limit if limit_crossed() => Decision::Fill(price),
+        OrderType::Stop if stop_reached() => Decision::Fill(price),
+        OrderType::StopLimit if order.triggered || stop_reached() => {
+            if limit_crossed() {
+                Decision::Fill(price)
+            } else if order.triggered {
+                Decision::Wait
+            } else {
+                Decision::Trigger
+            }
+        },
+        _ => Decision::Wait,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OrderStatus, TimeInForce};
+
+    fn order(side: OrderSide, order_type: OrderType, limit: Option<i64>, stop: Option<i64>) -> Order {
+        Order {
+            order_id: 1,
+            portfolio_id: 1,
+            asset_id: 1,
+            symbol: "TEST".to_string(),
+            side,
+            order_type,
+            time_in_force: TimeInForce::Gtc,
+            quantity: Decimal::ONE,
+            limit_price: limit.map(Decimal::from),
+            stop_price: stop.map(Decimal::from),
+            triggered: false,
+            reserved_funds: Decimal::ZERO,
+            status: OrderStatus::Pending,
+            status_reason: None,
+            fill_price: None,
+            executed_at: None,
+            expires_at: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn fill(price: i64) -> Decision {
+        Decision::Fill(Decimal::from(price))
+    }
+
+    #[test]
+    fn limits_fill_once_crossed() {
+        let buy = order(OrderSide::Buy, OrderType::Limit, Some(100), None);
+        let sell = order(OrderSide::Sell, OrderType::Limit, Some(100), None);
+        let cases = [
+            (&buy, 101, Decision::Wait),
+            (&buy, 100, fill(100)),
+            (&buy, 95, fill(95)),
+            (&sell, 99, Decision::Wait),
+            (&sell, 100, fill(100)),
+            (&sell, 105, fill(105)),
+        ];
+
+        for (order, price, expected) in cases {
+            assert_eq!(evaluate(order, Decimal::from(price)), expected, "{:?} at {price}", order.side);
+        }
+    }
+
+    #[test]
+    fn stops_fill_when_the_price_moves_through_them() {
+        let buy = order(OrderSide::Buy, OrderType::Stop, None, Some(100));
+        let sell = order(OrderSide::Sell, OrderType::Stop, None, Some(100));
+        let cases = [
+            (&buy, 99, Decision::Wait),
+            (&buy, 100, fill(100)),
+            (&buy, 104, fill(104)),
+            (&sell, 101, Decision::Wait),
+            (&sell, 100, fill(100)),
+            (&sell, 96, fill(96)),
+        ];
+
+        for (order, price, expected) in cases {
+            assert_eq!(evaluate(order, Decimal::from(price)), expected, "{:?} at {price}", order.side);
+        }
+    }
+
+    #[test]
+    fn stop_limits_trigger_then_wait_for_their_limit() {
+        // Sell stop at 100 with a limit of 98: a gap to 95 triggers it without a fill
+        let mut sell = order(OrderSide::Sell, OrderType::StopLimit, Some(98), Some(100));
+        assert_eq!(evaluate(&sell, Decimal::from(101)), Decision::Wait);
+        assert_eq!(evaluate(&sell, Decimal::from(95)), Decision::Trigger);
+
+        sell.triggered = true;
+        assert_eq!(evaluate(&sell, Decimal::from(97)), Decision::Wait);
+        // Once triggered it fills above the stop as well
+        assert_eq!(evaluate(&sell, Decimal::from(102)), fill(102));
+
+        // Buy stop at 100 with a limit of 102: reaching both at once fills straight away
+        let buy = order(OrderSide::Buy, OrderType::StopLimit, Some(102), Some(100));
+        assert_eq!(evaluate(&buy, Decimal::from(101)), fill(101));
+        assert_eq!(evaluate(&buy, Decimal::from(105)), Decision::Trigger);
+    }
+
+    #[test]
+    fn a_zero_price_always_waits() {
+        let cases = [
+            order(OrderSide::Buy, OrderType::Market, None, None),
+            order(OrderSide::Buy, OrderType::Limit, Some(100), None),
+            order(OrderSide::Sell, OrderType::Stop, None, Some(100)),
+            order(OrderSide::Buy, OrderType::StopLimit, Some(102), Some(100)),
+        ];
+
+        for order in cases {
+            assert_eq!(evaluate(&order, Decimal::ZERO), Decision::Wait, "{:?}", order.order_type);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use rust_decimal::Decimal;
+
+use crate::models::{Order, OrderStatus, TimeInForce};
+use crate::pretrade::{self, ProposedTrade};
+use crate::store::{Store, StoreResult};
+
+mod batch;
+mod matching;
+mod worker;
+
+use matching::{evaluate, Decision};
+pub use batch::execute_batch;
+pub use worker::{spawn_matcher, PriceEvents};
+
+/// Applies the matching decision for a pending order at `price`. An order
+/// that would now break a pre-trade rule is cancelled instead of filled.
+pub async fn process(store: &dyn Store, order: Order, price: Decimal) -> StoreResult<Order> {
+    match evaluate(&order, price) {
+        Decision::Wait => Ok(order),
+        Decision::Trigger => store.trigger_order(order.order_id).await,
+        Decision::Fill(price) => {
+            // Limits and the portfolio may have changed since the order was placed
+            let trade = ProposedTrade {
+                portfolio_id: order.portfolio_id,
+                asset_id: order.asset_id,
+                side: order.side,
+                quantity: order.quantity,
+                price: Some(price),
+            };
+            let mut snapshot = store.pre_trade_snapshot(order.portfolio_id, order.asset_id).await?;
+            // The order's reservation left the portfolio's cash when it was placed
+            snapshot.portfolio_value += order.reserved_funds;
+            if let Err(violation) = pretrade::evaluate(&trade, &snapshot) {
+                return store.cancel_order(order.order_id, &violation.message).await;
+            }
+            store.fill_order(order.order_id, price).await
+        },
+    }
+}
+
+/// Runs a newly placed or amended order through matching once. Market orders fill here;
+/// an IOC order that does not is cancelled.
+pub async fn submit(store: &dyn Store, order: Order) -> StoreResult<Order> {
+    let price = store.get_asset(order.asset_id).await?.price;
+    let order = process(store, order, price).await?;
+
+    if order.status == OrderStatus::Pending && order.time_in_force == TimeInForce::Ioc {
+        return store.cancel_order(order.order_id, "Immediate-or-cancel order could not be filled").await;
+    }
+    Ok(order)
+}
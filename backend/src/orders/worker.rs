@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::store::{Store, StoreError};
+use super::process;
+
+/// How often every pending order is re-checked and DAY orders are expired
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Price changes waiting to be matched
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Tells the matching worker that an asset's price changed
+#[derive(Clone)]
+pub struct PriceEvents {
+    sender: mpsc::Sender<i32>,
+}
+
+impl PriceEvents {
+    pub fn price_moved(&self, asset_id: i32) {
+        // A full queue only delays matching until the next sweep
+        let _ = self.sender.try_send(asset_id);
+    }
+}
+
+/// Starts the background task that fills resting orders
+pub fn spawn_matcher(store: Arc<dyn Store>) -> PriceEvents {
+    let (sender, mut receiver) = mpsc::channel(QUEUE_CAPACITY);
+
+    tokio::spawn(async move {
+        let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            tokio::select! {
+                Some(asset_id) = receiver.recv() => match_orders(store.as_ref(), Some(asset_id)).await,
+                _ = sweep.tick() => {
+                    expire_orders(store.as_ref()).await;
+                    match_orders(store.as_ref(), None).await;
+                },
+            }
+        }
+    });
+
+    PriceEvents { sender }
+}
+
+/// Checks the pending orders of one asset, or of all assets, oldest first
+async fn match_orders(store: &dyn Store, asset_id: Option<i32>) {
+    let orders = match store.pending_orders(asset_id).await {
+        Ok(orders) => orders,
+        Err(e) => {
+            eprintln!("Order matching failed to load pending orders: {}", e);
+            return;
+        },
+    };
+
+    let mut prices = HashMap::new();
+    for order in orders {
+        let price = match prices.get(&order.asset_id) {
+            Some(&price) => price,
+            None => match store.get_asset(order.asset_id).await {
+                Ok(asset) => *prices.entry(order.asset_id).or_insert(asset.price),
+                Err(e) => {
+                    eprintln!("Order matching failed to price asset {}: {}", order.asset_id, e);
+                    continue;
+                },
+            },
+        };
+
+        let order_id = order.order_id;
+        match process(store, order, price).await {
+            // Cancelled or filled since the list was read
+            Ok(_) | Err(StoreError::Conflict(_)) => {},
+            Err(e) => eprintln!("Order matching failed for order {}: {}", order_id, e),
+        }
+    }
+}
+
+async fn expire_orders(store: &dyn Store) {
+    let order_ids = match store.expired_order_ids().await {
+        Ok(order_ids) => order_ids,
+        Err(e) => {
+            eprintln!("Order matching failed to load expired orders: {}", e);
+            return;
+        },
+    };
+
+    for order_id in order_ids {
+        match store.cancel_order(order_id, "DAY order expired at the end of the day").await {
+            Ok(_) | Err(StoreError::Conflict(_)) => {},
+            Err(e) => eprintln!("Failed to expire order {}: {}", order_id, e),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+
+use crate::models::{PerformanceInterval, PerformancePoint, PortfolioPerformance, PortfolioSnapshot};
+
+/// Day that closes the interval `date` falls in
+fn interval_end(date: NaiveDate, interval: PerformanceInterval) -> NaiveDate {
+    match interval {
+        PerformanceInterval::Daily => date,
+        PerformanceInterval::Weekly => date + Days::new(u64::from(7 - date.weekday().number_from_monday())),
+        PerformanceInterval::Monthly => date.with_day(1)
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(date),
+    }
+}
+
+/// Turns daily snapshots, oldest first, into one point per interval.
+/// Gains are measured against the first snapshot, whose own flows are
+/// already part of its value.
+pub fn equity_curve(portfolio_id: i32, snapshots: &[PortfolioSnapshot], interval: PerformanceInterval) -> PortfolioPerformance {
+    let start_value = snapshots.first().map_or(Decimal::ZERO, PortfolioSnapshot::total_value);
+    let mut net_flows = Decimal::ZERO;
+    let mut points: Vec<PerformancePoint> = Vec::new();
+    let mut current_interval = None;
+
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        let flows = match index {
+            0 => Decimal::ZERO,
+            _ => snapshot.net_flows,
+        };
+        net_flows += flows;
+        let point = PerformancePoint {
+            date: snapshot.snapshot_date,
+            cash_value: snapshot.cash_value,
+            holdings_value: snapshot.holdings_value,
+            total_value: snapshot.total_value(),
+            net_flows: flows,
+            gain: snapshot.total_value() - start_value - net_flows,
+        };
+
+        let end = interval_end(snapshot.snapshot_date, interval);
+        match points.last_mut() {
+            Some(last) if current_interval == Some(end) => {
+                *last = PerformancePoint { net_flows: last.net_flows + flows, ..point };
+            },
+            _ => points.push(point),
+        }
+        current_interval = Some(end);
+    }
+
+    let end_value = snapshots.last().map_or(Decimal::ZERO, PortfolioSnapshot::total_value);
+    PortfolioPerformance {
+        portfolio_id,
+        interval,
+        from: snapshots.first().map(|s| s.snapshot_date),
+        to: snapshots.last().map(|s| s.snapshot_date),
+        start_value,
+        end_value,
+        net_flows,
+        gain: end_value - start_value - net_flows,
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn intervals_end_on_their_last_day() {
+        let cases = [
+            (date(2024, 3, 13), PerformanceInterval::Daily, date(2024, 3, 13)),
+            // Weeks run Monday to Sunday
+            (date(2024, 3, 11), PerformanceInterval::Weekly, date(2024, 3, 17)),
+            (date(2024, 3, 17), PerformanceInterval::Weekly, date(2024, 3, 17)),
+            (date(2024, 12, 30), PerformanceInterval::Weekly, date(2025, 1, 5)),
+            (date(2024, 1, 1), PerformanceInterval::Monthly, date(2024, 1, 31)),
+            (date(2024, 2, 10), PerformanceInterval::Monthly, date(2024, 2, 29)),
+            (date(2023, 2, 28), PerformanceInterval::Monthly, date(2023, 2, 28)),
+            (date(2024, 12, 15), PerformanceInterval::Monthly, date(2024, 12, 31)),
+        ];
+
+        for (day, interval, expected) in cases {
+            assert_eq!(interval_end(day, interval), expected, "{day} {interval:?}");
+        }
+    }
+}
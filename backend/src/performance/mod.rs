@@ -0,0 +1,42 @@
+use chrono::NaiveDate;
+
+use crate::models::PortfolioSnapshot;
+use crate::scheduler;
+use crate::store::{Store, StoreResult};
+
+mod curve;
+mod returns;
+mod snapshots;
+mod worker;
+
+pub use curve::equity_curve;
+pub use returns::{combine_snapshots, period_returns};
+pub use worker::spawn_snapshotter;
+
+/// Brings a portfolio's snapshots up to date: the last stored one is
+/// rewritten, since its day may have closed since, along with every day
+/// after it. A portfolio without snapshots is backfilled in full.
+pub async fn refresh_snapshots(store: &dyn Store, portfolio_id: i32) -> StoreResult<usize> {
+    let from = store.last_snapshot_date(portfolio_id).await?;
+    backfill_snapshots(store, portfolio_id, from).await
+}
+
+/// Rebuilds a portfolio's daily snapshots from `from`, or from the day it was
+/// created, through today and returns how many were written
+pub async fn backfill_snapshots(store: &dyn Store, portfolio_id: i32, from: Option<NaiveDate>) -> StoreResult<usize> {
+    let snapshots = daily_valuations(store, portfolio_id, from).await?;
+    if !snapshots.is_empty() {
+        store.save_portfolio_snapshots(portfolio_id, &snapshots).await?;
+    }
+    Ok(snapshots.len())
+}
+
+/// Values a portfolio at the end of every day from `from`, or from the day it
+/// was created, through today, oldest first, without storing the result
+pub async fn daily_valuations(store: &dyn Store, portfolio_id: i32, from: Option<NaiveDate>) -> StoreResult<Vec<PortfolioSnapshot>> {
+    let today = scheduler::today();
+    let history = store.portfolio_history(portfolio_id, from, today).await?;
+
+    let from = from.map_or(history.created_on, |from| from.max(history.created_on));
+    Ok(snapshots::daily_snapshots(&history, from, today))
+}
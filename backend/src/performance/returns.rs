@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+
+use crate::models::{round_to_scale, PeriodReturn, PortfolioSnapshot, ReturnPeriod, MONEY_SCALE};
+
+const PERIODS: [ReturnPeriod; 5] = [
+    ReturnPeriod::OneMonth,
+    ReturnPeriod::ThreeMonths,
+    ReturnPeriod::YearToDate,
+    ReturnPeriod::OneYear,
+    ReturnPeriod::Inception,
+];
+
+const DAYS_PER_YEAR: f64 = 365.0;
+
+/// Day whose closing value a period's returns start from
+fn period_start(period: ReturnPeriod, as_of: NaiveDate) -> Option<NaiveDate> {
+    match period {
+        ReturnPeriod::OneMonth => as_of.checked_sub_months(Months::new(1)),
+        ReturnPeriod::ThreeMonths => as_of.checked_sub_months(Months::new(3)),
+        ReturnPeriod::YearToDate => as_of.with_ordinal(1).and_then(|first| first.checked_sub_days(Days::new(1))),
+        ReturnPeriod::OneYear => as_of.checked_sub_months(Months::new(12)),
+        ReturnPeriod::Inception => None,
+    }
+}
+
+/// Adds up the daily snapshots of several portfolios. A portfolio counts as
+/// zero before its first snapshot and keeps its last value after its last.
+pub fn combine_snapshots(portfolios: &[Vec<PortfolioSnapshot>]) -> Vec<PortfolioSnapshot> {
+    let Some(last_day) = portfolios.iter().filter_map(|s| s.last()).map(|s| s.snapshot_date).max() else {
+        return Vec::new();
+    };
+
+    let mut combined: BTreeMap<NaiveDate, PortfolioSnapshot> = BTreeMap::new();
+    for snapshots in portfolios {
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            let until = snapshots.get(index + 1).map_or(last_day, |next| next.snapshot_date.pred_opt().unwrap_or(next.snapshot_date));
+            let mut day = snapshot.snapshot_date;
+            while day <= until {
+                let total = combined.entry(day).or_insert_with(|| PortfolioSnapshot {
+                    snapshot_date: day,
+                    cash_value: Decimal::ZERO,
+                    holdings_value: Decimal::ZERO,
+                    net_flows: Decimal::ZERO,
+                });
+                total.cash_value += snapshot.cash_value;
+                total.holdings_value += snapshot.holdings_value;
+                if day == snapshot.snapshot_date {
+                    total.net_flows += snapshot.net_flows;
+                }
+                match day.succ_opt() {
+                    Some(next) => day = next,
+                    None => break,
+                }
+            }
+        }
+    }
+    combined.into_values().collect()
+}
+
+/// Returns over each standard period from daily snapshots, oldest first
+pub fn period_returns(snapshots: &[PortfolioSnapshot]) -> Vec<PeriodReturn> {
+    let Some(last) = snapshots.last() else {
+        return Vec::new();
+    };
+
+    PERIODS.iter()
+        .map(|period| {
+            // The last snapshot on or before the start; a history shorter
+            // than the period starts from its first snapshot
+            let base = period_start(*period, last.snapshot_date)
+                .map_or(0, |start| snapshots.partition_point(|s| s.snapshot_date <= start).saturating_sub(1));
+            period_return(*period, &snapshots[base], &snapshots[base + 1..])
+        })
+        .collect()
+}
+
+/// Returns from the close of `base` through the snapshots after it. Flows
+/// are taken to arrive at the start of their day, so each day's return is
+/// its closing value against the previous close plus that day's flows.
+fn period_return(period: ReturnPeriod, base: &PortfolioSnapshot, snapshots: &[PortfolioSnapshot]) -> PeriodReturn {
+    let start_value = base.total_value();
+    let end = snapshots.last().unwrap_or(base);
+    let days = (end.snapshot_date - base.snapshot_date).num_days();
+
+    let mut growth = Decimal::ONE;
+    let mut invested = false;
+    let mut previous = start_value;
+    // Cash flows from the investor's side: money put in is negative
+    let mut cash_flows = vec![(0, -start_value)];
+    for snapshot in snapshots {
+        let capital = previous + snapshot.net_flows;
+        if capital > Decimal::ZERO {
+            growth *= snapshot.total_value() / capital;
+            invested = true;
+        }
+        previous = snapshot.total_value();
+        cash_flows.push(((snapshot.snapshot_date - base.snapshot_date).num_days(), -snapshot.net_flows));
+    }
+    cash_flows.push((days, end.total_value()));
+
+    let time_weighted = invested.then(|| growth - Decimal::ONE);
+    let irr = xirr(&cash_flows);
+    let money_weighted = irr.map(|rate| (1.0 + rate).powf(days as f64 / DAYS_PER_YEAR) - 1.0);
+    let annualized_time_weighted = time_weighted
+        .filter(|_| days as f64 >= DAYS_PER_YEAR)
+        .and_then(|twr| (twr + Decimal::ONE).to_f64())
+        .map(|g| g.powf(DAYS_PER_YEAR / days as f64) - 1.0);
+
+    PeriodReturn {
+        period,
+        from: base.snapshot_date,
+        to: end.snapshot_date,
+        start_value,
+        end_value: end.total_value(),
+        net_flows: snapshots.iter().map(|s| s.net_flows).sum(),
+        time_weighted_return: time_weighted.map(percent),
+        money_weighted_return: money_weighted.and_then(percent_from_f64),
+        annualized_time_weighted_return: annualized_time_weighted.and_then(percent_from_f64),
+        annualized_money_weighted_return: irr.filter(|_| days as f64 >= DAYS_PER_YEAR).and_then(percent_from_f64),
+    }
+}
+
+fn percent(rate: Decimal) -> Decimal {
+    round_to_scale(rate * Decimal::ONE_HUNDRED, MONEY_SCALE)
+}
+
+fn percent_from_f64(rate: f64) -> Option<Decimal> {
+    Decimal::from_f64(rate).map(percent)
+}
+
+/// Annual rate at which the cash flows, each on its day offset, are worth
+/// nothing today; found by bisection. None when the flows do not change
+/// sign or all fall on the same day.
+fn xirr(cash_flows: &[(i64, Decimal)]) -> Option<f64> {
+    let flows: Vec<(f64, f64)> = cash_flows.iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .filter_map(|(day, amount)| Some((*day as f64 / DAYS_PER_YEAR, amount.to_f64()?)))
+        .collect();
+    let spans_time = flows.windows(2).any(|pair| pair[0].0 != pair[1].0);
+    let paid_in = flows.iter().any(|(_, amount)| *amount < 0.0);
+    let paid_out = flows.iter().any(|(_, amount)| *amount > 0.0);
+    if !spans_time || !paid_in || !paid_out {
+        return None;
+    }
+
+    let present_value = |rate: f64| -> f64 {
+        flows.iter().map(|(years, amount)| amount / (1.0 + rate).powf(*years)).sum()
+    };
+
+    let mut low = -0.999_999;
+    let mut high = 1.0;
+    while present_value(low).signum() == present_value(high).signum() {
+        high *= 2.0;
+        if high > 1e9 {
+            return None;
+        }
+    }
+    let low_sign = present_value(low).signum();
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if present_value(mid).signum() == low_sign {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let rate = (low + high) / 2.0;
+    rate.is_finite().then_some(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(offset: u64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + Days::new(offset)
+    }
+
+    fn snapshot(offset: u64, value: i64, net_flows: i64) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            snapshot_date: day(offset),
+            cash_value: Decimal::from(value),
+            holdings_value: Decimal::ZERO,
+            net_flows: Decimal::from(net_flows),
+        }
+    }
+
+    fn flows(flows: &[(i64, i64)]) -> Vec<(i64, Decimal)> {
+        flows.iter().map(|(day, amount)| (*day, Decimal::from(*amount))).collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn xirr_finds_the_rate_that_zeroes_the_flows() {
+        // 1000 in, then 1000 more a year later; both grow 10% a year
+        // to 1000 * 1.1^2 + 1000 * 1.1 = 2310
+        let rate = xirr(&flows(&[(0, -1000), (365, -1000), (730, 2310)])).unwrap();
+        assert_close(rate, 0.10);
+
+        // Losing a fifth over a year; the empty flow on day 73 is skipped
+        let rate = xirr(&flows(&[(0, -1000), (73, 0), (365, 800)])).unwrap();
+        assert_close(rate, -0.20);
+    }
+
+    #[test]
+    fn xirr_needs_money_both_in_and_out_over_time() {
+        assert_eq!(xirr(&flows(&[(0, 1000), (365, 1100)])), None);
+        assert_eq!(xirr(&flows(&[(0, -1000), (365, -1100)])), None);
+        assert_eq!(xirr(&flows(&[(10, -1000), (10, 1100)])), None);
+        assert_eq!(xirr(&[]), None);
+    }
+
+    #[test]
+    fn time_weighted_return_ignores_a_mid_period_deposit() {
+        // +10% on day 1; 1000 deposited on day 2 then another +10%
+        let base = snapshot(0, 1000, 0);
+        let later = [snapshot(1, 1100, 0), snapshot(2, 2310, 1000)];
+        let result = period_return(ReturnPeriod::Inception, &base, &later);
+
+        assert_eq!(result.time_weighted_return, Some(Decimal::from(21)));
+        assert_eq!(result.net_flows, Decimal::from(1000));
+        assert_eq!(result.start_value, Decimal::from(1000));
+        assert_eq!(result.end_value, Decimal::from(2310));
+        // Too short to annualize
+        assert_eq!(result.annualized_time_weighted_return, None);
+    }
+}
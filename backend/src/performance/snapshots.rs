@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::models::{round_to_scale, OrderSide, PortfolioSnapshot, MONEY_SCALE};
+use crate::store::{CashMovement, HistoricalTrade, PortfolioHistory};
+
+/// Last close on or before `day`; days before the first close use that one
+fn close_on(series: &[(NaiveDate, Decimal)], day: NaiveDate) -> Decimal {
+    let after = series.partition_point(|(date, _)| *date <= day);
+    series.get(after.saturating_sub(1)).map_or(Decimal::ZERO, |(_, price)| *price)
+}
+
+/// Works out the portfolio's value at the end of every day from `from` to
+/// `today`, oldest first. It starts from the current cash and holdings and
+/// walks back a day at a time, undoing each day's trades and cash movements.
+pub fn daily_snapshots(history: &PortfolioHistory, from: NaiveDate, today: NaiveDate) -> Vec<PortfolioSnapshot> {
+    let closes: HashMap<i32, &[(NaiveDate, Decimal)]> = history.closes.iter()
+        .map(|(asset_id, series)| (*asset_id, series.as_slice()))
+        .collect();
+
+    // Anything stamped after today still belongs to the current state
+    let mut trades: BTreeMap<NaiveDate, Vec<&HistoricalTrade>> = BTreeMap::new();
+    for trade in &history.trades {
+        trades.entry(trade.date.min(today)).or_default().push(trade);
+    }
+    let mut movements: BTreeMap<NaiveDate, Vec<&CashMovement>> = BTreeMap::new();
+    for movement in &history.cash_movements {
+        movements.entry(movement.date.min(today)).or_default().push(movement);
+    }
+
+    let mut cash = history.cash;
+    let mut quantities: HashMap<i32, Decimal> = history.holdings.iter().copied().collect();
+    let mut snapshots = Vec::new();
+
+    let mut day = today;
+    while day >= from {
+        let day_movements = movements.get(&day).map(Vec::as_slice).unwrap_or_default();
+        let holdings_value = quantities.iter()
+            .map(|(asset_id, quantity)| *quantity * closes.get(asset_id).map_or(Decimal::ZERO, |series| close_on(series, day)))
+            .sum();
+        snapshots.push(PortfolioSnapshot {
+            snapshot_date: day,
+            cash_value: round_to_scale(cash, MONEY_SCALE),
+            holdings_value: round_to_scale(holdings_value, MONEY_SCALE),
+            net_flows: day_movements.iter().filter(|m| m.external).map(|m| m.amount).sum(),
+        });
+
+        // Back to the end of the day before
+        cash -= day_movements.iter().map(|m| m.amount).sum::<Decimal>();
+        for trade in trades.get(&day).map(Vec::as_slice).unwrap_or_default() {
+            let quantity = quantities.entry(trade.asset_id).or_default();
+            match trade.side {
+                OrderSide::Buy => *quantity -= trade.quantity,
+                OrderSide::Sell => *quantity += trade.quantity,
+            }
+        }
+
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+
+    snapshots.reverse();
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn movement(day: u32, amount: i64, external: bool) -> CashMovement {
+        CashMovement { date: date(day), amount: Decimal::from(amount), external }
+    }
+
+    #[test]
+    fn values_each_day_from_the_current_state_back() {
+        // Deposit 1000 and buy 10 at 50 on Thursday 7 March, sell 4 at 60 on
+        // Monday 11 March, a fee of 1 on each trade; no prices over the weekend
+        let history = PortfolioHistory {
+            created_on: date(6),
+            cash: Decimal::from(738),
+            holdings: vec![(1, Decimal::from(6))],
+            trades: vec![
+                HistoricalTrade { asset_id: 1, side: OrderSide::Buy, quantity: Decimal::from(10), date: date(7) },
+                HistoricalTrade { asset_id: 1, side: OrderSide::Sell, quantity: Decimal::from(4), date: date(11) },
+            ],
+            cash_movements: vec![
+                movement(7, 1000, true),
+                movement(7, -500, false),
+                movement(7, -1, false),
+                movement(11, 240, false),
+                movement(11, -1, false),
+            ],
+            closes: vec![(1, vec![
+                (date(5), Decimal::from(48)),
+                (date(7), Decimal::from(50)),
+                (date(8), Decimal::from(52)),
+                (date(11), Decimal::from(60)),
+                (date(12), Decimal::from(61)),
+            ])],
+        };
+
+        let snapshots = daily_snapshots(&history, date(6), date(12));
+
+        let values: Vec<(NaiveDate, Decimal, Decimal, Decimal)> = snapshots.iter()
+            .map(|s| (s.snapshot_date, s.cash_value, s.holdings_value, s.net_flows))
+            .collect();
+        let expected: Vec<(NaiveDate, Decimal, Decimal, Decimal)> = [
+            (6, 0, 0, 0),
+            (7, 499, 500, 1000),
+            (8, 499, 520, 0),
+            // The weekend keeps Friday's close
+            (9, 499, 520, 0),
+            (10, 499, 520, 0),
+            (11, 738, 360, 0),
+            (12, 738, 366, 0),
+        ]
+            .iter()
+            .map(|&(day, cash, holdings, flows)| (date(day), Decimal::from(cash), Decimal::from(holdings), Decimal::from(flows)))
+            .collect();
+        assert_eq!(values, expected);
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::store::Store;
+use super::refresh_snapshots;
+
+/// How often today's snapshot of every portfolio is brought up to date
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Starts the background task that keeps the daily valuation snapshots current
+pub fn spawn_snapshotter(store: Arc<dyn Store>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+        loop {
+            interval.tick().await;
+            snapshot_all(store.as_ref()).await;
+        }
+    });
+}
+
+async fn snapshot_all(store: &dyn Store) {
+    let portfolios = match store.list_portfolios(None).await {
+        Ok(portfolios) => portfolios,
+        Err(e) => {
+            eprintln!("Portfolio snapshots failed to list portfolios: {}", e);
+            return;
+        },
+    };
+
+    for portfolio in portfolios {
+        if let Err(e) = refresh_snapshots(store, portfolio.portfolio_id).await {
+            eprintln!("Portfolio {} snapshots failed: {}", portfolio.portfolio_id, e);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::models::{round_to_scale, OrderSide, RuleViolation, MONEY_SCALE};
+use crate::store::{PreTradeSnapshot, Store};
+
+mod rules;
+
+use rules::RULES;
+
+/// A trade or order about to be handed to the store
+pub struct ProposedTrade {
+    pub portfolio_id: i32,
+    pub asset_id: i32,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    /// Price the trade is expected at; the asset's current price when absent
+    pub price: Option<Decimal>,
+}
+
+impl ProposedTrade {
+    /// Value of the trade at its price, or at the asset's current price
+    pub fn notional(&self, snapshot: &PreTradeSnapshot) -> Decimal {
+        round_to_scale(self.quantity * self.price.unwrap_or(snapshot.price), MONEY_SCALE)
+    }
+}
+
+impl From<RuleViolation> for ApiError {
+    fn from(violation: RuleViolation) -> Self {
+        ApiError::TradeRejected(violation.message).with_details(json!({
+            "rule": violation.rule,
+            "limit": violation.limit,
+            "actual": violation.actual,
+        }))
+    }
+}
+
+/// Runs the trade through every rule in order; the first one it breaks rejects it
+pub fn evaluate(trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+    RULES.iter().try_for_each(|rule| rule.check(trade, snapshot))
+}
+
+/// Runs the trade through every rule and collects all the ones it breaks
+pub fn violations(trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Vec<RuleViolation> {
+    RULES.iter().filter_map(|rule| rule.check(trade, snapshot).err()).collect()
+}
+
+/// Reads the portfolio's limits and state, then evaluates the trade against them
+pub async fn check(store: &dyn Store, trade: &ProposedTrade) -> Result<(), ApiError> {
+    let snapshot = store.pre_trade_snapshot(trade.portfolio_id, trade.asset_id).await?;
+    evaluate(trade, &snapshot)?;
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use rust_decimal::Decimal;
+
+use crate::models::{round_to_scale, OrderSide, RuleViolation, MONEY_SCALE};
+use crate::store::PreTradeSnapshot;
+use super::ProposedTrade;
+
+/// One pre-trade check; passes when the limit it enforces is not set
+pub trait Rule: Sync {
+    fn check(&self, trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation>;
+}
+
+/// The pipeline, in the order rules are evaluated
+pub const RULES: &[&dyn Rule] = &[
+    &QuantityIncrementRule,
+    &StalePriceRule,
+    &MaxOrderNotionalRule,
+    &MaxDailyVolumeRule,
+    &MaxPositionConcentrationRule,
+];
+
+/// Quantities must be a multiple of the asset type's increment,
+/// so stocks trade in whole shares while crypto can be fractional
+pub struct QuantityIncrementRule;
+
+impl Rule for QuantityIncrementRule {
+    fn check(&self, trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+        let Some(increment) = snapshot.quantity_increment else {
+            return Ok(());
+        };
+        if (trade.quantity % increment).is_zero() {
+            return Ok(());
+        }
+        Err(RuleViolation {
+            rule: "quantity_increment".to_string(),
+            message: format!(
+                "{} quantities must be a multiple of {}",
+                snapshot.asset_type, increment.normalize()
+            ),
+            limit: increment,
+            actual: trade.quantity,
+        })
+    }
+}
+
+/// No trading on a price that has not been updated recently
+pub struct StalePriceRule;
+
+impl Rule for StalePriceRule {
+    fn check(&self, _trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+        let Some(max_minutes) = snapshot.limits.max_price_age_minutes else {
+            return Ok(());
+        };
+        if snapshot.price_age_secs <= i64::from(max_minutes) * 60 {
+            return Ok(());
+        }
+        let age_minutes = round_to_scale(Decimal::from(snapshot.price_age_secs) / Decimal::from(60), MONEY_SCALE);
+        Err(RuleViolation {
+            rule: "stale_price".to_string(),
+            message: format!(
+                "The asset's price was last updated {} minutes ago, more than the {} minutes allowed",
+                age_minutes, max_minutes
+            ),
+            limit: Decimal::from(max_minutes),
+            actual: age_minutes,
+        })
+    }
+}
+
+pub struct MaxOrderNotionalRule;
+
+impl Rule for MaxOrderNotionalRule {
+    fn check(&self, trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+        let Some(max_notional) = snapshot.limits.max_order_notional else {
+            return Ok(());
+        };
+        let notional = trade.notional(snapshot);
+        if notional <= max_notional {
+            return Ok(());
+        }
+        Err(RuleViolation {
+            rule: "max_order_notional".to_string(),
+            message: format!("Trade value {} exceeds the limit of {} per trade", notional, max_notional),
+            limit: max_notional,
+            actual: notional,
+        })
+    }
+}
+
+/// Buys and sells both count towards the day's volume
+pub struct MaxDailyVolumeRule;
+
+impl Rule for MaxDailyVolumeRule {
+    fn check(&self, trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+        let Some(max_volume) = snapshot.limits.max_daily_volume else {
+            return Ok(());
+        };
+        let volume = snapshot.traded_today + trade.notional(snapshot);
+        if volume <= max_volume {
+            return Ok(());
+        }
+        Err(RuleViolation {
+            rule: "max_daily_volume".to_string(),
+            message: format!(
+                "Trading {} today would exceed the daily limit of {} ({} already traded)",
+                volume, max_volume, snapshot.traded_today
+            ),
+            limit: max_volume,
+            actual: volume,
+        })
+    }
+}
+
+/// Only buys can concentrate a portfolio, so sells always pass
+pub struct MaxPositionConcentrationRule;
+
+impl Rule for MaxPositionConcentrationRule {
+    fn check(&self, trade: &ProposedTrade, snapshot: &PreTradeSnapshot) -> Result<(), RuleViolation> {
+        let Some(max_pct) = snapshot.limits.max_position_pct else {
+            return Ok(());
+        };
+        if trade.side == OrderSide::Sell || snapshot.portfolio_value <= Decimal::ZERO {
+            return Ok(());
+        }
+
+        // Buying moves cash into the position, so the portfolio's value stays the same
+        let position = snapshot.quantity_held * snapshot.price + trade.notional(snapshot);
+        let pct = round_to_scale(position / snapshot.portfolio_value * Decimal::ONE_HUNDRED, MONEY_SCALE);
+        if pct <= max_pct {
+            return Ok(());
+        }
+        Err(RuleViolation {
+            rule: "max_position_concentration".to_string(),
+            message: format!(
+                "The position would be {}% of the portfolio, more than the {}% allowed",
+                pct, max_pct.normalize()
+            ),
+            limit: max_pct,
+            actual: pct,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradingLimits;
+
+    /// A 1000 portfolio holding nothing of a stock priced 10, just updated
+    fn snapshot(limits: TradingLimits) -> PreTradeSnapshot {
+        PreTradeSnapshot {
+            limits,
+            asset_type: "Stock".to_string(),
+            price: Decimal::from(10),
+            price_age_secs: 0,
+            quantity_increment: Some(Decimal::ONE),
+            quantity_held: Decimal::ZERO,
+            portfolio_value: Decimal::from(1000),
+            traded_today: Decimal::ZERO,
+        }
+    }
+
+    fn trade(side: OrderSide, quantity: Decimal) -> ProposedTrade {
+        ProposedTrade { portfolio_id: 1, asset_id: 1, side, quantity, price: None }
+    }
+
+    fn buy(quantity: i64) -> ProposedTrade {
+        trade(OrderSide::Buy, Decimal::from(quantity))
+    }
+
+    fn broken_rule(result: Result<(), RuleViolation>) -> String {
+        result.expect_err("the trade should have been rejected").rule
+    }
+
+    #[test]
+    fn unset_limits_pass_everything() {
+        let snapshot = PreTradeSnapshot { quantity_increment: None, ..snapshot(TradingLimits::default()) };
+        let trade = trade(OrderSide::Buy, Decimal::new(12345, 3));
+        assert!(RULES.iter().all(|rule| rule.check(&trade, &snapshot).is_ok()));
+    }
+
+    #[test]
+    fn quantities_follow_the_increment() {
+        let snapshot = snapshot(TradingLimits::default());
+        assert!(QuantityIncrementRule.check(&buy(3), &snapshot).is_ok());
+        let violation = QuantityIncrementRule.check(&trade(OrderSide::Buy, Decimal::new(25, 1)), &snapshot).unwrap_err();
+        assert_eq!(violation.rule, "quantity_increment");
+        assert_eq!(violation.message, "Stock quantities must be a multiple of 1");
+    }
+
+    #[test]
+    fn a_price_exactly_as_old_as_allowed_is_fresh() {
+        let limits = TradingLimits { max_price_age_minutes: Some(60), ..Default::default() };
+        let at_limit = PreTradeSnapshot { price_age_secs: 3600, ..snapshot(limits.clone()) };
+        assert!(StalePriceRule.check(&buy(1), &at_limit).is_ok());
+
+        let past_limit = PreTradeSnapshot { price_age_secs: 3601, ..snapshot(limits) };
+        let violation = StalePriceRule.check(&buy(1), &past_limit).unwrap_err();
+        assert_eq!(violation.actual, Decimal::new(6002, 2));
+    }
+
+    #[test]
+    fn a_trade_worth_exactly_the_limit_passes() {
+        let snapshot = snapshot(TradingLimits { max_order_notional: Some(Decimal::from(100)), ..Default::default() });
+        assert!(MaxOrderNotionalRule.check(&buy(10), &snapshot).is_ok());
+
+        // 10 at 10.001 is 100.01 once rounded to cents
+        let over = ProposedTrade { price: Some(Decimal::new(10001, 3)), ..buy(10) };
+        assert_eq!(broken_rule(MaxOrderNotionalRule.check(&over, &snapshot)), "max_order_notional");
+    }
+
+    #[test]
+    fn daily_volume_counts_the_trade_itself() {
+        let limits = TradingLimits { max_daily_volume: Some(Decimal::from(150)), ..Default::default() };
+        let at_limit = PreTradeSnapshot { traded_today: Decimal::from(50), ..snapshot(limits.clone()) };
+        assert!(MaxDailyVolumeRule.check(&buy(10), &at_limit).is_ok());
+        assert!(MaxDailyVolumeRule.check(&trade(OrderSide::Sell, Decimal::from(10)), &at_limit).is_ok());
+
+        let past_limit = PreTradeSnapshot { traded_today: Decimal::new(5001, 2), ..snapshot(limits) };
+        let violation = MaxDailyVolumeRule.check(&buy(10), &past_limit).unwrap_err();
+        assert_eq!(violation.actual, Decimal::new(15001, 2));
+    }
+
+    #[test]
+    fn a_position_may_reach_exactly_the_concentration_limit() {
+        let limits = TradingLimits { max_position_pct: Some(Decimal::from(25)), ..Default::default() };
+        let snapshot = PreTradeSnapshot { quantity_held: Decimal::from(15), ..snapshot(limits) };
+
+        // 150 held plus 100 bought is 25% of 1000
+        assert!(MaxPositionConcentrationRule.check(&buy(10), &snapshot).is_ok());
+        let violation = MaxPositionConcentrationRule.check(&buy(11), &snapshot).unwrap_err();
+        assert_eq!(violation.actual, Decimal::from(26));
+        // Selling only ever lowers concentration
+        assert!(MaxPositionConcentrationRule.check(&trade(OrderSide::Sell, Decimal::from(100)), &snapshot).is_ok());
+    }
+}
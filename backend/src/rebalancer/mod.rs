@@ -0,0 +1,352 @@
+use rust_decimal::Decimal;
+
+use crate::error::ApiError;
+use crate::models::{
+    round_to_scale, AllocationDrift, AllocationTargets, BatchLeg, BatchOrderRequest, DriftReport,
+    OrderSide, RebalancePlan, RebalanceRequest, RebalanceTrade, MONEY_SCALE,
+};
+use crate::orders;
+use crate::store::{RebalancePosition, RebalanceSnapshot, Store};
+
+mod trades;
+
+use trades::plan_trades;
+
+/// Times the plan is redone to leave room for the fees of the previous attempt
+const FEE_PASSES: usize = 5;
+
+/// What a group of positions is measured against: one asset, or every asset of a type
+#[derive(Clone, PartialEq, Eq)]
+enum GroupKey {
+    Asset(i32),
+    AssetType(String),
+}
+
+/// The positions one target covers; untargeted holdings get a target of 0
+struct Group<'a> {
+    key: GroupKey,
+    target_weight: Decimal,
+    members: Vec<&'a RebalancePosition>,
+}
+
+impl Group<'_> {
+    fn current_value(&self) -> Decimal {
+        self.members.iter().map(|p| p.quantity_held * p.price).sum()
+    }
+}
+
+fn total_value(snapshot: &RebalanceSnapshot) -> Decimal {
+    snapshot.cash + snapshot.positions.iter().map(|p| p.quantity_held * p.price).sum::<Decimal>()
+}
+
+fn pct(value: Decimal, total: Decimal) -> Decimal {
+    if total > Decimal::ZERO {
+        value / total * Decimal::ONE_HUNDRED
+    } else {
+        Decimal::ZERO
+    }
+}
+
+/// Splits the portfolio into the targets' groups, followed by the holdings no target covers
+fn groups<'a>(targets: &AllocationTargets, snapshot: &'a RebalanceSnapshot) -> Vec<Group<'a>> {
+    let per_asset = targets.targets.iter().all(|t| t.asset_id.is_some());
+    let key_of = |position: &RebalancePosition| match per_asset {
+        true => GroupKey::Asset(position.asset_id),
+        false => GroupKey::AssetType(position.asset_type.clone()),
+    };
+
+    let mut groups: Vec<Group> = targets.targets.iter()
+        .map(|t| Group {
+            key: match (t.asset_id, &t.asset_type) {
+                (Some(asset_id), _) => GroupKey::Asset(asset_id),
+                (None, asset_type) => GroupKey::AssetType(asset_type.clone().unwrap_or_default()),
+            },
+            target_weight: t.weight,
+            members: Vec::new(),
+        })
+        .collect();
+
+    for position in &snapshot.positions {
+        let key = key_of(position);
+        match groups.iter_mut().find(|g| g.key == key) {
+            Some(group) => group.members.push(position),
+            None if position.quantity_held > Decimal::ZERO => groups.push(Group {
+                key,
+                target_weight: Decimal::ZERO,
+                members: vec![position],
+            }),
+            None => {},
+        }
+    }
+    groups
+}
+
+/// Measures each group against its target at current prices
+pub fn drift(targets: &AllocationTargets, snapshot: &RebalanceSnapshot) -> DriftReport {
+    let total = total_value(snapshot);
+    let tolerance = targets.drift_tolerance_pct;
+
+    let allocations: Vec<AllocationDrift> = groups(targets, snapshot).iter()
+        .map(|group| {
+            let current_value = group.current_value();
+            let drift = round_to_scale(pct(current_value, total) - group.target_weight, MONEY_SCALE);
+            let (asset_id, symbol, asset_type) = match &group.key {
+                GroupKey::Asset(asset_id) => (
+                    Some(*asset_id),
+                    group.members.first().map(|p| p.symbol.clone()),
+                    group.members.first().map(|p| p.asset_type.clone()),
+                ),
+                GroupKey::AssetType(asset_type) => (None, None, Some(asset_type.clone())),
+            };
+            AllocationDrift {
+                asset_id,
+                symbol,
+                asset_type,
+                target_weight: group.target_weight,
+                current_weight: round_to_scale(pct(current_value, total), MONEY_SCALE),
+                drift,
+                current_value: round_to_scale(current_value, MONEY_SCALE),
+                target_value: round_to_scale(total * group.target_weight / Decimal::ONE_HUNDRED, MONEY_SCALE),
+                within_tolerance: drift.abs() <= tolerance,
+            }
+        })
+        .collect();
+
+    let target_total: Decimal = targets.targets.iter().map(|t| t.weight).sum();
+    DriftReport {
+        portfolio_id: targets.portfolio_id,
+        total_value: round_to_scale(total, MONEY_SCALE),
+        cash: snapshot.cash,
+        cash_weight: round_to_scale(pct(snapshot.cash, total), MONEY_SCALE),
+        cash_target_weight: Decimal::ONE_HUNDRED - target_total,
+        drift_tolerance_pct: tolerance,
+        needs_rebalance: allocations.iter().any(|a| !a.within_tolerance),
+        allocations,
+    }
+}
+
+async fn targets_of(store: &dyn Store, portfolio_id: i32) -> Result<AllocationTargets, ApiError> {
+    let targets = store.allocation_targets(portfolio_id).await?;
+    if targets.targets.is_empty() {
+        return Err(ApiError::NotFound("No allocation targets set for this portfolio".to_string()));
+    }
+    Ok(targets)
+}
+
+/// Reads the portfolio's targets and positions and reports the drift between them
+pub async fn drift_report(store: &dyn Store, portfolio_id: i32) -> Result<DriftReport, ApiError> {
+    let targets = targets_of(store, portfolio_id).await?;
+    let snapshot = store.rebalance_snapshot(portfolio_id).await?;
+    Ok(drift(&targets, &snapshot))
+}
+
+/// Works out the trades that bring every drifted group back to its target and,
+/// when asked, executes them as one batch order.
+///
+/// Fees are quoted for each trade and held back from the cash the buys may
+/// spend; since smaller buys cost less in fees, a few passes settle the plan.
+pub async fn rebalance(store: &dyn Store, portfolio_id: i32, request: &RebalanceRequest) -> Result<RebalancePlan, ApiError> {
+    let targets = targets_of(store, portfolio_id).await?;
+    let snapshot = store.rebalance_snapshot(portfolio_id).await?;
+    let groups = groups(&targets, &snapshot);
+    let total = total_value(&snapshot);
+
+    let mut fee_reserve = Decimal::ZERO;
+    let mut planned = Vec::new();
+    let mut notes = Vec::new();
+    let mut fees = Vec::new();
+    for _ in 0..FEE_PASSES {
+        (planned, notes) = plan_trades(&groups, &targets, total, snapshot.cash, fee_reserve);
+
+        fees.clear();
+        for trade in &planned {
+            fees.push(store.quote_trading_fee(portfolio_id, trade.position.asset_id, trade.value()).await?);
+        }
+        let total_fees: Decimal = fees.iter().copied().sum();
+        if total_fees <= fee_reserve {
+            break;
+        }
+        fee_reserve = total_fees;
+    }
+
+    let trades: Vec<RebalanceTrade> = planned.iter().zip(&fees)
+        .map(|(trade, fee)| RebalanceTrade {
+            asset_id: trade.position.asset_id,
+            symbol: trade.position.symbol.clone(),
+            side: trade.side,
+            quantity: trade.quantity,
+            price: trade.position.price,
+            value: trade.value(),
+            estimated_fee: *fee,
+        })
+        .collect();
+
+    let projected_cash = trades.iter().fold(snapshot.cash, |cash, trade| match trade.side {
+        OrderSide::Sell => cash + trade.value - trade.estimated_fee,
+        OrderSide::Buy => cash - trade.value - trade.estimated_fee,
+    });
+
+    let execution = match request.execute && !trades.is_empty() {
+        true => {
+            let batch = BatchOrderRequest {
+                legs: trades.iter()
+                    .map(|trade| BatchLeg {
+                        asset_id: trade.asset_id,
+                        side: trade.side,
+                        quantity: trade.quantity,
+                        unit_price: None,
+                        lots: None,
+                    })
+                    .collect(),
+                all_or_nothing: request.all_or_nothing,
+            };
+            Some(orders::execute_batch(store, portfolio_id, &batch).await?)
+        },
+        false => None,
+    };
+
+    Ok(RebalancePlan {
+        portfolio_id,
+        drift: drift(&targets, &snapshot),
+        trades,
+        projected_cash,
+        notes,
+        execution,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AllocationTarget;
+
+    fn position(asset_id: i32, asset_type: &str, held: Decimal, price: i64, increment: Option<Decimal>) -> RebalancePosition {
+        RebalancePosition {
+            asset_id,
+            symbol: format!("A{}", asset_id),
+            asset_type: asset_type.to_string(),
+            quantity_held: held,
+            price: Decimal::from(price),
+            quantity_increment: increment,
+        }
+    }
+
+    fn per_asset(weights: &[(i32, i64)], min_trade_value: i64) -> AllocationTargets {
+        AllocationTargets {
+            portfolio_id: 1,
+            drift_tolerance_pct: Decimal::from(5),
+            min_trade_value: Decimal::from(min_trade_value),
+            targets: weights.iter()
+                .map(|(asset_id, weight)| AllocationTarget {
+                    asset_id: Some(*asset_id),
+                    symbol: None,
+                    asset_type: None,
+                    weight: Decimal::from(*weight),
+                })
+                .collect(),
+            updated_at: None,
+        }
+    }
+
+    /// Plans against the snapshot as `rebalance` would, with a fixed fee reserve
+    fn plan(targets: &AllocationTargets, snapshot: &RebalanceSnapshot, fee_reserve: i64) -> (Vec<(i32, OrderSide, Decimal)>, Vec<String>) {
+        let groups = groups(targets, snapshot);
+        let (trades, notes) = plan_trades(&groups, targets, total_value(snapshot), snapshot.cash, Decimal::from(fee_reserve));
+        let trades = trades.iter().map(|t| (t.position.asset_id, t.side, t.quantity)).collect();
+        (trades, notes)
+    }
+
+    #[test]
+    fn a_cash_only_portfolio_buys_into_every_target() {
+        let snapshot = RebalanceSnapshot {
+            cash: Decimal::from(1000),
+            positions: vec![
+                position(1, "Stock", Decimal::ZERO, 30, Some(Decimal::ONE)),
+                position(2, "Cryptocurrency", Decimal::ZERO, 7, Some(Decimal::new(1, 4))),
+            ],
+        };
+        let targets = per_asset(&[(1, 50), (2, 25)], 0);
+
+        let report = drift(&targets, &snapshot);
+        assert_eq!(report.total_value, Decimal::from(1000));
+        assert_eq!(report.cash_weight, Decimal::from(100));
+        assert_eq!(report.cash_target_weight, Decimal::from(25));
+        assert!(report.needs_rebalance);
+
+        // 500 / 30 rounds down to whole shares, 250 / 7 to the crypto increment
+        let (trades, notes) = plan(&targets, &snapshot, 0);
+        assert_eq!(trades, [
+            (1, OrderSide::Buy, Decimal::from(16)),
+            (2, OrderSide::Buy, Decimal::new(357142, 4)),
+        ]);
+        assert!(notes.is_empty(), "{notes:?}");
+    }
+
+    #[test]
+    fn a_cash_only_portfolio_cannot_pick_an_asset_for_a_type_target() {
+        let snapshot = RebalanceSnapshot { cash: Decimal::from(1000), positions: Vec::new() };
+        let targets = AllocationTargets {
+            targets: vec![AllocationTarget {
+                asset_id: None,
+                symbol: None,
+                asset_type: Some("Stock".to_string()),
+                weight: Decimal::from(60),
+            }],
+            ..per_asset(&[], 0)
+        };
+
+        let (trades, notes) = plan(&targets, &snapshot, 0);
+        assert!(trades.is_empty());
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].starts_with("No Stock asset is held"), "{}", notes[0]);
+    }
+
+    #[test]
+    fn buys_scaled_to_the_cash_left_round_down() {
+        let snapshot = RebalanceSnapshot {
+            cash: Decimal::from(100),
+            positions: vec![position(1, "Stock", Decimal::ZERO, 30, Some(Decimal::ONE))],
+        };
+        let targets = per_asset(&[(1, 100)], 0);
+
+        // 3 shares for 90 fit; once 15 is held back for fees, 85 covers only 2
+        assert_eq!(plan(&targets, &snapshot, 0).0, [(1, OrderSide::Buy, Decimal::from(3))]);
+        let (trades, notes) = plan(&targets, &snapshot, 15);
+        assert_eq!(trades, [(1, OrderSide::Buy, Decimal::from(2))]);
+        assert_eq!(notes, ["Buys scaled down to 94.44% to fit the 85 in cash available"]);
+    }
+
+    #[test]
+    fn untargeted_holdings_are_sold_whole() {
+        let snapshot = RebalanceSnapshot {
+            cash: Decimal::from(10),
+            positions: vec![
+                position(1, "Stock", Decimal::from(10), 10, Some(Decimal::ONE)),
+                // Half a share left over, below the whole-share increment
+                position(2, "Stock", Decimal::new(25, 1), 40, Some(Decimal::ONE)),
+            ],
+        };
+        let targets = per_asset(&[(1, 100)], 0);
+
+        // Selling all of asset 2 raises 100, enough for 11 more of asset 1
+        let (trades, notes) = plan(&targets, &snapshot, 0);
+        assert_eq!(trades, [
+            (2, OrderSide::Sell, Decimal::new(25, 1)),
+            (1, OrderSide::Buy, Decimal::from(11)),
+        ]);
+        assert!(notes.is_empty(), "{notes:?}");
+    }
+
+    #[test]
+    fn trades_below_the_minimum_are_skipped() {
+        let snapshot = RebalanceSnapshot {
+            cash: Decimal::from(1000),
+            positions: vec![position(1, "Stock", Decimal::ZERO, 30, Some(Decimal::ONE))],
+        };
+        let targets = per_asset(&[(1, 6)], 100);
+
+        let (trades, notes) = plan(&targets, &snapshot, 0);
+        assert!(trades.is_empty());
+        assert_eq!(notes, ["Skipped buying 2 A1 worth 60, below the minimum trade value of 100"]);
+    }
+}
@@ -0,0 +1,146 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::models::{round_to_scale, AllocationTargets, OrderSide, MONEY_SCALE, QUANTITY_SCALE};
+use crate::store::RebalancePosition;
+use super::{pct, Group, GroupKey};
+
+/// A trade of the plan, before fees are quoted
+pub struct PlannedTrade<'a> {
+    pub position: &'a RebalancePosition,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+}
+
+impl PlannedTrade<'_> {
+    pub fn value(&self) -> Decimal {
+        round_to_scale(self.quantity * self.position.price, MONEY_SCALE)
+    }
+}
+
+/// Rounds a quantity down to what the asset type can trade in
+fn tradable(quantity: Decimal, increment: Option<Decimal>) -> Decimal {
+    match increment {
+        Some(increment) if increment > Decimal::ZERO => (quantity / increment).floor() * increment,
+        _ => quantity.round_dp_with_strategy(QUANTITY_SCALE, RoundingStrategy::ToZero),
+    }
+    .normalize()
+}
+
+/// Turns a change in value into a trade of one position, rounded down so it never overshoots
+fn trade_for<'a>(position: &'a RebalancePosition, delta: Decimal) -> Option<PlannedTrade<'a>> {
+    if position.price <= Decimal::ZERO {
+        return None;
+    }
+    let (side, quantity) = if delta < Decimal::ZERO {
+        let held_value = position.quantity_held * position.price;
+        // Selling a position down to nothing takes all of it, whatever the increment
+        let quantity = match -delta >= held_value {
+            true => position.quantity_held,
+            false => tradable(-delta / position.price, position.quantity_increment).min(position.quantity_held),
+        };
+        (OrderSide::Sell, quantity)
+    } else {
+        (OrderSide::Buy, tradable(delta / position.price, position.quantity_increment))
+    };
+    (quantity > Decimal::ZERO).then_some(PlannedTrade { position, side, quantity })
+}
+
+/// Splits a group's change in value over its positions in proportion to their value.
+/// A group with nothing held can only be bought into when it is a single asset
+fn spread<'a>(group: &Group<'a>, delta: Decimal, notes: &mut Vec<String>) -> Vec<(&'a RebalancePosition, Decimal)> {
+    let held: Vec<&RebalancePosition> = group.members.iter()
+        .copied()
+        .filter(|p| p.quantity_held > Decimal::ZERO)
+        .collect();
+    let held_value: Decimal = held.iter().map(|p| p.quantity_held * p.price).sum();
+
+    match (&group.key, held_value > Decimal::ZERO) {
+        (GroupKey::Asset(_), _) => group.members.iter().map(|p| (*p, delta)).collect(),
+        (GroupKey::AssetType(_), true) => held.iter()
+            .map(|p| (*p, delta * (p.quantity_held * p.price) / held_value))
+            .collect(),
+        (GroupKey::AssetType(asset_type), false) => {
+            notes.push(format!(
+                "No {} asset is held, so the plan cannot choose which one to buy; buy one to start that allocation",
+                asset_type
+            ));
+            Vec::new()
+        },
+    }
+}
+
+/// Drops the trades worth less than the portfolio's minimum, noting each one
+fn above_minimum(trades: &mut Vec<PlannedTrade>, min_trade_value: Decimal, notes: &mut Vec<String>) {
+    trades.retain(|trade| {
+        let keep = trade.value() >= min_trade_value;
+        if !keep {
+            notes.push(format!(
+                "Skipped {} {} {} worth {}, below the minimum trade value of {}",
+                match trade.side { OrderSide::Buy => "buying", OrderSide::Sell => "selling" },
+                trade.quantity,
+                trade.position.symbol,
+                trade.value(),
+                min_trade_value
+            ));
+        }
+        keep
+    });
+}
+
+/// Trades that bring every group outside the tolerance back to its target, sells first.
+///
+/// Buys are scaled down when the cash on hand plus what the sells raise, less
+/// `fee_reserve`, does not cover them; trades below the minimum value are dropped.
+pub fn plan_trades<'a>(
+    groups: &[Group<'a>],
+    targets: &AllocationTargets,
+    total: Decimal,
+    cash: Decimal,
+    fee_reserve: Decimal,
+) -> (Vec<PlannedTrade<'a>>, Vec<String>) {
+    let mut notes = Vec::new();
+    let mut sells = Vec::new();
+    let mut buys = Vec::new();
+
+    for group in groups {
+        let current_value = group.current_value();
+        let drift = round_to_scale(pct(current_value, total) - group.target_weight, MONEY_SCALE);
+        if drift.abs() <= targets.drift_tolerance_pct {
+            continue;
+        }
+        let delta = total * group.target_weight / Decimal::ONE_HUNDRED - current_value;
+        for (position, delta) in spread(group, delta, &mut notes) {
+            match trade_for(position, delta) {
+                Some(trade) if trade.side == OrderSide::Sell => sells.push(trade),
+                Some(trade) => buys.push(trade),
+                None => {},
+            }
+        }
+    }
+
+    above_minimum(&mut sells, targets.min_trade_value, &mut notes);
+    let raised: Decimal = sells.iter().map(|t| t.value()).sum();
+    let available = cash + raised - fee_reserve;
+    let wanted: Decimal = buys.iter().map(|t| t.value()).sum();
+    if wanted > available {
+        if available <= Decimal::ZERO {
+            notes.push("Not enough cash for any of the buys".to_string());
+            buys.clear();
+        } else {
+            let factor = available / wanted;
+            notes.push(format!(
+                "Buys scaled down to {}% to fit the {} in cash available",
+                round_to_scale(factor * Decimal::ONE_HUNDRED, MONEY_SCALE),
+                round_to_scale(available, MONEY_SCALE)
+            ));
+            for trade in &mut buys {
+                trade.quantity = tradable(trade.quantity * factor, trade.position.quantity_increment);
+            }
+            buys.retain(|t| t.quantity > Decimal::ZERO);
+        }
+    }
+
+    above_minimum(&mut buys, targets.min_trade_value, &mut notes);
+    sells.extend(buys);
+    (sells, notes)
+}
@@ -0,0 +1,125 @@
+//! Co-movement of several assets' returns. Assets trade on different
+//! calendars (crypto every day, stocks on weekdays), so returns are only
+//! taken between days on which all of them have a close; a weekend's crypto
+//! move lands in Monday's return instead of pairing with stocks standing still.
+
+use chrono::NaiveDate;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Variance below which an asset counts as not moving at all
+const FLAT: f64 = 1e-18;
+
+/// Returns of every asset between consecutive days on which all of them
+/// have a close, indexed `[day][asset]`, along with the day each return ends on
+pub fn aligned_returns(closes: &[&[(NaiveDate, Decimal)]]) -> (Vec<NaiveDate>, Vec<Vec<f64>>) {
+    let Some((first, others)) = closes.split_first() else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut positions = vec![0; others.len()];
+    let mut previous: Option<Vec<Decimal>> = None;
+    let (mut days, mut returns) = (Vec::new(), Vec::new());
+    for (day, close) in first.iter() {
+        let mut row = vec![*close];
+        for (series, position) in others.iter().zip(&mut positions) {
+            *position += series[*position..].partition_point(|(date, _)| date < day);
+            match series.get(*position) {
+                Some((date, close)) if date == day => row.push(*close),
+                _ => break,
+            }
+        }
+        if row.len() < closes.len() {
+            continue;
+        }
+
+        if let Some(previous) = previous.replace(row.clone()) {
+            let growth: Option<Vec<f64>> = previous.iter().zip(&row)
+                .map(|(before, after)| {
+                    if *before <= Decimal::ZERO {
+                        return None;
+                    }
+                    (after / before).to_f64().map(|growth| growth - 1.0)
+                })
+                .collect();
+            if let Some(growth) = growth {
+                days.push(*day);
+                returns.push(growth);
+            }
+        }
+    }
+    (days, returns)
+}
+
+/// Correlation matrix from a covariance matrix. An asset whose price never
+/// moved has no correlation with anything, itself included.
+pub fn correlation(covariance: &[Vec<f64>]) -> Vec<Vec<Option<f64>>> {
+    covariance.iter().enumerate()
+        .map(|(i, row)| {
+            row.iter().enumerate()
+                .map(|(j, covariance_ij)| {
+                    let (variance_i, variance_j) = (covariance[i][i], covariance[j][j]);
+                    (variance_i > FLAT && variance_j > FLAT)
+                        .then(|| (covariance_ij / (variance_i * variance_j).sqrt()).clamp(-1.0, 1.0))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk::metrics::moments;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn closes(prices: &[(u32, i64)]) -> Vec<(NaiveDate, Decimal)> {
+        prices.iter().map(|(d, price)| (day(*d), Decimal::from(*price))).collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn returns_span_the_days_every_asset_traded() {
+        // Friday 7th to Monday 10th: the coin trades through the weekend
+        let coin = closes(&[(7, 100), (8, 110), (9, 120), (10, 150), (11, 135)]);
+        let stock = closes(&[(6, 40), (7, 50), (10, 55), (11, 66)]);
+        let (days, returns) = aligned_returns(&[&coin, &stock]);
+
+        assert_eq!(days, [day(10), day(11)]);
+        let expected = [[0.5, 0.1], [-0.1, 0.2]];
+        for (row, expected) in returns.iter().zip(expected) {
+            for (actual, expected) in row.iter().zip(expected) {
+                assert_close(*actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn correlation_is_scaled_covariance() {
+        let returns = vec![
+            vec![0.01, 0.02, 0.0],
+            vec![0.03, 0.06, 0.0],
+            vec![-0.01, -0.02, 0.0],
+        ];
+        let (_, covariance) = moments(&returns, 3).unwrap();
+        let correlation = correlation(&covariance);
+
+        // Mean 0.01; deviations 0, 0.02, -0.02 over 2 degrees of freedom
+        assert_close(covariance[0][0], 0.0004);
+        assert_close(covariance[0][1], 0.0008);
+        assert_close(covariance[1][1], 0.0016);
+        assert_close(correlation[0][1].unwrap(), 1.0);
+        assert_close(correlation[1][0].unwrap(), 1.0);
+        assert_close(correlation[0][0].unwrap(), 1.0);
+        // The third asset never moves
+        assert_close(covariance[0][2], 0.0);
+        assert_eq!(correlation[0][2], None);
+        assert_eq!(correlation[2][2], None);
+    }
+}
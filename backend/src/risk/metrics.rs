@@ -0,0 +1,209 @@
+//! Risk measures over a series of daily returns, given as fractions
+//! (0.01 is 1%). The series runs over calendar days, so a year has 365
+//! periods; days a market is closed contribute a zero return.
+
+/// Periods in a year of daily, calendar-day returns
+pub const PERIODS_PER_YEAR: f64 = 365.0;
+
+/// Spread below which a series counts as flat, allowing for rounding
+const FLAT: f64 = 1e-12;
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Sample standard deviation; needs at least two values
+fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// Sample covariance of two series of the same length
+fn covariance(left: &[f64], right: &[f64]) -> Option<f64> {
+    if left.len() != right.len() || left.len() < 2 {
+        return None;
+    }
+    let (left_mean, right_mean) = (mean(left)?, mean(right)?);
+    let sum: f64 = left.iter().zip(right).map(|(l, r)| (l - left_mean) * (r - right_mean)).sum();
+    Some(sum / (left.len() - 1) as f64)
+}
+
+/// Daily excess returns over an annual risk-free rate
+fn excess_returns(returns: &[f64], risk_free_rate: f64) -> Vec<f64> {
+    let daily_rate = risk_free_rate / PERIODS_PER_YEAR;
+    returns.iter().map(|r| r - daily_rate).collect()
+}
+
+/// Growth over the whole series: 0.1 for a 10% gain
+pub fn cumulative_return(returns: &[f64]) -> f64 {
+    returns.iter().map(|r| 1.0 + r).product::<f64>() - 1.0
+}
+
+/// Compound annual growth rate of the series
+pub fn annualized_return(returns: &[f64]) -> Option<f64> {
+    let growth = 1.0 + cumulative_return(returns);
+    (!returns.is_empty() && growth > 0.0)
+        .then(|| growth.powf(PERIODS_PER_YEAR / returns.len() as f64) - 1.0)
+}
+
+/// Standard deviation of daily returns scaled to a year
+pub fn annualized_volatility(returns: &[f64]) -> Option<f64> {
+    std_dev(returns).map(|std_dev| std_dev * PERIODS_PER_YEAR.sqrt())
+}
+
+/// Annualized mean excess return per unit of volatility; None for a flat series
+pub fn sharpe_ratio(returns: &[f64], risk_free_rate: f64) -> Option<f64> {
+    let std_dev = std_dev(returns).filter(|s| *s > FLAT)?;
+    let excess = mean(&excess_returns(returns, risk_free_rate))?;
+    Some(excess / std_dev * PERIODS_PER_YEAR.sqrt())
+}
+
+/// Like the Sharpe ratio, but only returns below the risk-free rate count
+/// as risk; None when there are none
+pub fn sortino_ratio(returns: &[f64], risk_free_rate: f64) -> Option<f64> {
+    let excess = excess_returns(returns, risk_free_rate);
+    let downside: Vec<f64> = excess.iter().map(|r| r.min(0.0).powi(2)).collect();
+    let downside_deviation = mean(&downside)?.sqrt();
+    if downside_deviation == 0.0 {
+        return None;
+    }
+    Some(mean(&excess)? / downside_deviation * PERIODS_PER_YEAR.sqrt())
+}
+
+/// Largest fall from a running peak of the growth index, as a negative
+/// fraction; zero when the series never falls
+pub fn max_drawdown(returns: &[f64]) -> f64 {
+    let mut index = 1.0;
+    let mut peak = 1.0_f64;
+    let mut drawdown = 0.0_f64;
+    for r in returns {
+        index *= 1.0 + r;
+        peak = peak.max(index);
+        drawdown = drawdown.min(index / peak - 1.0);
+    }
+    drawdown
+}
+
+/// Annualized return per unit of maximum drawdown; None without a drawdown
+pub fn calmar_ratio(returns: &[f64]) -> Option<f64> {
+    let drawdown = max_drawdown(returns);
+    if drawdown == 0.0 {
+        return None;
+    }
+    Some(annualized_return(returns)? / drawdown.abs())
+}
+
+/// Sensitivity of the portfolio to the benchmark, from returns paired by day
+pub fn beta(portfolio: &[f64], benchmark: &[f64]) -> Option<f64> {
+    let variance = std_dev(benchmark).filter(|s| *s > FLAT).map(|s| s * s)?;
+    Some(covariance(portfolio, benchmark)? / variance)
+}
+
+/// Mean return of each asset and their sample covariance matrix, from
+/// returns indexed `[day][asset]`; needs at least two days
+pub fn moments(returns: &[Vec<f64>], assets: usize) -> Option<(Vec<f64>, Vec<Vec<f64>>)> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let days = returns.len() as f64;
+    let means: Vec<f64> = (0..assets)
+        .map(|asset| returns.iter().map(|day| day[asset]).sum::<f64>() / days)
+        .collect();
+    let covariance = (0..assets)
+        .map(|i| {
+            (0..assets)
+                .map(|j| {
+                    returns.iter().map(|day| (day[i] - means[i]) * (day[j] - means[j])).sum::<f64>() / (days - 1.0)
+                })
+                .collect()
+        })
+        .collect();
+    Some((means, covariance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Up 10%, down 5%, up 2%, down 3%
+    const RETURNS: [f64; 4] = [0.10, -0.05, 0.02, -0.03];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn cumulative_return_compounds() {
+        // 1.10 * 0.95 * 1.02 * 0.97
+        assert_close(cumulative_return(&RETURNS), 0.033_923);
+        assert_close(cumulative_return(&[]), 0.0);
+    }
+
+    #[test]
+    fn annualized_return_compounds_over_a_year() {
+        // 1% a day for a full year of days
+        let returns = [0.01; 365];
+        assert_close(annualized_return(&returns).unwrap(), 1.01_f64.powi(365) - 1.0);
+        // Half a year of returns is scaled up to a year
+        assert_close(annualized_return(&[0.0, 0.21]).unwrap(), 1.21_f64.powf(182.5) - 1.0);
+        assert_eq!(annualized_return(&[]), None);
+        assert_eq!(annualized_return(&[-1.0]), None);
+    }
+
+    #[test]
+    fn volatility_is_sample_std_dev_scaled_to_a_year() {
+        // Mean 0.01; squared deviations sum to 0.0134 over 3 degrees of freedom
+        let daily = (0.0134_f64 / 3.0).sqrt();
+        assert_close(annualized_volatility(&RETURNS).unwrap(), daily * 365_f64.sqrt());
+        assert_close(annualized_volatility(&[0.01; 10]).unwrap(), 0.0);
+        assert_eq!(annualized_volatility(&[0.01]), None);
+    }
+
+    #[test]
+    fn sharpe_ratio_uses_excess_over_the_daily_risk_free_rate() {
+        let daily = (0.0134_f64 / 3.0).sqrt();
+        assert_close(sharpe_ratio(&RETURNS, 0.0).unwrap(), 0.01 / daily * 365_f64.sqrt());
+        // 3.65% a year is 0.01% a day
+        assert_close(sharpe_ratio(&RETURNS, 0.0365).unwrap(), 0.0099 / daily * 365_f64.sqrt());
+        assert_eq!(sharpe_ratio(&[0.01; 10], 0.0), None);
+    }
+
+    #[test]
+    fn sortino_ratio_only_counts_losses_as_risk() {
+        // Downside deviation: sqrt((0.05² + 0.03²) / 4)
+        let downside = (0.0034_f64 / 4.0).sqrt();
+        assert_close(sortino_ratio(&RETURNS, 0.0).unwrap(), 0.01 / downside * 365_f64.sqrt());
+        assert_eq!(sortino_ratio(&[0.01, 0.02], 0.0), None);
+    }
+
+    #[test]
+    fn max_drawdown_is_measured_from_the_running_peak() {
+        // Peak 1.10 after day one, trough 1.033923 at the end
+        assert_close(max_drawdown(&RETURNS), 1.033_923 / 1.10 - 1.0);
+        assert_close(max_drawdown(&[0.01, 0.02]), 0.0);
+        // A fall from the start counts against the starting value
+        assert_close(max_drawdown(&[-0.5, 0.5]), -0.5);
+    }
+
+    #[test]
+    fn calmar_ratio_divides_annual_return_by_drawdown() {
+        let expected = (1.033_923_f64.powf(365.0 / 4.0) - 1.0) / (1.0 - 1.033_923 / 1.10);
+        assert_close(calmar_ratio(&RETURNS).unwrap() / expected, 1.0);
+        assert_eq!(calmar_ratio(&[0.01, 0.02]), None);
+    }
+
+    #[test]
+    fn beta_measures_moves_against_the_benchmark() {
+        let benchmark = [0.01, 0.02, -0.01, 0.0];
+        let doubled: Vec<f64> = benchmark.iter().map(|r| 2.0 * r + 0.001).collect();
+        assert_close(beta(&doubled, &benchmark).unwrap(), 2.0);
+        let inverse: Vec<f64> = benchmark.iter().map(|r| -0.5 * r).collect();
+        assert_close(beta(&inverse, &benchmark).unwrap(), -0.5);
+        assert_eq!(beta(&benchmark, &[0.01; 4]), None);
+        assert_eq!(beta(&benchmark, &[0.01]), None);
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{Days, NaiveDate};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{
+    round_to_scale, AssetCorrelation, CorrelationAsset, PortfolioVar, VarContribution, VarMethod, MONEY_SCALE,
+};
+use crate::performance;
+use crate::scheduler;
+use crate::store::{NewRiskMetrics, Store, StoreError, StoreResult};
+
+mod correlation;
+mod metrics;
+mod series;
+mod var;
+
+/// Benchmark that beta is measured against
+const BENCHMARK_SYMBOL: &str = "SPX";
+
+/// Annual risk-free rate for the Sharpe and Sortino ratios
+const RISK_FREE_RATE: f64 = 0.02;
+
+/// Days of price history Value-at-Risk is estimated from
+const VAR_LOOKBACK_DAYS: u64 = 365;
+
+/// Fewest days of returns, common to every holding, VaR is estimated from
+const MIN_VAR_OBSERVATIONS: usize = 30;
+
+/// `RiskMetrics` columns are DECIMAL(10,2)
+const MAX_STORED_VALUE: f64 = 99_999_999.99;
+
+/// Rounds to the two places `RiskMetrics` keeps; values it cannot hold,
+/// such as ratios annualized from a few days, are dropped
+fn stored(value: f64) -> Option<f64> {
+    let rounded = (value * 100.0).round() / 100.0;
+    (rounded.is_finite() && rounded.abs() <= MAX_STORED_VALUE).then_some(rounded)
+}
+
+fn risk_level(volatility: Option<f64>, beta: Option<f64>) -> &'static str {
+    let (volatility, beta) = (volatility.unwrap_or_default(), beta.unwrap_or(1.0));
+    if volatility <= 10.0 && beta <= 0.8 {
+        "Conservative"
+    } else if volatility >= 20.0 || beta >= 1.2 {
+        "Aggressive"
+    } else {
+        "Moderate"
+    }
+}
+
+/// Works out a user's risk metrics over the last `days_back` days from the
+/// daily value of all of their portfolios together, rebuilt from their
+/// holdings and transaction history and `AssetPrices` (premium users only)
+pub async fn calculate_user_risk(store: &dyn Store, user_id: Uuid, days_back: u32) -> StoreResult<NewRiskMetrics> {
+    if !store.get_user_extended(user_id).await?.is_premium {
+        return Err(StoreError::Forbidden("Risk metrics only available for premium users".to_string()));
+    }
+
+    let today = scheduler::today();
+    // The day before the window, so its first day has a return
+    let from = today - Days::new(u64::from(days_back));
+
+    let mut valuations = Vec::new();
+    for portfolio in store.list_portfolios(Some(user_id)).await? {
+        valuations.push(performance::daily_valuations(store, portfolio.portfolio_id, Some(from)).await?);
+    }
+    let daily = series::portfolio_returns(&performance::combine_snapshots(&valuations));
+    let returns: Vec<f64> = daily.iter().map(|(_, r)| *r).collect();
+
+    let beta = match store.find_asset_id(BENCHMARK_SYMBOL).await? {
+        Some(benchmark_id) => {
+            let closes = store.asset_daily_closes(&[benchmark_id], from, today).await?;
+            let days: Vec<_> = daily.iter().map(|(day, _)| *day).collect();
+            let benchmark = closes.first().map(|(_, closes)| series::asset_returns(closes, &days)).unwrap_or_default();
+            let (portfolio, benchmark): (Vec<f64>, Vec<f64>) = returns.iter().zip(benchmark)
+                .filter_map(|(portfolio, benchmark)| Some((*portfolio, benchmark?)))
+                .unzip();
+            metrics::beta(&portfolio, &benchmark)
+        },
+        None => None,
+    };
+
+    let volatility = metrics::annualized_volatility(&returns).map(|v| v * 100.0);
+    let has_returns = !returns.is_empty();
+    Ok(NewRiskMetrics {
+        maximum_drawdown: has_returns.then(|| metrics::max_drawdown(&returns) * 100.0).and_then(stored),
+        beta: beta.and_then(stored),
+        sharpe_ratio: metrics::sharpe_ratio(&returns, RISK_FREE_RATE).and_then(stored),
+        sortino_ratio: metrics::sortino_ratio(&returns, RISK_FREE_RATE).and_then(stored),
+        calmar_ratio: metrics::calmar_ratio(&returns).and_then(stored),
+        absolute_return: has_returns.then(|| metrics::cumulative_return(&returns) * 100.0).and_then(stored),
+        volatility_score: volatility.and_then(stored),
+        risk_level: risk_level(volatility, beta).to_string(),
+    })
+}
+
+fn money(value: f64) -> Decimal {
+    Decimal::from_f64(value).map_or(Decimal::ZERO, |value| round_to_scale(value, MONEY_SCALE))
+}
+
+fn percent_of(amount: Decimal, total: Decimal) -> Decimal {
+    if total.is_zero() {
+        return Decimal::ZERO;
+    }
+    round_to_scale(amount / total * Decimal::ONE_HUNDRED, MONEY_SCALE)
+}
+
+/// Value-at-Risk and expected shortfall of a portfolio's current holdings
+/// over `horizon` days, from the last year of daily returns in `AssetPrices`.
+/// Returns are taken between days on which every holding has a close, as
+/// for correlations.
+pub async fn portfolio_var(store: &dyn Store, portfolio_id: i32, method: VarMethod, confidence: f64, horizon: u32) -> StoreResult<PortfolioVar> {
+    let holdings: Vec<_> = store.portfolio_holdings(portfolio_id).await?
+        .into_iter()
+        .filter(|h| h.market_value > Decimal::ZERO)
+        .collect();
+    let exposure: Decimal = holdings.iter().map(|h| h.market_value).sum();
+    if holdings.is_empty() {
+        return Ok(PortfolioVar {
+            portfolio_id,
+            method,
+            confidence,
+            horizon_days: horizon,
+            exposure,
+            var: Decimal::ZERO,
+            var_percent: Decimal::ZERO,
+            cvar: Decimal::ZERO,
+            cvar_percent: Decimal::ZERO,
+            observations: 0,
+            history_from: None,
+            history_to: None,
+            contributions: Vec::new(),
+        });
+    }
+
+    let today = scheduler::today();
+    let from = today - Days::new(VAR_LOOKBACK_DAYS);
+    let asset_ids: Vec<i32> = holdings.iter().map(|h| h.asset_id).collect();
+    let closes = store.asset_daily_closes(&asset_ids, from, today).await?;
+
+    let series: Vec<&[(NaiveDate, Decimal)]> = asset_ids.iter()
+        .map(|asset_id| closes.iter().find(|(id, _)| id == asset_id).map_or(&[][..], |(_, closes)| closes.as_slice()))
+        .collect();
+    let (history_days, returns) = correlation::aligned_returns(&series);
+
+    if returns.len() < MIN_VAR_OBSERVATIONS {
+        // One more close than returns
+        let short: Vec<&str> = holdings.iter().zip(&series)
+            .filter(|(_, closes)| closes.len() <= MIN_VAR_OBSERVATIONS)
+            .map(|(holding, _)| holding.symbol.as_str())
+            .collect();
+        let message = if short.is_empty() {
+            format!("Holdings share fewer than {} days of prices in the last year", MIN_VAR_OBSERVATIONS)
+        } else {
+            format!("Fewer than {} days of prices in the last year for {}", MIN_VAR_OBSERVATIONS, short.join(", "))
+        };
+        return Err(StoreError::BadRequest(message));
+    }
+
+    let exposures: Vec<f64> = holdings.iter()
+        .map(|h| h.market_value.to_f64().unwrap_or_default())
+        .collect();
+    let horizon_days = horizon as usize;
+    let tail = match method {
+        VarMethod::Historical => var::historical(&returns, &exposures, horizon_days, confidence),
+        VarMethod::Parametric => var::parametric(&returns, &exposures, horizon_days, confidence),
+        VarMethod::MonteCarlo => var::monte_carlo(&returns, &exposures, horizon_days, confidence),
+    };
+    let (var, cvar, var_contributions, cvar_contributions) = match tail {
+        Some(tail) => (tail.var, tail.cvar, tail.var_contributions, tail.cvar_contributions),
+        None => (0.0, 0.0, vec![0.0; holdings.len()], vec![0.0; holdings.len()]),
+    };
+
+    let (var, cvar) = (money(var), money(cvar));
+    Ok(PortfolioVar {
+        portfolio_id,
+        method,
+        confidence,
+        horizon_days: horizon,
+        exposure,
+        var,
+        var_percent: percent_of(var, exposure),
+        cvar,
+        cvar_percent: percent_of(cvar, exposure),
+        observations: returns.len(),
+        history_from: history_days.first().copied(),
+        history_to: history_days.last().copied(),
+        contributions: holdings.into_iter().enumerate()
+            .map(|(index, holding)| VarContribution {
+                asset_id: holding.asset_id,
+                weight: percent_of(holding.market_value, exposure),
+                var: money(var_contributions[index]),
+                cvar: money(cvar_contributions[index]),
+                symbol: holding.symbol,
+                market_value: holding.market_value,
+            })
+            .collect(),
+    })
+}
+
+/// Correlation and covariance of the daily returns of the given assets over
+/// the last `days` days of `AssetPrices`
+pub async fn asset_correlation(store: &dyn Store, asset_ids: &[i32], days: u32) -> StoreResult<AssetCorrelation> {
+    let mut assets = Vec::with_capacity(asset_ids.len());
+    for asset_id in asset_ids {
+        let asset = store.get_asset(*asset_id).await?;
+        assets.push(CorrelationAsset { asset_id: asset.asset_id, symbol: asset.symbol });
+    }
+    correlation_of(store, None, assets, days).await
+}
+
+/// The same for the assets a portfolio currently holds
+pub async fn portfolio_correlation(store: &dyn Store, portfolio_id: i32, days: u32) -> StoreResult<AssetCorrelation> {
+    let assets = store.portfolio_holdings(portfolio_id).await?
+        .into_iter()
+        .filter(|h| h.market_value > Decimal::ZERO)
+        .map(|h| CorrelationAsset { asset_id: h.asset_id, symbol: h.symbol })
+        .collect();
+    correlation_of(store, Some(portfolio_id), assets, days).await
+}
+
+async fn correlation_of(store: &dyn Store, portfolio_id: Option<i32>, assets: Vec<CorrelationAsset>, days: u32) -> StoreResult<AssetCorrelation> {
+    let today = scheduler::today();
+    let from = today - Days::new(u64::from(days));
+    let asset_ids: Vec<i32> = assets.iter().map(|a| a.asset_id).collect();
+    let closes = if asset_ids.is_empty() {
+        Vec::new()
+    } else {
+        store.asset_daily_closes(&asset_ids, from, today).await?
+    };
+    let series: Vec<&[(NaiveDate, Decimal)]> = asset_ids.iter()
+        .map(|asset_id| closes.iter().find(|(id, _)| id == asset_id).map_or(&[][..], |(_, closes)| closes.as_slice()))
+        .collect();
+
+    let (history_days, returns) = correlation::aligned_returns(&series);
+    let covariance = match metrics::moments(&returns, assets.len()) {
+        Some((_, covariance)) => covariance,
+        None if assets.is_empty() => Vec::new(),
+        None => {
+            // Two returns need three days of prices
+            let short: Vec<&str> = assets.iter().zip(&series)
+                .filter(|(_, closes)| closes.len() < 3)
+                .map(|(asset, _)| asset.symbol.as_str())
+                .collect();
+            let message = if short.is_empty() {
+                format!("The assets share fewer than 3 days of prices in the last {} days", days)
+            } else {
+                format!("Fewer than 3 days of prices in the last {} days for {}", days, short.join(", "))
+            };
+            return Err(StoreError::BadRequest(message));
+        },
+    };
+
+    Ok(AssetCorrelation {
+        portfolio_id,
+        days,
+        observations: returns.len(),
+        history_from: history_days.first().copied(),
+        history_to: history_days.last().copied(),
+        assets,
+        correlation: correlation::correlation(&covariance),
+        covariance,
+    })
+}
@@ -0,0 +1,97 @@
+use chrono::NaiveDate;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::models::PortfolioSnapshot;
+
+/// Daily returns of a run of end-of-day valuations, oldest first. A day's
+/// allocations and deallocations are taken to arrive at its start, so they
+/// do not count as gains or losses; days with nothing invested are left out.
+pub fn portfolio_returns(valuations: &[PortfolioSnapshot]) -> Vec<(NaiveDate, f64)> {
+    valuations.windows(2)
+        .filter_map(|pair| {
+            let capital = pair[0].total_value() + pair[1].net_flows;
+            if capital <= Decimal::ZERO {
+                return None;
+            }
+            let growth = (pair[1].total_value() / capital).to_f64()?;
+            Some((pair[1].snapshot_date, growth - 1.0))
+        })
+        .collect()
+}
+
+/// Last close on or before `day`
+fn close_on(closes: &[(NaiveDate, Decimal)], day: NaiveDate) -> Option<Decimal> {
+    let after = closes.partition_point(|(date, _)| *date <= day);
+    after.checked_sub(1).map(|index| closes[index].1)
+}
+
+/// The asset's return on each of `days`, from its close that day against
+/// the close the day before. Days without a price carry the last one
+/// forward, so a market that is shut returns zero.
+pub fn asset_returns(closes: &[(NaiveDate, Decimal)], days: &[NaiveDate]) -> Vec<Option<f64>> {
+    days.iter()
+        .map(|day| {
+            let previous = close_on(closes, day.pred_opt()?)?;
+            let close = close_on(closes, *day)?;
+            if previous <= Decimal::ZERO {
+                return None;
+            }
+            (close / previous).to_f64().map(|growth| growth - 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn valuation(d: u32, value: i64, net_flows: i64) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            snapshot_date: day(d),
+            cash_value: Decimal::from(value),
+            holdings_value: Decimal::ZERO,
+            net_flows: Decimal::from(net_flows),
+        }
+    }
+
+    #[test]
+    fn allocations_are_not_returns() {
+        let valuations = [
+            // Nothing invested until the first allocation
+            valuation(1, 0, 0),
+            valuation(2, 1000, 1000),
+            valuation(3, 1100, 0),
+            // 1000 more goes in and the whole 2100 falls 10%
+            valuation(4, 1890, 1000),
+            // Half is taken out; the rest is flat
+            valuation(5, 945, -945),
+        ];
+        let returns = portfolio_returns(&valuations);
+        let days: Vec<NaiveDate> = returns.iter().map(|(d, _)| *d).collect();
+        assert_eq!(days, [day(2), day(3), day(4), day(5)]);
+        let expected = [0.0, 0.1, -0.1, 0.0];
+        for ((_, actual), expected) in returns.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-12, "expected {expected}, got {actual}");
+        }
+    }
+
+    #[test]
+    fn closed_days_carry_the_last_close_forward() {
+        // Friday the 7th and Monday the 10th; nothing over the weekend
+        let closes = [(day(6), Decimal::from(100)), (day(7), Decimal::from(110)), (day(10), Decimal::from(99))];
+        let days = [day(6), day(7), day(8), day(9), day(10), day(11)];
+        let returns = asset_returns(&closes, &days);
+        // No close before the 6th to measure its return from
+        assert_eq!(returns[0], None);
+        assert!((returns[1].unwrap() - 0.1).abs() < 1e-12);
+        assert_eq!(returns[2], Some(0.0));
+        assert_eq!(returns[3], Some(0.0));
+        assert!((returns[4].unwrap() + 0.1).abs() < 1e-12);
+        assert_eq!(returns[5], Some(0.0));
+    }
+}
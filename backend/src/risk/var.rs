@@ -0,0 +1,334 @@
+//! Value-at-Risk and expected shortfall of a set of positions. Returns are
+//! fractions indexed `[day][asset]`, exposures are market values, and
+//! losses come out positive in the same currency as the exposures.
+
+use super::metrics::moments;
+
+/// Simulated scenarios for the Monte Carlo method
+const SIMULATIONS: usize = 10_000;
+
+/// Share of the scenarios on each side of the VaR one used to split it
+const QUANTILE_WINDOW: f64 = 0.005;
+
+/// Fixed so the same inputs always give the same figures
+const SIMULATION_SEED: u64 = 0x6D65_7550_6F72_7466;
+
+/// Loss that is only exceeded `1 - confidence` of the time (VaR), the
+/// average loss beyond it (CVaR), and how much of each comes from each
+/// position; contributions add up to the totals
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailRisk {
+    pub var: f64,
+    pub cvar: f64,
+    pub var_contributions: Vec<f64>,
+    pub cvar_contributions: Vec<f64>,
+}
+
+/// Tail of the historical `horizon`-day returns, taken over overlapping
+/// windows of daily returns
+pub fn historical(returns: &[Vec<f64>], exposures: &[f64], horizon: usize, confidence: f64) -> Option<TailRisk> {
+    let horizon = horizon.max(1);
+    let scenarios: Vec<Vec<f64>> = returns.windows(horizon)
+        .map(|window| {
+            (0..exposures.len())
+                .map(|asset| {
+                    let growth: f64 = window.iter().map(|day| 1.0 + day[asset]).product();
+                    -exposures[asset] * (growth - 1.0)
+                })
+                .collect()
+        })
+        .collect();
+    tail_of_scenarios(&scenarios, confidence)
+}
+
+/// Variance-covariance VaR, taking returns to be normally distributed and
+/// scaling the daily mean and covariance to the horizon. Contributions are
+/// each position's share of the marginal risk (Euler allocation).
+pub fn parametric(returns: &[Vec<f64>], exposures: &[f64], horizon: usize, confidence: f64) -> Option<TailRisk> {
+    let (means, covariance) = moments(returns, exposures.len())?;
+    let horizon = horizon.max(1) as f64;
+
+    // Covariance of each position with the whole portfolio, in currency
+    let portfolio_covariance: Vec<f64> = covariance.iter()
+        .map(|row| row.iter().zip(exposures).map(|(c, e)| c * e).sum())
+        .collect();
+    let variance: f64 = exposures.iter().zip(&portfolio_covariance).map(|(e, c)| e * c).sum();
+    let std_dev = variance.max(0.0).sqrt();
+
+    let z = normal_quantile(confidence);
+    let tail_mean = normal_density(z) / (1.0 - confidence);
+    let contributions = |multiplier: f64| -> Vec<f64> {
+        exposures.iter().enumerate()
+            .map(|(asset, exposure)| {
+                let marginal = if std_dev > 0.0 { portfolio_covariance[asset] / std_dev } else { 0.0 };
+                exposure * (multiplier * marginal * horizon.sqrt() - means[asset] * horizon)
+            })
+            .collect()
+    };
+
+    let var_contributions = contributions(z);
+    let cvar_contributions = contributions(tail_mean);
+    Some(TailRisk {
+        var: var_contributions.iter().sum(),
+        cvar: cvar_contributions.iter().sum(),
+        var_contributions,
+        cvar_contributions,
+    })
+}
+
+/// Tail of simulated `horizon`-day returns drawn from a multivariate normal
+/// with the historical daily mean and covariance scaled to the horizon
+pub fn monte_carlo(returns: &[Vec<f64>], exposures: &[f64], horizon: usize, confidence: f64) -> Option<TailRisk> {
+    let (means, covariance) = moments(returns, exposures.len())?;
+    let horizon = horizon.max(1) as f64;
+    let scaled: Vec<Vec<f64>> = covariance.iter()
+        .map(|row| row.iter().map(|c| c * horizon).collect())
+        .collect();
+    let factor = cholesky(&scaled);
+
+    let mut random = SplitMix64(SIMULATION_SEED);
+    let scenarios: Vec<Vec<f64>> = (0..SIMULATIONS)
+        .map(|_| {
+            let shocks: Vec<f64> = (0..exposures.len()).map(|_| random.standard_normal()).collect();
+            (0..exposures.len())
+                .map(|asset| {
+                    let shock: f64 = factor[asset].iter().zip(&shocks).map(|(l, z)| l * z).sum();
+                    -exposures[asset] * (means[asset] * horizon + shock)
+                })
+                .collect()
+        })
+        .collect();
+    tail_of_scenarios(&scenarios, confidence)
+}
+
+/// VaR is the loss of the scenario at the confidence quantile and CVaR the
+/// average of that one and every worse one. CVaR is split by position over
+/// the same scenarios; a single scenario's split is noisy, so VaR is split
+/// by the average over the scenarios either side of the quantile, scaled to
+/// add up to the VaR.
+fn tail_of_scenarios(scenarios: &[Vec<f64>], confidence: f64) -> Option<TailRisk> {
+    if scenarios.is_empty() {
+        return None;
+    }
+    let mut worst_first: Vec<(f64, &Vec<f64>)> = scenarios.iter()
+        .map(|losses| (losses.iter().sum::<f64>(), losses))
+        .collect();
+    worst_first.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let tail = ((scenarios.len() as f64 * (1.0 - confidence)).ceil() as usize).clamp(1, scenarios.len());
+    let (var, at_var) = worst_first[tail - 1];
+    let positions = at_var.len();
+    let average = |scenarios: &[(f64, &Vec<f64>)]| -> Vec<f64> {
+        (0..positions)
+            .map(|asset| scenarios.iter().map(|(_, losses)| losses[asset]).sum::<f64>() / scenarios.len() as f64)
+            .collect()
+    };
+    let cvar_contributions = average(&worst_first[..tail]);
+
+    let spread = (scenarios.len() as f64 * QUANTILE_WINDOW) as usize;
+    let around = &worst_first[(tail - 1).saturating_sub(spread)..(tail + spread).min(worst_first.len())];
+    let around_var = average(around);
+    let around_total: f64 = around_var.iter().sum();
+    let var_contributions = if around_total.abs() > f64::EPSILON {
+        around_var.iter().map(|loss| loss * var / around_total).collect()
+    } else {
+        at_var.clone()
+    };
+
+    Some(TailRisk {
+        var,
+        cvar: cvar_contributions.iter().sum(),
+        var_contributions,
+        cvar_contributions,
+    })
+}
+
+/// Lower-triangular `L` with `L * Lᵀ` equal to the matrix. Directions with
+/// no variance left, such as an asset that moves exactly with another,
+/// get a zero column instead of failing.
+fn cholesky(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let size = matrix.len();
+    let mut factor = vec![vec![0.0; size]; size];
+    for i in 0..size {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            if i == j {
+                factor[i][i] = (matrix[i][i] - dot).max(0.0).sqrt();
+            } else if factor[j][j] > 0.0 {
+                factor[i][j] = (matrix[i][j] - dot) / factor[j][j];
+            }
+        }
+    }
+    factor
+}
+
+fn normal_density(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Inverse of the standard normal distribution for 0 < p < 1 (Acklam's
+/// rational approximation, accurate to about 1e-9)
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Small, seedable generator; the simulation only needs reproducible
+/// uniform draws, not cryptographic ones
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in (0, 1]
+    fn next_unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Box-Muller transform
+    fn standard_normal(&mut self) -> f64 {
+        let (u1, u2) = (self.next_unit(), self.next_unit());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "expected {expected}, got {actual}");
+    }
+
+    /// One asset alternating +1% and -1%, then -3% and +2%
+    fn one_asset() -> Vec<Vec<f64>> {
+        let mut returns: Vec<Vec<f64>> = (0..18).map(|i| vec![if i % 2 == 0 { 0.01 } else { -0.01 }]).collect();
+        returns.push(vec![-0.03]);
+        returns.push(vec![0.02]);
+        returns
+    }
+
+    #[test]
+    fn normal_quantile_matches_tables() {
+        assert_close(normal_quantile(0.5), 0.0, 1e-9);
+        assert_close(normal_quantile(0.95), 1.644_853_627, 1e-8);
+        assert_close(normal_quantile(0.99), 2.326_347_874, 1e-8);
+        assert_close(normal_quantile(0.01), -2.326_347_874, 1e-8);
+    }
+
+    #[test]
+    fn historical_takes_the_scenario_at_the_quantile() {
+        // Losses of 10 on 1,000 for the two worst days out of 20
+        let returns = one_asset();
+        let risk = historical(&returns, &[1000.0], 1, 0.9).unwrap();
+        // ceil(20 * 0.1) = 2: the second-worst day (-1%) is the VaR and
+        // the CVaR averages it with the worst (-3%)
+        assert_close(risk.var, 10.0, 1e-9);
+        assert_close(risk.cvar, 20.0, 1e-9);
+        assert_eq!(risk.var_contributions.len(), 1);
+    }
+
+    #[test]
+    fn historical_horizon_compounds_overlapping_days() {
+        let returns = vec![vec![0.1], vec![-0.5], vec![0.2]];
+        // Two-day windows: 1.1 * 0.5 and 0.5 * 1.2
+        let risk = historical(&returns, &[100.0], 2, 0.99).unwrap();
+        assert_close(risk.var, 45.0, 1e-9);
+        assert_close(risk.cvar, 45.0, 1e-9);
+    }
+
+    #[test]
+    fn historical_contributions_add_up() {
+        let returns = vec![vec![0.01, -0.02], vec![-0.03, 0.01], vec![0.02, -0.04], vec![-0.01, -0.01]];
+        let risk = historical(&returns, &[500.0, 300.0], 1, 0.5).unwrap();
+        assert_close(risk.var_contributions.iter().sum(), risk.var, 1e-9);
+        assert_close(risk.cvar_contributions.iter().sum(), risk.cvar, 1e-9);
+        // The two worst days are the second (15 - 3) and the fourth (5 + 3)
+        assert_close(risk.var, 8.0, 1e-9);
+        assert_close(risk.cvar, (12.0 + 8.0) / 2.0, 1e-9);
+        assert_close(risk.var_contributions[0], 5.0, 1e-9);
+    }
+
+    #[test]
+    fn parametric_scales_sigma_by_the_square_root_of_time() {
+        let returns = one_asset();
+        let mean = returns.iter().map(|r| r[0]).sum::<f64>() / 20.0;
+        let variance = returns.iter().map(|r| (r[0] - mean).powi(2)).sum::<f64>() / 19.0;
+        let sigma = 1000.0 * variance.sqrt();
+        let z = normal_quantile(0.95);
+
+        let one_day = parametric(&returns, &[1000.0], 1, 0.95).unwrap();
+        assert_close(one_day.var, z * sigma - 1000.0 * mean, 1e-9);
+        assert_close(one_day.cvar, sigma * normal_density(z) / 0.05 - 1000.0 * mean, 1e-9);
+
+        let ten_day = parametric(&returns, &[1000.0], 10, 0.95).unwrap();
+        assert_close(ten_day.var, z * sigma * 10_f64.sqrt() - 10_000.0 * mean, 1e-9);
+    }
+
+    #[test]
+    fn parametric_contributions_follow_marginal_risk() {
+        // The second asset always moves twice as much as the first
+        let returns: Vec<Vec<f64>> = one_asset().into_iter().map(|r| vec![r[0], 2.0 * r[0]]).collect();
+        let risk = parametric(&returns, &[1000.0, 1000.0], 1, 0.99).unwrap();
+        assert_close(risk.var_contributions.iter().sum(), risk.var, 1e-9);
+        assert_close(risk.var_contributions[1], 2.0 * risk.var_contributions[0], 1e-9);
+        // A perfectly correlated pair is the same as one position of 3,000
+        let single = parametric(&one_asset(), &[3000.0], 1, 0.99).unwrap();
+        assert_close(risk.var, single.var, 1e-9);
+    }
+
+    #[test]
+    fn hedged_positions_have_no_variance() {
+        let returns: Vec<Vec<f64>> = one_asset().into_iter().map(|r| vec![r[0], r[0]]).collect();
+        let risk = parametric(&returns, &[1000.0, -1000.0], 1, 0.95).unwrap();
+        assert_close(risk.var, 0.0, 1e-9);
+        assert_close(risk.cvar, 0.0, 1e-9);
+    }
+
+    #[test]
+    fn monte_carlo_converges_on_parametric() {
+        let returns: Vec<Vec<f64>> = one_asset().into_iter().map(|r| vec![r[0], 0.5 * r[0] + 0.004]).collect();
+        let exposures = [2000.0, 1000.0];
+        let simulated = monte_carlo(&returns, &exposures, 5, 0.95).unwrap();
+        let exact = parametric(&returns, &exposures, 5, 0.95).unwrap();
+        assert_close(simulated.var, exact.var, 0.03 * exact.var);
+        assert_close(simulated.cvar, exact.cvar, 0.03 * exact.cvar);
+        assert_close(simulated.var_contributions.iter().sum(), simulated.var, 1e-9);
+        assert_close(simulated.cvar_contributions.iter().sum(), simulated.cvar, 1e-9);
+        // Both positions lose money in the tail
+        assert!(simulated.var_contributions.iter().all(|c| *c > 0.0));
+        // Same seed, same answer
+        assert_eq!(monte_carlo(&returns, &exposures, 5, 0.95), Some(simulated));
+    }
+
+    #[test]
+    fn too_little_history_has_no_estimate() {
+        assert_eq!(parametric(&[vec![0.01]], &[100.0], 1, 0.95), None);
+        assert_eq!(monte_carlo(&[], &[100.0], 1, 0.95), None);
+        assert_eq!(historical(&[], &[100.0], 1, 0.95), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::models::{
+    BuyAssetRequest, BuyAssetResponse, OrderSide, RecurrenceFrequency, RecurringInvestment,
+    RecurringInvestmentRun, QUANTITY_SCALE,
+};
+use crate::pretrade::{self, ProposedTrade};
+use crate::store::{RecurringRunOutcome, Store, StoreError, StoreResult};
+
+/// How often due recurring investments are looked for
+const RUN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Schedules run on UTC days, like every timestamp the API stores
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// First day on or after `from` that the schedule falls on
+pub fn next_run_date(
+    frequency: RecurrenceFrequency,
+    day_of_week: Option<i32>,
+    day_of_month: Option<i32>,
+    from: NaiveDate,
+) -> NaiveDate {
+    match frequency {
+        RecurrenceFrequency::Daily => from,
+        RecurrenceFrequency::Weekly => {
+            let target = day_of_week.unwrap_or(1) as u32;
+            let days_ahead = (7 + target - from.weekday().number_from_monday()) % 7;
+            from + Days::new(u64::from(days_ahead))
+        },
+        RecurrenceFrequency::Monthly => {
+            // Days of month are at most 28, so every month has them
+            let day = day_of_month.unwrap_or(1) as u32;
+            let month = match from.day() <= day {
+                true => from,
+                false => from + Months::new(1),
+            };
+            month.with_day(day).unwrap_or(month)
+        },
+    }
+}
+
+/// Starts the background task that runs recurring investments as they fall due
+pub fn spawn_scheduler(store: Arc<dyn Store>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RUN_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_due(store.as_ref(), today()).await;
+        }
+    });
+}
+
+async fn run_due(store: &dyn Store, today: NaiveDate) {
+    let schedules = match store.due_recurring_investments(today).await {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            eprintln!("Recurring investments failed to load due schedules: {}", e);
+            return;
+        },
+    };
+
+    for schedule in schedules {
+        if let Err(e) = run_schedule(store, &schedule, today).await {
+            eprintln!("Recurring investment {} failed to run: {}", schedule.schedule_id, e);
+        }
+    }
+}
+
+/// Runs a due schedule once and moves it on to its next day after `today`.
+/// A schedule that missed several days while the API was down runs only once.
+/// Returns None when another runner got to it first
+pub async fn run_schedule(
+    store: &dyn Store,
+    schedule: &RecurringInvestment,
+    today: NaiveDate,
+) -> StoreResult<Option<RecurringInvestmentRun>> {
+    let Some(run_date) = schedule.next_run_date else {
+        return Ok(None);
+    };
+    let next = next_run_date(schedule.frequency, schedule.day_of_week, schedule.day_of_month, today + Days::new(1));
+    let Some(run_id) = store.claim_recurring_run(schedule.schedule_id, run_date, next).await? else {
+        return Ok(None);
+    };
+
+    let outcome = match buy(store, schedule).await {
+        Ok(response) => RecurringRunOutcome::Executed(response),
+        Err(reason) => RecurringRunOutcome::Skipped(reason),
+    };
+    store.complete_recurring_run(run_id, &outcome).await.map(Some)
+}
+
+/// What a skipped run records; database errors are logged rather than kept
+fn skip_reason(schedule_id: i32, error: StoreError) -> String {
+    match error {
+        StoreError::Database(_) | StoreError::Internal(_) => {
+            eprintln!("Recurring investment {} could not buy: {}", schedule_id, error);
+            "The buy failed because of an internal error".to_string()
+        },
+        error => error.to_string(),
+    }
+}
+
+/// Buys the schedule's amount at the current price, going through the same
+/// pre-trade rules as a buy placed by hand
+async fn buy(store: &dyn Store, schedule: &RecurringInvestment) -> Result<BuyAssetResponse, String> {
+    let reason = |error| skip_reason(schedule.schedule_id, error);
+
+    let asset = store.get_asset(schedule.asset_id).await.map_err(reason)?;
+    if asset.price <= Decimal::ZERO {
+        return Err(format!("{} has no price", asset.symbol));
+    }
+    let quantity = (schedule.amount / asset.price).round_dp_with_strategy(QUANTITY_SCALE, RoundingStrategy::ToZero);
+    if quantity.is_zero() {
+        return Err(format!("{} buys less than the smallest quantity of {}", schedule.amount, asset.symbol));
+    }
+
+    let trade = ProposedTrade {
+        portfolio_id: schedule.portfolio_id,
+        asset_id: schedule.asset_id,
+        side: OrderSide::Buy,
+        quantity,
+        price: None,
+    };
+    let mut snapshot = store.pre_trade_snapshot(schedule.portfolio_id, schedule.asset_id).await.map_err(reason)?;
+    // Buying an amount rather than a quantity is fractional whatever the asset type
+    snapshot.quantity_increment = None;
+    pretrade::evaluate(&trade, &snapshot).map_err(|violation| violation.message)?;
+
+    store.buy_asset(&BuyAssetRequest {
+        portfolio_id: schedule.portfolio_id,
+        asset_id: schedule.asset_id,
+        quantity,
+        unit_price: None,
+    }).await.map_err(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn monthly(day_of_month: i32, from: NaiveDate) -> NaiveDate {
+        next_run_date(RecurrenceFrequency::Monthly, None, Some(day_of_month), from)
+    }
+
+    #[test]
+    fn monthly_runs_later_this_month_or_next() {
+        assert_eq!(monthly(15, date(2025, 3, 10)), date(2025, 3, 15));
+        assert_eq!(monthly(15, date(2025, 3, 15)), date(2025, 3, 15));
+        assert_eq!(monthly(15, date(2025, 3, 16)), date(2025, 4, 15));
+        assert_eq!(monthly(1, date(2025, 12, 2)), date(2026, 1, 1));
+    }
+
+    #[test]
+    fn monthly_rolls_over_from_the_end_of_a_month() {
+        // 31 January plus a month clamps to the end of February
+        assert_eq!(monthly(28, date(2025, 1, 31)), date(2025, 2, 28));
+        assert_eq!(monthly(28, date(2024, 1, 31)), date(2024, 2, 28));
+        assert_eq!(monthly(1, date(2025, 4, 30)), date(2025, 5, 1));
+    }
+
+    #[test]
+    fn runs_roll_over_the_29th_of_february() {
+        assert_eq!(monthly(28, date(2024, 2, 29)), date(2024, 3, 28));
+        assert_eq!(monthly(1, date(2024, 2, 29)), date(2024, 3, 1));
+        assert_eq!(next_run_date(RecurrenceFrequency::Daily, None, None, date(2024, 2, 29)), date(2024, 2, 29));
+        // The day after 29 February is a Friday
+        assert_eq!(next_run_date(RecurrenceFrequency::Weekly, Some(5), None, date(2024, 2, 29) + Days::new(1)), date(2024, 3, 1));
+        assert_eq!(next_run_date(RecurrenceFrequency::Weekly, Some(1), None, date(2024, 2, 27)), date(2024, 3, 4));
+    }
+
+    #[test]
+    fn weekly_runs_on_the_day_given() {
+        // 2025-03-12 is a Wednesday
+        let wednesday = date(2025, 3, 12);
+        assert_eq!(next_run_date(RecurrenceFrequency::Weekly, Some(3), None, wednesday), wednesday);
+        assert_eq!(next_run_date(RecurrenceFrequency::Weekly, Some(2), None, wednesday), date(2025, 3, 18));
+        assert_eq!(next_run_date(RecurrenceFrequency::Weekly, Some(7), None, wednesday), date(2025, 3, 16));
+    }
+}
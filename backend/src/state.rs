@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::auth::JwtKeys;
+use crate::config::Config;
+use crate::orders::PriceEvents;
+use crate::store::Store;
+
+/// Shared application state handed to every handler through axum's `State` extractor
+#[derive(Clone)]
+pub struct AppState {
+    pub store: Arc<dyn Store>,
+    pub jwt: Arc<JwtKeys>,
+    pub config: Arc<Config>,
+    /// Wakes the order matching worker after a price change
+    pub price_events: PriceEvents,
+}
+
+impl AppState {
+    pub fn new(store: Arc<dyn Store>, config: Config, price_events: PriceEvents) -> Self {
+        let jwt = JwtKeys::new(&config.auth);
+        Self { store, jwt: Arc::new(jwt), config: Arc::new(config), price_events }
+    }
+}
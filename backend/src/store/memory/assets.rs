@@ -0,0 +1,383 @@
+use async_trait::async_trait;
+use chrono::Duration;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use crate::models::{
+    Asset, AssetFactory, AssetPriceHistory, CommodityDetails, CompleteAsset, CryptoDetails,
+    CsvImportRequest, IndexDetails, StockDetails, UpdateAssetRequest, UpdatePriceResponse,
+};
+use crate::store::{AssetStore, NewAsset, PricePoint, StoreError, StoreResult};
+use super::{now, AssetDetails, AssetDetailsRecord, AssetRecord, MemoryData, MemoryStore, PriceRecord};
+
+impl MemoryData {
+    /// Newest first, like `vw_AssetPriceHistory` ordered by date
+    fn price_history(&self, asset_id: i32) -> StoreResult<Vec<AssetPriceHistory>> {
+        let asset = self.asset(asset_id)?;
+
+        let mut prices: Vec<&PriceRecord> = self.prices.iter()
+            .filter(|p| p.asset_id == asset_id)
+            .collect();
+        prices.sort_by_key(|p| std::cmp::Reverse(p.as_of));
+
+        Ok(prices.into_iter()
+            .map(|p| AssetPriceHistory {
+                asset_id,
+                symbol: asset.symbol.clone(),
+                price: p.price,
+                volume: p.volume,
+                timestamp: p.as_of.to_string(),
+            })
+            .collect())
+    }
+
+    fn set_details(&mut self, asset_id: i32, details: AssetDetails) {
+        self.asset_details.insert(asset_id, AssetDetailsRecord {
+            details,
+            last_updated: now(),
+        });
+    }
+}
+
+#[async_trait]
+impl AssetStore for MemoryStore {
+    async fn list_assets(&self, search: Option<&str>, asset_type: Option<&str>) -> StoreResult<Vec<Asset>> {
+        let data = self.lock();
+        let search = search.map(str::to_lowercase);
+
+        Ok(data.assets.values()
+            .filter(|a| asset_type.is_none_or(|t| a.asset_type == t))
+            .filter(|a| search.as_deref().is_none_or(|s| {
+                a.name.to_lowercase().contains(s) || a.symbol.to_lowercase().contains(s)
+            }))
+            .map(AssetRecord::to_asset)
+            .collect())
+    }
+
+    async fn get_asset(&self, asset_id: i32) -> StoreResult<Asset> {
+        Ok(self.lock().asset(asset_id)?.to_asset())
+    }
+
+    async fn find_asset_id(&self, symbol: &str) -> StoreResult<Option<i32>> {
+        Ok(self.lock().assets.values()
+            .find(|a| a.symbol == symbol)
+            .map(|a| a.asset_id))
+    }
+
+    async fn create_asset(&self, asset: &NewAsset) -> StoreResult<Asset> {
+        let mut data = self.lock();
+
+        if let Some(existing) = data.assets.values().find(|a| a.symbol == asset.symbol) {
+            return Ok(existing.to_asset());
+        }
+
+        let record = AssetRecord {
+            asset_id: data.next_id() as i32,
+            symbol: asset.symbol.clone(),
+            name: asset.name.clone(),
+            asset_type: asset.asset_type.clone(),
+            price: asset.price,
+            volume: asset.volume,
+            available_shares: asset.available_shares,
+            last_updated: now(),
+        };
+
+        let created = record.to_asset();
+        data.assets.insert(record.asset_id, record);
+        Ok(created)
+    }
+
+    async fn update_asset(&self, asset_id: i32, update: &UpdateAssetRequest) -> StoreResult<Asset> {
+        let mut data = self.lock();
+        let asset = data.assets.get_mut(&asset_id)
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))?;
+
+        if update.name.is_none() && update.price.is_none() && update.volume.is_none() && update.available_shares.is_none() {
+            return Err(StoreError::BadRequest("No fields to update".to_string()));
+        }
+
+        if let Some(name) = &update.name {
+            asset.name = name.clone();
+        }
+        if let Some(price) = update.price {
+            asset.price = price;
+        }
+        if let Some(volume) = update.volume {
+            asset.volume = volume;
+        }
+        if let Some(available_shares) = update.available_shares {
+            asset.available_shares = available_shares;
+        }
+        asset.last_updated = now();
+
+        Ok(asset.to_asset())
+    }
+
+    async fn delete_asset(&self, asset_id: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.asset(asset_id)?;
+
+        if data.holdings.iter().any(|h| h.asset_id == asset_id) {
+            return Err(StoreError::Conflict("Cannot delete asset with active holdings. Please ensure no portfolios hold this asset.".to_string()));
+        }
+        if data.transactions.iter().any(|t| t.asset_id == asset_id) {
+            return Err(StoreError::Conflict("Cannot delete asset with existing transaction history. Contact support if deletion is required.".to_string()));
+        }
+
+        // Prices, details, allocation targets and recurring investments cascade with the asset
+        data.prices.retain(|p| p.asset_id != asset_id);
+        data.asset_details.remove(&asset_id);
+        for record in data.allocation_targets.values_mut() {
+            record.targets.retain(|t| t.asset_id != Some(asset_id));
+        }
+        data.remove_recurring_investments(|r| r.asset_id == asset_id);
+        data.assets.remove(&asset_id);
+
+        Ok(())
+    }
+
+    async fn update_asset_price(&self, asset_id: i32, price: Decimal, volume: Option<i64>) -> StoreResult<UpdatePriceResponse> {
+        let mut data = self.lock();
+        data.asset(asset_id)?;
+
+        if price < Decimal::ZERO {
+            return Err(StoreError::BadRequest("Price cannot be negative".to_string()));
+        }
+
+        // Like the SQL Server store, a missing volume is sent as zero
+        let volume = volume.unwrap_or(0);
+        let now = now();
+        data.prices.push(PriceRecord {
+            asset_id,
+            as_of: now,
+            price,
+            volume,
+        });
+
+        let asset = data.assets.get_mut(&asset_id)
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))?;
+        asset.price = price;
+        asset.volume = volume;
+        asset.last_updated = now;
+
+        Ok(UpdatePriceResponse {
+            status: "SUCCESS".to_string(),
+            message: "Asset price updated successfully".to_string(),
+        })
+    }
+
+    async fn complete_asset(&self, asset_id: i32) -> StoreResult<CompleteAsset> {
+        let data = self.lock();
+        let asset = data.asset(asset_id)?.to_asset();
+
+        let mut complete = CompleteAsset {
+            asset,
+            stock_details: None,
+            crypto_details: None,
+            commodity_details: None,
+            index_details: None,
+            recent_prices: data.price_history(asset_id)?,
+        };
+
+        if let Some(record) = data.asset_details.get(&asset_id) {
+            let last_updated = record.last_updated.to_string();
+            match &record.details {
+                AssetDetails::Stock { sector, country, market_cap } => {
+                    complete.stock_details = Some(StockDetails {
+                        asset_id,
+                        sector: sector.clone(),
+                        country: country.clone(),
+                        market_cap: *market_cap,
+                        last_updated,
+                    });
+                },
+                AssetDetails::Crypto { blockchain, max_supply, circulating_supply } => {
+                    complete.crypto_details = Some(CryptoDetails {
+                        asset_id,
+                        blockchain: blockchain.clone(),
+                        max_supply: *max_supply,
+                        circulating_supply: *circulating_supply,
+                        last_updated,
+                    });
+                },
+                AssetDetails::Index { country, region, index_type, component_count } => {
+                    complete.index_details = Some(IndexDetails {
+                        asset_id,
+                        country: country.clone(),
+                        region: region.clone(),
+                        index_type: index_type.clone(),
+                        component_count: *component_count,
+                        last_updated,
+                    });
+                },
+                AssetDetails::Commodity { category, unit } => {
+                    complete.commodity_details = Some(CommodityDetails {
+                        asset_id,
+                        category: category.clone(),
+                        unit: unit.clone(),
+                        last_updated,
+                    });
+                },
+            }
+        }
+
+        Ok(complete)
+    }
+
+    async fn price_history(&self, asset_id: i32) -> StoreResult<Vec<AssetPriceHistory>> {
+        self.lock().price_history(asset_id)
+    }
+
+    async fn enhanced_asset_details(&self, asset_id: i32) -> StoreResult<serde_json::Value> {
+        let data = self.lock();
+        let asset = data.asset(asset_id)?;
+
+        let holdings: Vec<_> = data.holdings.iter().filter(|h| h.asset_id == asset_id).collect();
+        let mut details = json!({
+            "asset_id": asset_id,
+            "name": asset.name,
+            "symbol": asset.symbol,
+            "asset_type": asset.asset_type,
+            "price": asset.price,
+            "volume": asset.volume,
+            "available_shares": asset.available_shares,
+            "last_updated": asset.last_updated.to_string(),
+            "total_quantity_held": holdings.iter().map(|h| h.quantity_held).sum::<Decimal>(),
+            "total_portfolios_holding": holdings.len(),
+        });
+
+        // Same columns vw_AssetDetails derives from the detail tables
+        let (category_info, additional_info, market_metric) = match data.asset_details.get(&asset_id).map(|r| &r.details) {
+            Some(AssetDetails::Stock { sector, country, market_cap }) => (Some(sector), Some(country), Some(*market_cap)),
+            Some(AssetDetails::Crypto { blockchain, circulating_supply, .. }) => (Some(blockchain), None, Some(*circulating_supply)),
+            Some(AssetDetails::Commodity { category, unit }) => (Some(category), Some(unit), None),
+            _ => (None, None, None),
+        };
+        if let Some(category_info) = category_info {
+            details["category_info"] = json!(category_info);
+        }
+        if let Some(additional_info) = additional_info {
+            details["additional_info"] = json!(additional_info);
+        }
+        if let Some(market_metric) = market_metric {
+            details["market_metric"] = json!(market_metric);
+        }
+
+        let cutoff = now() - Duration::days(30);
+        let price_30_days_ago = data.prices.iter()
+            .filter(|p| p.asset_id == asset_id && p.as_of <= cutoff)
+            .max_by_key(|p| p.as_of)
+            .map(|p| p.price);
+        if let Some(price_30_days_ago) = price_30_days_ago {
+            details["price_30_days_ago"] = json!(price_30_days_ago);
+        }
+
+        Ok(details)
+    }
+
+    async fn insert_asset_details(&self, asset_id: i32, details: &CsvImportRequest) -> StoreResult<String> {
+        let mut data = self.lock();
+        data.asset(asset_id)?;
+
+        match details.asset_type.as_str() {
+            "Stock" => {
+                if let (Some(sector), Some(country)) = (&details.sector, &details.country) {
+                    AssetFactory::validate_stock_data(sector, country, details.market_cap)
+                        .map_err(|e| StoreError::BadRequest(format!("Stock validation failed: {}", e)))?;
+
+                    data.set_details(asset_id, AssetDetails::Stock {
+                        sector: sector.clone(),
+                        country: country.clone(),
+                        market_cap: details.market_cap.unwrap_or(Decimal::ZERO),
+                    });
+                    Ok("Stock details created successfully".to_string())
+                } else {
+                    Ok("Stock created without detailed information".to_string())
+                }
+            },
+            "Cryptocurrency" => {
+                if let Some(blockchain) = &details.blockchain {
+                    AssetFactory::validate_crypto_data(blockchain, details.max_supply, details.circulating_supply)
+                        .map_err(|e| StoreError::BadRequest(format!("Crypto validation failed: {}", e)))?;
+
+                    data.set_details(asset_id, AssetDetails::Crypto {
+                        blockchain: blockchain.clone(),
+                        max_supply: details.max_supply,
+                        circulating_supply: details.circulating_supply.unwrap_or(Decimal::ZERO),
+                    });
+                    Ok("Cryptocurrency details created successfully".to_string())
+                } else {
+                    Ok("Cryptocurrency created without detailed information".to_string())
+                }
+            },
+            "Index" => {
+                if let (Some(country), Some(region), Some(index_type)) = (&details.country, &details.region, &details.index_type) {
+                    AssetFactory::validate_index_data(country, region, index_type, details.component_count)
+                        .map_err(|e| StoreError::BadRequest(format!("Index validation failed: {}", e)))?;
+
+                    data.set_details(asset_id, AssetDetails::Index {
+                        country: country.clone(),
+                        region: region.clone(),
+                        index_type: index_type.clone(),
+                        component_count: details.component_count,
+                    });
+                    Ok("Index details created successfully".to_string())
+                } else {
+                    Ok("Index created without detailed information".to_string())
+                }
+            },
+            "Commodity" => {
+                if let (Some(category), Some(unit)) = (&details.category, &details.unit) {
+                    AssetFactory::validate_commodity_data(category, unit)
+                        .map_err(|e| StoreError::BadRequest(format!("Commodity validation failed: {}", e)))?;
+
+                    data.set_details(asset_id, AssetDetails::Commodity {
+                        category: category.clone(),
+                        unit: unit.clone(),
+                    });
+                    Ok("Commodity details created successfully".to_string())
+                } else {
+                    Ok("Commodity created without detailed information".to_string())
+                }
+            },
+            asset_type => Ok(format!("Asset type '{}' created without specific details", asset_type))
+        }
+    }
+
+    async fn import_price(&self, asset_id: i32, point: &PricePoint, update_current_price: bool) -> StoreResult<bool> {
+        let mut data = self.lock();
+        data.asset(asset_id)?;
+
+        let replaced = match data.prices.iter_mut().find(|p| p.asset_id == asset_id && p.as_of == point.as_of) {
+            Some(existing) => {
+                existing.price = point.price;
+                existing.volume = point.volume;
+                true
+            },
+            None => {
+                data.prices.push(PriceRecord {
+                    asset_id,
+                    as_of: point.as_of,
+                    price: point.price,
+                    volume: point.volume,
+                });
+                false
+            },
+        };
+
+        if update_current_price {
+            // Only the most recent import moves the current price
+            let latest = data.prices.iter()
+                .filter(|p| p.asset_id == asset_id)
+                .max_by_key(|p| p.as_of)
+                .map(|p| (p.price, p.volume));
+            if let (Some((price, volume)), Some(asset)) = (latest, data.assets.get_mut(&asset_id)) {
+                asset.price = price;
+                asset.volume = volume;
+                asset.last_updated = now();
+            }
+        }
+
+        Ok(replaced)
+    }
+}
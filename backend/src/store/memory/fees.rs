@@ -0,0 +1,157 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{FeeSchedule, FeeTier, FeeType, SetFeeScheduleRequest};
+use crate::store::{FeeStore, StoreError, StoreResult};
+use super::{now, FeeScheduleRecord, MemoryData, MemoryStore};
+
+impl MemoryData {
+    /// The default schedules `016_trading_fees.sql` inserts
+    pub(super) fn seed_fee_schedules(&mut self) {
+        let tiers = |tiers: &[(i64, i64)]| -> Vec<FeeTier> {
+            tiers.iter()
+                .map(|&(min_trade_value, rate)| FeeTier {
+                    min_trade_value: Decimal::from(min_trade_value),
+                    rate: Decimal::new(rate, 4),
+                })
+                .collect()
+        };
+        let defaults = [
+            ("Basic", None, FeeType::Percentage, Some(Decimal::new(2500, 4)), Vec::new(), Decimal::new(100, 2)),
+            ("Basic", Some("Cryptocurrency"), FeeType::Tiered, None, tiers(&[(0, 10000), (1000, 7500), (10000, 5000)]), Decimal::new(100, 2)),
+            ("Premium", None, FeeType::Percentage, Some(Decimal::new(1000, 4)), Vec::new(), Decimal::new(50, 2)),
+            ("Premium", Some("Cryptocurrency"), FeeType::Tiered, None, tiers(&[(0, 5000), (10000, 2500)]), Decimal::new(50, 2)),
+        ];
+        for (user_type, asset_type, fee_type, rate, tiers, minimum_fee) in defaults {
+            let fee_schedule_id = self.next_id() as i32;
+            self.fee_schedules.insert(fee_schedule_id, FeeScheduleRecord {
+                fee_schedule_id,
+                user_type: user_type.to_string(),
+                asset_type: asset_type.map(str::to_string),
+                fee_type,
+                flat_fee: None,
+                rate,
+                minimum_fee,
+                tiers,
+                updated_at: now(),
+            });
+        }
+    }
+
+    /// Fee for a trade, under the schedule for the user's type and the
+    /// asset's type, else the user type's default one; none means no fee
+    pub(super) fn trading_fee(&self, user_id: Uuid, asset_id: i32, trade_value: Decimal) -> Decimal {
+        self.fee_schedule_for(user_id, asset_id)
+            .map_or(Decimal::ZERO, |schedule| schedule.fee_for(trade_value))
+    }
+
+    /// Most a trade worth up to `trade_value` can cost in fees, as
+    /// `fn_MaxTradingFee` computes it; what buy orders reserve
+    pub(super) fn max_trading_fee(&self, user_id: Uuid, asset_id: i32, trade_value: Decimal) -> Decimal {
+        self.fee_schedule_for(user_id, asset_id)
+            .map_or(Decimal::ZERO, |schedule| schedule.max_fee_for(trade_value))
+    }
+
+    fn fee_schedule_for(&self, user_id: Uuid, asset_id: i32) -> Option<FeeSchedule> {
+        let (user, asset) = (self.users.get(&user_id)?, self.assets.get(&asset_id)?);
+        self.fee_schedules.values()
+            .filter(|s| s.user_type == user.user_type)
+            .filter(|s| s.asset_type.as_ref().is_none_or(|asset_type| *asset_type == asset.asset_type))
+            .min_by_key(|s| s.asset_type.is_none())
+            .map(|s| s.to_schedule())
+    }
+
+    /// Takes a trade's fee from portfolio cash and logs it against the trade,
+    /// as `sp_ChargeTradingFee` does
+    pub(super) fn charge_trading_fee(&mut self, portfolio_id: i32, transaction_id: i64, fee: Decimal) -> StoreResult<()> {
+        if fee <= Decimal::ZERO {
+            return Ok(());
+        }
+
+        let portfolio = self.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds -= fee;
+        portfolio.last_updated = now();
+        let (user_id, balance_after) = (portfolio.user_id, portfolio.current_funds);
+
+        self.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "TradingFee",
+            -fee,
+            balance_after,
+            Some(format!("Trading fee for transaction {}", transaction_id)),
+            Some(transaction_id),
+        );
+        Ok(())
+    }
+
+    pub(super) fn fees_paid(&self, portfolio_id: i32) -> Decimal {
+        -self.fund_transactions.iter()
+            .filter(|t| t.portfolio_id == Some(portfolio_id) && t.transaction_type == "TradingFee")
+            .map(|t| t.amount)
+            .sum::<Decimal>()
+    }
+}
+
+#[async_trait]
+impl FeeStore for MemoryStore {
+    async fn list_fee_schedules(&self) -> StoreResult<Vec<FeeSchedule>> {
+        let data = self.lock();
+        let mut schedules: Vec<&FeeScheduleRecord> = data.fee_schedules.values().collect();
+        schedules.sort_by(|a, b| {
+            (&a.user_type, a.asset_type.is_some(), &a.asset_type).cmp(&(&b.user_type, b.asset_type.is_some(), &b.asset_type))
+        });
+        Ok(schedules.into_iter().map(FeeScheduleRecord::to_schedule).collect())
+    }
+
+    async fn set_fee_schedule(&self, schedule: &SetFeeScheduleRequest) -> StoreResult<FeeSchedule> {
+        let mut data = self.lock();
+
+        let mut tiers = match schedule.fee_type {
+            FeeType::Tiered => schedule.tiers.clone().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        if schedule.fee_type == FeeType::Tiered && !tiers.iter().any(|tier| tier.min_trade_value.is_zero()) {
+            return Err(StoreError::BadRequest("Tiered fee schedules need a tier starting at a trade value of 0".to_string()));
+        }
+        tiers.sort_by_key(|tier| tier.min_trade_value);
+
+        let existing = data.fee_schedules.values()
+            .find(|s| s.user_type == schedule.user_type && s.asset_type == schedule.asset_type)
+            .map(|s| s.fee_schedule_id);
+        let fee_schedule_id = match existing {
+            Some(id) => id,
+            None => data.next_id() as i32,
+        };
+
+        let record = FeeScheduleRecord {
+            fee_schedule_id,
+            user_type: schedule.user_type.clone(),
+            asset_type: schedule.asset_type.clone(),
+            fee_type: schedule.fee_type,
+            flat_fee: schedule.flat_fee,
+            rate: schedule.rate,
+            minimum_fee: schedule.minimum_fee.unwrap_or_default(),
+            tiers,
+            updated_at: now(),
+        };
+        let response = record.to_schedule();
+        data.fee_schedules.insert(fee_schedule_id, record);
+        Ok(response)
+    }
+
+    async fn delete_fee_schedule(&self, fee_schedule_id: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.fee_schedules.remove(&fee_schedule_id)
+            .map(|_| ())
+            .ok_or_else(|| StoreError::NotFound("Fee schedule not found".to_string()))
+    }
+
+    async fn quote_trading_fee(&self, portfolio_id: i32, asset_id: i32, trade_value: Decimal) -> StoreResult<Decimal> {
+        let data = self.lock();
+        let user_id = data.trading_portfolio(portfolio_id)?;
+        data.asset(asset_id)?;
+        Ok(data.trading_fee(user_id, asset_id, trade_value))
+    }
+}
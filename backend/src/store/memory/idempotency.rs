@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::store::{IdempotencyClaim, IdempotencyStore, StoreResult};
+use super::{now, IdempotencyRecord, MemoryStore};
+
+#[async_trait]
+impl IdempotencyStore for MemoryStore {
+    async fn claim_idempotency_key(&self, user_id: Uuid, key: &str, request_hash: &str, lease_secs: i32) -> StoreResult<IdempotencyClaim> {
+        let mut data = self.lock();
+        let now = now();
+        let id = (user_id, key.to_string());
+
+        if let Some(record) = data.idempotency_keys.get(&id).filter(|r| r.expires_at > now) {
+            if record.request_hash != request_hash {
+                return Ok(IdempotencyClaim::Mismatch);
+            }
+            return Ok(match &record.response {
+                Some((status, body)) => IdempotencyClaim::Replay { status: *status, body: body.clone() },
+                None => IdempotencyClaim::InProgress,
+            });
+        }
+
+        data.idempotency_keys.insert(id, IdempotencyRecord {
+            request_hash: request_hash.to_string(),
+            response: None,
+            expires_at: now + Duration::seconds(lease_secs.into()),
+        });
+        Ok(IdempotencyClaim::New)
+    }
+
+    async fn renew_idempotency_key(&self, user_id: Uuid, key: &str, lease_secs: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        if let Some(record) = data.idempotency_keys.get_mut(&(user_id, key.to_string())).filter(|r| r.response.is_none()) {
+            record.expires_at = now() + Duration::seconds(lease_secs.into());
+        }
+        Ok(())
+    }
+
+    async fn complete_idempotency_key(&self, user_id: Uuid, key: &str, status: u16, body: &str, ttl_secs: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        if let Some(record) = data.idempotency_keys.get_mut(&(user_id, key.to_string())) {
+            record.response = Some((status, body.to_string()));
+            record.expires_at = now() + Duration::seconds(ttl_secs.into());
+        }
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, user_id: Uuid, key: &str) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.idempotency_keys.remove(&(user_id, key.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn renewing_keeps_a_running_claim_from_lapsing() {
+        let store = MemoryStore::new();
+        let user_id = Uuid::nil();
+
+        // A lease of no time at all lapses straight away unless renewed
+        let claim = store.claim_idempotency_key(user_id, "lapsed", "hash", 0).await.unwrap();
+        assert!(matches!(claim, IdempotencyClaim::New));
+        let retry = store.claim_idempotency_key(user_id, "lapsed", "hash", 0).await.unwrap();
+        assert!(matches!(retry, IdempotencyClaim::New));
+
+        store.claim_idempotency_key(user_id, "renewed", "hash", 0).await.unwrap();
+        store.renew_idempotency_key(user_id, "renewed", 60).await.unwrap();
+        let retry = store.claim_idempotency_key(user_id, "renewed", "hash", 0).await.unwrap();
+        assert!(matches!(retry, IdempotencyClaim::InProgress));
+    }
+
+    #[tokio::test]
+    async fn renewing_leaves_a_completed_key_alone() {
+        let store = MemoryStore::new();
+        let user_id = Uuid::nil();
+
+        store.claim_idempotency_key(user_id, "done", "hash", 60).await.unwrap();
+        store.complete_idempotency_key(user_id, "done", 201, "{}", 3600).await.unwrap();
+        store.renew_idempotency_key(user_id, "done", 0).await.unwrap();
+
+        let retry = store.claim_idempotency_key(user_id, "done", "hash", 60).await.unwrap();
+        assert!(matches!(retry, IdempotencyClaim::Replay { status: 201, .. }));
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{
+    Asset, CostBasisMethod, FeeSchedule, FeeTier, FeeType, OrderSide, OrderStatus, OrderType,
+    Portfolio, PortfolioSnapshot, RecurrenceFrequency, RecurringRunStatus, RiskMetrics, TimeInForce,
+    TradingLimits, User,
+};
+use super::{Store, StoreError, StoreResult};
+
+mod assets;
+mod fees;
+mod idempotency;
+mod orders;
+mod performance;
+mod portfolios;
+mod rebalancing;
+mod recurring;
+mod risk;
+mod sessions;
+mod tax_lots;
+mod trading_rules;
+mod users;
+
+/// Store that keeps everything in process memory.
+///
+/// It follows the rules of the stored procedures in `database/migrations`
+/// closely enough to run the whole HTTP API without SQL Server, which is what
+/// local development and the API tests use it for. Nothing survives a restart.
+pub struct MemoryStore {
+    data: Mutex<MemoryData>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    /// Starts empty apart from the default fee schedules and quantity increments
+    pub fn new() -> Self {
+        let mut data = MemoryData::default();
+        data.seed_fee_schedules();
+        data.seed_quantity_increments();
+        Self { data: Mutex::new(data) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryData> {
+        // A panic while holding the lock leaves the data usable, so keep going
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    fn backend_name(&self) -> &'static str {
+        "in-memory"
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        Ok(())
+    }
+}
+
+// =============================================================
+// RECORDS
+// =============================================================
+
+#[derive(Clone)]
+struct UserRecord {
+    user_id: Uuid,
+    name: String,
+    email: String,
+    password: String,
+    country_of_residence: String,
+    iban: String,
+    user_type: String,
+    account_balance: Decimal,
+    payment_method_type: Option<String>,
+    payment_method_details: Option<String>,
+    payment_method_expiry: Option<NaiveDate>,
+    payment_method_active: bool,
+    is_premium: bool,
+    premium_start_date: Option<NaiveDateTime>,
+    premium_end_date: Option<NaiveDateTime>,
+    monthly_subscription_rate: Decimal,
+    auto_renew_subscription: bool,
+    last_subscription_payment: Option<NaiveDateTime>,
+    next_subscription_payment: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl UserRecord {
+    fn to_user(&self) -> User {
+        User {
+            user_id: self.user_id,
+            name: self.name.clone(),
+            email: self.email.clone(),
+            country_of_residence: self.country_of_residence.clone(),
+            iban: self.iban.clone(),
+            user_type: self.user_type.clone(),
+            created_at: self.created_at.to_string(),
+            updated_at: self.updated_at.to_string(),
+        }
+    }
+
+    fn subscription_expired(&self, now: NaiveDateTime) -> bool {
+        self.is_premium && self.premium_end_date.is_some_and(|end| end <= now)
+    }
+
+    fn days_remaining_in_subscription(&self, now: NaiveDateTime) -> i32 {
+        match self.premium_end_date {
+            Some(end) if self.is_premium && end > now => (end - now).num_days() as i32,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct PortfolioRecord {
+    portfolio_id: i32,
+    user_id: Uuid,
+    name: String,
+    creation_date: NaiveDateTime,
+    current_funds: Decimal,
+    current_profit_pct: Decimal,
+    cost_basis_method: CostBasisMethod,
+    last_updated: NaiveDateTime,
+}
+
+impl PortfolioRecord {
+    fn to_portfolio(&self) -> Portfolio {
+        Portfolio {
+            portfolio_id: self.portfolio_id,
+            user_id: self.user_id,
+            name: self.name.clone(),
+            creation_date: self.creation_date.to_string(),
+            current_funds: self.current_funds,
+            current_profit_pct: self.current_profit_pct,
+            last_updated: self.last_updated.to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AssetRecord {
+    asset_id: i32,
+    symbol: String,
+    name: String,
+    asset_type: String,
+    price: Decimal,
+    volume: i64,
+    available_shares: Decimal,
+    last_updated: NaiveDateTime,
+}
+
+impl AssetRecord {
+    fn to_asset(&self) -> Asset {
+        Asset {
+            asset_id: self.asset_id,
+            name: self.name.clone(),
+            symbol: self.symbol.clone(),
+            asset_type: self.asset_type.clone(),
+            price: self.price,
+            volume: self.volume,
+            available_shares: self.available_shares,
+            last_updated: self.last_updated.to_string(),
+        }
+    }
+}
+
+/// Type-specific details, one table per variant in SQL Server
+#[derive(Clone)]
+enum AssetDetails {
+    Stock { sector: String, country: String, market_cap: Decimal },
+    Crypto { blockchain: String, max_supply: Option<Decimal>, circulating_supply: Decimal },
+    Index { country: String, region: String, index_type: String, component_count: Option<i32> },
+    Commodity { category: String, unit: String },
+}
+
+#[derive(Clone)]
+struct AssetDetailsRecord {
+    details: AssetDetails,
+    last_updated: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct PriceRecord {
+    asset_id: i32,
+    as_of: NaiveDateTime,
+    price: Decimal,
+    volume: i64,
+}
+
+#[derive(Clone)]
+struct HoldingRecord {
+    holding_id: i64,
+    portfolio_id: i32,
+    asset_id: i32,
+    quantity_held: Decimal,
+    average_price: Decimal,
+    total_cost: Decimal,
+    last_updated: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct TransactionRecord {
+    transaction_id: i64,
+    user_id: Uuid,
+    portfolio_id: i32,
+    asset_id: i32,
+    transaction_type: OrderSide,
+    quantity: Decimal,
+    unit_price: Decimal,
+    transaction_date: NaiveDateTime,
+    status: String,
+}
+
+#[derive(Clone)]
+struct LotRecord {
+    lot_id: i64,
+    portfolio_id: i32,
+    asset_id: i32,
+    buy_transaction_id: Option<i64>,
+    acquired_at: NaiveDateTime,
+    quantity: Decimal,
+    remaining_quantity: Decimal,
+    unit_cost: Decimal,
+}
+
+#[derive(Clone)]
+struct RealizedGainRecord {
+    realized_gain_id: i64,
+    sell_transaction_id: i64,
+    lot_id: i64,
+    quantity: Decimal,
+    cost_basis: Decimal,
+    proceeds: Decimal,
+    cost_basis_method: CostBasisMethod,
+    realized_at: NaiveDateTime,
+}
+
+/// Order details on top of a `Pending` transaction with the same id
+#[derive(Clone)]
+struct OrderRecord {
+    order_id: i64,
+    user_id: Uuid,
+    portfolio_id: i32,
+    asset_id: i32,
+    side: OrderSide,
+    order_type: OrderType,
+    time_in_force: TimeInForce,
+    quantity: Decimal,
+    limit_price: Option<Decimal>,
+    stop_price: Option<Decimal>,
+    triggered: bool,
+    reserved_funds: Decimal,
+    status: OrderStatus,
+    status_reason: Option<String>,
+    fill_price: Option<Decimal>,
+    executed_at: Option<NaiveDateTime>,
+    expires_at: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct FundTransactionRecord {
+    fund_transaction_id: i64,
+    user_id: Uuid,
+    portfolio_id: Option<i32>,
+    transaction_type: String,
+    amount: Decimal,
+    balance_after: Decimal,
+    description: Option<String>,
+    related_asset_transaction_id: Option<i64>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct FeeScheduleRecord {
+    fee_schedule_id: i32,
+    user_type: String,
+    asset_type: Option<String>,
+    fee_type: FeeType,
+    flat_fee: Option<Decimal>,
+    rate: Option<Decimal>,
+    minimum_fee: Decimal,
+    tiers: Vec<FeeTier>,
+    updated_at: NaiveDateTime,
+}
+
+impl FeeScheduleRecord {
+    fn to_schedule(&self) -> FeeSchedule {
+        FeeSchedule {
+            fee_schedule_id: self.fee_schedule_id,
+            user_type: self.user_type.clone(),
+            asset_type: self.asset_type.clone(),
+            fee_type: self.fee_type,
+            flat_fee: self.flat_fee,
+            rate: self.rate,
+            minimum_fee: self.minimum_fee,
+            tiers: self.tiers.clone(),
+            updated_at: self.updated_at.to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TradingLimitsRecord {
+    max_position_pct: Option<Decimal>,
+    max_order_notional: Option<Decimal>,
+    max_daily_volume: Option<Decimal>,
+    max_price_age_minutes: Option<i32>,
+    updated_at: NaiveDateTime,
+}
+
+impl TradingLimitsRecord {
+    fn to_limits(&self, portfolio_id: i32) -> TradingLimits {
+        TradingLimits {
+            portfolio_id,
+            max_position_pct: self.max_position_pct,
+            max_order_notional: self.max_order_notional,
+            max_daily_volume: self.max_daily_volume,
+            max_price_age_minutes: self.max_price_age_minutes,
+            updated_at: Some(self.updated_at.to_string()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AllocationTargetRecord {
+    asset_id: Option<i32>,
+    asset_type: Option<String>,
+    weight: Decimal,
+}
+
+#[derive(Clone)]
+struct AllocationTargetsRecord {
+    drift_tolerance_pct: Decimal,
+    min_trade_value: Decimal,
+    targets: Vec<AllocationTargetRecord>,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct RecurringInvestmentRecord {
+    schedule_id: i32,
+    portfolio_id: i32,
+    asset_id: i32,
+    amount: Decimal,
+    frequency: RecurrenceFrequency,
+    day_of_week: Option<i32>,
+    day_of_month: Option<i32>,
+    /// None while paused
+    next_run_date: Option<NaiveDate>,
+    last_run_date: Option<NaiveDate>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct RecurringRunRecord {
+    run_id: i64,
+    schedule_id: i32,
+    run_date: NaiveDate,
+    status: RecurringRunStatus,
+    transaction_id: Option<i64>,
+    fund_transaction_id: Option<i64>,
+    quantity: Option<Decimal>,
+    unit_price: Option<Decimal>,
+    total_cost: Option<Decimal>,
+    reason: Option<String>,
+    executed_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct QuantityIncrementRecord {
+    increment: Decimal,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+struct RiskMetricsRecord {
+    metric_id: i32,
+    user_id: Uuid,
+    maximum_drawdown: Option<f64>,
+    beta: Option<f64>,
+    sharpe_ratio: Option<f64>,
+    sortino_ratio: Option<f64>,
+    calmar_ratio: Option<f64>,
+    absolute_return: Option<f64>,
+    volatility_score: Option<f64>,
+    risk_level: String,
+    captured_at: NaiveDateTime,
+}
+
+impl RiskMetricsRecord {
+    fn to_metrics(&self) -> RiskMetrics {
+        RiskMetrics {
+            metric_id: self.metric_id,
+            user_id: self.user_id,
+            maximum_drawdown: self.maximum_drawdown,
+            beta: self.beta,
+            sharpe_ratio: self.sharpe_ratio,
+            sortino_ratio: self.sortino_ratio,
+            calmar_ratio: self.calmar_ratio,
+            absolute_return: self.absolute_return,
+            volatility_score: self.volatility_score,
+            risk_level: self.risk_level.clone(),
+            captured_at: self.captured_at.to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SessionRecord {
+    user_id: Uuid,
+    refresh_token_hash: String,
+    previous_token_hash: Option<String>,
+    expires_at: NaiveDateTime,
+    revoked: bool,
+}
+
+impl SessionRecord {
+    fn active(&self, now: NaiveDateTime) -> bool {
+        !self.revoked && self.expires_at > now
+    }
+}
+
+#[derive(Clone)]
+struct IdempotencyRecord {
+    request_hash: String,
+    /// Status and body, once the first request has finished
+    response: Option<(u16, String)>,
+    expires_at: NaiveDateTime,
+}
+
+// =============================================================
+// TABLES
+// =============================================================
+
+#[derive(Clone, Default)]
+struct MemoryData {
+    users: BTreeMap<Uuid, UserRecord>,
+    portfolios: BTreeMap<i32, PortfolioRecord>,
+    assets: BTreeMap<i32, AssetRecord>,
+    asset_details: BTreeMap<i32, AssetDetailsRecord>,
+    prices: Vec<PriceRecord>,
+    holdings: Vec<HoldingRecord>,
+    transactions: Vec<TransactionRecord>,
+    lots: BTreeMap<i64, LotRecord>,
+    realized_gains: Vec<RealizedGainRecord>,
+    orders: BTreeMap<i64, OrderRecord>,
+    fund_transactions: Vec<FundTransactionRecord>,
+    fee_schedules: BTreeMap<i32, FeeScheduleRecord>,
+    trading_limits: BTreeMap<i32, TradingLimitsRecord>,
+    allocation_targets: BTreeMap<i32, AllocationTargetsRecord>,
+    recurring_investments: BTreeMap<i32, RecurringInvestmentRecord>,
+    recurring_runs: BTreeMap<i64, RecurringRunRecord>,
+    /// By portfolio and day
+    portfolio_snapshots: BTreeMap<(i32, NaiveDate), PortfolioSnapshot>,
+    /// By asset type
+    quantity_increments: BTreeMap<String, QuantityIncrementRecord>,
+    risk_metrics: Vec<RiskMetricsRecord>,
+    sessions: BTreeMap<Uuid, SessionRecord>,
+    idempotency_keys: BTreeMap<(Uuid, String), IdempotencyRecord>,
+    last_id: i64,
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+impl MemoryData {
+    /// Hands out identity values; one sequence is enough for every table
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn user(&self, user_id: Uuid) -> StoreResult<&UserRecord> {
+        self.users.get(&user_id)
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))
+    }
+
+    fn user_mut(&mut self, user_id: Uuid) -> StoreResult<&mut UserRecord> {
+        self.users.get_mut(&user_id)
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))
+    }
+
+    fn portfolio(&self, portfolio_id: i32) -> StoreResult<&PortfolioRecord> {
+        self.portfolios.get(&portfolio_id)
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))
+    }
+
+    fn portfolio_mut(&mut self, portfolio_id: i32) -> StoreResult<&mut PortfolioRecord> {
+        self.portfolios.get_mut(&portfolio_id)
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))
+    }
+
+    fn asset(&self, asset_id: i32) -> StoreResult<&AssetRecord> {
+        self.assets.get(&asset_id)
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))
+    }
+
+    fn holdings_of(&self, portfolio_id: i32) -> impl Iterator<Item = &HoldingRecord> {
+        self.holdings.iter().filter(move |h| h.portfolio_id == portfolio_id)
+    }
+
+    /// Market value of a portfolio's holdings at current asset prices
+    fn holdings_value(&self, portfolio_id: i32) -> Decimal {
+        self.holdings_of(portfolio_id)
+            .map(|h| h.quantity_held * self.assets.get(&h.asset_id).map_or(Decimal::ZERO, |a| a.price))
+            .sum()
+    }
+
+    fn holdings_cost(&self, portfolio_id: i32) -> Decimal {
+        self.holdings_of(portfolio_id).map(|h| h.total_cost).sum()
+    }
+
+    fn user_portfolio_ids(&self, user_id: Uuid) -> Vec<i32> {
+        self.portfolios.values()
+            .filter(|p| p.user_id == user_id)
+            .map(|p| p.portfolio_id)
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_fund_transaction(
+        &mut self,
+        user_id: Uuid,
+        portfolio_id: Option<i32>,
+        transaction_type: &str,
+        amount: Decimal,
+        balance_after: Decimal,
+        description: Option<String>,
+        related_asset_transaction_id: Option<i64>,
+    ) -> i64 {
+        let fund_transaction_id = self.next_id();
+        self.fund_transactions.push(FundTransactionRecord {
+            fund_transaction_id,
+            user_id,
+            portfolio_id,
+            transaction_type: transaction_type.to_string(),
+            amount,
+            balance_after,
+            description,
+            related_asset_transaction_id,
+            created_at: now(),
+        });
+        fund_transaction_id
+    }
+}
@@ -0,0 +1,389 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use crate::models::{
+    AmendOrderRequest, Order, OrderSide, OrderStatus, OrderType, PlaceOrderRequest, TimeInForce, round_to_scale,
+    MONEY_SCALE,
+};
+use crate::store::{OrderStore, StoreError, StoreResult};
+use super::{now, MemoryData, MemoryStore, OrderRecord};
+
+impl MemoryData {
+    fn order(&self, order_id: i64) -> StoreResult<&OrderRecord> {
+        self.orders.get(&order_id)
+            .ok_or_else(|| StoreError::NotFound("Order not found".to_string()))
+    }
+
+    /// Fills and cancels only apply to orders still on the book
+    fn pending_order(&self, order_id: i64) -> StoreResult<&OrderRecord> {
+        let order = self.order(order_id)?;
+        if order.status != OrderStatus::Pending {
+            return Err(StoreError::Conflict("Order is no longer pending".to_string()));
+        }
+        Ok(order)
+    }
+
+    fn to_order(&self, order: &OrderRecord) -> Order {
+        Order {
+            order_id: order.order_id,
+            portfolio_id: order.portfolio_id,
+            asset_id: order.asset_id,
+            symbol: self.assets.get(&order.asset_id).map(|a| a.symbol.clone()).unwrap_or_default(),
+            side: order.side,
+            order_type: order.order_type,
+            time_in_force: order.time_in_force,
+            quantity: order.quantity,
+            limit_price: order.limit_price,
+            stop_price: order.stop_price,
+            triggered: order.triggered,
+            reserved_funds: order.reserved_funds,
+            status: order.status,
+            status_reason: order.status_reason.clone(),
+            fill_price: order.fill_price,
+            executed_at: order.executed_at.map(|dt| dt.to_string()),
+            expires_at: order.expires_at.map(|dt| dt.to_string()),
+            created_at: order.created_at.to_string(),
+            updated_at: order.updated_at.to_string(),
+        }
+    }
+
+    fn order_response(&self, order_id: i64) -> StoreResult<Order> {
+        Ok(self.to_order(self.order(order_id)?))
+    }
+
+    /// Cash pending orders hold out of the portfolio's funds, as
+    /// `fn_PortfolioReservedFunds` sums it
+    pub(super) fn reserved_funds(&self, portfolio_id: i32) -> Decimal {
+        self.orders.values()
+            .filter(|o| o.portfolio_id == portfolio_id && o.status == OrderStatus::Pending)
+            .map(|o| o.reserved_funds)
+            .sum()
+    }
+
+    /// Shares not already promised to pending sell orders, other than `except`
+    fn unreserved_holding(&self, portfolio_id: i32, asset_id: i32, except: Option<i64>) -> Decimal {
+        let held: Decimal = self.holdings_of(portfolio_id)
+            .filter(|h| h.asset_id == asset_id)
+            .map(|h| h.quantity_held)
+            .sum();
+        let pending_sells: Decimal = self.orders.values()
+            .filter(|o| o.portfolio_id == portfolio_id && o.asset_id == asset_id)
+            .filter(|o| o.side == OrderSide::Sell && o.status == OrderStatus::Pending)
+            .filter(|o| Some(o.order_id) != except)
+            .map(|o| o.quantity)
+            .sum();
+        held - pending_sells
+    }
+
+    /// Same as `sp_ReleaseOrderFunds`: plain releases are only logged when
+    /// money moves, any other transaction type is always logged
+    fn release_order_funds(&mut self, order_id: i64, transaction_type: &str, description: Option<String>) -> StoreResult<()> {
+        let order = self.order(order_id)?;
+        let (user_id, portfolio_id, reserved) = (order.user_id, order.portfolio_id, order.reserved_funds);
+        if reserved <= Decimal::ZERO && transaction_type == "OrderRelease" {
+            return Ok(());
+        }
+
+        let now = now();
+        let portfolio = self.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds += reserved;
+        portfolio.last_updated = now;
+        let balance_after = portfolio.current_funds;
+
+        if let Some(order) = self.orders.get_mut(&order_id) {
+            order.reserved_funds = Decimal::ZERO;
+            order.updated_at = now;
+        }
+
+        self.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            transaction_type,
+            reserved,
+            balance_after,
+            Some(description.unwrap_or_else(|| format!("Released funds for order {}", order_id))),
+            Some(order_id),
+        );
+        Ok(())
+    }
+
+    /// Moves an order and its transaction out of `Pending`
+    fn close_order(&mut self, order_id: i64, status: OrderStatus, reason: Option<&str>, fill_price: Option<Decimal>) {
+        let now = now();
+        if let Some(order) = self.orders.get_mut(&order_id) {
+            order.status = status;
+            order.status_reason = reason.map(|reason| reason.to_string());
+            order.fill_price = fill_price;
+            order.updated_at = now;
+            if status == OrderStatus::Executed {
+                order.executed_at = Some(now);
+            }
+        }
+        if let Some(transaction) = self.transactions.iter_mut().find(|t| t.transaction_id == order_id) {
+            transaction.status = status.as_str().to_string();
+            if let (OrderStatus::Executed, Some(fill_price)) = (status, fill_price) {
+                transaction.unit_price = fill_price;
+                transaction.transaction_date = now;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl OrderStore for MemoryStore {
+    async fn place_order(&self, portfolio_id: i32, request: &PlaceOrderRequest) -> StoreResult<Order> {
+        let mut data = self.lock();
+        let user_id = data.trading_portfolio(portfolio_id)?;
+        let market_price = data.asset(request.asset_id)?.price;
+
+        // Buys hold their worst-case cost, fee included, until they fill or are cancelled
+        let reserve_price = match request.order_type {
+            OrderType::Market => Some(market_price),
+            OrderType::Stop => request.stop_price,
+            OrderType::Limit | OrderType::StopLimit => request.limit_price,
+        }.unwrap_or(market_price);
+
+        let reserved_funds = match request.side {
+            OrderSide::Buy => {
+                let value = round_to_scale(request.quantity * reserve_price, MONEY_SCALE);
+                let reserved = value + data.max_trading_fee(user_id, request.asset_id, value);
+                if data.portfolio(portfolio_id)?.current_funds < reserved {
+                    return Err(StoreError::InsufficientFunds("Insufficient funds in portfolio for this order".to_string()));
+                }
+                reserved
+            },
+            OrderSide::Sell => {
+                if data.unreserved_holding(portfolio_id, request.asset_id, None) < request.quantity {
+                    return Err(StoreError::BadRequest("Insufficient holdings for this order".to_string()));
+                }
+                Decimal::ZERO
+            },
+        };
+
+        let order_id = data.record_trade(
+            portfolio_id,
+            request.asset_id,
+            request.side,
+            request.quantity,
+            reserve_price,
+            OrderStatus::Pending.as_str(),
+        );
+        let time_in_force = request.time_in_force.unwrap_or_default();
+        let now = now();
+
+        // DAY orders run until the end of the current (UTC) day
+        let expires_at = match time_in_force {
+            TimeInForce::Day => now.date().succ_opt().and_then(|day| day.and_hms_opt(0, 0, 0)),
+            TimeInForce::Gtc | TimeInForce::Ioc => None,
+        };
+
+        data.orders.insert(order_id, OrderRecord {
+            order_id,
+            user_id,
+            portfolio_id,
+            asset_id: request.asset_id,
+            side: request.side,
+            order_type: request.order_type,
+            time_in_force,
+            quantity: request.quantity,
+            limit_price: request.limit_price,
+            stop_price: request.stop_price,
+            triggered: false,
+            reserved_funds,
+            status: OrderStatus::Pending,
+            status_reason: None,
+            fill_price: None,
+            executed_at: None,
+            expires_at,
+            created_at: now,
+            updated_at: now,
+        });
+
+        let portfolio = data.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds -= reserved_funds;
+        portfolio.last_updated = now;
+        let balance_after = portfolio.current_funds;
+
+        // Sells reserve nothing but still get an opening entry
+        let reserved_kind = match request.side {
+            OrderSide::Buy => "funds",
+            OrderSide::Sell => "holdings",
+        };
+        data.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "OrderReservation",
+            Decimal::ZERO - reserved_funds,
+            balance_after,
+            Some(format!("Reserved {} for order {}", reserved_kind, order_id)),
+            Some(order_id),
+        );
+
+        data.order_response(order_id)
+    }
+
+    async fn get_order(&self, order_id: i64) -> StoreResult<Order> {
+        self.lock().order_response(order_id)
+    }
+
+    async fn list_orders(&self, portfolio_id: i32, status: Option<OrderStatus>) -> StoreResult<Vec<Order>> {
+        let data = self.lock();
+        Ok(data.orders.values()
+            .rev()
+            .filter(|o| o.portfolio_id == portfolio_id && status.is_none_or(|status| o.status == status))
+            .map(|o| data.to_order(o))
+            .collect())
+    }
+
+    async fn pending_orders(&self, asset_id: Option<i32>) -> StoreResult<Vec<Order>> {
+        let data = self.lock();
+        Ok(data.orders.values()
+            .filter(|o| o.status == OrderStatus::Pending && asset_id.is_none_or(|id| o.asset_id == id))
+            .map(|o| data.to_order(o))
+            .collect())
+    }
+
+    async fn expired_order_ids(&self) -> StoreResult<Vec<i64>> {
+        let data = self.lock();
+        let now = now();
+        Ok(data.orders.values()
+            .filter(|o| o.status == OrderStatus::Pending && o.expires_at.is_some_and(|at| at <= now))
+            .map(|o| o.order_id)
+            .collect())
+    }
+
+    async fn trigger_order(&self, order_id: i64) -> StoreResult<Order> {
+        let mut data = self.lock();
+        let order = data.orders.get_mut(&order_id)
+            .ok_or_else(|| StoreError::NotFound("Order not found".to_string()))?;
+        order.triggered = true;
+        order.updated_at = now();
+        data.order_response(order_id)
+    }
+
+    async fn fill_order(&self, order_id: i64, price: Decimal) -> StoreResult<Order> {
+        let mut data = self.lock();
+        let order = data.pending_order(order_id)?;
+        let (user_id, portfolio_id, asset_id, side, quantity, reserved) =
+            (order.user_id, order.portfolio_id, order.asset_id, order.side, order.quantity, order.reserved_funds);
+
+        // The reservation goes back to the portfolio before the trade settles
+        let total = round_to_scale(quantity * price, MONEY_SCALE);
+        let fee = data.trading_fee(user_id, asset_id, total);
+        let funds = data.portfolio(portfolio_id)?.current_funds;
+        let fail_reason = match side {
+            OrderSide::Buy => (funds + reserved < total + fee)
+                .then_some("Insufficient funds at fill time"),
+            OrderSide::Sell if !data.holdings_of(portfolio_id).any(|h| h.asset_id == asset_id && h.quantity_held >= quantity) => {
+                Some("Insufficient holdings at fill time")
+            },
+            OrderSide::Sell => (funds + total < fee)
+                .then_some("Insufficient funds at fill time"),
+        };
+
+        match fail_reason {
+            Some(reason) => {
+                data.release_order_funds(order_id, "OrderFailure", Some(format!("Order {} failed: {}", order_id, reason)))?;
+                data.close_order(order_id, OrderStatus::Failed, Some(reason), None);
+            },
+            None => {
+                data.release_order_funds(order_id, "OrderRelease", None)?;
+                match side {
+                    OrderSide::Buy => { data.settle_buy(user_id, order_id, portfolio_id, asset_id, quantity, price, fee)?; },
+                    OrderSide::Sell => { data.settle_sell(order_id, portfolio_id, asset_id, quantity, price, None, fee)?; },
+                }
+                data.close_order(order_id, OrderStatus::Executed, None, Some(price));
+            },
+        }
+
+        data.order_response(order_id)
+    }
+
+    async fn amend_order(&self, order_id: i64, amendment: &AmendOrderRequest) -> StoreResult<Order> {
+        let mut data = self.lock();
+        let order = data.pending_order(order_id)?;
+        let (user_id, portfolio_id, asset_id, side, order_type) =
+            (order.user_id, order.portfolio_id, order.asset_id, order.side, order.order_type);
+        let old_reserved = order.reserved_funds;
+
+        if amendment.quantity.is_some_and(|quantity| quantity <= Decimal::ZERO) {
+            return Err(StoreError::BadRequest("Quantity must be positive".to_string()));
+        }
+        let limits = matches!(order_type, OrderType::Limit | OrderType::StopLimit);
+        if amendment.limit_price.is_some() && !limits {
+            return Err(StoreError::BadRequest("Limit price can only be amended on Limit and StopLimit orders".to_string()));
+        }
+
+        let quantity = amendment.quantity.unwrap_or(order.quantity);
+        let limit_price = amendment.limit_price.or(order.limit_price);
+        // Limit prices are what buys reserve at; other types keep their price
+        let reserve_price = match order_type {
+            OrderType::Limit | OrderType::StopLimit => limit_price,
+            OrderType::Stop => order.stop_price,
+            OrderType::Market => None,
+        };
+        let reserve_price = match reserve_price {
+            Some(price) => price,
+            None => data.transactions.iter()
+                .find(|t| t.transaction_id == order_id)
+                .map(|t| t.unit_price)
+                .unwrap_or_default(),
+        };
+
+        let new_reserved = match side {
+            OrderSide::Buy => {
+                let value = round_to_scale(quantity * reserve_price, MONEY_SCALE);
+                let reserved = value + data.max_trading_fee(user_id, asset_id, value);
+                if data.portfolio(portfolio_id)?.current_funds < reserved - old_reserved {
+                    return Err(StoreError::InsufficientFunds("Insufficient funds in portfolio for this order".to_string()));
+                }
+                reserved
+            },
+            OrderSide::Sell => {
+                if data.unreserved_holding(portfolio_id, asset_id, Some(order_id)) < quantity {
+                    return Err(StoreError::BadRequest("Insufficient holdings for this order".to_string()));
+                }
+                Decimal::ZERO
+            },
+        };
+
+        let now = now();
+        let portfolio = data.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds -= new_reserved - old_reserved;
+        portfolio.last_updated = now;
+        let balance_after = portfolio.current_funds;
+
+        if let Some(order) = data.orders.get_mut(&order_id) {
+            order.quantity = quantity;
+            order.limit_price = limit_price;
+            order.reserved_funds = new_reserved;
+            order.updated_at = now;
+        }
+        if let Some(transaction) = data.transactions.iter_mut().find(|t| t.transaction_id == order_id) {
+            transaction.quantity = quantity;
+            transaction.unit_price = reserve_price;
+        }
+
+        data.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "OrderAmendment",
+            old_reserved - new_reserved,
+            balance_after,
+            Some(format!("Amended order {}", order_id)),
+            Some(order_id),
+        );
+
+        data.order_response(order_id)
+    }
+
+    async fn cancel_order(&self, order_id: i64, reason: &str) -> StoreResult<Order> {
+        let mut data = self.lock();
+        data.pending_order(order_id)?;
+
+        data.release_order_funds(order_id, "OrderCancellation", Some(format!("Cancelled order {}: {}", order_id, reason)))?;
+        data.close_order(order_id, OrderStatus::Cancelled, Some(reason), None);
+
+        data.order_response(order_id)
+    }
+}
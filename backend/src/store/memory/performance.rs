@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::models::PortfolioSnapshot;
+use crate::store::{CashMovement, HistoricalTrade, PerformanceStore, PortfolioHistory, StoreResult};
+use super::{MemoryData, MemoryStore};
+
+/// Fund transactions that only move money in and out of the pending-order reserve
+const ORDER_RESERVE_TYPES: [&str; 5] = ["OrderReservation", "OrderRelease", "OrderAmendment", "OrderCancellation", "OrderFailure"];
+
+impl MemoryData {
+    /// Last price of each day from `from` through `until`, oldest first,
+    /// led by the last close before `from`
+    pub(super) fn daily_closes(&self, asset_id: i32, from: NaiveDate, until: NaiveDate) -> Vec<(NaiveDate, Decimal)> {
+        let mut prices: Vec<_> = self.prices.iter()
+            .filter(|p| p.asset_id == asset_id && p.as_of.date() <= until)
+            .collect();
+        prices.sort_by_key(|p| p.as_of);
+
+        let mut series: Vec<(NaiveDate, Decimal)> = Vec::new();
+        for price in prices {
+            let date = price.as_of.date();
+            match series.last_mut() {
+                Some(last) if last.0 == date => last.1 = price.price,
+                // Only the last close before `from` is needed
+                Some(last) if date <= from => *last = (date, price.price),
+                _ => series.push((date, price.price)),
+            }
+        }
+        series
+    }
+}
+
+#[async_trait]
+impl PerformanceStore for MemoryStore {
+    async fn portfolio_history(&self, portfolio_id: i32, from: Option<NaiveDate>, today: NaiveDate) -> StoreResult<PortfolioHistory> {
+        let data = self.lock();
+        let portfolio = data.portfolio(portfolio_id)?;
+        let created_on = portfolio.creation_date.date();
+        let from = from.unwrap_or(created_on);
+
+        let reserved = data.reserved_funds(portfolio_id);
+        let holdings: Vec<(i32, Decimal)> = data.holdings_of(portfolio_id)
+            .map(|h| (h.asset_id, h.quantity_held))
+            .collect();
+
+        let trades: Vec<HistoricalTrade> = data.transactions.iter()
+            .filter(|t| t.portfolio_id == portfolio_id && t.status == "Executed" && t.transaction_date.date() >= from)
+            .map(|t| HistoricalTrade {
+                asset_id: t.asset_id,
+                side: t.transaction_type,
+                quantity: t.quantity,
+                date: t.transaction_date.date(),
+            })
+            .collect();
+
+        let cash_movements = data.fund_transactions.iter()
+            .filter(|t| t.portfolio_id == Some(portfolio_id) && t.created_at.date() >= from)
+            .filter(|t| !ORDER_RESERVE_TYPES.contains(&t.transaction_type.as_str()))
+            .map(|t| {
+                // Allocations are recorded from the user's side
+                let external = matches!(t.transaction_type.as_str(), "Allocation" | "Deallocation");
+                CashMovement {
+                    date: t.created_at.date(),
+                    amount: if external { -t.amount } else { t.amount },
+                    external,
+                }
+            })
+            .collect();
+
+        let asset_ids: BTreeSet<i32> = holdings.iter().map(|(asset_id, _)| *asset_id)
+            .chain(trades.iter().map(|t| t.asset_id))
+            .collect();
+        let closes = asset_ids.into_iter()
+            .map(|asset_id| {
+                let mut series = today.pred_opt()
+                    .map(|yesterday| data.daily_closes(asset_id, from, yesterday))
+                    .unwrap_or_default();
+                if let Some(asset) = data.assets.get(&asset_id) {
+                    series.push((today, asset.price));
+                }
+                (asset_id, series)
+            })
+            .collect();
+
+        Ok(PortfolioHistory {
+            created_on,
+            cash: portfolio.current_funds + reserved,
+            holdings,
+            trades,
+            cash_movements,
+            closes,
+        })
+    }
+
+    async fn last_snapshot_date(&self, portfolio_id: i32) -> StoreResult<Option<NaiveDate>> {
+        let data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        Ok(data.portfolio_snapshots.range((portfolio_id, NaiveDate::MIN)..=(portfolio_id, NaiveDate::MAX))
+            .next_back()
+            .map(|((_, date), _)| *date))
+    }
+
+    async fn save_portfolio_snapshots(&self, portfolio_id: i32, snapshots: &[PortfolioSnapshot]) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        for snapshot in snapshots {
+            data.portfolio_snapshots.insert((portfolio_id, snapshot.snapshot_date), snapshot.clone());
+        }
+        Ok(())
+    }
+
+    async fn portfolio_snapshots(&self, portfolio_id: i32, from: Option<NaiveDate>, to: Option<NaiveDate>) -> StoreResult<Vec<PortfolioSnapshot>> {
+        let data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        let from = from.unwrap_or(NaiveDate::MIN);
+        let to = to.unwrap_or(NaiveDate::MAX);
+        if from > to {
+            return Ok(Vec::new());
+        }
+        Ok(data.portfolio_snapshots.range((portfolio_id, from)..=(portfolio_id, to))
+            .map(|(_, snapshot)| snapshot.clone())
+            .collect())
+    }
+}
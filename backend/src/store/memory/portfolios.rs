@@ -0,0 +1,678 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{
+    AssetHolding, BatchLeg, BuyAssetRequest, BuyAssetResponse, CostBasisMethod, CreatePortfolioRequest,
+    LotSelection, OrderSide, Portfolio,
+    PortfolioBalance, PortfolioHolding, PortfolioHoldingsSummary, PortfolioSummary,
+    SellAssetRequest, SellAssetResponse, TradeProjection, TradingTransaction, TransactionFilter,
+    UpdatePortfolioRequest, round_to_scale, MONEY_SCALE, PRICE_SCALE,
+};
+use crate::store::{BatchOutcome, PortfolioStore, StoreError, StoreResult, TradePreview, TradingStore};
+use super::{now, HoldingRecord, MemoryData, MemoryStore, PortfolioRecord, TransactionRecord};
+
+/// A buy or sell that passed its checks; `trade_value` is the cost or
+/// proceeds before the fee
+struct TradePlan {
+    user_id: Uuid,
+    unit_price: Decimal,
+    trade_value: Decimal,
+    fee: Decimal,
+}
+
+impl MemoryData {
+    fn holdings_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary> {
+        let portfolio = self.portfolio(portfolio_id)?;
+
+        let holdings: Vec<PortfolioHolding> = self.holdings_of(portfolio_id)
+            .filter_map(|h| {
+                let asset = self.assets.get(&h.asset_id)?;
+                let current_value = h.quantity_held * asset.price;
+                Some(PortfolioHolding {
+                    holding_id: h.holding_id,
+                    portfolio_id,
+                    asset_id: h.asset_id,
+                    asset_name: asset.name.clone(),
+                    symbol: asset.symbol.clone(),
+                    asset_type: asset.asset_type.clone(),
+                    quantity_held: h.quantity_held,
+                    average_price: h.average_price,
+                    total_cost: h.total_cost,
+                    current_price: asset.price,
+                    current_value,
+                    unrealized_gain_loss: current_value - h.total_cost,
+                    last_updated: h.last_updated.to_string(),
+                })
+            })
+            .collect();
+
+        let total_holdings_value: Decimal = holdings.iter().map(|h| h.current_value).sum();
+        let total_cost_basis: Decimal = holdings.iter().map(|h| h.total_cost).sum();
+        let assets_count = holdings.len() as i32;
+
+        Ok(PortfolioHoldingsSummary {
+            portfolio_id,
+            portfolio_name: portfolio.name.clone(),
+            holdings,
+            total_holdings_value,
+            total_cost_basis,
+            total_unrealized_gain_loss: total_holdings_value - total_cost_basis,
+            assets_count,
+        })
+    }
+
+    fn portfolio_balance(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance> {
+        let portfolio = self.portfolio(portfolio_id)?;
+        let reserved_funds = self.reserved_funds(portfolio_id);
+        let holdings_value = self.holdings_value(portfolio_id);
+
+        Ok(PortfolioBalance {
+            portfolio_id,
+            portfolio_name: portfolio.name.clone(),
+            cash_balance: portfolio.current_funds,
+            reserved_funds,
+            holdings_value,
+            total_portfolio_value: portfolio.current_funds + reserved_funds + holdings_value,
+            holdings_count: Some(self.holdings_of(portfolio_id).count() as i32),
+            fees_paid: self.fees_paid(portfolio_id),
+        })
+    }
+
+    /// Checks the ownership rule the trading procedures start with
+    pub(super) fn trading_portfolio(&self, portfolio_id: i32) -> StoreResult<Uuid> {
+        let user_id = self.portfolio(portfolio_id)?.user_id;
+        if !self.users.contains_key(&user_id) {
+            return Err(StoreError::NotFound("User or portfolio not found".to_string()));
+        }
+        Ok(user_id)
+    }
+
+    pub(super) fn record_trade(
+        &mut self,
+        portfolio_id: i32,
+        asset_id: i32,
+        transaction_type: OrderSide,
+        quantity: Decimal,
+        unit_price: Decimal,
+        status: &str,
+    ) -> i64 {
+        let transaction_id = self.next_id();
+        let user_id = self.portfolios.get(&portfolio_id).map(|p| p.user_id).unwrap_or_default();
+        self.transactions.push(TransactionRecord {
+            transaction_id,
+            user_id,
+            portfolio_id,
+            asset_id,
+            transaction_type,
+            quantity,
+            unit_price,
+            transaction_date: now(),
+            status: status.to_string(),
+        });
+        transaction_id
+    }
+
+    /// Checks a buy the way `sp_BuyAsset` does before it writes anything
+    fn plan_buy(&self, request: &BuyAssetRequest) -> StoreResult<TradePlan> {
+        let user_id = self.trading_portfolio(request.portfolio_id)?;
+
+        let unit_price = match request.unit_price {
+            Some(price) => price,
+            None => self.asset(request.asset_id)?.price,
+        };
+        self.asset(request.asset_id)?;
+
+        let total_cost = round_to_scale(request.quantity * unit_price, MONEY_SCALE);
+        let fee = self.trading_fee(user_id, request.asset_id, total_cost);
+        if self.portfolio(request.portfolio_id)?.current_funds < total_cost + fee {
+            return Err(StoreError::InsufficientFunds("Insufficient portfolio funds to buy asset".to_string()));
+        }
+
+        Ok(TradePlan { user_id, unit_price, trade_value: total_cost, fee })
+    }
+
+    /// Checks a sell the way `sp_SellAsset` does before it writes anything
+    fn plan_sell(&self, request: &SellAssetRequest) -> StoreResult<TradePlan> {
+        let user_id = self.trading_portfolio(request.portfolio_id)?;
+
+        // Specific-lot portfolios have to say which lots are sold
+        if request.lots.is_none()
+            && self.portfolio(request.portfolio_id)?.cost_basis_method == CostBasisMethod::SpecificLot
+        {
+            return Err(StoreError::BadRequest(
+                "Lots to sell are required when the portfolio uses SpecificLot cost basis".to_string(),
+            ));
+        }
+
+        if !self.holdings_of(request.portfolio_id)
+            .any(|h| h.asset_id == request.asset_id && h.quantity_held >= request.quantity)
+        {
+            return Err(StoreError::BadRequest("Insufficient holdings to sell".to_string()));
+        }
+
+        let unit_price = match request.unit_price {
+            Some(price) => price,
+            None => self.asset(request.asset_id)?.price,
+        };
+
+        let gross_proceeds = round_to_scale(request.quantity * unit_price, MONEY_SCALE);
+        let fee = self.trading_fee(user_id, request.asset_id, gross_proceeds);
+        if self.portfolio(request.portfolio_id)?.current_funds + gross_proceeds < fee {
+            return Err(StoreError::InsufficientFunds("Insufficient funds in portfolio to pay the trading fee".to_string()));
+        }
+
+        // Checked up front so a rejected selection leaves no transaction behind
+        self.plan_lot_closure(request.portfolio_id, request.asset_id, request.quantity, request.lots.as_deref())?;
+
+        Ok(TradePlan { user_id, unit_price, trade_value: gross_proceeds, fee })
+    }
+
+    /// Executes a buy that passes `plan_buy`, as `sp_BuyAsset` does
+    fn buy(&mut self, request: &BuyAssetRequest) -> StoreResult<BuyAssetResponse> {
+        let plan = self.plan_buy(request)?;
+
+        let transaction_id = self.record_trade(
+            request.portfolio_id,
+            request.asset_id,
+            OrderSide::Buy,
+            request.quantity,
+            plan.unit_price,
+            "Executed",
+        );
+        let (total_cost, remaining_funds) = self.settle_buy(
+            plan.user_id,
+            transaction_id,
+            request.portfolio_id,
+            request.asset_id,
+            request.quantity,
+            plan.unit_price,
+            plan.fee,
+        )?;
+
+        Ok(BuyAssetResponse {
+            status: "Success".to_string(),
+            transaction_id,
+            quantity_purchased: request.quantity,
+            price_per_share: plan.unit_price,
+            total_cost: total_cost + plan.fee,
+            fee: plan.fee,
+            remaining_funds,
+        })
+    }
+
+    /// Executes a sell that passes `plan_sell`, as `sp_SellAsset` does
+    fn sell(&mut self, request: &SellAssetRequest) -> StoreResult<SellAssetResponse> {
+        let plan = self.plan_sell(request)?;
+
+        let transaction_id = self.record_trade(
+            request.portfolio_id,
+            request.asset_id,
+            OrderSide::Sell,
+            request.quantity,
+            plan.unit_price,
+            "Executed",
+        );
+        let (total_proceeds, new_funds_balance, cost_basis) = self.settle_sell(
+            transaction_id,
+            request.portfolio_id,
+            request.asset_id,
+            request.quantity,
+            plan.unit_price,
+            request.lots.as_deref(),
+            plan.fee,
+        )?;
+
+        Ok(SellAssetResponse {
+            status: "Success".to_string(),
+            transaction_id,
+            quantity_sold: request.quantity,
+            price_per_share: plan.unit_price,
+            total_proceeds: total_proceeds - plan.fee,
+            fee: plan.fee,
+            new_funds_balance,
+            cost_basis,
+            realized_gain: total_proceeds - plan.fee - cost_basis,
+        })
+    }
+
+    /// Quantity held of an asset and what it cost, or zeros when none is held
+    fn position(&self, portfolio_id: i32, asset_id: i32) -> (Decimal, Decimal) {
+        self.holdings_of(portfolio_id)
+            .find(|h| h.asset_id == asset_id)
+            .map_or((Decimal::ZERO, Decimal::ZERO), |h| (h.quantity_held, h.total_cost))
+    }
+
+    /// Pays for a purchase and its fee out of portfolio cash, as `sp_BuyAsset`
+    /// does once the funds are checked; returns the total cost before the fee
+    /// and the remaining funds
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn settle_buy(
+        &mut self,
+        user_id: Uuid,
+        transaction_id: i64,
+        portfolio_id: i32,
+        asset_id: i32,
+        quantity: Decimal,
+        unit_price: Decimal,
+        fee: Decimal,
+    ) -> StoreResult<(Decimal, Decimal)> {
+        let total_cost = round_to_scale(quantity * unit_price, MONEY_SCALE);
+        // The fee is part of what the shares cost
+        let cost = total_cost + fee;
+        let unit_cost = round_to_scale(cost / quantity, PRICE_SCALE);
+        let now = now();
+
+        let portfolio = self.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds -= total_cost;
+        portfolio.last_updated = now;
+        let remaining_funds = portfolio.current_funds;
+
+        match self.holdings.iter_mut().find(|h| h.portfolio_id == portfolio_id && h.asset_id == asset_id) {
+            Some(holding) => {
+                holding.average_price = round_to_scale(
+                    (holding.total_cost + cost) / (holding.quantity_held + quantity),
+                    PRICE_SCALE,
+                );
+                holding.quantity_held += quantity;
+                holding.total_cost += cost;
+                holding.last_updated = now;
+            },
+            None => {
+                let holding_id = self.next_id();
+                self.holdings.push(HoldingRecord {
+                    holding_id,
+                    portfolio_id,
+                    asset_id,
+                    quantity_held: quantity,
+                    average_price: unit_cost,
+                    total_cost: cost,
+                    last_updated: now,
+                });
+            },
+        }
+
+        // Each purchase opens its own tax lot
+        self.open_tax_lot(portfolio_id, asset_id, transaction_id, quantity, unit_cost);
+
+        self.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "AssetPurchase",
+            -total_cost,
+            remaining_funds,
+            Some(format!("Purchased {} shares of asset ID {}", quantity, asset_id)),
+            Some(transaction_id),
+        );
+        self.charge_trading_fee(portfolio_id, transaction_id, fee)?;
+
+        Ok((total_cost, remaining_funds - fee))
+    }
+
+    /// Turns holdings into portfolio cash less the fee, as `sp_SellAsset` does
+    /// once the holdings are checked, taking the cost basis from the tax lots
+    /// sold; returns the proceeds before the fee, the new funds balance and
+    /// the cost basis
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn settle_sell(
+        &mut self,
+        transaction_id: i64,
+        portfolio_id: i32,
+        asset_id: i32,
+        quantity: Decimal,
+        unit_price: Decimal,
+        lots: Option<&[LotSelection]>,
+        fee: Decimal,
+    ) -> StoreResult<(Decimal, Decimal, Decimal)> {
+        let holding_index = self.holdings.iter()
+            .position(|h| h.portfolio_id == portfolio_id && h.asset_id == asset_id)
+            .ok_or_else(|| StoreError::BadRequest("Insufficient holdings to sell".to_string()))?;
+        let closure = self.plan_lot_closure(portfolio_id, asset_id, quantity, lots)?;
+
+        let total_proceeds = round_to_scale(quantity * unit_price, MONEY_SCALE);
+        let now = now();
+
+        let portfolio = self.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds += total_proceeds;
+        portfolio.last_updated = now;
+        let (user_id, new_funds_balance) = (portfolio.user_id, portfolio.current_funds);
+
+        let cost_basis = self.close_tax_lots(closure, transaction_id, quantity, unit_price, fee);
+
+        let holding = &mut self.holdings[holding_index];
+        let new_quantity = holding.quantity_held - quantity;
+        if new_quantity > Decimal::ZERO {
+            holding.quantity_held = new_quantity;
+            holding.total_cost -= cost_basis;
+            holding.average_price = round_to_scale(holding.total_cost / new_quantity, PRICE_SCALE);
+            holding.last_updated = now;
+        } else {
+            // Sold all shares
+            self.holdings.remove(holding_index);
+        }
+
+        self.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "AssetSale",
+            total_proceeds,
+            new_funds_balance,
+            Some(format!("Sold {} shares of asset ID {}", quantity, asset_id)),
+            Some(transaction_id),
+        );
+        self.charge_trading_fee(portfolio_id, transaction_id, fee)?;
+
+        Ok((total_proceeds, new_funds_balance - fee, cost_basis))
+    }
+}
+
+#[async_trait]
+impl PortfolioStore for MemoryStore {
+    async fn list_portfolios(&self, user_id: Option<Uuid>) -> StoreResult<Vec<Portfolio>> {
+        let data = self.lock();
+        let mut portfolios: Vec<&PortfolioRecord> = data.portfolios.values()
+            .filter(|p| user_id.is_none_or(|id| p.user_id == id))
+            .collect();
+        portfolios.sort_by_key(|p| std::cmp::Reverse(p.creation_date));
+        Ok(portfolios.into_iter().map(PortfolioRecord::to_portfolio).collect())
+    }
+
+    async fn create_portfolio(&self, portfolio: &CreatePortfolioRequest) -> StoreResult<Portfolio> {
+        let mut data = self.lock();
+        if !data.users.contains_key(&portfolio.user_id) {
+            return Err(StoreError::BadRequest("User does not exist".to_string()));
+        }
+
+        let name = portfolio.name.trim();
+        if name.is_empty() {
+            return Err(StoreError::BadRequest("Portfolio name is required".to_string()));
+        }
+
+        let initial_funds = portfolio.initial_funds.unwrap_or(Decimal::ZERO);
+        if initial_funds < Decimal::ZERO {
+            return Err(StoreError::BadRequest("Initial funds cannot be negative".to_string()));
+        }
+
+        let now = now();
+        let record = PortfolioRecord {
+            portfolio_id: data.next_id() as i32,
+            user_id: portfolio.user_id,
+            name: name.to_string(),
+            creation_date: now,
+            current_funds: initial_funds,
+            current_profit_pct: Decimal::ZERO,
+            cost_basis_method: CostBasisMethod::default(),
+            last_updated: now,
+        };
+
+        let created = record.to_portfolio();
+        data.portfolios.insert(record.portfolio_id, record);
+        Ok(created)
+    }
+
+    async fn get_portfolio(&self, portfolio_id: i32) -> StoreResult<Portfolio> {
+        Ok(self.lock().portfolio(portfolio_id)?.to_portfolio())
+    }
+
+    async fn update_portfolio(&self, portfolio_id: i32, update: &UpdatePortfolioRequest) -> StoreResult<Portfolio> {
+        let mut data = self.lock();
+        let portfolio = data.portfolio_mut(portfolio_id)?;
+
+        let Some(name) = update.name.as_deref().map(str::trim) else {
+            return Err(StoreError::BadRequest("No fields to update".to_string()));
+        };
+        if name.is_empty() {
+            return Err(StoreError::BadRequest("Portfolio name cannot be empty".to_string()));
+        }
+
+        portfolio.name = name.to_string();
+        portfolio.last_updated = now();
+
+        Ok(portfolio.to_portfolio())
+    }
+
+    async fn delete_portfolio(&self, portfolio_id: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        if data.holdings_of(portfolio_id).next().is_some() {
+            return Err(StoreError::Conflict("Cannot delete portfolio with active holdings. Please sell all assets first.".to_string()));
+        }
+
+        // Transactions.PortfolioID has no cascade in SQL Server
+        if data.transactions.iter().any(|t| t.portfolio_id == portfolio_id) {
+            return Err(StoreError::Conflict("Cannot delete portfolio due to related transactions. Contact support if needed.".to_string()));
+        }
+
+        data.trading_limits.remove(&portfolio_id);
+        data.allocation_targets.remove(&portfolio_id);
+        data.remove_recurring_investments(|r| r.portfolio_id == portfolio_id);
+        data.portfolio_snapshots.retain(|(id, _), _| *id != portfolio_id);
+        data.portfolios.remove(&portfolio_id);
+        Ok(())
+    }
+
+    async fn portfolio_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioSummary> {
+        let data = self.lock();
+        let portfolio = data.portfolio(portfolio_id)?;
+
+        let invested = data.holdings_cost(portfolio_id);
+        let market_value = data.holdings_value(portfolio_id);
+        let current_profit_pct = if invested > Decimal::ZERO {
+            round_to_scale((market_value - invested) / invested * Decimal::ONE_HUNDRED, MONEY_SCALE)
+        } else {
+            Decimal::ZERO
+        };
+
+        Ok(PortfolioSummary {
+            portfolio_id,
+            portfolio_name: portfolio.name.clone(),
+            owner: data.users.get(&portfolio.user_id).map(|u| u.name.clone()).unwrap_or_default(),
+            current_funds: portfolio.current_funds,
+            current_profit_pct,
+            creation_date: portfolio.creation_date.to_string(),
+            total_trades: data.transactions.iter()
+                .filter(|t| t.portfolio_id == portfolio_id && t.status == "Executed")
+                .count() as i32,
+        })
+    }
+
+    async fn portfolio_holdings(&self, portfolio_id: i32) -> StoreResult<Vec<AssetHolding>> {
+        let data = self.lock();
+        let portfolio = data.portfolio(portfolio_id)?;
+
+        Ok(data.holdings_of(portfolio_id)
+            .filter_map(|h| {
+                let asset = data.assets.get(&h.asset_id)?;
+                Some(AssetHolding {
+                    portfolio_id,
+                    portfolio_name: portfolio.name.clone(),
+                    asset_id: h.asset_id,
+                    asset_name: asset.name.clone(),
+                    symbol: asset.symbol.clone(),
+                    asset_type: asset.asset_type.clone(),
+                    quantity_held: h.quantity_held,
+                    current_price: asset.price,
+                    market_value: h.quantity_held * asset.price,
+                })
+            })
+            .collect())
+    }
+
+    async fn portfolio_balance(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance> {
+        self.lock().portfolio_balance(portfolio_id)
+    }
+
+    async fn portfolio_balance_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance> {
+        Ok(PortfolioBalance {
+            holdings_count: None,
+            ..self.lock().portfolio_balance(portfolio_id)?
+        })
+    }
+
+    async fn holdings_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary> {
+        self.lock().holdings_summary(portfolio_id)
+    }
+
+    async fn holdings_summary_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary> {
+        self.lock().holdings_summary(portfolio_id)
+    }
+}
+
+#[async_trait]
+impl TradingStore for MemoryStore {
+    async fn buy_asset(&self, request: &BuyAssetRequest) -> StoreResult<BuyAssetResponse> {
+        self.lock().buy(request)
+    }
+
+    async fn sell_asset(&self, request: &SellAssetRequest) -> StoreResult<SellAssetResponse> {
+        self.lock().sell(request)
+    }
+
+    async fn preview_buy(&self, request: &BuyAssetRequest) -> StoreResult<TradePreview> {
+        let data = self.lock();
+        let plan = data.plan_buy(request)?;
+
+        let funds = data.portfolio(request.portfolio_id)?.current_funds;
+        let remaining_funds = funds - plan.trade_value - plan.fee;
+        let (held, held_cost) = data.position(request.portfolio_id, request.asset_id);
+        let quantity_held_after = held + request.quantity;
+        let average_price_after = if held > Decimal::ZERO {
+            round_to_scale((held_cost + plan.trade_value + plan.fee) / quantity_held_after, PRICE_SCALE)
+        } else {
+            round_to_scale((plan.trade_value + plan.fee) / request.quantity, PRICE_SCALE)
+        };
+
+        // The cash spent comes back as shares valued at the market price
+        let market_value = request.quantity * data.asset(request.asset_id)?.price;
+        let portfolio_value_after = round_to_scale(
+            remaining_funds + data.holdings_value(request.portfolio_id) + market_value,
+            MONEY_SCALE,
+        );
+
+        Ok(TradePreview {
+            projection: TradeProjection::Buy(BuyAssetResponse {
+                status: "Preview".to_string(),
+                transaction_id: 0,
+                quantity_purchased: request.quantity,
+                price_per_share: plan.unit_price,
+                total_cost: plan.trade_value + plan.fee,
+                fee: plan.fee,
+                remaining_funds,
+            }),
+            quantity_held_after,
+            average_price_after,
+            portfolio_value_after,
+        })
+    }
+
+    async fn preview_sell(&self, request: &SellAssetRequest) -> StoreResult<TradePreview> {
+        let data = self.lock();
+        let plan = data.plan_sell(request)?;
+
+        let closure = data.plan_lot_closure(request.portfolio_id, request.asset_id, request.quantity, request.lots.as_deref())?;
+        let cost_basis = data.lot_closure_cost_basis(&closure);
+
+        let funds = data.portfolio(request.portfolio_id)?.current_funds;
+        let new_funds_balance = funds + plan.trade_value - plan.fee;
+        let (held, held_cost) = data.position(request.portfolio_id, request.asset_id);
+        let quantity_held_after = held - request.quantity;
+        let average_price_after = if quantity_held_after > Decimal::ZERO {
+            round_to_scale((held_cost - cost_basis) / quantity_held_after, PRICE_SCALE)
+        } else {
+            Decimal::ZERO
+        };
+
+        let market_value = request.quantity * data.asset(request.asset_id)?.price;
+        let portfolio_value_after = round_to_scale(
+            new_funds_balance + data.holdings_value(request.portfolio_id) - market_value,
+            MONEY_SCALE,
+        );
+
+        Ok(TradePreview {
+            projection: TradeProjection::Sell(SellAssetResponse {
+                status: "Preview".to_string(),
+                transaction_id: 0,
+                quantity_sold: request.quantity,
+                price_per_share: plan.unit_price,
+                total_proceeds: plan.trade_value - plan.fee,
+                fee: plan.fee,
+                new_funds_balance,
+                cost_basis,
+                realized_gain: plan.trade_value - plan.fee - cost_basis,
+            }),
+            quantity_held_after,
+            average_price_after,
+            portfolio_value_after,
+        })
+    }
+
+    async fn execute_batch(&self, portfolio_id: i32, legs: &[&BatchLeg]) -> StoreResult<BatchOutcome> {
+        let mut data = self.lock();
+
+        // Trade on a copy and only keep it once every leg has gone through
+        let mut batch = data.clone();
+        let mut results = Vec::with_capacity(legs.len());
+        for (index, leg) in legs.iter().enumerate() {
+            let result = match leg.side {
+                OrderSide::Buy => batch.buy(&BuyAssetRequest {
+                    portfolio_id,
+                    asset_id: leg.asset_id,
+                    quantity: leg.quantity,
+                    unit_price: leg.unit_price,
+                }).map(TradeProjection::Buy),
+                OrderSide::Sell => batch.sell(&SellAssetRequest {
+                    portfolio_id,
+                    asset_id: leg.asset_id,
+                    quantity: leg.quantity,
+                    unit_price: leg.unit_price,
+                    lots: leg.lots.clone(),
+                }).map(TradeProjection::Sell),
+            };
+            match result {
+                Ok(projection) => results.push(projection),
+                Err(error) => return Ok(BatchOutcome::RolledBack(index, error)),
+            }
+        }
+
+        *data = batch;
+        Ok(BatchOutcome::Committed(results))
+    }
+
+    async fn list_transactions(
+        &self,
+        portfolio_id: i32,
+        filter: &TransactionFilter,
+        before: Option<i64>,
+        limit: Option<usize>,
+    ) -> StoreResult<Vec<TradingTransaction>> {
+        let data = self.lock();
+        Ok(data.transactions.iter()
+            .rev()
+            .filter(|t| t.portfolio_id == portfolio_id && before.is_none_or(|before| t.transaction_id < before))
+            .filter(|t| filter.asset_id.is_none_or(|id| t.asset_id == id))
+            .filter(|t| filter.transaction_type.is_none_or(|side| t.transaction_type == side))
+            .filter(|t| filter.status.is_none_or(|status| t.status == status.as_str()))
+            .filter(|t| filter.from.is_none_or(|from| t.transaction_date.date() >= from))
+            .filter(|t| filter.to.is_none_or(|to| t.transaction_date.date() <= to))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|t| {
+                let asset = data.assets.get(&t.asset_id);
+                TradingTransaction {
+                    transaction_id: t.transaction_id,
+                    user_id: t.user_id,
+                    portfolio_id: t.portfolio_id,
+                    asset_id: t.asset_id,
+                    symbol: asset.map(|a| a.symbol.clone()).unwrap_or_default(),
+                    asset_name: asset.map(|a| a.name.clone()).unwrap_or_default(),
+                    transaction_type: t.transaction_type.as_str().to_string(),
+                    quantity: t.quantity,
+                    unit_price: t.unit_price,
+                    total_value: round_to_scale(t.quantity * t.unit_price, MONEY_SCALE),
+                    status: t.status.clone(),
+                    transaction_date: t.transaction_date.to_string(),
+                }
+            })
+            .collect())
+    }
+}
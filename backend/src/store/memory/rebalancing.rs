@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use crate::models::{
+    AllocationTarget, AllocationTargets, SetAllocationTargetsRequest, DEFAULT_DRIFT_TOLERANCE_PCT,
+};
+use crate::store::{RebalancePosition, RebalanceSnapshot, RebalanceStore, StoreResult};
+use super::{now, AllocationTargetRecord, AllocationTargetsRecord, MemoryData, MemoryStore};
+
+impl MemoryData {
+    fn allocation_targets(&self, portfolio_id: i32) -> StoreResult<AllocationTargets> {
+        self.portfolio(portfolio_id)?;
+
+        let Some(record) = self.allocation_targets.get(&portfolio_id) else {
+            return Ok(AllocationTargets {
+                portfolio_id,
+                drift_tolerance_pct: DEFAULT_DRIFT_TOLERANCE_PCT,
+                min_trade_value: Decimal::ZERO,
+                targets: Vec::new(),
+                updated_at: None,
+            });
+        };
+
+        Ok(AllocationTargets {
+            portfolio_id,
+            drift_tolerance_pct: record.drift_tolerance_pct,
+            min_trade_value: record.min_trade_value,
+            targets: record.targets.iter()
+                .map(|t| AllocationTarget {
+                    asset_id: t.asset_id,
+                    symbol: t.asset_id.and_then(|id| self.assets.get(&id)).map(|a| a.symbol.clone()),
+                    asset_type: t.asset_type.clone(),
+                    weight: t.weight,
+                })
+                .collect(),
+            updated_at: Some(record.updated_at.to_string()),
+        })
+    }
+}
+
+#[async_trait]
+impl RebalanceStore for MemoryStore {
+    async fn allocation_targets(&self, portfolio_id: i32) -> StoreResult<AllocationTargets> {
+        self.lock().allocation_targets(portfolio_id)
+    }
+
+    async fn set_allocation_targets(&self, portfolio_id: i32, targets: &SetAllocationTargetsRequest) -> StoreResult<AllocationTargets> {
+        let mut data = self.lock();
+        data.portfolio(portfolio_id)?;
+        for asset_id in targets.targets.iter().filter_map(|t| t.asset_id) {
+            data.asset(asset_id)?;
+        }
+
+        data.allocation_targets.insert(portfolio_id, AllocationTargetsRecord {
+            drift_tolerance_pct: targets.drift_tolerance_pct.unwrap_or(DEFAULT_DRIFT_TOLERANCE_PCT),
+            min_trade_value: targets.min_trade_value.unwrap_or_default(),
+            targets: targets.targets.iter()
+                .map(|t| AllocationTargetRecord {
+                    asset_id: t.asset_id,
+                    asset_type: t.asset_type.clone(),
+                    weight: t.weight,
+                })
+                .collect(),
+            updated_at: now(),
+        });
+        data.allocation_targets(portfolio_id)
+    }
+
+    async fn rebalance_snapshot(&self, portfolio_id: i32) -> StoreResult<RebalanceSnapshot> {
+        let data = self.lock();
+        let cash = data.portfolio(portfolio_id)?.current_funds;
+
+        let held = data.holdings_of(portfolio_id).map(|h| (h.asset_id, h.quantity_held));
+        let targeted = data.allocation_targets.get(&portfolio_id)
+            .into_iter()
+            .flat_map(|record| record.targets.iter().filter_map(|t| t.asset_id))
+            .filter(|asset_id| !data.holdings_of(portfolio_id).any(|h| h.asset_id == *asset_id))
+            .map(|asset_id| (asset_id, Decimal::ZERO));
+
+        let positions = held.chain(targeted)
+            .filter_map(|(asset_id, quantity_held)| {
+                let asset = data.assets.get(&asset_id)?;
+                Some(RebalancePosition {
+                    asset_id,
+                    symbol: asset.symbol.clone(),
+                    asset_type: asset.asset_type.clone(),
+                    quantity_held,
+                    price: asset.price,
+                    quantity_increment: data.quantity_increments.get(&asset.asset_type).map(|q| q.increment),
+                })
+            })
+            .collect();
+
+        Ok(RebalanceSnapshot { cash, positions })
+    }
+}
@@ -0,0 +1,232 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::models::{RecurringInvestment, RecurringInvestmentRun, RecurringRunStatus};
+use crate::store::{
+    RecurringInvestmentStore, RecurringRunOutcome, RecurringSchedule, StoreError, StoreResult,
+};
+use super::{now, MemoryData, MemoryStore, RecurringInvestmentRecord, RecurringRunRecord};
+
+impl RecurringInvestmentRecord {
+    fn to_recurring_investment(&self, symbol: &str) -> RecurringInvestment {
+        RecurringInvestment {
+            schedule_id: self.schedule_id,
+            portfolio_id: self.portfolio_id,
+            asset_id: self.asset_id,
+            symbol: symbol.to_string(),
+            amount: self.amount,
+            frequency: self.frequency,
+            day_of_week: self.day_of_week,
+            day_of_month: self.day_of_month,
+            active: self.next_run_date.is_some(),
+            next_run_date: self.next_run_date,
+            last_run_date: self.last_run_date,
+            created_at: self.created_at.to_string(),
+            updated_at: self.updated_at.to_string(),
+        }
+    }
+}
+
+impl RecurringRunRecord {
+    fn to_run(&self) -> RecurringInvestmentRun {
+        RecurringInvestmentRun {
+            run_id: self.run_id,
+            schedule_id: self.schedule_id,
+            run_date: self.run_date,
+            status: self.status,
+            transaction_id: self.transaction_id,
+            fund_transaction_id: self.fund_transaction_id,
+            quantity: self.quantity,
+            unit_price: self.unit_price,
+            total_cost: self.total_cost,
+            reason: self.reason.clone(),
+            executed_at: self.executed_at.to_string(),
+        }
+    }
+}
+
+impl MemoryData {
+    fn recurring_investment(&self, schedule_id: i32) -> StoreResult<RecurringInvestment> {
+        let record = self.recurring_investments.get(&schedule_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment not found".to_string()))?;
+        let symbol = self.asset(record.asset_id)?.symbol.as_str();
+        Ok(record.to_recurring_investment(symbol))
+    }
+
+    /// Drops the matching schedules along with their runs
+    pub(super) fn remove_recurring_investments(&mut self, matches: impl Fn(&RecurringInvestmentRecord) -> bool) {
+        let schedule_ids: Vec<i32> = self.recurring_investments.values()
+            .filter(|r| matches(r))
+            .map(|r| r.schedule_id)
+            .collect();
+        self.recurring_runs.retain(|_, run| !schedule_ids.contains(&run.schedule_id));
+        self.recurring_investments.retain(|id, _| !schedule_ids.contains(id));
+    }
+}
+
+#[async_trait]
+impl RecurringInvestmentStore for MemoryStore {
+    async fn list_recurring_investments(&self, portfolio_id: i32) -> StoreResult<Vec<RecurringInvestment>> {
+        let data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        data.recurring_investments.values()
+            .filter(|r| r.portfolio_id == portfolio_id)
+            .map(|r| data.recurring_investment(r.schedule_id))
+            .collect()
+    }
+
+    async fn get_recurring_investment(&self, schedule_id: i32) -> StoreResult<RecurringInvestment> {
+        self.lock().recurring_investment(schedule_id)
+    }
+
+    async fn create_recurring_investment(&self, portfolio_id: i32, asset_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment> {
+        let mut data = self.lock();
+        data.portfolio(portfolio_id)?;
+        data.asset(asset_id)?;
+
+        let schedule_id = data.next_id() as i32;
+        data.recurring_investments.insert(schedule_id, RecurringInvestmentRecord {
+            schedule_id,
+            portfolio_id,
+            asset_id,
+            amount: schedule.amount,
+            frequency: schedule.frequency,
+            day_of_week: schedule.day_of_week,
+            day_of_month: schedule.day_of_month,
+            next_run_date: schedule.next_run_date,
+            last_run_date: None,
+            created_at: now(),
+            updated_at: now(),
+        });
+        data.recurring_investment(schedule_id)
+    }
+
+    async fn update_recurring_investment(&self, schedule_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment> {
+        let mut data = self.lock();
+        let record = data.recurring_investments.get_mut(&schedule_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment not found".to_string()))?;
+
+        record.amount = schedule.amount;
+        record.frequency = schedule.frequency;
+        record.day_of_week = schedule.day_of_week;
+        record.day_of_month = schedule.day_of_month;
+        record.next_run_date = schedule.next_run_date;
+        record.updated_at = now();
+
+        data.recurring_investment(schedule_id)
+    }
+
+    async fn delete_recurring_investment(&self, schedule_id: i32) -> StoreResult<()> {
+        let mut data = self.lock();
+        if !data.recurring_investments.contains_key(&schedule_id) {
+            return Err(StoreError::NotFound("Recurring investment not found".to_string()));
+        }
+        data.remove_recurring_investments(|r| r.schedule_id == schedule_id);
+        Ok(())
+    }
+
+    async fn due_recurring_investments(&self, today: NaiveDate) -> StoreResult<Vec<RecurringInvestment>> {
+        let data = self.lock();
+
+        data.recurring_investments.values()
+            .filter(|r| r.next_run_date.is_some_and(|date| date <= today))
+            .map(|r| data.recurring_investment(r.schedule_id))
+            .collect()
+    }
+
+    async fn claim_recurring_run(&self, schedule_id: i32, run_date: NaiveDate, next_run_date: NaiveDate) -> StoreResult<Option<i64>> {
+        let mut data = self.lock();
+        let record = data.recurring_investments.get_mut(&schedule_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment not found".to_string()))?;
+        if record.next_run_date != Some(run_date) {
+            return Ok(None);
+        }
+
+        record.next_run_date = Some(next_run_date);
+        record.last_run_date = Some(run_date);
+        let run_id = data.next_id();
+        data.recurring_runs.insert(run_id, RecurringRunRecord {
+            run_id,
+            schedule_id,
+            run_date,
+            status: RecurringRunStatus::Running,
+            transaction_id: None,
+            fund_transaction_id: None,
+            quantity: None,
+            unit_price: None,
+            total_cost: None,
+            reason: None,
+            executed_at: now(),
+        });
+        Ok(Some(run_id))
+    }
+
+    async fn complete_recurring_run(&self, run_id: i64, outcome: &RecurringRunOutcome) -> StoreResult<RecurringInvestmentRun> {
+        let mut data = self.lock();
+        let schedule_id = data.recurring_runs.get(&run_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment run not found".to_string()))?
+            .schedule_id;
+        let portfolio_id = data.recurring_investments.get(&schedule_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment not found".to_string()))?
+            .portfolio_id;
+
+        let fund_transaction_id = match outcome {
+            // The purchase's own fund transaction is tagged with the schedule
+            RecurringRunOutcome::Executed(buy) => data.fund_transactions.iter_mut()
+                .find(|t| t.transaction_type == "AssetPurchase" && t.related_asset_transaction_id == Some(buy.transaction_id))
+                .map(|t| {
+                    t.description = Some(format!(
+                        "Recurring investment {}: {}",
+                        schedule_id, t.description.as_deref().unwrap_or_default()
+                    ));
+                    t.fund_transaction_id
+                }),
+            RecurringRunOutcome::Skipped(reason) => {
+                let portfolio = data.portfolio(portfolio_id)?;
+                let (user_id, current_funds) = (portfolio.user_id, portfolio.current_funds);
+                Some(data.record_fund_transaction(
+                    user_id,
+                    Some(portfolio_id),
+                    "RecurringSkipped",
+                    Decimal::ZERO,
+                    current_funds,
+                    Some(format!("Recurring investment {} skipped: {}", schedule_id, reason)),
+                    None,
+                ))
+            },
+        };
+
+        let run = data.recurring_runs.get_mut(&run_id)
+            .ok_or_else(|| StoreError::NotFound("Recurring investment run not found".to_string()))?;
+        run.fund_transaction_id = fund_transaction_id;
+        match outcome {
+            RecurringRunOutcome::Executed(buy) => {
+                run.status = RecurringRunStatus::Executed;
+                run.transaction_id = Some(buy.transaction_id);
+                run.quantity = Some(buy.quantity_purchased);
+                run.unit_price = Some(buy.price_per_share);
+                run.total_cost = Some(buy.total_cost);
+            },
+            RecurringRunOutcome::Skipped(reason) => {
+                run.status = RecurringRunStatus::Skipped;
+                run.reason = Some(reason.clone());
+            },
+        }
+        Ok(run.to_run())
+    }
+
+    async fn list_recurring_runs(&self, schedule_id: i32) -> StoreResult<Vec<RecurringInvestmentRun>> {
+        let data = self.lock();
+        if !data.recurring_investments.contains_key(&schedule_id) {
+            return Err(StoreError::NotFound("Recurring investment not found".to_string()));
+        }
+
+        Ok(data.recurring_runs.values()
+            .rev()
+            .filter(|run| run.schedule_id == schedule_id)
+            .map(RecurringRunRecord::to_run)
+            .collect())
+    }
+}
@@ -0,0 +1,151 @@
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{PortfolioRiskAnalysis, RiskAnalysis, RiskMetrics, RiskSummary};
+use crate::store::{NewRiskMetrics, RiskStore, StoreError, StoreResult};
+use super::{now, MemoryData, MemoryStore, RiskMetricsRecord};
+
+impl MemoryData {
+    fn latest_metrics(&self, user_id: Uuid) -> Option<&RiskMetricsRecord> {
+        self.risk_metrics.iter()
+            .filter(|m| m.user_id == user_id)
+            .max_by_key(|m| m.captured_at)
+    }
+
+    fn risk_summary(&self, users: &[Uuid], portfolio_ids: &[i32]) -> RiskSummary {
+        let volatility: Vec<f64> = users.iter()
+            .filter_map(|id| self.latest_metrics(*id))
+            .filter_map(|m| m.volatility_score)
+            .collect();
+        let average_system_risk = if volatility.is_empty() {
+            0.0
+        } else {
+            volatility.iter().sum::<f64>() / volatility.len() as f64
+        };
+
+        RiskSummary {
+            total_users: users.len() as i32,
+            total_portfolios: portfolio_ids.len() as i32,
+            total_assets_under_management: portfolio_ids.iter()
+                .filter_map(|id| self.portfolios.get(id))
+                .map(|p| p.current_funds)
+                .sum(),
+            average_system_risk,
+            calculated_at: now().to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl RiskStore for MemoryStore {
+    async fn user_risk_analysis(&self, user_id: Uuid) -> StoreResult<RiskAnalysis> {
+        let data = self.lock();
+        let user = data.user(user_id)?;
+        let portfolio_ids = data.user_portfolio_ids(user_id);
+        let metrics = data.latest_metrics(user_id);
+
+        Ok(RiskAnalysis {
+            user_id,
+            user_name: user.name.clone(),
+            user_type: user.user_type.clone(),
+            total_portfolios: portfolio_ids.len() as i32,
+            total_investment: portfolio_ids.iter().map(|id| data.holdings_cost(*id)).sum(),
+            maximum_drawdown: metrics.and_then(|m| m.maximum_drawdown),
+            sharpe_ratio: metrics.and_then(|m| m.sharpe_ratio),
+            risk_level: metrics.map(|m| m.risk_level.clone()).unwrap_or_default(),
+            last_updated: metrics.map(|m| m.captured_at.to_string()).unwrap_or_default(),
+        })
+    }
+
+    async fn portfolio_risk_analysis(&self, portfolio_id: i32) -> StoreResult<PortfolioRiskAnalysis> {
+        let data = self.lock();
+        let portfolio = data.portfolio(portfolio_id)?;
+        let metrics = data.latest_metrics(portfolio.user_id);
+
+        Ok(PortfolioRiskAnalysis {
+            portfolio_id,
+            portfolio_name: portfolio.name.clone(),
+            current_funds: portfolio.current_funds,
+            current_profit_pct: portfolio.current_profit_pct,
+            maximum_drawdown: metrics.and_then(|m| m.maximum_drawdown),
+            beta: metrics.and_then(|m| m.beta),
+            sharpe_ratio: metrics.and_then(|m| m.sharpe_ratio),
+            risk_level: metrics.map(|m| m.risk_level.clone()).unwrap_or_default(),
+        })
+    }
+
+    async fn risk_summary(&self) -> StoreResult<RiskSummary> {
+        let data = self.lock();
+        let users: Vec<Uuid> = data.users.keys().copied().collect();
+        let portfolio_ids: Vec<i32> = data.portfolios.keys().copied().collect();
+        Ok(data.risk_summary(&users, &portfolio_ids))
+    }
+
+    async fn portfolio_risk_summary(&self, portfolio_id: i32) -> StoreResult<RiskSummary> {
+        let data = self.lock();
+        let user_id = data.portfolio(portfolio_id)?.user_id;
+        Ok(data.risk_summary(&[user_id], &[portfolio_id]))
+    }
+
+    async fn user_risk_summary(&self, user_id: Uuid) -> StoreResult<RiskSummary> {
+        let data = self.lock();
+        data.user(user_id)?;
+        let portfolio_ids = data.user_portfolio_ids(user_id);
+        Ok(data.risk_summary(&[user_id], &portfolio_ids))
+    }
+
+    async fn latest_risk_metrics(&self, user_id: Uuid) -> StoreResult<RiskMetrics> {
+        self.lock().latest_metrics(user_id)
+            .map(RiskMetricsRecord::to_metrics)
+            .ok_or_else(|| StoreError::NotFound("No risk metrics found for user".to_string()))
+    }
+
+    async fn record_risk_metrics(&self, user_id: Uuid, metrics: &NewRiskMetrics) -> StoreResult<RiskMetrics> {
+        let mut data = self.lock();
+        data.user(user_id)?;
+
+        let record = RiskMetricsRecord {
+            metric_id: data.next_id() as i32,
+            user_id,
+            maximum_drawdown: metrics.maximum_drawdown,
+            beta: metrics.beta,
+            sharpe_ratio: metrics.sharpe_ratio,
+            sortino_ratio: metrics.sortino_ratio,
+            calmar_ratio: metrics.calmar_ratio,
+            absolute_return: metrics.absolute_return,
+            volatility_score: metrics.volatility_score,
+            risk_level: metrics.risk_level.clone(),
+            captured_at: now(),
+        };
+        let recorded = record.to_metrics();
+        data.risk_metrics.push(record);
+
+        Ok(recorded)
+    }
+
+    async fn risk_trend(&self, user_id: Uuid, days_back: i32) -> StoreResult<Vec<RiskMetrics>> {
+        let data = self.lock();
+        let cutoff = now() - Duration::days(days_back as i64);
+
+        let mut trend: Vec<&RiskMetricsRecord> = data.risk_metrics.iter()
+            .filter(|m| m.user_id == user_id && m.captured_at >= cutoff)
+            .collect();
+        trend.sort_by_key(|m| m.captured_at);
+
+        if trend.is_empty() {
+            return Err(StoreError::NotFound("No risk trend data found for user".to_string()));
+        }
+
+        Ok(trend.into_iter().map(RiskMetricsRecord::to_metrics).collect())
+    }
+
+    async fn asset_daily_closes(&self, asset_ids: &[i32], from: NaiveDate, to: NaiveDate) -> StoreResult<Vec<(i32, Vec<(NaiveDate, Decimal)>)>> {
+        let data = self.lock();
+        Ok(asset_ids.iter()
+            .map(|asset_id| (*asset_id, data.daily_closes(*asset_id, from, to)))
+            .filter(|(_, closes)| !closes.is_empty())
+            .collect())
+    }
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::store::{NewSession, RefreshOutcome, SessionStore, StoreError, StoreResult};
+use super::{now, MemoryStore, SessionRecord};
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn create_session(&self, session: &NewSession) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.user(session.user_id)?;
+
+        data.sessions.insert(session.session_id, SessionRecord {
+            user_id: session.user_id,
+            refresh_token_hash: session.refresh_token_hash.clone(),
+            previous_token_hash: None,
+            expires_at: now() + Duration::seconds(session.ttl_secs.into()),
+            revoked: false,
+        });
+
+        Ok(())
+    }
+
+    async fn session_active(&self, session_id: Uuid) -> StoreResult<bool> {
+        let data = self.lock();
+        Ok(data.sessions.get(&session_id).is_some_and(|s| s.active(now())))
+    }
+
+    async fn rotate_session(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, ttl_secs: i32) -> StoreResult<RefreshOutcome> {
+        let mut data = self.lock();
+        let now = now();
+
+        if let Some((session_id, session)) = data.sessions.iter_mut()
+            .find(|(_, s)| s.refresh_token_hash == refresh_token_hash && s.active(now))
+        {
+            session.previous_token_hash = Some(std::mem::replace(
+                &mut session.refresh_token_hash,
+                new_refresh_token_hash.to_string(),
+            ));
+            session.expires_at = now + Duration::seconds(ttl_secs.into());
+            return Ok(RefreshOutcome::Rotated { session_id: *session_id, user_id: session.user_id });
+        }
+
+        // A token that was already rotated away means someone kept a copy
+        match data.sessions.values_mut()
+            .find(|s| !s.revoked && s.previous_token_hash.as_deref() == Some(refresh_token_hash))
+        {
+            Some(session) => {
+                session.revoked = true;
+                Ok(RefreshOutcome::Reused)
+            },
+            None => Ok(RefreshOutcome::Invalid),
+        }
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> StoreResult<()> {
+        let mut data = self.lock();
+        match data.sessions.get_mut(&session_id) {
+            Some(session) if !session.revoked => {
+                session.revoked = true;
+                Ok(())
+            },
+            _ => Err(StoreError::NotFound("Session not found".to_string())),
+        }
+    }
+
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> StoreResult<u64> {
+        let mut data = self.lock();
+        let mut revoked = 0;
+        for session in data.sessions.values_mut().filter(|s| s.user_id == user_id && !s.revoked) {
+            session.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+}
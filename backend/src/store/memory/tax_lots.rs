@@ -0,0 +1,384 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::models::{
+    CostBasisMethod, LotSelection, RealizedLot, TaxLot, round_to_scale, MONEY_SCALE,
+    QUANTITY_SCALE,
+};
+use crate::store::{StoreError, StoreResult, TaxLotStore};
+use super::{now, LotRecord, MemoryData, MemoryStore, RealizedGainRecord};
+
+/// Lots a sale consumes and how much it takes from each, in the order taken
+pub(super) struct LotClosure {
+    method: CostBasisMethod,
+    take: Vec<(i64, Decimal)>,
+}
+
+impl MemoryData {
+    fn open_lots_of(&self, portfolio_id: i32, asset_id: i32) -> impl Iterator<Item = &LotRecord> {
+        self.lots.values()
+            .filter(move |l| l.portfolio_id == portfolio_id && l.asset_id == asset_id && l.remaining_quantity > Decimal::ZERO)
+    }
+
+    /// Opens a lot for a purchase, as `sp_OpenTaxLot` does
+    pub(super) fn open_tax_lot(
+        &mut self,
+        portfolio_id: i32,
+        asset_id: i32,
+        buy_transaction_id: i64,
+        quantity: Decimal,
+        unit_cost: Decimal,
+    ) {
+        let lot_id = self.next_id();
+        self.lots.insert(lot_id, LotRecord {
+            lot_id,
+            portfolio_id,
+            asset_id,
+            buy_transaction_id: Some(buy_transaction_id),
+            acquired_at: now(),
+            quantity,
+            remaining_quantity: quantity,
+            unit_cost,
+        });
+    }
+
+    /// Works out which lots a sale consumes under the rules of
+    /// `sp_CloseTaxLots`, failing before anything has changed
+    pub(super) fn plan_lot_closure(
+        &self,
+        portfolio_id: i32,
+        asset_id: i32,
+        quantity: Decimal,
+        lots: Option<&[LotSelection]>,
+    ) -> StoreResult<LotClosure> {
+        let method = match (lots, self.portfolio(portfolio_id)?.cost_basis_method) {
+            (Some(_), _) => CostBasisMethod::SpecificLot,
+            (None, CostBasisMethod::SpecificLot) => CostBasisMethod::Fifo,
+            (None, method) => method,
+        };
+
+        let mut take: Vec<(i64, Decimal)> = Vec::new();
+        match (method, lots) {
+            (CostBasisMethod::SpecificLot, Some(lots)) => {
+                for selection in lots {
+                    match take.iter_mut().find(|(lot_id, _)| *lot_id == selection.lot_id) {
+                        Some((_, taken)) => *taken += selection.quantity,
+                        None => take.push((selection.lot_id, selection.quantity)),
+                    }
+                }
+                take.sort_by_key(|(lot_id, _)| *lot_id);
+
+                let valid = take.iter().all(|(lot_id, taken)| {
+                    self.lots.get(lot_id).is_some_and(|l| {
+                        l.portfolio_id == portfolio_id && l.asset_id == asset_id
+                            && *taken > Decimal::ZERO && *taken <= l.remaining_quantity
+                    })
+                });
+                if !valid {
+                    return Err(StoreError::BadRequest(
+                        "Selected lots must be open lots of this holding with enough remaining shares".to_string(),
+                    ));
+                }
+                if take.iter().map(|(_, taken)| *taken).sum::<Decimal>() != quantity {
+                    return Err(StoreError::BadRequest(
+                        "Selected lot quantities must add up to the quantity sold".to_string(),
+                    ));
+                }
+            },
+            (CostBasisMethod::Average, _) => {
+                // The same fraction of every open lot keeps the average cost
+                // unchanged; what truncating leaves over goes to the lots with
+                // the most room, and no lot gives up more than it holds
+                let open: Vec<&LotRecord> = self.open_lots_of(portfolio_id, asset_id).collect();
+                let open_quantity: Decimal = open.iter().map(|l| l.remaining_quantity).sum();
+                take = open.iter()
+                    .map(|l| {
+                        let share = (l.remaining_quantity * quantity / open_quantity)
+                            .round_dp_with_strategy(QUANTITY_SCALE, RoundingStrategy::ToZero);
+                        (l.lot_id, share.min(l.remaining_quantity))
+                    })
+                    .collect();
+
+                let mut left = quantity - take.iter().map(|(_, taken)| *taken).sum::<Decimal>();
+                let mut by_room: Vec<(usize, Decimal)> = open.iter().zip(&take)
+                    .map(|(l, (_, taken))| l.remaining_quantity - taken)
+                    .enumerate()
+                    .collect();
+                by_room.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                for (i, room) in by_room {
+                    if left <= Decimal::ZERO {
+                        break;
+                    }
+                    let extra = room.min(left);
+                    take[i].1 += extra;
+                    left -= extra;
+                }
+            },
+            _ => {
+                // FIFO walks the lots oldest first, LIFO newest first, until the sale is covered
+                let mut open: Vec<&LotRecord> = self.open_lots_of(portfolio_id, asset_id).collect();
+                if method == CostBasisMethod::Lifo {
+                    open.reverse();
+                }
+                let mut left = quantity;
+                for lot in open {
+                    if left <= Decimal::ZERO {
+                        break;
+                    }
+                    let taken = lot.remaining_quantity.min(left);
+                    take.push((lot.lot_id, taken));
+                    left -= taken;
+                }
+            },
+        }
+
+        Ok(LotClosure { method, take })
+    }
+
+    /// Cost basis the planned lots would give up, without closing them
+    pub(super) fn lot_closure_cost_basis(&self, closure: &LotClosure) -> Decimal {
+        closure.take.iter()
+            .filter(|(_, taken)| *taken > Decimal::ZERO)
+            .filter_map(|(lot_id, taken)| self.lots.get(lot_id).map(|l| round_to_scale(*taken * l.unit_cost, MONEY_SCALE)))
+            .sum()
+    }
+
+    /// Consumes the planned lots and records one realized gain per lot, the
+    /// fee taken off each lot's proceeds by the share of the quantity it gives
+    /// up and rounding left on the last one; returns the cost basis sold
+    pub(super) fn close_tax_lots(
+        &mut self,
+        closure: LotClosure,
+        sell_transaction_id: i64,
+        quantity: Decimal,
+        unit_price: Decimal,
+        fee: Decimal,
+    ) -> Decimal {
+        let now = now();
+        let first = self.realized_gains.len();
+
+        for (lot_id, taken) in closure.take {
+            let Some(lot) = self.lots.get_mut(&lot_id) else { continue };
+            lot.remaining_quantity -= taken;
+            if taken <= Decimal::ZERO {
+                continue;
+            }
+            let cost_basis = round_to_scale(taken * lot.unit_cost, MONEY_SCALE);
+
+            let realized_gain_id = self.next_id();
+            self.realized_gains.push(RealizedGainRecord {
+                realized_gain_id,
+                sell_transaction_id,
+                lot_id,
+                quantity: taken,
+                cost_basis,
+                proceeds: round_to_scale(taken * unit_price - fee * taken / quantity, MONEY_SCALE),
+                cost_basis_method: closure.method,
+                realized_at: now,
+            });
+        }
+
+        let gains = &mut self.realized_gains[first..];
+        let total_proceeds = round_to_scale(quantity * unit_price, MONEY_SCALE) - fee;
+        let split: Decimal = gains.iter().map(|g| g.proceeds).sum();
+        if let Some(last) = gains.last_mut() {
+            last.proceeds += total_proceeds - split;
+        }
+
+        gains.iter().map(|g| g.cost_basis).sum()
+    }
+
+    fn realized_lot(&self, gain: &RealizedGainRecord) -> Option<RealizedLot> {
+        let lot = self.lots.get(&gain.lot_id)?;
+        let sale = self.transactions.iter().find(|t| t.transaction_id == gain.sell_transaction_id)?;
+        Some(RealizedLot {
+            sell_transaction_id: gain.sell_transaction_id,
+            lot_id: lot.lot_id,
+            portfolio_id: lot.portfolio_id,
+            asset_id: lot.asset_id,
+            symbol: self.assets.get(&lot.asset_id).map(|a| a.symbol.clone()).unwrap_or_default(),
+            acquired_at: lot.acquired_at.to_string(),
+            unit_cost: lot.unit_cost,
+            sale_price: sale.unit_price,
+            quantity: gain.quantity,
+            cost_basis: gain.cost_basis,
+            proceeds: gain.proceeds,
+            realized_gain: gain.proceeds - gain.cost_basis,
+            cost_basis_method: gain.cost_basis_method,
+            realized_at: gain.realized_at.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl TaxLotStore for MemoryStore {
+    async fn cost_basis_method(&self, portfolio_id: i32) -> StoreResult<CostBasisMethod> {
+        let data = self.lock();
+        Ok(data.portfolio(portfolio_id)?.cost_basis_method)
+    }
+
+    async fn set_cost_basis_method(&self, portfolio_id: i32, method: CostBasisMethod) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.portfolio_mut(portfolio_id)?.cost_basis_method = method;
+        Ok(())
+    }
+
+    async fn open_lots(&self, portfolio_id: i32, asset_id: Option<i32>) -> StoreResult<Vec<TaxLot>> {
+        let data = self.lock();
+        Ok(data.lots.values()
+            .filter(|l| l.portfolio_id == portfolio_id && l.remaining_quantity > Decimal::ZERO)
+            .filter(|l| asset_id.is_none_or(|id| l.asset_id == id))
+            .map(|l| TaxLot {
+                lot_id: l.lot_id,
+                portfolio_id: l.portfolio_id,
+                asset_id: l.asset_id,
+                symbol: data.assets.get(&l.asset_id).map(|a| a.symbol.clone()).unwrap_or_default(),
+                buy_transaction_id: l.buy_transaction_id,
+                acquired_at: l.acquired_at.to_string(),
+                quantity: l.quantity,
+                remaining_quantity: l.remaining_quantity,
+                unit_cost: l.unit_cost,
+                cost_basis: l.remaining_quantity * l.unit_cost,
+            })
+            .collect())
+    }
+
+    async fn realized_lots(
+        &self,
+        portfolio_id: i32,
+        asset_id: Option<i32>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<RealizedLot>> {
+        let data = self.lock();
+        let mut gains: Vec<&RealizedGainRecord> = data.realized_gains.iter()
+            .filter(|g| from.is_none_or(|from| g.realized_at.date() >= from))
+            .filter(|g| to.is_none_or(|to| g.realized_at.date() <= to))
+            .collect();
+        gains.sort_by_key(|g| (std::cmp::Reverse(g.sell_transaction_id), g.realized_gain_id));
+
+        Ok(gains.into_iter()
+            .filter_map(|g| data.realized_lot(g))
+            .filter(|l| l.portfolio_id == portfolio_id && asset_id.is_none_or(|id| l.asset_id == id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::PortfolioRecord;
+    use uuid::Uuid;
+
+    fn qty(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// A portfolio with one lot per `(quantity, unit cost)`, lot ids from 1 in that order
+    fn holding(method: CostBasisMethod, lots: &[(&str, i64)]) -> MemoryData {
+        let mut data = MemoryData::default();
+        data.portfolios.insert(1, PortfolioRecord {
+            portfolio_id: 1,
+            user_id: Uuid::nil(),
+            name: "Test".to_string(),
+            creation_date: now(),
+            current_funds: Decimal::ZERO,
+            current_profit_pct: Decimal::ZERO,
+            cost_basis_method: method,
+            last_updated: now(),
+        });
+        for (i, (quantity, unit_cost)) in lots.iter().enumerate() {
+            data.open_tax_lot(1, 1, 100 + i as i64, qty(quantity), Decimal::from(*unit_cost));
+        }
+        data
+    }
+
+    fn selection(lots: &[(i64, &str)]) -> Vec<LotSelection> {
+        lots.iter().map(|(lot_id, quantity)| LotSelection { lot_id: *lot_id, quantity: qty(quantity) }).collect()
+    }
+
+    #[test]
+    fn each_method_takes_its_own_lots() {
+        let lots = [("10", 100), ("5", 110), ("8", 120)];
+        let specific = selection(&[(3, "7"), (2, "5")]);
+        let cases = [
+            (CostBasisMethod::Fifo, None, vec![(1, "10"), (2, "2")]),
+            (CostBasisMethod::Lifo, None, vec![(3, "8"), (2, "4")]),
+            // 12/23 of each lot, truncated, and the share left over on lot 1, which has the most room
+            (CostBasisMethod::Average, None, vec![(1, "5.217392"), (2, "2.608695"), (3, "4.173913")]),
+            // Selected lots win over the portfolio's method, in lot order
+            (CostBasisMethod::Fifo, Some(specific.as_slice()), vec![(2, "5"), (3, "7")]),
+            // SpecificLot without a selection falls back to FIFO
+            (CostBasisMethod::SpecificLot, None, vec![(1, "10"), (2, "2")]),
+        ];
+
+        for (method, selected, expected) in cases {
+            let data = holding(method, &lots);
+            let closure = data.plan_lot_closure(1, 1, qty("12"), selected).unwrap();
+            let expected: Vec<(i64, Decimal)> = expected.iter().map(|(lot_id, q)| (*lot_id, qty(q))).collect();
+            assert_eq!(closure.take, expected, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn selections_must_cover_the_sale_from_open_lots() {
+        let data = holding(CostBasisMethod::Fifo, &[("10", 100), ("5", 110)]);
+        let cases: [(&[(i64, &str)], bool); 6] = [
+            (&[(1, "3")], false),
+            (&[(1, "3"), (2, "3")], false),
+            (&[(2, "6"), (1, "-1")], false),
+            (&[(9, "5")], false),
+            (&[(1, "2"), (2, "3")], true),
+            // Repeats of a lot add up
+            (&[(1, "2"), (1, "3")], true),
+        ];
+
+        for (lots, valid) in cases {
+            let result = data.plan_lot_closure(1, 1, qty("5"), Some(&selection(lots)));
+            match result {
+                Ok(_) => assert!(valid, "{lots:?} was accepted"),
+                Err(StoreError::BadRequest(_)) => assert!(!valid, "{lots:?} was refused"),
+                Err(e) => panic!("{lots:?}: {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn average_never_takes_more_than_a_lot_holds() {
+        let data = holding(CostBasisMethod::Average, &[("1", 10), ("1", 10), ("1", 10), ("0.000001", 10)]);
+        let closure = data.plan_lot_closure(1, 1, qty("2"), None).unwrap();
+
+        // 2/3.000001 of each truncates to 0.666666 and nothing of the last lot;
+        // the 0.000002 left over goes to a lot with room for it
+        assert_eq!(closure.take, vec![
+            (1, qty("0.666668")),
+            (2, qty("0.666666")),
+            (3, qty("0.666666")),
+            (4, Decimal::ZERO),
+        ]);
+        for (lot_id, taken) in &closure.take {
+            assert!(*taken <= data.lots[lot_id].remaining_quantity, "lot {lot_id}");
+        }
+    }
+
+    #[test]
+    fn proceeds_add_up_to_the_sale_net_of_its_fee() {
+        let lots = [("10", 100), ("5", 110), ("8", 120)];
+        // 12 at 101.2345 is 1214.81 before the 3.07 fee
+        for method in [CostBasisMethod::Fifo, CostBasisMethod::Lifo, CostBasisMethod::Average] {
+            let mut data = holding(method, &lots);
+            let closure = data.plan_lot_closure(1, 1, qty("12"), None).unwrap();
+            let planned_cost_basis = data.lot_closure_cost_basis(&closure);
+
+            let cost_basis = data.close_tax_lots(closure, 500, qty("12"), qty("101.2345"), qty("3.07"));
+
+            let proceeds: Decimal = data.realized_gains.iter().map(|g| g.proceeds).sum();
+            assert_eq!(proceeds, qty("1211.74"), "{method:?}");
+            assert_eq!(cost_basis, planned_cost_basis, "{method:?}");
+            assert_eq!(cost_basis, data.realized_gains.iter().map(|g| g.cost_basis).sum::<Decimal>(), "{method:?}");
+            assert_eq!(data.lots.values().map(|l| l.remaining_quantity).sum::<Decimal>(), qty("11"), "{method:?}");
+            assert!(data.realized_gains.iter().all(|g| g.sell_transaction_id == 500 && g.cost_basis_method == method));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use crate::models::{round_to_scale, OrderStatus, QuantityIncrement, SetTradingLimitsRequest, TradingLimits, MONEY_SCALE};
+use crate::store::{PreTradeSnapshot, StoreResult, TradingRuleStore};
+use super::{now, MemoryData, MemoryStore, QuantityIncrementRecord, TradingLimitsRecord};
+
+impl MemoryData {
+    /// The increments `017_trading_limits.sql` inserts
+    pub(super) fn seed_quantity_increments(&mut self) {
+        let defaults = [
+            ("Stock", Decimal::ONE),
+            ("Index", Decimal::ONE),
+            ("Commodity", Decimal::new(1, 2)),
+            ("Cryptocurrency", Decimal::new(1, 6)),
+        ];
+        for (asset_type, increment) in defaults {
+            self.quantity_increments.insert(asset_type.to_string(), QuantityIncrementRecord {
+                increment,
+                updated_at: now(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl TradingRuleStore for MemoryStore {
+    async fn trading_limits(&self, portfolio_id: i32) -> StoreResult<TradingLimits> {
+        let data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        Ok(data.trading_limits.get(&portfolio_id)
+            .map(|limits| limits.to_limits(portfolio_id))
+            .unwrap_or(TradingLimits { portfolio_id, ..TradingLimits::default() }))
+    }
+
+    async fn set_trading_limits(&self, portfolio_id: i32, limits: &SetTradingLimitsRequest) -> StoreResult<TradingLimits> {
+        let mut data = self.lock();
+        data.portfolio(portfolio_id)?;
+
+        let record = TradingLimitsRecord {
+            max_position_pct: limits.max_position_pct,
+            max_order_notional: limits.max_order_notional,
+            max_daily_volume: limits.max_daily_volume,
+            max_price_age_minutes: limits.max_price_age_minutes,
+            updated_at: now(),
+        };
+        let response = record.to_limits(portfolio_id);
+        data.trading_limits.insert(portfolio_id, record);
+        Ok(response)
+    }
+
+    async fn quantity_increments(&self) -> StoreResult<Vec<QuantityIncrement>> {
+        let data = self.lock();
+        Ok(data.quantity_increments.iter()
+            .map(|(asset_type, record)| QuantityIncrement {
+                asset_type: asset_type.clone(),
+                increment: record.increment,
+                updated_at: record.updated_at.to_string(),
+            })
+            .collect())
+    }
+
+    async fn set_quantity_increment(&self, asset_type: &str, increment: Decimal) -> StoreResult<QuantityIncrement> {
+        let mut data = self.lock();
+        let updated_at = now();
+        data.quantity_increments.insert(asset_type.to_string(), QuantityIncrementRecord { increment, updated_at });
+
+        Ok(QuantityIncrement {
+            asset_type: asset_type.to_string(),
+            increment,
+            updated_at: updated_at.to_string(),
+        })
+    }
+
+    async fn pre_trade_snapshot(&self, portfolio_id: i32, asset_id: i32) -> StoreResult<PreTradeSnapshot> {
+        let data = self.lock();
+        let portfolio = data.portfolio(portfolio_id)?;
+        let asset = data.asset(asset_id)?;
+        let now = now();
+        let today = now.date();
+
+        let limits = data.trading_limits.get(&portfolio_id)
+            .map(|limits| limits.to_limits(portfolio_id))
+            .unwrap_or(TradingLimits { portfolio_id, ..TradingLimits::default() });
+        let quantity_held = data.holdings_of(portfolio_id)
+            .filter(|h| h.asset_id == asset_id)
+            .map(|h| h.quantity_held)
+            .sum();
+        let traded_today = data.transactions.iter()
+            .filter(|t| t.portfolio_id == portfolio_id && t.status == OrderStatus::Executed.as_str())
+            .filter(|t| t.transaction_date.date() == today)
+            .map(|t| round_to_scale(t.quantity * t.unit_price, MONEY_SCALE))
+            .sum();
+
+        Ok(PreTradeSnapshot {
+            limits,
+            asset_type: asset.asset_type.clone(),
+            price: asset.price,
+            price_age_secs: (now - asset.last_updated).num_seconds(),
+            quantity_increment: data.quantity_increments.get(&asset.asset_type).map(|record| record.increment),
+            quantity_held,
+            portfolio_value: portfolio.current_funds + data.holdings_value(portfolio_id),
+            traded_today,
+        })
+    }
+}
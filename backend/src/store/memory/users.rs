@@ -0,0 +1,545 @@
+use async_trait::async_trait;
+use chrono::{Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::models::{
+    AccountSummary, CreateUserRequest, ExtendedUser, FundTransaction, PaymentMethodResponse,
+    SetPaymentMethodRequest, SubscriptionResponse, UpdateUserRequest, User,
+};
+use crate::store::{AllocationBalances, FundStore, StoreError, StoreResult, UserCredentials, UserStore};
+use super::{now, MemoryData, MemoryStore, UserRecord};
+
+const VALID_PAYMENT_METHODS: [&str; 4] = ["CreditCard", "BankTransfer", "PayPal", "Other"];
+
+fn iso_format(dt: chrono::NaiveDateTime) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn check_user_type(user_type: &str) -> StoreResult<()> {
+    if !["Basic", "Premium", "Admin"].contains(&user_type) {
+        return Err(StoreError::BadRequest("User type must be Basic, Premium or Admin".to_string()));
+    }
+    Ok(())
+}
+
+fn check_positive(amount: Decimal, operation: &str) -> StoreResult<()> {
+    if amount <= Decimal::ZERO {
+        return Err(StoreError::BadRequest(format!("{} amount must be positive", operation)));
+    }
+    Ok(())
+}
+
+impl MemoryData {
+    /// Mirrors the ACTIVATE/RENEW branch of `sp_ManageSubscription`
+    fn activate_subscription(&mut self, user_id: Uuid, months: i32, monthly_rate: Decimal) -> StoreResult<(Decimal, Decimal)> {
+        if months <= 0 {
+            return Err(StoreError::BadRequest("Months to add must be positive".to_string()));
+        }
+        if monthly_rate <= Decimal::ZERO {
+            return Err(StoreError::BadRequest("Monthly rate must be positive".to_string()));
+        }
+
+        let total_cost = Decimal::from(months) * monthly_rate;
+        let now = now();
+        let user = self.user_mut(user_id)?;
+
+        if user.account_balance < total_cost {
+            return Err(StoreError::InsufficientFunds("Insufficient account balance for subscription".to_string()));
+        }
+
+        let added = Months::new(months as u32);
+        let expired = user.subscription_expired(now);
+        let end_date = match user.premium_end_date {
+            Some(end) if user.is_premium && !expired => end.checked_add_months(added),
+            _ => now.checked_add_months(added),
+        }.unwrap_or(now);
+
+        if !user.is_premium {
+            user.premium_start_date = Some(now);
+        }
+        user.account_balance -= total_cost;
+        user.user_type = "Premium".to_string();
+        user.is_premium = true;
+        user.premium_end_date = Some(end_date);
+        user.monthly_subscription_rate = monthly_rate;
+        user.auto_renew_subscription = true;
+        user.last_subscription_payment = Some(now);
+        user.next_subscription_payment = Some(end_date);
+        user.updated_at = now;
+
+        let new_balance = user.account_balance;
+        self.record_fund_transaction(
+            user_id,
+            None,
+            "PremiumUpgrade",
+            -total_cost,
+            new_balance,
+            Some(format!("Premium subscription - {} months at ${}/month", months, monthly_rate)),
+            None,
+        );
+
+        Ok((total_cost, new_balance))
+    }
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn list_users(&self) -> StoreResult<Vec<User>> {
+        let data = self.lock();
+        let mut users: Vec<&UserRecord> = data.users.values().collect();
+        users.sort_by_key(|u| std::cmp::Reverse(u.created_at));
+        Ok(users.into_iter().map(UserRecord::to_user).collect())
+    }
+
+    async fn get_user(&self, user_id: Uuid) -> StoreResult<User> {
+        Ok(self.lock().user(user_id)?.to_user())
+    }
+
+    async fn get_user_extended(&self, user_id: Uuid) -> StoreResult<ExtendedUser> {
+        let data = self.lock();
+        let user = data.user(user_id)?;
+        let now = now();
+
+        Ok(ExtendedUser {
+            user_id,
+            name: user.name.clone(),
+            email: user.email.clone(),
+            country_of_residence: user.country_of_residence.clone(),
+            iban: user.iban.clone(),
+            user_type: user.user_type.clone(),
+            account_balance: user.account_balance,
+            payment_method_type: user.payment_method_type.clone(),
+            payment_method_details: user.payment_method_details.clone(),
+            payment_method_expiry: user.payment_method_expiry.map(|d| d.format("%Y-%m-%d").to_string()),
+            payment_method_active: user.payment_method_active,
+            is_premium: user.is_premium,
+            premium_start_date: user.premium_start_date.map(iso_format),
+            premium_end_date: user.premium_end_date.map(iso_format),
+            monthly_subscription_rate: Some(user.monthly_subscription_rate),
+            auto_renew_subscription: user.auto_renew_subscription,
+            last_subscription_payment: user.last_subscription_payment.map(iso_format),
+            next_subscription_payment: user.next_subscription_payment.map(iso_format),
+            days_remaining_in_subscription: user.days_remaining_in_subscription(now),
+            subscription_expired: user.subscription_expired(now),
+            created_at: iso_format(user.created_at),
+            updated_at: iso_format(user.updated_at),
+        })
+    }
+
+    async fn create_user(&self, user: &CreateUserRequest) -> StoreResult<User> {
+        let name = user.name.trim();
+        let email = user.email.trim();
+        if name.is_empty() {
+            return Err(StoreError::BadRequest("Name is required".to_string()));
+        }
+        if email.is_empty() {
+            return Err(StoreError::BadRequest("Email is required".to_string()));
+        }
+        check_user_type(&user.user_type)?;
+
+        let mut data = self.lock();
+        if data.users.values().any(|u| u.email == email) {
+            return Err(StoreError::BadRequest("Email already exists".to_string()));
+        }
+
+        let now = now();
+        let record = UserRecord {
+            user_id: Uuid::new_v4(),
+            name: name.to_string(),
+            email: email.to_string(),
+            password: user.password.clone(),
+            country_of_residence: user.country_of_residence.clone(),
+            iban: user.iban.clone(),
+            user_type: user.user_type.clone(),
+            account_balance: Decimal::ZERO,
+            payment_method_type: None,
+            payment_method_details: None,
+            payment_method_expiry: None,
+            payment_method_active: false,
+            is_premium: false,
+            premium_start_date: None,
+            premium_end_date: None,
+            monthly_subscription_rate: Decimal::from(50),
+            auto_renew_subscription: false,
+            last_subscription_payment: None,
+            next_subscription_payment: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let created = record.to_user();
+        data.users.insert(record.user_id, record);
+        Ok(created)
+    }
+
+    async fn update_user(&self, user_id: Uuid, update: &UpdateUserRequest) -> StoreResult<User> {
+        let mut data = self.lock();
+        data.user(user_id)?;
+
+        let email = update.email.as_deref().map(str::trim);
+        if let Some(email) = email {
+            if data.users.values().any(|u| u.email == email && u.user_id != user_id) {
+                return Err(StoreError::BadRequest("Email already exists".to_string()));
+            }
+        }
+
+        if update.name.is_none()
+            && update.email.is_none()
+            && update.password.is_none()
+            && update.country_of_residence.is_none()
+            && update.iban.is_none()
+            && update.user_type.is_none()
+        {
+            return Err(StoreError::BadRequest("No fields to update".to_string()));
+        }
+
+        if let Some(user_type) = &update.user_type {
+            check_user_type(user_type)?;
+        }
+
+        let user = data.user_mut(user_id)?;
+        if let Some(name) = &update.name {
+            user.name = name.trim().to_string();
+        }
+        if let Some(email) = email {
+            user.email = email.to_string();
+        }
+        if let Some(password) = &update.password {
+            user.password = password.clone();
+        }
+        if let Some(country) = &update.country_of_residence {
+            user.country_of_residence = country.clone();
+        }
+        if let Some(iban) = &update.iban {
+            user.iban = iban.clone();
+        }
+        if let Some(user_type) = &update.user_type {
+            user.user_type = user_type.clone();
+        }
+        user.updated_at = now();
+
+        Ok(user.to_user())
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.user(user_id)?;
+
+        // Same cascade as the foreign keys on portfolio.Users
+        let portfolio_ids = data.user_portfolio_ids(user_id);
+        data.holdings.retain(|h| !portfolio_ids.contains(&h.portfolio_id));
+        data.transactions.retain(|t| !portfolio_ids.contains(&t.portfolio_id));
+        let lot_ids: Vec<i64> = data.lots.values()
+            .filter(|l| portfolio_ids.contains(&l.portfolio_id))
+            .map(|l| l.lot_id)
+            .collect();
+        data.realized_gains.retain(|g| !lot_ids.contains(&g.lot_id));
+        data.lots.retain(|_, l| !portfolio_ids.contains(&l.portfolio_id));
+        data.orders.retain(|_, o| o.user_id != user_id);
+        data.trading_limits.retain(|id, _| !portfolio_ids.contains(id));
+        data.allocation_targets.retain(|id, _| !portfolio_ids.contains(id));
+        data.remove_recurring_investments(|r| portfolio_ids.contains(&r.portfolio_id));
+        data.portfolio_snapshots.retain(|(id, _), _| !portfolio_ids.contains(id));
+        data.portfolios.retain(|_, p| p.user_id != user_id);
+        data.fund_transactions.retain(|t| t.user_id != user_id);
+        data.risk_metrics.retain(|m| m.user_id != user_id);
+        data.sessions.retain(|_, s| s.user_id != user_id);
+        data.idempotency_keys.retain(|(owner, _), _| *owner != user_id);
+        data.users.remove(&user_id);
+
+        Ok(())
+    }
+
+    async fn find_credentials(&self, email: &str) -> StoreResult<Option<UserCredentials>> {
+        let data = self.lock();
+        Ok(data.users.values()
+            .find(|u| u.email == email)
+            .map(|u| UserCredentials {
+                password: u.password.clone(),
+                user: u.to_user(),
+            }))
+    }
+
+    async fn set_password(&self, user_id: Uuid, password_hash: &str) -> StoreResult<()> {
+        let mut data = self.lock();
+        data.user_mut(user_id)?.password = password_hash.to_string();
+        Ok(())
+    }
+
+    async fn set_payment_method(&self, user_id: Uuid, request: &SetPaymentMethodRequest) -> StoreResult<PaymentMethodResponse> {
+        if !VALID_PAYMENT_METHODS.contains(&request.payment_method_type.as_str()) {
+            return Err(StoreError::BadRequest("Invalid payment method type".to_string()));
+        }
+
+        let expiry = match request.payment_method_expiry.as_deref() {
+            Some(expiry) => Some(NaiveDate::parse_from_str(expiry, "%Y-%m-%d")
+                .map_err(|_| StoreError::BadRequest(format!("Invalid expiry date: {}", expiry)))?),
+            None => None,
+        };
+
+        let mut data = self.lock();
+        let user = data.user_mut(user_id)?;
+        user.payment_method_type = Some(request.payment_method_type.clone());
+        user.payment_method_details = Some(request.payment_method_details.clone());
+        user.payment_method_expiry = expiry;
+        user.payment_method_active = true;
+        user.updated_at = now();
+
+        Ok(PaymentMethodResponse {
+            status: "SUCCESS".to_string(),
+            message: "Payment method updated successfully".to_string(),
+        })
+    }
+
+    async fn manage_subscription(&self, user_id: Uuid, action: &str, months: i32, monthly_rate: Decimal) -> StoreResult<SubscriptionResponse> {
+        let mut data = self.lock();
+
+        match action {
+            "ACTIVATE" | "RENEW" => {
+                let (amount_paid, new_balance) = data.activate_subscription(user_id, months, monthly_rate)?;
+                Ok(SubscriptionResponse {
+                    status: "SUCCESS".to_string(),
+                    amount_paid: Some(amount_paid),
+                    months_added: Some(months),
+                    new_balance: Some(new_balance),
+                    message: Some("Subscription updated successfully".to_string()),
+                })
+            },
+            "CANCEL" => {
+                let user = data.user_mut(user_id)?;
+                user.user_type = "Basic".to_string();
+                user.is_premium = false;
+                user.auto_renew_subscription = false;
+                user.updated_at = now();
+
+                Ok(SubscriptionResponse {
+                    status: "SUCCESS".to_string(),
+                    amount_paid: None,
+                    months_added: None,
+                    new_balance: None,
+                    message: Some("Subscription cancelled successfully".to_string()),
+                })
+            },
+            _ => Err(StoreError::BadRequest("Invalid action specified".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl FundStore for MemoryStore {
+    async fn deposit_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal> {
+        check_positive(amount, "Deposit")?;
+
+        let mut data = self.lock();
+        let user = data.user_mut(user_id)?;
+        user.account_balance += amount;
+        user.updated_at = now();
+        let new_balance = user.account_balance;
+
+        let description = if description.is_empty() { "Account deposit" } else { description };
+        data.record_fund_transaction(user_id, None, "Deposit", amount, new_balance, Some(description.to_string()), None);
+
+        Ok(new_balance)
+    }
+
+    async fn withdraw_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal> {
+        check_positive(amount, "Withdrawal")?;
+
+        let mut data = self.lock();
+        let user = data.user_mut(user_id)?;
+        if user.account_balance < amount {
+            return Err(StoreError::InsufficientFunds("Insufficient account balance".to_string()));
+        }
+        user.account_balance -= amount;
+        user.updated_at = now();
+        let new_balance = user.account_balance;
+
+        let description = if description.is_empty() { "Account withdrawal" } else { description };
+        data.record_fund_transaction(user_id, None, "Withdrawal", -amount, new_balance, Some(description.to_string()), None);
+
+        Ok(new_balance)
+    }
+
+    async fn allocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances> {
+        check_positive(amount, "Allocation")?;
+
+        let mut data = self.lock();
+        if data.portfolios.get(&portfolio_id).is_none_or(|p| p.user_id != user_id) {
+            return Err(StoreError::NotFound("Portfolio not found".to_string()));
+        }
+
+        let user = data.user_mut(user_id)?;
+        if user.account_balance < amount {
+            return Err(StoreError::InsufficientFunds("Insufficient account balance".to_string()));
+        }
+        user.account_balance -= amount;
+        let new_user_balance = user.account_balance;
+
+        let portfolio = data.portfolio_mut(portfolio_id)?;
+        portfolio.current_funds += amount;
+        portfolio.last_updated = now();
+        let new_portfolio_funds = portfolio.current_funds;
+
+        data.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "Allocation",
+            -amount,
+            new_user_balance,
+            Some(format!("Allocated to portfolio {}", portfolio_id)),
+            None,
+        );
+
+        Ok(AllocationBalances { new_user_balance, new_portfolio_funds })
+    }
+
+    async fn deallocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances> {
+        check_positive(amount, "Deallocation")?;
+
+        let mut data = self.lock();
+        if data.portfolios.get(&portfolio_id).is_none_or(|p| p.user_id != user_id) {
+            return Err(StoreError::NotFound("Portfolio not found".to_string()));
+        }
+
+        let portfolio = data.portfolio_mut(portfolio_id)?;
+        if portfolio.current_funds < amount {
+            return Err(StoreError::InsufficientFunds("Insufficient portfolio funds".to_string()));
+        }
+        portfolio.current_funds -= amount;
+        portfolio.last_updated = now();
+        let new_portfolio_funds = portfolio.current_funds;
+
+        let user = data.user_mut(user_id)?;
+        user.account_balance += amount;
+        let new_user_balance = user.account_balance;
+
+        data.record_fund_transaction(
+            user_id,
+            Some(portfolio_id),
+            "Deallocation",
+            amount,
+            new_user_balance,
+            Some(format!("Deallocated from portfolio {}", portfolio_id)),
+            None,
+        );
+
+        Ok(AllocationBalances { new_user_balance, new_portfolio_funds })
+    }
+
+    async fn upgrade_to_premium(&self, user_id: Uuid, months: i32, monthly_rate: Decimal) -> StoreResult<Decimal> {
+        let (_, new_balance) = self.lock().activate_subscription(user_id, months, monthly_rate)
+            .map_err(|e| match e {
+                StoreError::InsufficientFunds(_) => {
+                    StoreError::InsufficientFunds("Insufficient account balance for premium upgrade".to_string())
+                },
+                other => other,
+            })?;
+        Ok(new_balance)
+    }
+
+    async fn account_summary(&self, user_id: Uuid) -> StoreResult<AccountSummary> {
+        let data = self.lock();
+        let user = data.user(user_id)?;
+
+        let portfolio_ids = data.user_portfolio_ids(user_id);
+        let total_portfolio_value: Decimal = portfolio_ids.iter()
+            .filter_map(|id| data.portfolios.get(id))
+            .map(|p| p.current_funds)
+            .sum();
+
+        Ok(AccountSummary {
+            user_id,
+            name: user.name.clone(),
+            user_type: user.user_type.clone(),
+            account_balance: user.account_balance,
+            total_portfolio_value,
+            total_net_worth: user.account_balance + total_portfolio_value,
+            portfolio_count: portfolio_ids.len() as i32,
+        })
+    }
+
+    async fn fund_transactions(&self, user_id: Uuid) -> StoreResult<Vec<FundTransaction>> {
+        let data = self.lock();
+        data.user(user_id)?;
+
+        Ok(data.fund_transactions.iter()
+            .rev()
+            .filter(|t| t.user_id == user_id)
+            .map(|t| FundTransaction {
+                fund_transaction_id: t.fund_transaction_id,
+                user_id,
+                portfolio_id: t.portfolio_id,
+                transaction_type: t.transaction_type.clone(),
+                amount: t.amount,
+                balance_after: t.balance_after,
+                description: t.description.clone(),
+                related_asset_transaction_id: t.related_asset_transaction_id,
+                created_at: t.created_at.to_string(),
+            })
+            .collect())
+    }
+
+    async fn enhanced_account_summary(&self, user_id: Uuid) -> StoreResult<serde_json::Value> {
+        let data = self.lock();
+        let user = data.user(user_id)?;
+        let now = now();
+
+        let portfolio_ids = data.user_portfolio_ids(user_id);
+        let total_funds_in_portfolios: Decimal = portfolio_ids.iter()
+            .filter_map(|id| data.portfolios.get(id))
+            .map(|p| p.current_funds)
+            .sum();
+        let total_market_value: Decimal = portfolio_ids.iter()
+            .map(|id| data.holdings_value(*id))
+            .sum();
+
+        let mut summary = json!({
+            "user_id": user_id.to_string(),
+            "name": user.name,
+            "email": user.email,
+            "country_of_residence": user.country_of_residence,
+            "user_type": user.user_type,
+            "account_balance": user.account_balance,
+            "total_funds_in_portfolios": total_funds_in_portfolios,
+            "total_market_value": total_market_value,
+            "total_net_worth": user.account_balance + total_funds_in_portfolios + total_market_value,
+            "payment_method_active": user.payment_method_active,
+            "is_premium": user.is_premium,
+            "monthly_subscription_rate": user.monthly_subscription_rate,
+            "auto_renew_subscription": user.auto_renew_subscription,
+            "days_remaining_in_subscription": user.days_remaining_in_subscription(now),
+            "subscription_expired": user.subscription_expired(now),
+            "total_portfolios": portfolio_ids.len(),
+            "created_at": user.created_at.to_string(),
+            "updated_at": user.updated_at.to_string(),
+        });
+
+        // Optional fields are left out rather than sent as null, like the SQL Server store does
+        let optional = [
+            ("payment_method_type", user.payment_method_type.clone()),
+            ("payment_method_details", user.payment_method_details.clone()),
+            ("payment_method_expiry", user.payment_method_expiry.map(|d| d.to_string())),
+            ("premium_start_date", user.premium_start_date.map(|dt| dt.date().to_string())),
+            ("premium_end_date", user.premium_end_date.map(|dt| dt.date().to_string())),
+            ("last_fund_transaction_date", data.fund_transactions.iter()
+                .filter(|t| t.user_id == user_id)
+                .map(|t| t.created_at)
+                .max()
+                .map(|dt| dt.to_string())),
+            ("last_trade_date", data.transactions.iter()
+                .filter(|t| portfolio_ids.contains(&t.portfolio_id))
+                .map(|t| t.transaction_date)
+                .max()
+                .map(|dt| dt.to_string())),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                summary[key] = serde_json::Value::String(value);
+            }
+        }
+
+        Ok(summary)
+    }
+}
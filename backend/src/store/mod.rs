@@ -0,0 +1,505 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{
+    AccountSummary, AllocationTargets, AmendOrderRequest, Asset, AssetHolding,
+    AssetPriceHistory, BatchLeg, BuyAssetRequest, BuyAssetResponse, CompleteAsset,
+    CostBasisMethod, CreatePortfolioRequest, CreateUserRequest, CsvImportRequest, ExtendedUser,
+    FeeSchedule, FundTransaction, Order, OrderSide, OrderStatus, PaymentMethodResponse,
+    PlaceOrderRequest, Portfolio, PortfolioBalance, PortfolioHoldingsSummary, PortfolioRiskAnalysis,
+    PortfolioSnapshot, PortfolioSummary, QuantityIncrement, RealizedLot, RecurrenceFrequency, RecurringInvestment,
+    RecurringInvestmentRun, RiskAnalysis, RiskMetrics, RiskSummary, SellAssetRequest,
+    SellAssetResponse, SetAllocationTargetsRequest, SetFeeScheduleRequest,
+    SetPaymentMethodRequest, SetTradingLimitsRequest, SubscriptionResponse, TaxLot,
+    TradeProjection, TradingLimits, TradingTransaction, TransactionFilter, UpdateAssetRequest,
+    UpdatePortfolioRequest, UpdatePriceResponse, UpdateUserRequest, User,
+};
+
+mod memory;
+mod sqlserver;
+
+pub use memory::MemoryStore;
+pub use sqlserver::SqlServerStore;
+
+// =============================================================
+// ERRORS
+// =============================================================
+
+/// Error returned by every store operation.
+///
+/// Each variant maps onto an `ApiError`, so a handler can simply `?` a store call.
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound(String),
+    BadRequest(String),
+    InsufficientFunds(String),
+    Conflict(String),
+    Forbidden(String),
+    /// Unexpected driver error; the text is logged, not returned to clients
+    Database(String),
+    Internal(String),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::NotFound(msg)
+            | StoreError::BadRequest(msg)
+            | StoreError::InsufficientFunds(msg)
+            | StoreError::Conflict(msg)
+            | StoreError::Forbidden(msg)
+            | StoreError::Database(msg)
+            | StoreError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+// =============================================================
+// STORE INPUT/OUTPUT TYPES
+// =============================================================
+
+/// Stored password together with the user it belongs to, used by login
+pub struct UserCredentials {
+    pub password: String,
+    pub user: User,
+}
+
+/// A login session, created alongside the first refresh token
+pub struct NewSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    /// SHA-256 of the refresh token; the token itself is never stored
+    pub refresh_token_hash: String,
+    /// SQL Server's DATEADD only takes an int
+    pub ttl_secs: i32,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Result of presenting a refresh token
+pub enum RefreshOutcome {
+    Rotated { session_id: Uuid, user_id: Uuid },
+    /// The token had already been rotated away, so it was likely stolen;
+    /// the session it belonged to has been revoked
+    Reused,
+    /// Unknown, expired or revoked
+    Invalid,
+}
+
+/// How a request carrying an `Idempotency-Key` should be handled
+pub enum IdempotencyClaim {
+    /// First use of the key; run the request, then store its response
+    New,
+    /// The same request already completed; send its response again
+    Replay { status: u16, body: String },
+    /// The key was already used for a different request
+    Mismatch,
+    /// The first request with this key has not finished yet
+    InProgress,
+}
+
+/// Balances after moving money between a user account and a portfolio
+pub struct AllocationBalances {
+    pub new_user_balance: Decimal,
+    pub new_portfolio_funds: Decimal,
+}
+
+/// Risk measures for a new `portfolio.RiskMetrics` row; percentages are
+/// already scaled and everything is rounded to two places
+pub struct NewRiskMetrics {
+    pub maximum_drawdown: Option<f64>,
+    pub beta: Option<f64>,
+    pub sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    pub calmar_ratio: Option<f64>,
+    pub absolute_return: Option<f64>,
+    pub volatility_score: Option<f64>,
+    pub risk_level: String,
+}
+
+/// A new row for `portfolio.Assets`; the symbol is stored as given
+pub struct NewAsset {
+    pub symbol: String,
+    pub name: String,
+    pub asset_type: String,
+    pub price: Decimal,
+    pub volume: i64,
+    pub available_shares: Decimal,
+}
+
+/// One parsed OHLC line from a price import
+pub struct PricePoint {
+    pub as_of: chrono::NaiveDateTime,
+    pub price: Decimal,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub volume: i64,
+    pub change_percent: Option<Decimal>,
+}
+
+/// What the pre-trade rules need to know about a portfolio and an asset,
+/// read together so every rule sees the same state
+pub struct PreTradeSnapshot {
+    pub limits: TradingLimits,
+    pub asset_type: String,
+    pub price: Decimal,
+    /// Seconds since the asset's price was last updated
+    pub price_age_secs: i64,
+    /// None when the asset type has no increment configured
+    pub quantity_increment: Option<Decimal>,
+    pub quantity_held: Decimal,
+    /// Cash plus holdings at current prices
+    pub portfolio_value: Decimal,
+    /// Value of the portfolio's executed buys and sells today
+    pub traded_today: Decimal,
+}
+
+/// A buy or sell worked out by the store without keeping any of its effects
+pub struct TradePreview {
+    pub projection: TradeProjection,
+    pub quantity_held_after: Decimal,
+    /// 0 when nothing is held after the trade
+    pub average_price_after: Decimal,
+    /// Cash plus holdings at current prices once the trade is done
+    pub portfolio_value_after: Decimal,
+}
+
+/// An asset a rebalance may trade: every holding, plus the per-asset targets not held
+pub struct RebalancePosition {
+    pub asset_id: i32,
+    pub symbol: String,
+    pub asset_type: String,
+    pub quantity_held: Decimal,
+    pub price: Decimal,
+    /// None when the asset type has no increment configured
+    pub quantity_increment: Option<Decimal>,
+}
+
+/// What the rebalancer needs to know about a portfolio, read together
+pub struct RebalanceSnapshot {
+    pub cash: Decimal,
+    pub positions: Vec<RebalancePosition>,
+}
+
+/// A recurring investment's schedule as the API settled it
+pub struct RecurringSchedule {
+    pub amount: Decimal,
+    pub frequency: RecurrenceFrequency,
+    pub day_of_week: Option<i32>,
+    pub day_of_month: Option<i32>,
+    /// None pauses the schedule
+    pub next_run_date: Option<NaiveDate>,
+}
+
+/// How a claimed run of a recurring investment ended
+pub enum RecurringRunOutcome {
+    Executed(BuyAssetResponse),
+    /// Nothing was bought, for this reason
+    Skipped(String),
+}
+
+/// An executed buy or sell, by the day it happened
+pub struct HistoricalTrade {
+    pub asset_id: i32,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub date: NaiveDate,
+}
+
+/// A change to a portfolio's cash, signed from the portfolio's side
+pub struct CashMovement {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    /// Allocations and deallocations; trades, fees and the like are internal
+    pub external: bool,
+}
+
+/// A portfolio's current state and everything that changed it since `from`,
+/// enough to work out its value on any day in between
+pub struct PortfolioHistory {
+    pub created_on: NaiveDate,
+    /// Current cash, including funds held back for pending orders
+    pub cash: Decimal,
+    /// Current quantity held of each asset
+    pub holdings: Vec<(i32, Decimal)>,
+    pub trades: Vec<HistoricalTrade>,
+    /// Movements in and out of the pending-order reserve are left out
+    pub cash_movements: Vec<CashMovement>,
+    /// Daily closes, oldest first, of every asset held or traded since `from`.
+    /// Each series starts with the last close before `from` and ends with the
+    /// current price as today's
+    pub closes: Vec<(i32, Vec<(NaiveDate, Decimal)>)>,
+}
+
+/// How a batch of trades run in one transaction ended
+pub enum BatchOutcome {
+    /// Every leg executed and the transaction was committed
+    Committed(Vec<TradeProjection>),
+    /// The leg at this index failed, so the transaction was rolled back
+    RolledBack(usize, StoreError),
+}
+
+// =============================================================
+// AGGREGATE TRAITS
+// =============================================================
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn list_users(&self) -> StoreResult<Vec<User>>;
+    async fn get_user(&self, user_id: Uuid) -> StoreResult<User>;
+    async fn get_user_extended(&self, user_id: Uuid) -> StoreResult<ExtendedUser>;
+    async fn create_user(&self, user: &CreateUserRequest) -> StoreResult<User>;
+    async fn update_user(&self, user_id: Uuid, update: &UpdateUserRequest) -> StoreResult<User>;
+    async fn delete_user(&self, user_id: Uuid) -> StoreResult<()>;
+    /// Looks a user up by email for login; `None` when no account matches
+    async fn find_credentials(&self, email: &str) -> StoreResult<Option<UserCredentials>>;
+    /// Replaces the stored password; the caller hashes it first
+    async fn set_password(&self, user_id: Uuid, password_hash: &str) -> StoreResult<()>;
+    async fn set_payment_method(&self, user_id: Uuid, request: &SetPaymentMethodRequest) -> StoreResult<PaymentMethodResponse>;
+    /// `action` is one of ACTIVATE, RENEW or CANCEL
+    async fn manage_subscription(&self, user_id: Uuid, action: &str, months: i32, monthly_rate: Decimal) -> StoreResult<SubscriptionResponse>;
+}
+
+#[async_trait]
+pub trait FundStore: Send + Sync {
+    /// Returns the new account balance
+    async fn deposit_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal>;
+    /// Returns the new account balance
+    async fn withdraw_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal>;
+    async fn allocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances>;
+    async fn deallocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances>;
+    /// Returns the new account balance
+    async fn upgrade_to_premium(&self, user_id: Uuid, months: i32, monthly_rate: Decimal) -> StoreResult<Decimal>;
+    async fn account_summary(&self, user_id: Uuid) -> StoreResult<AccountSummary>;
+    /// Newest first
+    async fn fund_transactions(&self, user_id: Uuid) -> StoreResult<Vec<FundTransaction>>;
+    async fn enhanced_account_summary(&self, user_id: Uuid) -> StoreResult<serde_json::Value>;
+}
+
+#[async_trait]
+pub trait PortfolioStore: Send + Sync {
+    /// Newest first, optionally restricted to one owner
+    async fn list_portfolios(&self, user_id: Option<Uuid>) -> StoreResult<Vec<Portfolio>>;
+    async fn create_portfolio(&self, portfolio: &CreatePortfolioRequest) -> StoreResult<Portfolio>;
+    async fn get_portfolio(&self, portfolio_id: i32) -> StoreResult<Portfolio>;
+    async fn update_portfolio(&self, portfolio_id: i32, update: &UpdatePortfolioRequest) -> StoreResult<Portfolio>;
+    async fn delete_portfolio(&self, portfolio_id: i32) -> StoreResult<()>;
+    async fn portfolio_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioSummary>;
+    async fn portfolio_holdings(&self, portfolio_id: i32) -> StoreResult<Vec<AssetHolding>>;
+    async fn portfolio_balance(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance>;
+    /// Same figures as `portfolio_balance`, read through `sp_GetPortfolioBalance`
+    async fn portfolio_balance_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance>;
+    async fn holdings_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary>;
+    /// Same figures as `holdings_summary`, read through `sp_GetPortfolioHoldingsSummary`
+    async fn holdings_summary_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary>;
+}
+
+#[async_trait]
+pub trait TradingStore: Send + Sync {
+    async fn buy_asset(&self, request: &BuyAssetRequest) -> StoreResult<BuyAssetResponse>;
+    async fn sell_asset(&self, request: &SellAssetRequest) -> StoreResult<SellAssetResponse>;
+    /// Runs the same checks and arithmetic as `buy_asset`, failing the same
+    /// way, but writes nothing
+    async fn preview_buy(&self, request: &BuyAssetRequest) -> StoreResult<TradePreview>;
+    /// Runs the same checks and arithmetic as `sell_asset`, failing the same
+    /// way, but writes nothing
+    async fn preview_sell(&self, request: &SellAssetRequest) -> StoreResult<TradePreview>;
+    /// Runs the legs in the order given, as `buy_asset` and `sell_asset`
+    /// would, inside one transaction that the first failing leg rolls back
+    async fn execute_batch(&self, portfolio_id: i32, legs: &[&BatchLeg]) -> StoreResult<BatchOutcome>;
+    /// Newest first by transaction id, starting below `before` when given
+    async fn list_transactions(
+        &self,
+        portfolio_id: i32,
+        filter: &TransactionFilter,
+        before: Option<i64>,
+        limit: Option<usize>,
+    ) -> StoreResult<Vec<TradingTransaction>>;
+}
+
+#[async_trait]
+pub trait OrderStore: Send + Sync {
+    /// Records a pending order and reserves the cash a buy may need
+    async fn place_order(&self, portfolio_id: i32, order: &PlaceOrderRequest) -> StoreResult<Order>;
+    async fn get_order(&self, order_id: i64) -> StoreResult<Order>;
+    /// Newest first, optionally restricted to one status
+    async fn list_orders(&self, portfolio_id: i32, status: Option<OrderStatus>) -> StoreResult<Vec<Order>>;
+    /// Oldest first, optionally restricted to one asset
+    async fn pending_orders(&self, asset_id: Option<i32>) -> StoreResult<Vec<Order>>;
+    /// Pending DAY orders whose day has ended
+    async fn expired_order_ids(&self) -> StoreResult<Vec<i64>>;
+    /// Records that a stop-limit order's stop price was reached
+    async fn trigger_order(&self, order_id: i64) -> StoreResult<Order>;
+    /// Executes a pending order at `price`. An order the portfolio can no
+    /// longer cover comes back `Failed`; one that is not pending is a conflict.
+    async fn fill_order(&self, order_id: i64, price: Decimal) -> StoreResult<Order>;
+    /// Changes a pending order's quantity and/or limit price, moving the
+    /// difference in a buy's reservation to or from the portfolio
+    async fn amend_order(&self, order_id: i64, amendment: &AmendOrderRequest) -> StoreResult<Order>;
+    /// Cancels a pending order and releases its reservation
+    async fn cancel_order(&self, order_id: i64, reason: &str) -> StoreResult<Order>;
+}
+
+#[async_trait]
+pub trait TaxLotStore: Send + Sync {
+    async fn cost_basis_method(&self, portfolio_id: i32) -> StoreResult<CostBasisMethod>;
+    /// Applies to sales made from now on; lots already sold keep their basis
+    async fn set_cost_basis_method(&self, portfolio_id: i32, method: CostBasisMethod) -> StoreResult<()>;
+    /// Lots with shares left, oldest first, optionally for one asset
+    async fn open_lots(&self, portfolio_id: i32, asset_id: Option<i32>) -> StoreResult<Vec<TaxLot>>;
+    /// Lots consumed by sales realized between `from` and `to` (inclusive
+    /// days), newest sale first and in the order each sale consumed them
+    async fn realized_lots(
+        &self,
+        portfolio_id: i32,
+        asset_id: Option<i32>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<RealizedLot>>;
+}
+
+#[async_trait]
+pub trait FeeStore: Send + Sync {
+    /// By user type, each one's default schedule first
+    async fn list_fee_schedules(&self) -> StoreResult<Vec<FeeSchedule>>;
+    /// Creates or replaces the schedule for the request's user type and asset type
+    async fn set_fee_schedule(&self, schedule: &SetFeeScheduleRequest) -> StoreResult<FeeSchedule>;
+    async fn delete_fee_schedule(&self, fee_schedule_id: i32) -> StoreResult<()>;
+    /// Fee a trade of this value in the portfolio would be charged now
+    async fn quote_trading_fee(&self, portfolio_id: i32, asset_id: i32, trade_value: Decimal) -> StoreResult<Decimal>;
+}
+
+#[async_trait]
+pub trait TradingRuleStore: Send + Sync {
+    /// No limits when none were ever set
+    async fn trading_limits(&self, portfolio_id: i32) -> StoreResult<TradingLimits>;
+    async fn set_trading_limits(&self, portfolio_id: i32, limits: &SetTradingLimitsRequest) -> StoreResult<TradingLimits>;
+    /// One per asset type
+    async fn quantity_increments(&self) -> StoreResult<Vec<QuantityIncrement>>;
+    async fn set_quantity_increment(&self, asset_type: &str, increment: Decimal) -> StoreResult<QuantityIncrement>;
+    async fn pre_trade_snapshot(&self, portfolio_id: i32, asset_id: i32) -> StoreResult<PreTradeSnapshot>;
+}
+
+#[async_trait]
+pub trait RebalanceStore: Send + Sync {
+    /// Default tolerance and no targets when none were ever set
+    async fn allocation_targets(&self, portfolio_id: i32) -> StoreResult<AllocationTargets>;
+    /// Replaces every target and the rebalance settings of a portfolio
+    async fn set_allocation_targets(&self, portfolio_id: i32, targets: &SetAllocationTargetsRequest) -> StoreResult<AllocationTargets>;
+    async fn rebalance_snapshot(&self, portfolio_id: i32) -> StoreResult<RebalanceSnapshot>;
+}
+
+#[async_trait]
+pub trait RecurringInvestmentStore: Send + Sync {
+    async fn list_recurring_investments(&self, portfolio_id: i32) -> StoreResult<Vec<RecurringInvestment>>;
+    async fn get_recurring_investment(&self, schedule_id: i32) -> StoreResult<RecurringInvestment>;
+    async fn create_recurring_investment(&self, portfolio_id: i32, asset_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment>;
+    async fn update_recurring_investment(&self, schedule_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment>;
+    /// Also removes its runs; their fund transactions stay
+    async fn delete_recurring_investment(&self, schedule_id: i32) -> StoreResult<()>;
+    /// Active schedules whose next run is on or before `today`
+    async fn due_recurring_investments(&self, today: NaiveDate) -> StoreResult<Vec<RecurringInvestment>>;
+    /// Starts the run due on `run_date` and moves the schedule on to `next_run_date`;
+    /// None when the schedule no longer has that run due, e.g. another runner took it
+    async fn claim_recurring_run(&self, schedule_id: i32, run_date: NaiveDate, next_run_date: NaiveDate) -> StoreResult<Option<i64>>;
+    /// Records the outcome of a run and links it to its fund transaction
+    async fn complete_recurring_run(&self, run_id: i64, outcome: &RecurringRunOutcome) -> StoreResult<RecurringInvestmentRun>;
+    /// Newest first
+    async fn list_recurring_runs(&self, schedule_id: i32) -> StoreResult<Vec<RecurringInvestmentRun>>;
+}
+
+#[async_trait]
+pub trait PerformanceStore: Send + Sync {
+    /// History since `from`, or since the portfolio was created; `today` dates the current state
+    async fn portfolio_history(&self, portfolio_id: i32, from: Option<NaiveDate>, today: NaiveDate) -> StoreResult<PortfolioHistory>;
+    async fn last_snapshot_date(&self, portfolio_id: i32) -> StoreResult<Option<NaiveDate>>;
+    /// Inserts the snapshots, replacing any already stored for the same days
+    async fn save_portfolio_snapshots(&self, portfolio_id: i32, snapshots: &[PortfolioSnapshot]) -> StoreResult<()>;
+    /// Oldest first
+    async fn portfolio_snapshots(&self, portfolio_id: i32, from: Option<NaiveDate>, to: Option<NaiveDate>) -> StoreResult<Vec<PortfolioSnapshot>>;
+}
+
+#[async_trait]
+pub trait AssetStore: Send + Sync {
+    /// `search` matches name or symbol as a substring
+    async fn list_assets(&self, search: Option<&str>, asset_type: Option<&str>) -> StoreResult<Vec<Asset>>;
+    async fn get_asset(&self, asset_id: i32) -> StoreResult<Asset>;
+    async fn find_asset_id(&self, symbol: &str) -> StoreResult<Option<i32>>;
+    /// Inserts the asset unless one with the same symbol exists, then returns it
+    async fn create_asset(&self, asset: &NewAsset) -> StoreResult<Asset>;
+    async fn update_asset(&self, asset_id: i32, update: &UpdateAssetRequest) -> StoreResult<Asset>;
+    async fn delete_asset(&self, asset_id: i32) -> StoreResult<()>;
+    async fn update_asset_price(&self, asset_id: i32, price: Decimal, volume: Option<i64>) -> StoreResult<UpdatePriceResponse>;
+    async fn complete_asset(&self, asset_id: i32) -> StoreResult<CompleteAsset>;
+    /// Newest first
+    async fn price_history(&self, asset_id: i32) -> StoreResult<Vec<AssetPriceHistory>>;
+    async fn enhanced_asset_details(&self, asset_id: i32) -> StoreResult<serde_json::Value>;
+    /// Stores the type-specific details carried by a CSV import; returns a status line
+    async fn insert_asset_details(&self, asset_id: i32, details: &CsvImportRequest) -> StoreResult<String>;
+    /// Upserts one price row; returns true when an existing row was replaced
+    async fn import_price(&self, asset_id: i32, point: &PricePoint, update_current_price: bool) -> StoreResult<bool>;
+}
+
+#[async_trait]
+pub trait RiskStore: Send + Sync {
+    async fn user_risk_analysis(&self, user_id: Uuid) -> StoreResult<RiskAnalysis>;
+    async fn portfolio_risk_analysis(&self, portfolio_id: i32) -> StoreResult<PortfolioRiskAnalysis>;
+    async fn risk_summary(&self) -> StoreResult<RiskSummary>;
+    async fn portfolio_risk_summary(&self, portfolio_id: i32) -> StoreResult<RiskSummary>;
+    async fn user_risk_summary(&self, user_id: Uuid) -> StoreResult<RiskSummary>;
+    async fn latest_risk_metrics(&self, user_id: Uuid) -> StoreResult<RiskMetrics>;
+    /// Appends a metrics row worked out by the risk engine and returns it
+    async fn record_risk_metrics(&self, user_id: Uuid, metrics: &NewRiskMetrics) -> StoreResult<RiskMetrics>;
+    /// Oldest first, limited to the last `days_back` days
+    async fn risk_trend(&self, user_id: Uuid, days_back: i32) -> StoreResult<Vec<RiskMetrics>>;
+    /// Last price of each day from `from` through `to` from `AssetPrices`,
+    /// oldest first and led by the last close before `from`; assets without
+    /// prices are left out
+    async fn asset_daily_closes(&self, asset_ids: &[i32], from: NaiveDate, to: NaiveDate) -> StoreResult<Vec<(i32, Vec<(NaiveDate, Decimal)>)>>;
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn create_session(&self, session: &NewSession) -> StoreResult<()>;
+    /// True while the session is neither revoked nor expired
+    async fn session_active(&self, session_id: Uuid) -> StoreResult<bool>;
+    /// Swaps the refresh token for a new one and restarts the session's expiry
+    async fn rotate_session(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, ttl_secs: i32) -> StoreResult<RefreshOutcome>;
+    async fn revoke_session(&self, session_id: Uuid) -> StoreResult<()>;
+    /// Returns how many sessions were revoked
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> StoreResult<u64>;
+}
+
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Reserves `key` for this request or reports how it was used before.
+    /// A claim that is never completed lapses after `lease_secs`, so a
+    /// request cut off mid-flight does not block its retries for long.
+    async fn claim_idempotency_key(&self, user_id: Uuid, key: &str, request_hash: &str, lease_secs: i32) -> StoreResult<IdempotencyClaim>;
+    /// Extends a claim that has not been completed to `lease_secs` from now
+    async fn renew_idempotency_key(&self, user_id: Uuid, key: &str, lease_secs: i32) -> StoreResult<()>;
+    /// Stores the response and keeps the key for `ttl_secs` from now
+    async fn complete_idempotency_key(&self, user_id: Uuid, key: &str, status: u16, body: &str, ttl_secs: i32) -> StoreResult<()>;
+    /// Forgets a claimed key so the request can be retried
+    async fn release_idempotency_key(&self, user_id: Uuid, key: &str) -> StoreResult<()>;
+}
+
+/// Everything the HTTP layer needs from persistence
+#[async_trait]
+pub trait Store:
+    UserStore + FundStore + PortfolioStore + TradingStore + OrderStore + TaxLotStore + FeeStore
+    + TradingRuleStore + RebalanceStore + RecurringInvestmentStore + PerformanceStore + AssetStore + RiskStore
+    + SessionStore + IdempotencyStore
+{
+    /// Human readable backend name, reported by `/db-health`
+    fn backend_name(&self) -> &'static str;
+    /// Checks that the backend is reachable
+    async fn ping(&self) -> StoreResult<()>;
+}
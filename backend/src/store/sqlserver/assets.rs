@@ -0,0 +1,591 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::time::chrono;
+
+use crate::models::{
+    Asset, AssetFactory, AssetPriceHistory, CompleteAsset, CryptoDetails, CsvImportRequest,
+    StockDetails, UpdateAssetRequest, UpdatePriceResponse,
+};
+use crate::store::{AssetStore, NewAsset, PricePoint, StoreError, StoreResult};
+use super::{asset_from_row, count, sql_error, SqlServerStore};
+
+#[async_trait]
+impl AssetStore for SqlServerStore {
+    async fn list_assets(&self, search: Option<&str>, asset_type: Option<&str>) -> StoreResult<Vec<Asset>> {
+        let mut client = self.client().await?;
+
+        struct QueryParams {
+            query: String,
+            params: Vec<Box<dyn tiberius::ToSql>>,
+        }
+
+        let query_params = match (search, asset_type) {
+            (Some(search), Some(type_filter)) => {
+                let pattern = format!("%{}%", search);
+                QueryParams {
+                    query: "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                           FROM portfolio.Assets
+                           WHERE (Name LIKE @P1 OR Symbol LIKE @P1) AND AssetType = @P2".to_string(),
+                    params: vec![
+                        Box::new(pattern),
+                        Box::new(type_filter.to_string())
+                    ],
+                }
+            },
+            (Some(search), None) => {
+                let pattern = format!("%{}%", search);
+                QueryParams {
+                    query: "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                           FROM portfolio.Assets
+                           WHERE Name LIKE @P1 OR Symbol LIKE @P1".to_string(),
+                    params: vec![Box::new(pattern)],
+                }
+            },
+            (None, Some(type_filter)) => QueryParams {
+                query: "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                       FROM portfolio.Assets
+                       WHERE AssetType = @P1".to_string(),
+                params: vec![Box::new(type_filter.to_string())],
+            },
+            (None, None) => QueryParams {
+                query: "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                       FROM portfolio.Assets".to_string(),
+                params: vec![],
+            },
+        };
+
+        let param_refs: Vec<&dyn tiberius::ToSql> = query_params.params.iter()
+            .map(|p| p.as_ref() as &dyn tiberius::ToSql)
+            .collect();
+
+        let stream = client.query(&query_params.query, &param_refs[..]).await.map_err(sql_error("Query error"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Row error"))?;
+
+        Ok(rows.iter().map(asset_from_row).collect())
+    }
+
+    async fn get_asset(&self, asset_id: i32) -> StoreResult<Asset> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                     FROM portfolio.Assets WHERE AssetID = @P1";
+
+        let stream = client.query(query, &[&asset_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))?;
+
+        Ok(asset_from_row(&row))
+    }
+
+    async fn find_asset_id(&self, symbol: &str) -> StoreResult<Option<i32>> {
+        let mut client = self.client().await?;
+
+        let find_query = "SELECT AssetID FROM portfolio.Assets WHERE Symbol = @P1";
+        let find_stream = client.query(find_query, &[&symbol]).await.map_err(sql_error("Failed to find asset"))?;
+
+        let find_result = find_stream.into_first_result().await.map_err(sql_error("Failed to fetch asset"))?;
+
+        Ok(find_result.first().and_then(|row| row.get("AssetID")))
+    }
+
+    async fn create_asset(&self, asset: &NewAsset) -> StoreResult<Asset> {
+        let mut client = self.client().await?;
+
+        // Since handling OUTPUT parameters is complex in Tiberius, check for the symbol first
+        let check_query = "SELECT AssetID FROM portfolio.Assets WHERE Symbol = @P1";
+        let check_stream = client.query(check_query, &[&asset.symbol]).await.map_err(sql_error("Failed to check existing asset"))?;
+
+        let existing_result = check_stream.into_first_result().await.map_err(sql_error("Failed to fetch asset check"))?;
+
+        let asset_id: Option<i32> = existing_result.first().and_then(|row| row.get("AssetID"));
+
+        if asset_id.is_none() {
+            // Asset doesn't exist, create it directly
+            let insert_query = "INSERT INTO portfolio.Assets (Symbol, Name, AssetType, Price, Volume, AvailableShares)
+                               VALUES (@P1, @P2, @P3, @P4, @P5, @P6)";
+            let inserted = client.query(
+                insert_query,
+                &[
+                    &asset.symbol,
+                    &asset.name,
+                    &asset.asset_type,
+                    &asset.price,
+                    &asset.volume,
+                    &asset.available_shares
+                ],
+            ).await;
+
+            match inserted {
+                Ok(insert_stream) => {
+                    // Consume the insert result
+                    insert_stream.into_first_result().await.map_err(sql_error("Failed to execute insert"))?;
+                },
+                Err(e) => {
+                    let error_msg = format!("{}", e);
+                    // Another request might have created it, that's ok
+                    if !error_msg.contains("UNIQUE KEY constraint") && !error_msg.contains("duplicate") {
+                        return Err(sql_error("Failed to create asset")(e));
+                    }
+                }
+            }
+        }
+
+        // Now find the asset by symbol to get the ID
+        let find_query = "SELECT AssetID, Name, Symbol, AssetType, Price, Volume, AvailableShares, LastUpdated
+                          FROM portfolio.Assets WHERE Symbol = @P1";
+
+        let find_stream = client.query(find_query, &[&asset.symbol]).await.map_err(sql_error("Failed to find created asset"))?;
+
+        let row = find_stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch created asset"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::Internal("Failed to retrieve created asset".to_string()))?;
+
+        Ok(asset_from_row(&row))
+    }
+
+    async fn update_asset(&self, asset_id: i32, update: &UpdateAssetRequest) -> StoreResult<Asset> {
+        let mut client = self.client().await?;
+
+        // Check if asset exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Assets WHERE AssetID = @P1",
+            &[&asset_id],
+            "asset existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("Asset not found".to_string()));
+        }
+
+        // Build update query dynamically
+        let mut set_clauses = Vec::new();
+        let mut params: Vec<Box<dyn tiberius::ToSql>> = vec![Box::new(asset_id)];
+        let mut param_index = 2;
+
+        if let Some(name) = &update.name {
+            set_clauses.push(format!("Name = @P{}", param_index));
+            params.push(Box::new(name.clone()));
+            param_index += 1;
+        }
+
+        if let Some(price) = update.price {
+            set_clauses.push(format!("Price = @P{}", param_index));
+            params.push(Box::new(price));
+            param_index += 1;
+        }
+
+        if let Some(volume) = update.volume {
+            set_clauses.push(format!("Volume = @P{}", param_index));
+            params.push(Box::new(volume));
+            param_index += 1;
+        }
+
+        if let Some(available_shares) = update.available_shares {
+            set_clauses.push(format!("AvailableShares = @P{}", param_index));
+            params.push(Box::new(available_shares));
+        }
+
+        if set_clauses.is_empty() {
+            return Err(StoreError::BadRequest("No fields to update".to_string()));
+        }
+
+        let update_query = format!(
+            "UPDATE portfolio.Assets SET {} WHERE AssetID = @P1",
+            set_clauses.join(", ")
+        );
+
+        let param_refs: Vec<&dyn tiberius::ToSql> = params.iter()
+            .map(|p| p.as_ref() as &dyn tiberius::ToSql)
+            .collect();
+
+        let stream = client.query(&update_query, &param_refs[..]).await.map_err(sql_error("Failed to update asset"))?;
+
+        stream.into_first_result().await.map_err(sql_error("Failed to confirm update"))?;
+
+        // Return updated asset (releasing our connection back to the pool first)
+        drop(client);
+        self.get_asset(asset_id).await
+    }
+
+    async fn delete_asset(&self, asset_id: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        // First check if asset exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Assets WHERE AssetID = @P1",
+            &[&asset_id],
+            "asset existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("Asset not found".to_string()));
+        }
+
+        // Check for active holdings that would prevent deletion
+        let holdings_count = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.PortfolioHoldings WHERE AssetID = @P1",
+            &[&asset_id],
+            "asset holdings",
+        ).await?;
+
+        if holdings_count > 0 {
+            return Err(StoreError::Conflict("Cannot delete asset with active holdings. Please ensure no portfolios hold this asset.".to_string()));
+        }
+
+        // Check for existing transactions that would prevent deletion
+        let trans_count = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Transactions WHERE AssetID = @P1",
+            &[&asset_id],
+            "asset transactions",
+        ).await?;
+
+        if trans_count > 0 {
+            return Err(StoreError::Conflict("Cannot delete asset with existing transaction history. Contact support if deletion is required.".to_string()));
+        }
+
+        // Delete the asset - cascade deletes will handle:
+        // - AssetPrices (ON DELETE CASCADE)
+        // - StockDetails (ON DELETE CASCADE)
+        // - CryptoDetails (ON DELETE CASCADE)
+        // - CommodityDetails (ON DELETE CASCADE)
+        // - IndexDetails (ON DELETE CASCADE)
+        let delete_query = "DELETE FROM portfolio.Assets WHERE AssetID = @P1";
+        let delete_stream = client.query(delete_query, &[&asset_id]).await.map_err(|e| {
+            let error_msg = format!("{}", e);
+            if error_msg.contains("REFERENCE constraint") || error_msg.contains("FOREIGN KEY") {
+                StoreError::Conflict("Cannot delete asset due to related data. Contact support if needed.".to_string())
+            } else {
+                sql_error("Failed to delete asset")(e)
+            }
+        })?;
+
+        delete_stream.into_first_result().await.map_err(sql_error("Failed to confirm deletion"))?;
+
+        Ok(())
+    }
+
+    async fn update_asset_price(&self, asset_id: i32, price: Decimal, volume: Option<i64>) -> StoreResult<UpdatePriceResponse> {
+        let mut client = self.client().await?;
+
+        let volume = volume.unwrap_or(0);
+        let query = "EXEC portfolio.sp_UpdateAssetPrice @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&asset_id, &price, &volume],
+        ).await.map_err(sql_error("Failed to update price"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned".to_string()))?;
+
+        Ok(UpdatePriceResponse {
+            status: row.get::<&str, _>("Status").unwrap_or("SUCCESS").to_string(),
+            message: row.get::<&str, _>("Message").unwrap_or("Price updated successfully").to_string(),
+        })
+    }
+
+    async fn complete_asset(&self, asset_id: i32) -> StoreResult<CompleteAsset> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_GetAssetComplete @P1";
+        let stream = client.query(query, &[&asset_id]).await.map_err(sql_error("Failed to get complete asset"))?;
+
+        // The stored procedure returns multiple result sets
+        let results = stream.into_results().await.map_err(sql_error("Failed to fetch results"))?;
+
+        if results.is_empty() {
+            return Err(StoreError::NotFound("Asset not found".to_string()));
+        }
+
+        // First result set: basic asset info
+        let asset_row = results[0].first()
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))?;
+
+        let asset = asset_from_row(asset_row);
+
+        // Initialize detail fields
+        let mut stock_details = None;
+        let mut crypto_details = None;
+        let commodity_details = None;
+        let index_details = None;
+
+        // Second result set: asset-specific details (based on asset type)
+        if results.len() > 1 && !results[1].is_empty() {
+            match asset.asset_type.as_str() {
+                "Stock" => {
+                    if let Some(detail_row) = results[1].first() {
+                        stock_details = Some(StockDetails {
+                            asset_id,
+                            sector: detail_row.get::<&str, _>("Sector").unwrap_or("").to_string(),
+                            country: detail_row.get::<&str, _>("Country").unwrap_or("").to_string(),
+                            market_cap: detail_row.get::<Decimal, _>("MarketCap")
+                                .unwrap_or_default(),
+                            last_updated: detail_row.get::<chrono::NaiveDateTime, _>("LastUpdated")
+                                .map(|dt| dt.to_string())
+                                .unwrap_or_default(),
+                        });
+                    }
+                },
+                "Cryptocurrency" => {
+                    if let Some(detail_row) = results[1].first() {
+                        crypto_details = Some(CryptoDetails {
+                            asset_id,
+                            blockchain: detail_row.get::<&str, _>("Blockchain").unwrap_or("").to_string(),
+                            max_supply: detail_row.get::<Decimal, _>("MaxSupply"),
+                            circulating_supply: detail_row.get::<Decimal, _>("CirculatingSupply")
+                                .unwrap_or_default(),
+                            last_updated: detail_row.get::<chrono::NaiveDateTime, _>("LastUpdated")
+                                .map(|dt| dt.to_string())
+                                .unwrap_or_default(),
+                        });
+                    }
+                },
+                _ => {} // Handle other types as needed
+            }
+        }
+
+        // Third result set: price history
+        let recent_prices = results.get(2).map(|rows| {
+            rows.iter().map(|row| AssetPriceHistory {
+                asset_id,
+                symbol: asset.symbol.clone(),
+                price: row.get::<Decimal, _>("Price").unwrap_or_default(),
+                volume: row.get::<i64, _>("Volume").unwrap_or_default(),
+                timestamp: row.get::<chrono::NaiveDateTime, _>("AsOf")
+                    .map(|dt| dt.to_string())
+                    .unwrap_or_default(),
+            }).collect()
+        }).unwrap_or_default();
+
+        Ok(CompleteAsset {
+            asset,
+            stock_details,
+            crypto_details,
+            commodity_details,
+            index_details,
+            recent_prices,
+        })
+    }
+
+    async fn price_history(&self, asset_id: i32) -> StoreResult<Vec<AssetPriceHistory>> {
+        let mut client = self.client().await?;
+
+        // First check if asset exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Assets WHERE AssetID = @P1",
+            &[&asset_id],
+            "asset existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("Asset not found".to_string()));
+        }
+
+        // Use the enhanced asset price history view for better performance and more data
+        let query = "SELECT AssetID, Symbol, AssetName, ClosePrice, Volume, PriceDate,
+                            OpenPrice, HighPrice, LowPrice, DailyChangePercent, DailyChangeAmount
+                     FROM portfolio.vw_AssetPriceHistory
+                     WHERE AssetID = @P1
+                     ORDER BY PriceDate DESC";
+
+        let stream = client.query(query, &[&asset_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        let price_history: Vec<AssetPriceHistory> = rows.into_iter().map(|row| {
+            AssetPriceHistory {
+                asset_id: row.get("AssetID").unwrap_or_default(),
+                symbol: row.get::<&str, _>("Symbol").unwrap_or("").to_string(),
+                price: row.get::<Decimal, _>("ClosePrice")
+                    .unwrap_or_default(),
+                volume: row.get::<i64, _>("Volume").unwrap_or_default(),
+                timestamp: row.get::<chrono::NaiveDateTime, _>("PriceDate")
+                    .map(|dt| dt.to_string())
+                    .unwrap_or_default(),
+            }
+        }).collect();
+
+        Ok(price_history)
+    }
+
+    async fn enhanced_asset_details(&self, asset_id: i32) -> StoreResult<serde_json::Value> {
+        let mut client = self.client().await?;
+
+        // Use the enhanced asset details view
+        let query = "SELECT * FROM portfolio.vw_AssetDetails WHERE AssetID = @P1";
+
+        let stream = client.query(query, &[&asset_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Asset not found".to_string()))?;
+
+        // Build enhanced asset details response
+        let mut enhanced_details = serde_json::Map::new();
+
+        // Basic asset info
+        enhanced_details.insert("asset_id".to_string(), serde_json::Value::Number(serde_json::Number::from(asset_id)));
+        enhanced_details.insert("name".to_string(), serde_json::Value::String(row.get::<&str, _>("Name").unwrap_or("").to_string()));
+        enhanced_details.insert("symbol".to_string(), serde_json::Value::String(row.get::<&str, _>("Symbol").unwrap_or("").to_string()));
+        enhanced_details.insert("asset_type".to_string(), serde_json::Value::String(row.get::<&str, _>("AssetType").unwrap_or("").to_string()));
+        enhanced_details.insert("price".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("Price").unwrap_or_default().to_string()
+        ));
+        enhanced_details.insert("volume".to_string(), serde_json::Value::Number(serde_json::Number::from(row.get::<i64, _>("Volume").unwrap_or_default())));
+        enhanced_details.insert("available_shares".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("AvailableShares").unwrap_or_default().to_string()
+        ));
+        enhanced_details.insert("last_updated".to_string(), serde_json::Value::String(
+            row.get::<chrono::NaiveDateTime, _>("LastUpdated").map(|dt| dt.to_string()).unwrap_or_default()
+        ));
+
+        // Enhanced fields from the view
+        if let Some(category_info) = row.get::<&str, _>("CategoryInfo") {
+            enhanced_details.insert("category_info".to_string(), serde_json::Value::String(category_info.to_string()));
+        }
+        if let Some(additional_info) = row.get::<&str, _>("AdditionalInfo") {
+            enhanced_details.insert("additional_info".to_string(), serde_json::Value::String(additional_info.to_string()));
+        }
+        if let Some(market_metric) = row.get::<Decimal, _>("MarketMetric") {
+            enhanced_details.insert("market_metric".to_string(), serde_json::Value::String(market_metric.to_string()));
+        }
+        if let Some(price_30_days_ago) = row.get::<Decimal, _>("Price30DaysAgo") {
+            enhanced_details.insert("price_30_days_ago".to_string(), serde_json::Value::String(price_30_days_ago.to_string()));
+        }
+
+        // Holdings statistics
+        enhanced_details.insert("total_quantity_held".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("TotalQuantityHeld").unwrap_or_default().to_string()
+        ));
+        enhanced_details.insert("total_portfolios_holding".to_string(), serde_json::Value::Number(
+            serde_json::Number::from(row.get::<i32, _>("TotalPortfoliosHolding").unwrap_or_default())
+        ));
+
+        Ok(serde_json::Value::Object(enhanced_details))
+    }
+
+    async fn insert_asset_details(&self, asset_id: i32, details: &CsvImportRequest) -> StoreResult<String> {
+        let mut client = self.client().await?;
+
+        match details.asset_type.as_str() {
+            "Stock" => {
+                if let (Some(sector), Some(country)) = (&details.sector, &details.country) {
+                    // Validate stock data
+                    AssetFactory::validate_stock_data(sector, country, details.market_cap)
+                        .map_err(|e| StoreError::BadRequest(format!("Stock validation failed: {}", e)))?;
+
+                    let query = "INSERT INTO portfolio.StockDetails (AssetID, Sector, Country, MarketCap) VALUES (@P1, @P2, @P3, @P4)";
+                    let stream = client.query(
+                        query,
+                        &[&asset_id, sector, country, &details.market_cap.unwrap_or_default()],
+                    ).await.map_err(sql_error("Failed to insert stock details"))?;
+
+                    stream.into_first_result().await.map_err(sql_error("Failed to confirm stock details insert"))?;
+
+                    Ok("Stock details created successfully".to_string())
+                } else {
+                    Ok("Stock created without detailed information".to_string())
+                }
+            },
+            "Cryptocurrency" => {
+                if let Some(blockchain) = &details.blockchain {
+                    // Validate crypto data
+                    AssetFactory::validate_crypto_data(blockchain, details.max_supply, details.circulating_supply)
+                        .map_err(|e| StoreError::BadRequest(format!("Crypto validation failed: {}", e)))?;
+
+                    let query = "INSERT INTO portfolio.CryptoDetails (AssetID, Blockchain, MaxSupply, CirculatingSupply) VALUES (@P1, @P2, @P3, @P4)";
+                    let stream = client.query(
+                        query,
+                        &[&asset_id, blockchain, &details.max_supply, &details.circulating_supply.unwrap_or_default()],
+                    ).await.map_err(sql_error("Failed to insert crypto details"))?;
+
+                    stream.into_first_result().await.map_err(sql_error("Failed to confirm crypto details insert"))?;
+
+                    Ok("Cryptocurrency details created successfully".to_string())
+                } else {
+                    Ok("Cryptocurrency created without detailed information".to_string())
+                }
+            },
+            "Index" => {
+                if let (Some(country), Some(region), Some(index_type)) = (&details.country, &details.region, &details.index_type) {
+                    // Validate index data
+                    AssetFactory::validate_index_data(country, region, index_type, details.component_count)
+                        .map_err(|e| StoreError::BadRequest(format!("Index validation failed: {}", e)))?;
+
+                    let query = "INSERT INTO portfolio.IndexDetails (AssetID, Country, Region, IndexType, ComponentCount) VALUES (@P1, @P2, @P3, @P4, @P5)";
+                    let stream = client.query(
+                        query,
+                        &[&asset_id, country, region, index_type, &details.component_count],
+                    ).await.map_err(sql_error("Failed to insert index details"))?;
+
+                    stream.into_first_result().await.map_err(sql_error("Failed to confirm index details insert"))?;
+
+                    Ok("Index details created successfully".to_string())
+                } else {
+                    Ok("Index created without detailed information".to_string())
+                }
+            },
+            "Commodity" => {
+                if let (Some(category), Some(unit)) = (&details.category, &details.unit) {
+                    // Validate commodity data
+                    AssetFactory::validate_commodity_data(category, unit)
+                        .map_err(|e| StoreError::BadRequest(format!("Commodity validation failed: {}", e)))?;
+
+                    let query = "INSERT INTO portfolio.CommodityDetails (AssetID, Category, Unit) VALUES (@P1, @P2, @P3)";
+                    let stream = client.query(
+                        query,
+                        &[&asset_id, category, unit],
+                    ).await.map_err(sql_error("Failed to insert commodity details"))?;
+
+                    stream.into_first_result().await.map_err(sql_error("Failed to confirm commodity details insert"))?;
+
+                    Ok("Commodity details created successfully".to_string())
+                } else {
+                    Ok("Commodity created without detailed information".to_string())
+                }
+            },
+            asset_type => Ok(format!("Asset type '{}' created without specific details", asset_type))
+        }
+    }
+
+    async fn import_price(&self, asset_id: i32, point: &PricePoint, update_current_price: bool) -> StoreResult<bool> {
+        let mut client = self.client().await?;
+
+        // Import the price data with change percentage
+        let query = "EXEC portfolio.sp_import_asset_price @P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9";
+        let stream = client.query(
+            query,
+            &[
+                &asset_id,
+                &point.price,
+                &point.as_of,
+                &point.open,
+                &point.high,
+                &point.low,
+                &point.volume,
+                &point.change_percent,
+                &update_current_price,
+            ],
+        ).await.map_err(sql_error("Database error"))?;
+
+        stream.into_first_result().await.map_err(sql_error("Failed to get import result"))?;
+
+        // The procedure does not report whether it replaced an existing row
+        Ok(false)
+    }
+}
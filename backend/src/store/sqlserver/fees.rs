@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+
+use crate::models::{FeeSchedule, FeeTier, FeeType, SetFeeScheduleRequest};
+use crate::store::{FeeStore, StoreError, StoreResult};
+use super::{sql_error, SqlServerStore};
+
+fn fee_schedule_from_row(row: &Row) -> FeeSchedule {
+    FeeSchedule {
+        fee_schedule_id: row.get("FeeScheduleID").unwrap_or_default(),
+        user_type: row.get::<&str, _>("UserType").unwrap_or_default().to_string(),
+        asset_type: row.get::<&str, _>("AssetType").map(str::to_string),
+        fee_type: row.get::<&str, _>("FeeType")
+            .and_then(|fee_type| fee_type.parse().ok())
+            .unwrap_or(FeeType::Flat),
+        flat_fee: row.get::<Decimal, _>("FlatFee"),
+        rate: row.get::<Decimal, _>("Rate"),
+        minimum_fee: row.get::<Decimal, _>("MinimumFee").unwrap_or_default(),
+        tiers: Vec::new(),
+        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait]
+impl FeeStore for SqlServerStore {
+    async fn list_fee_schedules(&self) -> StoreResult<Vec<FeeSchedule>> {
+        let mut client = self.client().await?;
+
+        let stream = client.simple_query(
+            "SELECT * FROM portfolio.FeeSchedules
+             ORDER BY UserType, CASE WHEN AssetType IS NULL THEN 0 ELSE 1 END, AssetType;
+             SELECT * FROM portfolio.FeeTiers ORDER BY FeeScheduleID, MinTradeValue;",
+        ).await.map_err(sql_error("Failed to list fee schedules"))?;
+
+        let mut results = stream.into_results().await.map_err(sql_error("Failed to fetch fee schedules"))?;
+        let tier_rows = results.pop().unwrap_or_default();
+        let schedule_rows = results.pop().unwrap_or_default();
+
+        let mut schedules: Vec<FeeSchedule> = schedule_rows.iter().map(fee_schedule_from_row).collect();
+        for row in &tier_rows {
+            let fee_schedule_id: i32 = row.get("FeeScheduleID").unwrap_or_default();
+            if let Some(schedule) = schedules.iter_mut().find(|s| s.fee_schedule_id == fee_schedule_id) {
+                schedule.tiers.push(FeeTier {
+                    min_trade_value: row.get::<Decimal, _>("MinTradeValue").unwrap_or_default(),
+                    rate: row.get::<Decimal, _>("Rate").unwrap_or_default(),
+                });
+            }
+        }
+
+        Ok(schedules)
+    }
+
+    async fn set_fee_schedule(&self, schedule: &SetFeeScheduleRequest) -> StoreResult<FeeSchedule> {
+        let fee_schedule_id = {
+            let mut client = self.client().await?;
+
+            let tiers_param: Option<String> = match &schedule.tiers {
+                Some(tiers) => Some(serde_json::to_string(tiers)
+                    .map_err(|e| StoreError::Internal(format!("Failed to encode fee tiers: {}", e)))?),
+                None => None,
+            };
+            let stream = client.query(
+                "EXEC portfolio.sp_SetFeeSchedule @P1, @P2, @P3, @P4, @P5, @P6, @P7",
+                &[
+                    &schedule.user_type.as_str(),
+                    &schedule.asset_type.as_deref(),
+                    &schedule.fee_type.as_str(),
+                    &schedule.flat_fee,
+                    &schedule.rate,
+                    &schedule.minimum_fee.unwrap_or_default(),
+                    &tiers_param,
+                ],
+            ).await.map_err(sql_error("Failed to set fee schedule"))?;
+
+            let row = stream.into_row().await.map_err(sql_error("Failed to fetch fee schedule"))?
+                .ok_or_else(|| StoreError::Internal("No result returned from fee schedule update".to_string()))?;
+            row.get::<i32, _>("FeeScheduleID").unwrap_or_default()
+        };
+
+        self.list_fee_schedules().await?
+            .into_iter()
+            .find(|s| s.fee_schedule_id == fee_schedule_id)
+            .ok_or_else(|| StoreError::Internal("Fee schedule disappeared after update".to_string()))
+    }
+
+    async fn delete_fee_schedule(&self, fee_schedule_id: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let result = client.execute(
+            "DELETE FROM portfolio.FeeSchedules WHERE FeeScheduleID = @P1",
+            &[&fee_schedule_id],
+        ).await.map_err(sql_error("Failed to delete fee schedule"))?;
+
+        if result.total() == 0 {
+            return Err(StoreError::NotFound("Fee schedule not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn quote_trading_fee(&self, portfolio_id: i32, asset_id: i32, trade_value: Decimal) -> StoreResult<Decimal> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT portfolio.fn_CalculateTradingFee(p.UserID, @P2, @P3) AS Fee
+             FROM portfolio.Portfolios p
+             WHERE p.PortfolioID = @P1",
+            &[&portfolio_id, &asset_id, &trade_value],
+        ).await.map_err(sql_error("Failed to quote trading fee"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch trading fee"))?
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+        Ok(row.get::<Decimal, _>("Fee").unwrap_or_default())
+    }
+}
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::store::{IdempotencyClaim, IdempotencyStore, StoreResult};
+use super::{sql_error, sql_uuid, SqlServerStore};
+
+#[async_trait]
+impl IdempotencyStore for SqlServerStore {
+    async fn claim_idempotency_key(&self, user_id: Uuid, key: &str, request_hash: &str, lease_secs: i32) -> StoreResult<IdempotencyClaim> {
+        let mut client = self.client().await?;
+        let user_id = sql_uuid(user_id);
+
+        client.execute(
+            "DELETE FROM portfolio.IdempotencyKeys
+             WHERE UserID = @P1 AND IdempotencyKey = @P2 AND ExpiresAt <= SYSDATETIME()",
+            &[&user_id, &key],
+        ).await.map_err(sql_error("Failed to expire idempotency key"))?;
+
+        // The lock hints make the existence check and the insert one step, so
+        // two concurrent requests with the same key cannot both claim it
+        let claim = "INSERT INTO portfolio.IdempotencyKeys (UserID, IdempotencyKey, RequestHash, ExpiresAt)
+                     SELECT @P1, @P2, @P3, DATEADD(SECOND, @P4, SYSDATETIME())
+                     WHERE NOT EXISTS (
+                         SELECT 1 FROM portfolio.IdempotencyKeys WITH (UPDLOCK, HOLDLOCK)
+                         WHERE UserID = @P1 AND IdempotencyKey = @P2
+                     )";
+        let result = client.execute(claim, &[&user_id, &key, &request_hash, &lease_secs]).await
+            .map_err(sql_error("Failed to claim idempotency key"))?;
+        if result.total() > 0 {
+            return Ok(IdempotencyClaim::New);
+        }
+
+        let query = "SELECT RequestHash, ResponseStatus, ResponseBody
+                     FROM portfolio.IdempotencyKeys
+                     WHERE UserID = @P1 AND IdempotencyKey = @P2";
+        let stream = client.query(query, &[&user_id, &key]).await
+            .map_err(sql_error("Failed to read idempotency key"))?;
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch idempotency key"))?;
+
+        // Released between the insert and the read; the client may retry
+        let Some(row) = row else {
+            return Ok(IdempotencyClaim::InProgress);
+        };
+
+        if row.get::<&str, _>("RequestHash") != Some(request_hash) {
+            return Ok(IdempotencyClaim::Mismatch);
+        }
+
+        match row.get::<i32, _>("ResponseStatus") {
+            Some(status) => Ok(IdempotencyClaim::Replay {
+                status: status as u16,
+                body: row.get::<&str, _>("ResponseBody").unwrap_or_default().to_string(),
+            }),
+            None => Ok(IdempotencyClaim::InProgress),
+        }
+    }
+
+    async fn renew_idempotency_key(&self, user_id: Uuid, key: &str, lease_secs: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        client.execute(
+            "UPDATE portfolio.IdempotencyKeys
+             SET ExpiresAt = DATEADD(SECOND, @P3, SYSDATETIME())
+             WHERE UserID = @P1 AND IdempotencyKey = @P2 AND ResponseStatus IS NULL",
+            &[&sql_uuid(user_id), &key, &lease_secs],
+        ).await.map_err(sql_error("Failed to renew idempotency key"))?;
+
+        Ok(())
+    }
+
+    async fn complete_idempotency_key(&self, user_id: Uuid, key: &str, status: u16, body: &str, ttl_secs: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        client.execute(
+            "UPDATE portfolio.IdempotencyKeys
+             SET ResponseStatus = @P3, ResponseBody = @P4, ExpiresAt = DATEADD(SECOND, @P5, SYSDATETIME())
+             WHERE UserID = @P1 AND IdempotencyKey = @P2",
+            &[&sql_uuid(user_id), &key, &i32::from(status), &body, &ttl_secs],
+        ).await.map_err(sql_error("Failed to store idempotent response"))?;
+
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, user_id: Uuid, key: &str) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        client.execute(
+            "DELETE FROM portfolio.IdempotencyKeys WHERE UserID = @P1 AND IdempotencyKey = @P2",
+            &[&sql_uuid(user_id), &key],
+        ).await.map_err(sql_error("Failed to release idempotency key"))?;
+
+        Ok(())
+    }
+}
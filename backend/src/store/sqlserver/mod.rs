@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use bb8::PooledConnection;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+use uuid::Uuid;
+
+use crate::db::{DbClient, DbPool, TiberiusConnectionManager};
+use crate::models::{Asset, Portfolio, RiskMetrics, User};
+use super::{Store, StoreError, StoreResult};
+
+mod assets;
+mod fees;
+mod idempotency;
+mod orders;
+mod performance;
+mod portfolios;
+mod rebalancing;
+mod recurring;
+mod risk;
+mod sessions;
+mod tax_lots;
+mod trading_rules;
+mod users;
+
+/// Store backed by the SQL Server schema in `database/migrations`
+pub struct SqlServerStore {
+    pool: DbPool,
+}
+
+impl SqlServerStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    async fn client(&self) -> StoreResult<PooledConnection<'_, TiberiusConnectionManager>> {
+        self.pool.get().await.map_err(|e|
+            StoreError::Database(format!("Failed to connect to database: {}", e)))
+    }
+}
+
+#[async_trait]
+impl Store for SqlServerStore {
+    fn backend_name(&self) -> &'static str {
+        "SQL Server"
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        self.client().await.map(|_client| ())
+    }
+}
+
+// =============================================================
+// ROW HELPERS
+// =============================================================
+
+// Risk ratios are statistics, not money, so they are the only DECIMAL
+// columns read as f64
+fn ratio(row: &Row, column: &str) -> Option<f64> {
+    row.get::<Decimal, _>(column).and_then(|value| value.to_f64())
+}
+
+fn sql_uuid(id: Uuid) -> tiberius::Uuid {
+    tiberius::Uuid::from_bytes(*id.as_bytes())
+}
+
+fn user_from_row(row: &Row) -> User {
+    User {
+        user_id: row.get::<tiberius::Uuid, _>("UserID")
+            .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
+            .unwrap_or_default(),
+        name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
+        email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
+        country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
+        iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
+        user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
+        created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn portfolio_from_row(row: &Row) -> Portfolio {
+    Portfolio {
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        user_id: row.get::<tiberius::Uuid, _>("UserID")
+            .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
+            .unwrap_or_default(),
+        name: row.get::<&str, _>("Name").unwrap_or_default().to_string(),
+        creation_date: row.get::<chrono::NaiveDateTime, _>("CreationDate")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        current_funds: row.get::<Decimal, _>("CurrentFunds")
+            .unwrap_or_default(),
+        current_profit_pct: row.get::<Decimal, _>("CurrentProfitPct")
+            .unwrap_or_default(),
+        last_updated: row.get::<chrono::NaiveDateTime, _>("LastUpdated")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn asset_from_row(row: &Row) -> Asset {
+    Asset {
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or("").to_string(),
+        asset_type: row.get::<&str, _>("AssetType").unwrap_or("").to_string(),
+        price: row.get::<Decimal, _>("Price")
+            .unwrap_or_default(),
+        volume: row.get::<i64, _>("Volume").unwrap_or_default(),
+        available_shares: row.get::<Decimal, _>("AvailableShares")
+            .unwrap_or_default(),
+        last_updated: row.get::<chrono::NaiveDateTime, _>("LastUpdated")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn risk_metrics_from_row(row: &Row, user_id: Uuid) -> RiskMetrics {
+    RiskMetrics {
+        metric_id: row.get("MetricID").unwrap_or_default(),
+        user_id,
+        maximum_drawdown: ratio(row, "MaximumDrawdown"),
+        beta: ratio(row, "Beta"),
+        sharpe_ratio: ratio(row, "SharpeRatio"),
+        sortino_ratio: ratio(row, "SortinoRatio"),
+        calmar_ratio: ratio(row, "CalmarRatio"),
+        absolute_return: ratio(row, "AbsoluteReturn"),
+        volatility_score: ratio(row, "VolatilityScore"),
+        risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
+        captured_at: row.get::<chrono::NaiveDateTime, _>("CapturedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// Runs a `SELECT COUNT(*) as count ...` existence check
+async fn count(
+    client: &mut DbClient,
+    query: &str,
+    params: &[&dyn tiberius::ToSql],
+    context: &str,
+) -> StoreResult<i32> {
+    let stream = client.query(query, params).await
+        .map_err(|e| StoreError::Database(format!("Failed to check {}: {}", context, e)))?;
+
+    let result = stream.into_first_result().await
+        .map_err(|e| StoreError::Database(format!("Failed to fetch {} check results: {}", context, e)))?;
+
+    Ok(result.first()
+        .and_then(|row| row.get("count"))
+        .unwrap_or(0))
+}
+
+// Get the user ID from the portfolio - this is needed for the trading procedures
+async fn portfolio_owner(client: &mut DbClient, portfolio_id: i32) -> StoreResult<tiberius::Uuid> {
+    let user_query = "SELECT UserID FROM portfolio.Portfolios WHERE PortfolioID = @P1";
+    let user_stream = client.query(user_query, &[&portfolio_id]).await.map_err(sql_error("Failed to get portfolio owner"))?;
+
+    let user_rows = user_stream.into_first_result().await.map_err(sql_error("Failed to fetch portfolio owner"))?;
+
+    user_rows.into_iter().next()
+        .and_then(|row| row.get::<tiberius::Uuid, _>("UserID"))
+        .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))
+}
+
+// =============================================================
+// ERROR MAPPING
+// =============================================================
+
+/// Error number SQL Server assigns to `RAISERROR('message', 16, 1)`
+const RAISERROR_NUMBER: u32 = 50000;
+
+type ErrorVariant = fn(String) -> StoreError;
+
+/// Known `RAISERROR` messages from the procedures and the error each becomes;
+/// the first matching fragment wins and anything else raised is a bad request
+const PROCEDURE_ERRORS: &[(&str, ErrorVariant)] = &[
+    ("not found", StoreError::NotFound),
+    ("does not exist", StoreError::NotFound),
+    ("does not belong to user", StoreError::NotFound),
+    ("Insufficient funds", StoreError::InsufficientFunds),
+    ("Insufficient account balance", StoreError::InsufficientFunds),
+    ("Insufficient portfolio funds", StoreError::InsufficientFunds),
+    ("no longer pending", StoreError::Conflict),
+    ("already exists", StoreError::Conflict),
+    ("already Premium", StoreError::Conflict),
+    ("only available for premium users", StoreError::Forbidden),
+];
+
+/// Classifies a driver error: procedure messages and constraint violations
+/// become 4xx errors, everything else is a `Database` error whose raw text
+/// (prefixed with `context`) is only logged
+fn sql_error(context: &str) -> impl Fn(tiberius::error::Error) -> StoreError + '_ {
+    move |e| {
+        if let tiberius::error::Error::Server(token) = &e {
+            let message = token.message();
+            match token.code() {
+                RAISERROR_NUMBER => {
+                    let variant = PROCEDURE_ERRORS.iter()
+                        .find(|(fragment, _)| message.contains(fragment))
+                        .map_or(StoreError::BadRequest as ErrorVariant, |(_, variant)| *variant);
+                    return variant(message.to_string());
+                },
+                // Unique constraint or unique index violation
+                2627 | 2601 => return StoreError::Conflict("A record with the same unique value already exists".to_string()),
+                547 if message.contains("CHECK constraint") => {
+                    return StoreError::BadRequest("A value is outside the allowed range".to_string());
+                },
+                547 => return StoreError::Conflict("The operation conflicts with related records".to_string()),
+                _ => {},
+            }
+        }
+        StoreError::Database(format!("{}: {}", context, e))
+    }
+}
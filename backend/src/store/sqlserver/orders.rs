@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+
+use crate::models::{AmendOrderRequest, Order, OrderStatus, PlaceOrderRequest};
+use crate::store::{OrderStore, StoreError, StoreResult};
+use super::{portfolio_owner, sql_error, SqlServerStore};
+
+fn order_from_row(row: &Row) -> Order {
+    Order {
+        order_id: row.get("OrderID").unwrap_or_default(),
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+        side: row.get::<&str, _>("Side")
+            .and_then(|side| side.parse().ok())
+            .unwrap_or_default(),
+        order_type: row.get::<&str, _>("OrderType")
+            .and_then(|order_type| order_type.parse().ok())
+            .unwrap_or_default(),
+        time_in_force: row.get::<&str, _>("TimeInForce")
+            .and_then(|time_in_force| time_in_force.parse().ok())
+            .unwrap_or_default(),
+        quantity: row.get::<Decimal, _>("Quantity").unwrap_or_default(),
+        limit_price: row.get::<Decimal, _>("LimitPrice"),
+        stop_price: row.get::<Decimal, _>("StopPrice"),
+        triggered: row.get::<bool, _>("Triggered").unwrap_or_default(),
+        reserved_funds: row.get::<Decimal, _>("ReservedFunds").unwrap_or_default(),
+        status: row.get::<&str, _>("Status")
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_default(),
+        status_reason: row.get::<&str, _>("StatusReason").map(|reason| reason.to_string()),
+        fill_price: row.get::<Decimal, _>("FillPrice"),
+        executed_at: row.get::<chrono::NaiveDateTime, _>("ExecutedAt").map(|dt| dt.to_string()),
+        expires_at: row.get::<chrono::NaiveDateTime, _>("ExpiresAt").map(|dt| dt.to_string()),
+        created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// The order procedures all finish by selecting the order from vw_Orders
+fn order_result(row: Option<Row>) -> StoreResult<Order> {
+    row.map(|row| order_from_row(&row))
+        .ok_or_else(|| StoreError::NotFound("Order not found".to_string()))
+}
+
+#[async_trait]
+impl OrderStore for SqlServerStore {
+    async fn place_order(&self, portfolio_id: i32, order: &PlaceOrderRequest) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, portfolio_id).await?;
+
+        let query = "EXEC portfolio.sp_PlaceOrder @P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9";
+        let stream = client.query(
+            query,
+            &[
+                &user_id,
+                &portfolio_id,
+                &order.asset_id,
+                &order.side.as_str(),
+                &order.order_type.as_str(),
+                &order.time_in_force.unwrap_or_default().as_str(),
+                &order.quantity,
+                &order.limit_price,
+                &order.stop_price,
+            ],
+        ).await.map_err(sql_error("Failed to place order"))?;
+
+        order_result(stream.into_row().await.map_err(sql_error("Failed to fetch placed order"))?)
+    }
+
+    async fn get_order(&self, order_id: i64) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        let stream = client.query("SELECT * FROM portfolio.vw_Orders WHERE OrderID = @P1", &[&order_id]).await
+            .map_err(sql_error("Failed to get order"))?;
+
+        order_result(stream.into_row().await.map_err(sql_error("Failed to fetch order"))?)
+    }
+
+    async fn list_orders(&self, portfolio_id: i32, status: Option<OrderStatus>) -> StoreResult<Vec<Order>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.vw_Orders
+                     WHERE PortfolioID = @P1 AND (@P2 IS NULL OR Status = @P2)
+                     ORDER BY CreatedAt DESC, OrderID DESC";
+        let status = status.map(|status| status.as_str());
+        let stream = client.query(query, &[&portfolio_id, &status]).await
+            .map_err(sql_error("Failed to list orders"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch orders"))?;
+
+        Ok(rows.iter().map(order_from_row).collect())
+    }
+
+    async fn pending_orders(&self, asset_id: Option<i32>) -> StoreResult<Vec<Order>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.vw_Orders
+                     WHERE Status = 'Pending' AND (@P1 IS NULL OR AssetID = @P1)
+                     ORDER BY OrderID";
+        let stream = client.query(query, &[&asset_id]).await
+            .map_err(sql_error("Failed to list pending orders"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch pending orders"))?;
+
+        Ok(rows.iter().map(order_from_row).collect())
+    }
+
+    async fn expired_order_ids(&self) -> StoreResult<Vec<i64>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT OrderID FROM portfolio.vw_Orders
+                     WHERE Status = 'Pending' AND ExpiresAt <= SYSDATETIME()";
+        let stream = client.query(query, &[]).await
+            .map_err(sql_error("Failed to list expired orders"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch expired orders"))?;
+
+        Ok(rows.iter().filter_map(|row| row.get::<i64, _>("OrderID")).collect())
+    }
+
+    async fn trigger_order(&self, order_id: i64) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        client.execute(
+            "UPDATE portfolio.Orders SET Triggered = 1, UpdatedAt = SYSDATETIME() WHERE OrderID = @P1",
+            &[&order_id],
+        ).await.map_err(sql_error("Failed to trigger order"))?;
+
+        drop(client);
+        self.get_order(order_id).await
+    }
+
+    async fn fill_order(&self, order_id: i64, price: Decimal) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        let stream = client.query("EXEC portfolio.sp_FillOrder @P1, @P2", &[&order_id, &price]).await
+            .map_err(sql_error("Failed to fill order"))?;
+
+        order_result(stream.into_row().await.map_err(sql_error("Failed to fetch filled order"))?)
+    }
+
+    async fn amend_order(&self, order_id: i64, amendment: &AmendOrderRequest) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_AmendOrder @P1, @P2, @P3",
+            &[&order_id, &amendment.quantity, &amendment.limit_price],
+        ).await.map_err(sql_error("Failed to amend order"))?;
+
+        order_result(stream.into_row().await.map_err(sql_error("Failed to fetch amended order"))?)
+    }
+
+    async fn cancel_order(&self, order_id: i64, reason: &str) -> StoreResult<Order> {
+        let mut client = self.client().await?;
+
+        let stream = client.query("EXEC portfolio.sp_CancelOrder @P1, @P2", &[&order_id, &reason]).await
+            .map_err(sql_error("Failed to cancel order"))?;
+
+        order_result(stream.into_row().await.map_err(sql_error("Failed to fetch cancelled order"))?)
+    }
+}
@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::time::chrono::NaiveDate;
+
+use crate::models::{OrderSide, PortfolioSnapshot};
+use crate::store::{
+    CashMovement, HistoricalTrade, PerformanceStore, PortfolioHistory, PortfolioStore, StoreError, StoreResult,
+};
+use super::{sql_error, SqlServerStore};
+
+#[async_trait]
+impl PerformanceStore for SqlServerStore {
+    async fn portfolio_history(&self, portfolio_id: i32, from: Option<NaiveDate>, today: NaiveDate) -> StoreResult<PortfolioHistory> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_GetPortfolioHistory @P1, @P2, @P3",
+            &[&portfolio_id, &from, &today],
+        ).await.map_err(sql_error("Failed to read portfolio history"))?;
+
+        let mut results = stream.into_results().await.map_err(sql_error("Failed to fetch portfolio history"))?;
+        let close_rows = results.pop().unwrap_or_default();
+        let movement_rows = results.pop().unwrap_or_default();
+        let trade_rows = results.pop().unwrap_or_default();
+        let holding_rows = results.pop().unwrap_or_default();
+        let portfolio_rows = results.pop().unwrap_or_default();
+        let portfolio = portfolio_rows.first()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        let mut closes: Vec<(i32, Vec<(NaiveDate, Decimal)>)> = Vec::new();
+        for row in &close_rows {
+            let asset_id = row.get::<i32, _>("AssetID").unwrap_or_default();
+            let close = (
+                row.get::<NaiveDate, _>("PriceDate").unwrap_or_default(),
+                row.get::<Decimal, _>("Price").unwrap_or_default(),
+            );
+            match closes.last_mut() {
+                Some((last_id, series)) if *last_id == asset_id => series.push(close),
+                _ => closes.push((asset_id, vec![close])),
+            }
+        }
+
+        Ok(PortfolioHistory {
+            created_on: portfolio.get::<NaiveDate, _>("CreatedOn").unwrap_or(today),
+            cash: portfolio.get::<Decimal, _>("Cash").unwrap_or_default(),
+            holdings: holding_rows.iter()
+                .map(|row| (
+                    row.get::<i32, _>("AssetID").unwrap_or_default(),
+                    row.get::<Decimal, _>("QuantityHeld").unwrap_or_default(),
+                ))
+                .collect(),
+            trades: trade_rows.iter()
+                .map(|row| HistoricalTrade {
+                    asset_id: row.get("AssetID").unwrap_or_default(),
+                    side: row.get::<&str, _>("TransactionType")
+                        .and_then(|side| side.parse().ok())
+                        .unwrap_or(OrderSide::Buy),
+                    quantity: row.get::<Decimal, _>("Quantity").unwrap_or_default(),
+                    date: row.get::<NaiveDate, _>("TradeDate").unwrap_or(today),
+                })
+                .collect(),
+            cash_movements: movement_rows.iter()
+                .map(|row| CashMovement {
+                    date: row.get::<NaiveDate, _>("MovementDate").unwrap_or(today),
+                    amount: row.get::<Decimal, _>("Amount").unwrap_or_default(),
+                    external: row.get::<bool, _>("IsExternal").unwrap_or_default(),
+                })
+                .collect(),
+            closes,
+        })
+    }
+
+    async fn last_snapshot_date(&self, portfolio_id: i32) -> StoreResult<Option<NaiveDate>> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT (SELECT MAX(SnapshotDate) FROM portfolio.PortfolioSnapshots WHERE PortfolioID = @P1) AS LastSnapshotDate
+             FROM portfolio.Portfolios
+             WHERE PortfolioID = @P1",
+            &[&portfolio_id],
+        ).await.map_err(sql_error("Failed to get last portfolio snapshot"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch last portfolio snapshot"))?
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+        Ok(row.get::<NaiveDate, _>("LastSnapshotDate"))
+    }
+
+    async fn save_portfolio_snapshots(&self, portfolio_id: i32, snapshots: &[PortfolioSnapshot]) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let snapshots_param = serde_json::to_string(snapshots)
+            .map_err(|e| StoreError::Internal(format!("Failed to encode portfolio snapshots: {}", e)))?;
+        client.execute(
+            "EXEC portfolio.sp_SavePortfolioSnapshots @P1, @P2",
+            &[&portfolio_id, &snapshots_param],
+        ).await.map_err(sql_error("Failed to save portfolio snapshots"))?;
+
+        Ok(())
+    }
+
+    async fn portfolio_snapshots(&self, portfolio_id: i32, from: Option<NaiveDate>, to: Option<NaiveDate>) -> StoreResult<Vec<PortfolioSnapshot>> {
+        self.get_portfolio(portfolio_id).await?;
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT SnapshotDate, CashValue, HoldingsValue, NetFlows
+             FROM portfolio.PortfolioSnapshots
+             WHERE PortfolioID = @P1
+               AND (@P2 IS NULL OR SnapshotDate >= @P2)
+               AND (@P3 IS NULL OR SnapshotDate <= @P3)
+             ORDER BY SnapshotDate",
+            &[&portfolio_id, &from, &to],
+        ).await.map_err(sql_error("Failed to list portfolio snapshots"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch portfolio snapshots"))?;
+        Ok(rows.iter()
+            .map(|row| PortfolioSnapshot {
+                snapshot_date: row.get::<NaiveDate, _>("SnapshotDate").unwrap_or_default(),
+                cash_value: row.get::<Decimal, _>("CashValue").unwrap_or_default(),
+                holdings_value: row.get::<Decimal, _>("HoldingsValue").unwrap_or_default(),
+                net_flows: row.get::<Decimal, _>("NetFlows").unwrap_or_default(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,667 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+use uuid::Uuid;
+
+use crate::models::{
+    AssetHolding, BatchLeg, BuyAssetRequest, BuyAssetResponse, CreatePortfolioRequest, LotSelection,
+    OrderSide, Portfolio,
+    PortfolioBalance, PortfolioHolding, PortfolioHoldingsSummary, PortfolioSummary,
+    SellAssetRequest, SellAssetResponse, TradeProjection, TradingTransaction, TransactionFilter,
+    UpdatePortfolioRequest,
+};
+use crate::db::DbClient;
+use crate::store::{BatchOutcome, PortfolioStore, StoreError, StoreResult, TradePreview, TradingStore};
+use super::{count, sql_error, portfolio_from_row, portfolio_owner, sql_uuid, SqlServerStore};
+
+fn transaction_from_row(row: &Row) -> TradingTransaction {
+    TradingTransaction {
+        transaction_id: row.get("TransactionID").unwrap_or_default(),
+        user_id: row.get::<tiberius::Uuid, _>("UserID")
+            .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
+            .unwrap_or_default(),
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+        asset_name: row.get::<&str, _>("AssetName").unwrap_or_default().to_string(),
+        transaction_type: row.get::<&str, _>("TransactionType").unwrap_or_default().to_string(),
+        quantity: row.get::<Decimal, _>("Quantity").unwrap_or_default(),
+        unit_price: row.get::<Decimal, _>("UnitPrice").unwrap_or_default(),
+        total_value: row.get::<Decimal, _>("TotalValue").unwrap_or_default(),
+        status: row.get::<&str, _>("Status").unwrap_or_default().to_string(),
+        transaction_date: row.get::<chrono::NaiveDateTime, _>("TransactionDate")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// Maps a holdings row; the view and the procedure name the symbol column differently
+fn holding_from_row(row: &Row, symbol_column: &str) -> PortfolioHolding {
+    PortfolioHolding {
+        holding_id: row.get("HoldingID").unwrap_or(0),
+        portfolio_id: row.get("PortfolioID").unwrap_or(0),
+        asset_id: row.get("AssetID").unwrap_or(0),
+        asset_name: row.get::<&str, _>("AssetName").unwrap_or("").to_string(),
+        symbol: row.get::<&str, _>(symbol_column).unwrap_or("").to_string(),
+        asset_type: row.get::<&str, _>("AssetType").unwrap_or("").to_string(),
+        quantity_held: row.get::<Decimal, _>("QuantityHeld")
+            .unwrap_or_default(),
+        average_price: row.get::<Decimal, _>("AveragePrice")
+            .unwrap_or_default(),
+        total_cost: row.get::<Decimal, _>("TotalCost")
+            .unwrap_or_default(),
+        current_price: row.get::<Decimal, _>("CurrentPrice")
+            .unwrap_or_default(),
+        current_value: row.get::<Decimal, _>("CurrentValue")
+            .unwrap_or_default(),
+        unrealized_gain_loss: row.get::<Decimal, _>("UnrealizedGainLoss")
+            .unwrap_or_default(),
+        last_updated: row.get::<chrono::NaiveDateTime, _>("LastUpdated")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn summarize_holdings(portfolio_id: i32, portfolio_name: String, holdings: Vec<PortfolioHolding>) -> PortfolioHoldingsSummary {
+    let total_holdings_value: Decimal = holdings.iter().map(|h| h.current_value).sum();
+    let total_cost_basis: Decimal = holdings.iter().map(|h| h.total_cost).sum();
+    let assets_count = holdings.len() as i32;
+
+    PortfolioHoldingsSummary {
+        portfolio_id,
+        portfolio_name,
+        holdings,
+        total_holdings_value,
+        total_cost_basis,
+        total_unrealized_gain_loss: total_holdings_value - total_cost_basis,
+        assets_count,
+    }
+}
+
+async fn portfolio_exists(client: &mut DbClient, portfolio_id: i32) -> StoreResult<()> {
+    let existing = count(
+        client,
+        "SELECT COUNT(*) as count FROM portfolio.Portfolios WHERE PortfolioID = @P1",
+        &[&portfolio_id],
+        "portfolio existence",
+    ).await?;
+
+    if existing == 0 {
+        return Err(StoreError::NotFound("Portfolio not found".to_string()));
+    }
+    Ok(())
+}
+
+async fn portfolio_name(client: &mut DbClient, portfolio_id: i32) -> StoreResult<String> {
+    let portfolio_query = "SELECT Name FROM portfolio.Portfolios WHERE PortfolioID = @P1";
+    let portfolio_stream = client.query(portfolio_query, &[&portfolio_id]).await.map_err(sql_error("Failed to get portfolio"))?;
+
+    let portfolio_rows = portfolio_stream.into_first_result().await.map_err(sql_error("Failed to fetch portfolio"))?;
+
+    portfolio_rows.into_iter().next()
+        .and_then(|row| row.get::<&str, _>("Name").map(|s| s.to_string()))
+        .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))
+}
+
+fn buy_response_from_row(row: &Row, quantity: Decimal) -> BuyAssetResponse {
+    BuyAssetResponse {
+        status: "Success".to_string(),
+        transaction_id: row.get("TransactionID").unwrap_or(0),
+        quantity_purchased: row.get::<Decimal, _>("QuantityPurchased")
+            .unwrap_or(quantity),
+        price_per_share: row.get::<Decimal, _>("PricePerShare")
+            .unwrap_or_default(),
+        total_cost: row.get::<Decimal, _>("TotalCost")
+            .unwrap_or_default(),
+        fee: row.get::<Decimal, _>("Fee")
+            .unwrap_or_default(),
+        remaining_funds: row.get::<Decimal, _>("RemainingFunds")
+            .unwrap_or_default(),
+    }
+}
+
+fn sell_response_from_row(row: &Row, quantity: Decimal) -> SellAssetResponse {
+    SellAssetResponse {
+        status: "Success".to_string(),
+        transaction_id: row.get("TransactionID").unwrap_or(0),
+        quantity_sold: row.get::<Decimal, _>("QuantitySold")
+            .unwrap_or(quantity),
+        price_per_share: row.get::<Decimal, _>("PricePerShare")
+            .unwrap_or_default(),
+        total_proceeds: row.get::<Decimal, _>("TotalProceeds")
+            .unwrap_or_default(),
+        fee: row.get::<Decimal, _>("Fee")
+            .unwrap_or_default(),
+        new_funds_balance: row.get::<Decimal, _>("NewFunds")
+            .unwrap_or_default(),
+        cost_basis: row.get::<Decimal, _>("CostBasis")
+            .unwrap_or_default(),
+        realized_gain: row.get::<Decimal, _>("RealizedGain")
+            .unwrap_or_default(),
+    }
+}
+
+/// The preview procedures return the trade's result row, then the position it leaves
+fn preview_from_results(
+    results: Vec<Vec<Row>>,
+    projection: impl FnOnce(&Row) -> TradeProjection,
+) -> StoreResult<TradePreview> {
+    let mut results = results.into_iter();
+    let (Some(trade), Some(position)) = (
+        results.next().and_then(|rows| rows.into_iter().next()),
+        results.next().and_then(|rows| rows.into_iter().next()),
+    ) else {
+        return Err(StoreError::Internal("No result returned from trade preview".to_string()));
+    };
+
+    Ok(TradePreview {
+        projection: projection(&trade),
+        quantity_held_after: position.get::<Decimal, _>("QuantityHeldAfter").unwrap_or_default(),
+        average_price_after: position.get::<Decimal, _>("AveragePriceAfter").unwrap_or_default(),
+        portfolio_value_after: position.get::<Decimal, _>("PortfolioValueAfter").unwrap_or_default(),
+    })
+}
+
+/// `sp_SellAsset` takes the lot selection as JSON
+fn lots_json(lots: Option<&[LotSelection]>) -> StoreResult<Option<String>> {
+    lots.map(|lots| serde_json::to_string(lots)
+        .map_err(|e| StoreError::Internal(format!("Failed to encode lots: {}", e))))
+        .transpose()
+}
+
+async fn exec_buy(client: &mut DbClient, user_id: &tiberius::Uuid, request: &BuyAssetRequest) -> StoreResult<BuyAssetResponse> {
+    // Use the comprehensive stored procedure that handles PortfolioHoldings automatically
+    let query = "EXEC portfolio.sp_BuyAsset @P1, @P2, @P3, @P4, @P5";
+    let unit_price_param: Option<Decimal> = request.unit_price;
+    let stream = client.query(
+        query,
+        &[
+            user_id,
+            &request.portfolio_id,
+            &request.asset_id,
+            &request.quantity,
+            &unit_price_param
+        ],
+    ).await.map_err(sql_error("Failed to buy asset"))?;
+
+    let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch buy result"))?;
+
+    let row = rows.into_iter().next()
+        .ok_or_else(|| StoreError::Internal("No result returned from buy operation".to_string()))?;
+
+    Ok(buy_response_from_row(&row, request.quantity))
+}
+
+async fn exec_sell(client: &mut DbClient, user_id: &tiberius::Uuid, request: &SellAssetRequest) -> StoreResult<SellAssetResponse> {
+    // Use the comprehensive stored procedure that handles PortfolioHoldings automatically
+    let query = "EXEC portfolio.sp_SellAsset @P1, @P2, @P3, @P4, @P5, @P6";
+    let unit_price_param: Option<Decimal> = request.unit_price;
+    let lots_param = lots_json(request.lots.as_deref())?;
+    let stream = client.query(
+        query,
+        &[
+            user_id,
+            &request.portfolio_id,
+            &request.asset_id,
+            &request.quantity,
+            &unit_price_param,
+            &lots_param
+        ],
+    ).await.map_err(sql_error("Failed to sell asset"))?;
+
+    let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch sell result"))?;
+
+    let row = rows.into_iter().next()
+        .ok_or_else(|| StoreError::Internal("No result returned from sell operation".to_string()))?;
+
+    Ok(sell_response_from_row(&row, request.quantity))
+}
+
+#[async_trait]
+impl PortfolioStore for SqlServerStore {
+    async fn list_portfolios(&self, user_id: Option<Uuid>) -> StoreResult<Vec<Portfolio>> {
+        let mut client = self.client().await?;
+
+        // Store the UUID bytes in a variable that lives for the entire function
+        let uuid_bytes = user_id.map(sql_uuid);
+
+        let (query_str, params) = if let Some(ref uuid) = uuid_bytes {
+            (
+                "SELECT PortfolioID, UserID, Name, CreationDate, CurrentFunds, CurrentProfitPct, LastUpdated
+                 FROM portfolio.Portfolios
+                 WHERE UserID = @P1
+                 ORDER BY CreationDate DESC",
+                vec![uuid as &dyn tiberius::ToSql]
+            )
+        } else {
+            (
+                "SELECT PortfolioID, UserID, Name, CreationDate, CurrentFunds, CurrentProfitPct, LastUpdated
+                 FROM portfolio.Portfolios
+                 ORDER BY CreationDate DESC",
+                vec![]
+            )
+        };
+
+        let stream = client.query(query_str, &params[..]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        Ok(rows.iter().map(portfolio_from_row).collect())
+    }
+
+    async fn create_portfolio(&self, portfolio: &CreatePortfolioRequest) -> StoreResult<Portfolio> {
+        let mut client = self.client().await?;
+
+        // Use 0.0 as default if initial_funds is not provided
+        let initial_funds = portfolio.initial_funds.unwrap_or_default();
+
+        // Use stored procedure for creation
+        let query = "EXEC portfolio.sp_CreatePortfolio @P1, @P2, @P3";
+
+        let stream = client.query(
+            query,
+            &[
+                &sql_uuid(portfolio.user_id),
+                &portfolio.name,
+                &initial_funds,
+            ],
+        ).await.map_err(sql_error("Failed to create portfolio"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to get created portfolio"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::Internal("No result returned from create portfolio".to_string()))?;
+
+        Ok(Portfolio {
+            user_id: portfolio.user_id,
+            ..portfolio_from_row(&row)
+        })
+    }
+
+    async fn get_portfolio(&self, portfolio_id: i32) -> StoreResult<Portfolio> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.Portfolios WHERE PortfolioID = @P1";
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(portfolio_from_row(&row))
+    }
+
+    async fn update_portfolio(&self, portfolio_id: i32, update: &UpdatePortfolioRequest) -> StoreResult<Portfolio> {
+        let mut client = self.client().await?;
+
+        // Use stored procedure for update
+        let query = "EXEC portfolio.sp_UpdatePortfolio @P1, @P2";
+
+        // Prepare parameters - pass NULL for fields that shouldn't be updated
+        let name_param: Option<&str> = update.name.as_deref();
+
+        let stream = client.query(
+            query,
+            &[
+                &portfolio_id,
+                &name_param,
+            ],
+        ).await.map_err(sql_error("Failed to update portfolio"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch updated portfolio"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(portfolio_from_row(&row))
+    }
+
+    async fn delete_portfolio(&self, portfolio_id: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists
+        portfolio_exists(&mut client, portfolio_id).await?;
+
+        // Check for active holdings that would prevent deletion
+        let holdings_count = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.PortfolioHoldings WHERE PortfolioID = @P1",
+            &[&portfolio_id],
+            "portfolio holdings",
+        ).await?;
+
+        if holdings_count > 0 {
+            return Err(StoreError::Conflict("Cannot delete portfolio with active holdings. Please sell all assets first.".to_string()));
+        }
+
+        // Try to delete the portfolio - foreign key constraints will handle cleanup of related data
+        let delete_query = "DELETE FROM portfolio.Portfolios WHERE PortfolioID = @P1";
+        let delete_stream = client.query(delete_query, &[&portfolio_id]).await.map_err(|e| {
+            let error_msg = format!("{}", e);
+            if error_msg.contains("REFERENCE constraint") || error_msg.contains("FOREIGN KEY") {
+                StoreError::Conflict("Cannot delete portfolio due to related transactions. Contact support if needed.".to_string())
+            } else {
+                sql_error("Failed to delete portfolio")(e)
+            }
+        })?;
+
+        delete_stream.into_first_result().await.map_err(sql_error("Failed to confirm deletion"))?;
+
+        Ok(())
+    }
+
+    async fn portfolio_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioSummary> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists
+        portfolio_exists(&mut client, portfolio_id).await?;
+
+        // Use the enhanced portfolio summary view
+        let query = "SELECT * FROM portfolio.vw_PortfolioSummary WHERE PortfolioID = @P1";
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio summary not found".to_string()))?;
+
+        Ok(PortfolioSummary {
+            portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+            portfolio_name: row.get::<&str, _>("PortfolioName").unwrap_or_default().to_string(),
+            owner: row.get::<&str, _>("OwnerName").unwrap_or_default().to_string(),
+            current_funds: row.get::<Decimal, _>("CurrentFunds")
+                .unwrap_or_default(),
+            current_profit_pct: row.get::<Decimal, _>("UnrealizedGainLossPercent")
+                .unwrap_or_default(),
+            creation_date: row.get::<chrono::NaiveDateTime, _>("CreationDate")
+                .map(|dt| dt.to_string())
+                .unwrap_or_default(),
+            total_trades: row.get("TotalTrades").unwrap_or_default(),
+        })
+    }
+
+    async fn portfolio_holdings(&self, portfolio_id: i32) -> StoreResult<Vec<AssetHolding>> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists
+        portfolio_exists(&mut client, portfolio_id).await?;
+
+        // Use the correct view name for portfolio holdings
+        let query = "SELECT * FROM portfolio.vw_PortfolioHoldings WHERE PortfolioID = @P1";
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        Ok(rows.into_iter().map(|row| {
+            AssetHolding {
+                portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+                portfolio_name: row.get::<&str, _>("PortfolioName").unwrap_or_default().to_string(),
+                asset_id: row.get("AssetID").unwrap_or_default(),
+                asset_name: row.get::<&str, _>("AssetName").unwrap_or_default().to_string(),
+                symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+                asset_type: row.get::<&str, _>("AssetType").unwrap_or_default().to_string(),
+                quantity_held: row.get::<Decimal, _>("QuantityHeld")
+                    .unwrap_or_default(),
+                current_price: row.get::<Decimal, _>("CurrentPrice")
+                    .unwrap_or_default(),
+                market_value: row.get::<Decimal, _>("CurrentValue")
+                    .unwrap_or_default(),
+            }
+        }).collect())
+    }
+
+    async fn portfolio_balance(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance> {
+        let mut client = self.client().await?;
+
+        // Use the enhanced portfolio summary view for consistent data
+        let query = "SELECT *, portfolio.fn_PortfolioReservedFunds(PortfolioID) AS ReservedFunds,
+                            portfolio.fn_PortfolioFeesPaid(PortfolioID) AS FeesPaid
+                     FROM portfolio.vw_PortfolioSummary WHERE PortfolioID = @P1";
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to get portfolio balance"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch portfolio balance"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        let cash_balance = row.get::<Decimal, _>("CurrentFunds")
+            .unwrap_or_default();
+        let reserved_funds = row.get::<Decimal, _>("ReservedFunds")
+            .unwrap_or_default();
+        let holdings_value = row.get::<Decimal, _>("CurrentMarketValue")
+            .unwrap_or_default();
+
+        Ok(PortfolioBalance {
+            portfolio_id,
+            portfolio_name: row.get::<&str, _>("PortfolioName").unwrap_or("").to_string(),
+            cash_balance,
+            reserved_funds,
+            holdings_value,
+            total_portfolio_value: cash_balance + reserved_funds + holdings_value,
+            holdings_count: row.get::<i32, _>("TotalHoldings"),
+            fees_paid: row.get::<Decimal, _>("FeesPaid").unwrap_or_default(),
+        })
+    }
+
+    async fn portfolio_balance_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioBalance> {
+        let mut client = self.client().await?;
+
+        // Use the enhanced stored procedure for portfolio balance
+        let query = "EXEC portfolio.sp_GetPortfolioBalance @P1";
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to get portfolio balance"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch portfolio balance"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        let cash_balance = row.get::<Decimal, _>("CurrentFunds")
+            .unwrap_or_default();
+        let reserved_funds = row.get::<Decimal, _>("ReservedFunds")
+            .unwrap_or_default();
+        let holdings_value = row.get::<Decimal, _>("CurrentMarketValue")
+            .unwrap_or_default();
+
+        Ok(PortfolioBalance {
+            portfolio_id,
+            portfolio_name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
+            cash_balance,
+            reserved_funds,
+            holdings_value,
+            total_portfolio_value: cash_balance + reserved_funds + holdings_value,
+            holdings_count: None, // Not returned by this procedure
+            fees_paid: row.get::<Decimal, _>("FeesPaid").unwrap_or_default(),
+        })
+    }
+
+    async fn holdings_summary(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists and get its name
+        let portfolio_name = portfolio_name(&mut client, portfolio_id).await?;
+
+        // Use the enhanced portfolio holdings view for detailed information
+        let holdings_query = "SELECT * FROM portfolio.vw_PortfolioHoldings WHERE PortfolioID = @P1";
+
+        let holdings_stream = client.query(holdings_query, &[&portfolio_id]).await.map_err(sql_error("Failed to get holdings"))?;
+
+        let holdings_rows = holdings_stream.into_first_result().await.map_err(sql_error("Failed to fetch holdings"))?;
+
+        let holdings = holdings_rows.iter().map(|row| holding_from_row(row, "Symbol")).collect();
+
+        Ok(summarize_holdings(portfolio_id, portfolio_name, holdings))
+    }
+
+    async fn holdings_summary_sp(&self, portfolio_id: i32) -> StoreResult<PortfolioHoldingsSummary> {
+        let mut client = self.client().await?;
+
+        // First get portfolio name
+        let portfolio_name = portfolio_name(&mut client, portfolio_id).await?;
+
+        // Use the enhanced stored procedure for holdings summary
+        let holdings_query = "EXEC portfolio.sp_GetPortfolioHoldingsSummary @P1";
+
+        let holdings_stream = client.query(holdings_query, &[&portfolio_id]).await.map_err(sql_error("Failed to get holdings"))?;
+
+        let holdings_rows = holdings_stream.into_first_result().await.map_err(sql_error("Failed to fetch holdings"))?;
+
+        let holdings = holdings_rows.iter().map(|row| holding_from_row(row, "AssetSymbol")).collect();
+
+        Ok(summarize_holdings(portfolio_id, portfolio_name, holdings))
+    }
+}
+
+#[async_trait]
+impl TradingStore for SqlServerStore {
+    async fn buy_asset(&self, request: &BuyAssetRequest) -> StoreResult<BuyAssetResponse> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, request.portfolio_id).await?;
+
+        exec_buy(&mut client, &user_id, request).await
+    }
+
+    async fn sell_asset(&self, request: &SellAssetRequest) -> StoreResult<SellAssetResponse> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, request.portfolio_id).await?;
+
+        exec_sell(&mut client, &user_id, request).await
+    }
+
+    async fn preview_buy(&self, request: &BuyAssetRequest) -> StoreResult<TradePreview> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, request.portfolio_id).await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_PreviewBuyAsset @P1, @P2, @P3, @P4, @P5",
+            &[
+                &user_id,
+                &request.portfolio_id,
+                &request.asset_id,
+                &request.quantity,
+                &request.unit_price
+            ],
+        ).await.map_err(sql_error("Failed to preview buy"))?;
+
+        let results = stream.into_results().await.map_err(sql_error("Failed to fetch buy preview"))?;
+        preview_from_results(results, |row| TradeProjection::Buy(BuyAssetResponse {
+            status: "Preview".to_string(),
+            transaction_id: 0,
+            ..buy_response_from_row(row, request.quantity)
+        }))
+    }
+
+    async fn preview_sell(&self, request: &SellAssetRequest) -> StoreResult<TradePreview> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, request.portfolio_id).await?;
+
+        let lots_param = lots_json(request.lots.as_deref())?;
+        let stream = client.query(
+            "EXEC portfolio.sp_PreviewSellAsset @P1, @P2, @P3, @P4, @P5, @P6",
+            &[
+                &user_id,
+                &request.portfolio_id,
+                &request.asset_id,
+                &request.quantity,
+                &request.unit_price,
+                &lots_param
+            ],
+        ).await.map_err(sql_error("Failed to preview sell"))?;
+
+        let results = stream.into_results().await.map_err(sql_error("Failed to fetch sell preview"))?;
+        preview_from_results(results, |row| TradeProjection::Sell(SellAssetResponse {
+            status: "Preview".to_string(),
+            transaction_id: 0,
+            ..sell_response_from_row(row, request.quantity)
+        }))
+    }
+
+    async fn execute_batch(&self, portfolio_id: i32, legs: &[&BatchLeg]) -> StoreResult<BatchOutcome> {
+        let mut client = self.client().await?;
+
+        let user_id = portfolio_owner(&mut client, portfolio_id).await?;
+
+        // The trading procedures nest their own transactions inside this one;
+        // the first to fail rolls everything back
+        client.begin_transaction().await.map_err(sql_error("Failed to begin batch transaction"))?;
+
+        let mut results = Vec::with_capacity(legs.len());
+        for (index, leg) in legs.iter().enumerate() {
+            let result = match leg.side {
+                OrderSide::Buy => exec_buy(&mut client, &user_id, &BuyAssetRequest {
+                    portfolio_id,
+                    asset_id: leg.asset_id,
+                    quantity: leg.quantity,
+                    unit_price: leg.unit_price,
+                }).await.map(TradeProjection::Buy),
+                OrderSide::Sell => exec_sell(&mut client, &user_id, &SellAssetRequest {
+                    portfolio_id,
+                    asset_id: leg.asset_id,
+                    quantity: leg.quantity,
+                    unit_price: leg.unit_price,
+                    lots: leg.lots.clone(),
+                }).await.map(TradeProjection::Sell),
+            };
+            match result {
+                Ok(projection) => results.push(projection),
+                Err(error) => {
+                    client.rollback().await.map_err(sql_error("Failed to roll back batch"))?;
+                    return Ok(BatchOutcome::RolledBack(index, error));
+                },
+            }
+        }
+
+        if let Err(error) = client.commit().await {
+            client.rollback().await.map_err(sql_error("Failed to roll back batch"))?;
+            return Err(sql_error("Failed to commit batch")(error));
+        }
+        Ok(BatchOutcome::Committed(results))
+    }
+
+    async fn list_transactions(
+        &self,
+        portfolio_id: i32,
+        filter: &TransactionFilter,
+        before: Option<i64>,
+        limit: Option<usize>,
+    ) -> StoreResult<Vec<TradingTransaction>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT TOP (ISNULL(@P8, 2147483647)) * FROM portfolio.vw_TransactionHistory
+                     WHERE PortfolioID = @P1
+                       AND (@P2 IS NULL OR AssetID = @P2)
+                       AND (@P3 IS NULL OR TransactionType = @P3)
+                       AND (@P4 IS NULL OR Status = @P4)
+                       AND (@P5 IS NULL OR TransactionDate >= @P5)
+                       AND (@P6 IS NULL OR TransactionDate < DATEADD(DAY, 1, @P6))
+                       AND (@P7 IS NULL OR TransactionID < @P7)
+                     ORDER BY TransactionID DESC";
+        let limit = limit.map(|limit| i32::try_from(limit).unwrap_or(i32::MAX));
+        let stream = client.query(
+            query,
+            &[
+                &portfolio_id,
+                &filter.asset_id,
+                &filter.transaction_type.map(|side| side.as_str()),
+                &filter.status.map(|status| status.as_str()),
+                &filter.from,
+                &filter.to,
+                &before,
+                &limit,
+            ],
+        ).await.map_err(sql_error("Failed to list transactions"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch transactions"))?;
+
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+}
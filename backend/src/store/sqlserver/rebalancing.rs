@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::time::chrono;
+
+use crate::models::{AllocationTarget, AllocationTargets, SetAllocationTargetsRequest, DEFAULT_DRIFT_TOLERANCE_PCT};
+use crate::store::{RebalancePosition, RebalanceSnapshot, RebalanceStore, StoreError, StoreResult};
+use super::{sql_error, SqlServerStore};
+
+#[async_trait]
+impl RebalanceStore for SqlServerStore {
+    async fn allocation_targets(&self, portfolio_id: i32) -> StoreResult<AllocationTargets> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT rp.DriftTolerancePct, rp.MinTradeValue, rp.UpdatedAt
+             FROM portfolio.Portfolios p
+             LEFT JOIN portfolio.RebalancePolicies rp ON rp.PortfolioID = p.PortfolioID
+             WHERE p.PortfolioID = @P1;
+             SELECT t.AssetID, a.Symbol, t.AssetType, t.Weight
+             FROM portfolio.AllocationTargets t
+             LEFT JOIN portfolio.Assets a ON a.AssetID = t.AssetID
+             WHERE t.PortfolioID = @P1
+             ORDER BY t.TargetID",
+            &[&portfolio_id],
+        ).await.map_err(sql_error("Failed to get allocation targets"))?;
+
+        let mut results = stream.into_results().await.map_err(sql_error("Failed to fetch allocation targets"))?;
+        let target_rows = results.pop().unwrap_or_default();
+        let policy_rows = results.pop().unwrap_or_default();
+        let policy = policy_rows.first()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(AllocationTargets {
+            portfolio_id,
+            drift_tolerance_pct: policy.get::<Decimal, _>("DriftTolerancePct").unwrap_or(DEFAULT_DRIFT_TOLERANCE_PCT),
+            min_trade_value: policy.get::<Decimal, _>("MinTradeValue").unwrap_or_default(),
+            targets: target_rows.iter()
+                .map(|row| AllocationTarget {
+                    asset_id: row.get::<i32, _>("AssetID"),
+                    symbol: row.get::<&str, _>("Symbol").map(str::to_string),
+                    asset_type: row.get::<&str, _>("AssetType").map(str::to_string),
+                    weight: row.get::<Decimal, _>("Weight").unwrap_or_default(),
+                })
+                .collect(),
+            updated_at: policy.get::<chrono::NaiveDateTime, _>("UpdatedAt").map(|dt| dt.to_string()),
+        })
+    }
+
+    async fn set_allocation_targets(&self, portfolio_id: i32, targets: &SetAllocationTargetsRequest) -> StoreResult<AllocationTargets> {
+        {
+            let mut client = self.client().await?;
+
+            let targets_param = serde_json::to_string(&targets.targets)
+                .map_err(|e| StoreError::Internal(format!("Failed to encode allocation targets: {}", e)))?;
+            client.execute(
+                "EXEC portfolio.sp_SetAllocationTargets @P1, @P2, @P3, @P4",
+                &[
+                    &portfolio_id,
+                    &targets_param,
+                    &targets.drift_tolerance_pct.unwrap_or(DEFAULT_DRIFT_TOLERANCE_PCT),
+                    &targets.min_trade_value.unwrap_or_default(),
+                ],
+            ).await.map_err(sql_error("Failed to set allocation targets"))?;
+        }
+
+        self.allocation_targets(portfolio_id).await
+    }
+
+    async fn rebalance_snapshot(&self, portfolio_id: i32) -> StoreResult<RebalanceSnapshot> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_GetRebalanceSnapshot @P1",
+            &[&portfolio_id],
+        ).await.map_err(sql_error("Failed to read rebalance snapshot"))?;
+
+        let mut results = stream.into_results().await.map_err(sql_error("Failed to fetch rebalance snapshot"))?;
+        let position_rows = results.pop().unwrap_or_default();
+        let cash = results.pop().unwrap_or_default()
+            .first()
+            .and_then(|row| row.get::<Decimal, _>("CurrentFunds"))
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(RebalanceSnapshot {
+            cash,
+            positions: position_rows.iter()
+                .map(|row| RebalancePosition {
+                    asset_id: row.get("AssetID").unwrap_or_default(),
+                    symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+                    asset_type: row.get::<&str, _>("AssetType").unwrap_or_default().to_string(),
+                    quantity_held: row.get::<Decimal, _>("QuantityHeld").unwrap_or_default(),
+                    price: row.get::<Decimal, _>("Price").unwrap_or_default(),
+                    quantity_increment: row.get::<Decimal, _>("QuantityIncrement"),
+                })
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono::{self, NaiveDate};
+
+use crate::models::{RecurringInvestment, RecurringInvestmentRun, RecurringRunStatus, RecurrenceFrequency};
+use crate::store::{
+    PortfolioStore, RecurringInvestmentStore, RecurringRunOutcome, RecurringSchedule, StoreError, StoreResult,
+};
+use super::{sql_error, SqlServerStore};
+
+const SELECT_SCHEDULES: &str =
+    "SELECT s.*, a.Symbol
+     FROM portfolio.RecurringInvestments s
+     JOIN portfolio.Assets a ON a.AssetID = s.AssetID";
+
+fn recurring_investment_from_row(row: &Row) -> RecurringInvestment {
+    RecurringInvestment {
+        schedule_id: row.get("ScheduleID").unwrap_or_default(),
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+        amount: row.get::<Decimal, _>("Amount").unwrap_or_default(),
+        frequency: row.get::<&str, _>("Frequency")
+            .and_then(|frequency| frequency.parse().ok())
+            .unwrap_or(RecurrenceFrequency::Daily),
+        day_of_week: row.get::<u8, _>("DayOfWeek").map(i32::from),
+        day_of_month: row.get::<u8, _>("DayOfMonth").map(i32::from),
+        active: row.get::<NaiveDate, _>("NextRunDate").is_some(),
+        next_run_date: row.get::<NaiveDate, _>("NextRunDate"),
+        last_run_date: row.get::<NaiveDate, _>("LastRunDate"),
+        created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn run_from_row(row: &Row) -> RecurringInvestmentRun {
+    RecurringInvestmentRun {
+        run_id: row.get("RunID").unwrap_or_default(),
+        schedule_id: row.get("ScheduleID").unwrap_or_default(),
+        run_date: row.get::<NaiveDate, _>("RunDate").unwrap_or_default(),
+        status: row.get::<&str, _>("Status")
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(RecurringRunStatus::Running),
+        transaction_id: row.get::<i64, _>("TransactionID"),
+        fund_transaction_id: row.get::<i64, _>("FundTransactionID"),
+        quantity: row.get::<Decimal, _>("Quantity"),
+        unit_price: row.get::<Decimal, _>("UnitPrice"),
+        total_cost: row.get::<Decimal, _>("TotalCost"),
+        reason: row.get::<&str, _>("Reason").map(str::to_string),
+        executed_at: row.get::<chrono::NaiveDateTime, _>("ExecutedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+impl SqlServerStore {
+    async fn recurring_investments_where(&self, condition: &str, param: &(dyn tiberius::ToSql + Sync)) -> StoreResult<Vec<RecurringInvestment>> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            format!("{} WHERE {} ORDER BY s.ScheduleID", SELECT_SCHEDULES, condition),
+            &[param],
+        ).await.map_err(sql_error("Failed to list recurring investments"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch recurring investments"))?;
+        Ok(rows.iter().map(recurring_investment_from_row).collect())
+    }
+}
+
+#[async_trait]
+impl RecurringInvestmentStore for SqlServerStore {
+    async fn list_recurring_investments(&self, portfolio_id: i32) -> StoreResult<Vec<RecurringInvestment>> {
+        self.get_portfolio(portfolio_id).await?;
+        self.recurring_investments_where("s.PortfolioID = @P1", &portfolio_id).await
+    }
+
+    async fn get_recurring_investment(&self, schedule_id: i32) -> StoreResult<RecurringInvestment> {
+        self.recurring_investments_where("s.ScheduleID = @P1", &schedule_id).await?
+            .pop()
+            .ok_or_else(|| StoreError::NotFound("Recurring investment not found".to_string()))
+    }
+
+    async fn create_recurring_investment(&self, portfolio_id: i32, asset_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment> {
+        let schedule_id = {
+            let mut client = self.client().await?;
+
+            let stream = client.query(
+                "INSERT INTO portfolio.RecurringInvestments
+                     (PortfolioID, AssetID, Amount, Frequency, DayOfWeek, DayOfMonth, NextRunDate)
+                 OUTPUT inserted.ScheduleID
+                 SELECT p.PortfolioID, a.AssetID, @P3, @P4, @P5, @P6, @P7
+                 FROM portfolio.Portfolios p
+                 JOIN portfolio.Assets a ON a.AssetID = @P2
+                 WHERE p.PortfolioID = @P1",
+                &[
+                    &portfolio_id,
+                    &asset_id,
+                    &schedule.amount,
+                    &schedule.frequency.as_str(),
+                    &schedule.day_of_week.map(|day| day as u8),
+                    &schedule.day_of_month.map(|day| day as u8),
+                    &schedule.next_run_date,
+                ],
+            ).await.map_err(sql_error("Failed to create recurring investment"))?;
+
+            let row = stream.into_row().await.map_err(sql_error("Failed to fetch recurring investment"))?
+                .ok_or_else(|| StoreError::NotFound("Portfolio or asset not found".to_string()))?;
+            row.get::<i32, _>("ScheduleID").unwrap_or_default()
+        };
+
+        self.get_recurring_investment(schedule_id).await
+    }
+
+    async fn update_recurring_investment(&self, schedule_id: i32, schedule: &RecurringSchedule) -> StoreResult<RecurringInvestment> {
+        {
+            let mut client = self.client().await?;
+
+            let result = client.execute(
+                "UPDATE portfolio.RecurringInvestments
+                 SET Amount = @P2, Frequency = @P3, DayOfWeek = @P4, DayOfMonth = @P5,
+                     NextRunDate = @P6, UpdatedAt = SYSDATETIME()
+                 WHERE ScheduleID = @P1",
+                &[
+                    &schedule_id,
+                    &schedule.amount,
+                    &schedule.frequency.as_str(),
+                    &schedule.day_of_week.map(|day| day as u8),
+                    &schedule.day_of_month.map(|day| day as u8),
+                    &schedule.next_run_date,
+                ],
+            ).await.map_err(sql_error("Failed to update recurring investment"))?;
+
+            if result.total() == 0 {
+                return Err(StoreError::NotFound("Recurring investment not found".to_string()));
+            }
+        }
+
+        self.get_recurring_investment(schedule_id).await
+    }
+
+    async fn delete_recurring_investment(&self, schedule_id: i32) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let result = client.execute(
+            "DELETE FROM portfolio.RecurringInvestments WHERE ScheduleID = @P1",
+            &[&schedule_id],
+        ).await.map_err(sql_error("Failed to delete recurring investment"))?;
+
+        if result.total() == 0 {
+            return Err(StoreError::NotFound("Recurring investment not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn due_recurring_investments(&self, today: NaiveDate) -> StoreResult<Vec<RecurringInvestment>> {
+        self.recurring_investments_where("s.NextRunDate <= @P1", &today).await
+    }
+
+    async fn claim_recurring_run(&self, schedule_id: i32, run_date: NaiveDate, next_run_date: NaiveDate) -> StoreResult<Option<i64>> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_ClaimRecurringRun @P1, @P2, @P3",
+            &[&schedule_id, &run_date, &next_run_date],
+        ).await.map_err(sql_error("Failed to claim recurring investment run"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch recurring investment run"))?;
+        Ok(row.and_then(|row| row.get::<i64, _>("RunID")))
+    }
+
+    async fn complete_recurring_run(&self, run_id: i64, outcome: &RecurringRunOutcome) -> StoreResult<RecurringInvestmentRun> {
+        let mut client = self.client().await?;
+
+        let (status, buy, reason) = match outcome {
+            RecurringRunOutcome::Executed(buy) => (RecurringRunStatus::Executed, Some(buy), None),
+            RecurringRunOutcome::Skipped(reason) => (RecurringRunStatus::Skipped, None, Some(reason.as_str())),
+        };
+        let stream = client.query(
+            "EXEC portfolio.sp_CompleteRecurringRun @P1, @P2, @P3, @P4, @P5, @P6, @P7",
+            &[
+                &run_id,
+                &status.as_str(),
+                &buy.map(|b| b.transaction_id),
+                &buy.map(|b| b.quantity_purchased),
+                &buy.map(|b| b.price_per_share),
+                &buy.map(|b| b.total_cost),
+                &reason,
+            ],
+        ).await.map_err(sql_error("Failed to complete recurring investment run"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch recurring investment run"))?
+            .ok_or_else(|| StoreError::Internal("No result returned from recurring investment run".to_string()))?;
+        Ok(run_from_row(&row))
+    }
+
+    async fn list_recurring_runs(&self, schedule_id: i32) -> StoreResult<Vec<RecurringInvestmentRun>> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT ScheduleID FROM portfolio.RecurringInvestments WHERE ScheduleID = @P1;
+             SELECT * FROM portfolio.RecurringInvestmentRuns WHERE ScheduleID = @P1 ORDER BY RunDate DESC, RunID DESC",
+            &[&schedule_id],
+        ).await.map_err(sql_error("Failed to list recurring investment runs"))?;
+
+        let mut results = stream.into_results().await.map_err(sql_error("Failed to fetch recurring investment runs"))?;
+        let run_rows = results.pop().unwrap_or_default();
+        if results.pop().unwrap_or_default().is_empty() {
+            return Err(StoreError::NotFound("Recurring investment not found".to_string()));
+        }
+        Ok(run_rows.iter().map(run_from_row).collect())
+    }
+}
@@ -0,0 +1,277 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+use tiberius::time::chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::models::{PortfolioRiskAnalysis, RiskAnalysis, RiskMetrics, RiskSummary};
+use crate::store::{NewRiskMetrics, RiskStore, StoreError, StoreResult};
+use super::{count, sql_error, ratio, risk_metrics_from_row, sql_uuid, SqlServerStore};
+
+fn risk_summary_from_row(row: &Row) -> RiskSummary {
+    RiskSummary {
+        total_users: row.get("TotalUsers").unwrap_or_default(),
+        total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
+        total_assets_under_management: row.get::<Decimal, _>("TotalAssetsUnderManagement")
+            .unwrap_or_default(),
+        average_system_risk: row.get::<f64, _>("AverageSystemRisk")
+            .unwrap_or_default(),
+        calculated_at: chrono::Utc::now().naive_utc().to_string(),
+    }
+}
+
+#[async_trait]
+impl RiskStore for SqlServerStore {
+    async fn user_risk_analysis(&self, user_id: Uuid) -> StoreResult<RiskAnalysis> {
+        let mut client = self.client().await?;
+
+        // Check if user exists
+        let tiberius_uuid = sql_uuid(user_id);
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1",
+            &[&tiberius_uuid],
+            "user existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("User not found".to_string()));
+        }
+
+        let query = "SELECT * FROM portfolio.vw_RiskAnalysis WHERE UserID = @P1";
+        let stream = client.query(query, &[&tiberius_uuid]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Risk analysis not found".to_string()))?;
+
+        Ok(RiskAnalysis {
+            user_id,
+            user_name: row.get::<&str, _>("UserName").unwrap_or_default().to_string(),
+            user_type: row.get::<&str, _>("UserType").unwrap_or_default().to_string(),
+            total_portfolios: row.get("TotalPortfolios").unwrap_or_default(),
+            total_investment: row.get::<Decimal, _>("TotalInvestment")
+                .unwrap_or_default(),
+            maximum_drawdown: ratio(&row, "MaximumDrawdown"),
+            sharpe_ratio: ratio(&row, "SharpeRatio"),
+            risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
+            last_updated: row.get::<chrono::NaiveDateTime, _>("RiskMetricsLastUpdated")
+                .map(|dt| dt.to_string())
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn portfolio_risk_analysis(&self, portfolio_id: i32) -> StoreResult<PortfolioRiskAnalysis> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists and get basic info
+        let portfolio_query = "SELECT p.*, rm.* FROM portfolio.Portfolios p
+            LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = p.UserID
+            WHERE p.PortfolioID = @P1";
+        let stream = client.query(portfolio_query, &[&portfolio_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(PortfolioRiskAnalysis {
+            portfolio_id,
+            portfolio_name: row.get::<&str, _>("Name").unwrap_or_default().to_string(),
+            current_funds: row.get::<Decimal, _>("CurrentFunds")
+                .unwrap_or_default(),
+            current_profit_pct: row.get::<Decimal, _>("CurrentProfitPct")
+                .unwrap_or_default(),
+            maximum_drawdown: ratio(&row, "MaximumDrawdown"),
+            beta: ratio(&row, "Beta"),
+            sharpe_ratio: ratio(&row, "SharpeRatio"),
+            risk_level: row.get::<&str, _>("RiskLevel").unwrap_or_default().to_string(),
+        })
+    }
+
+    async fn risk_summary(&self) -> StoreResult<RiskSummary> {
+        let mut client = self.client().await?;
+
+        let query = "
+            SELECT
+                COUNT(DISTINCT u.UserID) as TotalUsers,
+                COUNT(DISTINCT p.PortfolioID) as TotalPortfolios,
+                SUM(p.CurrentFunds) as TotalAssetsUnderManagement,
+                AVG(CAST(rm.VolatilityScore as float)) as AverageSystemRisk
+            FROM portfolio.Users u
+            LEFT JOIN portfolio.Portfolios p ON p.UserID = u.UserID
+            LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = u.UserID";
+
+        let stream = client.query(query, &[]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::Internal("Failed to get risk summary".to_string()))?;
+
+        Ok(risk_summary_from_row(&row))
+    }
+
+    async fn portfolio_risk_summary(&self, portfolio_id: i32) -> StoreResult<RiskSummary> {
+        let mut client = self.client().await?;
+
+        // First check if portfolio exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Portfolios WHERE PortfolioID = @P1",
+            &[&portfolio_id],
+            "portfolio existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("Portfolio not found".to_string()));
+        }
+
+        let query = "
+            SELECT
+                1 as TotalUsers,
+                1 as TotalPortfolios,
+                p.CurrentFunds as TotalAssetsUnderManagement,
+                COALESCE(CAST(rm.VolatilityScore as float), 0) as AverageSystemRisk
+            FROM portfolio.Portfolios p
+            LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = p.UserID
+            WHERE p.PortfolioID = @P1";
+
+        let stream = client.query(query, &[&portfolio_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::Internal("Failed to get risk summary".to_string()))?;
+
+        Ok(risk_summary_from_row(&row))
+    }
+
+    async fn user_risk_summary(&self, user_id: Uuid) -> StoreResult<RiskSummary> {
+        let mut client = self.client().await?;
+
+        // Check if user exists
+        let tiberius_uuid = sql_uuid(user_id);
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1",
+            &[&tiberius_uuid],
+            "user existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("User not found".to_string()));
+        }
+
+        let query = "
+            SELECT
+                1 as TotalUsers,
+                COUNT(DISTINCT p.PortfolioID) as TotalPortfolios,
+                SUM(p.CurrentFunds) as TotalAssetsUnderManagement,
+                COALESCE(CAST(rm.VolatilityScore as float), 0) as AverageSystemRisk
+            FROM portfolio.Users u
+            LEFT JOIN portfolio.Portfolios p ON p.UserID = u.UserID
+            LEFT JOIN portfolio.RiskMetrics rm ON rm.UserID = u.UserID
+            WHERE u.UserID = @P1
+            GROUP BY rm.VolatilityScore";
+
+        let stream = client.query(query, &[&tiberius_uuid]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::Internal("Failed to get risk summary".to_string()))?;
+
+        Ok(risk_summary_from_row(&row))
+    }
+
+    async fn latest_risk_metrics(&self, user_id: Uuid) -> StoreResult<RiskMetrics> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_GetUserLatestRiskMetrics @P1";
+
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let row = stream.into_first_result().await
+            .map_err(sql_error("Failed to fetch results"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StoreError::NotFound("No risk metrics found for user".to_string()))?;
+
+        Ok(risk_metrics_from_row(&row, user_id))
+    }
+
+    async fn record_risk_metrics(&self, user_id: Uuid, metrics: &NewRiskMetrics) -> StoreResult<RiskMetrics> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_RecordUserRiskMetrics @P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9",
+            &[
+                &sql_uuid(user_id),
+                &metrics.maximum_drawdown,
+                &metrics.beta,
+                &metrics.sharpe_ratio,
+                &metrics.sortino_ratio,
+                &metrics.calmar_ratio,
+                &metrics.absolute_return,
+                &metrics.volatility_score,
+                &metrics.risk_level.as_str(),
+            ],
+        ).await.map_err(sql_error("Failed to record risk metrics"))?;
+
+        let row = stream.into_row().await
+            .map_err(sql_error("Failed to fetch recorded risk metrics"))?
+            .ok_or_else(|| StoreError::Internal("Risk metrics were not recorded".to_string()))?;
+
+        Ok(risk_metrics_from_row(&row, user_id))
+    }
+
+    async fn risk_trend(&self, user_id: Uuid, days_back: i32) -> StoreResult<Vec<RiskMetrics>> {
+        let mut client = self.client().await?;
+
+        let sp_query = "EXEC portfolio.sp_GetUserRiskTrend @P1, @P2";
+
+        let stream = client.query(sp_query, &[&sql_uuid(user_id), &days_back]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let results = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        if results.is_empty() {
+            return Err(StoreError::NotFound("No risk trend data found for user".to_string()));
+        }
+
+        Ok(results.iter().map(|row| risk_metrics_from_row(row, user_id)).collect())
+    }
+
+    async fn asset_daily_closes(&self, asset_ids: &[i32], from: NaiveDate, to: NaiveDate) -> StoreResult<Vec<(i32, Vec<(NaiveDate, Decimal)>)>> {
+        let mut client = self.client().await?;
+
+        let asset_ids_param = serde_json::to_string(asset_ids)
+            .map_err(|e| StoreError::Internal(format!("Failed to encode asset IDs: {}", e)))?;
+        let stream = client.query(
+            "EXEC portfolio.sp_GetAssetDailyCloses @P1, @P2, @P3",
+            &[&asset_ids_param, &from, &to],
+        ).await.map_err(sql_error("Failed to read daily closes"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch daily closes"))?;
+        let mut closes: Vec<(i32, Vec<(NaiveDate, Decimal)>)> = Vec::new();
+        for row in &rows {
+            let asset_id = row.get::<i32, _>("AssetID").unwrap_or_default();
+            let close = (
+                row.get::<NaiveDate, _>("PriceDate").unwrap_or_default(),
+                row.get::<Decimal, _>("Price").unwrap_or_default(),
+            );
+            match closes.last_mut() {
+                Some((last_id, series)) if *last_id == asset_id => series.push(close),
+                _ => closes.push((asset_id, vec![close])),
+            }
+        }
+        Ok(closes)
+    }
+}
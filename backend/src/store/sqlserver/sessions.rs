@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use tiberius::Row;
+use uuid::Uuid;
+
+use crate::db::DbClient;
+use crate::store::{NewSession, RefreshOutcome, SessionStore, StoreError, StoreResult};
+use super::{count, sql_error, sql_uuid, SqlServerStore};
+
+fn uuid_from_row(row: &Row, column: &str) -> Uuid {
+    row.get::<tiberius::Uuid, _>(column)
+        .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
+        .unwrap_or_default()
+}
+
+// Records a session event through the audit procedure. Auditing must never
+// block a login or logout, so failures are only reported.
+async fn log_session(
+    client: &mut DbClient,
+    user_id: Uuid,
+    session_id: Uuid,
+    ip_address: Option<&str>,
+    user_agent: Option<&str>,
+    action: &str,
+    success: bool,
+) {
+    let query = "EXEC portfolio.sp_LogUserSession @P1, @P2, @P3, @P4, @P5, @P6";
+    let result = client.execute(
+        query,
+        &[&sql_uuid(user_id), &session_id.to_string(), &ip_address, &user_agent, &action, &success],
+    ).await;
+
+    if let Err(e) = result {
+        eprintln!("Failed to log {} for session {}: {}", action, session_id, e);
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqlServerStore {
+    async fn create_session(&self, session: &NewSession) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let query = "INSERT INTO portfolio.UserSessions
+                         (SessionID, UserID, RefreshTokenHash, ExpiresAt, IPAddress, UserAgent)
+                     VALUES (@P1, @P2, @P3, DATEADD(SECOND, @P4, SYSDATETIME()), @P5, @P6)";
+        client.execute(
+            query,
+            &[
+                &sql_uuid(session.session_id),
+                &sql_uuid(session.user_id),
+                &session.refresh_token_hash,
+                &session.ttl_secs,
+                &session.ip_address.as_deref(),
+                &session.user_agent.as_deref(),
+            ],
+        ).await.map_err(sql_error("Failed to create session"))?;
+
+        log_session(
+            &mut client,
+            session.user_id,
+            session.session_id,
+            session.ip_address.as_deref(),
+            session.user_agent.as_deref(),
+            "LOGIN",
+            true,
+        ).await;
+
+        Ok(())
+    }
+
+    async fn session_active(&self, session_id: Uuid) -> StoreResult<bool> {
+        let mut client = self.client().await?;
+
+        let active = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.UserSessions
+             WHERE SessionID = @P1 AND RevokedAt IS NULL AND ExpiresAt > SYSDATETIME()",
+            &[&sql_uuid(session_id)],
+            "session",
+        ).await?;
+
+        Ok(active > 0)
+    }
+
+    async fn rotate_session(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, ttl_secs: i32) -> StoreResult<RefreshOutcome> {
+        let mut client = self.client().await?;
+
+        // Single statement so two concurrent refreshes cannot both succeed
+        let rotate = "UPDATE portfolio.UserSessions
+                      SET PreviousTokenHash = RefreshTokenHash,
+                          RefreshTokenHash = @P2,
+                          LastUsedAt = SYSDATETIME(),
+                          ExpiresAt = DATEADD(SECOND, @P3, SYSDATETIME())
+                      OUTPUT inserted.SessionID, inserted.UserID
+                      WHERE RefreshTokenHash = @P1 AND RevokedAt IS NULL AND ExpiresAt > SYSDATETIME()";
+        let stream = client.query(rotate, &[&refresh_token_hash, &new_refresh_token_hash, &ttl_secs]).await
+            .map_err(sql_error("Failed to rotate session"))?;
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch rotated session"))?;
+
+        if let Some(row) = rows.first() {
+            return Ok(RefreshOutcome::Rotated {
+                session_id: uuid_from_row(row, "SessionID"),
+                user_id: uuid_from_row(row, "UserID"),
+            });
+        }
+
+        // A token that was already rotated away means someone kept a copy
+        let revoke = "UPDATE portfolio.UserSessions
+                      SET RevokedAt = SYSDATETIME()
+                      OUTPUT inserted.SessionID, inserted.UserID
+                      WHERE PreviousTokenHash = @P1 AND RevokedAt IS NULL";
+        let stream = client.query(revoke, &[&refresh_token_hash]).await
+            .map_err(sql_error("Failed to revoke reused session"))?;
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch revoked session"))?;
+
+        match rows.first() {
+            Some(row) => {
+                let (session_id, user_id) = (uuid_from_row(row, "SessionID"), uuid_from_row(row, "UserID"));
+                log_session(&mut client, user_id, session_id, None, None, "SESSION_EXPIRED", false).await;
+                Ok(RefreshOutcome::Reused)
+            },
+            None => Ok(RefreshOutcome::Invalid),
+        }
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let query = "UPDATE portfolio.UserSessions
+                     SET RevokedAt = SYSDATETIME()
+                     OUTPUT inserted.UserID
+                     WHERE SessionID = @P1 AND RevokedAt IS NULL";
+        let stream = client.query(query, &[&sql_uuid(session_id)]).await
+            .map_err(sql_error("Failed to revoke session"))?;
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch revoked session"))?;
+
+        let row = rows.first()
+            .ok_or_else(|| StoreError::NotFound("Session not found".to_string()))?;
+        log_session(&mut client, uuid_from_row(row, "UserID"), session_id, None, None, "LOGOUT", true).await;
+
+        Ok(())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> StoreResult<u64> {
+        let mut client = self.client().await?;
+
+        let query = "UPDATE portfolio.UserSessions
+                     SET RevokedAt = SYSDATETIME()
+                     OUTPUT inserted.SessionID
+                     WHERE UserID = @P1 AND RevokedAt IS NULL";
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await
+            .map_err(sql_error("Failed to revoke sessions"))?;
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch revoked sessions"))?;
+
+        for row in &rows {
+            log_session(&mut client, user_id, uuid_from_row(row, "SessionID"), None, None, "LOGOUT", true).await;
+        }
+
+        Ok(rows.len() as u64)
+    }
+}
@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+
+use crate::models::{CostBasisMethod, RealizedLot, TaxLot};
+use crate::store::{StoreError, StoreResult, TaxLotStore};
+use super::{sql_error, SqlServerStore};
+
+fn lot_from_row(row: &Row) -> TaxLot {
+    TaxLot {
+        lot_id: row.get("LotID").unwrap_or_default(),
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+        buy_transaction_id: row.get("BuyTransactionID"),
+        acquired_at: row.get::<chrono::NaiveDateTime, _>("AcquiredAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        quantity: row.get::<Decimal, _>("Quantity").unwrap_or_default(),
+        remaining_quantity: row.get::<Decimal, _>("RemainingQuantity").unwrap_or_default(),
+        unit_cost: row.get::<Decimal, _>("UnitCost").unwrap_or_default(),
+        cost_basis: row.get::<Decimal, _>("CostBasis").unwrap_or_default(),
+    }
+}
+
+fn realized_lot_from_row(row: &Row) -> RealizedLot {
+    RealizedLot {
+        sell_transaction_id: row.get("SellTransactionID").unwrap_or_default(),
+        lot_id: row.get("LotID").unwrap_or_default(),
+        portfolio_id: row.get("PortfolioID").unwrap_or_default(),
+        asset_id: row.get("AssetID").unwrap_or_default(),
+        symbol: row.get::<&str, _>("Symbol").unwrap_or_default().to_string(),
+        acquired_at: row.get::<chrono::NaiveDateTime, _>("AcquiredAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+        unit_cost: row.get::<Decimal, _>("UnitCost").unwrap_or_default(),
+        sale_price: row.get::<Decimal, _>("SalePrice").unwrap_or_default(),
+        quantity: row.get::<Decimal, _>("Quantity").unwrap_or_default(),
+        cost_basis: row.get::<Decimal, _>("CostBasis").unwrap_or_default(),
+        proceeds: row.get::<Decimal, _>("Proceeds").unwrap_or_default(),
+        realized_gain: row.get::<Decimal, _>("RealizedGain").unwrap_or_default(),
+        cost_basis_method: row.get::<&str, _>("CostBasisMethod")
+            .and_then(|method| method.parse().ok())
+            .unwrap_or_default(),
+        realized_at: row.get::<chrono::NaiveDateTime, _>("RealizedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait]
+impl TaxLotStore for SqlServerStore {
+    async fn cost_basis_method(&self, portfolio_id: i32) -> StoreResult<CostBasisMethod> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT CostBasisMethod FROM portfolio.Portfolios WHERE PortfolioID = @P1",
+            &[&portfolio_id],
+        ).await.map_err(sql_error("Failed to get cost basis method"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch cost basis method"))?
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+
+        Ok(row.get::<&str, _>("CostBasisMethod")
+            .and_then(|method| method.parse().ok())
+            .unwrap_or_default())
+    }
+
+    async fn set_cost_basis_method(&self, portfolio_id: i32, method: CostBasisMethod) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let result = client.execute(
+            "UPDATE portfolio.Portfolios SET CostBasisMethod = @P2 WHERE PortfolioID = @P1",
+            &[&portfolio_id, &method.as_str()],
+        ).await.map_err(sql_error("Failed to set cost basis method"))?;
+
+        if result.total() == 0 {
+            return Err(StoreError::NotFound("Portfolio not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn open_lots(&self, portfolio_id: i32, asset_id: Option<i32>) -> StoreResult<Vec<TaxLot>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.vw_TaxLots
+                     WHERE PortfolioID = @P1 AND RemainingQuantity > 0 AND (@P2 IS NULL OR AssetID = @P2)
+                     ORDER BY LotID";
+        let stream = client.query(query, &[&portfolio_id, &asset_id]).await
+            .map_err(sql_error("Failed to list tax lots"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch tax lots"))?;
+
+        Ok(rows.iter().map(lot_from_row).collect())
+    }
+
+    async fn realized_lots(
+        &self,
+        portfolio_id: i32,
+        asset_id: Option<i32>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<RealizedLot>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.vw_RealizedGains
+                     WHERE PortfolioID = @P1
+                       AND (@P2 IS NULL OR AssetID = @P2)
+                       AND (@P3 IS NULL OR RealizedAt >= @P3)
+                       AND (@P4 IS NULL OR RealizedAt < DATEADD(DAY, 1, @P4))
+                     ORDER BY SellTransactionID DESC, RealizedGainID";
+        let stream = client.query(query, &[&portfolio_id, &asset_id, &from, &to]).await
+            .map_err(sql_error("Failed to list realized gains"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch realized gains"))?;
+
+        Ok(rows.iter().map(realized_lot_from_row).collect())
+    }
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::Row;
+use tiberius::time::chrono;
+
+use crate::models::{QuantityIncrement, SetTradingLimitsRequest, TradingLimits};
+use crate::store::{PreTradeSnapshot, StoreError, StoreResult, TradingRuleStore};
+use super::{sql_error, SqlServerStore};
+
+/// Limits from a row that may come from a LEFT JOIN, so every column can be NULL
+fn limits_from_row(portfolio_id: i32, row: &Row, updated_at_column: &str) -> TradingLimits {
+    TradingLimits {
+        portfolio_id,
+        max_position_pct: row.get::<Decimal, _>("MaxPositionPct"),
+        max_order_notional: row.get::<Decimal, _>("MaxOrderNotional"),
+        max_daily_volume: row.get::<Decimal, _>("MaxDailyVolume"),
+        max_price_age_minutes: row.get::<i32, _>("MaxPriceAgeMinutes"),
+        updated_at: row.get::<chrono::NaiveDateTime, _>(updated_at_column).map(|dt| dt.to_string()),
+    }
+}
+
+fn quantity_increment_from_row(row: &Row) -> QuantityIncrement {
+    QuantityIncrement {
+        asset_type: row.get::<&str, _>("AssetType").unwrap_or_default().to_string(),
+        increment: row.get::<Decimal, _>("Increment").unwrap_or_default(),
+        updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+            .map(|dt| dt.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait]
+impl TradingRuleStore for SqlServerStore {
+    async fn trading_limits(&self, portfolio_id: i32) -> StoreResult<TradingLimits> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "SELECT l.MaxPositionPct, l.MaxOrderNotional, l.MaxDailyVolume, l.MaxPriceAgeMinutes, l.UpdatedAt
+             FROM portfolio.Portfolios p
+             LEFT JOIN portfolio.TradingLimits l ON l.PortfolioID = p.PortfolioID
+             WHERE p.PortfolioID = @P1",
+            &[&portfolio_id],
+        ).await.map_err(sql_error("Failed to get trading limits"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch trading limits"))?
+            .ok_or_else(|| StoreError::NotFound("Portfolio not found".to_string()))?;
+        Ok(limits_from_row(portfolio_id, &row, "UpdatedAt"))
+    }
+
+    async fn set_trading_limits(&self, portfolio_id: i32, limits: &SetTradingLimitsRequest) -> StoreResult<TradingLimits> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_SetTradingLimits @P1, @P2, @P3, @P4, @P5",
+            &[
+                &portfolio_id,
+                &limits.max_position_pct,
+                &limits.max_order_notional,
+                &limits.max_daily_volume,
+                &limits.max_price_age_minutes,
+            ],
+        ).await.map_err(sql_error("Failed to set trading limits"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch trading limits"))?
+            .ok_or_else(|| StoreError::Internal("No result returned from trading limits update".to_string()))?;
+        Ok(limits_from_row(portfolio_id, &row, "UpdatedAt"))
+    }
+
+    async fn quantity_increments(&self) -> StoreResult<Vec<QuantityIncrement>> {
+        let mut client = self.client().await?;
+
+        let stream = client.simple_query(
+            "SELECT * FROM portfolio.QuantityIncrements ORDER BY AssetType",
+        ).await.map_err(sql_error("Failed to list quantity increments"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch quantity increments"))?;
+        Ok(rows.iter().map(quantity_increment_from_row).collect())
+    }
+
+    async fn set_quantity_increment(&self, asset_type: &str, increment: Decimal) -> StoreResult<QuantityIncrement> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "UPDATE portfolio.QuantityIncrements
+             SET Increment = @P2, UpdatedAt = SYSDATETIME()
+             OUTPUT inserted.*
+             WHERE AssetType = @P1",
+            &[&asset_type, &increment],
+        ).await.map_err(sql_error("Failed to set quantity increment"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch quantity increment"))?
+            .ok_or_else(|| StoreError::NotFound(format!("No quantity increment for asset type {}", asset_type)))?;
+        Ok(quantity_increment_from_row(&row))
+    }
+
+    async fn pre_trade_snapshot(&self, portfolio_id: i32, asset_id: i32) -> StoreResult<PreTradeSnapshot> {
+        let mut client = self.client().await?;
+
+        let stream = client.query(
+            "EXEC portfolio.sp_GetPreTradeSnapshot @P1, @P2",
+            &[&portfolio_id, &asset_id],
+        ).await.map_err(sql_error("Failed to read pre-trade snapshot"))?;
+
+        let row = stream.into_row().await.map_err(sql_error("Failed to fetch pre-trade snapshot"))?
+            .ok_or_else(|| StoreError::Internal("No result returned from pre-trade snapshot".to_string()))?;
+
+        Ok(PreTradeSnapshot {
+            limits: limits_from_row(portfolio_id, &row, "LimitsUpdatedAt"),
+            asset_type: row.get::<&str, _>("AssetType").unwrap_or_default().to_string(),
+            price: row.get::<Decimal, _>("Price").unwrap_or_default(),
+            price_age_secs: row.get::<i64, _>("PriceAgeSeconds").unwrap_or_default(),
+            quantity_increment: row.get::<Decimal, _>("QuantityIncrement"),
+            quantity_held: row.get::<Decimal, _>("QuantityHeld").unwrap_or_default(),
+            portfolio_value: row.get::<Decimal, _>("PortfolioValue").unwrap_or_default(),
+            traded_today: row.get::<Decimal, _>("TradedToday").unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,566 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use tiberius::time::chrono;
+use uuid::Uuid;
+
+use crate::models::{
+    AccountSummary, CreateUserRequest, ExtendedUser, FundTransaction, PaymentMethodResponse,
+    SetPaymentMethodRequest, SubscriptionResponse, UpdateUserRequest, User,
+};
+use crate::store::{AllocationBalances, FundStore, StoreError, StoreResult, UserCredentials, UserStore};
+use super::{count, sql_error, sql_uuid, user_from_row, SqlServerStore};
+
+#[async_trait]
+impl UserStore for SqlServerStore {
+    async fn list_users(&self) -> StoreResult<Vec<User>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT UserID, Name, Email, CountryOfResidence, IBAN, UserType, CreatedAt, UpdatedAt FROM portfolio.Users ORDER BY CreatedAt DESC";
+        let stream = client.query(query, &[]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        Ok(rows.iter().map(user_from_row).collect())
+    }
+
+    async fn get_user(&self, user_id: Uuid) -> StoreResult<User> {
+        let mut client = self.client().await?;
+
+        // Use simple query for basic user info
+        let query = "SELECT UserID, Name, Email, CountryOfResidence, IBAN, UserType, CreatedAt, UpdatedAt FROM portfolio.Users WHERE UserID = @P1";
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        rows.first()
+            .map(user_from_row)
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))
+    }
+
+    async fn get_user_extended(&self, user_id: Uuid) -> StoreResult<ExtendedUser> {
+        let mut client = self.client().await?;
+
+        // Use the stored procedure for complete user info
+        let query = "EXEC portfolio.sp_GetUserCompleteInfo @P1";
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))?;
+
+        Ok(ExtendedUser {
+            user_id,
+            name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
+            email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
+            country_of_residence: row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string(),
+            iban: row.get::<&str, _>("IBAN").unwrap_or("").to_string(),
+            user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
+            account_balance: row.get::<Decimal, _>("AccountBalance").unwrap_or_default(),
+
+            // Payment Method Fields
+            payment_method_type: row.get::<&str, _>("PaymentMethodType").map(|s| s.to_string()),
+            payment_method_details: row.get::<&str, _>("PaymentMethodDetails").map(|s| s.to_string()),
+            payment_method_expiry: match row.try_get::<chrono::NaiveDate, _>("PaymentMethodExpiry") {
+                Ok(Some(d)) => Some(d.format("%Y-%m-%d").to_string()),
+                _ => None,
+            },
+            payment_method_active: row.get::<bool, _>("PaymentMethodActive").unwrap_or(false),
+
+            // Subscription Fields
+            is_premium: row.get::<bool, _>("IsPremium").unwrap_or(false),
+            premium_start_date: match row.try_get::<chrono::NaiveDateTime, _>("PremiumStartDate") {
+                Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                _ => None,
+            },
+            premium_end_date: match row.try_get::<chrono::NaiveDateTime, _>("PremiumEndDate") {
+                Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                _ => None,
+            },
+            monthly_subscription_rate: row.get::<Decimal, _>("MonthlySubscriptionRate"),
+            auto_renew_subscription: row.get::<bool, _>("AutoRenewSubscription").unwrap_or(false),
+            last_subscription_payment: match row.try_get::<chrono::NaiveDateTime, _>("LastSubscriptionPayment") {
+                Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                _ => None,
+            },
+            next_subscription_payment: match row.try_get::<chrono::NaiveDateTime, _>("NextSubscriptionPayment") {
+                Ok(Some(dt)) => Some(dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                _ => None,
+            },
+
+            // Calculated fields
+            days_remaining_in_subscription: row.get::<i32, _>("DaysRemainingInSubscription").unwrap_or(0),
+            subscription_expired: row.get::<bool, _>("SubscriptionExpired").unwrap_or(false),
+
+            created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
+                .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                .unwrap_or_default(),
+            updated_at: row.get::<chrono::NaiveDateTime, _>("UpdatedAt")
+                .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn create_user(&self, user: &CreateUserRequest) -> StoreResult<User> {
+        let mut client = self.client().await?;
+
+        // Check if email already exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Users WHERE Email = @P1",
+            &[&user.email],
+            "email",
+        ).await?;
+
+        if existing > 0 {
+            return Err(StoreError::BadRequest("Email already exists".to_string()));
+        }
+
+        let query = "EXEC portfolio.sp_CreateUser @P1, @P2, @P3, @P4, @P5, @P6";
+        let stream = client.query(
+            query,
+            &[
+                &user.name,
+                &user.email,
+                &user.password,
+                &user.country_of_residence,
+                &user.iban,
+                &user.user_type,
+            ],
+        ).await.map_err(sql_error("Failed to create user"))?;
+
+        let result = stream.into_first_result().await.map_err(sql_error("Failed to get created user"))?;
+
+        let row = result.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from create user".to_string()))?;
+
+        let user_id = row.get::<tiberius::Uuid, _>("UserID")
+            .map(|uuid| Uuid::from_bytes(*uuid.as_bytes()))
+            .ok_or_else(|| StoreError::Internal("UserID was null".to_string()))?;
+
+        // Hand the connection back to the pool before re-reading
+        drop(client);
+        self.get_user(user_id).await
+    }
+
+    async fn update_user(&self, user_id: Uuid, update: &UpdateUserRequest) -> StoreResult<User> {
+        let mut client = self.client().await?;
+
+        // Use stored procedure for update
+        let query = "EXEC portfolio.sp_UpdateUser @P1, @P2, @P3, @P4, @P5, @P6, @P7";
+
+        // Prepare parameters - pass NULL for fields that shouldn't be updated
+        let name_param: Option<&str> = update.name.as_deref();
+        let email_param: Option<&str> = update.email.as_deref();
+        let password_param: Option<&str> = update.password.as_deref();
+        let country_param: Option<&str> = update.country_of_residence.as_deref();
+        let iban_param: Option<&str> = update.iban.as_deref();
+        let user_type_param: Option<&str> = update.user_type.as_deref();
+
+        let stream = client.query(
+            query,
+            &[
+                &sql_uuid(user_id),
+                &name_param,
+                &email_param,
+                &password_param,
+                &country_param,
+                &iban_param,
+                &user_type_param,
+            ],
+        ).await.map_err(sql_error("Failed to update user"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch updated user"))?;
+
+        rows.first()
+            .map(user_from_row)
+            .ok_or_else(|| StoreError::NotFound("User not found after update".to_string()))
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> StoreResult<()> {
+        let mut client = self.client().await?;
+        let tiberius_user_id = sql_uuid(user_id);
+
+        // Check if user exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1",
+            &[&tiberius_user_id],
+            "user existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("User not found".to_string()));
+        }
+
+        let query = "DELETE FROM portfolio.Users WHERE UserID = @P1";
+        let stream = client.query(query, &[&tiberius_user_id]).await.map_err(sql_error("Failed to delete user"))?;
+
+        stream.into_first_result().await.map_err(sql_error("Failed to confirm deletion"))?;
+
+        Ok(())
+    }
+
+    async fn find_credentials(&self, email: &str) -> StoreResult<Option<UserCredentials>> {
+        let mut client = self.client().await?;
+
+        let query = "SELECT * FROM portfolio.Users WHERE Email = @P1";
+        let stream = client.query(query, &[&email]).await.map_err(sql_error("Query error"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Row error"))?;
+
+        Ok(rows.first().map(|row| UserCredentials {
+            password: row.get::<&str, _>("Password").unwrap_or("").to_string(),
+            user: user_from_row(row),
+        }))
+    }
+
+    async fn set_password(&self, user_id: Uuid, password_hash: &str) -> StoreResult<()> {
+        let mut client = self.client().await?;
+
+        let query = "UPDATE portfolio.Users SET Password = @P1 WHERE UserID = @P2";
+        let result = client.execute(query, &[&password_hash, &sql_uuid(user_id)]).await
+            .map_err(sql_error("Failed to update password"))?;
+
+        if result.total() == 0 {
+            return Err(StoreError::NotFound("User not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn set_payment_method(&self, user_id: Uuid, request: &SetPaymentMethodRequest) -> StoreResult<PaymentMethodResponse> {
+        let mut client = self.client().await?;
+
+        // Parse expiry date if provided
+        let expiry_date: Option<&str> = request.payment_method_expiry.as_deref();
+
+        let query = "EXEC portfolio.sp_SetUserPaymentMethod @P1, @P2, @P3, @P4";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &request.payment_method_type, &request.payment_method_details, &expiry_date],
+        ).await.map_err(sql_error("Failed to set payment method"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned".to_string()))?;
+
+        Ok(PaymentMethodResponse {
+            status: row.get::<&str, _>("Status").unwrap_or("Unknown").to_string(),
+            message: row.get::<&str, _>("Message").unwrap_or("Payment method updated").to_string(),
+        })
+    }
+
+    async fn manage_subscription(&self, user_id: Uuid, action: &str, months: i32, monthly_rate: Decimal) -> StoreResult<SubscriptionResponse> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_ManageSubscription @P1, @P2, @P3, @P4";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &action, &months, &monthly_rate],
+        ).await.map_err(sql_error("Failed to manage subscription"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned".to_string()))?;
+
+        let status: String = row.get::<&str, _>("Status").unwrap_or("Unknown").to_string();
+
+        if action == "CANCEL" {
+            let message: String = row.get::<&str, _>("Message").unwrap_or("Subscription cancelled").to_string();
+            Ok(SubscriptionResponse {
+                status,
+                amount_paid: None,
+                months_added: None,
+                new_balance: None,
+                message: Some(message),
+            })
+        } else {
+            Ok(SubscriptionResponse {
+                status,
+                amount_paid: row.get::<Decimal, _>("AmountPaid"),
+                months_added: row.get("MonthsAdded"),
+                new_balance: row.get::<Decimal, _>("NewBalance"),
+                message: Some("Subscription updated successfully".to_string()),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl FundStore for SqlServerStore {
+    async fn deposit_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_DepositFunds @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &amount, &description],
+        ).await.map_err(sql_error("Failed to deposit funds"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch deposit result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from deposit".to_string()))?;
+
+        Ok(row.get::<Decimal, _>("NewBalance")
+            .unwrap_or_default())
+    }
+
+    async fn withdraw_funds(&self, user_id: Uuid, amount: Decimal, description: &str) -> StoreResult<Decimal> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_WithdrawFunds @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &amount, &description],
+        ).await.map_err(sql_error("Failed to withdraw funds"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch withdrawal result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from withdrawal".to_string()))?;
+
+        Ok(row.get::<Decimal, _>("NewBalance")
+            .unwrap_or_default())
+    }
+
+    async fn allocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_AllocateFunds @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &portfolio_id, &amount],
+        ).await.map_err(sql_error("Failed to allocate funds"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch allocation result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from allocation".to_string()))?;
+
+        Ok(AllocationBalances {
+            new_user_balance: row.get::<Decimal, _>("NewUserBalance")
+                .unwrap_or_default(),
+            new_portfolio_funds: row.get::<Decimal, _>("NewPortfolioFunds")
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn deallocate_funds(&self, user_id: Uuid, portfolio_id: i32, amount: Decimal) -> StoreResult<AllocationBalances> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_DeallocateFunds @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &portfolio_id, &amount],
+        ).await.map_err(sql_error("Failed to deallocate funds"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch deallocation result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from deallocation".to_string()))?;
+
+        Ok(AllocationBalances {
+            new_user_balance: row.get::<Decimal, _>("NewUserBalance")
+                .unwrap_or_default(),
+            new_portfolio_funds: row.get::<Decimal, _>("NewPortfolioFunds")
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn upgrade_to_premium(&self, user_id: Uuid, months: i32, monthly_rate: Decimal) -> StoreResult<Decimal> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_UpgradeToPremium @P1, @P2, @P3";
+        let stream = client.query(
+            query,
+            &[&sql_uuid(user_id), &months, &monthly_rate],
+        ).await.map_err(sql_error("Failed to upgrade to premium"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch upgrade result"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::Internal("No result returned from upgrade".to_string()))?;
+
+        Ok(row.get::<Decimal, _>("NewBalance")
+            .unwrap_or_default())
+    }
+
+    async fn account_summary(&self, user_id: Uuid) -> StoreResult<AccountSummary> {
+        let mut client = self.client().await?;
+
+        let query = "EXEC portfolio.sp_GetUserAccountSummary @P1";
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await.map_err(sql_error("Failed to get account summary"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch account summary"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))?;
+
+        Ok(AccountSummary {
+            user_id,
+            name: row.get::<&str, _>("Name").unwrap_or("").to_string(),
+            user_type: row.get::<&str, _>("UserType").unwrap_or("").to_string(),
+            account_balance: row.get::<Decimal, _>("AccountBalance")
+                .unwrap_or_default(),
+            total_portfolio_value: row.get::<Decimal, _>("TotalPortfolioValue")
+                .unwrap_or_default(),
+            total_net_worth: row.get::<Decimal, _>("TotalNetWorth")
+                .unwrap_or_default(),
+            portfolio_count: row.get("PortfolioCount").unwrap_or(0),
+        })
+    }
+
+    async fn fund_transactions(&self, user_id: Uuid) -> StoreResult<Vec<FundTransaction>> {
+        let mut client = self.client().await?;
+        let tiberius_user_id = sql_uuid(user_id);
+
+        // Check if user exists
+        let existing = count(
+            &mut client,
+            "SELECT COUNT(*) as count FROM portfolio.Users WHERE UserID = @P1",
+            &[&tiberius_user_id],
+            "user existence",
+        ).await?;
+
+        if existing == 0 {
+            return Err(StoreError::NotFound("User not found".to_string()));
+        }
+
+        // Use the enhanced fund transaction history view
+        let query = "SELECT * FROM portfolio.vw_FundTransactionHistory WHERE UserID = @P1 ORDER BY CreatedAt DESC";
+        let stream = client.query(query, &[&tiberius_user_id]).await.map_err(sql_error("Failed to execute query"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch results"))?;
+
+        Ok(rows.into_iter().map(|row| {
+            FundTransaction {
+                fund_transaction_id: row.get("FundTransactionID").unwrap_or_default(),
+                user_id,
+                portfolio_id: row.get("PortfolioID"),
+                transaction_type: row.get::<&str, _>("TransactionType").unwrap_or("").to_string(),
+                amount: row.get::<Decimal, _>("Amount")
+                    .unwrap_or_default(),
+                balance_after: row.get::<Decimal, _>("BalanceAfter")
+                    .unwrap_or_default(),
+                description: row.get::<&str, _>("Description").map(|s| s.to_string()),
+                related_asset_transaction_id: row.get("RelatedAssetTransactionID"),
+                created_at: row.get::<chrono::NaiveDateTime, _>("CreatedAt")
+                    .map(|dt| dt.to_string())
+                    .unwrap_or_default(),
+            }
+        }).collect())
+    }
+
+    async fn enhanced_account_summary(&self, user_id: Uuid) -> StoreResult<serde_json::Value> {
+        let mut client = self.client().await?;
+
+        // Use the enhanced user account summary view
+        let query = "SELECT * FROM portfolio.vw_UserAccountSummary WHERE UserID = @P1";
+        let stream = client.query(query, &[&sql_uuid(user_id)]).await.map_err(sql_error("Failed to get enhanced account summary"))?;
+
+        let rows = stream.into_first_result().await.map_err(sql_error("Failed to fetch enhanced account summary"))?;
+
+        let row = rows.into_iter().next()
+            .ok_or_else(|| StoreError::NotFound("User not found".to_string()))?;
+
+        // Build comprehensive account summary
+        let mut summary = serde_json::Map::new();
+
+        // Basic user info
+        summary.insert("user_id".to_string(), serde_json::Value::String(user_id.to_string()));
+        summary.insert("name".to_string(), serde_json::Value::String(row.get::<&str, _>("Name").unwrap_or("").to_string()));
+        summary.insert("email".to_string(), serde_json::Value::String(row.get::<&str, _>("Email").unwrap_or("").to_string()));
+        summary.insert("country_of_residence".to_string(), serde_json::Value::String(row.get::<&str, _>("CountryOfResidence").unwrap_or("").to_string()));
+        summary.insert("user_type".to_string(), serde_json::Value::String(row.get::<&str, _>("UserType").unwrap_or("").to_string()));
+
+        // Financial information
+        summary.insert("account_balance".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("AccountBalance").unwrap_or_default().to_string()
+        ));
+        summary.insert("total_funds_in_portfolios".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("TotalFundsInPortfolios").unwrap_or_default().to_string()
+        ));
+        summary.insert("total_market_value".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("TotalMarketValue").unwrap_or_default().to_string()
+        ));
+        summary.insert("total_net_worth".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("TotalNetWorth").unwrap_or_default().to_string()
+        ));
+
+        // Payment method information
+        if let Some(payment_type) = row.get::<&str, _>("PaymentMethodType") {
+            summary.insert("payment_method_type".to_string(), serde_json::Value::String(payment_type.to_string()));
+        }
+        if let Some(payment_details) = row.get::<&str, _>("PaymentMethodDetails") {
+            summary.insert("payment_method_details".to_string(), serde_json::Value::String(payment_details.to_string()));
+        }
+        // Safely handle payment method expiry date which might be NULL
+        // NULL or conversion errors are skipped gracefully
+        if let Ok(Some(payment_expiry)) = row.try_get::<chrono::NaiveDate, _>("PaymentMethodExpiry") {
+            summary.insert("payment_method_expiry".to_string(), serde_json::Value::String(payment_expiry.to_string()));
+        }
+        summary.insert("payment_method_active".to_string(), serde_json::Value::Bool(
+            row.get::<bool, _>("PaymentMethodActive").unwrap_or(false)
+        ));
+
+        // Subscription information
+        summary.insert("is_premium".to_string(), serde_json::Value::Bool(
+            row.get::<bool, _>("IsPremium").unwrap_or(false)
+        ));
+        // Safely handle premium start date which might be NULL
+        // NULL or conversion errors are skipped gracefully
+        if let Ok(Some(premium_start)) = row.try_get::<chrono::NaiveDate, _>("PremiumStartDate") {
+            summary.insert("premium_start_date".to_string(), serde_json::Value::String(premium_start.to_string()));
+        }
+        // Safely handle premium end date which might be NULL
+        // NULL or conversion errors are skipped gracefully
+        if let Ok(Some(premium_end)) = row.try_get::<chrono::NaiveDate, _>("PremiumEndDate") {
+            summary.insert("premium_end_date".to_string(), serde_json::Value::String(premium_end.to_string()));
+        }
+        summary.insert("monthly_subscription_rate".to_string(), serde_json::Value::String(
+            row.get::<Decimal, _>("MonthlySubscriptionRate").unwrap_or_default().to_string()
+        ));
+        summary.insert("auto_renew_subscription".to_string(), serde_json::Value::Bool(
+            row.get::<bool, _>("AutoRenewSubscription").unwrap_or(false)
+        ));
+
+        // Subscription status calculations
+        summary.insert("days_remaining_in_subscription".to_string(), serde_json::Value::Number(
+            serde_json::Number::from(row.get::<i32, _>("DaysRemainingInSubscription").unwrap_or(0))
+        ));
+        summary.insert("subscription_expired".to_string(), serde_json::Value::Bool(
+            row.get::<bool, _>("SubscriptionExpired").unwrap_or(false)
+        ));
+
+        // Portfolio statistics
+        summary.insert("total_portfolios".to_string(), serde_json::Value::Number(
+            serde_json::Number::from(row.get::<i32, _>("TotalPortfolios").unwrap_or(0))
+        ));
+
+        // Recent activity
+        // Safely handle last fund transaction date which might be NULL
+        // NULL or conversion errors are skipped gracefully
+        if let Ok(Some(last_fund_transaction)) = row.try_get::<chrono::NaiveDateTime, _>("LastFundTransactionDate") {
+            summary.insert("last_fund_transaction_date".to_string(), serde_json::Value::String(last_fund_transaction.to_string()));
+        }
+        // Safely handle last trade date which might be NULL
+        // NULL or conversion errors are skipped gracefully
+        if let Ok(Some(last_trade)) = row.try_get::<chrono::NaiveDateTime, _>("LastTradeDate") {
+            summary.insert("last_trade_date".to_string(), serde_json::Value::String(last_trade.to_string()));
+        }
+
+        // Account dates
+        summary.insert("created_at".to_string(), serde_json::Value::String(
+            row.get::<chrono::NaiveDateTime, _>("CreatedAt").map(|dt| dt.to_string()).unwrap_or_default()
+        ));
+        summary.insert("updated_at".to_string(), serde_json::Value::String(
+            row.get::<chrono::NaiveDateTime, _>("UpdatedAt").map(|dt| dt.to_string()).unwrap_or_default()
+        ));
+
+        Ok(serde_json::Value::Object(summary))
+    }
+}
@@ -0,0 +1,539 @@
+//! Drives the HTTP API end to end over the in-memory store
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use backend::app;
+use backend::auth::hash_password;
+use backend::config::{AuthConfig, Config, MemoryConfig, StoreConfig};
+use backend::models::CreateUserRequest;
+use backend::orders;
+use backend::state::AppState;
+use backend::store::{MemoryStore, Store};
+
+const PASSWORD: &str = "Passw0rd!";
+
+struct TestApp {
+    router: Router,
+    store: Arc<dyn Store>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::new());
+        let config = Config {
+            bind_address: ([127, 0, 0, 1], 0).into(),
+            cors_allowed_origins: vec!["*".to_string()],
+            store: StoreConfig::Memory(MemoryConfig::default()),
+            auth: AuthConfig {
+                jwt_secret: "api-test-secret".parse().unwrap(),
+                access_token_ttl_secs: 900,
+                refresh_token_ttl_secs: 3600,
+            },
+        };
+        let price_events = orders::spawn_matcher(store.clone());
+        let router = app::router(AppState::new(store.clone(), config, price_events));
+        Self { router, store }
+    }
+
+    async fn request(&self, method: Method, path: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(format!("/api/v1{}", path));
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header("content-type", "application/json");
+                Body::from(body.to_string())
+            },
+            None => Body::empty(),
+        };
+
+        let response = self.router.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value = serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into()));
+        (status, value)
+    }
+
+    async fn get(&self, path: &str, token: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, path, Some(token), None).await
+    }
+
+    async fn post(&self, path: &str, token: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, path, Some(token), Some(body)).await
+    }
+
+    /// Registers a Basic user through the API and logs in; returns the user id and token
+    async fn sign_up(&self, email: &str) -> (String, String) {
+        let (status, user) = self.request(Method::POST, "/users", None, Some(json!({
+            "name": "Test User",
+            "email": email,
+            "password": PASSWORD,
+            "country_of_residence": "Portugal",
+            "iban": "PT50000201231234567890154",
+            "user_type": "Basic",
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "{user}");
+        let user_id = user["user_id"].as_str().unwrap().to_string();
+        (user_id, self.log_in(email).await)
+    }
+
+    async fn log_in(&self, email: &str) -> String {
+        let (status, login) = self.request(Method::POST, "/users/login", None, Some(json!({
+            "email": email,
+            "password": PASSWORD,
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "{login}");
+        login["token"].as_str().unwrap().to_string()
+    }
+
+    /// Registration refuses the Admin role, so the administrator goes straight into the store
+    async fn admin_token(&self) -> String {
+        self.store.create_user(&CreateUserRequest {
+            name: "Administrator".to_string(),
+            email: "admin@example.com".to_string(),
+            password: hash_password(PASSWORD).unwrap(),
+            country_of_residence: String::new(),
+            iban: String::new(),
+            user_type: "Admin".to_string(),
+        }).await.unwrap();
+        self.log_in("admin@example.com").await
+    }
+
+    async fn create_asset(&self, admin: &str, symbol: &str, price: &str) -> i64 {
+        let (status, asset) = self.post("/assets", admin, json!({
+            "symbol": symbol,
+            "name": format!("{} Inc.", symbol),
+            "asset_type": "Stock",
+            "initial_price": price,
+            "available_shares": "1000000",
+        })).await;
+        assert_eq!(status, StatusCode::OK, "{asset}");
+        asset["asset_id"].as_i64().unwrap()
+    }
+
+    /// A portfolio holding `funds` in cash, deposited and allocated through the API
+    async fn funded_portfolio(&self, user_id: &str, token: &str, funds: &str) -> i64 {
+        let (status, _) = self.post(&format!("/users/{}/deposit", user_id), token, json!({ "amount": funds })).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, portfolio) = self.post("/portfolios", token, json!({ "user_id": user_id, "name": "Main" })).await;
+        assert_eq!(status, StatusCode::OK, "{portfolio}");
+        let portfolio_id = portfolio["portfolio_id"].as_i64().unwrap();
+        let (status, _) = self.post(&format!("/users/{}/allocate", user_id), token, json!({
+            "portfolio_id": portfolio_id,
+            "amount": funds,
+        })).await;
+        assert_eq!(status, StatusCode::OK);
+        portfolio_id
+    }
+}
+
+fn decimal(value: &Value) -> Decimal {
+    value.as_str().unwrap_or_else(|| panic!("expected a decimal string, got {value}")).parse().unwrap()
+}
+
+fn assert_error(body: &Value, code: &str) {
+    assert_eq!(body["code"], code, "{body}");
+    assert!(body["message"].is_string(), "{body}");
+    assert!(body["request_id"].is_string(), "{body}");
+}
+
+#[tokio::test]
+async fn protected_routes_need_a_valid_token() {
+    let app = TestApp::new();
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.request(Method::GET, &format!("/users/{}", user_id), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "UNAUTHORIZED");
+
+    let (status, body) = app.get(&format!("/users/{}", user_id), "not-a-token").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "UNAUTHORIZED");
+
+    let (status, body) = app.get(&format!("/users/{}", user_id), &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["email"], "owner@example.com");
+}
+
+#[tokio::test]
+async fn login_rejects_a_wrong_password() {
+    let app = TestApp::new();
+    app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.request(Method::POST, "/users/login", None, Some(json!({
+        "email": "owner@example.com",
+        "password": "wrong",
+    }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "UNAUTHORIZED");
+}
+
+#[tokio::test]
+async fn users_only_reach_their_own_resources() {
+    let app = TestApp::new();
+    let (owner_id, owner) = app.sign_up("owner@example.com").await;
+    let (_, other) = app.sign_up("other@example.com").await;
+    let portfolio_id = app.funded_portfolio(&owner_id, &owner, "100").await;
+
+    let (status, body) = app.get(&format!("/users/{}", owner_id), &other).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "FORBIDDEN");
+
+    let (status, body) = app.get(&format!("/portfolios/{}", portfolio_id), &other).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "FORBIDDEN");
+}
+
+#[tokio::test]
+async fn market_data_writes_are_for_administrators() {
+    let app = TestApp::new();
+    let (_, token) = app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.post("/assets", &token, json!({
+        "symbol": "ACME",
+        "name": "Acme",
+        "asset_type": "Stock",
+        "initial_price": "10",
+    })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "FORBIDDEN");
+
+    let (status, body) = app.request(Method::POST, "/users", None, Some(json!({
+        "name": "Mallory",
+        "email": "mallory@example.com",
+        "password": PASSWORD,
+        "country_of_residence": "Portugal",
+        "iban": "PT50000201231234567890154",
+        "user_type": "Admin",
+    }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "FORBIDDEN");
+
+    let admin = app.admin_token().await;
+    app.create_asset(&admin, "ACME", "10").await;
+}
+
+#[tokio::test]
+async fn premium_only_comes_with_a_subscription() {
+    let app = TestApp::new();
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.request(Method::POST, "/users", None, Some(json!({
+        "name": "Mallory",
+        "email": "mallory@example.com",
+        "password": PASSWORD,
+        "country_of_residence": "Portugal",
+        "iban": "PT50000201231234567890154",
+        "user_type": "Premium",
+    }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    assert_error(&body, "FORBIDDEN");
+
+    let path = format!("/users/{}", user_id);
+    let (status, body) = app.request(Method::PUT, &path, Some(&token), Some(json!({ "user_type": "Premium" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    assert_error(&body, "FORBIDDEN");
+
+    let (_, user) = app.get(&path, &token).await;
+    assert_eq!(user["user_type"], "Basic");
+}
+
+#[tokio::test]
+async fn deposits_and_withdrawals_move_the_account_balance() {
+    let app = TestApp::new();
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.post(&format!("/users/{}/deposit", user_id), &token, json!({ "amount": "1000.50" })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(decimal(&body["new_balance"]), Decimal::new(100050, 2));
+
+    let (status, body) = app.post(&format!("/users/{}/withdraw", user_id), &token, json!({ "amount": "200" })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(decimal(&body["new_balance"]), Decimal::new(80050, 2));
+
+    let (status, body) = app.post(&format!("/users/{}/withdraw", user_id), &token, json!({ "amount": "5000" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_error(&body, "INSUFFICIENT_FUNDS");
+
+    let (status, body) = app.post(&format!("/users/{}/deposit", user_id), &token, json!({ "amount": "-5" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "VALIDATION_ERROR");
+
+    let (status, body) = app.get(&format!("/users/{}/account-summary", user_id), &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(decimal(&body["account_balance"]), Decimal::new(80050, 2));
+}
+
+#[tokio::test]
+async fn portfolio_cash_only_moves_through_allocations() {
+    let app = TestApp::new();
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "100").await;
+    let path = format!("/portfolios/{}", portfolio_id);
+
+    let (status, body) = app.request(Method::PUT, &path, Some(&token), Some(json!({ "current_funds": "1000000" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let (status, body) = app.request(Method::PUT, &path, Some(&token), Some(json!({
+        "name": "Renamed",
+        "current_funds": "1000000",
+    }))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["name"], "Renamed");
+    assert_eq!(decimal(&body["current_funds"]), Decimal::from(100));
+}
+
+#[tokio::test]
+async fn buying_and_selling_updates_cash_and_holdings() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let asset_id = app.create_asset(&admin, "ACME", "50").await;
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "1000").await;
+
+    let (status, bought) = app.post("/portfolios/buy", &token, json!({
+        "portfolio_id": portfolio_id,
+        "asset_id": asset_id,
+        "quantity": "10",
+    })).await;
+    assert_eq!(status, StatusCode::OK, "{bought}");
+    let fee = decimal(&bought["fee"]);
+    assert_eq!(decimal(&bought["total_cost"]), Decimal::from(500) + fee);
+    assert_eq!(decimal(&bought["remaining_funds"]), Decimal::from(500) - fee);
+    // Amounts and prices come back at their column's scale
+    assert_eq!(bought["price_per_share"], "50.0000");
+    assert_eq!(bought["total_cost"], format!("{:.2}", Decimal::from(500) + fee));
+
+    let (status, holdings) = app.get(&format!("/portfolios/{}/holdings", portfolio_id), &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(holdings.as_array().unwrap().len(), 1);
+    assert_eq!(decimal(&holdings[0]["quantity_held"]), Decimal::from(10));
+
+    let (status, sold) = app.post("/portfolios/sell", &token, json!({
+        "portfolio_id": portfolio_id,
+        "asset_id": asset_id,
+        "quantity": "4",
+    })).await;
+    assert_eq!(status, StatusCode::OK, "{sold}");
+    assert_eq!(decimal(&sold["total_proceeds"]), Decimal::from(200) - decimal(&sold["fee"]));
+    // Both fees count against the gain: the buy's through the lot's cost
+    let unit_cost = ((Decimal::from(500) + fee) / Decimal::from(10)).round_dp(4);
+    let cost_basis = (Decimal::from(4) * unit_cost).round_dp(2);
+    assert_eq!(decimal(&sold["cost_basis"]), cost_basis);
+    assert_eq!(decimal(&sold["realized_gain"]), decimal(&sold["total_proceeds"]) - cost_basis);
+
+    let (_, holdings) = app.get(&format!("/portfolios/{}/holdings", portfolio_id), &token).await;
+    assert_eq!(decimal(&holdings[0]["quantity_held"]), Decimal::from(6));
+}
+
+#[tokio::test]
+async fn batch_legs_count_towards_each_others_concentration() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let asset_id = app.create_asset(&admin, "ACME", "10").await;
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "1000").await;
+
+    let (status, body) = app.request(
+        Method::PUT,
+        &format!("/portfolios/{}/trading-limits", portfolio_id),
+        Some(&token),
+        Some(json!({ "max_position_pct": "25" })),
+    ).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Each leg is 20% of the portfolio on its own, 40% together
+    let leg = json!({ "asset_id": asset_id, "side": "Buy", "quantity": "20" });
+    let (status, batch) = app.post(&format!("/portfolios/{}/orders/batch", portfolio_id), &token, json!({
+        "legs": [leg, leg],
+    })).await;
+    assert!(status.is_success(), "{status}: {batch}");
+    assert_eq!(batch["status"], "PartiallyExecuted", "{batch}");
+    assert_eq!(batch["legs"][0]["status"], "Executed");
+    assert_eq!(batch["legs"][1]["status"], "Failed");
+    assert_eq!(batch["legs"][1]["error"]["code"], "TRADE_REJECTED");
+
+    let (_, holdings) = app.get(&format!("/portfolios/{}/holdings", portfolio_id), &token).await;
+    assert_eq!(decimal(&holdings[0]["quantity_held"]), Decimal::from(20));
+}
+
+#[tokio::test]
+async fn trades_beyond_cash_or_holdings_are_refused() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let asset_id = app.create_asset(&admin, "ACME", "50").await;
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "100").await;
+
+    let (status, body) = app.post("/portfolios/buy", &token, json!({
+        "portfolio_id": portfolio_id,
+        "asset_id": asset_id,
+        "quantity": "10",
+    })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_error(&body, "INSUFFICIENT_FUNDS");
+
+    let (status, body) = app.post("/portfolios/sell", &token, json!({
+        "portfolio_id": portfolio_id,
+        "asset_id": asset_id,
+        "quantity": "1",
+    })).await;
+    assert!(status.is_client_error(), "{status}: {body}");
+    assert!(body["code"].is_string(), "{body}");
+
+    // Nothing changed hands
+    let (_, holdings) = app.get(&format!("/portfolios/{}/holdings", portfolio_id), &token).await;
+    assert_eq!(holdings.as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn unknown_resources_are_not_found() {
+    let app = TestApp::new();
+    let (_, token) = app.sign_up("owner@example.com").await;
+
+    let (status, body) = app.request(Method::GET, "/assets/999999", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "NOT_FOUND");
+
+    let (status, body) = app.get("/orders/999999", &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn malformed_requests_get_the_error_body() {
+    let app = TestApp::new();
+    let (_, token) = app.sign_up("owner@example.com").await;
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/users")
+        .header("content-type", "application/json")
+        .body(Body::from("{\"email\":"))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_error(&serde_json::from_slice(&bytes).unwrap(), "VALIDATION_ERROR");
+
+    let (status, body) = app.request(Method::GET, "/assets/not-a-number", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "VALIDATION_ERROR");
+
+    let (status, body) = app.get("/portfolios/1/orders?status=Whenever", &token).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "VALIDATION_ERROR");
+}
+
+#[tokio::test]
+async fn limit_buys_reserve_their_fee_until_they_fill() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let asset_id = app.create_asset(&admin, "ACME", "50").await;
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "501.25").await;
+
+    // 10 at 45 is 450, plus the 0.25% fee of 1.13
+    let (status, order) = app.post(&format!("/portfolios/{}/orders", portfolio_id), &token, json!({
+        "asset_id": asset_id,
+        "side": "Buy",
+        "order_type": "Limit",
+        "quantity": "10",
+        "limit_price": "45",
+    })).await;
+    assert_eq!(status, StatusCode::CREATED, "{order}");
+    assert_eq!(decimal(&order["reserved_funds"]), Decimal::new(45113, 2));
+    let order_id = order["order_id"].as_i64().unwrap();
+
+    // Only what the order leaves can be moved out meanwhile
+    let (status, body) = app.post(&format!("/users/{}/deallocate", user_id), &token, json!({
+        "portfolio_id": portfolio_id,
+        "amount": "50.13",
+    })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+    let (status, body) = app.post(&format!("/users/{}/deallocate", user_id), &token, json!({
+        "portfolio_id": portfolio_id,
+        "amount": "50.12",
+    })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // The reservation is still the portfolio's money
+    let (status, balance) = app.get(&format!("/portfolios/{}/balance", portfolio_id), &token).await;
+    assert_eq!(status, StatusCode::OK, "{balance}");
+    assert_eq!(decimal(&balance["cash_balance"]), Decimal::ZERO);
+    assert_eq!(decimal(&balance["reserved_funds"]), Decimal::new(45113, 2));
+    assert_eq!(decimal(&balance["total_portfolio_value"]), Decimal::new(45113, 2));
+
+    let (status, body) = app.post(&format!("/assets/{}/price", asset_id), &admin, json!({ "price": "44" })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let mut order = Value::Null;
+    for _ in 0..50 {
+        (_, order) = app.get(&format!("/orders/{}", order_id), &token).await;
+        if order["status"] != "Pending" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(order["status"], "Executed", "{order}");
+
+    // Filled at 44 for 440 plus 1.10 in fees; the rest of the reservation comes back
+    let (_, portfolio) = app.get(&format!("/portfolios/{}", portfolio_id), &token).await;
+    assert_eq!(decimal(&portfolio["current_funds"]), Decimal::new(1003, 2));
+}
+
+#[tokio::test]
+async fn resting_orders_meet_the_rules_again_when_they_fill() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let asset_id = app.create_asset(&admin, "ACME", "50").await;
+    let (user_id, token) = app.sign_up("owner@example.com").await;
+    let portfolio_id = app.funded_portfolio(&user_id, &token, "1000").await;
+
+    let (status, order) = app.post(&format!("/portfolios/{}/orders", portfolio_id), &token, json!({
+        "asset_id": asset_id,
+        "side": "Buy",
+        "order_type": "Limit",
+        "quantity": "10",
+        "limit_price": "45",
+    })).await;
+    assert_eq!(status, StatusCode::CREATED, "{order}");
+    let order_id = order["order_id"].as_i64().unwrap();
+
+    // A limit set after placing the order still applies to its fill
+    let (status, body) = app.request(
+        Method::PUT,
+        &format!("/portfolios/{}/trading-limits", portfolio_id),
+        Some(&token),
+        Some(json!({ "max_order_notional": "400" })),
+    ).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = app.post(&format!("/assets/{}/price", asset_id), &admin, json!({ "price": "44" })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let mut order = Value::Null;
+    for _ in 0..50 {
+        (_, order) = app.get(&format!("/orders/{}", order_id), &token).await;
+        if order["status"] != "Pending" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(order["status"], "Cancelled", "{order}");
+    assert_eq!(order["status_reason"], "Trade value 440 exceeds the limit of 400 per trade");
+
+    // Nothing was bought and the reservation is back
+    let (_, portfolio) = app.get(&format!("/portfolios/{}", portfolio_id), &token).await;
+    assert_eq!(decimal(&portfolio["current_funds"]), Decimal::from(1000));
+    let (_, holdings) = app.get(&format!("/portfolios/{}/holdings", portfolio_id), &token).await;
+    assert_eq!(holdings.as_array().map(Vec::len), Some(0), "{holdings}");
+}
@@ -0,0 +1,151 @@
+/* ============================================================
+meuPortfolio – Password Hashes
+Users.Password now stores Argon2id PHC strings produced by the API
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. WIDEN PASSWORD COLUMN
+============================================================ */
+
+-- PHC strings are ~97 characters with the default parameters; leave headroom
+-- for stronger parameters. Existing plaintext rows are rehashed on next login.
+ALTER TABLE portfolio.Users ALTER COLUMN Password NVARCHAR(255) NOT NULL;
+GO
+
+/* ============================================================
+2. USER PROCEDURES
+============================================================ */
+
+-- Create new user (password arrives already hashed)
+ALTER PROCEDURE portfolio.sp_CreateUser (
+    @Name NVARCHAR(100),
+    @Email NVARCHAR(100),
+    @Password NVARCHAR(255),  -- Argon2id PHC string hashed by the API
+    @CountryOfResidence NVARCHAR(100),
+    @IBAN NVARCHAR(34),
+    @UserType NVARCHAR(20) = 'Basic'
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        -- Validate inputs
+        IF @Name IS NULL OR LTRIM(RTRIM(@Name)) = ''
+        BEGIN
+            RAISERROR('Name is required', 16, 1);
+            RETURN;
+        END
+        
+        IF @Email IS NULL OR LTRIM(RTRIM(@Email)) = ''
+        BEGIN
+            RAISERROR('Email is required', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if email already exists
+        IF EXISTS (SELECT 1 FROM portfolio.Users WHERE Email = @Email)
+        BEGIN
+            RAISERROR('Email already exists', 16, 1);
+            RETURN;
+        END
+        
+        DECLARE @UserID UNIQUEIDENTIFIER = NEWID();
+        
+        INSERT INTO portfolio.Users (
+            UserID, Name, Email, Password, 
+            CountryOfResidence, IBAN, UserType
+        )
+        VALUES (
+            @UserID, LTRIM(RTRIM(@Name)), LTRIM(RTRIM(@Email)), @Password,
+            @CountryOfResidence, @IBAN, @UserType
+        );
+        
+        -- Return the created user info
+        SELECT 
+            @UserID AS UserID,
+            @Name AS Name,
+            @Email AS Email,
+            @UserType AS UserType,
+            SYSDATETIME() AS CreatedAt;
+            
+    END TRY
+    BEGIN CATCH
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Update user information (trigger-compatible)
+ALTER PROCEDURE portfolio.sp_UpdateUser (
+    @UserID UNIQUEIDENTIFIER,
+    @Name NVARCHAR(100) = NULL,
+    @Email NVARCHAR(100) = NULL,
+    @Password NVARCHAR(255) = NULL,
+    @CountryOfResidence NVARCHAR(100) = NULL,
+    @IBAN NVARCHAR(34) = NULL,
+    @UserType NVARCHAR(20) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        -- Check if user exists
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Users WHERE UserID = @UserID)
+        BEGIN
+            RAISERROR('User not found', 16, 1);
+            RETURN;
+        END
+        
+        -- If email is being updated, check for duplicates
+        IF @Email IS NOT NULL
+        BEGIN
+            IF EXISTS (SELECT 1 FROM portfolio.Users WHERE Email = @Email AND UserID != @UserID)
+            BEGIN
+                RAISERROR('Email already exists', 16, 1);
+                RETURN;
+            END
+        END
+        
+        -- Check if any fields are provided for update
+        IF @Name IS NULL AND @Email IS NULL AND @Password IS NULL 
+           AND @CountryOfResidence IS NULL AND @IBAN IS NULL AND @UserType IS NULL
+        BEGIN
+            RAISERROR('No fields to update', 16, 1);
+            RETURN;
+        END
+        
+        -- Perform the update with only non-null fields
+        UPDATE portfolio.Users 
+        SET 
+            Name = COALESCE(LTRIM(RTRIM(@Name)), Name),
+            Email = COALESCE(LTRIM(RTRIM(@Email)), Email),
+            Password = COALESCE(@Password, Password),
+            CountryOfResidence = COALESCE(@CountryOfResidence, CountryOfResidence),
+            IBAN = COALESCE(@IBAN, IBAN),
+            UserType = COALESCE(@UserType, UserType)
+            -- Note: UpdatedAt will be set automatically by the trigger
+        WHERE UserID = @UserID;
+        
+        -- Return the updated user data
+        SELECT 
+            UserID,
+            Name,
+            Email,
+            CountryOfResidence,
+            IBAN,
+            UserType,
+            AccountBalance,
+            CreatedAt,
+            UpdatedAt
+        FROM portfolio.Users 
+        WHERE UserID = @UserID;
+        
+    END TRY
+    BEGIN CATCH
+        THROW;
+    END CATCH
+END;
+GO
@@ -0,0 +1,38 @@
+/* ============================================================
+meuPortfolio – User Sessions
+Server-side sessions backing rotating refresh tokens
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. SESSIONS TABLE
+============================================================ */
+
+-- Only SHA-256 hashes of refresh tokens are stored. PreviousTokenHash keeps the
+-- token that was rotated away so a replayed copy can revoke the whole session.
+CREATE TABLE portfolio.UserSessions (
+    SessionID UNIQUEIDENTIFIER PRIMARY KEY,
+    UserID UNIQUEIDENTIFIER NOT NULL,
+    RefreshTokenHash CHAR(64) NOT NULL,
+    PreviousTokenHash CHAR(64) NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    LastUsedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    ExpiresAt DATETIME NOT NULL,
+    RevokedAt DATETIME NULL,
+    IPAddress NVARCHAR(45) NULL,
+    UserAgent NVARCHAR(500) NULL,
+    CONSTRAINT FK_UserSessions_Users FOREIGN KEY (UserID)
+        REFERENCES portfolio.Users(UserID) ON DELETE CASCADE
+);
+GO
+
+/* ============================================================
+2. INDEXES
+============================================================ */
+
+CREATE UNIQUE INDEX IX_UserSessions_RefreshTokenHash ON portfolio.UserSessions(RefreshTokenHash);
+CREATE INDEX IX_UserSessions_PreviousTokenHash ON portfolio.UserSessions(PreviousTokenHash);
+CREATE INDEX IX_UserSessions_UserID ON portfolio.UserSessions(UserID);
+GO
@@ -0,0 +1,37 @@
+/* ============================================================
+meuPortfolio – Admin Role
+Adds 'Admin' to the allowed UserType values
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. USER TYPE CONSTRAINT
+============================================================ */
+
+-- The original CHECK constraint was created without a name; look it up and
+-- replace it with a named one that also accepts 'Admin'
+DECLARE @ConstraintName SYSNAME;
+
+SELECT @ConstraintName = cc.name
+FROM sys.check_constraints cc
+JOIN sys.columns c ON c.object_id = cc.parent_object_id AND c.column_id = cc.parent_column_id
+WHERE cc.parent_object_id = OBJECT_ID('portfolio.Users') AND c.name = 'UserType';
+
+IF @ConstraintName IS NOT NULL
+    EXEC('ALTER TABLE portfolio.Users DROP CONSTRAINT ' + @ConstraintName);
+GO
+
+ALTER TABLE portfolio.Users
+ADD CONSTRAINT CK_Users_UserType CHECK (UserType IN ('Basic', 'Premium', 'Admin'));
+GO
+
+/* ============================================================
+2. GRANTING THE ROLE
+============================================================ */
+
+-- The API refuses to register or promote administrators, so the role is
+-- granted here. The user must log in again (or refresh) to get an admin token.
+--
+-- UPDATE portfolio.Users SET UserType = 'Admin' WHERE Email = 'admin@example.com';
@@ -0,0 +1,38 @@
+/* ============================================================
+meuPortfolio – Idempotency Keys
+Responses remembered per Idempotency-Key so retried trades and
+fund movements run only once
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. IDEMPOTENCY KEYS TABLE
+============================================================ */
+
+-- Keys are scoped to the user that sent them. RequestHash is the SHA-256 of
+-- method, path and body; a key replayed with another hash is rejected.
+-- ResponseStatus stays NULL while the first request is still running; until
+-- then ExpiresAt is a short lease that the request keeps renewing, pushed out
+-- to the full TTL on completion.
+CREATE TABLE portfolio.IdempotencyKeys (
+    UserID UNIQUEIDENTIFIER NOT NULL,
+    IdempotencyKey NVARCHAR(255) NOT NULL,
+    RequestHash CHAR(64) NOT NULL,
+    ResponseStatus INT NULL,
+    ResponseBody NVARCHAR(MAX) NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    ExpiresAt DATETIME NOT NULL,
+    CONSTRAINT PK_IdempotencyKeys PRIMARY KEY (UserID, IdempotencyKey),
+    CONSTRAINT FK_IdempotencyKeys_Users FOREIGN KEY (UserID)
+        REFERENCES portfolio.Users(UserID) ON DELETE CASCADE
+);
+GO
+
+/* ============================================================
+2. INDEXES
+============================================================ */
+
+CREATE INDEX IX_IdempotencyKeys_ExpiresAt ON portfolio.IdempotencyKeys(ExpiresAt);
+GO
@@ -0,0 +1,550 @@
+/* ============================================================
+meuPortfolio – Orders
+Limit, stop and stop-limit orders resting on top of Pending
+rows in portfolio.Transactions, filled by the API's matching worker
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. ORDERS TABLE
+============================================================ */
+
+-- One row per order, extending the Pending transaction it was placed as; the
+-- order id is the TransactionID. Status, side and quantity stay on
+-- Transactions so trade history keeps working unchanged. While a buy order
+-- is pending its worst-case cost is held in ReservedFunds and taken out of
+-- Portfolios.CurrentFunds.
+CREATE TABLE portfolio.Orders (
+    OrderID BIGINT NOT NULL,
+    OrderType NVARCHAR(10) NOT NULL,
+    TimeInForce NVARCHAR(3) NOT NULL DEFAULT 'GTC',
+    LimitPrice DECIMAL(18,4) NULL,
+    StopPrice DECIMAL(18,4) NULL,
+    Triggered BIT NOT NULL DEFAULT 0,
+    ReservedFunds DECIMAL(18,2) NOT NULL DEFAULT 0,
+    ExpiresAt DATETIME NULL,
+    StatusReason NVARCHAR(255) NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    UpdatedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_Orders PRIMARY KEY (OrderID),
+    CONSTRAINT FK_Orders_Transactions FOREIGN KEY (OrderID)
+        REFERENCES portfolio.Transactions(TransactionID) ON DELETE CASCADE,
+    CONSTRAINT CK_Orders_OrderType CHECK (OrderType IN ('Market', 'Limit', 'Stop', 'StopLimit')),
+    CONSTRAINT CK_Orders_TimeInForce CHECK (TimeInForce IN ('GTC', 'DAY', 'IOC')),
+    CONSTRAINT CK_Orders_Prices CHECK (
+        (OrderType = 'Market' AND LimitPrice IS NULL AND StopPrice IS NULL)
+        OR (OrderType = 'Limit' AND LimitPrice > 0 AND StopPrice IS NULL)
+        OR (OrderType = 'Stop' AND LimitPrice IS NULL AND StopPrice > 0)
+        OR (OrderType = 'StopLimit' AND LimitPrice > 0 AND StopPrice > 0)
+    ),
+    CONSTRAINT CK_Orders_ReservedFunds CHECK (ReservedFunds >= 0)
+);
+GO
+
+/* ============================================================
+2. FUND TRANSACTION TYPES
+============================================================ */
+
+-- Reserving and releasing order funds is recorded like any other movement of
+-- portfolio cash. The original CHECK constraint has no name; replace it.
+DECLARE @ConstraintName SYSNAME;
+
+SELECT @ConstraintName = cc.name
+FROM sys.check_constraints cc
+JOIN sys.columns c ON c.object_id = cc.parent_object_id AND c.column_id = cc.parent_column_id
+WHERE cc.parent_object_id = OBJECT_ID('portfolio.FundTransactions') AND c.name = 'TransactionType';
+
+IF @ConstraintName IS NOT NULL
+    EXEC('ALTER TABLE portfolio.FundTransactions DROP CONSTRAINT ' + @ConstraintName);
+GO
+
+ALTER TABLE portfolio.FundTransactions
+ADD CONSTRAINT CK_FundTransactions_TransactionType CHECK (TransactionType IN (
+    'Deposit', 'Withdrawal', 'Allocation', 'Deallocation', 'PremiumUpgrade',
+    'AssetPurchase', 'AssetSale', 'OrderReservation', 'OrderRelease'
+));
+GO
+
+/* ============================================================
+3. INDEXES
+============================================================ */
+
+-- The matching worker scans pending orders per asset
+CREATE NONCLUSTERED INDEX IX_Transactions_Status_AssetID
+ON portfolio.Transactions(Status, AssetID);
+
+CREATE NONCLUSTERED INDEX IX_Orders_ExpiresAt
+ON portfolio.Orders(ExpiresAt)
+WHERE ExpiresAt IS NOT NULL;
+GO
+
+/* ============================================================
+4. ORDERS VIEW
+============================================================ */
+
+CREATE VIEW portfolio.vw_Orders AS
+SELECT
+    o.OrderID,
+    t.UserID,
+    t.PortfolioID,
+    t.AssetID,
+    a.Symbol,
+    t.TransactionType AS Side,
+    o.OrderType,
+    o.TimeInForce,
+    t.Quantity,
+    o.LimitPrice,
+    o.StopPrice,
+    o.Triggered,
+    o.ReservedFunds,
+    t.Status,
+    o.StatusReason,
+    CASE WHEN t.Status = 'Executed' THEN t.UnitPrice END AS FillPrice,
+    CASE WHEN t.Status = 'Executed' THEN t.TransactionDate END AS ExecutedAt,
+    o.ExpiresAt,
+    o.CreatedAt,
+    o.UpdatedAt
+FROM portfolio.Orders o
+JOIN portfolio.Transactions t ON t.TransactionID = o.OrderID
+JOIN portfolio.Assets a ON a.AssetID = t.AssetID;
+GO
+
+/* ============================================================
+5. ORDER PROCEDURES
+============================================================ */
+
+-- Place an order: inserts the Pending transaction and, for buys, reserves its
+-- cost at the limit price (limit and stop-limit), the stop price (stop) or the
+-- current price (market)
+CREATE PROCEDURE portfolio.sp_PlaceOrder (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Side NVARCHAR(10),
+    @OrderType NVARCHAR(10),
+    @TimeInForce NVARCHAR(3),
+    @Quantity DECIMAL(18,6),
+    @LimitPrice DECIMAL(18,4) = NULL,
+    @StopPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @MarketPrice DECIMAL(18,4);
+        SELECT @MarketPrice = Price FROM portfolio.Assets WHERE AssetID = @AssetID;
+
+        IF @MarketPrice IS NULL
+        BEGIN
+            RAISERROR('Asset not found', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @ReservePrice DECIMAL(18,4) = CASE @OrderType
+            WHEN 'Market' THEN @MarketPrice
+            WHEN 'Stop' THEN @StopPrice
+            ELSE @LimitPrice
+        END;
+        DECLARE @Reserved DECIMAL(18,2) = 0;
+
+        IF @Side = 'Buy'
+        BEGIN
+            SET @Reserved = @Quantity * @ReservePrice;
+
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) < @Reserved
+            BEGIN
+                RAISERROR('Insufficient funds in portfolio for this order', 16, 1);
+                RETURN;
+            END
+        END
+        ELSE
+        BEGIN
+            -- Shares already promised to other pending sells are not available
+            DECLARE @Available DECIMAL(18,6) =
+                ISNULL((
+                    SELECT QuantityHeld FROM portfolio.PortfolioHoldings
+                    WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID
+                ), 0)
+                - ISNULL((
+                    SELECT SUM(t.Quantity)
+                    FROM portfolio.Transactions t
+                    JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+                    WHERE t.PortfolioID = @PortfolioID AND t.AssetID = @AssetID
+                      AND t.TransactionType = 'Sell' AND t.Status = 'Pending'
+                ), 0);
+
+            IF @Available < @Quantity
+            BEGIN
+                RAISERROR('Insufficient holdings for this order', 16, 1);
+                RETURN;
+            END
+        END
+
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType,
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, @Side,
+            @Quantity, @ReservePrice, 'Pending'
+        );
+
+        DECLARE @OrderID BIGINT = SCOPE_IDENTITY();
+
+        -- DAY orders run until the end of the current day
+        INSERT INTO portfolio.Orders (
+            OrderID, OrderType, TimeInForce, LimitPrice, StopPrice, ReservedFunds, ExpiresAt
+        ) VALUES (
+            @OrderID, @OrderType, @TimeInForce, @LimitPrice, @StopPrice, @Reserved,
+            CASE WHEN @TimeInForce = 'DAY'
+                THEN DATEADD(DAY, 1, CAST(CAST(SYSDATETIME() AS DATE) AS DATETIME))
+            END
+        );
+
+        IF @Reserved > 0
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds - @Reserved
+            WHERE PortfolioID = @PortfolioID;
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'OrderReservation', -@Reserved,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Reserved funds for order ', @OrderID),
+                @OrderID
+            );
+        END
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Give back the funds a pending order holds; the caller owns the transaction
+CREATE PROCEDURE portfolio.sp_ReleaseOrderFunds (
+    @OrderID BIGINT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @Reserved DECIMAL(18,2);
+
+    SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @Reserved = o.ReservedFunds
+    FROM portfolio.Orders o
+    JOIN portfolio.Transactions t ON t.TransactionID = o.OrderID
+    WHERE o.OrderID = @OrderID;
+
+    IF @Reserved > 0
+    BEGIN
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds + @Reserved
+        WHERE PortfolioID = @PortfolioID;
+
+        UPDATE portfolio.Orders
+        SET ReservedFunds = 0, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'OrderRelease', @Reserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Released funds for order ', @OrderID),
+            @OrderID
+        );
+    END
+END;
+GO
+
+-- Fill a pending order at @FillPrice. The reservation is released first; if
+-- the portfolio can no longer cover the trade the order is marked Failed
+-- instead of raising an error.
+CREATE PROCEDURE portfolio.sp_FillOrder (
+    @OrderID BIGINT,
+    @FillPrice DECIMAL(18,4)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @Quantity DECIMAL(18,6), @Status NVARCHAR(20);
+
+        -- The lock keeps a concurrent fill or cancel out until this one commits
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @Quantity = t.Quantity, @Status = t.Status
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID;
+
+        DECLARE @Total DECIMAL(18,2) = @Quantity * @FillPrice;
+        DECLARE @FailReason NVARCHAR(255) = NULL;
+        DECLARE @CurrentHolding DECIMAL(18,6), @HoldingCost DECIMAL(18,2);
+
+        IF @Side = 'Buy'
+        BEGIN
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) < @Total
+                SET @FailReason = 'Insufficient funds at fill time';
+        END
+        ELSE
+        BEGIN
+            SELECT @CurrentHolding = QuantityHeld, @HoldingCost = TotalCost
+            FROM portfolio.PortfolioHoldings
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+
+            IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+                SET @FailReason = 'Insufficient holdings at fill time';
+        END
+
+        IF @FailReason IS NOT NULL
+        BEGIN
+            UPDATE portfolio.Transactions SET Status = 'Failed' WHERE TransactionID = @OrderID;
+            UPDATE portfolio.Orders
+            SET StatusReason = @FailReason, UpdatedAt = SYSDATETIME()
+            WHERE OrderID = @OrderID;
+
+            COMMIT;
+            SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+            RETURN;
+        END
+
+        IF @Side = 'Buy'
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds - @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = QuantityHeld + @Quantity,
+                    AveragePrice = ((TotalCost + @Total) / (QuantityHeld + @Quantity)),
+                    TotalCost = TotalCost + @Total
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                INSERT INTO portfolio.PortfolioHoldings (
+                    PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+                ) VALUES (
+                    @PortfolioID, @AssetID, @Quantity, @FillPrice, @Total
+                );
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetPurchase', -@Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+        ELSE
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds + @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+            DECLARE @CostBasis DECIMAL(18,2) = (@Quantity / @CurrentHolding) * @HoldingCost;
+
+            IF @NewQuantity > 0
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = @NewQuantity,
+                    TotalCost = @HoldingCost - @CostBasis
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                DELETE FROM portfolio.PortfolioHoldings
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetSale', @Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+
+        UPDATE portfolio.Transactions
+        SET Status = 'Executed', UnitPrice = @FillPrice, TransactionDate = SYSDATETIME()
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders SET UpdatedAt = SYSDATETIME() WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Cancel a pending order and release its reservation
+CREATE PROCEDURE portfolio.sp_CancelOrder (
+    @OrderID BIGINT,
+    @Reason NVARCHAR(255)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @Status NVARCHAR(20);
+
+        SELECT @Status = t.Status
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID;
+
+        UPDATE portfolio.Transactions SET Status = 'Cancelled' WHERE TransactionID = @OrderID;
+        UPDATE portfolio.Orders
+        SET StatusReason = @Reason, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+/* ============================================================
+6. PORTFOLIO BALANCE
+============================================================ */
+
+-- Funds pending buy orders hold out of CurrentFunds; they still belong to
+-- the portfolio until the order fills or is released
+CREATE FUNCTION portfolio.fn_PortfolioReservedFunds (
+    @PortfolioID INT
+)
+RETURNS DECIMAL(18,2)
+AS
+BEGIN
+    RETURN ISNULL((
+        SELECT SUM(o.ReservedFunds)
+        FROM portfolio.Orders o
+        JOIN portfolio.Transactions t ON t.TransactionID = o.OrderID
+        WHERE t.PortfolioID = @PortfolioID AND t.Status = 'Pending'
+    ), 0);
+END;
+GO
+
+-- Portfolio balance as in 005_3_trading_procedures.sql, plus the reserved funds
+CREATE OR ALTER PROCEDURE portfolio.sp_GetPortfolioBalance (
+    @PortfolioID INT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+    BEGIN
+        RAISERROR('Portfolio not found', 16, 1);
+        RETURN;
+    END
+    
+    SELECT 
+        PortfolioID,
+        UserID,
+        Name,
+        CurrentFunds,
+        CurrentProfitPct,
+        CreationDate,
+        LastUpdated,
+        -- Calculate total market value from holdings
+        ISNULL((
+            SELECT SUM(ph.QuantityHeld * a.Price)
+            FROM portfolio.PortfolioHoldings ph
+            JOIN portfolio.Assets a ON a.AssetID = ph.AssetID
+            WHERE ph.PortfolioID = @PortfolioID
+        ), 0) AS CurrentMarketValue,
+        -- Calculate total invested amount
+        ISNULL((
+            SELECT SUM(ph.TotalCost)
+            FROM portfolio.PortfolioHoldings ph
+            WHERE ph.PortfolioID = @PortfolioID
+        ), 0) AS TotalInvested,
+        portfolio.fn_PortfolioReservedFunds(@PortfolioID) AS ReservedFunds
+    FROM portfolio.Portfolios 
+    WHERE PortfolioID = @PortfolioID;
+END;
+GO
+
+PRINT 'Order tables and procedures created successfully!';
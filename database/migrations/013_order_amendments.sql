@@ -0,0 +1,538 @@
+/* ============================================================
+meuPortfolio – Order Amendments
+Amending pending orders, and a FundTransactions entry for every
+order state change (placed, amended, cancelled, failed, filled)
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. FUND TRANSACTION TYPES
+============================================================ */
+
+ALTER TABLE portfolio.FundTransactions DROP CONSTRAINT CK_FundTransactions_TransactionType;
+GO
+
+ALTER TABLE portfolio.FundTransactions
+ADD CONSTRAINT CK_FundTransactions_TransactionType CHECK (TransactionType IN (
+    'Deposit', 'Withdrawal', 'Allocation', 'Deallocation', 'PremiumUpgrade',
+    'AssetPurchase', 'AssetSale', 'OrderReservation', 'OrderRelease',
+    'OrderAmendment', 'OrderCancellation', 'OrderFailure'
+));
+GO
+
+/* ============================================================
+2. ORDER PROCEDURES
+============================================================ */
+
+-- Place an order: as in 012_orders.sql, but sell orders now get an
+-- OrderReservation entry too (for zero)
+CREATE OR ALTER PROCEDURE portfolio.sp_PlaceOrder (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Side NVARCHAR(10),
+    @OrderType NVARCHAR(10),
+    @TimeInForce NVARCHAR(3),
+    @Quantity DECIMAL(18,6),
+    @LimitPrice DECIMAL(18,4) = NULL,
+    @StopPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @MarketPrice DECIMAL(18,4);
+        SELECT @MarketPrice = Price FROM portfolio.Assets WHERE AssetID = @AssetID;
+
+        IF @MarketPrice IS NULL
+        BEGIN
+            RAISERROR('Asset not found', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @ReservePrice DECIMAL(18,4) = CASE @OrderType
+            WHEN 'Market' THEN @MarketPrice
+            WHEN 'Stop' THEN @StopPrice
+            ELSE @LimitPrice
+        END;
+        DECLARE @Reserved DECIMAL(18,2) = 0;
+
+        IF @Side = 'Buy'
+        BEGIN
+            SET @Reserved = @Quantity * @ReservePrice;
+
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) < @Reserved
+            BEGIN
+                RAISERROR('Insufficient funds in portfolio for this order', 16, 1);
+                RETURN;
+            END
+        END
+        ELSE
+        BEGIN
+            -- Shares already promised to other pending sells are not available
+            DECLARE @Available DECIMAL(18,6) =
+                ISNULL((
+                    SELECT QuantityHeld FROM portfolio.PortfolioHoldings
+                    WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID
+                ), 0)
+                - ISNULL((
+                    SELECT SUM(t.Quantity)
+                    FROM portfolio.Transactions t
+                    JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+                    WHERE t.PortfolioID = @PortfolioID AND t.AssetID = @AssetID
+                      AND t.TransactionType = 'Sell' AND t.Status = 'Pending'
+                ), 0);
+
+            IF @Available < @Quantity
+            BEGIN
+                RAISERROR('Insufficient holdings for this order', 16, 1);
+                RETURN;
+            END
+        END
+
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType,
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, @Side,
+            @Quantity, @ReservePrice, 'Pending'
+        );
+
+        DECLARE @OrderID BIGINT = SCOPE_IDENTITY();
+
+        -- DAY orders run until the end of the current day
+        INSERT INTO portfolio.Orders (
+            OrderID, OrderType, TimeInForce, LimitPrice, StopPrice, ReservedFunds, ExpiresAt
+        ) VALUES (
+            @OrderID, @OrderType, @TimeInForce, @LimitPrice, @StopPrice, @Reserved,
+            CASE WHEN @TimeInForce = 'DAY'
+                THEN DATEADD(DAY, 1, CAST(CAST(SYSDATETIME() AS DATE) AS DATETIME))
+            END
+        );
+
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds - @Reserved
+        WHERE PortfolioID = @PortfolioID;
+
+        -- Sells reserve nothing but are still logged so every order has an
+        -- opening entry
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'OrderReservation', -@Reserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Reserved ', CASE @Side WHEN 'Buy' THEN 'funds' ELSE 'holdings' END, ' for order ', @OrderID),
+            @OrderID
+        );
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Give back the funds a pending order holds; the caller owns the transaction.
+-- Plain releases (ahead of a fill) are only logged when money moves; any
+-- other @TransactionType marks the order closing and is always logged.
+CREATE OR ALTER PROCEDURE portfolio.sp_ReleaseOrderFunds (
+    @OrderID BIGINT,
+    @TransactionType NVARCHAR(20) = 'OrderRelease',
+    @Description NVARCHAR(255) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @Reserved DECIMAL(18,2);
+
+    SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @Reserved = o.ReservedFunds
+    FROM portfolio.Orders o
+    JOIN portfolio.Transactions t ON t.TransactionID = o.OrderID
+    WHERE o.OrderID = @OrderID;
+
+    IF @Reserved > 0 OR @TransactionType <> 'OrderRelease'
+    BEGIN
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds + @Reserved
+        WHERE PortfolioID = @PortfolioID;
+
+        UPDATE portfolio.Orders
+        SET ReservedFunds = 0, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, @TransactionType, @Reserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            ISNULL(@Description, CONCAT('Released funds for order ', @OrderID)),
+            @OrderID
+        );
+    END
+END;
+GO
+
+-- Fill a pending order at @FillPrice: as in 012_orders.sql, but a failed fill
+-- logs an OrderFailure entry with the released reservation
+CREATE OR ALTER PROCEDURE portfolio.sp_FillOrder (
+    @OrderID BIGINT,
+    @FillPrice DECIMAL(18,4)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @Quantity DECIMAL(18,6), @Status NVARCHAR(20),
+                @Reserved DECIMAL(18,2);
+
+        -- The lock keeps a concurrent fill or cancel out until this one commits
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @Quantity = t.Quantity, @Status = t.Status,
+               @Reserved = o.ReservedFunds
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @Total DECIMAL(18,2) = @Quantity * @FillPrice;
+        DECLARE @FailReason NVARCHAR(255) = NULL;
+        DECLARE @CurrentHolding DECIMAL(18,6), @HoldingCost DECIMAL(18,2);
+
+        IF @Side = 'Buy'
+        BEGIN
+            -- The reservation goes back to the portfolio before the trade settles
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) + @Reserved < @Total
+                SET @FailReason = 'Insufficient funds at fill time';
+        END
+        ELSE
+        BEGIN
+            SELECT @CurrentHolding = QuantityHeld, @HoldingCost = TotalCost
+            FROM portfolio.PortfolioHoldings
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+
+            IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+                SET @FailReason = 'Insufficient holdings at fill time';
+        END
+
+        IF @FailReason IS NOT NULL
+        BEGIN
+            DECLARE @FailDescription NVARCHAR(255) = CONCAT('Order ', @OrderID, ' failed: ', @FailReason);
+            EXEC portfolio.sp_ReleaseOrderFunds @OrderID, 'OrderFailure', @FailDescription;
+
+            UPDATE portfolio.Transactions SET Status = 'Failed' WHERE TransactionID = @OrderID;
+            UPDATE portfolio.Orders
+            SET StatusReason = @FailReason, UpdatedAt = SYSDATETIME()
+            WHERE OrderID = @OrderID;
+
+            COMMIT;
+            SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+            RETURN;
+        END
+
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID;
+
+        IF @Side = 'Buy'
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds - @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = QuantityHeld + @Quantity,
+                    AveragePrice = ((TotalCost + @Total) / (QuantityHeld + @Quantity)),
+                    TotalCost = TotalCost + @Total
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                INSERT INTO portfolio.PortfolioHoldings (
+                    PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+                ) VALUES (
+                    @PortfolioID, @AssetID, @Quantity, @FillPrice, @Total
+                );
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetPurchase', -@Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+        ELSE
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds + @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+            DECLARE @CostBasis DECIMAL(18,2) = (@Quantity / @CurrentHolding) * @HoldingCost;
+
+            IF @NewQuantity > 0
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = @NewQuantity,
+                    TotalCost = @HoldingCost - @CostBasis
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                DELETE FROM portfolio.PortfolioHoldings
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetSale', @Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+
+        UPDATE portfolio.Transactions
+        SET Status = 'Executed', UnitPrice = @FillPrice, TransactionDate = SYSDATETIME()
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders SET UpdatedAt = SYSDATETIME() WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Amend the quantity and/or limit price of a pending order. Buy orders have
+-- their reservation recomputed and the difference moved to or from
+-- Portfolios.CurrentFunds; sells are checked against unreserved holdings.
+CREATE OR ALTER PROCEDURE portfolio.sp_AmendOrder (
+    @OrderID BIGINT,
+    @Quantity DECIMAL(18,6) = NULL,
+    @LimitPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @OrderType NVARCHAR(10), @Status NVARCHAR(20),
+                @OldQuantity DECIMAL(18,6), @OldLimitPrice DECIMAL(18,4),
+                @ReservePrice DECIMAL(18,4), @OldReserved DECIMAL(18,2);
+
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @OrderType = o.OrderType, @Status = t.Status,
+               @OldQuantity = t.Quantity, @OldLimitPrice = o.LimitPrice,
+               @ReservePrice = t.UnitPrice, @OldReserved = o.ReservedFunds
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+
+        IF @LimitPrice IS NOT NULL AND @OrderType NOT IN ('Limit', 'StopLimit')
+        BEGIN
+            RAISERROR('Limit price can only be amended on Limit and StopLimit orders', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @NewQuantity DECIMAL(18,6) = ISNULL(@Quantity, @OldQuantity);
+        DECLARE @NewLimitPrice DECIMAL(18,4) = ISNULL(@LimitPrice, @OldLimitPrice);
+
+        -- Limit prices are what buys reserve at; other types keep their price
+        IF @OrderType IN ('Limit', 'StopLimit')
+            SET @ReservePrice = @NewLimitPrice;
+
+        DECLARE @NewReserved DECIMAL(18,2) = 0;
+
+        IF @Side = 'Buy'
+        BEGIN
+            SET @NewReserved = @NewQuantity * @ReservePrice;
+
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WITH (UPDLOCK) WHERE PortfolioID = @PortfolioID)
+                < @NewReserved - @OldReserved
+            BEGIN
+                RAISERROR('Insufficient funds in portfolio for this order', 16, 1);
+                RETURN;
+            END
+        END
+        ELSE
+        BEGIN
+            -- Shares promised to the other pending sells are not available
+            DECLARE @Available DECIMAL(18,6) =
+                ISNULL((
+                    SELECT QuantityHeld FROM portfolio.PortfolioHoldings
+                    WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID
+                ), 0)
+                - ISNULL((
+                    SELECT SUM(t.Quantity)
+                    FROM portfolio.Transactions t
+                    JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+                    WHERE t.PortfolioID = @PortfolioID AND t.AssetID = @AssetID
+                      AND t.TransactionType = 'Sell' AND t.Status = 'Pending'
+                      AND t.TransactionID <> @OrderID
+                ), 0);
+
+            IF @Available < @NewQuantity
+            BEGIN
+                RAISERROR('Insufficient holdings for this order', 16, 1);
+                RETURN;
+            END
+        END
+
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds - (@NewReserved - @OldReserved)
+        WHERE PortfolioID = @PortfolioID;
+
+        UPDATE portfolio.Transactions
+        SET Quantity = @NewQuantity, UnitPrice = @ReservePrice
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders
+        SET LimitPrice = @NewLimitPrice, ReservedFunds = @NewReserved, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'OrderAmendment', @OldReserved - @NewReserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Amended order ', @OrderID),
+            @OrderID
+        );
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Cancel a pending order and release its reservation; the OrderCancellation
+-- entry carries the released amount (zero for sells)
+CREATE OR ALTER PROCEDURE portfolio.sp_CancelOrder (
+    @OrderID BIGINT,
+    @Reason NVARCHAR(255)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @Status NVARCHAR(20);
+
+        SELECT @Status = t.Status
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @Description NVARCHAR(255) = CONCAT('Cancelled order ', @OrderID, ': ', @Reason);
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID, 'OrderCancellation', @Description;
+
+        UPDATE portfolio.Transactions SET Status = 'Cancelled' WHERE TransactionID = @OrderID;
+        UPDATE portfolio.Orders
+        SET StatusReason = @Reason, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Order amendment procedures created successfully!';
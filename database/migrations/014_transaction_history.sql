@@ -0,0 +1,44 @@
+/* ============================================================
+meuPortfolio – Transaction History
+Trade history per portfolio, paged by TransactionID for the
+API's transactions endpoint and its CSV export
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TRANSACTION HISTORY VIEW
+============================================================ */
+
+-- Buy and sell transactions with the asset they traded; TotalValue uses the
+-- fill price once executed and the reserved price while an order is pending
+CREATE OR ALTER VIEW portfolio.vw_TransactionHistory AS
+SELECT
+    t.TransactionID,
+    t.UserID,
+    t.PortfolioID,
+    t.AssetID,
+    a.Symbol,
+    a.Name AS AssetName,
+    t.TransactionType,
+    t.Quantity,
+    t.UnitPrice,
+    CAST(t.Quantity * t.UnitPrice AS DECIMAL(18,2)) AS TotalValue,
+    t.Status,
+    t.TransactionDate
+FROM portfolio.Transactions t
+JOIN portfolio.Assets a ON a.AssetID = t.AssetID;
+GO
+
+/* ============================================================
+2. INDEXES
+============================================================ */
+
+-- Pages are read newest first by TransactionID within a portfolio
+CREATE NONCLUSTERED INDEX IX_Transactions_PortfolioID_TransactionID
+ON portfolio.Transactions(PortfolioID, TransactionID DESC)
+INCLUDE (AssetID, TransactionType, Status, TransactionDate);
+GO
+
+PRINT 'Transaction history view created successfully!';
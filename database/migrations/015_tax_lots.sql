@@ -0,0 +1,724 @@
+/* ============================================================
+meuPortfolio – Tax Lots
+Every purchase becomes a tax lot; sales consume lots by the
+portfolio's cost basis method and record the realized gain
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. COST BASIS METHOD
+============================================================ */
+
+-- FIFO and LIFO sell the oldest or newest lots first, Average takes the same
+-- fraction of every open lot, SpecificLot sells the lots named on each sale
+ALTER TABLE portfolio.Portfolios
+ADD CostBasisMethod NVARCHAR(12) NOT NULL
+    CONSTRAINT DF_Portfolios_CostBasisMethod DEFAULT 'FIFO'
+    CONSTRAINT CK_Portfolios_CostBasisMethod CHECK (CostBasisMethod IN ('FIFO', 'LIFO', 'Average', 'SpecificLot'));
+GO
+
+/* ============================================================
+2. TAX LOT TABLES
+============================================================ */
+
+-- One row per purchase. BuyTransactionID is NULL for the lots backfilled
+-- below from holdings that existed before this migration.
+CREATE TABLE portfolio.TaxLots (
+    LotID BIGINT IDENTITY(1,1) NOT NULL,
+    PortfolioID INT NOT NULL,
+    AssetID INT NOT NULL,
+    BuyTransactionID BIGINT NULL,
+    AcquiredAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    Quantity DECIMAL(18,6) NOT NULL,
+    RemainingQuantity DECIMAL(18,6) NOT NULL,
+    UnitCost DECIMAL(18,4) NOT NULL,
+    CONSTRAINT PK_TaxLots PRIMARY KEY (LotID),
+    CONSTRAINT FK_TaxLots_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE,
+    CONSTRAINT FK_TaxLots_Assets FOREIGN KEY (AssetID)
+        REFERENCES portfolio.Assets(AssetID),
+    CONSTRAINT CK_TaxLots_Quantity CHECK (Quantity > 0 AND RemainingQuantity BETWEEN 0 AND Quantity)
+);
+GO
+
+-- One row per lot consumed by a sale; a sale across three lots has three rows
+CREATE TABLE portfolio.RealizedGains (
+    RealizedGainID BIGINT IDENTITY(1,1) NOT NULL,
+    SellTransactionID BIGINT NOT NULL,
+    LotID BIGINT NOT NULL,
+    Quantity DECIMAL(18,6) NOT NULL,
+    CostBasis DECIMAL(18,2) NOT NULL,
+    Proceeds DECIMAL(18,2) NOT NULL,
+    RealizedGain AS (Proceeds - CostBasis) PERSISTED,
+    CostBasisMethod NVARCHAR(12) NOT NULL,
+    RealizedAt DATETIME NOT NULL DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_RealizedGains PRIMARY KEY (RealizedGainID),
+    CONSTRAINT FK_RealizedGains_TaxLots FOREIGN KEY (LotID)
+        REFERENCES portfolio.TaxLots(LotID) ON DELETE CASCADE
+);
+GO
+
+CREATE NONCLUSTERED INDEX IX_TaxLots_PortfolioID_AssetID
+ON portfolio.TaxLots(PortfolioID, AssetID, LotID)
+INCLUDE (RemainingQuantity, UnitCost);
+
+CREATE NONCLUSTERED INDEX IX_RealizedGains_SellTransactionID
+ON portfolio.RealizedGains(SellTransactionID);
+GO
+
+-- Existing holdings become one lot each at their average price
+INSERT INTO portfolio.TaxLots (PortfolioID, AssetID, AcquiredAt, Quantity, RemainingQuantity, UnitCost)
+SELECT PortfolioID, AssetID, LastUpdated, QuantityHeld, QuantityHeld, TotalCost / QuantityHeld
+FROM portfolio.PortfolioHoldings
+WHERE QuantityHeld > 0;
+GO
+
+/* ============================================================
+3. VIEWS
+============================================================ */
+
+CREATE OR ALTER VIEW portfolio.vw_TaxLots AS
+SELECT
+    l.LotID,
+    l.PortfolioID,
+    l.AssetID,
+    a.Symbol,
+    l.BuyTransactionID,
+    l.AcquiredAt,
+    l.Quantity,
+    l.RemainingQuantity,
+    l.UnitCost,
+    CAST(l.RemainingQuantity * l.UnitCost AS DECIMAL(18,2)) AS CostBasis
+FROM portfolio.TaxLots l
+JOIN portfolio.Assets a ON a.AssetID = l.AssetID;
+GO
+
+CREATE OR ALTER VIEW portfolio.vw_RealizedGains AS
+SELECT
+    g.RealizedGainID,
+    g.SellTransactionID,
+    g.LotID,
+    l.PortfolioID,
+    l.AssetID,
+    a.Symbol,
+    l.AcquiredAt,
+    l.UnitCost,
+    t.UnitPrice AS SalePrice,
+    g.Quantity,
+    g.CostBasis,
+    g.Proceeds,
+    g.RealizedGain,
+    g.CostBasisMethod,
+    g.RealizedAt
+FROM portfolio.RealizedGains g
+JOIN portfolio.TaxLots l ON l.LotID = g.LotID
+JOIN portfolio.Assets a ON a.AssetID = l.AssetID
+JOIN portfolio.Transactions t ON t.TransactionID = g.SellTransactionID;
+GO
+
+/* ============================================================
+4. LOT PROCEDURES
+============================================================ */
+
+-- Open a lot for a purchase; the caller owns the transaction
+CREATE OR ALTER PROCEDURE portfolio.sp_OpenTaxLot (
+    @PortfolioID INT,
+    @AssetID INT,
+    @BuyTransactionID BIGINT,
+    @Quantity DECIMAL(18,6),
+    @UnitCost DECIMAL(18,4)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    INSERT INTO portfolio.TaxLots (
+        PortfolioID, AssetID, BuyTransactionID, Quantity, RemainingQuantity, UnitCost
+    ) VALUES (
+        @PortfolioID, @AssetID, @BuyTransactionID, @Quantity, @Quantity, @UnitCost
+    );
+END;
+GO
+
+-- Consume lots for a sale and record one RealizedGains row per lot. @Lots,
+-- when given, names the lots to sell whatever the portfolio's method; without
+-- it SpecificLot falls back to FIFO. The sale's proceeds are split across the
+-- lots, with rounding left on the last one so they add up exactly. Returns
+-- the cost basis sold; the caller owns the transaction.
+CREATE OR ALTER PROCEDURE portfolio.sp_CloseTaxLots (
+    @PortfolioID INT,
+    @AssetID INT,
+    @SellTransactionID BIGINT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4),
+    @Lots NVARCHAR(MAX) = NULL,
+    @CostBasis DECIMAL(18,2) OUTPUT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    DECLARE @Method NVARCHAR(12);
+    SELECT @Method = CostBasisMethod FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID;
+
+    IF @Lots IS NOT NULL
+        SET @Method = 'SpecificLot';
+    ELSE IF @Method = 'SpecificLot'
+        SET @Method = 'FIFO';
+
+    DECLARE @Take TABLE (LotID BIGINT PRIMARY KEY, Quantity DECIMAL(18,6) NOT NULL, Seq INT NOT NULL);
+
+    IF @Method = 'SpecificLot'
+    BEGIN
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT j.LotID, SUM(j.Quantity), ROW_NUMBER() OVER (ORDER BY j.LotID)
+        FROM OPENJSON(@Lots) WITH (
+            LotID BIGINT '$.lot_id',
+            Quantity DECIMAL(18,6) '$.quantity'
+        ) j
+        GROUP BY j.LotID;
+
+        IF EXISTS (
+            SELECT 1
+            FROM @Take t
+            LEFT JOIN portfolio.TaxLots l WITH (UPDLOCK)
+                ON l.LotID = t.LotID AND l.PortfolioID = @PortfolioID AND l.AssetID = @AssetID
+            WHERE l.LotID IS NULL OR t.Quantity <= 0 OR t.Quantity > l.RemainingQuantity
+        )
+        BEGIN
+            RAISERROR('Selected lots must be open lots of this holding with enough remaining shares', 16, 1);
+            RETURN;
+        END
+
+        IF (SELECT SUM(Quantity) FROM @Take) <> @Quantity
+        BEGIN
+            RAISERROR('Selected lot quantities must add up to the quantity sold', 16, 1);
+            RETURN;
+        END
+    END
+    ELSE IF @Method = 'Average'
+    BEGIN
+        -- The same fraction of every open lot keeps the average cost
+        -- unchanged; what truncating leaves over goes to the lots with the
+        -- most room, and no lot gives up more than it holds
+        DECLARE @OpenQuantity DECIMAL(18,6) = (
+            SELECT SUM(RemainingQuantity) FROM portfolio.TaxLots WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0
+        );
+
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT LotID, ROUND(RemainingQuantity * @Quantity / @OpenQuantity, 6, 1),
+               ROW_NUMBER() OVER (ORDER BY LotID)
+        FROM portfolio.TaxLots
+        WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0;
+
+        DECLARE @Left DECIMAL(18,6) = @Quantity - (SELECT SUM(Quantity) FROM @Take);
+
+        UPDATE t
+        SET Quantity = t.Quantity + CASE WHEN r.Covered <= @Left THEN r.Room
+                                         ELSE @Left - (r.Covered - r.Room) END
+        FROM @Take t
+        JOIN (
+            SELECT t.LotID, l.RemainingQuantity - t.Quantity AS Room,
+                   SUM(l.RemainingQuantity - t.Quantity) OVER (
+                       ORDER BY l.RemainingQuantity - t.Quantity DESC, t.Seq
+                       ROWS UNBOUNDED PRECEDING
+                   ) AS Covered
+            FROM @Take t
+            JOIN portfolio.TaxLots l ON l.LotID = t.LotID
+        ) r ON r.LotID = t.LotID
+        WHERE @Left > 0 AND r.Room > 0 AND r.Covered - r.Room < @Left;
+    END
+    ELSE
+    BEGIN
+        -- FIFO walks the lots oldest first, LIFO newest first, until the sale is covered
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT LotID,
+               CASE WHEN Covered <= @Quantity THEN RemainingQuantity
+                    ELSE @Quantity - (Covered - RemainingQuantity) END,
+               Seq
+        FROM (
+            SELECT LotID, RemainingQuantity,
+                   ROW_NUMBER() OVER (ORDER BY CASE WHEN @Method = 'LIFO' THEN -LotID ELSE LotID END) AS Seq,
+                   SUM(RemainingQuantity) OVER (
+                       ORDER BY CASE WHEN @Method = 'LIFO' THEN -LotID ELSE LotID END
+                       ROWS UNBOUNDED PRECEDING
+                   ) AS Covered
+            FROM portfolio.TaxLots WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0
+        ) lots
+        WHERE Covered - RemainingQuantity < @Quantity;
+    END
+
+    UPDATE l
+    SET RemainingQuantity = l.RemainingQuantity - t.Quantity
+    FROM portfolio.TaxLots l
+    JOIN @Take t ON t.LotID = l.LotID;
+
+    INSERT INTO portfolio.RealizedGains (
+        SellTransactionID, LotID, Quantity, CostBasis, Proceeds, CostBasisMethod
+    )
+    SELECT @SellTransactionID, t.LotID, t.Quantity,
+           CAST(t.Quantity * l.UnitCost AS DECIMAL(18,2)),
+           CAST(t.Quantity * @UnitPrice AS DECIMAL(18,2)),
+           @Method
+    FROM @Take t
+    JOIN portfolio.TaxLots l ON l.LotID = t.LotID
+    WHERE t.Quantity > 0;
+
+    DECLARE @TotalProceeds DECIMAL(18,2) = @Quantity * @UnitPrice;
+
+    UPDATE portfolio.RealizedGains
+    SET Proceeds = Proceeds + (
+        @TotalProceeds - (SELECT SUM(Proceeds) FROM portfolio.RealizedGains WHERE SellTransactionID = @SellTransactionID)
+    )
+    WHERE RealizedGainID = (
+        SELECT MAX(RealizedGainID) FROM portfolio.RealizedGains WHERE SellTransactionID = @SellTransactionID
+    );
+
+    SELECT @CostBasis = ISNULL(SUM(CostBasis), 0)
+    FROM portfolio.RealizedGains
+    WHERE SellTransactionID = @SellTransactionID;
+END;
+GO
+
+/* ============================================================
+5. TRADING PROCEDURES
+============================================================ */
+
+-- Buy, sell and order fills as before, now opening and closing tax lots.
+-- Sells take their cost basis from the lots they consume and recompute the
+-- holding's AveragePrice from what is left.
+CREATE OR ALTER PROCEDURE portfolio.sp_BuyAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL -- If NULL, use current market price
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        BEGIN TRANSACTION;
+        
+        -- Validate inputs
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if portfolio belongs to user
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios 
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+        
+        -- Get current asset price if not provided
+        IF @UnitPrice IS NULL
+        BEGIN
+            SELECT @UnitPrice = Price 
+            FROM portfolio.Assets 
+            WHERE AssetID = @AssetID;
+            
+            IF @UnitPrice IS NULL
+            BEGIN
+                RAISERROR('Asset not found', 16, 1);
+                RETURN;
+            END
+        END
+        
+        -- Calculate total cost
+        DECLARE @TotalCost DECIMAL(18,2) = @Quantity * @UnitPrice;
+        
+        -- Check if portfolio has sufficient funds
+        DECLARE @CurrentFunds DECIMAL(18,2);
+        SELECT @CurrentFunds = CurrentFunds 
+        FROM portfolio.Portfolios 
+        WHERE PortfolioID = @PortfolioID;
+        
+        IF @CurrentFunds < @TotalCost
+        BEGIN
+            RAISERROR('Insufficient funds in portfolio for this purchase', 16, 1);
+            RETURN;
+        END
+        
+        -- Create transaction record
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType, 
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, 'Buy',
+            @Quantity, @UnitPrice, 'Executed'
+        );
+        
+        DECLARE @TransactionID BIGINT = SCOPE_IDENTITY();
+        
+        -- Update portfolio funds
+        UPDATE portfolio.Portfolios 
+        SET CurrentFunds = CurrentFunds - @TotalCost
+        WHERE PortfolioID = @PortfolioID;
+        
+        -- Update or insert holdings
+        IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+        BEGIN
+            -- Update existing holding - calculate new average price
+            UPDATE portfolio.PortfolioHoldings 
+            SET QuantityHeld = QuantityHeld + @Quantity,
+                AveragePrice = ((TotalCost + @TotalCost) / (QuantityHeld + @Quantity)),
+                TotalCost = TotalCost + @TotalCost
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        ELSE
+        BEGIN
+            -- Create new holding
+            INSERT INTO portfolio.PortfolioHoldings (
+                PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+            ) VALUES (
+                @PortfolioID, @AssetID, @Quantity, @UnitPrice, @TotalCost
+            );
+        END
+        
+        -- Each purchase opens its own tax lot
+        EXEC portfolio.sp_OpenTaxLot @PortfolioID, @AssetID, @TransactionID, @Quantity, @UnitPrice;
+        
+        -- Record fund transaction
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount, 
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'AssetPurchase', -@TotalCost,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+            @TransactionID
+        );
+        
+        COMMIT;
+        
+        -- Return success with transaction details
+        SELECT 
+            'SUCCESS' AS Status,
+            @TransactionID AS TransactionID,
+            @Quantity AS QuantityPurchased,
+            @UnitPrice AS PricePerShare,
+            @TotalCost AS TotalCost,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) AS RemainingFunds;
+            
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_SellAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL, -- If NULL, use current market price
+    @Lots NVARCHAR(MAX) = NULL -- JSON [{"lot_id", "quantity"}] choosing the lots to sell
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        BEGIN TRANSACTION;
+        
+        -- Validate inputs
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if portfolio belongs to user
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios 
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+        
+        -- Specific-lot portfolios have to say which lots are sold
+        IF @Lots IS NULL AND (
+            SELECT CostBasisMethod FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID
+        ) = 'SpecificLot'
+        BEGIN
+            RAISERROR('Lots to sell are required when the portfolio uses SpecificLot cost basis', 16, 1);
+            RETURN;
+        END
+        
+        -- Check current holdings
+        DECLARE @CurrentHolding DECIMAL(18,6), @AveragePrice DECIMAL(18,4), @TotalCost DECIMAL(18,2);
+        SELECT @CurrentHolding = QuantityHeld, @AveragePrice = AveragePrice, @TotalCost = TotalCost
+        FROM portfolio.PortfolioHoldings 
+        WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        
+        IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+        BEGIN
+            RAISERROR('Insufficient holdings for this sale', 16, 1);
+            RETURN;
+        END
+        
+        -- Get current asset price if not provided
+        IF @UnitPrice IS NULL
+        BEGIN
+            SELECT @UnitPrice = Price 
+            FROM portfolio.Assets 
+            WHERE AssetID = @AssetID;
+            
+            IF @UnitPrice IS NULL
+            BEGIN
+                RAISERROR('Asset not found', 16, 1);
+                RETURN;
+            END
+        END
+        
+        -- Calculate total proceeds
+        DECLARE @TotalProceeds DECIMAL(18,2) = @Quantity * @UnitPrice;
+        
+        -- Create transaction record
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType, 
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, 'Sell',
+            @Quantity, @UnitPrice, 'Executed'
+        );
+        
+        DECLARE @TransactionID BIGINT = SCOPE_IDENTITY();
+        
+        -- Update portfolio funds (add proceeds)
+        UPDATE portfolio.Portfolios 
+        SET CurrentFunds = CurrentFunds + @TotalProceeds
+        WHERE PortfolioID = @PortfolioID;
+        
+        -- Update holdings
+        DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+        DECLARE @CostBasis DECIMAL(18,2);
+        EXEC portfolio.sp_CloseTaxLots
+            @PortfolioID, @AssetID, @TransactionID, @Quantity, @UnitPrice, @Lots,
+            @CostBasis OUTPUT;
+        
+        IF @NewQuantity > 0
+        BEGIN
+            -- Update existing holding
+            UPDATE portfolio.PortfolioHoldings 
+            SET QuantityHeld = @NewQuantity,
+                AveragePrice = (@TotalCost - @CostBasis) / @NewQuantity,
+                TotalCost = @TotalCost - @CostBasis
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        ELSE
+        BEGIN
+            -- Remove holding completely (sold all shares)
+            DELETE FROM portfolio.PortfolioHoldings 
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        
+        -- Record fund transaction
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount, 
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'AssetSale', @TotalProceeds,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+            @TransactionID
+        );
+        
+        COMMIT;
+        
+        -- Return success with transaction details
+        SELECT 
+            'SUCCESS' AS Status,
+            @TransactionID AS TransactionID,
+            @Quantity AS QuantitySold,
+            @UnitPrice AS PricePerShare,
+            @TotalProceeds AS TotalProceeds,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) AS NewFunds,
+            @NewQuantity AS RemainingShares,
+            @CostBasis AS CostBasis,
+            @TotalProceeds - @CostBasis AS RealizedGain;
+            
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_FillOrder (
+    @OrderID BIGINT,
+    @FillPrice DECIMAL(18,4)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @Quantity DECIMAL(18,6), @Status NVARCHAR(20),
+                @Reserved DECIMAL(18,2);
+
+        -- The lock keeps a concurrent fill or cancel out until this one commits
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @Quantity = t.Quantity, @Status = t.Status,
+               @Reserved = o.ReservedFunds
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @Total DECIMAL(18,2) = @Quantity * @FillPrice;
+        DECLARE @FailReason NVARCHAR(255) = NULL;
+        DECLARE @CurrentHolding DECIMAL(18,6), @HoldingCost DECIMAL(18,2);
+
+        IF @Side = 'Buy'
+        BEGIN
+            -- The reservation goes back to the portfolio before the trade settles
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) + @Reserved < @Total
+                SET @FailReason = 'Insufficient funds at fill time';
+        END
+        ELSE
+        BEGIN
+            SELECT @CurrentHolding = QuantityHeld, @HoldingCost = TotalCost
+            FROM portfolio.PortfolioHoldings
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+
+            IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+                SET @FailReason = 'Insufficient holdings at fill time';
+        END
+
+        IF @FailReason IS NOT NULL
+        BEGIN
+            DECLARE @FailDescription NVARCHAR(255) = CONCAT('Order ', @OrderID, ' failed: ', @FailReason);
+            EXEC portfolio.sp_ReleaseOrderFunds @OrderID, 'OrderFailure', @FailDescription;
+
+            UPDATE portfolio.Transactions SET Status = 'Failed' WHERE TransactionID = @OrderID;
+            UPDATE portfolio.Orders
+            SET StatusReason = @FailReason, UpdatedAt = SYSDATETIME()
+            WHERE OrderID = @OrderID;
+
+            COMMIT;
+            SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+            RETURN;
+        END
+
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID;
+
+        IF @Side = 'Buy'
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds - @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = QuantityHeld + @Quantity,
+                    AveragePrice = ((TotalCost + @Total) / (QuantityHeld + @Quantity)),
+                    TotalCost = TotalCost + @Total
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                INSERT INTO portfolio.PortfolioHoldings (
+                    PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+                ) VALUES (
+                    @PortfolioID, @AssetID, @Quantity, @FillPrice, @Total
+                );
+            END
+
+            EXEC portfolio.sp_OpenTaxLot @PortfolioID, @AssetID, @OrderID, @Quantity, @FillPrice;
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetPurchase', -@Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+        ELSE
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds + @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+            DECLARE @CostBasis DECIMAL(18,2);
+
+            -- Orders cannot pick lots, so specific-lot portfolios sell FIFO here
+            EXEC portfolio.sp_CloseTaxLots
+                @PortfolioID, @AssetID, @OrderID, @Quantity, @FillPrice, NULL,
+                @CostBasis OUTPUT;
+
+            IF @NewQuantity > 0
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = @NewQuantity,
+                    AveragePrice = (@HoldingCost - @CostBasis) / @NewQuantity,
+                    TotalCost = @HoldingCost - @CostBasis
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                DELETE FROM portfolio.PortfolioHoldings
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetSale', @Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+
+        UPDATE portfolio.Transactions
+        SET Status = 'Executed', UnitPrice = @FillPrice, TransactionDate = SYSDATETIME()
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders SET UpdatedAt = SYSDATETIME() WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Tax lots created successfully!';
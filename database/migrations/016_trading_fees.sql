@@ -0,0 +1,1169 @@
+/* ============================================================
+meuPortfolio – Trading Fees
+Fee schedules per user type and asset type, charged on every
+buy, sell and order fill as their own fund transactions
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. FEE SCHEDULES
+============================================================ */
+
+-- One schedule per user type and asset type; a NULL AssetType covers every
+-- asset type without a schedule of its own. Flat charges FlatFee per trade,
+-- Percentage charges Rate percent of the trade value and Tiered charges the
+-- Rate of the highest tier the trade value reaches. MinimumFee applies to all.
+CREATE TABLE portfolio.FeeSchedules (
+    FeeScheduleID INT IDENTITY(1,1) NOT NULL,
+    UserType NVARCHAR(20) NOT NULL,
+    AssetType NVARCHAR(20) NULL,
+    FeeType NVARCHAR(12) NOT NULL,
+    FlatFee DECIMAL(18,2) NULL,
+    Rate DECIMAL(9,4) NULL,
+    MinimumFee DECIMAL(18,2) NOT NULL CONSTRAINT DF_FeeSchedules_MinimumFee DEFAULT 0,
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_FeeSchedules_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_FeeSchedules PRIMARY KEY (FeeScheduleID),
+    CONSTRAINT UQ_FeeSchedules_UserType_AssetType UNIQUE (UserType, AssetType),
+    CONSTRAINT CK_FeeSchedules_UserType CHECK (UserType IN ('Basic', 'Premium')),
+    CONSTRAINT CK_FeeSchedules_AssetType CHECK (AssetType IN ('Stock', 'Index', 'Cryptocurrency', 'Commodity')),
+    CONSTRAINT CK_FeeSchedules_FeeType CHECK (
+        (FeeType = 'Flat' AND FlatFee >= 0)
+        OR (FeeType = 'Percentage' AND Rate >= 0)
+        OR FeeType = 'Tiered'
+    ),
+    CONSTRAINT CK_FeeSchedules_MinimumFee CHECK (MinimumFee >= 0)
+);
+GO
+
+-- Rates of a Tiered schedule by the trade value they start at
+CREATE TABLE portfolio.FeeTiers (
+    FeeScheduleID INT NOT NULL,
+    MinTradeValue DECIMAL(18,2) NOT NULL,
+    Rate DECIMAL(9,4) NOT NULL,
+    CONSTRAINT PK_FeeTiers PRIMARY KEY (FeeScheduleID, MinTradeValue),
+    CONSTRAINT FK_FeeTiers_FeeSchedules FOREIGN KEY (FeeScheduleID)
+        REFERENCES portfolio.FeeSchedules(FeeScheduleID) ON DELETE CASCADE,
+    CONSTRAINT CK_FeeTiers_Values CHECK (MinTradeValue >= 0 AND Rate >= 0)
+);
+GO
+
+-- Default schedules; Premium users pay less and crypto is tiered by trade value
+INSERT INTO portfolio.FeeSchedules (UserType, AssetType, FeeType, FlatFee, Rate, MinimumFee) VALUES
+    ('Basic', NULL, 'Percentage', NULL, 0.2500, 1.00),
+    ('Basic', 'Cryptocurrency', 'Tiered', NULL, NULL, 1.00),
+    ('Premium', NULL, 'Percentage', NULL, 0.1000, 0.50),
+    ('Premium', 'Cryptocurrency', 'Tiered', NULL, NULL, 0.50);
+
+INSERT INTO portfolio.FeeTiers (FeeScheduleID, MinTradeValue, Rate)
+SELECT s.FeeScheduleID, t.MinTradeValue, t.Rate
+FROM portfolio.FeeSchedules s
+JOIN (VALUES
+    ('Basic', 0, 1.0000), ('Basic', 1000, 0.7500), ('Basic', 10000, 0.5000),
+    ('Premium', 0, 0.5000), ('Premium', 10000, 0.2500)
+) t (UserType, MinTradeValue, Rate) ON t.UserType = s.UserType
+WHERE s.AssetType = 'Cryptocurrency';
+GO
+
+ALTER TABLE portfolio.FundTransactions DROP CONSTRAINT CK_FundTransactions_TransactionType;
+GO
+
+ALTER TABLE portfolio.FundTransactions
+ADD CONSTRAINT CK_FundTransactions_TransactionType CHECK (TransactionType IN (
+    'Deposit', 'Withdrawal', 'Allocation', 'Deallocation', 'PremiumUpgrade',
+    'AssetPurchase', 'AssetSale', 'OrderReservation', 'OrderRelease',
+    'OrderAmendment', 'OrderCancellation', 'OrderFailure', 'TradingFee'
+));
+GO
+
+/* ============================================================
+2. FEE FUNCTIONS AND PROCEDURES
+============================================================ */
+
+-- Fee for a trade of @TradeValue in @AssetID by @UserID, under the schedule
+-- for the user's type and the asset's type, else the user type's default one.
+-- No schedule means no fee.
+CREATE OR ALTER FUNCTION portfolio.fn_CalculateTradingFee (
+    @UserID UNIQUEIDENTIFIER,
+    @AssetID INT,
+    @TradeValue DECIMAL(18,2)
+)
+RETURNS DECIMAL(18,2)
+AS
+BEGIN
+    DECLARE @FeeScheduleID INT, @FeeType NVARCHAR(12), @FlatFee DECIMAL(18,2),
+            @Rate DECIMAL(9,4), @MinimumFee DECIMAL(18,2);
+
+    SELECT TOP 1
+        @FeeScheduleID = s.FeeScheduleID, @FeeType = s.FeeType, @FlatFee = s.FlatFee,
+        @Rate = s.Rate, @MinimumFee = s.MinimumFee
+    FROM portfolio.FeeSchedules s
+    JOIN portfolio.Users u ON u.UserType = s.UserType
+    JOIN portfolio.Assets a ON a.AssetID = @AssetID
+    WHERE u.UserID = @UserID AND (s.AssetType = a.AssetType OR s.AssetType IS NULL)
+    ORDER BY CASE WHEN s.AssetType IS NULL THEN 1 ELSE 0 END;
+
+    IF @FeeScheduleID IS NULL
+        RETURN 0;
+
+    IF @FeeType = 'Tiered'
+        SELECT TOP 1 @Rate = Rate
+        FROM portfolio.FeeTiers
+        WHERE FeeScheduleID = @FeeScheduleID AND MinTradeValue <= @TradeValue
+        ORDER BY MinTradeValue DESC;
+
+    DECLARE @Fee DECIMAL(18,2) = CASE
+        WHEN @FeeType = 'Flat' THEN @FlatFee
+        ELSE ROUND(@TradeValue * ISNULL(@Rate, 0) / 100, 2)
+    END;
+
+    RETURN CASE WHEN @Fee < @MinimumFee THEN @MinimumFee ELSE @Fee END;
+END;
+GO
+
+-- Total trading fees a portfolio has paid
+CREATE OR ALTER FUNCTION portfolio.fn_PortfolioFeesPaid (
+    @PortfolioID INT
+)
+RETURNS DECIMAL(18,2)
+AS
+BEGIN
+    RETURN ISNULL((
+        SELECT -SUM(Amount)
+        FROM portfolio.FundTransactions
+        WHERE PortfolioID = @PortfolioID AND TransactionType = 'TradingFee'
+    ), 0);
+END;
+GO
+
+-- Take a trade's fee from portfolio cash and log it against the trade;
+-- the caller owns the transaction
+CREATE OR ALTER PROCEDURE portfolio.sp_ChargeTradingFee (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @TransactionID BIGINT,
+    @Fee DECIMAL(18,2)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    IF @Fee <= 0
+        RETURN;
+
+    UPDATE portfolio.Portfolios
+    SET CurrentFunds = CurrentFunds - @Fee
+    WHERE PortfolioID = @PortfolioID;
+
+    INSERT INTO portfolio.FundTransactions (
+        UserID, PortfolioID, TransactionType, Amount,
+        BalanceAfter, Description, RelatedAssetTransactionID
+    ) VALUES (
+        @UserID, @PortfolioID, 'TradingFee', -@Fee,
+        (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+        CONCAT('Trading fee for transaction ', @TransactionID),
+        @TransactionID
+    );
+END;
+GO
+
+-- Replace the schedule for a user type and asset type, tiers included.
+-- @Tiers is JSON [{"min_trade_value", "rate"}] and only used by Tiered schedules.
+CREATE OR ALTER PROCEDURE portfolio.sp_SetFeeSchedule (
+    @UserType NVARCHAR(20),
+    @AssetType NVARCHAR(20) = NULL,
+    @FeeType NVARCHAR(12),
+    @FlatFee DECIMAL(18,2) = NULL,
+    @Rate DECIMAL(9,4) = NULL,
+    @MinimumFee DECIMAL(18,2) = 0,
+    @Tiers NVARCHAR(MAX) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF @FeeType = 'Tiered' AND NOT EXISTS (
+            SELECT 1 FROM OPENJSON(@Tiers) WITH (MinTradeValue DECIMAL(18,2) '$.min_trade_value')
+            WHERE MinTradeValue = 0
+        )
+        BEGIN
+            RAISERROR('Tiered fee schedules need a tier starting at a trade value of 0', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @FeeScheduleID INT = (
+            SELECT FeeScheduleID FROM portfolio.FeeSchedules WITH (UPDLOCK, HOLDLOCK)
+            WHERE UserType = @UserType
+              AND (AssetType = @AssetType OR (AssetType IS NULL AND @AssetType IS NULL))
+        );
+
+        IF @FeeScheduleID IS NULL
+        BEGIN
+            INSERT INTO portfolio.FeeSchedules (UserType, AssetType, FeeType, FlatFee, Rate, MinimumFee)
+            VALUES (@UserType, @AssetType, @FeeType, @FlatFee, @Rate, @MinimumFee);
+            SET @FeeScheduleID = SCOPE_IDENTITY();
+        END
+        ELSE
+        BEGIN
+            UPDATE portfolio.FeeSchedules
+            SET FeeType = @FeeType, FlatFee = @FlatFee, Rate = @Rate,
+                MinimumFee = @MinimumFee, UpdatedAt = SYSDATETIME()
+            WHERE FeeScheduleID = @FeeScheduleID;
+
+            DELETE FROM portfolio.FeeTiers WHERE FeeScheduleID = @FeeScheduleID;
+        END
+
+        IF @FeeType = 'Tiered'
+            INSERT INTO portfolio.FeeTiers (FeeScheduleID, MinTradeValue, Rate)
+            SELECT @FeeScheduleID, MinTradeValue, Rate
+            FROM OPENJSON(@Tiers) WITH (
+                MinTradeValue DECIMAL(18,2) '$.min_trade_value',
+                Rate DECIMAL(9,4) '$.rate'
+            );
+
+        COMMIT;
+
+        SELECT @FeeScheduleID AS FeeScheduleID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Portfolio balance as before, plus the trading fees the portfolio has paid
+CREATE OR ALTER PROCEDURE portfolio.sp_GetPortfolioBalance (
+    @PortfolioID INT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+    BEGIN
+        RAISERROR('Portfolio not found', 16, 1);
+        RETURN;
+    END
+    
+    SELECT 
+        PortfolioID,
+        UserID,
+        Name,
+        CurrentFunds,
+        CurrentProfitPct,
+        CreationDate,
+        LastUpdated,
+        -- Calculate total market value from holdings
+        ISNULL((
+            SELECT SUM(ph.QuantityHeld * a.Price)
+            FROM portfolio.PortfolioHoldings ph
+            JOIN portfolio.Assets a ON a.AssetID = ph.AssetID
+            WHERE ph.PortfolioID = @PortfolioID
+        ), 0) AS CurrentMarketValue,
+        -- Calculate total invested amount
+        ISNULL((
+            SELECT SUM(ph.TotalCost)
+            FROM portfolio.PortfolioHoldings ph
+            WHERE ph.PortfolioID = @PortfolioID
+        ), 0) AS TotalInvested,
+        portfolio.fn_PortfolioReservedFunds(@PortfolioID) AS ReservedFunds,
+        portfolio.fn_PortfolioFeesPaid(@PortfolioID) AS FeesPaid
+    FROM portfolio.Portfolios 
+    WHERE PortfolioID = @PortfolioID;
+END;
+GO
+
+/* ============================================================
+3. TRADING PROCEDURES
+============================================================ */
+
+-- Close lots as in 015_tax_lots.sql, with the sale's @Fee taken off the
+-- proceeds in proportion to the quantity each lot gives up
+CREATE OR ALTER PROCEDURE portfolio.sp_CloseTaxLots (
+    @PortfolioID INT,
+    @AssetID INT,
+    @SellTransactionID BIGINT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4),
+    @Lots NVARCHAR(MAX) = NULL,
+    @CostBasis DECIMAL(18,2) OUTPUT,
+    @Fee DECIMAL(18,2) = 0
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    DECLARE @Method NVARCHAR(12);
+    SELECT @Method = CostBasisMethod FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID;
+
+    IF @Lots IS NOT NULL
+        SET @Method = 'SpecificLot';
+    ELSE IF @Method = 'SpecificLot'
+        SET @Method = 'FIFO';
+
+    DECLARE @Take TABLE (LotID BIGINT PRIMARY KEY, Quantity DECIMAL(18,6) NOT NULL, Seq INT NOT NULL);
+
+    IF @Method = 'SpecificLot'
+    BEGIN
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT j.LotID, SUM(j.Quantity), ROW_NUMBER() OVER (ORDER BY j.LotID)
+        FROM OPENJSON(@Lots) WITH (
+            LotID BIGINT '$.lot_id',
+            Quantity DECIMAL(18,6) '$.quantity'
+        ) j
+        GROUP BY j.LotID;
+
+        IF EXISTS (
+            SELECT 1
+            FROM @Take t
+            LEFT JOIN portfolio.TaxLots l WITH (UPDLOCK)
+                ON l.LotID = t.LotID AND l.PortfolioID = @PortfolioID AND l.AssetID = @AssetID
+            WHERE l.LotID IS NULL OR t.Quantity <= 0 OR t.Quantity > l.RemainingQuantity
+        )
+        BEGIN
+            RAISERROR('Selected lots must be open lots of this holding with enough remaining shares', 16, 1);
+            RETURN;
+        END
+
+        IF (SELECT SUM(Quantity) FROM @Take) <> @Quantity
+        BEGIN
+            RAISERROR('Selected lot quantities must add up to the quantity sold', 16, 1);
+            RETURN;
+        END
+    END
+    ELSE IF @Method = 'Average'
+    BEGIN
+        -- The same fraction of every open lot keeps the average cost
+        -- unchanged; what truncating leaves over goes to the lots with the
+        -- most room, and no lot gives up more than it holds
+        DECLARE @OpenQuantity DECIMAL(18,6) = (
+            SELECT SUM(RemainingQuantity) FROM portfolio.TaxLots WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0
+        );
+
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT LotID, ROUND(RemainingQuantity * @Quantity / @OpenQuantity, 6, 1),
+               ROW_NUMBER() OVER (ORDER BY LotID)
+        FROM portfolio.TaxLots
+        WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0;
+
+        DECLARE @Left DECIMAL(18,6) = @Quantity - (SELECT SUM(Quantity) FROM @Take);
+
+        UPDATE t
+        SET Quantity = t.Quantity + CASE WHEN r.Covered <= @Left THEN r.Room
+                                         ELSE @Left - (r.Covered - r.Room) END
+        FROM @Take t
+        JOIN (
+            SELECT t.LotID, l.RemainingQuantity - t.Quantity AS Room,
+                   SUM(l.RemainingQuantity - t.Quantity) OVER (
+                       ORDER BY l.RemainingQuantity - t.Quantity DESC, t.Seq
+                       ROWS UNBOUNDED PRECEDING
+                   ) AS Covered
+            FROM @Take t
+            JOIN portfolio.TaxLots l ON l.LotID = t.LotID
+        ) r ON r.LotID = t.LotID
+        WHERE @Left > 0 AND r.Room > 0 AND r.Covered - r.Room < @Left;
+    END
+    ELSE
+    BEGIN
+        -- FIFO walks the lots oldest first, LIFO newest first, until the sale is covered
+        INSERT INTO @Take (LotID, Quantity, Seq)
+        SELECT LotID,
+               CASE WHEN Covered <= @Quantity THEN RemainingQuantity
+                    ELSE @Quantity - (Covered - RemainingQuantity) END,
+               Seq
+        FROM (
+            SELECT LotID, RemainingQuantity,
+                   ROW_NUMBER() OVER (ORDER BY CASE WHEN @Method = 'LIFO' THEN -LotID ELSE LotID END) AS Seq,
+                   SUM(RemainingQuantity) OVER (
+                       ORDER BY CASE WHEN @Method = 'LIFO' THEN -LotID ELSE LotID END
+                       ROWS UNBOUNDED PRECEDING
+                   ) AS Covered
+            FROM portfolio.TaxLots WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID AND RemainingQuantity > 0
+        ) lots
+        WHERE Covered - RemainingQuantity < @Quantity;
+    END
+
+    UPDATE l
+    SET RemainingQuantity = l.RemainingQuantity - t.Quantity
+    FROM portfolio.TaxLots l
+    JOIN @Take t ON t.LotID = l.LotID;
+
+    INSERT INTO portfolio.RealizedGains (
+        SellTransactionID, LotID, Quantity, CostBasis, Proceeds, CostBasisMethod
+    )
+    SELECT @SellTransactionID, t.LotID, t.Quantity,
+           CAST(t.Quantity * l.UnitCost AS DECIMAL(18,2)),
+           CAST(t.Quantity * @UnitPrice - @Fee * t.Quantity / @Quantity AS DECIMAL(18,2)),
+           @Method
+    FROM @Take t
+    JOIN portfolio.TaxLots l ON l.LotID = t.LotID
+    WHERE t.Quantity > 0;
+
+    DECLARE @TotalProceeds DECIMAL(18,2) = @Quantity * @UnitPrice - @Fee;
+
+    UPDATE portfolio.RealizedGains
+    SET Proceeds = Proceeds + (
+        @TotalProceeds - (SELECT SUM(Proceeds) FROM portfolio.RealizedGains WHERE SellTransactionID = @SellTransactionID)
+    )
+    WHERE RealizedGainID = (
+        SELECT MAX(RealizedGainID) FROM portfolio.RealizedGains WHERE SellTransactionID = @SellTransactionID
+    );
+
+    SELECT @CostBasis = ISNULL(SUM(CostBasis), 0)
+    FROM portfolio.RealizedGains
+    WHERE SellTransactionID = @SellTransactionID;
+END;
+GO
+
+-- Buy, sell and order fills as before, now charging the trading fee.
+-- Buys need funds for the trade plus the fee; sells take the fee out of the
+-- proceeds. TotalCost includes the fee and TotalProceeds are net of it.
+-- A buy's fee is part of its holding's and tax lot's cost, and a sale's fee
+-- comes off its proceeds, so RealizedGain is net of both.
+CREATE OR ALTER PROCEDURE portfolio.sp_BuyAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL -- If NULL, use current market price
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        BEGIN TRANSACTION;
+        
+        -- Validate inputs
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if portfolio belongs to user
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios 
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+        
+        -- Get current asset price if not provided
+        IF @UnitPrice IS NULL
+        BEGIN
+            SELECT @UnitPrice = Price 
+            FROM portfolio.Assets 
+            WHERE AssetID = @AssetID;
+            
+            IF @UnitPrice IS NULL
+            BEGIN
+                RAISERROR('Asset not found', 16, 1);
+                RETURN;
+            END
+        END
+        
+        -- Calculate total cost and the fee on top of it
+        DECLARE @TotalCost DECIMAL(18,2) = @Quantity * @UnitPrice;
+        DECLARE @Fee DECIMAL(18,2) = portfolio.fn_CalculateTradingFee(@UserID, @AssetID, @TotalCost);
+        
+        -- Check if portfolio has sufficient funds
+        DECLARE @CurrentFunds DECIMAL(18,2);
+        SELECT @CurrentFunds = CurrentFunds 
+        FROM portfolio.Portfolios 
+        WHERE PortfolioID = @PortfolioID;
+        
+        IF @CurrentFunds < @TotalCost + @Fee
+        BEGIN
+            RAISERROR('Insufficient funds in portfolio for this purchase', 16, 1);
+            RETURN;
+        END
+        
+        -- Create transaction record
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType, 
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, 'Buy',
+            @Quantity, @UnitPrice, 'Executed'
+        );
+        
+        DECLARE @TransactionID BIGINT = SCOPE_IDENTITY();
+        
+        -- Update portfolio funds
+        UPDATE portfolio.Portfolios 
+        SET CurrentFunds = CurrentFunds - @TotalCost
+        WHERE PortfolioID = @PortfolioID;
+        
+        -- Update or insert holdings
+        IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+        BEGIN
+            -- Update existing holding - calculate new average price
+            UPDATE portfolio.PortfolioHoldings 
+            SET QuantityHeld = QuantityHeld + @Quantity,
+                AveragePrice = ((TotalCost + @TotalCost + @Fee) / (QuantityHeld + @Quantity)),
+                TotalCost = TotalCost + @TotalCost + @Fee
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        ELSE
+        BEGIN
+            -- Create new holding
+            INSERT INTO portfolio.PortfolioHoldings (
+                PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+            ) VALUES (
+                @PortfolioID, @AssetID, @Quantity, (@TotalCost + @Fee) / @Quantity, @TotalCost + @Fee
+            );
+        END
+        
+        -- Each purchase opens its own tax lot, its fee part of the cost
+        DECLARE @UnitCost DECIMAL(18,4) = (@TotalCost + @Fee) / @Quantity;
+        EXEC portfolio.sp_OpenTaxLot @PortfolioID, @AssetID, @TransactionID, @Quantity, @UnitCost;
+        
+        -- Record fund transaction
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount, 
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'AssetPurchase', -@TotalCost,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+            @TransactionID
+        );
+        
+        EXEC portfolio.sp_ChargeTradingFee @UserID, @PortfolioID, @TransactionID, @Fee;
+        
+        COMMIT;
+        
+        -- Return success with transaction details
+        SELECT 
+            'SUCCESS' AS Status,
+            @TransactionID AS TransactionID,
+            @Quantity AS QuantityPurchased,
+            @UnitPrice AS PricePerShare,
+            @TotalCost + @Fee AS TotalCost,
+            @Fee AS Fee,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) AS RemainingFunds;
+            
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_SellAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL, -- If NULL, use current market price
+    @Lots NVARCHAR(MAX) = NULL -- JSON [{"lot_id", "quantity"}] choosing the lots to sell
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        BEGIN TRANSACTION;
+        
+        -- Validate inputs
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if portfolio belongs to user
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios 
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+        
+        -- Specific-lot portfolios have to say which lots are sold
+        IF @Lots IS NULL AND (
+            SELECT CostBasisMethod FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID
+        ) = 'SpecificLot'
+        BEGIN
+            RAISERROR('Lots to sell are required when the portfolio uses SpecificLot cost basis', 16, 1);
+            RETURN;
+        END
+        
+        -- Check current holdings
+        DECLARE @CurrentHolding DECIMAL(18,6), @AveragePrice DECIMAL(18,4), @TotalCost DECIMAL(18,2);
+        SELECT @CurrentHolding = QuantityHeld, @AveragePrice = AveragePrice, @TotalCost = TotalCost
+        FROM portfolio.PortfolioHoldings 
+        WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        
+        IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+        BEGIN
+            RAISERROR('Insufficient holdings for this sale', 16, 1);
+            RETURN;
+        END
+        
+        -- Get current asset price if not provided
+        IF @UnitPrice IS NULL
+        BEGIN
+            SELECT @UnitPrice = Price 
+            FROM portfolio.Assets 
+            WHERE AssetID = @AssetID;
+            
+            IF @UnitPrice IS NULL
+            BEGIN
+                RAISERROR('Asset not found', 16, 1);
+                RETURN;
+            END
+        END
+        
+        -- Calculate total proceeds; the fee comes out of them
+        DECLARE @TotalProceeds DECIMAL(18,2) = @Quantity * @UnitPrice;
+        DECLARE @Fee DECIMAL(18,2) = portfolio.fn_CalculateTradingFee(@UserID, @AssetID, @TotalProceeds);
+        
+        IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) + @TotalProceeds < @Fee
+        BEGIN
+            RAISERROR('Insufficient funds in portfolio to pay the trading fee', 16, 1);
+            RETURN;
+        END
+        
+        -- Create transaction record
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType, 
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, 'Sell',
+            @Quantity, @UnitPrice, 'Executed'
+        );
+        
+        DECLARE @TransactionID BIGINT = SCOPE_IDENTITY();
+        
+        -- Update portfolio funds (add proceeds)
+        UPDATE portfolio.Portfolios 
+        SET CurrentFunds = CurrentFunds + @TotalProceeds
+        WHERE PortfolioID = @PortfolioID;
+        
+        -- Update holdings
+        DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+        DECLARE @CostBasis DECIMAL(18,2);
+        EXEC portfolio.sp_CloseTaxLots
+            @PortfolioID, @AssetID, @TransactionID, @Quantity, @UnitPrice, @Lots,
+            @CostBasis OUTPUT, @Fee;
+        
+        IF @NewQuantity > 0
+        BEGIN
+            -- Update existing holding
+            UPDATE portfolio.PortfolioHoldings 
+            SET QuantityHeld = @NewQuantity,
+                AveragePrice = (@TotalCost - @CostBasis) / @NewQuantity,
+                TotalCost = @TotalCost - @CostBasis
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        ELSE
+        BEGIN
+            -- Remove holding completely (sold all shares)
+            DELETE FROM portfolio.PortfolioHoldings 
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+        END
+        
+        -- Record fund transaction
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount, 
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'AssetSale', @TotalProceeds,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+            @TransactionID
+        );
+        
+        EXEC portfolio.sp_ChargeTradingFee @UserID, @PortfolioID, @TransactionID, @Fee;
+        
+        COMMIT;
+        
+        -- Return success with transaction details
+        SELECT 
+            'SUCCESS' AS Status,
+            @TransactionID AS TransactionID,
+            @Quantity AS QuantitySold,
+            @UnitPrice AS PricePerShare,
+            @TotalProceeds - @Fee AS TotalProceeds,
+            @Fee AS Fee,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) AS NewFunds,
+            @NewQuantity AS RemainingShares,
+            @CostBasis AS CostBasis,
+            @TotalProceeds - @Fee - @CostBasis AS RealizedGain;
+            
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_FillOrder (
+    @OrderID BIGINT,
+    @FillPrice DECIMAL(18,4)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @Quantity DECIMAL(18,6), @Status NVARCHAR(20),
+                @Reserved DECIMAL(18,2);
+
+        -- The lock keeps a concurrent fill or cancel out until this one commits
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @Quantity = t.Quantity, @Status = t.Status,
+               @Reserved = o.ReservedFunds
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @Total DECIMAL(18,2) = @Quantity * @FillPrice;
+        DECLARE @Fee DECIMAL(18,2) = portfolio.fn_CalculateTradingFee(@UserID, @AssetID, @Total);
+        DECLARE @FailReason NVARCHAR(255) = NULL;
+        DECLARE @CurrentHolding DECIMAL(18,6), @HoldingCost DECIMAL(18,2);
+
+        IF @Side = 'Buy'
+        BEGIN
+            -- The reservation goes back to the portfolio before the trade settles
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) + @Reserved < @Total + @Fee
+                SET @FailReason = 'Insufficient funds at fill time';
+        END
+        ELSE
+        BEGIN
+            SELECT @CurrentHolding = QuantityHeld, @HoldingCost = TotalCost
+            FROM portfolio.PortfolioHoldings
+            WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+
+            IF @CurrentHolding IS NULL OR @CurrentHolding < @Quantity
+                SET @FailReason = 'Insufficient holdings at fill time';
+            ELSE IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) + @Total < @Fee
+                SET @FailReason = 'Insufficient funds at fill time';
+        END
+
+        IF @FailReason IS NOT NULL
+        BEGIN
+            DECLARE @FailDescription NVARCHAR(255) = CONCAT('Order ', @OrderID, ' failed: ', @FailReason);
+            EXEC portfolio.sp_ReleaseOrderFunds @OrderID, 'OrderFailure', @FailDescription;
+
+            UPDATE portfolio.Transactions SET Status = 'Failed' WHERE TransactionID = @OrderID;
+            UPDATE portfolio.Orders
+            SET StatusReason = @FailReason, UpdatedAt = SYSDATETIME()
+            WHERE OrderID = @OrderID;
+
+            COMMIT;
+            SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+            RETURN;
+        END
+
+        EXEC portfolio.sp_ReleaseOrderFunds @OrderID;
+
+        IF @Side = 'Buy'
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds - @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            IF EXISTS (SELECT 1 FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID)
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = QuantityHeld + @Quantity,
+                    AveragePrice = ((TotalCost + @Total + @Fee) / (QuantityHeld + @Quantity)),
+                    TotalCost = TotalCost + @Total + @Fee
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                INSERT INTO portfolio.PortfolioHoldings (
+                    PortfolioID, AssetID, QuantityHeld, AveragePrice, TotalCost
+                ) VALUES (
+                    @PortfolioID, @AssetID, @Quantity, (@Total + @Fee) / @Quantity, @Total + @Fee
+                );
+            END
+
+            DECLARE @UnitCost DECIMAL(18,4) = (@Total + @Fee) / @Quantity;
+            EXEC portfolio.sp_OpenTaxLot @PortfolioID, @AssetID, @OrderID, @Quantity, @UnitCost;
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetPurchase', -@Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Purchased ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+        ELSE
+        BEGIN
+            UPDATE portfolio.Portfolios
+            SET CurrentFunds = CurrentFunds + @Total
+            WHERE PortfolioID = @PortfolioID;
+
+            DECLARE @NewQuantity DECIMAL(18,6) = @CurrentHolding - @Quantity;
+            DECLARE @CostBasis DECIMAL(18,2);
+
+            -- Orders cannot pick lots, so specific-lot portfolios sell FIFO here
+            EXEC portfolio.sp_CloseTaxLots
+                @PortfolioID, @AssetID, @OrderID, @Quantity, @FillPrice, NULL,
+                @CostBasis OUTPUT, @Fee;
+
+            IF @NewQuantity > 0
+            BEGIN
+                UPDATE portfolio.PortfolioHoldings
+                SET QuantityHeld = @NewQuantity,
+                    AveragePrice = (@HoldingCost - @CostBasis) / @NewQuantity,
+                    TotalCost = @HoldingCost - @CostBasis
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+            ELSE
+            BEGIN
+                DELETE FROM portfolio.PortfolioHoldings
+                WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID;
+            END
+
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount,
+                BalanceAfter, Description, RelatedAssetTransactionID
+            ) VALUES (
+                @UserID, @PortfolioID, 'AssetSale', @Total,
+                (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+                CONCAT('Sold ', @Quantity, ' shares of asset ID ', @AssetID),
+                @OrderID
+            );
+        END
+
+        EXEC portfolio.sp_ChargeTradingFee @UserID, @PortfolioID, @OrderID, @Fee;
+
+        UPDATE portfolio.Transactions
+        SET Status = 'Executed', UnitPrice = @FillPrice, TransactionDate = SYSDATETIME()
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders SET UpdatedAt = SYSDATETIME() WHERE OrderID = @OrderID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+/* ============================================================
+4. ORDER RESERVATIONS
+============================================================ */
+
+-- Most a trade worth up to @TradeValue can cost in fees. Tiers lower the
+-- rate as trades grow, so a trade a cent below a tier can cost more than a
+-- larger one; the fee is taken at @TradeValue and just below every tier.
+CREATE OR ALTER FUNCTION portfolio.fn_MaxTradingFee (
+    @UserID UNIQUEIDENTIFIER,
+    @AssetID INT,
+    @TradeValue DECIMAL(18,2)
+)
+RETURNS DECIMAL(18,2)
+AS
+BEGIN
+    RETURN (
+        SELECT MAX(portfolio.fn_CalculateTradingFee(@UserID, @AssetID, v.TradeValue))
+        FROM (
+            SELECT @TradeValue AS TradeValue
+            UNION
+            SELECT MinTradeValue - 0.01 FROM portfolio.FeeTiers
+            WHERE MinTradeValue > 0 AND MinTradeValue - 0.01 < @TradeValue
+        ) v
+    );
+END;
+GO
+
+-- Place and amend orders as in 013_order_amendments.sql, but buys also
+-- reserve the worst-case fee on their reserved value, so a fill at or below
+-- the reserve price never needs the rest of the portfolio's cash.
+-- sp_FillOrder hands the whole reservation back before charging the actual
+-- trade and fee, which releases whatever was not needed.
+CREATE OR ALTER PROCEDURE portfolio.sp_PlaceOrder (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Side NVARCHAR(10),
+    @OrderType NVARCHAR(10),
+    @TimeInForce NVARCHAR(3),
+    @Quantity DECIMAL(18,6),
+    @LimitPrice DECIMAL(18,4) = NULL,
+    @StopPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+
+        IF NOT EXISTS (
+            SELECT 1 FROM portfolio.Portfolios WITH (UPDLOCK)
+            WHERE PortfolioID = @PortfolioID AND UserID = @UserID
+        )
+        BEGIN
+            RAISERROR('Portfolio not found or does not belong to user', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @MarketPrice DECIMAL(18,4);
+        SELECT @MarketPrice = Price FROM portfolio.Assets WHERE AssetID = @AssetID;
+
+        IF @MarketPrice IS NULL
+        BEGIN
+            RAISERROR('Asset not found', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @ReservePrice DECIMAL(18,4) = CASE @OrderType
+            WHEN 'Market' THEN @MarketPrice
+            WHEN 'Stop' THEN @StopPrice
+            ELSE @LimitPrice
+        END;
+        DECLARE @Reserved DECIMAL(18,2) = 0;
+
+        IF @Side = 'Buy'
+        BEGIN
+            SET @Reserved = @Quantity * @ReservePrice;
+            SET @Reserved = @Reserved + portfolio.fn_MaxTradingFee(@UserID, @AssetID, @Reserved);
+
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID) < @Reserved
+            BEGIN
+                RAISERROR('Insufficient funds in portfolio for this order', 16, 1);
+                RETURN;
+            END
+        END
+        ELSE
+        BEGIN
+            -- Shares already promised to other pending sells are not available
+            DECLARE @Available DECIMAL(18,6) =
+                ISNULL((
+                    SELECT QuantityHeld FROM portfolio.PortfolioHoldings
+                    WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID
+                ), 0)
+                - ISNULL((
+                    SELECT SUM(t.Quantity)
+                    FROM portfolio.Transactions t
+                    JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+                    WHERE t.PortfolioID = @PortfolioID AND t.AssetID = @AssetID
+                      AND t.TransactionType = 'Sell' AND t.Status = 'Pending'
+                ), 0);
+
+            IF @Available < @Quantity
+            BEGIN
+                RAISERROR('Insufficient holdings for this order', 16, 1);
+                RETURN;
+            END
+        END
+
+        INSERT INTO portfolio.Transactions (
+            UserID, PortfolioID, AssetID, TransactionType,
+            Quantity, UnitPrice, Status
+        ) VALUES (
+            @UserID, @PortfolioID, @AssetID, @Side,
+            @Quantity, @ReservePrice, 'Pending'
+        );
+
+        DECLARE @OrderID BIGINT = SCOPE_IDENTITY();
+
+        -- DAY orders run until the end of the current day
+        INSERT INTO portfolio.Orders (
+            OrderID, OrderType, TimeInForce, LimitPrice, StopPrice, ReservedFunds, ExpiresAt
+        ) VALUES (
+            @OrderID, @OrderType, @TimeInForce, @LimitPrice, @StopPrice, @Reserved,
+            CASE WHEN @TimeInForce = 'DAY'
+                THEN DATEADD(DAY, 1, CAST(CAST(SYSDATETIME() AS DATE) AS DATETIME))
+            END
+        );
+
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds - @Reserved
+        WHERE PortfolioID = @PortfolioID;
+
+        -- Sells reserve nothing but are still logged so every order has an
+        -- opening entry
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'OrderReservation', -@Reserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Reserved ', CASE @Side WHEN 'Buy' THEN 'funds' ELSE 'holdings' END, ' for order ', @OrderID),
+            @OrderID
+        );
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_AmendOrder (
+    @OrderID BIGINT,
+    @Quantity DECIMAL(18,6) = NULL,
+    @LimitPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @UserID UNIQUEIDENTIFIER, @PortfolioID INT, @AssetID INT,
+                @Side NVARCHAR(10), @OrderType NVARCHAR(10), @Status NVARCHAR(20),
+                @OldQuantity DECIMAL(18,6), @OldLimitPrice DECIMAL(18,4),
+                @ReservePrice DECIMAL(18,4), @OldReserved DECIMAL(18,2);
+
+        SELECT @UserID = t.UserID, @PortfolioID = t.PortfolioID, @AssetID = t.AssetID,
+               @Side = t.TransactionType, @OrderType = o.OrderType, @Status = t.Status,
+               @OldQuantity = t.Quantity, @OldLimitPrice = o.LimitPrice,
+               @ReservePrice = t.UnitPrice, @OldReserved = o.ReservedFunds
+        FROM portfolio.Transactions t WITH (UPDLOCK, ROWLOCK)
+        JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+        WHERE t.TransactionID = @OrderID;
+
+        IF @Status IS NULL
+        BEGIN
+            RAISERROR('Order not found', 16, 1);
+            RETURN;
+        END
+
+        IF @Status <> 'Pending'
+        BEGIN
+            RAISERROR('Order is no longer pending', 16, 1);
+            RETURN;
+        END
+
+        IF @Quantity <= 0
+        BEGIN
+            RAISERROR('Quantity must be positive', 16, 1);
+            RETURN;
+        END
+
+        IF @LimitPrice IS NOT NULL AND @OrderType NOT IN ('Limit', 'StopLimit')
+        BEGIN
+            RAISERROR('Limit price can only be amended on Limit and StopLimit orders', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @NewQuantity DECIMAL(18,6) = ISNULL(@Quantity, @OldQuantity);
+        DECLARE @NewLimitPrice DECIMAL(18,4) = ISNULL(@LimitPrice, @OldLimitPrice);
+
+        -- Limit prices are what buys reserve at; other types keep their price
+        IF @OrderType IN ('Limit', 'StopLimit')
+            SET @ReservePrice = @NewLimitPrice;
+
+        DECLARE @NewReserved DECIMAL(18,2) = 0;
+
+        IF @Side = 'Buy'
+        BEGIN
+            SET @NewReserved = @NewQuantity * @ReservePrice;
+            SET @NewReserved = @NewReserved + portfolio.fn_MaxTradingFee(@UserID, @AssetID, @NewReserved);
+
+            IF (SELECT CurrentFunds FROM portfolio.Portfolios WITH (UPDLOCK) WHERE PortfolioID = @PortfolioID)
+                < @NewReserved - @OldReserved
+            BEGIN
+                RAISERROR('Insufficient funds in portfolio for this order', 16, 1);
+                RETURN;
+            END
+        END
+        ELSE
+        BEGIN
+            -- Shares promised to the other pending sells are not available
+            DECLARE @Available DECIMAL(18,6) =
+                ISNULL((
+                    SELECT QuantityHeld FROM portfolio.PortfolioHoldings
+                    WHERE PortfolioID = @PortfolioID AND AssetID = @AssetID
+                ), 0)
+                - ISNULL((
+                    SELECT SUM(t.Quantity)
+                    FROM portfolio.Transactions t
+                    JOIN portfolio.Orders o ON o.OrderID = t.TransactionID
+                    WHERE t.PortfolioID = @PortfolioID AND t.AssetID = @AssetID
+                      AND t.TransactionType = 'Sell' AND t.Status = 'Pending'
+                      AND t.TransactionID <> @OrderID
+                ), 0);
+
+            IF @Available < @NewQuantity
+            BEGIN
+                RAISERROR('Insufficient holdings for this order', 16, 1);
+                RETURN;
+            END
+        END
+
+        UPDATE portfolio.Portfolios
+        SET CurrentFunds = CurrentFunds - (@NewReserved - @OldReserved)
+        WHERE PortfolioID = @PortfolioID;
+
+        UPDATE portfolio.Transactions
+        SET Quantity = @NewQuantity, UnitPrice = @ReservePrice
+        WHERE TransactionID = @OrderID;
+
+        UPDATE portfolio.Orders
+        SET LimitPrice = @NewLimitPrice, ReservedFunds = @NewReserved, UpdatedAt = SYSDATETIME()
+        WHERE OrderID = @OrderID;
+
+        INSERT INTO portfolio.FundTransactions (
+            UserID, PortfolioID, TransactionType, Amount,
+            BalanceAfter, Description, RelatedAssetTransactionID
+        ) VALUES (
+            @UserID, @PortfolioID, 'OrderAmendment', @OldReserved - @NewReserved,
+            (SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID),
+            CONCAT('Amended order ', @OrderID),
+            @OrderID
+        );
+
+        COMMIT;
+
+        SELECT * FROM portfolio.vw_Orders WHERE OrderID = @OrderID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Trading fees created successfully!';
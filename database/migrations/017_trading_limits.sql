@@ -0,0 +1,163 @@
+/* ============================================================
+meuPortfolio – Trading Limits
+Per-portfolio pre-trade limits and per-asset-type quantity
+increments, checked by the API before a trade or order is sent
+to the trading procedures
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TRADING LIMITS
+============================================================ */
+
+-- At most one row per portfolio; a NULL column means no limit
+CREATE TABLE portfolio.TradingLimits (
+    PortfolioID INT NOT NULL,
+    MaxPositionPct DECIMAL(9,4) NULL,
+    MaxOrderNotional DECIMAL(18,2) NULL,
+    MaxDailyVolume DECIMAL(18,2) NULL,
+    MaxPriceAgeMinutes INT NULL,
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_TradingLimits_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_TradingLimits PRIMARY KEY (PortfolioID),
+    CONSTRAINT FK_TradingLimits_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE,
+    CONSTRAINT CK_TradingLimits_Values CHECK (
+        (MaxPositionPct IS NULL OR MaxPositionPct > 0 AND MaxPositionPct <= 100)
+        AND (MaxOrderNotional IS NULL OR MaxOrderNotional > 0)
+        AND (MaxDailyVolume IS NULL OR MaxDailyVolume > 0)
+        AND (MaxPriceAgeMinutes IS NULL OR MaxPriceAgeMinutes > 0)
+    )
+);
+GO
+
+/* ============================================================
+2. QUANTITY INCREMENTS
+============================================================ */
+
+-- Traded quantities must be a multiple of their asset type's increment
+CREATE TABLE portfolio.QuantityIncrements (
+    AssetType NVARCHAR(20) NOT NULL,
+    Increment DECIMAL(18,6) NOT NULL,
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_QuantityIncrements_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_QuantityIncrements PRIMARY KEY (AssetType),
+    CONSTRAINT CK_QuantityIncrements_AssetType CHECK (AssetType IN ('Stock', 'Index', 'Cryptocurrency', 'Commodity')),
+    CONSTRAINT CK_QuantityIncrements_Increment CHECK (Increment > 0)
+);
+GO
+
+-- Whole shares and index units, fractional commodities and crypto
+INSERT INTO portfolio.QuantityIncrements (AssetType, Increment) VALUES
+    ('Stock', 1),
+    ('Index', 1),
+    ('Commodity', 0.01),
+    ('Cryptocurrency', 0.000001);
+GO
+
+/* ============================================================
+3. PROCEDURES
+============================================================ */
+
+-- Replaces every limit of a portfolio; NULL removes a limit
+CREATE OR ALTER PROCEDURE portfolio.sp_SetTradingLimits (
+    @PortfolioID INT,
+    @MaxPositionPct DECIMAL(9,4) = NULL,
+    @MaxOrderNotional DECIMAL(18,2) = NULL,
+    @MaxDailyVolume DECIMAL(18,2) = NULL,
+    @MaxPriceAgeMinutes INT = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+        BEGIN
+            RAISERROR('Portfolio not found', 16, 1);
+            RETURN;
+        END
+
+        MERGE portfolio.TradingLimits WITH (HOLDLOCK) AS target
+        USING (SELECT @PortfolioID AS PortfolioID) AS source
+        ON target.PortfolioID = source.PortfolioID
+        WHEN MATCHED THEN
+            UPDATE SET MaxPositionPct = @MaxPositionPct,
+                       MaxOrderNotional = @MaxOrderNotional,
+                       MaxDailyVolume = @MaxDailyVolume,
+                       MaxPriceAgeMinutes = @MaxPriceAgeMinutes,
+                       UpdatedAt = SYSDATETIME()
+        WHEN NOT MATCHED THEN
+            INSERT (PortfolioID, MaxPositionPct, MaxOrderNotional, MaxDailyVolume, MaxPriceAgeMinutes)
+            VALUES (@PortfolioID, @MaxPositionPct, @MaxOrderNotional, @MaxDailyVolume, @MaxPriceAgeMinutes);
+
+        COMMIT;
+
+        SELECT * FROM portfolio.TradingLimits WHERE PortfolioID = @PortfolioID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Everything the pre-trade rules read, in one row; the price age and
+-- today's volume use the server clock, like the timestamps they compare to
+CREATE OR ALTER PROCEDURE portfolio.sp_GetPreTradeSnapshot (
+    @PortfolioID INT,
+    @AssetID INT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+    BEGIN
+        RAISERROR('Portfolio not found', 16, 1);
+        RETURN;
+    END
+
+    IF NOT EXISTS (SELECT 1 FROM portfolio.Assets WHERE AssetID = @AssetID)
+    BEGIN
+        RAISERROR('Asset not found', 16, 1);
+        RETURN;
+    END
+
+    SELECT
+        l.MaxPositionPct,
+        l.MaxOrderNotional,
+        l.MaxDailyVolume,
+        l.MaxPriceAgeMinutes,
+        l.UpdatedAt AS LimitsUpdatedAt,
+        a.AssetType,
+        a.Price,
+        CAST(DATEDIFF_BIG(SECOND, a.LastUpdated, SYSDATETIME()) AS BIGINT) AS PriceAgeSeconds,
+        qi.Increment AS QuantityIncrement,
+        ISNULL((
+            SELECT h.QuantityHeld FROM portfolio.PortfolioHoldings h
+            WHERE h.PortfolioID = @PortfolioID AND h.AssetID = @AssetID
+        ), 0) AS QuantityHeld,
+        CAST(p.CurrentFunds + ISNULL((
+            SELECT SUM(h.QuantityHeld * ha.Price)
+            FROM portfolio.PortfolioHoldings h
+            JOIN portfolio.Assets ha ON ha.AssetID = h.AssetID
+            WHERE h.PortfolioID = @PortfolioID
+        ), 0) AS DECIMAL(18,2)) AS PortfolioValue,
+        CAST(ISNULL((
+            SELECT SUM(ROUND(t.Quantity * t.UnitPrice, 2))
+            FROM portfolio.Transactions t
+            WHERE t.PortfolioID = @PortfolioID
+              AND t.Status = 'Executed'
+              AND t.TransactionDate >= CAST(CAST(SYSDATETIME() AS DATE) AS DATETIME)
+        ), 0) AS DECIMAL(18,2)) AS TradedToday
+    FROM portfolio.Portfolios p
+    CROSS JOIN portfolio.Assets a
+    LEFT JOIN portfolio.TradingLimits l ON l.PortfolioID = p.PortfolioID
+    LEFT JOIN portfolio.QuantityIncrements qi ON qi.AssetType = a.AssetType
+    WHERE p.PortfolioID = @PortfolioID AND a.AssetID = @AssetID;
+END;
+GO
+
+PRINT 'Trading limits created successfully!';
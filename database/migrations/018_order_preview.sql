@@ -0,0 +1,94 @@
+/* ============================================================
+meuPortfolio – Order Preview
+Runs a buy or sell through the real trading procedures inside a
+transaction that is always rolled back, so the API can show a
+trade's outcome before it is placed
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. PROCEDURES
+============================================================ */
+
+-- Position and portfolio value as the current transaction sees them
+CREATE OR ALTER PROCEDURE portfolio.sp_GetPreviewPosition (
+    @PortfolioID INT,
+    @AssetID INT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    SELECT
+        ISNULL(h.QuantityHeld, 0) AS QuantityHeldAfter,
+        ISNULL(h.AveragePrice, 0) AS AveragePriceAfter,
+        CAST(p.CurrentFunds + ISNULL((
+            SELECT SUM(ph.QuantityHeld * a.Price)
+            FROM portfolio.PortfolioHoldings ph
+            JOIN portfolio.Assets a ON a.AssetID = ph.AssetID
+            WHERE ph.PortfolioID = @PortfolioID
+        ), 0) AS DECIMAL(18,2)) AS PortfolioValueAfter
+    FROM portfolio.Portfolios p
+    LEFT JOIN portfolio.PortfolioHoldings h
+        ON h.PortfolioID = p.PortfolioID AND h.AssetID = @AssetID
+    WHERE p.PortfolioID = @PortfolioID;
+END;
+GO
+
+-- Returns sp_BuyAsset's result row, then the position it would leave.
+-- Nothing is kept, but the rolled back TransactionID is still consumed
+CREATE OR ALTER PROCEDURE portfolio.sp_PreviewBuyAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        EXEC portfolio.sp_BuyAsset @UserID, @PortfolioID, @AssetID, @Quantity, @UnitPrice;
+        EXEC portfolio.sp_GetPreviewPosition @PortfolioID, @AssetID;
+
+        ROLLBACK;
+    END TRY
+    BEGIN CATCH
+        -- A failing sp_BuyAsset has already rolled everything back
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Returns sp_SellAsset's result row, then the position it would leave
+CREATE OR ALTER PROCEDURE portfolio.sp_PreviewSellAsset (
+    @UserID UNIQUEIDENTIFIER,
+    @PortfolioID INT,
+    @AssetID INT,
+    @Quantity DECIMAL(18,6),
+    @UnitPrice DECIMAL(18,4) = NULL,
+    @Lots NVARCHAR(MAX) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        EXEC portfolio.sp_SellAsset @UserID, @PortfolioID, @AssetID, @Quantity, @UnitPrice, @Lots;
+        EXEC portfolio.sp_GetPreviewPosition @PortfolioID, @AssetID;
+
+        ROLLBACK;
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Order preview created successfully!';
@@ -0,0 +1,151 @@
+/* ============================================================
+meuPortfolio – Rebalancing
+Target weights per asset or per asset type for each portfolio,
+with the drift tolerance and minimum trade value the API's
+rebalancer works with
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TABLES
+============================================================ */
+
+-- At most one row per portfolio, created with its first targets
+CREATE TABLE portfolio.RebalancePolicies (
+    PortfolioID INT NOT NULL,
+    DriftTolerancePct DECIMAL(9,4) NOT NULL CONSTRAINT DF_RebalancePolicies_DriftTolerancePct DEFAULT 5,
+    MinTradeValue DECIMAL(18,2) NOT NULL CONSTRAINT DF_RebalancePolicies_MinTradeValue DEFAULT 0,
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_RebalancePolicies_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_RebalancePolicies PRIMARY KEY (PortfolioID),
+    CONSTRAINT FK_RebalancePolicies_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE,
+    CONSTRAINT CK_RebalancePolicies_Values CHECK (
+        DriftTolerancePct >= 0 AND DriftTolerancePct <= 100 AND MinTradeValue >= 0
+    )
+);
+GO
+
+-- Each target names either an asset or an asset type; whatever the
+-- weights leave out of 100% is meant to stay in cash
+CREATE TABLE portfolio.AllocationTargets (
+    TargetID INT IDENTITY(1,1) NOT NULL,
+    PortfolioID INT NOT NULL,
+    AssetID INT NULL,
+    AssetType NVARCHAR(20) NULL,
+    Weight DECIMAL(9,4) NOT NULL,
+    CONSTRAINT PK_AllocationTargets PRIMARY KEY (TargetID),
+    CONSTRAINT FK_AllocationTargets_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE,
+    CONSTRAINT FK_AllocationTargets_Assets FOREIGN KEY (AssetID)
+        REFERENCES portfolio.Assets(AssetID) ON DELETE CASCADE,
+    CONSTRAINT CK_AllocationTargets_Subject CHECK (
+        (AssetID IS NOT NULL AND AssetType IS NULL) OR (AssetID IS NULL AND AssetType IS NOT NULL)
+    ),
+    CONSTRAINT CK_AllocationTargets_AssetType CHECK (AssetType IN ('Stock', 'Index', 'Cryptocurrency', 'Commodity')),
+    CONSTRAINT CK_AllocationTargets_Weight CHECK (Weight > 0 AND Weight <= 100)
+);
+GO
+
+CREATE UNIQUE INDEX UX_AllocationTargets_Asset
+    ON portfolio.AllocationTargets (PortfolioID, AssetID) WHERE AssetID IS NOT NULL;
+CREATE UNIQUE INDEX UX_AllocationTargets_AssetType
+    ON portfolio.AllocationTargets (PortfolioID, AssetType) WHERE AssetType IS NOT NULL;
+GO
+
+/* ============================================================
+2. PROCEDURES
+============================================================ */
+
+-- Replaces every target of a portfolio from a JSON array of
+-- {"asset_id", "asset_type", "weight"} objects
+CREATE OR ALTER PROCEDURE portfolio.sp_SetAllocationTargets (
+    @PortfolioID INT,
+    @Targets NVARCHAR(MAX),
+    @DriftTolerancePct DECIMAL(9,4) = 5,
+    @MinTradeValue DECIMAL(18,2) = 0
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+        BEGIN
+            RAISERROR('Portfolio not found', 16, 1);
+            RETURN;
+        END
+
+        IF EXISTS (
+            SELECT 1 FROM OPENJSON(@Targets) WITH (AssetID INT '$.asset_id') t
+            WHERE t.AssetID IS NOT NULL
+              AND NOT EXISTS (SELECT 1 FROM portfolio.Assets a WHERE a.AssetID = t.AssetID)
+        )
+        BEGIN
+            RAISERROR('Asset not found', 16, 1);
+            RETURN;
+        END
+
+        MERGE portfolio.RebalancePolicies WITH (HOLDLOCK) AS target
+        USING (SELECT @PortfolioID AS PortfolioID) AS source
+        ON target.PortfolioID = source.PortfolioID
+        WHEN MATCHED THEN
+            UPDATE SET DriftTolerancePct = @DriftTolerancePct,
+                       MinTradeValue = @MinTradeValue,
+                       UpdatedAt = SYSDATETIME()
+        WHEN NOT MATCHED THEN
+            INSERT (PortfolioID, DriftTolerancePct, MinTradeValue)
+            VALUES (@PortfolioID, @DriftTolerancePct, @MinTradeValue);
+
+        DELETE FROM portfolio.AllocationTargets WHERE PortfolioID = @PortfolioID;
+
+        INSERT INTO portfolio.AllocationTargets (PortfolioID, AssetID, AssetType, Weight)
+        SELECT @PortfolioID, AssetID, AssetType, Weight
+        FROM OPENJSON(@Targets) WITH (
+            AssetID INT '$.asset_id',
+            AssetType NVARCHAR(20) '$.asset_type',
+            Weight DECIMAL(9,4) '$.weight'
+        );
+
+        COMMIT;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Cash, then every held position plus the targeted assets not held
+-- yet, with current prices and quantity increments
+CREATE OR ALTER PROCEDURE portfolio.sp_GetRebalanceSnapshot (
+    @PortfolioID INT
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    SELECT CurrentFunds FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID;
+
+    SELECT h.AssetID, h.Symbol, h.AssetType, h.QuantityHeld, h.CurrentPrice AS Price, qi.Increment AS QuantityIncrement
+    FROM portfolio.vw_PortfolioHoldings h
+    LEFT JOIN portfolio.QuantityIncrements qi ON qi.AssetType = h.AssetType
+    WHERE h.PortfolioID = @PortfolioID AND h.QuantityHeld > 0
+
+    UNION ALL
+
+    SELECT a.AssetID, a.Symbol, a.AssetType, CAST(0 AS DECIMAL(18,6)), a.Price, qi.Increment
+    FROM portfolio.AllocationTargets t
+    JOIN portfolio.Assets a ON a.AssetID = t.AssetID
+    LEFT JOIN portfolio.QuantityIncrements qi ON qi.AssetType = a.AssetType
+    WHERE t.PortfolioID = @PortfolioID
+      AND NOT EXISTS (
+          SELECT 1 FROM portfolio.PortfolioHoldings ph
+          WHERE ph.PortfolioID = @PortfolioID AND ph.AssetID = t.AssetID AND ph.QuantityHeld > 0
+      );
+END;
+GO
+
+PRINT 'Rebalancing created successfully!';
@@ -0,0 +1,208 @@
+/* ============================================================
+meuPortfolio – Recurring Investments
+Schedules that buy a fixed amount of an asset every day, week
+or month, run by the API's scheduler. Every run is recorded and
+leaves a trace in FundTransactions
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TABLES
+============================================================ */
+
+-- NextRunDate is NULL while a schedule is paused
+CREATE TABLE portfolio.RecurringInvestments (
+    ScheduleID INT IDENTITY(1,1) NOT NULL,
+    PortfolioID INT NOT NULL,
+    AssetID INT NOT NULL,
+    Amount DECIMAL(18,2) NOT NULL,
+    Frequency NVARCHAR(10) NOT NULL,
+    DayOfWeek TINYINT NULL,
+    DayOfMonth TINYINT NULL,
+    NextRunDate DATE NULL,
+    LastRunDate DATE NULL,
+    CreatedAt DATETIME NOT NULL CONSTRAINT DF_RecurringInvestments_CreatedAt DEFAULT SYSDATETIME(),
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_RecurringInvestments_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_RecurringInvestments PRIMARY KEY (ScheduleID),
+    CONSTRAINT FK_RecurringInvestments_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE,
+    CONSTRAINT FK_RecurringInvestments_Assets FOREIGN KEY (AssetID)
+        REFERENCES portfolio.Assets(AssetID) ON DELETE CASCADE,
+    CONSTRAINT CK_RecurringInvestments_Amount CHECK (Amount > 0),
+    -- Weekly schedules name an ISO weekday (1 = Monday), monthly ones a day every month has
+    CONSTRAINT CK_RecurringInvestments_Frequency CHECK (
+        (Frequency = 'Daily' AND DayOfWeek IS NULL AND DayOfMonth IS NULL)
+        OR (Frequency = 'Weekly' AND DayOfWeek BETWEEN 1 AND 7 AND DayOfMonth IS NULL)
+        OR (Frequency = 'Monthly' AND DayOfWeek IS NULL AND DayOfMonth BETWEEN 1 AND 28)
+    )
+);
+GO
+
+CREATE INDEX IX_RecurringInvestments_NextRunDate
+    ON portfolio.RecurringInvestments (NextRunDate) WHERE NextRunDate IS NOT NULL;
+CREATE INDEX IX_RecurringInvestments_PortfolioID
+    ON portfolio.RecurringInvestments (PortfolioID);
+GO
+
+-- One row per due date, so two runners can never both buy for the same run.
+-- TransactionID and FundTransactionID are plain IDs so a run never blocks
+-- deleting the rows it points at
+CREATE TABLE portfolio.RecurringInvestmentRuns (
+    RunID BIGINT IDENTITY(1,1) NOT NULL,
+    ScheduleID INT NOT NULL,
+    RunDate DATE NOT NULL,
+    Status NVARCHAR(10) NOT NULL CONSTRAINT DF_RecurringInvestmentRuns_Status DEFAULT 'Running',
+    TransactionID BIGINT NULL,
+    FundTransactionID BIGINT NULL,
+    Quantity DECIMAL(18,6) NULL,
+    UnitPrice DECIMAL(18,4) NULL,
+    TotalCost DECIMAL(18,2) NULL,
+    Reason NVARCHAR(255) NULL,
+    ExecutedAt DATETIME NOT NULL CONSTRAINT DF_RecurringInvestmentRuns_ExecutedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_RecurringInvestmentRuns PRIMARY KEY (RunID),
+    CONSTRAINT FK_RecurringInvestmentRuns_Schedules FOREIGN KEY (ScheduleID)
+        REFERENCES portfolio.RecurringInvestments(ScheduleID) ON DELETE CASCADE,
+    CONSTRAINT UQ_RecurringInvestmentRuns_Date UNIQUE (ScheduleID, RunDate),
+    CONSTRAINT CK_RecurringInvestmentRuns_Status CHECK (Status IN ('Running', 'Executed', 'Skipped'))
+);
+GO
+
+-- A skipped run is recorded as a zero amount fund transaction
+ALTER TABLE portfolio.FundTransactions DROP CONSTRAINT CK_FundTransactions_TransactionType;
+GO
+
+ALTER TABLE portfolio.FundTransactions
+ADD CONSTRAINT CK_FundTransactions_TransactionType CHECK (TransactionType IN (
+    'Deposit', 'Withdrawal', 'Allocation', 'Deallocation', 'PremiumUpgrade',
+    'AssetPurchase', 'AssetSale', 'OrderReservation', 'OrderRelease',
+    'OrderAmendment', 'OrderCancellation', 'OrderFailure', 'TradingFee',
+    'RecurringSkipped'
+));
+GO
+
+/* ============================================================
+2. PROCEDURES
+============================================================ */
+
+-- Starts the run due on @RunDate and moves the schedule on to @NextRunDate.
+-- Returns the new RunID, or no row when the run is no longer due
+CREATE OR ALTER PROCEDURE portfolio.sp_ClaimRecurringRun (
+    @ScheduleID INT,
+    @RunDate DATE,
+    @NextRunDate DATE
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        UPDATE portfolio.RecurringInvestments
+        SET NextRunDate = @NextRunDate,
+            LastRunDate = @RunDate,
+            UpdatedAt = SYSDATETIME()
+        WHERE ScheduleID = @ScheduleID AND NextRunDate = @RunDate;
+
+        IF @@ROWCOUNT = 0
+        BEGIN
+            COMMIT;
+            RETURN;
+        END
+
+        INSERT INTO portfolio.RecurringInvestmentRuns (ScheduleID, RunDate)
+        VALUES (@ScheduleID, @RunDate);
+
+        DECLARE @RunID BIGINT = SCOPE_IDENTITY();
+
+        COMMIT;
+
+        SELECT @RunID AS RunID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Records how a run ended. An executed run tags the purchase's own
+-- AssetPurchase row; a skipped run adds a RecurringSkipped row
+CREATE OR ALTER PROCEDURE portfolio.sp_CompleteRecurringRun (
+    @RunID BIGINT,
+    @Status NVARCHAR(10),
+    @TransactionID BIGINT = NULL,
+    @Quantity DECIMAL(18,6) = NULL,
+    @UnitPrice DECIMAL(18,4) = NULL,
+    @TotalCost DECIMAL(18,2) = NULL,
+    @Reason NVARCHAR(255) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        DECLARE @ScheduleID INT, @PortfolioID INT, @UserID UNIQUEIDENTIFIER, @CurrentFunds DECIMAL(18,2);
+        SELECT @ScheduleID = r.ScheduleID, @PortfolioID = s.PortfolioID,
+               @UserID = p.UserID, @CurrentFunds = p.CurrentFunds
+        FROM portfolio.RecurringInvestmentRuns r
+        JOIN portfolio.RecurringInvestments s ON s.ScheduleID = r.ScheduleID
+        JOIN portfolio.Portfolios p ON p.PortfolioID = s.PortfolioID
+        WHERE r.RunID = @RunID;
+
+        IF @ScheduleID IS NULL
+        BEGIN
+            RAISERROR('Recurring investment run not found', 16, 1);
+            RETURN;
+        END
+
+        DECLARE @FundTransactionID BIGINT;
+
+        IF @Status = 'Executed'
+        BEGIN
+            SELECT @FundTransactionID = FundTransactionID
+            FROM portfolio.FundTransactions
+            WHERE RelatedAssetTransactionID = @TransactionID AND TransactionType = 'AssetPurchase';
+
+            UPDATE portfolio.FundTransactions
+            SET Description = LEFT(CONCAT('Recurring investment ', @ScheduleID, ': ', Description), 255)
+            WHERE FundTransactionID = @FundTransactionID;
+        END
+        ELSE
+        BEGIN
+            INSERT INTO portfolio.FundTransactions (
+                UserID, PortfolioID, TransactionType, Amount, BalanceAfter, Description
+            ) VALUES (
+                @UserID, @PortfolioID, 'RecurringSkipped', 0, @CurrentFunds,
+                LEFT(CONCAT('Recurring investment ', @ScheduleID, ' skipped: ', @Reason), 255)
+            );
+
+            SET @FundTransactionID = SCOPE_IDENTITY();
+        END
+
+        UPDATE portfolio.RecurringInvestmentRuns
+        SET Status = @Status,
+            TransactionID = @TransactionID,
+            FundTransactionID = @FundTransactionID,
+            Quantity = @Quantity,
+            UnitPrice = @UnitPrice,
+            TotalCost = @TotalCost,
+            Reason = @Reason
+        WHERE RunID = @RunID;
+
+        COMMIT;
+
+        SELECT * FROM portfolio.RecurringInvestmentRuns WHERE RunID = @RunID;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Recurring investments created successfully!';
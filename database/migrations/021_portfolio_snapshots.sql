@@ -0,0 +1,214 @@
+/* ============================================================
+meuPortfolio – Portfolio Snapshots
+Each portfolio's value at the end of every day, written by the
+API's snapshot job and rebuilt from AssetPrices and the
+transaction history when backfilling
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TABLES
+============================================================ */
+
+-- NetFlows is money allocated to the portfolio that day less money
+-- deallocated, so gains can be told apart from deposits
+CREATE TABLE portfolio.PortfolioSnapshots (
+    PortfolioID INT NOT NULL,
+    SnapshotDate DATE NOT NULL,
+    CashValue DECIMAL(18,2) NOT NULL,
+    HoldingsValue DECIMAL(18,2) NOT NULL,
+    TotalValue AS (CashValue + HoldingsValue) PERSISTED,
+    NetFlows DECIMAL(18,2) NOT NULL CONSTRAINT DF_PortfolioSnapshots_NetFlows DEFAULT 0,
+    UpdatedAt DATETIME NOT NULL CONSTRAINT DF_PortfolioSnapshots_UpdatedAt DEFAULT SYSDATETIME(),
+    CONSTRAINT PK_PortfolioSnapshots PRIMARY KEY (PortfolioID, SnapshotDate),
+    CONSTRAINT FK_PortfolioSnapshots_Portfolios FOREIGN KEY (PortfolioID)
+        REFERENCES portfolio.Portfolios(PortfolioID) ON DELETE CASCADE
+);
+GO
+
+/* ============================================================
+2. PROCEDURES
+============================================================ */
+
+-- Current cash (with funds reserved by pending orders) and holdings,
+-- then the executed trades and cash movements since @From, then the
+-- daily closes of every asset involved. Each asset's closes start
+-- with the last one before @From and end with its current price,
+-- dated @Today. @From defaults to the portfolio's creation date
+CREATE OR ALTER PROCEDURE portfolio.sp_GetPortfolioHistory (
+    @PortfolioID INT,
+    @From DATE = NULL,
+    @Today DATE
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    SELECT @From = ISNULL(@From, CAST(CreationDate AS DATE))
+    FROM portfolio.Portfolios
+    WHERE PortfolioID = @PortfolioID;
+
+    SELECT CAST(p.CreationDate AS DATE) AS CreatedOn,
+           p.CurrentFunds + ISNULL((
+               SELECT SUM(o.ReservedFunds)
+               FROM portfolio.Orders o
+               JOIN portfolio.Transactions t ON t.TransactionID = o.OrderID
+               WHERE t.PortfolioID = @PortfolioID AND t.Status = 'Pending'
+           ), 0) AS Cash
+    FROM portfolio.Portfolios p
+    WHERE p.PortfolioID = @PortfolioID;
+
+    SELECT AssetID, QuantityHeld
+    FROM portfolio.PortfolioHoldings
+    WHERE PortfolioID = @PortfolioID;
+
+    SELECT AssetID, TransactionType, Quantity, CAST(TransactionDate AS DATE) AS TradeDate
+    FROM portfolio.Transactions
+    WHERE PortfolioID = @PortfolioID AND Status = 'Executed' AND TransactionDate >= @From;
+
+    -- Allocations are recorded from the user's side, so their sign flips
+    SELECT CAST(CreatedAt AS DATE) AS MovementDate,
+           CASE WHEN TransactionType IN ('Allocation', 'Deallocation') THEN -Amount ELSE Amount END AS Amount,
+           CAST(CASE WHEN TransactionType IN ('Allocation', 'Deallocation') THEN 1 ELSE 0 END AS BIT) AS IsExternal
+    FROM portfolio.FundTransactions
+    WHERE PortfolioID = @PortfolioID
+      AND CreatedAt >= @From
+      AND TransactionType NOT IN ('OrderReservation', 'OrderRelease', 'OrderAmendment', 'OrderCancellation', 'OrderFailure');
+
+    WITH HistoryAssets AS (
+        SELECT AssetID FROM portfolio.PortfolioHoldings WHERE PortfolioID = @PortfolioID
+        UNION
+        SELECT AssetID FROM portfolio.Transactions
+        WHERE PortfolioID = @PortfolioID AND Status = 'Executed' AND TransactionDate >= @From
+    ),
+    Closes AS (
+        SELECT ap.AssetID, CAST(ap.AsOf AS DATE) AS PriceDate, ap.Price,
+               ROW_NUMBER() OVER (PARTITION BY ap.AssetID, CAST(ap.AsOf AS DATE) ORDER BY ap.AsOf DESC, ap.PriceID DESC) AS DayRank
+        FROM portfolio.AssetPrices ap
+        JOIN HistoryAssets ha ON ha.AssetID = ap.AssetID
+        WHERE ap.AsOf < @Today
+    )
+    SELECT c.AssetID, c.PriceDate, c.Price
+    FROM Closes c
+    WHERE c.DayRank = 1
+      AND (c.PriceDate >= @From OR c.PriceDate = (
+          SELECT MAX(CAST(p.AsOf AS DATE)) FROM portfolio.AssetPrices p
+          WHERE p.AssetID = c.AssetID AND p.AsOf < @From
+      ))
+
+    UNION ALL
+
+    SELECT a.AssetID, @Today, a.Price
+    FROM portfolio.Assets a
+    JOIN HistoryAssets ha ON ha.AssetID = a.AssetID
+
+    ORDER BY AssetID, PriceDate;
+END;
+GO
+
+-- Upserts snapshots from a JSON array of {"snapshot_date", "cash_value",
+-- "holdings_value", "net_flows"} objects
+CREATE OR ALTER PROCEDURE portfolio.sp_SavePortfolioSnapshots (
+    @PortfolioID INT,
+    @Snapshots NVARCHAR(MAX)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        BEGIN TRANSACTION;
+
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+        BEGIN
+            RAISERROR('Portfolio not found', 16, 1);
+            RETURN;
+        END
+
+        MERGE portfolio.PortfolioSnapshots WITH (HOLDLOCK) AS target
+        USING (
+            SELECT SnapshotDate, CashValue, HoldingsValue, NetFlows
+            FROM OPENJSON(@Snapshots) WITH (
+                SnapshotDate DATE '$.snapshot_date',
+                CashValue DECIMAL(18,2) '$.cash_value',
+                HoldingsValue DECIMAL(18,2) '$.holdings_value',
+                NetFlows DECIMAL(18,2) '$.net_flows'
+            )
+        ) AS source
+        ON target.PortfolioID = @PortfolioID AND target.SnapshotDate = source.SnapshotDate
+        WHEN MATCHED THEN
+            UPDATE SET CashValue = source.CashValue,
+                       HoldingsValue = source.HoldingsValue,
+                       NetFlows = source.NetFlows,
+                       UpdatedAt = SYSDATETIME()
+        WHEN NOT MATCHED THEN
+            INSERT (PortfolioID, SnapshotDate, CashValue, HoldingsValue, NetFlows)
+            VALUES (@PortfolioID, source.SnapshotDate, source.CashValue, source.HoldingsValue, source.NetFlows);
+
+        COMMIT;
+
+    END TRY
+    BEGIN CATCH
+        IF @@TRANCOUNT > 0 ROLLBACK;
+        THROW;
+    END CATCH
+END;
+GO
+
+-- Portfolio update as in 005_2_portfolio_procedures.sql, but for the name
+-- only: cash moves through allocations and deallocations, which log the
+-- flows snapshots and returns are worked out from
+CREATE OR ALTER PROCEDURE portfolio.sp_UpdatePortfolio (
+    @PortfolioID INT,
+    @Name NVARCHAR(100) = NULL
+) AS
+BEGIN
+    SET NOCOUNT ON;
+    
+    BEGIN TRY
+        -- Check if portfolio exists
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Portfolios WHERE PortfolioID = @PortfolioID)
+        BEGIN
+            RAISERROR('Portfolio not found', 16, 1);
+            RETURN;
+        END
+        
+        -- Check if any fields are provided for update
+        IF @Name IS NULL
+        BEGIN
+            RAISERROR('No fields to update', 16, 1);
+            RETURN;
+        END
+        
+        -- Validate name if provided
+        IF LTRIM(RTRIM(@Name)) = ''
+        BEGIN
+            RAISERROR('Portfolio name cannot be empty', 16, 1);
+            RETURN;
+        END
+        
+        UPDATE portfolio.Portfolios 
+        SET Name = LTRIM(RTRIM(@Name))
+            -- LastUpdated will be set automatically by trigger
+        WHERE PortfolioID = @PortfolioID;
+        
+        -- Return the updated portfolio data
+        SELECT 
+            PortfolioID,
+            UserID,
+            Name,
+            CreationDate,
+            CurrentFunds,
+            CurrentProfitPct,
+            LastUpdated
+        FROM portfolio.Portfolios 
+        WHERE PortfolioID = @PortfolioID;
+        
+    END TRY
+    BEGIN CATCH
+        THROW;
+    END CATCH
+END;
+GO
+
+PRINT 'Portfolio snapshots created successfully!';
@@ -0,0 +1,161 @@
+/* ============================================================
+meuPortfolio – Risk Engine
+Risk metrics are now worked out by the API from each portfolio's
+daily valuations and AssetPrices; the database only stores them.
+The T-SQL calculation valued every past date at today's holdings
+and prices, so it is dropped
+============================================================ */
+
+USE p6g4;
+GO
+
+/* ============================================================
+1. TABLES
+============================================================ */
+
+ALTER TABLE portfolio.RiskMetrics ADD
+    SortinoRatio DECIMAL(10,2) NULL,
+    CalmarRatio DECIMAL(10,2) NULL;
+GO
+
+/* ============================================================
+2. RETIRED CALCULATION
+============================================================ */
+
+DROP PROCEDURE IF EXISTS portfolio.sp_CalculateAllUserRiskMetrics;
+DROP PROCEDURE IF EXISTS portfolio.sp_CalculateUserRiskMetrics;
+DROP FUNCTION IF EXISTS portfolio.fn_CalculatePortfolioBeta;
+DROP FUNCTION IF EXISTS portfolio.fn_CalculatePortfolioSharpeRatio;
+DROP FUNCTION IF EXISTS portfolio.fn_CalculatePortfolioMaxDrawdown;
+DROP FUNCTION IF EXISTS portfolio.fn_CalculatePortfolioVolatility;
+GO
+
+/* ============================================================
+3. PROCEDURES
+============================================================ */
+
+-- Stores metrics calculated by the API and returns the new row.
+-- The activity trigger on RiskMetrics rules out OUTPUT inserted.*
+CREATE OR ALTER PROCEDURE portfolio.sp_RecordUserRiskMetrics (
+    @UserID UNIQUEIDENTIFIER,
+    @MaximumDrawdown DECIMAL(10,2) = NULL,
+    @Beta DECIMAL(10,2) = NULL,
+    @SharpeRatio DECIMAL(10,2) = NULL,
+    @SortinoRatio DECIMAL(10,2) = NULL,
+    @CalmarRatio DECIMAL(10,2) = NULL,
+    @AbsoluteReturn DECIMAL(10,2) = NULL,
+    @VolatilityScore DECIMAL(10,2) = NULL,
+    @RiskLevel NVARCHAR(20)
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    BEGIN TRY
+        IF NOT EXISTS (SELECT 1 FROM portfolio.Users WHERE UserID = @UserID)
+        BEGIN
+            RAISERROR('User not found', 16, 1);
+            RETURN;
+        END
+
+        INSERT INTO portfolio.RiskMetrics (
+            UserID, MaximumDrawdown, Beta, SharpeRatio, SortinoRatio,
+            CalmarRatio, AbsoluteReturn, VolatilityScore, RiskLevel
+        )
+        VALUES (
+            @UserID, @MaximumDrawdown, @Beta, @SharpeRatio, @SortinoRatio,
+            @CalmarRatio, @AbsoluteReturn, @VolatilityScore, @RiskLevel
+        );
+
+        SELECT MetricID, UserID, MaximumDrawdown, Beta, SharpeRatio, SortinoRatio,
+               CalmarRatio, AbsoluteReturn, VolatilityScore, RiskLevel, CapturedAt
+        FROM portfolio.RiskMetrics
+        WHERE MetricID = SCOPE_IDENTITY();
+    END TRY
+    BEGIN CATCH
+        THROW;
+    END CATCH
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_GetUserLatestRiskMetrics (
+    @UserID UNIQUEIDENTIFIER
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    SELECT TOP 1
+        MetricID,
+        UserID,
+        MaximumDrawdown,
+        Beta,
+        SharpeRatio,
+        SortinoRatio,
+        CalmarRatio,
+        AbsoluteReturn,
+        VolatilityScore,
+        RiskLevel,
+        CapturedAt
+    FROM portfolio.RiskMetrics
+    WHERE UserID = @UserID
+    ORDER BY CapturedAt DESC;
+END;
+GO
+
+CREATE OR ALTER PROCEDURE portfolio.sp_GetUserRiskTrend (
+    @UserID UNIQUEIDENTIFIER,
+    @DaysBack INT = 90
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    SELECT
+        MetricID,
+        MaximumDrawdown,
+        Beta,
+        SharpeRatio,
+        SortinoRatio,
+        CalmarRatio,
+        AbsoluteReturn,
+        VolatilityScore,
+        RiskLevel,
+        CapturedAt
+    FROM portfolio.RiskMetrics
+    WHERE UserID = @UserID
+      AND CapturedAt >= DATEADD(DAY, -@DaysBack, SYSDATETIME())
+    ORDER BY CapturedAt ASC;
+END;
+GO
+
+-- The last price of each day from @From through @To for a JSON array of
+-- asset IDs, each asset's series led by its last close before @From
+CREATE OR ALTER PROCEDURE portfolio.sp_GetAssetDailyCloses (
+    @AssetIDs NVARCHAR(MAX),
+    @From DATE,
+    @To DATE
+) AS
+BEGIN
+    SET NOCOUNT ON;
+
+    WITH RequestedAssets AS (
+        SELECT DISTINCT CAST(value AS INT) AS AssetID
+        FROM OPENJSON(@AssetIDs)
+    ),
+    Closes AS (
+        SELECT ap.AssetID, CAST(ap.AsOf AS DATE) AS PriceDate, ap.Price,
+               ROW_NUMBER() OVER (PARTITION BY ap.AssetID, CAST(ap.AsOf AS DATE) ORDER BY ap.AsOf DESC, ap.PriceID DESC) AS DayRank
+        FROM portfolio.AssetPrices ap
+        JOIN RequestedAssets ra ON ra.AssetID = ap.AssetID
+        WHERE ap.AsOf < DATEADD(DAY, 1, CAST(@To AS DATETIME))
+    )
+    SELECT c.AssetID, c.PriceDate, c.Price
+    FROM Closes c
+    WHERE c.DayRank = 1
+      AND (c.PriceDate >= @From OR c.PriceDate = (
+          SELECT MAX(CAST(p.AsOf AS DATE)) FROM portfolio.AssetPrices p
+          WHERE p.AssetID = c.AssetID AND p.AsOf < @From
+      ))
+    ORDER BY c.AssetID, c.PriceDate;
+END;
+GO
+
+PRINT 'Risk engine storage created successfully!';